@@ -20,6 +20,18 @@ pub struct LintCmd {
 
     #[arg(long, help = "Skip name check comma separated crates list")]
     skip_namecheck: Option<String>,
+
+    #[arg(
+        long,
+        help = "Check that advisory URLs are canonical and resolve (requires network access)"
+    )]
+    check_urls: bool,
+
+    #[arg(
+        long,
+        help = "Check affected function paths against the published crate source (downloads crates)"
+    )]
+    check_functions: bool,
 }
 
 impl Runnable for LintCmd {
@@ -30,7 +42,13 @@ impl Runnable for LintCmd {
             _ => unreachable!(),
         };
 
-        let linter = Linter::new(repo_path, self.skip_namecheck.to_owned()).unwrap_or_else(|e| {
+        let linter = Linter::new(
+            repo_path,
+            self.skip_namecheck.to_owned(),
+            self.check_urls,
+            self.check_functions,
+        )
+        .unwrap_or_else(|e| {
             status_err!(
                 "error loading advisory DB repo from {}: {}",
                 repo_path.display(),
@@ -4,12 +4,18 @@ use crate::{
     error::{Error, ErrorKind},
     lock::acquire_cargo_package_lock,
     prelude::*,
+    source_check::SourceChecker,
 };
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
+    time::Duration,
+};
+use tame_index::{
+    external::reqwest::{self, blocking::Client, redirect, StatusCode},
+    index::RemoteGitIndex,
 };
-use tame_index::index::RemoteGitIndex;
 
 /// List of "collections" within the Advisory DB
 // TODO(tarcieri): provide some other means of iterating over the collections?
@@ -32,13 +38,27 @@ pub struct Linter {
 
     /// Skip namecheck list
     skip_namecheck: Option<String>,
+
+    /// Checker for reference URLs (only when enabled)
+    url_checker: Option<UrlChecker>,
+
+    /// Checker for affected functions (only when enabled)
+    source_checker: Option<SourceChecker>,
 }
 
 impl Linter {
     /// Create a new linter for the database at the given path
+    ///
+    /// With `check_urls`, the `url` and `references` of each advisory are
+    /// also checked to be canonical and to resolve, which requires network
+    /// access. With `check_functions`, the `affected.functions` paths of each
+    /// advisory are checked against the published source of the crate, which
+    /// requires downloading it.
     pub fn new(
         repo_path: impl Into<PathBuf>,
         skip_namecheck: Option<String>,
+        check_urls: bool,
+        check_functions: bool,
     ) -> Result<Self, Error> {
         let repo_path = repo_path.into();
         let cargo_package_lock = acquire_cargo_package_lock()?;
@@ -51,12 +71,26 @@ impl Linter {
         crates_index.fetch(&cargo_package_lock)?;
         let advisory_db = rustsec::Database::open(&repo_path)?;
 
+        let url_checker = if check_urls {
+            Some(UrlChecker::new()?)
+        } else {
+            None
+        };
+
+        let source_checker = if check_functions {
+            Some(SourceChecker::new()?)
+        } else {
+            None
+        };
+
         Ok(Self {
             repo_path,
             crates_index,
             advisory_db,
             invalid_advisories: 0,
             skip_namecheck,
+            url_checker,
+            source_checker,
         })
     }
 
@@ -87,9 +121,30 @@ impl Linter {
             }
         }
 
+        self.lint_duplicates();
+
         Ok(self.invalid_advisories)
     }
 
+    /// Lint the database as a whole for advisories which duplicate each other
+    /// (e.g. by sharing a CVE alias), which per-file linting can't detect
+    fn lint_duplicates(&mut self) {
+        let duplicates = rustsec::advisory::linter::find_duplicates(&self.advisory_db);
+
+        if duplicates.is_empty() {
+            status_ok!("Linted", "ok: no duplicate advisories");
+            return;
+        }
+
+        status_err!("database contains the following duplicate advisories:");
+
+        for duplicate in &duplicates {
+            println!("  - {}", duplicate);
+        }
+
+        self.invalid_advisories += duplicates.len();
+    }
+
     /// Lint an advisory at the specified path
     // TODO(tarcieri): separate out presentation (`status_*`) from linting code?
     fn lint_advisory(
@@ -112,9 +167,38 @@ impl Linter {
             self.crates_io_lints(&advisory)?;
         }
 
-        let lint_result = rustsec::advisory::Linter::lint_file(advisory_path)?;
+        let mut lint_result = rustsec::advisory::Linter::lint_file(advisory_path)?;
+        lint_result.lint_references(&self.advisory_db);
+
+        let mut extra_errors = vec![];
+
+        if collection == rustsec::Collection::Crates
+            && advisory.metadata.withdrawn.is_none()
+            && !self.name_is_skipped(advisory.metadata.package.as_str())
+        {
+            extra_errors.extend(self.version_lints(&advisory)?);
+        }
+
+        if let Some(url_checker) = &mut self.url_checker {
+            lint_result.lint_url_canonicalization();
+
+            for url in advisory
+                .metadata
+                .url
+                .iter()
+                .chain(&advisory.metadata.references)
+            {
+                if let Err(reason) = url_checker.check(url) {
+                    extra_errors.push(format!("URL `{}` does not resolve: {}", url, reason));
+                }
+            }
+        }
+
+        if collection == rustsec::Collection::Crates && self.source_checker.is_some() {
+            extra_errors.extend(self.affected_function_lints(&advisory)?);
+        }
 
-        if lint_result.errors().is_empty() {
+        if lint_result.errors().is_empty() && extra_errors.is_empty() {
             status_ok!("Linted", "ok: {}", advisory_path.display());
         } else {
             self.invalid_advisories += 1;
@@ -127,6 +211,10 @@ impl Linter {
             for error in lint_result.errors() {
                 println!("  - {}", error);
             }
+
+            for error in &extra_errors {
+                println!("  - {}", error);
+            }
         }
 
         Ok(())
@@ -150,6 +238,82 @@ impl Linter {
         Ok(())
     }
 
+    /// Check the `patched` and `unaffected` requirements of an advisory
+    /// against the versions of the crate published on crates.io, catching
+    /// e.g. fixed versions which were never released
+    fn version_lints(&self, advisory: &rustsec::Advisory) -> Result<Vec<String>, Error> {
+        let krate = match self.crates_index.krate(
+            advisory.metadata.package.as_str().try_into()?,
+            true,
+            &acquire_cargo_package_lock()?,
+        )? {
+            Some(krate) => krate,
+            None => return Ok(vec![]),
+        };
+
+        let published: Vec<rustsec::Version> = krate
+            .versions
+            .iter()
+            .filter_map(|release| release.version.parse().ok())
+            .collect();
+
+        let mut errors = vec![];
+
+        for (key, reqs) in [
+            ("patched", advisory.versions.patched()),
+            ("unaffected", advisory.versions.unaffected()),
+        ] {
+            for req in reqs {
+                if !published.iter().any(|version| req.matches(version)) {
+                    errors.push(format!(
+                        "`{}` requirement `{}` matches no published version of {}",
+                        key, req, advisory.metadata.package
+                    ));
+                }
+            }
+        }
+
+        if !published
+            .iter()
+            .any(|version| advisory.versions.is_vulnerable(version))
+        {
+            errors.push(format!(
+                "`patched` and `unaffected` cover every published version of {}, so no version is affected",
+                advisory.metadata.package
+            ));
+        }
+
+        Ok(errors)
+    }
+
+    /// Check the affected functions of an advisory against the source of the
+    /// crate on crates.io
+    fn affected_function_lints(
+        &mut self,
+        advisory: &rustsec::Advisory,
+    ) -> Result<Vec<String>, Error> {
+        let has_functions = advisory
+            .affected
+            .as_ref()
+            .map_or(false, |affected| !affected.functions.is_empty());
+
+        let source_checker = match &mut self.source_checker {
+            Some(source_checker) if has_functions => source_checker,
+            _ => return Ok(vec![]),
+        };
+
+        let krate = self.crates_index.krate(
+            advisory.metadata.package.as_str().try_into()?,
+            true,
+            &acquire_cargo_package_lock()?,
+        )?;
+
+        match krate {
+            Some(krate) => source_checker.check(advisory, &krate),
+            None => Ok(vec![]),
+        }
+    }
+
     /// Checks whether the name is in the skiplist
     fn name_is_skipped(&self, package_name: &str) -> bool {
         match &self.skip_namecheck {
@@ -174,3 +338,70 @@ impl Linter {
         }
     }
 }
+
+/// Checks that URLs resolve, remembering the outcome for each URL since the
+/// same links are referenced by many advisories
+struct UrlChecker {
+    /// HTTP client which doesn't follow redirects
+    client: Client,
+
+    /// Outcome of previous checks
+    checked: BTreeMap<String, Result<(), String>>,
+}
+
+impl UrlChecker {
+    /// Timeout for each request
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Create a new URL checker
+    fn new() -> Result<Self, Error> {
+        // Only permanent redirects are accepted, so they aren't followed
+        let client = Client::builder()
+            .user_agent(concat!("rustsec-admin/", env!("CARGO_PKG_VERSION")))
+            .timeout(Self::TIMEOUT)
+            .redirect(redirect::Policy::none())
+            .build()
+            .map_err(|e| format_err!(ErrorKind::Io, "couldn't create HTTP client: {}", e))?;
+
+        Ok(Self {
+            client,
+            checked: BTreeMap::new(),
+        })
+    }
+
+    /// Check that the given URL responds with 200 OK or a permanent redirect
+    fn check(&mut self, url: &reqwest::Url) -> Result<(), String> {
+        if let Some(result) = self.checked.get(url.as_str()) {
+            return result.clone();
+        }
+
+        let result = self.request(reqwest::Method::HEAD, url).and_then(|status| {
+            // Some servers don't support HEAD requests
+            if status == StatusCode::METHOD_NOT_ALLOWED {
+                self.request(reqwest::Method::GET, url)
+            } else {
+                Ok(status)
+            }
+        });
+
+        let result = match result {
+            Ok(StatusCode::OK | StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT) => {
+                Ok(())
+            }
+            Ok(status) => Err(format!("HTTP {}", status)),
+            Err(e) => Err(e),
+        };
+
+        self.checked.insert(url.to_string(), result.clone());
+        result
+    }
+
+    /// Perform a request, returning the response status
+    fn request(&self, method: reqwest::Method, url: &reqwest::Url) -> Result<StatusCode, String> {
+        self.client
+            .request(method, url.as_str())
+            .send()
+            .map(|response| response.status())
+            .map_err(|e| e.to_string())
+    }
+}
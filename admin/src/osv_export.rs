@@ -27,10 +27,12 @@ pub struct OsvExporter {
 impl OsvExporter {
     /// Load the the database at the given path
     pub fn new(repo_path: Option<&Path>) -> Result<Self, Error> {
-        let repository = match repo_path {
+        let mut repository = match repo_path {
             Some(path) => Repository::open(path)?,
             None => Repository::fetch_default_repo()?,
         };
+        // Modification times need the full history
+        repository.unshallow()?;
         let mod_times = GitModificationTimes::new(&repository)?;
         Ok(Self {
             repository,
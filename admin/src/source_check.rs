@@ -0,0 +1,302 @@
+//! Validation of `affected.functions` paths against the published source of
+//! the affected crate
+//!
+//! For each path, the latest non-yanked release to which it applies is
+//! downloaded from crates.io and its source is scanned for the items named by
+//! the path. Items can be re-exported under other paths, so the check is by
+//! name: the last segment must be a function, method or type defined in the
+//! crate (a method of the preceding type, if that is a type), and every other
+//! segment must be a module or type defined in the crate. This catches typos
+//! and stale paths, without attempting full name resolution.
+
+use crate::error::{Error, ErrorKind};
+use rustsec::{
+    advisory::affected::FunctionPath,
+    semver::{Version, VersionReq},
+    Advisory,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Read,
+    path::Path,
+    time::Duration,
+};
+use syn::visit::{self, Visit};
+use tame_index::{external::reqwest::blocking::Client, IndexKrate};
+
+/// Base URL for downloading `.crate` files
+const DOWNLOAD_URL: &str = "https://static.crates.io/crates";
+
+/// Timeout for each download
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Checks affected function paths against crate sources, remembering the
+/// items of each crate version it has downloaded
+pub struct SourceChecker {
+    /// HTTP client used for downloads
+    client: Client,
+
+    /// Items defined in previously downloaded crate versions
+    sources: BTreeMap<(String, Version), SourceItems>,
+}
+
+impl SourceChecker {
+    /// Create a new source checker
+    pub fn new() -> Result<Self, Error> {
+        let client = Client::builder()
+            .user_agent(concat!("rustsec-admin/", env!("CARGO_PKG_VERSION")))
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build()
+            .map_err(|e| format_err!(ErrorKind::Io, "couldn't create HTTP client: {}", e))?;
+
+        Ok(Self {
+            client,
+            sources: BTreeMap::new(),
+        })
+    }
+
+    /// Check the affected functions of an advisory, returning a message for
+    /// each path which couldn't be found
+    pub fn check(&mut self, advisory: &Advisory, krate: &IndexKrate) -> Result<Vec<String>, Error> {
+        let functions = match &advisory.affected {
+            Some(affected) => &affected.functions,
+            None => return Ok(vec![]),
+        };
+
+        let name = advisory.metadata.package.as_str();
+        let mut errors = vec![];
+
+        for (path, reqs) in functions {
+            let version = match latest_affected_version(advisory, krate, reqs) {
+                Some(version) => version,
+                None => {
+                    errors.push(format!(
+                        "no published version of {} matches the versions of `{}`",
+                        name, path
+                    ));
+                    continue;
+                }
+            };
+
+            let items = self.items(name, &version)?;
+
+            if !items.contains(path) {
+                errors.push(format!("`{}` not found in {} {}", path, name, version));
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Get the items defined in a crate version, downloading it if needed
+    fn items(&mut self, name: &str, version: &Version) -> Result<&SourceItems, Error> {
+        let key = (name.to_owned(), version.clone());
+
+        if !self.sources.contains_key(&key) {
+            let items = self.download(name, version)?;
+            self.sources.insert(key.clone(), items);
+        }
+
+        Ok(&self.sources[&key])
+    }
+
+    /// Download a crate version and collect the items defined in its source
+    fn download(&self, name: &str, version: &Version) -> Result<SourceItems, Error> {
+        let url = format!("{}/{}/{}-{}.crate", DOWNLOAD_URL, name, name, version);
+
+        let body = self
+            .client
+            .get(&url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .map_err(|e| format_err!(ErrorKind::CratesIo, "couldn't download {}: {}", url, e))?;
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(body.as_ref()));
+        let mut items = SourceItems::default();
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+                continue;
+            }
+
+            let mut source = String::new();
+            if entry.read_to_string(&mut source).is_err() {
+                continue;
+            }
+
+            items.add_module_file(&path);
+
+            // Files which don't parse (e.g. using newer syntax) are skipped
+            if let Ok(file) = syn::parse_file(&source) {
+                items.visit_file(&file);
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+/// Find the latest non-yanked release to which a function path applies and
+/// which is affected by the advisory
+fn latest_affected_version(
+    advisory: &Advisory,
+    krate: &IndexKrate,
+    reqs: &[VersionReq],
+) -> Option<Version> {
+    krate
+        .versions
+        .iter()
+        .filter(|release| !release.yanked)
+        .filter_map(|release| release.version.parse::<Version>().ok())
+        .filter(|version| reqs.iter().any(|req| req.matches(version)))
+        .filter(|version| advisory.versions.is_vulnerable(version))
+        .max()
+}
+
+/// Names of the items defined in a crate's source
+#[derive(Debug, Default)]
+struct SourceItems {
+    /// Modules (inline or from files)
+    modules: BTreeSet<String>,
+
+    /// Structs, enums, unions, type aliases and traits
+    types: BTreeSet<String>,
+
+    /// Free functions
+    functions: BTreeSet<String>,
+
+    /// Methods defined in `impl` and `trait` blocks, by type or trait
+    methods: BTreeMap<String, BTreeSet<String>>,
+
+    /// Traits implemented for each type
+    impls: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl SourceItems {
+    /// Does the crate define the items named by the given path?
+    fn contains(&self, path: &FunctionPath) -> bool {
+        let segments: Vec<&str> = path.iter().map(|segment| segment.as_str()).collect();
+
+        let (last, parents) = match segments.split_last() {
+            Some((last, parents)) if !parents.is_empty() => (*last, &parents[1..]),
+            _ => return false,
+        };
+
+        // Leading segments (after the crate name) must be modules or types
+        if !parents
+            .iter()
+            .all(|segment| self.modules.contains(*segment) || self.types.contains(*segment))
+        {
+            return false;
+        }
+
+        match parents.last() {
+            Some(parent) if self.types.contains(*parent) => self.has_method(parent, last),
+            _ => self.functions.contains(last) || self.types.contains(last),
+        }
+    }
+
+    /// Does the given type (or trait) have a method with the given name,
+    /// either of its own or from a trait it implements?
+    fn has_method(&self, ty: &str, method: &str) -> bool {
+        let defines = |name: &str| {
+            self.methods
+                .get(name)
+                .map_or(false, |methods| methods.contains(method))
+        };
+
+        defines(ty)
+            || self
+                .impls
+                .get(ty)
+                .map_or(false, |traits| traits.iter().any(|t| defines(t)))
+    }
+
+    /// Add the module corresponding to a source file (e.g. `src/foo.rs` or
+    /// `src/foo/mod.rs`)
+    fn add_module_file(&mut self, path: &Path) {
+        let module = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some("mod") => path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .and_then(|name| name.to_str()),
+            other => other,
+        };
+
+        if let Some(module) = module {
+            self.modules.insert(module.to_owned());
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for SourceItems {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        self.modules.insert(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.functions.insert(item.sig.ident.to_string());
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        self.types.insert(item.ident.to_string());
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        self.types.insert(item.ident.to_string());
+    }
+
+    fn visit_item_union(&mut self, item: &'ast syn::ItemUnion) {
+        self.types.insert(item.ident.to_string());
+    }
+
+    fn visit_item_type(&mut self, item: &'ast syn::ItemType) {
+        self.types.insert(item.ident.to_string());
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        let name = item.ident.to_string();
+        let methods = self.methods.entry(name.clone()).or_default();
+
+        for trait_item in &item.items {
+            if let syn::TraitItem::Fn(method) = trait_item {
+                methods.insert(method.sig.ident.to_string());
+            }
+        }
+
+        self.types.insert(name);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let ty = match &*item.self_ty {
+            syn::Type::Path(path) => match path.path.segments.last() {
+                Some(segment) => segment.ident.to_string(),
+                None => return,
+            },
+            _ => return,
+        };
+
+        let methods = self.methods.entry(ty.clone()).or_default();
+
+        for impl_item in &item.items {
+            if let syn::ImplItem::Fn(method) = impl_item {
+                methods.insert(method.sig.ident.to_string());
+            }
+        }
+
+        if let Some((_, trait_path, _)) = &item.trait_ {
+            if let Some(segment) = trait_path.segments.last() {
+                self.impls
+                    .entry(ty)
+                    .or_default()
+                    .insert(segment.ident.to_string());
+            }
+        }
+    }
+}
@@ -103,10 +103,12 @@ pub fn render_advisories(output_folder: PathBuf) {
     fs::create_dir_all(&output_folder).unwrap();
 
     // Get static pages from repository
-    let repo = Repository::fetch_default_repo().unwrap();
+    let mut repo = Repository::fetch_default_repo().unwrap();
     let contributing_path = repo.path().join("CONTRIBUTING.md");
 
-    // Get publication and latest modification dates
+    // Get publication and latest modification dates, which need the full
+    // history
+    repo.unshallow().unwrap();
     let mod_times = GitModificationTimes::new(&repo).unwrap();
 
     // Get advisories
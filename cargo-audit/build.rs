@@ -0,0 +1,86 @@
+//! Build script for `cargo-audit`
+//!
+//! When the `embedded-db` feature is enabled, this embeds a compressed
+//! snapshot of the advisory database located at the path given in the
+//! `CARGO_AUDIT_EMBEDDED_DB` environment variable (a git checkout of
+//! <https://github.com/RustSec/advisory-db>).
+
+fn main() {
+    #[cfg(feature = "embedded-db")]
+    embedded_db::generate();
+
+    #[cfg(not(feature = "embedded-db"))]
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+#[cfg(feature = "embedded-db")]
+mod embedded_db {
+    use flate2::{write::GzEncoder, Compression};
+    use rustsec::{repository::git::Repository, Database};
+    use std::{
+        env,
+        fs::File,
+        io::{BufWriter, Write},
+        path::PathBuf,
+    };
+
+    /// Environment variable containing the path to the advisory database
+    const DB_PATH_VAR: &str = "CARGO_AUDIT_EMBEDDED_DB";
+
+    /// Name of the generated snapshot file in `OUT_DIR`
+    const SNAPSHOT_FILE: &str = "advisory-db.json.gz";
+
+    pub fn generate() {
+        println!("cargo:rerun-if-env-changed={}", DB_PATH_VAR);
+
+        let db_path = PathBuf::from(env::var_os(DB_PATH_VAR).unwrap_or_else(|| {
+            panic!(
+                "the `embedded-db` feature requires {} to point to a checkout of the advisory database",
+                DB_PATH_VAR
+            )
+        }));
+
+        println!("cargo:rerun-if-changed={}", db_path.display());
+
+        let repo = Repository::open(&db_path).unwrap_or_else(|e| {
+            panic!(
+                "couldn't open advisory database at {}: {}",
+                db_path.display(),
+                e
+            )
+        });
+
+        let db = Database::load_from_repo(&repo)
+            .unwrap_or_else(|e| panic!("couldn't load advisory database: {}", e));
+
+        let commit = db
+            .latest_commit()
+            .expect("missing advisory database commit");
+
+        let out_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join(SNAPSHOT_FILE);
+        let file = File::create(&out_path).expect("couldn't create advisory database snapshot");
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::best());
+
+        let advisories: Vec<_> = db.iter().collect();
+        serde_json::to_writer(&mut encoder, &advisories)
+            .expect("couldn't serialize advisory database snapshot");
+
+        encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .expect("couldn't write advisory database snapshot");
+
+        println!(
+            "cargo:rustc-env=CARGO_AUDIT_EMBEDDED_DB_COMMIT={}",
+            commit.commit_id.to_hex()
+        );
+        println!(
+            "cargo:rustc-env=CARGO_AUDIT_EMBEDDED_DB_DATE={}",
+            commit.timestamp.date()
+        );
+        println!(
+            "cargo:rustc-env=CARGO_AUDIT_EMBEDDED_DB_TIMESTAMP={}",
+            commit.timestamp.unix_timestamp()
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Groups per-platform builds of the same artifact (e.g. `myapp-linux-x86_64`
+//! and `myapp-aarch64-apple-darwin`) into a single report, recording which
+//! platforms each finding applies to.
+
+use rustsec::{
+    advisory, package, platforms::Platform, Report, Version, Vulnerability, Warning, WarningKind,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap as Map, path::Path};
+
+/// Words in file names which identify the platform a binary was built for
+const PLATFORM_TOKENS: &[&str] = &[
+    "x86_64",
+    "amd64",
+    "x64",
+    "x86",
+    "i686",
+    "i586",
+    "i386",
+    "aarch64",
+    "arm64",
+    "armv7",
+    "armv7l",
+    "riscv64",
+    "riscv64gc",
+    "powerpc64le",
+    "ppc64le",
+    "s390x",
+    "wasm32",
+    "linux",
+    "windows",
+    "win32",
+    "win64",
+    "macos",
+    "darwin",
+    "osx",
+    "freebsd",
+    "netbsd",
+    "openbsd",
+    "android",
+    "ios",
+];
+
+/// Words which are only taken as part of a platform next to one of the
+/// [`PLATFORM_TOKENS`], since they're common in names too (e.g. `arm` in
+/// `arm-toolkit` or `pc` in `pc-tool`)
+const QUALIFIER_TOKENS: &[&str] = &[
+    "arm",
+    "win",
+    "apple",
+    "unknown",
+    "pc",
+    "gnu",
+    "musl",
+    "msvc",
+    "gnueabihf",
+    "universal",
+];
+
+/// Report for a logical artifact which was built for several platforms
+#[derive(Clone, Debug, Serialize)]
+pub struct ArtifactReport {
+    /// Name of the artifact, with platform identifiers removed
+    pub artifact: String,
+
+    /// Platforms the artifact was built for
+    pub platforms: Vec<String>,
+
+    /// Vulnerabilities found in any of the builds
+    pub vulnerabilities: Vec<Finding<Vulnerability>>,
+
+    /// Warnings found in any of the builds
+    pub warnings: Vec<Finding<Warning>>,
+}
+
+/// A vulnerability or warning, along with the platforms it applies to
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding<T> {
+    /// The vulnerability or warning
+    #[serde(flatten)]
+    pub finding: T,
+
+    /// Platforms whose builds are affected
+    pub platforms: Vec<String>,
+}
+
+/// Key identifying the same vulnerability across builds
+type VulnerabilityKey = (advisory::Id, package::Name, Version);
+
+/// Key identifying the same warning across builds
+type WarningKey = (WarningKind, Option<advisory::Id>, package::Name, Version);
+
+/// Builder which merges the reports for each build of an artifact
+#[derive(Debug)]
+pub struct ArtifactGroup {
+    artifact: String,
+    platforms: Vec<String>,
+    vulnerabilities: Map<VulnerabilityKey, Finding<Vulnerability>>,
+    warnings: Map<WarningKey, Finding<Warning>>,
+}
+
+impl ArtifactGroup {
+    /// Create an empty group for the given artifact
+    pub fn new(artifact: String) -> Self {
+        Self {
+            artifact,
+            platforms: vec![],
+            vulnerabilities: Map::new(),
+            warnings: Map::new(),
+        }
+    }
+
+    /// Add the report for a build of this artifact on the given platform
+    pub fn add(&mut self, platform: String, report: Report) {
+        for vuln in report.vulnerabilities.list {
+            let key = (
+                vuln.advisory.id.clone(),
+                vuln.package.name.clone(),
+                vuln.package.version.clone(),
+            );
+
+            self.vulnerabilities
+                .entry(key)
+                .or_insert_with(|| Finding {
+                    finding: vuln,
+                    platforms: vec![],
+                })
+                .platforms
+                .push(platform.clone());
+        }
+
+        for warning in report.warnings.into_values().flatten() {
+            let key = (
+                warning.kind,
+                warning
+                    .advisory
+                    .as_ref()
+                    .map(|metadata| metadata.id.clone()),
+                warning.package.name.clone(),
+                warning.package.version.clone(),
+            );
+
+            self.warnings
+                .entry(key)
+                .or_insert_with(|| Finding {
+                    finding: warning,
+                    platforms: vec![],
+                })
+                .platforms
+                .push(platform.clone());
+        }
+
+        self.platforms.push(platform);
+    }
+
+    /// Finish building the report for this artifact
+    pub fn into_report(self) -> ArtifactReport {
+        ArtifactReport {
+            artifact: self.artifact,
+            platforms: self.platforms,
+            vulnerabilities: self.vulnerabilities.into_values().collect(),
+            warnings: self.warnings.into_values().collect(),
+        }
+    }
+}
+
+/// Split a binary's path into the name of the logical artifact it is a build
+/// of, and a label for the platform it was built for.
+///
+/// A platform suffix is stripped from the file name where present:
+/// `myapp-1.2.0-x86_64-unknown-linux-gnu` is `myapp-1.2.0` built for
+/// `x86_64-unknown-linux-gnu`, and `myapp-linux-arm64` is `myapp` built for
+/// `linux-arm64`. Otherwise the name of the parent directory is used as the
+/// platform label (`dist/linux-arm64/myapp`).
+pub fn artifact_and_platform(path: &Path) -> (String, String) {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let stem = file_name
+        .strip_suffix(".exe")
+        .unwrap_or(&file_name)
+        .to_owned();
+
+    // Only whole `-` separated words are stripped, keeping e.g. versions
+    // (`1.2.0`) intact, and at least one word is left for the name
+    let tokens: Vec<&str> = stem.split('-').collect();
+    if let Some(start) = (1..tokens.len()).find(|&start| is_platform(&tokens[start..])) {
+        return (tokens[..start].join("-"), tokens[start..].join("-"));
+    }
+
+    let parent = path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+
+    (stem, parent)
+}
+
+/// Do the given words of a file name identify a platform, either as a
+/// target triple or as platform words such as `linux-arm64`?
+fn is_platform(tokens: &[&str]) -> bool {
+    let tokens: Vec<String> = tokens
+        .iter()
+        .map(|token| token.to_ascii_lowercase())
+        .collect();
+
+    if Platform::find(&tokens.join("-")).is_some() {
+        return true;
+    }
+
+    tokens.iter().all(|token| {
+        PLATFORM_TOKENS.contains(&token.as_str()) || QUALIFIER_TOKENS.contains(&token.as_str())
+    }) && tokens
+        .iter()
+        .any(|token| PLATFORM_TOKENS.contains(&token.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Report with a `yanked` warning about the only package of a lockfile
+    fn yanked_report() -> Report {
+        let lockfile: rustsec::Lockfile = r#"
+version = 3
+
+[[package]]
+name = "left-pad"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap();
+
+        let database = rustsec::Database::from_advisories(vec![]).unwrap();
+        let mut report = Report::generate(&database, &lockfile, &Default::default());
+        report.warnings.insert(
+            WarningKind::Yanked,
+            vec![Warning::new(
+                WarningKind::Yanked,
+                &lockfile.packages[0],
+                None,
+                None,
+                None,
+            )],
+        );
+        report
+    }
+
+    #[test]
+    fn merged_findings() {
+        let mut clean = yanked_report();
+        clean.warnings.clear();
+
+        let mut group = ArtifactGroup::new("myapp".to_owned());
+        group.add("linux-x86_64".to_owned(), yanked_report());
+        group.add("macos-arm64".to_owned(), clean);
+        group.add("windows-x86_64".to_owned(), yanked_report());
+
+        let report = group.into_report();
+        assert_eq!(report.artifact, "myapp");
+        assert_eq!(
+            report.platforms,
+            ["linux-x86_64", "macos-arm64", "windows-x86_64"]
+        );
+        assert!(report.vulnerabilities.is_empty());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(
+            report.warnings[0].platforms,
+            ["linux-x86_64", "windows-x86_64"]
+        );
+    }
+
+    fn split(path: &str) -> (String, String) {
+        artifact_and_platform(Path::new(path))
+    }
+
+    #[test]
+    fn target_triples() {
+        assert_eq!(
+            split("dist/myapp-x86_64-unknown-linux-gnu"),
+            ("myapp".to_owned(), "x86_64-unknown-linux-gnu".to_owned())
+        );
+        assert_eq!(
+            split("dist/myapp-x86_64-pc-windows-msvc.exe"),
+            ("myapp".to_owned(), "x86_64-pc-windows-msvc".to_owned())
+        );
+        assert_eq!(
+            split("dist/my-app-aarch64-apple-darwin"),
+            ("my-app".to_owned(), "aarch64-apple-darwin".to_owned())
+        );
+    }
+
+    #[test]
+    fn platform_words() {
+        assert_eq!(
+            split("myapp-linux-arm64"),
+            ("myapp".to_owned(), "linux-arm64".to_owned())
+        );
+        assert_eq!(
+            split("myapp-macos-universal"),
+            ("myapp".to_owned(), "macos-universal".to_owned())
+        );
+    }
+
+    /// Versions and words which are only platform qualifiers are kept
+    #[test]
+    fn names_are_kept() {
+        assert_eq!(
+            split("myapp-1.2.0-x86_64-unknown-linux-musl"),
+            (
+                "myapp-1.2.0".to_owned(),
+                "x86_64-unknown-linux-musl".to_owned()
+            )
+        );
+        assert_eq!(
+            split("dist/linux/arm-toolkit"),
+            ("arm-toolkit".to_owned(), "linux".to_owned())
+        );
+        assert_eq!(
+            split("dist/windows/pc-win"),
+            ("pc-win".to_owned(), "windows".to_owned())
+        );
+        assert_eq!(
+            split("dist/linux-arm64/myapp"),
+            ("myapp".to_owned(), "linux-arm64".to_owned())
+        );
+    }
+}
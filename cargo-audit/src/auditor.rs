@@ -1,20 +1,42 @@
 //! Core auditing functionality
 
 use crate::{
-    binary_format::BinaryFormat, config::AuditConfig, error::display_err_with_source, prelude::*,
+    binary_format::BinaryFormat,
+    config::{AuditConfig, OsvMode, PackagesConfig},
+    dependency_list::StdinFormat,
+    error::display_err_with_source,
+    metadata::Metadata,
+    prelude::*,
     presenter::Presenter,
+    progress::ProgressBar,
+    strict::{self, Anomaly, Failure},
+};
+use rustsec::{
+    advisory,
+    progress::Progress,
+    registry, report,
+    transport::{HttpTransport, Transport, Vcr},
+    Error, ErrorKind, Lockfile, Warning, WarningKind,
 };
-use rustsec::{registry, report, Error, ErrorKind, Lockfile, Warning, WarningKind};
 use std::{
     io::{self, Read},
-    path::Path,
+    path::{Path, PathBuf},
     process::exit,
+    sync::Arc,
     time::Duration,
 };
 
 // TODO: make configurable
 const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
+/// Maximum size of the files binaries are loaded from, which are read into
+/// memory in full
+#[cfg(feature = "binary-scanning")]
+const MAX_BINARY_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Timeout for API requests made while recording them
+const RECORD_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Security vulnerability auditor
 pub struct Auditor {
     /// RustSec Advisory Database
@@ -28,17 +50,79 @@ pub struct Auditor {
 
     /// Audit report settings
     report_settings: report::Settings,
+
+    /// Check for yanked crates?
+    check_yanked: bool,
+
+    /// Add release information to findings?
+    release_info: bool,
+
+    /// Suggest upgrades resolving vulnerabilities?
+    suggest_upgrades: bool,
+
+    /// Client for looking up release dates (only when fetching is enabled)
+    crates_io: Option<registry::CratesIoClient>,
+
+    /// Treat skipped checks and incomplete data as errors?
+    strict: bool,
+
+    /// Client for querying osv.dev for additional advisories
+    osv: Option<rustsec::osv::OsvClient>,
+
+    /// Are advisories exclusively taken from osv.dev?
+    osv_only: bool,
+
+    /// Client for enriching advisories with data from NVD
+    nvd: Option<rustsec::nvd::NvdClient>,
+
+    /// Downgrade vulnerabilities whose affected functions aren't in audited binaries?
+    filter_symbols: bool,
+
+    /// Files and directories to look for separate debug info of binaries in
+    debug_info: Vec<PathBuf>,
+
+    /// Report vulnerabilities regardless of the crate features enabled in the project?
+    ignore_features: bool,
+
+    /// Exclude findings for packages which are only used as dev-dependencies?
+    exclude_dev: bool,
+
+    /// Check patched versions against the version requirements in the
+    /// project's manifests?
+    check_requirements: bool,
+
+    /// Target triple whose dependencies are audited, excluding those only
+    /// used on other platforms
+    target_triple: Option<String>,
+
+    /// Warnings allowed for specific packages
+    package_overrides: PackagesConfig,
+
+    /// Format of the dependency list read from STDIN
+    stdin_format: StdinFormat,
+
+    /// File the dependencies of `cargo-audit` itself are cached in, if
+    /// caching is enabled
+    #[cfg(feature = "binary-scanning")]
+    self_deps_cache: Option<PathBuf>,
 }
 
 impl Auditor {
     /// Initialize the auditor
     pub fn new(config: &AuditConfig) -> Self {
-        let advisory_db_url = config
-            .database
-            .url
-            .as_ref()
-            .map(AsRef::as_ref)
-            .unwrap_or(rustsec::repository::git::DEFAULT_URL);
+        let transport = vcr_transport(config);
+
+        // Git fetches and crates.io index updates can't be replayed, so the
+        // local copies are used as they are
+        let replay_config;
+        let config = if transport.is_some() && !config.database.vcr_record {
+            let mut replay = config.clone();
+            replay.database.fetch = false;
+            replay_config = replay;
+            &replay_config
+        } else {
+            config
+        };
 
         let advisory_db_path = config
             .database
@@ -47,88 +131,84 @@ impl Auditor {
             .cloned()
             .unwrap_or_else(rustsec::repository::git::Repository::default_path);
 
-        let database = if config.database.fetch {
-            if !config.output.is_quiet() {
-                status_ok!("Fetching", "advisory database from `{}`", advisory_db_url);
-            }
-
-            let mut result = rustsec::repository::git::Repository::fetch(
-                advisory_db_url,
-                &advisory_db_path,
-                !config.database.stale,
-                Duration::from_secs(0),
-            );
-            // If the directory is locked, print a message and wait for it to become unlocked.
-            // If we don't print the message, `cargo audit` would just hang with no explanation.
-            if let Err(e) = &result {
-                if e.kind() == ErrorKind::LockTimeout {
-                    status_warn!("directory {} is locked, waiting for up to {} seconds for it to become available", advisory_db_path.display(), DEFAULT_LOCK_TIMEOUT.as_secs());
-                    result = rustsec::repository::git::Repository::fetch(
-                        advisory_db_url,
-                        &advisory_db_path,
-                        !config.database.stale,
-                        DEFAULT_LOCK_TIMEOUT,
-                    );
-                }
-            }
+        // Advisories come exclusively from osv.dev in `only` mode
+        let mut database = if config.database.osv == OsvMode::Only {
+            rustsec::Database::from_advisories(vec![]).expect("empty database is valid")
+        } else {
+            load_database(config)
+        };
 
-            let advisory_db_repo = result.unwrap_or_else(|e| {
-                status_err!(
-                    "couldn't fetch advisory database: {}",
-                    display_err_with_source(&e)
+        if config.database.ghsa {
+            if let Err(err) = fetch_ghsa(config, transport.as_ref())
+                .and_then(|advisories| database.extend(advisories))
+            {
+                strict::report(
+                    config.output.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!(
+                        "couldn't fetch GitHub Security Advisories: {}",
+                        display_err_with_source(&err)
+                    ),
                 );
-                exit(1);
-            });
+            }
+        }
 
-            rustsec::Database::load_from_repo(&advisory_db_repo).unwrap_or_else(|e| {
-                status_err!(
-                    "error loading advisory database: {}",
-                    display_err_with_source(&e)
-                );
-                exit(1);
-            })
-        } else {
-            rustsec::Database::open(&advisory_db_path).unwrap_or_else(|e| {
-                status_err!(
-                    "error loading advisory database: {}",
-                    display_err_with_source(&e)
+        if let Some(dir) = &config.database.native_advisories {
+            if let Err(err) =
+                import_native(config, dir).and_then(|advisories| database.extend(advisories))
+            {
+                strict::report(
+                    config.output.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!(
+                        "couldn't import native library advisories: {}",
+                        display_err_with_source(&err)
+                    ),
                 );
-                exit(1);
-            })
-        };
-
-        if !config.output.is_quiet() {
-            status_ok!(
-                "Loaded",
-                "{} security advisories (from {})",
-                database.iter().count(),
-                advisory_db_path.display()
-            );
+            }
         }
 
-        let registry_index = if config.yanked.enabled {
+        let registry_index = if config.yanked.enabled
+            || config.output.release_info
+            || config.output.suggest_upgrades
+        {
             if config.yanked.update_index && config.database.fetch {
                 if !config.output.is_quiet() {
                     status_ok!("Updating", "crates.io index");
                 }
 
-                let mut result = registry::CachedIndex::fetch(None, Duration::from_secs(0));
+                let progress = progress_bar(config, "Updating");
+                let fetch = |lock_timeout| {
+                    registry::CachedIndex::fetch_with_options(
+                        None,
+                        lock_timeout,
+                        config.network_settings(),
+                        progress.clone(),
+                    )
+                };
+
+                let mut result = fetch(Duration::from_secs(0));
 
                 // If the directory is locked, print a message and wait for it to become unlocked.
                 // If we don't print the message, `cargo audit` would just hang with no explanation.
                 if let Err(e) = &result {
                     if e.kind() == ErrorKind::LockTimeout {
                         status_warn!("directory {} is locked, waiting for up to {} seconds for it to become available", advisory_db_path.display(), DEFAULT_LOCK_TIMEOUT.as_secs());
-                        result = registry::CachedIndex::fetch(None, DEFAULT_LOCK_TIMEOUT);
+                        result = fetch(DEFAULT_LOCK_TIMEOUT);
                     }
                 }
 
                 match result {
                     Ok(index) => Some(index),
                     Err(err) => {
-                        if !config.output.is_quiet() {
-                            status_warn!("couldn't update crates.io index: {}", err);
-                        }
+                        strict::report(
+                            config.output.strict,
+                            config.output.is_quiet(),
+                            Anomaly::SkippedChecks,
+                            format_args!("couldn't update crates.io index: {}", err),
+                        );
 
                         None
                     }
@@ -148,9 +228,12 @@ impl Auditor {
                 match result {
                     Ok(index) => Some(index),
                     Err(err) => {
-                        if !config.output.is_quiet() {
-                            status_warn!("couldn't open crates.io index: {}", err);
-                        }
+                        strict::report(
+                            config.output.strict,
+                            config.output.is_quiet(),
+                            Anomaly::SkippedChecks,
+                            format_args!("couldn't open crates.io index: {}", err),
+                        );
 
                         None
                     }
@@ -160,67 +243,567 @@ impl Auditor {
             None
         };
 
+        let crates_io =
+            if config.output.release_info && (config.database.fetch || transport.is_some()) {
+                match registry::CratesIoClient::new() {
+                    Ok(client) => Some(match &transport {
+                        Some(transport) => client.transport(transport.clone()),
+                        None => client,
+                    }),
+                    Err(err) => {
+                        strict::report(
+                            config.output.strict,
+                            false,
+                            Anomaly::SkippedChecks,
+                            format_args!("couldn't look up release dates: {}", err),
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+        let osv = if config.database.osv == OsvMode::Off {
+            None
+        } else {
+            match rustsec::osv::OsvClient::new() {
+                Ok(client) => Some(match &transport {
+                    Some(transport) => client.transport(transport.clone()),
+                    None => client,
+                }),
+                Err(err) if config.database.osv == OsvMode::Only => {
+                    status_err!("couldn't query osv.dev: {}", err);
+                    exit(1);
+                }
+                Err(err) => {
+                    strict::report(
+                        config.output.strict,
+                        false,
+                        Anomaly::SkippedChecks,
+                        format_args!("couldn't query osv.dev: {}", err),
+                    );
+                    None
+                }
+            }
+        };
+
+        let nvd = if config.database.nvd {
+            match rustsec::nvd::NvdClient::new(nvd_cache_path(&advisory_db_path)) {
+                Ok(client) => {
+                    let client = match &transport {
+                        Some(transport) => client.transport(transport.clone()),
+                        None => client,
+                    };
+
+                    Some(match std::env::var("NVD_API_KEY") {
+                        Ok(api_key) => client.api_key(api_key),
+                        Err(_) => client,
+                    })
+                }
+                Err(err) => {
+                    strict::report(
+                        config.output.strict,
+                        false,
+                        Anomaly::SkippedChecks,
+                        format_args!("couldn't look up advisories in NVD: {}", err),
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         Self {
             database,
             registry_index,
             presenter: Presenter::new(&config.output),
             report_settings: config.report_settings(),
+            check_yanked: config.yanked.enabled,
+            release_info: config.output.release_info,
+            suggest_upgrades: config.output.suggest_upgrades,
+            crates_io,
+            strict: config.output.strict,
+            osv,
+            osv_only: config.database.osv == OsvMode::Only,
+            nvd,
+            filter_symbols: config.advisories.filter_symbols,
+            debug_info: config.advisories.debug_info.clone(),
+            ignore_features: config.advisories.ignore_features,
+            exclude_dev: config.advisories.exclude_dev,
+            check_requirements: config.advisories.check_requirements,
+            target_triple: config.target.triple.clone(),
+            package_overrides: config.packages.clone(),
+            stdin_format: StdinFormat::default(),
+            #[cfg(feature = "binary-scanning")]
+            self_deps_cache: config
+                .database
+                .cache_enabled()
+                .then(|| self_deps_cache_path(&advisory_db_path)),
         }
     }
 
+    /// Get the advisory database this auditor audits against
+    pub fn database(&self) -> &rustsec::Database {
+        &self.database
+    }
+
+    /// Set the format of the dependency list read from STDIN when the
+    /// lockfile path is `-`
+    pub fn stdin_format(&mut self, format: StdinFormat) {
+        self.stdin_format = format;
+    }
+
     /// Perform an audit of a textual `Cargo.lock` file
     pub fn audit_lockfile(&mut self, lockfile_path: &Path) -> rustsec::Result<rustsec::Report> {
-        let lockfile = match self.load_lockfile(lockfile_path) {
-            Ok(l) => l,
-            Err(e) => {
-                return Err(Error::with_source(
-                    ErrorKind::NotFound,
-                    format!("Couldn't load {}", lockfile_path.display()),
-                    e,
-                ))
-            }
-        };
+        let lockfile = self.load_lockfile_for_audit(lockfile_path)?;
 
         self.presenter.before_report(lockfile_path, &lockfile);
 
-        let report = self.audit(&lockfile, None, None);
+        let report = self.lockfile_report(lockfile_path, &lockfile);
+
+        self.presenter.print_report(&report, &lockfile, None);
 
         let self_advisories = self.self_advisories();
 
         self.presenter.print_self_report(self_advisories.as_slice());
 
+        Ok(report)
+    }
+
+    /// Print a report generated earlier for a textual `Cargo.lock` file
+    /// (e.g. a cached one) the same way [`Auditor::audit_lockfile`] does,
+    /// including advisories about `cargo-audit` itself
+    pub fn present_lockfile_report(
+        &mut self,
+        lockfile_path: &Path,
+        report: &rustsec::Report,
+    ) -> rustsec::Result<()> {
+        let lockfile = self.load_lockfile_for_audit(lockfile_path)?;
+
+        self.presenter.before_report(lockfile_path, &lockfile);
+        self.presenter.print_report(report, &lockfile, None);
+
+        let self_advisories = self.self_advisories();
+
+        self.presenter.print_self_report(self_advisories.as_slice());
+
+        Ok(())
+    }
+
+    /// Audit a textual `Cargo.lock` file without printing anything, e.g. for
+    /// subcommands which present the results in their own way
+    pub fn report_lockfile(&mut self, lockfile_path: &Path) -> rustsec::Result<rustsec::Report> {
+        let lockfile = self.load_lockfile_for_audit(lockfile_path)?;
+        Ok(self.lockfile_report(lockfile_path, &lockfile))
+    }
+
+    /// Audit a textual `Cargo.lock` file and compare its findings with those
+    /// of a baseline report, e.g. from a previous `cargo audit --json` run
+    pub fn compare_lockfile(
+        &mut self,
+        lockfile_path: &Path,
+        baseline: &rustsec::Report,
+    ) -> rustsec::Result<report::diff::ReportDiff> {
+        let lockfile = self.load_lockfile_for_audit(lockfile_path)?;
+
+        self.presenter.before_report(lockfile_path, &lockfile);
+
+        let report = self.lockfile_report(lockfile_path, &lockfile);
+        let diff = report::diff::ReportDiff::new(baseline, &report);
+
+        self.presenter
+            .print_compared_report(&report, &lockfile, &diff);
+
+        Ok(diff)
+    }
+
+    /// Load the `Cargo.lock` file to audit
+    fn load_lockfile_for_audit(&self, lockfile_path: &Path) -> rustsec::Result<Lockfile> {
+        self.load_lockfile(lockfile_path).map_err(|e| {
+            Error::with_source(
+                ErrorKind::NotFound,
+                format!("Couldn't load {}", lockfile_path.display()),
+                e,
+            )
+        })
+    }
+
+    /// Generate the report for a `Cargo.lock` file
+    fn lockfile_report(&mut self, lockfile_path: &Path, lockfile: &Lockfile) -> rustsec::Report {
+        // `cargo metadata` can't be run for lockfiles read from STDIN
+        if lockfile_path == Path::new("-") {
+            let mut report = self
+                .build_report(lockfile, None, None)
+                .unwrap_or_else(Failure::exit);
+            apply_package_overrides(&self.package_overrides, &mut report);
+            return report;
+        }
+
+        // Packages which are only used on other platforms than the target
+        // are excluded from matching entirely
+        let metadata = match &self.target_triple {
+            Some(target) => match Metadata::load_for_target(lockfile_path, Some(target)) {
+                Ok(metadata) => Some(metadata),
+                Err(err) => {
+                    strict::report(
+                        self.strict,
+                        false,
+                        Anomaly::SkippedChecks,
+                        format_args!(
+                            "couldn't resolve the dependencies used on {}: {}",
+                            target,
+                            display_err_with_source(&err)
+                        ),
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut report = match &metadata {
+            Some(metadata) => self.build_report(&metadata.prune(lockfile), None, None),
+            None => self.build_report(lockfile, None, None),
+        }
+        .unwrap_or_else(Failure::exit);
+
+        self.refine_with_metadata(lockfile_path, metadata, &mut report);
+        apply_package_overrides(&self.package_overrides, &mut report);
         report
     }
 
+    /// Refine the report with how the project is built according to `cargo metadata`:
+    /// exclude findings for dev-only dependencies (if enabled), downgrade
+    /// vulnerabilities in code gated by disabled crate features, and check
+    /// patched versions against the actual version requirements (if
+    /// enabled). Metadata which was already loaded is reused.
+    fn refine_with_metadata(
+        &self,
+        lockfile_path: &Path,
+        metadata: Option<Metadata>,
+        report: &mut rustsec::Report,
+    ) {
+        // Avoid running `cargo metadata` unless it can make a difference
+        let filter_features = !self.ignore_features
+            && report
+                .vulnerabilities
+                .list
+                .iter()
+                .any(|vuln| !vuln.affected_features().is_empty());
+
+        let check_patched = self.check_requirements
+            && report
+                .vulnerabilities
+                .list
+                .iter()
+                .any(|vuln| vuln.patched().is_some());
+
+        if !filter_features && !self.exclude_dev && !check_patched {
+            return;
+        }
+
+        let metadata = match metadata.map_or_else(|| Metadata::load(lockfile_path), Ok) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                strict::report(
+                    self.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!(
+                        "couldn't resolve dependency kinds, crate features and requirements: {}",
+                        display_err_with_source(&err)
+                    ),
+                );
+                return;
+            }
+        };
+
+        if self.exclude_dev {
+            exclude_dev_only(&metadata, report);
+        }
+
+        if filter_features {
+            filter_by_features(&metadata, report);
+        }
+
+        // Patched versions are otherwise checked against Cargo's default
+        // (caret) requirements on the versions in use
+        if self.check_requirements {
+            set_requirements(&metadata, report);
+        }
+    }
+
+    /// Explain why the given advisory does or does not match the packages
+    /// in a textual `Cargo.lock` file
+    pub fn explain_lockfile(
+        &mut self,
+        lockfile_path: &Path,
+        advisory_id: &advisory::Id,
+    ) -> rustsec::Result<()> {
+        let lockfile = self.load_lockfile(lockfile_path).map_err(|e| {
+            Error::with_source(
+                ErrorKind::NotFound,
+                format!("Couldn't load {}", lockfile_path.display()),
+                e,
+            )
+        })?;
+
+        let advisory = self.database.get(advisory_id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                &format!("advisory {} not found in the database", advisory_id),
+            )
+        })?;
+
+        let packages: Vec<_> = lockfile
+            .packages
+            .iter()
+            .filter(|package| package.name == advisory.metadata.package)
+            .collect();
+
+        let ignored = self.report_settings.ignore.contains(advisory_id);
+        let informational_warning = advisory
+            .metadata
+            .informational
+            .as_ref()
+            .map_or(false, |kind| {
+                self.report_settings.informational_warnings.contains(kind)
+            });
+
+        self.presenter.print_explanation(
+            advisory,
+            &self.report_settings.query(),
+            &packages,
+            ignored,
+            informational_warning,
+        );
+
+        Ok(())
+    }
+
     #[cfg(feature = "binary-scanning")]
     /// Perform an audit of multiple binary files
     pub fn audit_binaries<P>(&mut self, binaries: &[P]) -> MultiFileReportSummmary
     where
         P: AsRef<Path>,
     {
+        self.audit_loaded_binaries(
+            binaries
+                .iter()
+                .map(|path| (path.as_ref().to_owned(), load_binaries(path.as_ref()))),
+        )
+    }
+
+    #[cfg(feature = "archive-scanning")]
+    /// Perform an audit of the binaries in the layers of container images
+    pub fn audit_images(&mut self, images: &[String]) -> MultiFileReportSummmary {
+        self.audit_loaded_binaries(images.iter().map(|image| {
+            let loaded = crate::container_image::load_image(image).map(|entries| {
+                if entries.is_empty() {
+                    status_warn!("no executables found in image {}", image);
+                }
+
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.path, entry.contents))
+                    .collect()
+            });
+
+            (PathBuf::from(image), loaded)
+        }))
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Perform an audit of binaries, loaded from each file (or image) in turn
+    fn audit_loaded_binaries(
+        &mut self,
+        loaded: impl Iterator<Item = (PathBuf, rustsec::Result<Vec<(PathBuf, Vec<u8>)>>)>,
+    ) -> MultiFileReportSummmary {
         let mut summary = MultiFileReportSummmary::default();
-        for path in binaries {
-            let result = self.audit_binary(path.as_ref());
-            match result {
-                Ok(report) => {
-                    if self.presenter.should_exit_with_failure(&report) {
-                        summary.vulnerabilities_found = true;
+        for (target, binaries) in loaded {
+            let binaries = match binaries {
+                Ok(binaries) => binaries,
+                Err(e) => {
+                    self.presenter
+                        .print_error(&target, &display_err_with_source(&e));
+                    summary.errors_encountered = true;
+                    continue;
+                }
+            };
+
+            for (path, data) in binaries {
+                match self.audit_binary(&path, &data) {
+                    Ok(report) => {
+                        if self.presenter.should_exit_with_failure(&report) {
+                            summary.vulnerabilities_found = true;
+                        }
+                    }
+                    Err(e) => {
+                        self.presenter
+                            .print_error(&path, &display_err_with_source(&e));
+                        summary.errors_encountered = true;
                     }
                 }
+            }
+        }
+
+        let self_advisories = self.self_advisories();
+
+        self.presenter.print_self_report(self_advisories.as_slice());
+
+        if self
+            .presenter
+            .should_exit_with_failure_due_to_self(&self.self_advisories())
+        {
+            summary.errors_encountered = true;
+        }
+        summary
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Perform an audit of all of the executables under the given directories
+    /// (loading them in parallel), presenting one aggregated report grouped by
+    /// binary
+    pub fn audit_binary_tree<P>(&mut self, roots: &[P]) -> MultiFileReportSummmary
+    where
+        P: AsRef<Path>,
+    {
+        use crate::binary_tree::{discover, load_parallel, TreeReport};
+
+        let mut summary = MultiFileReportSummmary::default();
+        let mut files = vec![];
+
+        for root in roots {
+            match discover(root.as_ref()) {
+                Ok(mut found) => files.append(&mut found),
                 Err(e) => {
-                    status_err!("{}", display_err_with_source(&e));
+                    self.presenter
+                        .print_error(root.as_ref(), &display_err_with_source(&e));
                     summary.errors_encountered = true;
                 }
             }
         }
 
+        let mut tree_report = TreeReport::default();
+
+        load_parallel(&files, |path, loaded| match loaded {
+            Ok(binaries) => {
+                for (path, data) in binaries {
+                    let outcome = self.binary_report(&path, &data);
+                    match &outcome {
+                        Ok((lockfile, report)) => {
+                            if self.presenter.should_exit_with_failure(report) {
+                                summary.vulnerabilities_found = true;
+                            }
+
+                            if self.presenter.streams_reports() {
+                                self.presenter
+                                    .print_report(report, lockfile, Some(path.as_path()));
+                            }
+                        }
+                        Err(e) => {
+                            summary.errors_encountered = true;
+
+                            if self.presenter.streams_reports() {
+                                self.presenter
+                                    .print_error(&path, &display_err_with_source(e));
+                            }
+                        }
+                    }
+                    tree_report.add(&path, outcome);
+                }
+            }
+            Err(e) => {
+                summary.errors_encountered = true;
+
+                if self.presenter.streams_reports() {
+                    self.presenter
+                        .print_error(path, &display_err_with_source(&e));
+                }
+                tree_report.add(path, Err(e));
+            }
+        });
+
+        if tree_report.binaries.is_empty() {
+            status_warn!("no executables found");
+        }
+
+        self.presenter.print_tree_report(&tree_report);
+
         let self_advisories = self.self_advisories();
 
         self.presenter.print_self_report(self_advisories.as_slice());
 
         if self
             .presenter
-            .should_exit_with_failure_due_to_self(&self.self_advisories())
+            .should_exit_with_failure_due_to_self(&self_advisories)
+        {
+            summary.errors_encountered = true;
+        }
+        summary
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Perform an audit of per-platform builds of the same artifacts,
+    /// presenting a single report for each logical artifact
+    pub fn audit_binary_groups<P>(&mut self, binaries: &[P]) -> MultiFileReportSummmary
+    where
+        P: AsRef<Path>,
+    {
+        use crate::artifact_group::{artifact_and_platform, ArtifactGroup};
+        use std::collections::BTreeMap;
+
+        let mut summary = MultiFileReportSummmary::default();
+        let mut groups = BTreeMap::new();
+
+        for path in binaries {
+            let binaries = match load_binaries(path.as_ref()) {
+                Ok(binaries) => binaries,
+                Err(e) => {
+                    self.presenter
+                        .print_error(path.as_ref(), &display_err_with_source(&e));
+                    summary.errors_encountered = true;
+                    continue;
+                }
+            };
+
+            for (path, data) in binaries {
+                match self.binary_report(&path, &data) {
+                    Ok((_lockfile, report)) => {
+                        if self.presenter.should_exit_with_failure(&report) {
+                            summary.vulnerabilities_found = true;
+                        }
+
+                        let (artifact, platform) = artifact_and_platform(&path);
+                        groups
+                            .entry(artifact.clone())
+                            .or_insert_with(|| ArtifactGroup::new(artifact))
+                            .add(platform, report);
+                    }
+                    Err(e) => {
+                        self.presenter
+                            .print_error(&path, &display_err_with_source(&e));
+                        summary.errors_encountered = true;
+                    }
+                }
+            }
+        }
+
+        let reports: Vec<_> = groups
+            .into_values()
+            .map(ArtifactGroup::into_report)
+            .collect();
+        self.presenter.print_artifact_reports(&reports);
+
+        let self_advisories = self.self_advisories();
+
+        self.presenter.print_self_report(self_advisories.as_slice());
+
+        if self
+            .presenter
+            .should_exit_with_failure_due_to_self(&self_advisories)
         {
             summary.errors_encountered = true;
         }
@@ -229,13 +812,66 @@ impl Auditor {
 
     #[cfg(feature = "binary-scanning")]
     /// Perform an audit of a binary file with dependency data embedded by `cargo auditable`
-    fn audit_binary(&mut self, binary_path: &Path) -> rustsec::Result<rustsec::Report> {
+    fn audit_binary(
+        &mut self,
+        binary_path: &Path,
+        data: &[u8],
+    ) -> rustsec::Result<rustsec::Report> {
+        let (lockfile, report) = self.binary_report(binary_path, data)?;
+        self.presenter
+            .print_report(&report, &lockfile, Some(binary_path));
+        Ok(report)
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Generate a report for a binary file without presenting it, returning
+    /// the dependencies recovered from the binary along with the report
+    fn binary_report(
+        &mut self,
+        binary_path: &Path,
+        data: &[u8],
+    ) -> rustsec::Result<(Lockfile, rustsec::Report)> {
+        // The reports for each architecture of a universal (fat) Mach-O
+        // binary are merged
+        crate::binary_fat::map_slices(
+            binary_path,
+            data,
+            |slice_path, slice_data| self.single_binary_report(binary_path, slice_path, slice_data),
+            |(merged_lockfile, merged_report), (lockfile, report)| {
+                crate::binary_deps::merge_lockfiles(merged_lockfile, lockfile);
+                merge_reports(merged_report, report);
+            },
+        )
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Generate a report for a binary for a single architecture, named
+    /// `slice_path` if it's part of a universal binary
+    fn single_binary_report(
+        &mut self,
+        binary_path: &Path,
+        slice_path: &Path,
+        data: &[u8],
+    ) -> rustsec::Result<(Lockfile, rustsec::Report)> {
         use crate::binary_deps::BinaryReport::*;
-        let (binary_type, report) = crate::binary_deps::load_deps_from_binary(binary_path)?;
-        self.presenter.binary_scan_report(&report, binary_path);
+        let (binary_type, report, debug_info) =
+            crate::binary_deps::load_deps_with_debug_info(slice_path, data, || {
+                self.load_debug_info(binary_path, data)
+            })?;
+
+        self.presenter.binary_scan_report(&report, slice_path);
         match report {
             Complete(lockfile) | Incomplete(lockfile) => {
-                self.audit(&lockfile, Some(binary_path), Some(binary_type))
+                let binary_arch = crate::binary_format::detect_arch(data);
+                let mut report = self
+                    .build_report(&lockfile, Some(binary_type), binary_arch)
+                    .unwrap_or_else(Failure::exit);
+                self.add_toolchain_vulnerabilities(data, binary_type, binary_arch, &mut report);
+                if self.filter_symbols {
+                    self.filter_by_symbols(binary_path, data, debug_info, &mut report)?;
+                }
+                apply_package_overrides(&self.package_overrides, &mut report);
+                Ok((lockfile, report))
             }
             None => Err(Error::new(
                 ErrorKind::Parse,
@@ -244,38 +880,302 @@ impl Auditor {
         }
     }
 
-    /// The part of the auditing process that is shared between auditing lockfiles and binary files
-    fn audit(
+    #[cfg(feature = "binary-scanning")]
+    /// Add vulnerabilities in the standard library of the toolchain the
+    /// binary was built with, when its version is recorded in the binary
+    fn add_toolchain_vulnerabilities(
+        &self,
+        data: &[u8],
+        binary_type: BinaryFormat,
+        binary_arch: Option<rustsec::platforms::target::Arch>,
+        report: &mut rustsec::Report,
+    ) {
+        use crate::binary_toolchain::{rustc_version, std_package};
+        use crate::binary_type_filter::filter_report_by_binary_type;
+
+        let package = match rustc_version(data) {
+            Some(version) => std_package(version),
+            None => return,
+        };
+
+        let query = self
+            .report_settings
+            .query()
+            .collection(rustsec::Collection::Rust)
+            .package(&package);
+
+        let vulnerabilities: Vec<_> = self
+            .database
+            .query(&query)
+            .into_iter()
+            .filter(|advisory| !self.report_settings.ignore.contains(advisory.id()))
+            .map(|advisory| {
+                let mut vulnerability = rustsec::Vulnerability::new(advisory, &package);
+                vulnerability.severity_override = self
+                    .report_settings
+                    .severity_override(advisory.id())
+                    .cloned();
+                vulnerability
+            })
+            .collect();
+
+        if vulnerabilities.is_empty() {
+            return;
+        }
+
+        let vulns = &mut report.vulnerabilities;
+        vulns.list.extend(vulnerabilities);
+        vulns.count = vulns.list.len();
+        vulns.found = true;
+
+        filter_report_by_binary_type(&binary_type, binary_arch, report);
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Load the separate debug info of a binary, if it can be found
+    fn load_debug_info(&self, binary_path: &Path, data: &[u8]) -> Option<Vec<u8>> {
+        let path = crate::binary_debug_info::find(binary_path, data, &self.debug_info)?;
+
+        match std::fs::read(&path) {
+            Ok(debug_info) => Some(debug_info),
+            Err(e) => {
+                strict::report(
+                    self.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!("couldn't read debug info {}: {}", path.display(), e),
+                );
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Downgrade vulnerabilities whose affected functions weren't compiled
+    /// into the binary, looking symbols up in its separate debug info if the
+    /// binary is stripped
+    fn filter_by_symbols(
+        &self,
+        binary_path: &Path,
+        data: &[u8],
+        debug_info: Option<Vec<u8>>,
+        report: &mut rustsec::Report,
+    ) -> rustsec::Result<()> {
+        use crate::binary_symbols::{filter_report_by_symbols, Symbols};
+
+        let symbols = match Symbols::load(data)? {
+            Some(symbols) => Some(symbols),
+            None => match debug_info.or_else(|| self.load_debug_info(binary_path, data)) {
+                Some(debug_info) => Symbols::load(&debug_info)?,
+                None => None,
+            },
+        };
+
+        match symbols {
+            Some(symbols) => filter_report_by_symbols(&symbols, report),
+            None => strict::report(
+                self.strict,
+                false,
+                Anomaly::SkippedChecks,
+                format_args!(
+                    "no symbols found in {}, so affected functions can't be checked",
+                    binary_path.display()
+                ),
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Generate a report for the given lockfile without presenting it.
+    ///
+    /// The advisory database and crates.io index loaded by this auditor are
+    /// reused, which makes this suitable for auditing many lockfiles in a
+    /// long-running process. Conditions which would otherwise end the
+    /// process (e.g. anomalies in strict mode) are returned as failures.
+    pub fn report(&mut self, lockfile: &Lockfile) -> Result<rustsec::Report, Failure> {
+        let mut report = self.build_report(lockfile, None, None)?;
+        apply_package_overrides(&self.package_overrides, &mut report);
+        Ok(report)
+    }
+
+    /// Generate a report, applying binary-specific filtering and yanked crate checks
+    fn build_report(
         &mut self,
         lockfile: &Lockfile,
-        path: Option<&Path>,
         #[allow(unused_variables)] // May be unused when the "binary-scanning" feature is disabled
         binary_format: Option<BinaryFormat>,
-    ) -> rustsec::Result<rustsec::Report> {
+        #[allow(unused_variables)] binary_arch: Option<rustsec::platforms::target::Arch>,
+    ) -> Result<rustsec::Report, Failure> {
+        if let Some(client) = &self.osv {
+            // Advisories for one lockfile are kept for the following ones,
+            // so the database is only extended rather than copied
+            if let Err(err) = client
+                .query_lockfile(lockfile)
+                .and_then(|advisories| self.database.extend(advisories))
+            {
+                let message = format!("couldn't query osv.dev: {}", display_err_with_source(&err));
+
+                // There are no other advisories to audit against
+                if self.osv_only {
+                    return Err(Failure {
+                        exit_code: 1,
+                        message,
+                    });
+                }
+
+                strict::check(
+                    self.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!("{}", message),
+                )?;
+            }
+        }
+
         let mut report = rustsec::Report::generate(&self.database, lockfile, &self.report_settings);
 
+        // Enrichment can add CVSS scores, so it happens before anything else
+        // which depends on the severity of vulnerabilities
+        if let Some(nvd) = &self.nvd {
+            for err in nvd.enrich_report(&mut report) {
+                strict::check(
+                    self.strict,
+                    false,
+                    Anomaly::IncompleteData,
+                    format_args!(
+                        "couldn't look up advisory in NVD: {}",
+                        display_err_with_source(&err)
+                    ),
+                )?;
+            }
+        }
+
+        // Dependencies recovered from binaries never have checksums
+        if self.strict && binary_format.is_none() {
+            check_checksums(lockfile)?;
+        }
+
         #[cfg(feature = "binary-scanning")]
         if let Some(format) = binary_format {
             use crate::binary_type_filter::filter_report_by_binary_type;
-            filter_report_by_binary_type(&format, &mut report);
+            filter_report_by_binary_type(&format, binary_arch, &mut report);
         }
 
         // Warn for yanked crates
-        let mut yanked = self.check_for_yanked_crates(lockfile);
-        if !yanked.is_empty() {
-            report
-                .warnings
-                .entry(WarningKind::Yanked)
-                .or_default()
-                .append(&mut yanked);
+        if self.check_yanked {
+            let mut yanked = self.check_for_yanked_crates(lockfile)?;
+            if !yanked.is_empty() {
+                report
+                    .warnings
+                    .entry(WarningKind::Yanked)
+                    .or_default()
+                    .append(&mut yanked);
+            }
+        }
+
+        if self.release_info {
+            self.add_release_info(&mut report)?;
         }
 
-        self.presenter.print_report(&report, lockfile, path);
+        if self.suggest_upgrades {
+            self.add_upgrade_suggestions(&mut report)?;
+        }
 
         Ok(report)
     }
 
-    fn check_for_yanked_crates(&mut self, lockfile: &Lockfile) -> Vec<Warning> {
+    /// Add information about how far behind the latest release each affected package is
+    fn add_release_info(&mut self, report: &mut rustsec::Report) -> Result<(), Failure> {
+        let index = match &mut self.registry_index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        // Only crates.io packages are in the index
+        let (packages, releases): (Vec<_>, Vec<_>) = report
+            .vulnerabilities
+            .list
+            .iter_mut()
+            .map(|vuln| (&vuln.package, &mut vuln.release))
+            .chain(
+                report
+                    .warnings
+                    .values_mut()
+                    .flatten()
+                    .map(|warning| (&warning.package, &mut warning.release)),
+            )
+            .filter(|(package, _)| {
+                package
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| source.is_default_registry())
+            })
+            .unzip();
+
+        let results = index.release_info(packages.iter().copied());
+
+        // Release dates are only looked up for the packages found in the index
+        let found: Vec<_> = packages
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(package, _)| (&package.name, &package.version))
+            .collect();
+        let mut dates = match &self.crates_io {
+            Some(crates_io) => crates_io.release_dates(found).into_iter(),
+            None => Vec::new().into_iter(),
+        };
+
+        for ((package, release), result) in packages.iter().zip(releases).zip(results) {
+            match result {
+                Ok(mut info) => {
+                    info.released = dates.next().and_then(Result::ok);
+                    *release = Some(info);
+                }
+                Err(e) => strict::check(
+                    self.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!(
+                        "couldn't look up releases of {}: {}",
+                        package.name,
+                        display_err_with_source(&e)
+                    ),
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Suggest which dependencies to upgrade to resolve each vulnerability
+    fn add_upgrade_suggestions(&mut self, report: &mut rustsec::Report) -> Result<(), Failure> {
+        let index = match &mut self.registry_index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        for vuln in &mut report.vulnerabilities.list {
+            match index.suggest_upgrades(vuln) {
+                Ok(suggestions) => vuln.suggestions = suggestions,
+                Err(e) => strict::check(
+                    self.strict,
+                    false,
+                    Anomaly::SkippedChecks,
+                    format_args!(
+                        "couldn't suggest upgrades for {}: {}",
+                        vuln.package.name,
+                        display_err_with_source(&e)
+                    ),
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_for_yanked_crates(&mut self, lockfile: &Lockfile) -> Result<Vec<Warning>, Failure> {
         let mut result = Vec::new();
         if let Some(index) = &mut self.registry_index {
             let pkgs_to_check: Vec<_> = lockfile
@@ -295,6 +1195,15 @@ impl Auditor {
                         let warning = Warning::new(WarningKind::Yanked, pkg, None, None, None);
                         result.push(warning);
                     }
+                    Err(e) if self.strict => strict::check(
+                        true,
+                        false,
+                        Anomaly::SkippedChecks,
+                        format_args!(
+                            "couldn't check if the package is yanked: {}",
+                            display_err_with_source(&e)
+                        ),
+                    )?,
                     Err(e) => status_err!(
                         "couldn't check if the package is yanked: {}",
                         display_err_with_source(&e)
@@ -302,35 +1211,58 @@ impl Auditor {
                 }
             }
         }
-        result
+        Ok(result)
     }
 
     /// Load the lockfile to be audited
     fn load_lockfile(&self, lockfile_path: &Path) -> rustsec::Result<Lockfile> {
         if lockfile_path == Path::new("-") {
-            // Read Cargo.lock from STDIN
-            let mut lockfile_toml = String::new();
-            io::stdin().read_to_string(&mut lockfile_toml)?;
-            Ok(lockfile_toml.parse()?)
+            // Read Cargo.lock (or another dependency list) from STDIN
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            self.stdin_format.parse(&input)
         } else {
             Ok(Lockfile::load(lockfile_path)?)
         }
     }
 
-    /// Query the database for advisories about `cargo-audit` or `rustsec` itself
+    /// Query the database for advisories about `cargo-audit` itself: its own
+    /// dependency list when it's embedded in the executable (see
+    /// [`crate::binary_deps::current_exe_deps`]), and otherwise only the
+    /// `cargo-audit` and `rustsec` crates
     fn self_advisories(&self) -> Vec<rustsec::Advisory> {
-        let mut results = vec![];
+        let mut packages: Vec<(rustsec::package::Name, rustsec::Version)> = vec![
+            (
+                "cargo-audit".parse().unwrap(),
+                crate::VERSION.parse().unwrap(),
+            ),
+            (
+                "rustsec".parse().unwrap(),
+                rustsec::VERSION.parse().unwrap(),
+            ),
+        ];
 
-        for (package_name, package_version) in [
-            ("cargo-audit", crate::VERSION),
-            ("rustsec", rustsec::VERSION),
-        ] {
+        #[cfg(feature = "binary-scanning")]
+        packages.extend(
+            crate::binary_deps::current_exe_deps(self.self_deps_cache.as_deref())
+                .iter()
+                .map(|package| (package.name.clone(), package.version.clone())),
+        );
+
+        let mut results: Vec<rustsec::Advisory> = vec![];
+
+        for (package_name, package_version) in packages {
             let query = rustsec::database::Query::crate_scope()
-                .package_name(package_name.parse().unwrap())
-                .package_version(package_version.parse().unwrap());
+                .package_name(package_name)
+                .package_version(package_version);
 
             for advisory in self.database.query(&query) {
-                results.push(advisory.clone());
+                if !results
+                    .iter()
+                    .any(|a| a.metadata.id == advisory.metadata.id)
+                {
+                    results.push(advisory.clone());
+                }
             }
         }
 
@@ -349,6 +1281,466 @@ impl Auditor {
     }
 }
 
+/// Fetch GitHub Security Advisories, authenticating with the token in
+/// `GITHUB_TOKEN`
+fn fetch_ghsa(
+    config: &AuditConfig,
+    transport: Option<&Arc<dyn Transport>>,
+) -> rustsec::Result<Vec<rustsec::Advisory>> {
+    // Recorded responses can be replayed without a token
+    let token = match std::env::var("GITHUB_TOKEN") {
+        Ok(token) => token,
+        Err(_) if transport.is_some() && !config.database.vcr_record => String::new(),
+        Err(_) => return Err(Error::new(ErrorKind::NotFound, &"GITHUB_TOKEN is not set")),
+    };
+
+    if !config.output.is_quiet() {
+        status_ok!("Fetching", "GitHub Security Advisories");
+    }
+
+    let client = rustsec::ghsa::GhsaClient::new(token)?;
+
+    match transport {
+        Some(transport) => client.transport(transport.clone()).fetch_advisories(),
+        None => client.fetch_advisories(),
+    }
+}
+
+/// Get the transport for recording or replaying API responses, if enabled.
+///
+/// Errors are reported to the user, and exit the process.
+fn vcr_transport(config: &AuditConfig) -> Option<Arc<dyn Transport>> {
+    let dir = config.database.vcr.as_ref()?;
+
+    if !config.database.vcr_record {
+        return Some(Arc::new(Vcr::replay(dir)));
+    }
+
+    match HttpTransport::new(RECORD_TIMEOUT) {
+        Ok(transport) => Some(Arc::new(Vcr::record(dir, transport))),
+        Err(e) => {
+            status_err!(
+                "couldn't record API responses: {}",
+                display_err_with_source(&e)
+            );
+            exit(1);
+        }
+    }
+}
+
+/// Import advisories about native libraries wrapped by `-sys` crates from a
+/// directory of OSV records
+fn import_native(config: &AuditConfig, dir: &Path) -> rustsec::Result<Vec<rustsec::Advisory>> {
+    let mut mapping = rustsec::osv::SysMapping::builtin();
+
+    if let Some(path) = &config.database.sys_mapping {
+        mapping.extend(rustsec::osv::SysMapping::load_file(path)?);
+    }
+
+    rustsec::osv::import_native_dir(dir, &mapping)
+}
+
+/// Load the local copy of the advisory database without fetching it
+pub(crate) fn load_local_database(
+    config: &AuditConfig,
+    advisory_db_path: &Path,
+) -> rustsec::Result<rustsec::Database> {
+    if config.database.cache_enabled() {
+        // The database may not be a git checkout, in which case
+        // there is no commit to key the cache on
+        rustsec::repository::git::Repository::open(advisory_db_path)
+            .and_then(|repo| {
+                rustsec::Database::load_from_repo_cached(&repo, &cache_path(advisory_db_path))
+            })
+            .or_else(|_| rustsec::Database::open(advisory_db_path))
+    } else {
+        rustsec::Database::open(advisory_db_path)
+    }
+}
+
+/// Describe how old the given database is, for warnings about using it
+/// when it couldn't be updated
+fn describe_age(database: &rustsec::Database) -> String {
+    let commit = match database.latest_commit() {
+        Some(commit) => commit,
+        None => return String::new(),
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default();
+    let days = (now - commit.timestamp.unix_timestamp()).max(0) / (24 * 60 * 60);
+
+    format!(
+        " (last updated {}, {} {} ago)",
+        commit.timestamp.date(),
+        days,
+        if days == 1 { "day" } else { "days" }
+    )
+}
+
+/// Can the local copy of the database be used when fetching it failed with
+/// the given error? This is only the case for errors which may be transient,
+/// such as network errors, but not e.g. for invalid URLs or paths.
+fn can_fall_back(err: &Error) -> bool {
+    err.kind() == ErrorKind::RetriesExhausted || rustsec::network::is_transient(err.kind())
+}
+
+/// Get a database to use when it can neither be fetched nor loaded from the
+/// local copy: the embedded snapshot if available. Otherwise the error is
+/// reported to the user, and the process exits.
+#[cfg(feature = "embedded-db")]
+fn load_fallback_database(config: &AuditConfig, message: &str) -> (rustsec::Database, String) {
+    strict::report(
+        config.output.strict,
+        false,
+        Anomaly::StaleDatabase,
+        format_args!("{}", message),
+    );
+    load_embedded_database(config)
+}
+
+/// Get a database to use when it can neither be fetched nor loaded from the
+/// local copy: the embedded snapshot if available. Otherwise the error is
+/// reported to the user, and the process exits.
+#[cfg(not(feature = "embedded-db"))]
+fn load_fallback_database(_config: &AuditConfig, message: &str) -> (rustsec::Database, String) {
+    status_err!("{}", message);
+    exit(1);
+}
+
+/// Load the advisory database according to the given configuration,
+/// fetching it first if enabled.
+///
+/// Errors are reported to the user, and exit the process.
+pub fn load_database(config: &AuditConfig) -> rustsec::Database {
+    let advisory_db_url = config
+        .database
+        .url
+        .as_ref()
+        .map(AsRef::as_ref)
+        .unwrap_or(rustsec::repository::git::DEFAULT_URL);
+
+    let advisory_db_path = config
+        .database
+        .path
+        .as_ref()
+        .cloned()
+        .unwrap_or_else(rustsec::repository::git::Repository::default_path);
+
+    let mut loaded_from = advisory_db_path.display().to_string();
+
+    let database = if config.database.embedded {
+        let (database, source) = load_embedded_database(config);
+        loaded_from = source;
+        database
+    } else if config.database.fetch {
+        if !config.output.is_quiet() {
+            status_ok!("Fetching", "advisory database from `{}`", advisory_db_url);
+        }
+
+        let progress = progress_bar(config, "Fetching");
+        let fetch = |lock_timeout| {
+            // Staleness is checked below, so that it's also checked for the
+            // local copy used when fetching fails
+            let fetch_repo = if config.database.shallow {
+                rustsec::repository::git::Repository::fetch_shallow::<&PathBuf>
+            } else {
+                rustsec::repository::git::Repository::fetch_with_options::<&PathBuf>
+            };
+
+            fetch_repo(
+                advisory_db_url,
+                &advisory_db_path,
+                false,
+                lock_timeout,
+                config.network_settings(),
+                progress.clone(),
+            )
+        };
+
+        let mut result = fetch(Duration::from_secs(0));
+        // If the directory is locked, print a message and wait for it to become unlocked.
+        // If we don't print the message, `cargo audit` would just hang with no explanation.
+        if let Err(e) = &result {
+            if e.kind() == ErrorKind::LockTimeout {
+                status_warn!("directory {} is locked, waiting for up to {} seconds for it to become available", advisory_db_path.display(), DEFAULT_LOCK_TIMEOUT.as_secs());
+                result = fetch(DEFAULT_LOCK_TIMEOUT);
+            }
+        }
+
+        match result {
+            Ok(advisory_db_repo) => {
+                let result = if config.database.cache_enabled() {
+                    rustsec::Database::load_from_repo_cached(
+                        &advisory_db_repo,
+                        &cache_path(&advisory_db_path),
+                    )
+                } else {
+                    rustsec::Database::load_from_repo(&advisory_db_repo)
+                };
+
+                result.unwrap_or_else(|e| {
+                    status_err!(
+                        "error loading advisory database: {}",
+                        display_err_with_source(&e)
+                    );
+                    exit(1);
+                })
+            }
+            Err(e) => {
+                let message = format!(
+                    "couldn't fetch advisory database: {}",
+                    display_err_with_source(&e)
+                );
+
+                if config.database.require_fresh || !can_fall_back(&e) {
+                    status_err!("{}", message);
+                    exit(1);
+                }
+
+                // Carry on with the local copy on transient network errors,
+                // or failing that the embedded snapshot
+                match load_local_database(config, &advisory_db_path) {
+                    Ok(database) => {
+                        strict::report(
+                            config.output.strict,
+                            false,
+                            Anomaly::StaleDatabase,
+                            format_args!(
+                                "{}; using the local copy instead{}",
+                                message,
+                                describe_age(&database)
+                            ),
+                        );
+                        database
+                    }
+                    Err(_) => {
+                        let (database, source) = load_fallback_database(config, &message);
+                        loaded_from = source;
+                        database
+                    }
+                }
+            }
+        }
+    } else {
+        load_local_database(config, &advisory_db_path).unwrap_or_else(|e| {
+            status_err!(
+                "error loading advisory database: {}",
+                display_err_with_source(&e)
+            );
+            exit(1);
+        })
+    };
+
+    if let Some(commit) = database.latest_commit().filter(|commit| !commit.is_fresh()) {
+        let message = format!(
+            "advisory database is stale (last commit: {})",
+            commit.timestamp.date()
+        );
+
+        // Stale databases are reported with a dedicated exit code in strict
+        // mode. Otherwise only fetched databases (or the local copy used when
+        // fetching failed) are required to be fresh.
+        if config.output.strict {
+            strict::report(
+                true,
+                false,
+                Anomaly::StaleDatabase,
+                format_args!("{}", message),
+            );
+        } else if config.database.fetch && !config.database.embedded && !config.database.stale {
+            status_err!("{} (use --stale to audit with it anyway)", message);
+            exit(1);
+        }
+    }
+
+    if !config.output.is_quiet() {
+        status_ok!(
+            "Loaded",
+            "{} security advisories (from {})",
+            database.iter().count(),
+            loaded_from
+        );
+    }
+
+    database
+}
+
+/// Load the advisory database snapshot embedded in this binary, returning
+/// the database along with a description of where it came from
+#[cfg(feature = "embedded-db")]
+fn load_embedded_database(config: &AuditConfig) -> (rustsec::Database, String) {
+    use crate::embedded_db;
+
+    if embedded_db::is_stale() && (!config.database.stale || config.output.strict) {
+        strict::report(
+            config.output.strict,
+            false,
+            Anomaly::StaleDatabase,
+            format_args!(
+                "embedded advisory database is {} days old (from {}), recent advisories may be missing",
+                embedded_db::age_days(),
+                embedded_db::DATE
+            ),
+        );
+    }
+
+    let database = embedded_db::load().unwrap_or_else(|e| {
+        status_err!(
+            "error loading embedded advisory database: {}",
+            display_err_with_source(&e)
+        );
+        exit(1);
+    });
+
+    let source = format!(
+        "embedded snapshot of commit {} from {}",
+        embedded_db::COMMIT,
+        embedded_db::DATE
+    );
+
+    (database, source)
+}
+
+/// Load the advisory database snapshot embedded in this binary
+#[cfg(not(feature = "embedded-db"))]
+fn load_embedded_database(_config: &AuditConfig) -> (rustsec::Database, String) {
+    status_err!(
+        "this build of cargo-audit has no embedded advisory database (enable the `embedded-db` feature)"
+    );
+    exit(1);
+}
+
+/// Fail in strict mode if registry packages in the lockfile have no checksum
+fn check_checksums(lockfile: &Lockfile) -> Result<(), Failure> {
+    let missing: Vec<_> = lockfile
+        .packages
+        .iter()
+        .filter(|package| {
+            package.checksum.is_none()
+                && package
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| source.is_registry())
+        })
+        .map(|package| format!("{} {}", package.name, package.version))
+        .collect();
+
+    if !missing.is_empty() {
+        strict::check(
+            true,
+            false,
+            Anomaly::IncompleteData,
+            format_args!("missing checksums for {}", missing.join(", ")),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Location of the parsed advisory database snapshot cache for the given database path
+fn cache_path(advisory_db_path: &Path) -> PathBuf {
+    let mut path = advisory_db_path.as_os_str().to_owned();
+    path.push(".cache");
+    path.into()
+}
+
+/// Progress bar for fetching the advisory database or the crates.io index,
+/// unless output is quiet (or STDERR isn't a terminal)
+fn progress_bar(config: &AuditConfig, label: &'static str) -> Option<Arc<dyn Progress>> {
+    if config.output.is_quiet() {
+        return None;
+    }
+
+    ProgressBar::new(label).map(|bar| bar as Arc<dyn Progress>)
+}
+
+/// Location of the cached dependencies of `cargo-audit` itself for the given
+/// database path
+#[cfg(feature = "binary-scanning")]
+fn self_deps_cache_path(advisory_db_path: &Path) -> PathBuf {
+    let mut path = advisory_db_path.as_os_str().to_owned();
+    path.push(".self-deps.json");
+    path.into()
+}
+
+/// Location of the NVD response cache for the given database path
+fn nvd_cache_path(advisory_db_path: &Path) -> PathBuf {
+    let mut path = advisory_db_path.as_os_str().to_owned();
+    path.push(".nvd");
+    path.into()
+}
+
+/// Load the binaries to audit from a file: the file itself, or the
+/// executables in it if it's an archive
+#[cfg(feature = "binary-scanning")]
+pub(crate) fn load_binaries(path: &Path) -> rustsec::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut data = vec![];
+    std::fs::File::open(path)?
+        .take(MAX_BINARY_SIZE + 1)
+        .read_to_end(&mut data)?;
+
+    if data.len() as u64 > MAX_BINARY_SIZE {
+        return Err(Error::new(
+            ErrorKind::BadParam,
+            &format!(
+                "{} is larger than {} bytes",
+                path.display(),
+                MAX_BINARY_SIZE
+            ),
+        ));
+    }
+
+    #[cfg(feature = "archive-scanning")]
+    if let Some(entries) = crate::binary_archive::executables(path, &data)? {
+        if entries.is_empty() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                &format!("no executables found in archive {}", path.display()),
+            ));
+        }
+
+        return Ok(entries
+            .into_iter()
+            .map(|entry| (entry.path, entry.contents))
+            .collect());
+    }
+
+    Ok(vec![(path.to_owned(), data)])
+}
+
+/// Add the findings of a report which aren't in the merged report yet
+#[cfg(feature = "binary-scanning")]
+fn merge_reports(merged: &mut rustsec::Report, report: rustsec::Report) {
+    let vulns = &mut merged.vulnerabilities;
+    for vuln in report.vulnerabilities.list {
+        let duplicate = vulns.list.iter().any(|existing| {
+            existing.advisory.id == vuln.advisory.id && existing.package == vuln.package
+        });
+        if !duplicate {
+            vulns.list.push(vuln);
+        }
+    }
+    vulns.count = vulns.list.len();
+    vulns.found = !vulns.list.is_empty();
+
+    for (kind, warnings) in report.warnings {
+        let merged_warnings = merged.warnings.entry(kind).or_default();
+        for warning in warnings {
+            let duplicate = merged_warnings.iter().any(|existing| {
+                existing.package == warning.package
+                    && existing.advisory.as_ref().map(|metadata| &metadata.id)
+                        == warning.advisory.as_ref().map(|metadata| &metadata.id)
+            });
+            if !duplicate {
+                merged_warnings.push(warning);
+            }
+        }
+    }
+}
+
 /// Summary of the report over multiple scanned files
 #[derive(Clone, Copy, Debug, Default)]
 pub struct MultiFileReportSummmary {
@@ -357,3 +1749,170 @@ pub struct MultiFileReportSummmary {
     /// Whether any errors were encountered during scanning
     pub errors_encountered: bool,
 }
+
+/// Remove findings for packages which are only used as dev-dependencies
+fn exclude_dev_only(metadata: &Metadata, report: &mut rustsec::Report) {
+    let vulns = &mut report.vulnerabilities;
+    vulns
+        .list
+        .retain(|vuln| !metadata.is_dev_only(&vuln.package));
+    vulns.count = vulns.list.len();
+    vulns.found = !vulns.list.is_empty();
+
+    for warnings in report.warnings.values_mut() {
+        warnings.retain(|warning| !metadata.is_dev_only(&warning.package));
+    }
+    report.warnings.retain(|_, warnings| !warnings.is_empty());
+}
+
+/// Check the patched versions of vulnerable packages against the version
+/// requirements the packages depending on them have on them
+fn set_requirements(metadata: &Metadata, report: &mut rustsec::Report) {
+    for vuln in &mut report.vulnerabilities.list {
+        let requirements = metadata.requirements(&vuln.package.name, &vuln.package.version);
+        if !requirements.is_empty() {
+            vuln.set_requirements(&requirements);
+        }
+    }
+}
+
+/// Remove warnings which are allowed for their package.
+///
+/// This runs once a report is otherwise complete, so it also covers the
+/// warnings added by later stages, e.g. `not-applicable` and `uncompiled`
+/// vulnerabilities. The `[output.policy]` only sees the warnings left over.
+fn apply_package_overrides(overrides: &PackagesConfig, report: &mut rustsec::Report) {
+    for (kind, warnings) in report.warnings.iter_mut() {
+        warnings.retain(|warning| !overrides.allows(&warning.package.name, *kind));
+    }
+
+    report.warnings.retain(|_, warnings| !warnings.is_empty());
+}
+
+/// Downgrade vulnerabilities in code gated by crate features which aren't
+/// enabled in the project to `not-applicable` warnings
+fn filter_by_features(metadata: &Metadata, report: &mut rustsec::Report) {
+    let vulns = &mut report.vulnerabilities;
+    let (applicable, not_applicable): (Vec<_>, Vec<_>) = vulns.list.drain(..).partition(|vuln| {
+        let features = vuln.affected_features();
+        features.is_empty()
+            || metadata.features(&vuln.package).map_or(true, |enabled| {
+                features.iter().any(|feature| enabled.contains(feature))
+            })
+    });
+
+    vulns.list = applicable;
+    vulns.count = vulns.list.len();
+    vulns.found = !vulns.list.is_empty();
+
+    if !not_applicable.is_empty() {
+        report
+            .warnings
+            .entry(WarningKind::NotApplicable)
+            .or_default()
+            .extend(not_applicable.into_iter().map(Warning::not_applicable));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OutputConfig;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "accepted"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "other"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    /// Report with `not-applicable` warnings about both packages, as added
+    /// after the report is generated
+    fn late_warnings() -> rustsec::Report {
+        let database = rustsec::Database::from_advisories(vec![]).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let mut report =
+            rustsec::Report::generate(&database, &lockfile, &report::Settings::default());
+
+        for package in &lockfile.packages {
+            let warning = Warning::new(WarningKind::NotApplicable, package, None, None, None);
+            report
+                .warnings
+                .entry(WarningKind::NotApplicable)
+                .or_default()
+                .push(warning);
+        }
+
+        report
+    }
+
+    fn overrides() -> PackagesConfig {
+        toml::from_str(
+            r#"
+            [[overrides]]
+            name = "accepted"
+            allow = ["not-applicable"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn package_overrides_remove_late_warnings() {
+        let mut report = late_warnings();
+        apply_package_overrides(&overrides(), &mut report);
+
+        let warnings = &report.warnings[&WarningKind::NotApplicable];
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].package.name.as_str(), "other");
+
+        let mut report = late_warnings();
+        apply_package_overrides(&PackagesConfig::default(), &mut report);
+        assert_eq!(report.warnings[&WarningKind::NotApplicable].len(), 2);
+    }
+
+    #[test]
+    fn policy_applies_after_package_overrides() {
+        // The policy denies the kind of warning only for the package which
+        // overrides allow it, so nothing is left to deny
+        let config: OutputConfig = toml::from_str(
+            r#"
+            [policy.packages.accepted.warnings]
+            not-applicable = "deny"
+            "#,
+        )
+        .unwrap();
+        let presenter = Presenter::new(&config);
+
+        let mut report = late_warnings();
+        assert!(presenter.should_exit_with_failure(&report));
+
+        apply_package_overrides(&overrides(), &mut report);
+        assert!(!presenter.should_exit_with_failure(&report));
+    }
+
+    #[test]
+    fn fall_back_on_transient_errors() {
+        for kind in [ErrorKind::Io, ErrorKind::Repo, ErrorKind::RetriesExhausted] {
+            assert!(can_fall_back(&Error::new(kind, &"connection reset")));
+        }
+    }
+
+    #[test]
+    fn no_fall_back_on_permanent_errors() {
+        for kind in [
+            ErrorKind::BadParam,
+            ErrorKind::Parse,
+            ErrorKind::LockTimeout,
+        ] {
+            assert!(!can_fall_back(&Error::new(kind, &"invalid URL")));
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! Extracts executables from archives in memory, so release artifacts can be
+//! audited as they're shipped: tarballs (optionally compressed with gzip, xz
+//! or zstd), zip files, and Debian and RPM packages.
+
+use std::{
+    io::{BufRead, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use rustsec::{Error, ErrorKind};
+
+use crate::binary_format::BinaryFormat;
+
+/// Maximum size of decompressed data and of each file extracted from archives
+pub const MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Maximum total size of the data decompressed and extracted from an archive,
+/// which is all held in memory at once
+const MAX_TOTAL_SIZE: u64 = 2 * MAX_SIZE;
+
+/// Limit on the total amount of data extracted from an archive (and the
+/// archives nested in it), so that archives full of large or highly
+/// compressible members can't exhaust memory
+pub struct Budget {
+    /// Number of bytes which can still be extracted
+    remaining: u64,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Budget {
+    /// Create the budget for extracting one archive
+    pub fn new() -> Self {
+        Self::with_limit(MAX_TOTAL_SIZE)
+    }
+
+    /// Create a budget with the given limit
+    fn with_limit(remaining: u64) -> Self {
+        Self { remaining }
+    }
+
+    /// Account for `size` bytes of extracted data
+    pub fn spend(&mut self, path: &Path, size: u64) -> rustsec::Result<()> {
+        self.remaining = self
+            .remaining
+            .checked_sub(size)
+            .ok_or_else(|| too_large(path))?;
+        Ok(())
+    }
+
+    /// Read all of the data from a reader, failing if it exceeds
+    /// [`MAX_SIZE`] or the remaining budget
+    pub fn read(&mut self, path: &Path, reader: impl Read) -> rustsec::Result<Vec<u8>> {
+        let limit = MAX_SIZE.min(self.remaining);
+        let mut contents = vec![];
+        reader
+            .take(limit + 1)
+            .read_to_end(&mut contents)
+            .map_err(|e| extract_error(path, e))?;
+
+        if contents.len() as u64 > limit {
+            return Err(too_large(path));
+        }
+
+        self.spend(path, contents.len() as u64)?;
+        Ok(contents)
+    }
+}
+
+/// Executable extracted from an archive
+pub struct ArchiveEntry {
+    /// Path of the executable, i.e. its path inside the archive appended to
+    /// the path of the archive
+    pub path: PathBuf,
+
+    /// Contents of the executable
+    pub contents: Vec<u8>,
+}
+
+/// Extract the executables from the archive at the given path. Returns
+/// `None` if the data isn't an archive in one of the supported formats.
+pub fn executables(path: &Path, data: &[u8]) -> rustsec::Result<Option<Vec<ArchiveEntry>>> {
+    let mut budget = Budget::new();
+
+    let files = if let Some(tar) = decompress(path, data, &mut budget)? {
+        if !is_tar(&tar) {
+            return Ok(None);
+        }
+        tar_files(path, &tar, &mut budget)?
+    } else if is_tar(data) {
+        tar_files(path, data, &mut budget)?
+    } else if data.starts_with(b"PK\x03\x04") {
+        zip_files(path, data, &mut budget)?
+    } else if data.starts_with(b"!<arch>\ndebian-binary") {
+        deb_files(path, data, &mut budget)?
+    } else if data.starts_with(&[0xed, 0xab, 0xee, 0xdb]) {
+        rpm_files(path, data, &mut budget)?
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        files
+            .into_iter()
+            .filter(|(_, contents)| is_executable(contents))
+            .map(|(inner, contents)| ArchiveEntry {
+                path: path.join(inner.trim_start_matches("./").trim_start_matches('/')),
+                contents,
+            })
+            .collect(),
+    ))
+}
+
+/// Is the data an executable (or library) in a format binaries can be
+/// scanned in?
+pub fn is_executable(data: &[u8]) -> bool {
+    BinaryFormat::detect(data) != BinaryFormat::Unknown
+}
+
+/// Decompress gzip, xz or zstd data. Returns `None` if the data isn't
+/// compressed in one of these formats.
+pub fn decompress(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Option<Vec<u8>>> {
+    if !is_compressed(data) {
+        return Ok(None);
+    }
+
+    budget.read(path, decoder(path, data)?).map(Some)
+}
+
+/// Does the data start with the magic number of gzip, xz or zstd?
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+        || data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00])
+        || data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+}
+
+/// Wrap a reader to decompress gzip, xz or zstd data as it's read, detected
+/// from the first bytes of the data. Other data is read as-is.
+pub fn decoder<'a, R: BufRead + 'a>(
+    path: &Path,
+    mut reader: R,
+) -> rustsec::Result<Box<dyn Read + 'a>> {
+    let start = reader.fill_buf().map_err(|e| extract_error(path, e))?;
+
+    Ok(if start.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::bufread::GzDecoder::new(reader))
+    } else if start.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Box::new(xz2::bufread::XzDecoder::new(reader))
+    } else if start.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(
+            zstd::stream::read::Decoder::with_buffer(reader).map_err(|e| extract_error(path, e))?,
+        )
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Is the data a tar archive?
+fn is_tar(data: &[u8]) -> bool {
+    data.get(257..262) == Some(&b"ustar"[..])
+}
+
+/// Extract the regular files from a tar archive
+pub fn tar_files(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(Cursor::new(data));
+    let mut files = vec![];
+
+    for entry in archive.entries().map_err(|e| extract_error(path, e))? {
+        let entry = entry.map_err(|e| extract_error(path, e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry
+            .path()
+            .map_err(|e| extract_error(path, e))?
+            .to_string_lossy()
+            .into_owned();
+        files.push((name, budget.read(path, entry)?));
+    }
+
+    Ok(files)
+}
+
+/// Extract the regular files from a zip archive
+fn zip_files(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Vec<(String, Vec<u8>)>> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(data)).map_err(|e| extract_error(path, e))?;
+    let mut files = vec![];
+
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|e| extract_error(path, e))?;
+        if !file.is_file() {
+            continue;
+        }
+
+        let name = file.name().to_owned();
+        files.push((name, budget.read(path, file)?));
+    }
+
+    Ok(files)
+}
+
+/// Extract the installed files from a Debian package: an `ar` archive with
+/// the files in a (compressed) `data.tar` member
+fn deb_files(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Vec<(String, Vec<u8>)>> {
+    const HEADER_SIZE: usize = 60;
+
+    let mut offset = b"!<arch>\n".len();
+
+    while offset + HEADER_SIZE <= data.len() {
+        let header = &data[offset..offset + HEADER_SIZE];
+        let name = String::from_utf8_lossy(&header[..16]);
+        let size: usize = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse().ok())
+            .ok_or_else(|| malformed(path, "invalid member header"))?;
+
+        let start = offset + HEADER_SIZE;
+        let end = start
+            .checked_add(size)
+            .ok_or_else(|| malformed(path, "invalid member header"))?;
+        let member = data
+            .get(start..end)
+            .ok_or_else(|| malformed(path, "truncated member"))?;
+
+        if name
+            .trim_end()
+            .trim_end_matches('/')
+            .starts_with("data.tar")
+        {
+            return match decompress(path, member, budget)? {
+                Some(tar) => tar_files(path, &tar, budget),
+                None => tar_files(path, member, budget),
+            };
+        }
+
+        // Members are aligned to even offsets
+        offset = end + size % 2;
+    }
+
+    Err(malformed(path, "no data.tar member"))
+}
+
+/// Extract the installed files from an RPM package: a lead and two headers,
+/// followed by a compressed `cpio` archive in the "new ASCII" format
+fn rpm_files(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Vec<(String, Vec<u8>)>> {
+    const LEAD_SIZE: usize = 96;
+
+    // The signature header is padded to a multiple of 8 bytes
+    let signature_end = LEAD_SIZE + rpm_header_size(path, data, LEAD_SIZE)?;
+    let header_start = (signature_end + 7) / 8 * 8;
+    let payload_start = header_start + rpm_header_size(path, data, header_start)?;
+
+    let payload = data
+        .get(payload_start..)
+        .ok_or_else(|| malformed(path, "truncated header"))?;
+
+    match decompress(path, payload, budget)? {
+        Some(cpio) => cpio_files(path, &cpio, budget),
+        None => cpio_files(path, payload, budget),
+    }
+}
+
+/// Get the size of the RPM header structure starting at the given offset
+fn rpm_header_size(path: &Path, data: &[u8], offset: usize) -> rustsec::Result<usize> {
+    let header = data
+        .get(offset..offset + 16)
+        .filter(|header| header.starts_with(&[0x8e, 0xad, 0xe8, 0x01]))
+        .ok_or_else(|| malformed(path, "invalid header"))?;
+
+    let index_entries = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+    let store_size = u32::from_be_bytes([header[12], header[13], header[14], header[15]]);
+
+    Ok(16 + index_entries as usize * 16 + store_size as usize)
+}
+
+/// Extract the regular files from a `cpio` archive in the "new ASCII" format
+fn cpio_files(
+    path: &Path,
+    data: &[u8],
+    budget: &mut Budget,
+) -> rustsec::Result<Vec<(String, Vec<u8>)>> {
+    const HEADER_SIZE: usize = 110;
+    const REGULAR_FILE: u32 = 0o100000;
+    const FILE_TYPE: u32 = 0o170000;
+
+    let mut files = vec![];
+    let mut offset = 0;
+
+    loop {
+        let header = data
+            .get(offset..offset + HEADER_SIZE)
+            .filter(|header| header.starts_with(b"07070"))
+            .ok_or_else(|| malformed(path, "invalid cpio header"))?;
+
+        // Header fields are 8 hexadecimal digits each, after the magic number
+        let field = |index: usize| {
+            let start = 6 + index * 8;
+            std::str::from_utf8(&header[start..start + 8])
+                .ok()
+                .and_then(|field| u32::from_str_radix(field, 16).ok())
+                .ok_or_else(|| malformed(path, "invalid cpio header"))
+        };
+
+        let mode = field(1)?;
+        let file_size = field(6)? as usize;
+        let name_size = field(11)? as usize;
+
+        // The name (including a trailing NUL) and data are both padded to
+        // multiples of 4 bytes
+        let name_start = offset + HEADER_SIZE;
+        let name = data
+            .get(name_start..name_start + name_size.saturating_sub(1))
+            .ok_or_else(|| malformed(path, "truncated cpio archive"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let data_start = (name_start + name_size + 3) / 4 * 4;
+        let contents = data
+            .get(data_start..data_start + file_size)
+            .ok_or_else(|| malformed(path, "truncated cpio archive"))?;
+
+        if name == "TRAILER!!!" {
+            return Ok(files);
+        }
+
+        if mode & FILE_TYPE == REGULAR_FILE {
+            if file_size as u64 > MAX_SIZE {
+                return Err(too_large(path));
+            }
+
+            budget.spend(path, file_size as u64)?;
+            files.push((name, contents.to_vec()));
+        }
+
+        offset = (data_start + file_size + 3) / 4 * 4;
+    }
+}
+
+/// Error for an archive which couldn't be extracted
+pub fn extract_error(path: &Path, error: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::with_source(
+        ErrorKind::Parse,
+        format!("could not extract {}", path.display()),
+        error,
+    )
+}
+
+/// Error for an archive whose contents exceed the size limits
+pub fn too_large(path: &Path) -> Error {
+    Error::new(
+        ErrorKind::Parse,
+        &format!(
+            "{} contains files larger than {} bytes, or more than {} bytes in total",
+            path.display(),
+            MAX_SIZE,
+            MAX_TOTAL_SIZE
+        ),
+    )
+}
+
+/// Error for a malformed archive
+fn malformed(path: &Path, reason: &str) -> Error {
+    Error::new(
+        ErrorKind::Parse,
+        &format!("could not extract {}: {}", path.display(), reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        for (name, contents) in files {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    /// `ar` archive with the given members, as used by Debian packages
+    fn ar(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = b"!<arch>\n".to_vec();
+
+        for (name, contents) in members {
+            let header = format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                0,
+                0,
+                100644,
+                contents.len()
+            );
+            data.extend_from_slice(header.as_bytes());
+            data.extend_from_slice(contents);
+
+            if contents.len() % 2 == 1 {
+                data.push(b'\n');
+            }
+        }
+
+        data
+    }
+
+    /// `cpio` archive in the "new ASCII" format with the given files and modes
+    fn cpio(files: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut data = vec![];
+        let trailer = [("TRAILER!!!", 0, &b""[..])];
+
+        for (name, mode, contents) in files.iter().chain(&trailer) {
+            let fields = [
+                0,
+                *mode,
+                0,
+                0,
+                1,
+                0,
+                contents.len() as u32,
+                0,
+                0,
+                0,
+                0,
+                name.len() as u32 + 1,
+                0,
+            ];
+            data.extend_from_slice(b"070701");
+            for field in fields {
+                data.extend_from_slice(format!("{:08x}", field).as_bytes());
+            }
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+            while data.len() % 4 != 0 {
+                data.push(0);
+            }
+            data.extend_from_slice(contents);
+            while data.len() % 4 != 0 {
+                data.push(0);
+            }
+        }
+
+        data
+    }
+
+    fn names(files: &[(String, Vec<u8>)]) -> Vec<&str> {
+        files.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn budget_limits_total_size() {
+        let path = Path::new("archive.tar");
+        let mut budget = Budget::with_limit(10);
+
+        assert_eq!(budget.read(path, &b"123456"[..]).unwrap(), b"123456");
+        assert!(budget.read(path, &b"123456"[..]).is_err());
+
+        let data = tar(&[("a", b"123456"), ("b", b"123456")]);
+        assert!(tar_files(path, &data, &mut Budget::with_limit(10)).is_err());
+        assert_eq!(
+            tar_files(path, &data, &mut Budget::with_limit(12))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn extract_deb() {
+        let data_tar = tar(&[("./usr/bin/app", b"binary"), ("./usr/share/doc", b"doc")]);
+        let data = ar(&[
+            ("debian-binary", b"2.0\n"),
+            ("control.tar", &tar(&[("./control", b"Package: app")])),
+            ("data.tar", &data_tar),
+        ]);
+
+        let files = deb_files(Path::new("app.deb"), &data, &mut Budget::new()).unwrap();
+        assert_eq!(names(&files), ["usr/bin/app", "usr/share/doc"]);
+        assert_eq!(files[0].1, b"binary");
+
+        let data = ar(&[("debian-binary", b"2.0\n")]);
+        assert!(deb_files(Path::new("app.deb"), &data, &mut Budget::new()).is_err());
+    }
+
+    #[test]
+    fn extract_cpio() {
+        let data = cpio(&[
+            ("usr/bin", 0o040755, b""),
+            ("usr/bin/app", 0o100755, b"binary"),
+            ("usr/bin/link", 0o120777, b"app"),
+        ]);
+
+        let files = cpio_files(Path::new("app.cpio"), &data, &mut Budget::new()).unwrap();
+        assert_eq!(names(&files), ["usr/bin/app"]);
+        assert_eq!(files[0].1, b"binary");
+
+        // The trailer is required
+        let truncated = &data[..data.len() - 124];
+        assert!(cpio_files(Path::new("app.cpio"), truncated, &mut Budget::new()).is_err());
+        assert!(cpio_files(Path::new("app.cpio"), &data, &mut Budget::with_limit(5)).is_err());
+    }
+
+    #[test]
+    fn extract_rpm() {
+        let mut data = vec![0xed, 0xab, 0xee, 0xdb];
+        data.resize(96, 0);
+
+        // Signature header with one index entry and 4 bytes of data, padded
+        // to a multiple of 8 bytes, then the main header without entries
+        data.extend_from_slice(&[0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0]);
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&[0; 20]);
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&[0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&cpio(&[("./usr/bin/app", 0o100755, b"binary")]));
+
+        let files = rpm_files(Path::new("app.rpm"), &data, &mut Budget::new()).unwrap();
+        assert_eq!(names(&files), ["./usr/bin/app"]);
+
+        data[96] = 0;
+        assert!(rpm_files(Path::new("app.rpm"), &data, &mut Budget::new()).is_err());
+    }
+}
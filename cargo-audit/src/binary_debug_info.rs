@@ -0,0 +1,485 @@
+//! Finds the separate debug info of stripped binaries, whose symbols and
+//! source paths recover what stripping removed from the binary itself.
+//!
+//! Debug info is looked up in the conventional places:
+//! - `.build-id/<xx>/<rest>.debug` under `/usr/lib/debug`, for ELF binaries
+//!   with a GNU build ID
+//! - the file named in the `.gnu_debuglink` section of ELF binaries, next to
+//!   the binary, in `.debug` next to it, or under `/usr/lib/debug`
+//! - `<binary>.debug` and `.debug/<name>.debug` next to the binary
+//! - `<binary>.dSYM/Contents/Resources/DWARF/<name>` for Mach-O binaries
+//! - `<path of the binary>.debug` under `/usr/lib/debug`
+//!
+//! Additional directories are searched in the same way, and files given
+//! explicitly are used for every binary. Debug info for ELF binaries is only
+//! used if it has the same build ID, or for binaries without one, if it has
+//! the checksum recorded in `.gnu_debuglink`.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Directory debug info is installed to by Linux distributions
+const SYSTEM_DEBUG_DIR: &str = "/usr/lib/debug";
+
+/// Type of the ELF note holding the GNU build ID
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// Type of ELF sections which take up no space in the file, such as the
+/// code sections of separate debug info
+const SHT_NOBITS: u32 = 8;
+
+/// Find the separate debug info of a binary, given the files and directories
+/// to search in addition to the conventional places
+pub fn find(binary_path: &Path, data: &[u8], search: &[PathBuf]) -> Option<PathBuf> {
+    let elf = Elf::parse(data);
+    let build_id = elf.as_ref().and_then(Elf::build_id);
+    let debuglink = elf.as_ref().and_then(Elf::debuglink);
+    let belongs = |path: &Path| belongs_to_binary(path, build_id, debuglink.map(|(_, crc)| crc));
+
+    // Explicitly given files take precedence
+    if let Some(file) = search
+        .iter()
+        .filter(|path| path.is_file())
+        .find(|path| belongs(path))
+    {
+        return Some(file.clone());
+    }
+
+    let file_name = binary_path.file_name()?;
+    let dir = binary_path.parent().unwrap_or_else(|| Path::new(""));
+    let absolute_dir = binary_path
+        .canonicalize()
+        .ok()
+        .and_then(|absolute| Some(absolute.parent()?.strip_prefix("/").ok()?.to_owned()));
+    let debug_name = format!("{}.debug", file_name.to_string_lossy());
+    let dsym = |dir: &Path| {
+        dir.join(format!("{}.dSYM", file_name.to_string_lossy()))
+            .join("Contents/Resources/DWARF")
+            .join(file_name)
+    };
+
+    let search_dirs: Vec<&Path> = search
+        .iter()
+        .filter(|path| path.is_dir())
+        .map(PathBuf::as_path)
+        .chain([Path::new(SYSTEM_DEBUG_DIR)])
+        .collect();
+
+    // Paths derived from the build ID can't belong to another binary
+    if let Some(build_id) = build_id.filter(|build_id| build_id.len() >= 2) {
+        let build_id: String = build_id
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let found = search_dirs
+            .iter()
+            .map(|search_dir| {
+                search_dir
+                    .join(".build-id")
+                    .join(&build_id[..2])
+                    .join(format!("{}.debug", &build_id[2..]))
+            })
+            .find(|candidate| candidate.is_file());
+
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    let mut candidates = vec![];
+
+    if let Some((link, _)) = debuglink {
+        candidates.push(dir.join(link));
+        candidates.push(dir.join(".debug").join(link));
+
+        for search_dir in &search_dirs {
+            candidates.push(search_dir.join(link));
+            if let Some(absolute_dir) = &absolute_dir {
+                candidates.push(search_dir.join(absolute_dir).join(link));
+            }
+        }
+    }
+
+    candidates.push(dir.join(&debug_name));
+    candidates.push(dir.join(".debug").join(&debug_name));
+    candidates.push(dsym(dir));
+
+    for search_dir in &search_dirs {
+        candidates.push(search_dir.join(&debug_name));
+        candidates.push(dsym(search_dir));
+        if let Some(absolute_dir) = &absolute_dir {
+            candidates.push(search_dir.join(absolute_dir).join(&debug_name));
+        }
+    }
+
+    candidates
+        .into_iter()
+        // The binary itself may be named in its own `.gnu_debuglink`
+        .filter(|candidate| candidate.as_path() != binary_path)
+        .find(|candidate| candidate.is_file() && belongs(candidate))
+}
+
+/// Does the debug info at the given path belong to the binary? It must have
+/// the binary's build ID, or if there is none, the checksum recorded in the
+/// binary's `.gnu_debuglink`. Debug info of binaries with neither can't be
+/// checked.
+fn belongs_to_binary(path: &Path, build_id: Option<&[u8]>, crc: Option<u32>) -> bool {
+    if build_id.is_none() && crc.is_none() {
+        return true;
+    }
+
+    let debug_info = match fs::read(path) {
+        Ok(debug_info) => debug_info,
+        Err(_) => return false,
+    };
+
+    match build_id {
+        Some(build_id) => Elf::parse(&debug_info).and_then(|elf| elf.build_id()) == Some(build_id),
+        None => crc == Some(crc32(&debug_info)),
+    }
+}
+
+/// CRC-32 checksum of a file, as recorded in `.gnu_debuglink`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Section headers of an ELF file, with the byte order of the file
+struct Elf<'a> {
+    big_endian: bool,
+    sections: Vec<Section<'a>>,
+}
+
+/// Section of an ELF file
+struct Section<'a> {
+    name: &'a [u8],
+    contents: &'a [u8],
+}
+
+impl<'a> Elf<'a> {
+    /// Parse the section headers of an ELF file, if it is one
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(b"\x7fELF") {
+            return None;
+        }
+
+        let is_64 = *data.get(4)? == 2;
+        let big_endian = *data.get(5)? == 2;
+        let read = |offset: usize, size: usize| read_uint(data, offset, size, big_endian);
+
+        // `e_shoff`, `e_shentsize`, `e_shnum` and `e_shstrndx`
+        let (offset, entry_size, count, names_index) = if is_64 {
+            (
+                read(0x28, 8)?,
+                read(0x3a, 2)?,
+                read(0x3c, 2)?,
+                read(0x3e, 2)?,
+            )
+        } else {
+            (
+                read(0x20, 4)?,
+                read(0x2e, 2)?,
+                read(0x30, 2)?,
+                read(0x32, 2)?,
+            )
+        };
+
+        // `sh_name`, `sh_type`, `sh_offset` and `sh_size` of each section
+        let headers = (0..count)
+            .map(|index| {
+                let header = offset.checked_add(index.checked_mul(entry_size)?)?;
+                let header = usize::try_from(header)
+                    .ok()
+                    .filter(|&header| header < data.len())?;
+                if is_64 {
+                    Some([
+                        read(header, 4)?,
+                        read(header + 4, 4)?,
+                        read(header + 24, 8)?,
+                        read(header + 32, 8)?,
+                    ])
+                } else {
+                    Some([
+                        read(header, 4)?,
+                        read(header + 4, 4)?,
+                        read(header + 16, 4)?,
+                        read(header + 20, 4)?,
+                    ])
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let contents = |[_, kind, offset, size]: [u64; 4]| -> &'a [u8] {
+            let range = usize::try_from(offset)
+                .ok()
+                .and_then(|start| Some(start..start.checked_add(usize::try_from(size).ok()?)?));
+
+            match range {
+                Some(range) if kind != u64::from(SHT_NOBITS) => data.get(range).unwrap_or_default(),
+                _ => &[],
+            }
+        };
+
+        let names = contents(*headers.get(usize::try_from(names_index).ok()?)?);
+        let sections = headers
+            .iter()
+            .map(|&header| Section {
+                name: usize::try_from(header[0])
+                    .ok()
+                    .and_then(|start| names.get(start..))
+                    .and_then(|name| name.split(|&byte| byte == 0).next())
+                    .unwrap_or_default(),
+                contents: contents(header),
+            })
+            .collect();
+
+        Some(Self {
+            big_endian,
+            sections,
+        })
+    }
+
+    /// Contents of the section with the given name
+    fn section(&self, name: &str) -> Option<&'a [u8]> {
+        self.sections
+            .iter()
+            .find(|section| section.name == name.as_bytes())
+            .map(|section| section.contents)
+    }
+
+    /// GNU build ID, from the note in the `.note.gnu.build-id` section:
+    /// the sizes of the name (`GNU\0`) and of the ID, and the type of the
+    /// note, followed by the name and the ID, each padded to 4 bytes
+    fn build_id(&self) -> Option<&'a [u8]> {
+        let mut notes = self.section(".note.gnu.build-id")?;
+
+        while !notes.is_empty() {
+            let read = |offset| read_uint(notes, offset, 4, self.big_endian);
+            let name_size = usize::try_from(read(0)?).ok()?;
+            let id_size = usize::try_from(read(4)?).ok()?;
+            let note_type = read(8)?;
+
+            let name_end = 12usize.checked_add(name_size)?;
+            let id_start = align4(name_end)?;
+            let id_end = id_start.checked_add(id_size)?;
+
+            if note_type == u64::from(NT_GNU_BUILD_ID)
+                && notes.get(12..name_end)? == b"GNU\0"
+                && id_size > 0
+            {
+                return notes.get(id_start..id_end);
+            }
+
+            notes = notes.get(align4(id_end)?..).unwrap_or_default();
+        }
+
+        None
+    }
+
+    /// File name of the separate debug info and its CRC-32 checksum, from
+    /// the `.gnu_debuglink` section: the NUL-terminated name, padded to 4
+    /// bytes, followed by the checksum
+    fn debuglink(&self) -> Option<(&'a str, u32)> {
+        let section = self.section(".gnu_debuglink")?;
+        let name_len = section.iter().position(|&byte| byte == 0)?;
+        let name = std::str::from_utf8(&section[..name_len]).ok()?;
+
+        // Only a file name is expected, which mustn't lead elsewhere
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return None;
+        }
+
+        let crc = read_uint(section, align4(name_len + 1)?, 4, self.big_endian)?;
+        Some((name, u32::try_from(crc).ok()?))
+    }
+}
+
+/// Read an unsigned integer of the given size (in bytes) at an offset
+fn read_uint(data: &[u8], offset: usize, size: usize, big_endian: bool) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    let fold = |value: u64, &byte: &u8| value << 8 | u64::from(byte);
+
+    Some(if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    })
+}
+
+/// Round an offset up to a multiple of 4
+fn align4(offset: usize) -> Option<usize> {
+    Some(offset.checked_add(3)? & !3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal 64-bit little-endian ELF file with the given sections, each
+    /// of which is placed after the headers
+    fn elf(sections: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut names = vec![0u8];
+        let mut name_offsets = vec![];
+        for (name, _) in sections.iter().chain([&(".shstrtab", &[][..])]) {
+            name_offsets.push(names.len() as u32);
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+
+        let mut contents: Vec<&[u8]> = sections.iter().map(|(_, data)| *data).collect();
+        contents.push(&names);
+
+        let count = contents.len() + 1;
+        let headers_offset = 64;
+        let mut data_offset = headers_offset + count * 64;
+
+        let mut file = vec![0u8; 64];
+        file[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        file[0x28..0x30].copy_from_slice(&(headers_offset as u64).to_le_bytes());
+        file[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        file[0x3c..0x3e].copy_from_slice(&(count as u16).to_le_bytes());
+        file[0x3e..0x40].copy_from_slice(&((count - 1) as u16).to_le_bytes());
+
+        // The first section header is null
+        file.extend_from_slice(&[0; 64]);
+        for (name_offset, section) in name_offsets.iter().zip(&contents) {
+            let mut header = [0u8; 64];
+            header[..4].copy_from_slice(&name_offset.to_le_bytes());
+            header[4..8].copy_from_slice(&1u32.to_le_bytes());
+            header[24..32].copy_from_slice(&(data_offset as u64).to_le_bytes());
+            header[32..40].copy_from_slice(&(section.len() as u64).to_le_bytes());
+            file.extend_from_slice(&header);
+            data_offset += section.len();
+        }
+
+        for section in contents {
+            file.extend_from_slice(section);
+        }
+
+        file
+    }
+
+    /// `.note.gnu.build-id` contents with the given ID, preceded by another
+    /// note
+    fn build_id_note(id: &[u8]) -> Vec<u8> {
+        let mut notes = vec![];
+        for (name, note_type, desc) in [(&b"Go\0\0"[..], 4u32, &b"abcde"[..]), (b"GNU\0", 3, id)] {
+            notes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            notes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+            notes.extend_from_slice(&note_type.to_le_bytes());
+            notes.extend_from_slice(name);
+            notes.extend_from_slice(desc);
+            notes.resize(align4(notes.len()).unwrap(), 0);
+        }
+        notes
+    }
+
+    fn debuglink(name: &str, crc: u32) -> Vec<u8> {
+        let mut section = name.as_bytes().to_vec();
+        section.push(0);
+        section.resize(align4(section.len()).unwrap(), 0);
+        section.extend_from_slice(&crc.to_le_bytes());
+        section
+    }
+
+    #[test]
+    fn parse_build_id_and_debuglink() {
+        let note = build_id_note(&[0xab, 0xcd, 0xef]);
+        let link = debuglink("app.debug", 0x1234_5678);
+        let data = elf(&[(".note.gnu.build-id", &note), (".gnu_debuglink", &link)]);
+
+        let parsed = Elf::parse(&data).unwrap();
+        assert_eq!(parsed.build_id(), Some(&[0xab, 0xcd, 0xef][..]));
+        assert_eq!(parsed.debuglink(), Some(("app.debug", 0x1234_5678)));
+
+        // A `GNU` string elsewhere isn't mistaken for the build ID
+        let data = elf(&[(".rodata", b"\x04\0\0\0\x02\0\0\0\x03\0\0\0GNU\0\x01\x02")]);
+        assert_eq!(Elf::parse(&data).unwrap().build_id(), None);
+
+        let link = debuglink("../../etc/passwd", 0);
+        let data = elf(&[(".gnu_debuglink", &link)]);
+        assert_eq!(Elf::parse(&data).unwrap().debuglink(), None);
+
+        assert!(Elf::parse(b"MZ").is_none());
+    }
+
+    #[test]
+    fn find_by_debuglink() {
+        let dir = tempfile::tempdir().unwrap();
+        let debug_info = elf(&[(".debug_info", b"debug")]);
+        let binary = elf(&[(".gnu_debuglink", &debuglink("app.dbg", crc32(&debug_info)))]);
+        let binary_path = dir.path().join("app");
+
+        fs::create_dir(dir.path().join(".debug")).unwrap();
+        let debug_path = dir.path().join(".debug/app.dbg");
+        fs::write(&debug_path, &debug_info).unwrap();
+        assert_eq!(find(&binary_path, &binary, &[]), Some(debug_path.clone()));
+
+        // Debug info with another checksum is from another build
+        fs::write(&debug_path, b"stale").unwrap();
+        assert_eq!(find(&binary_path, &binary, &[]), None);
+    }
+
+    #[test]
+    fn explicit_files_must_match_build_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = elf(&[(".note.gnu.build-id", &build_id_note(&[1, 2, 3, 4]))]);
+        let binary_path = dir.path().join("app");
+
+        let other = dir.path().join("other.debug");
+        fs::write(
+            &other,
+            elf(&[(".note.gnu.build-id", &build_id_note(&[5, 6, 7, 8]))]),
+        )
+        .unwrap();
+        let matching = dir.path().join("matching.debug");
+        fs::write(
+            &matching,
+            elf(&[(".note.gnu.build-id", &build_id_note(&[1, 2, 3, 4]))]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            find(&binary_path, &binary, std::slice::from_ref(&other)),
+            None
+        );
+        assert_eq!(
+            find(&binary_path, &binary, &[other, matching.clone()]),
+            Some(matching.clone())
+        );
+
+        // Without a build ID or debuglink, there's nothing to check
+        assert_eq!(
+            find(
+                &binary_path,
+                b"\xcf\xfa\xed\xfe",
+                std::slice::from_ref(&matching)
+            ),
+            Some(matching)
+        );
+    }
+
+    #[test]
+    fn find_by_build_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = elf(&[(".note.gnu.build-id", &build_id_note(&[0xab, 0xcd, 0xef]))]);
+
+        let build_id_dir = dir.path().join(".build-id/ab");
+        fs::create_dir_all(&build_id_dir).unwrap();
+        let debug_path = build_id_dir.join("cdef.debug");
+        fs::write(&debug_path, b"").unwrap();
+
+        assert_eq!(
+            find(Path::new("/bin/app"), &binary, &[dir.path().to_owned()]),
+            Some(debug_path)
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Dependency lists recovered from binaries, as printed by
+//! `cargo audit bin --print-deps` regardless of any vulnerabilities
+//!
+//! The text format lists each package as `name version source`, followed by
+//! its direct dependencies indented by two spaces, which `cargo audit
+//! --stdin-format list` reads back. Path dependencies are left out, as they
+//! can't have advisories and would otherwise be read back as crates.io
+//! packages.
+
+use crate::binary_deps::{self, BinaryReport};
+use rustsec::{Error, ErrorKind, Lockfile};
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Dependencies recovered from a binary
+#[derive(Clone, Debug, Serialize)]
+pub struct BinaryDeps {
+    /// Path to the binary
+    pub path: PathBuf,
+
+    /// Was the complete dependency graph embedded by `cargo auditable`,
+    /// rather than partially recovered from panic messages?
+    pub complete: bool,
+
+    /// Packages the binary was built from
+    pub packages: Vec<DepPackage>,
+}
+
+/// Package a binary was built from
+#[derive(Clone, Debug, Serialize)]
+pub struct DepPackage {
+    /// Name of the package
+    pub name: String,
+
+    /// Version of the package
+    pub version: String,
+
+    /// Source of the package, unless it's a path dependency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// Direct dependencies of the package
+    pub dependencies: Vec<DepRef>,
+}
+
+/// Reference to a dependency of a package
+#[derive(Clone, Debug, Serialize)]
+pub struct DepRef {
+    /// Name of the dependency
+    pub name: String,
+
+    /// Version of the dependency
+    pub version: String,
+
+    /// Source of the dependency, unless it's a path dependency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl BinaryDeps {
+    /// Recover the dependencies of a binary (or of each architecture of a
+    /// universal binary), looking for separate debug info in the given paths
+    pub fn load(binary_path: &Path, data: &[u8], debug_info: &[PathBuf]) -> rustsec::Result<Self> {
+        let (lockfile, complete) = crate::binary_fat::map_slices(
+            binary_path,
+            data,
+            |slice_path, slice_data| load_lockfile(binary_path, slice_path, slice_data, debug_info),
+            |(merged_lockfile, merged_complete), (lockfile, complete)| {
+                binary_deps::merge_lockfiles(merged_lockfile, lockfile);
+                *merged_complete &= complete;
+            },
+        )?;
+
+        Ok(Self::new(binary_path, &lockfile, complete))
+    }
+
+    /// Describe the packages of the given lockfile, recovered from a binary
+    fn new(binary_path: &Path, lockfile: &Lockfile, complete: bool) -> Self {
+        let mut packages: Vec<_> = lockfile
+            .packages
+            .iter()
+            .map(|package| DepPackage {
+                name: package.name.to_string(),
+                version: package.version.to_string(),
+                source: package.source.as_ref().map(ToString::to_string),
+                dependencies: package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| DepRef {
+                        name: dependency.name.to_string(),
+                        version: dependency.version.to_string(),
+                        source: dependency.source.as_ref().map(ToString::to_string),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+
+        Self {
+            path: binary_path.to_owned(),
+            complete,
+            packages,
+        }
+    }
+
+    /// Format the dependency list as text, leaving out path dependencies
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "# {}: {} packages ({})\n",
+            self.path.display(),
+            self.packages.len(),
+            if self.complete {
+                "embedded by cargo auditable"
+            } else {
+                "incomplete, recovered from panic messages"
+            }
+        );
+
+        for package in &self.packages {
+            let source = match &package.source {
+                Some(source) => source,
+                None => continue,
+            };
+            writeln!(text, "{} {} {}", package.name, package.version, source).unwrap();
+
+            for dependency in &package.dependencies {
+                if let Some(source) = &dependency.source {
+                    writeln!(
+                        text,
+                        "  {} {} {}",
+                        dependency.name, dependency.version, source
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        text
+    }
+}
+
+/// Recover the dependencies of a binary for a single architecture, named
+/// `slice_path` if it's part of a universal binary, and whether they're
+/// complete
+fn load_lockfile(
+    binary_path: &Path,
+    slice_path: &Path,
+    data: &[u8],
+    debug_info: &[PathBuf],
+) -> rustsec::Result<(Lockfile, bool)> {
+    let (_, report, _) = binary_deps::load_deps_with_debug_info(slice_path, data, || {
+        crate::binary_debug_info::find(binary_path, data, debug_info)
+            .and_then(|path| fs::read(path).ok())
+    })?;
+
+    match report {
+        BinaryReport::Complete(lockfile) => Ok((lockfile, true)),
+        BinaryReport::Incomplete(lockfile) => Ok((lockfile, false)),
+        BinaryReport::None => Err(Error::new(
+            ErrorKind::Parse,
+            &format!(
+                "no dependency information found in {}! Is this a Rust executable built with cargo?",
+                slice_path.display()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "serde",
+ "util",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.190"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "util"
+version = "0.2.0"
+"#;
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    #[test]
+    fn text() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let deps = BinaryDeps::new(Path::new("target/release/app"), &lockfile, true);
+
+        assert_eq!(
+            deps.to_text(),
+            format!(
+                "# target/release/app: 3 packages (embedded by cargo auditable)\n\
+                 serde 1.0.190 {}\n",
+                CRATES_IO
+            )
+        );
+
+        let deps = BinaryDeps::new(Path::new("app"), &lockfile, false);
+        assert!(deps
+            .to_text()
+            .starts_with("# app: 3 packages (incomplete, recovered from panic messages)\n"));
+    }
+
+    /// The text output is read back with the sources of the packages
+    #[test]
+    fn text_round_trip() {
+        let mut lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let git = "git+https://github.com/example/util#0123456789abcdef0123456789abcdef01234567";
+        lockfile.packages[2].source = Some(git.parse().unwrap());
+        lockfile.packages[0].source = Some(CRATES_IO.parse().unwrap());
+        let sources: Vec<_> = lockfile
+            .packages
+            .iter()
+            .map(|package| (package.name.clone(), package.source.clone()))
+            .collect();
+        for dependency in &mut lockfile.packages[0].dependencies {
+            dependency.source = sources
+                .iter()
+                .find(|(name, _)| name == &dependency.name)
+                .and_then(|(_, source)| source.clone());
+        }
+
+        let text = BinaryDeps::new(Path::new("app"), &lockfile, true).to_text();
+        let parsed = crate::dependency_list::StdinFormat::List
+            .parse(&text)
+            .unwrap();
+
+        let sources: Vec<_> = parsed
+            .packages
+            .iter()
+            .map(|package| (package.name.as_str(), package.source.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                ("app", CRATES_IO.parse().unwrap()),
+                ("serde", CRATES_IO.parse().unwrap()),
+                ("util", git.parse().unwrap()),
+            ]
+        );
+    }
+}
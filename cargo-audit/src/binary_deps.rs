@@ -1,11 +1,22 @@
 //! Extracts dependencies from binary files, using one of two ways:
-//! 1. Recovers the dependency list embedded by `cargo auditable` (using `auditable-info`)
+//! 1. Recovers the dependency list embedded by `cargo auditable` (using `auditable-info`,
+//!    or from the custom section of WebAssembly modules)
 //! 2. Failing that, recovers as many crates as possible from panic messages (using `quitters`)
+//!
+//! Static libraries and rlibs are scanned one object file at a time.
 
-use std::{path::Path, str::FromStr};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
 
 use cargo_lock::{Lockfile, Package};
+use once_cell::sync::OnceCell;
 use rustsec::{Error, ErrorKind};
+use serde::{Deserialize, Serialize};
 
 use crate::binary_format::BinaryFormat;
 
@@ -18,12 +29,27 @@ pub enum BinaryReport {
     None,
 }
 
-/// Load the dependency tree from a binary file
-pub fn load_deps_from_binary(binary_path: &Path) -> rustsec::Result<(BinaryFormat, BinaryReport)> {
-    // TODO: input size limit
-    let file_contents = std::fs::read(binary_path)?;
-    let format = binfarce::detect_format(&file_contents).into();
-    let stuff = auditable_info::audit_info_from_slice(&file_contents, 8 * 1024 * 1024);
+/// Load the dependency tree from the contents of a binary file
+pub fn load_deps_from_binary(
+    binary_path: &Path,
+    file_contents: &[u8],
+) -> rustsec::Result<(BinaryFormat, BinaryReport)> {
+    if let Some(objects) = crate::binary_static_lib::objects(file_contents) {
+        let objects = objects.map_err(|reason| {
+            Error::new(
+                ErrorKind::Parse,
+                &format!("could not read {}: {}", binary_path.display(), reason),
+            )
+        })?;
+        return load_deps_from_static_lib(binary_path, file_contents, objects);
+    }
+
+    let format = BinaryFormat::detect(file_contents);
+    if format == BinaryFormat::Wasm {
+        return load_deps_from_wasm(binary_path, file_contents).map(|report| (format, report));
+    }
+
+    let stuff = auditable_info::audit_info_from_slice(file_contents, 8 * 1024 * 1024);
 
     use auditable_info::Error::*; // otherwise rustfmt makes the matches multiline and unreadable
     match stuff {
@@ -33,7 +59,7 @@ pub fn load_deps_from_binary(binary_path: &Path) -> rustsec::Result<(BinaryForma
         )),
         Err(e) => match e {
             NoAuditData => {
-                if let Some(deps) = deps_from_panic_messages(&file_contents) {
+                if let Some(deps) = deps_from_panic_messages(file_contents) {
                     Ok((format, BinaryReport::Incomplete(deps)))
                 } else {
                     Ok((format, BinaryReport::None))
@@ -64,6 +90,212 @@ pub fn load_deps_from_binary(binary_path: &Path) -> rustsec::Result<(BinaryForma
     }
 }
 
+/// Load the dependencies of a static library or rlib, merging those found
+/// in each of its object files
+fn load_deps_from_static_lib(
+    lib_path: &Path,
+    lib: &[u8],
+    objects: Vec<crate::binary_static_lib::Object<'_>>,
+) -> rustsec::Result<(BinaryFormat, BinaryReport)> {
+    let mut format = BinaryFormat::Unknown;
+    let mut complete: Option<Lockfile> = None;
+    let mut incomplete: Option<Lockfile> = None;
+
+    for object in objects {
+        // Members are conventionally named `archive(member)`
+        let object_path = PathBuf::from(format!("{}({})", lib_path.display(), object.name));
+        let (object_format, report) = load_deps_from_binary(&object_path, object.data)?;
+
+        if format == BinaryFormat::Unknown {
+            format = object_format;
+        }
+
+        let (merged, lockfile) = match report {
+            BinaryReport::Complete(lockfile) => (&mut complete, lockfile),
+            BinaryReport::Incomplete(lockfile) => (&mut incomplete, lockfile),
+            BinaryReport::None => continue,
+        };
+
+        match merged {
+            Some(merged) => merge_lockfiles(merged, lockfile),
+            None => *merged = Some(lockfile),
+        }
+    }
+
+    let report = match (complete, incomplete) {
+        (Some(lockfile), _) => BinaryReport::Complete(lockfile),
+        (None, Some(lockfile)) => BinaryReport::Incomplete(lockfile),
+        // Object files in formats which aren't recognized, like the COFF
+        // objects of MSVC libraries, may still contain panic messages
+        (None, None) => match deps_from_panic_messages(lib) {
+            Some(lockfile) => BinaryReport::Incomplete(lockfile),
+            None => BinaryReport::None,
+        },
+    };
+
+    Ok((format, report))
+}
+
+/// Load the dependency tree from the contents of a binary for a single
+/// architecture like [`load_deps_from_binary`], also using its separate debug
+/// info (loaded with `load_debug_info`) unless `cargo auditable` embedded the
+/// complete tree.
+///
+/// Panic messages are kept by stripping, but source paths in the separate
+/// debug info may name more of the crates. The debug info is returned too,
+/// if it was loaded.
+pub fn load_deps_with_debug_info(
+    binary_path: &Path,
+    data: &[u8],
+    load_debug_info: impl FnOnce() -> Option<Vec<u8>>,
+) -> rustsec::Result<(BinaryFormat, BinaryReport, Option<Vec<u8>>)> {
+    let (format, report) = load_deps_from_binary(binary_path, data)?;
+
+    if let BinaryReport::Complete(_) = report {
+        return Ok((format, report, None));
+    }
+
+    Ok(match load_debug_info() {
+        Some(debug_info) => (
+            format,
+            add_debug_info_deps(report, &debug_info),
+            Some(debug_info),
+        ),
+        None => (format, report, None),
+    })
+}
+
+/// Add the dependencies named by source paths in the separate debug info of
+/// a stripped binary to those recovered from the binary itself
+pub fn add_debug_info_deps(report: BinaryReport, debug_info: &[u8]) -> BinaryReport {
+    let deps = match deps_from_panic_messages(debug_info) {
+        Some(deps) => deps,
+        None => return report,
+    };
+
+    match report {
+        BinaryReport::Complete(lockfile) => BinaryReport::Complete(lockfile),
+        BinaryReport::Incomplete(mut lockfile) => {
+            merge_lockfiles(&mut lockfile, deps);
+            BinaryReport::Incomplete(lockfile)
+        }
+        BinaryReport::None => BinaryReport::Incomplete(deps),
+    }
+}
+
+/// Add the packages of a lockfile which aren't in the merged lockfile yet
+pub fn merge_lockfiles(merged: &mut Lockfile, lockfile: Lockfile) {
+    for package in lockfile.packages {
+        if !merged.packages.contains(&package) {
+            merged.packages.push(package);
+        }
+    }
+}
+
+/// Name of the custom section `cargo auditable` embeds dependency data in
+const WASM_SECTION: &[u8] = b".dep-v0";
+
+/// Maximum size of the decompressed dependency data, as for other formats
+const MAX_AUDIT_DATA_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Load the dependency tree from a WebAssembly module
+fn load_deps_from_wasm(binary_path: &Path, module: &[u8]) -> rustsec::Result<BinaryReport> {
+    let invalid = |reason: &str| {
+        Error::new(
+            ErrorKind::Parse,
+            &format!(
+                "could not extract dependencies from binary {}: {}",
+                binary_path.display(),
+                reason
+            ),
+        )
+    };
+
+    let compressed = match wasm_custom_section(module, WASM_SECTION) {
+        Ok(Some(section)) => section,
+        Ok(None) => {
+            return Ok(match deps_from_panic_messages(module) {
+                Some(deps) => BinaryReport::Incomplete(deps),
+                None => BinaryReport::None,
+            })
+        }
+        Err(reason) => return Err(invalid(reason)),
+    };
+
+    // The data is zlib-compressed JSON, as in other formats
+    let mut json = vec![];
+    flate2::read::ZlibDecoder::new(compressed)
+        .take(MAX_AUDIT_DATA_SIZE + 1)
+        .read_to_end(&mut json)
+        .map_err(|_| invalid("invalid compressed audit data"))?;
+    if json.len() as u64 > MAX_AUDIT_DATA_SIZE {
+        return Err(invalid("audit data is too large"));
+    }
+
+    let info: auditable_serde::VersionInfo =
+        serde_json::from_slice(&json).map_err(|_| invalid("invalid audit data"))?;
+
+    Ok(BinaryReport::Complete(cargo_lock::Lockfile::try_from(
+        &info,
+    )?))
+}
+
+/// Find the contents of the custom section with the given name in a
+/// WebAssembly module
+fn wasm_custom_section<'a>(
+    module: &'a [u8],
+    name: &[u8],
+) -> Result<Option<&'a [u8]>, &'static str> {
+    const CUSTOM_SECTION: u8 = 0;
+
+    // Skip the magic number and version
+    let mut offset = 8;
+
+    while offset < module.len() {
+        let id = module[offset];
+        offset += 1;
+
+        let size = read_leb128(module, &mut offset).ok_or("invalid section header")?;
+        let section = module
+            .get(offset..offset + size)
+            .ok_or("truncated section")?;
+        offset += size;
+
+        if id == CUSTOM_SECTION {
+            let mut name_offset = 0;
+            let name_len =
+                read_leb128(section, &mut name_offset).ok_or("invalid custom section name")?;
+            let section_name = section
+                .get(name_offset..name_offset + name_len)
+                .ok_or("invalid custom section name")?;
+
+            if section_name == name {
+                return Ok(Some(&section[name_offset + name_len..]));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Read an unsigned LEB128-encoded integer (of at most 32 bits, as used for
+/// sizes in WebAssembly modules), advancing the offset past it
+fn read_leb128(data: &[u8], offset: &mut usize) -> Option<usize> {
+    let mut value: usize = 0;
+
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        value |= usize::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
 fn deps_from_panic_messages(data: &[u8]) -> Option<Lockfile> {
     let deps = quitters::versions(data);
     if !deps.is_empty() {
@@ -97,3 +329,147 @@ fn to_package(quitter: (&str, cargo_lock::Version)) -> Package {
         replace: None,
     }
 }
+
+/// Dependencies of the running `cargo-audit` executable itself, as embedded
+/// by `cargo auditable`, loaded once per run
+///
+/// Finding them means parsing the whole executable, so they're cached in the
+/// file at `cache_path` (if given) for as long as the executable is unchanged.
+/// Dependencies recovered from panic messages aren't used, since crates only
+/// named in those would raise advisories about `cargo-audit` itself. Returns
+/// an empty list if the executable can't be read or wasn't built with
+/// `cargo auditable`.
+pub fn current_exe_deps(cache_path: Option<&Path>) -> &'static [Package] {
+    static DEPS: OnceCell<Vec<Package>> = OnceCell::new();
+
+    DEPS.get_or_init(|| {
+        std::env::current_exe()
+            .ok()
+            .and_then(|path| ExeStamp::new(&path))
+            .map(|exe| exe_deps(exe, cache_path))
+            .unwrap_or_default()
+    })
+}
+
+/// Dependencies embedded in the given executable, read from the cache if
+/// it's up to date
+fn exe_deps(exe: ExeStamp, cache_path: Option<&Path>) -> Vec<Package> {
+    if let Some(packages) = cache_path.and_then(|path| read_cache(path, &exe)) {
+        return packages;
+    }
+
+    let packages = fs::read(&exe.path)
+        .ok()
+        .and_then(|contents| load_deps_from_binary(&exe.path, &contents).ok())
+        .map(|(_, report)| complete_deps(report))
+        .unwrap_or_default();
+
+    if let Some(path) = cache_path {
+        write_cache(path, exe, &packages);
+    }
+
+    packages
+}
+
+/// Dependencies in the given report, if it's a complete list
+fn complete_deps(report: BinaryReport) -> Vec<Package> {
+    match report {
+        BinaryReport::Complete(lockfile) => lockfile.packages,
+        BinaryReport::Incomplete(_) | BinaryReport::None => vec![],
+    }
+}
+
+/// Which version of an executable dependencies were cached for
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct ExeStamp {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl ExeStamp {
+    /// Get the stamp of the executable at the given path, if it exists
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+
+        Some(Self {
+            path: path.to_owned(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Dependencies of an executable, cached on disk
+#[derive(Deserialize, Serialize)]
+struct CachedDeps {
+    exe: ExeStamp,
+    packages: Vec<Package>,
+}
+
+/// Read the cached dependencies of the given executable, unless the cache is
+/// missing or was written for another version of it
+fn read_cache(cache_path: &Path, exe: &ExeStamp) -> Option<Vec<Package>> {
+    let cached: CachedDeps = serde_json::from_slice(&fs::read(cache_path).ok()?).ok()?;
+    (&cached.exe == exe).then_some(cached.packages)
+}
+
+/// Cache the dependencies of the given executable, ignoring errors since
+/// they'll be found again on the next run
+fn write_cache(cache_path: &Path, exe: ExeStamp, packages: &[Package]) {
+    let cached = CachedDeps {
+        exe,
+        packages: packages.to_vec(),
+    };
+
+    if let Ok(json) = serde_json::to_vec(&cached) {
+        let _ = fs::write(cache_path, json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "left-pad"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn only_complete_deps() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        assert_eq!(
+            complete_deps(BinaryReport::Complete(lockfile.clone())),
+            lockfile.packages
+        );
+        assert!(complete_deps(BinaryReport::Incomplete(lockfile)).is_empty());
+        assert!(complete_deps(BinaryReport::None).is_empty());
+    }
+
+    #[test]
+    fn cached_deps() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe_path = dir.path().join("cargo-audit");
+        fs::write(&exe_path, b"executable").unwrap();
+        let cache_path = dir.path().join("self-deps.json");
+
+        let exe = ExeStamp::new(&exe_path).unwrap();
+        assert_eq!(read_cache(&cache_path, &exe), None);
+
+        let packages = LOCKFILE.parse::<Lockfile>().unwrap().packages;
+        write_cache(&cache_path, exe, &packages);
+
+        let exe = ExeStamp::new(&exe_path).unwrap();
+        assert_eq!(read_cache(&cache_path, &exe), Some(packages));
+
+        // The cache is discarded once the executable changes
+        fs::write(&exe_path, b"updated executable").unwrap();
+        let exe = ExeStamp::new(&exe_path).unwrap();
+        assert_eq!(read_cache(&cache_path, &exe), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Splits universal ("fat") Mach-O binaries into the binaries for each
+//! architecture they contain, which may embed different dependency data.
+
+use rustsec::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Magic number of fat binaries with 32-bit offsets
+const FAT_MAGIC: u32 = 0xcafe_babe;
+
+/// Magic number of fat binaries with 64-bit offsets
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+/// Java class files share the magic number of fat binaries, but have a
+/// version of at least 45 where fat binaries have their number of slices
+const MAX_SLICES: u32 = 30;
+
+/// Binary for a single architecture in a fat binary
+pub struct Slice<'a> {
+    /// Name of the architecture, e.g. `arm64`
+    pub arch: String,
+
+    /// Contents of the binary
+    pub data: &'a [u8],
+}
+
+/// Split a fat Mach-O binary into its slices. Returns `None` if the data
+/// isn't a fat binary.
+pub fn slices(data: &[u8]) -> Option<Result<Vec<Slice<'_>>, &'static str>> {
+    let magic = read_u32(data, 0)?;
+    let count = read_u32(data, 4)?;
+
+    let is_64 = match magic {
+        FAT_MAGIC => false,
+        FAT_MAGIC_64 => true,
+        _ => return None,
+    };
+
+    if count == 0 || count > MAX_SLICES {
+        return None;
+    }
+
+    let entry_size = if is_64 { 32 } else { 20 };
+    let mut slices = vec![];
+
+    for index in 0..count as usize {
+        let entry = 8 + index * entry_size;
+
+        match read_slice(data, entry, is_64) {
+            Some(slice) => slices.push(slice),
+            None => return Some(Err("truncated fat binary")),
+        }
+    }
+
+    Some(Ok(slices))
+}
+
+/// Run `f` on the binary for each architecture of a fat binary, named e.g.
+/// `path (arm64)`, merging the results with `merge`, or on the binary itself
+/// if it isn't a fat binary.
+///
+/// Architectures `f` fails for are skipped as long as it succeeds for
+/// another one.
+pub fn map_slices<T>(
+    binary_path: &Path,
+    data: &[u8],
+    mut f: impl FnMut(&Path, &[u8]) -> rustsec::Result<T>,
+    mut merge: impl FnMut(&mut T, T),
+) -> rustsec::Result<T> {
+    let slices = match slices(data) {
+        Some(Ok(slices)) => slices,
+        Some(Err(reason)) => {
+            return Err(Error::new(
+                ErrorKind::Parse,
+                &format!("could not read {}: {}", binary_path.display(), reason),
+            ))
+        }
+        None => return f(binary_path, data),
+    };
+
+    let mut merged: Option<T> = None;
+    let mut first_error = None;
+
+    for slice in slices {
+        let slice_path = PathBuf::from(format!("{} ({})", binary_path.display(), slice.arch));
+
+        match f(&slice_path, slice.data) {
+            Ok(result) => match &mut merged {
+                Some(merged) => merge(merged, result),
+                None => merged = Some(result),
+            },
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (merged, first_error) {
+        (Some(merged), _) => Ok(merged),
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(Error::new(
+            ErrorKind::Parse,
+            &format!("no architectures found in {}", binary_path.display()),
+        )),
+    }
+}
+
+/// Read the slice described by the `fat_arch` entry at the given offset
+fn read_slice(data: &[u8], entry: usize, is_64: bool) -> Option<Slice<'_>> {
+    let cpu_type = read_u32(data, entry)?;
+    let (offset, size) = if is_64 {
+        (read_u64(data, entry + 8)?, read_u64(data, entry + 16)?)
+    } else {
+        (
+            u64::from(read_u32(data, entry + 8)?),
+            u64::from(read_u32(data, entry + 12)?),
+        )
+    };
+
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+
+    Some(Slice {
+        arch: arch_name(cpu_type),
+        data: data.get(start..end)?,
+    })
+}
+
+/// Name of the architecture with the given Mach-O CPU type
+fn arch_name(cpu_type: u32) -> String {
+    const ABI64: u32 = 0x0100_0000;
+    const ABI64_32: u32 = 0x0200_0000;
+
+    match cpu_type {
+        7 => "i386".to_owned(),
+        0x0100_0007 => "x86_64".to_owned(),
+        12 => "arm".to_owned(),
+        0x0100_000c => "arm64".to_owned(),
+        0x0200_000c => "arm64_32".to_owned(),
+        18 => "ppc".to_owned(),
+        0x0100_0012 => "ppc64".to_owned(),
+        other => format!(
+            "cpu-type-{}{}",
+            other & !(ABI64 | ABI64_32),
+            if other & ABI64 != 0 { "-64" } else { "" }
+        ),
+    }
+}
+
+/// Read a big-endian `u32` at the given offset
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Read a big-endian `u64` at the given offset
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let high = read_u32(data, offset)?;
+    let low = read_u32(data, offset + 4)?;
+    Some(u64::from(high) << 32 | u64::from(low))
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "binary-scanning")]
+use rustsec::platforms::target::Arch;
+
 /// A shim around `binfarce::Format` so that `binfarce` crate could be an optional dependency
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum BinaryFormat {
@@ -5,9 +8,27 @@ pub enum BinaryFormat {
     Elf64,
     Macho,
     PE,
+    Wasm,
     Unknown,
 }
 
+impl BinaryFormat {
+    /// Detect the format of a binary from its contents
+    #[cfg(feature = "binary-scanning")]
+    pub fn detect(data: &[u8]) -> Self {
+        // WebAssembly modules start with `\0asm` followed by the version
+        if data.starts_with(b"\0asm\x01\0\0\0") {
+            BinaryFormat::Wasm
+        } else if crate::binary_fat::slices(data).is_some() {
+            BinaryFormat::Macho
+        } else if let Some(format) = crate::binary_static_lib::format(data) {
+            format
+        } else {
+            binfarce::detect_format(data).into()
+        }
+    }
+}
+
 #[cfg(feature = "binary-scanning")]
 impl From<binfarce::Format> for BinaryFormat {
     fn from(value: binfarce::Format) -> Self {
@@ -20,3 +41,103 @@ impl From<binfarce::Format> for BinaryFormat {
         }
     }
 }
+
+/// Detect the CPU architecture a binary was built for from its header. For
+/// static libraries, the architecture of the first object file is used.
+#[cfg(feature = "binary-scanning")]
+pub fn detect_arch(data: &[u8]) -> Option<Arch> {
+    if data.starts_with(b"\0asm") {
+        return Some(Arch::Wasm32);
+    }
+
+    if let Some(Ok(objects)) = crate::binary_static_lib::objects(data) {
+        return objects.first().and_then(|object| detect_arch(object.data));
+    }
+
+    if data.starts_with(b"\x7fELF") {
+        elf_arch(data)
+    } else if data.starts_with(b"MZ") {
+        pe_arch(data)
+    } else {
+        macho_arch(data)
+    }
+}
+
+/// Architecture of an ELF binary, from `e_machine`
+#[cfg(feature = "binary-scanning")]
+fn elf_arch(data: &[u8]) -> Option<Arch> {
+    let is_64 = *data.get(4)? == 2;
+    let big_endian = *data.get(5)? == 2;
+    let machine = data.get(18..20)?;
+    let machine = if big_endian {
+        u16::from_be_bytes([machine[0], machine[1]])
+    } else {
+        u16::from_le_bytes([machine[0], machine[1]])
+    };
+
+    Some(match machine {
+        3 => Arch::X86,
+        62 => Arch::X86_64,
+        40 => Arch::Arm,
+        183 => Arch::AArch64,
+        8 if is_64 => Arch::Mips64,
+        8 => Arch::Mips,
+        20 => Arch::PowerPc,
+        21 => Arch::PowerPc64,
+        22 => Arch::S390X,
+        2 | 18 => Arch::Sparc,
+        43 => Arch::Sparc64,
+        243 if is_64 => Arch::Riscv64,
+        243 => Arch::Riscv32,
+        258 => Arch::Loongarch64,
+        _ => return None,
+    })
+}
+
+/// Architecture of a PE binary, from the machine type in the COFF header
+#[cfg(feature = "binary-scanning")]
+fn pe_arch(data: &[u8]) -> Option<Arch> {
+    let offset = data.get(0x3c..0x40)?;
+    let offset = u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]]) as usize;
+    let header = data.get(offset..offset.checked_add(6)?)?;
+
+    if !header.starts_with(b"PE\0\0") {
+        return None;
+    }
+
+    Some(match u16::from_le_bytes([header[4], header[5]]) {
+        0x014c => Arch::X86,
+        0x8664 => Arch::X86_64,
+        0x01c0 | 0x01c4 => Arch::Arm,
+        0xaa64 => Arch::AArch64,
+        0xa641 => Arch::Arm64ec,
+        _ => return None,
+    })
+}
+
+/// Architecture of a (thin) Mach-O binary, from its CPU type
+#[cfg(feature = "binary-scanning")]
+fn macho_arch(data: &[u8]) -> Option<Arch> {
+    let magic = data.get(..4)?;
+    let cpu_type = data.get(4..8)?;
+    let cpu_type = match magic {
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf] => {
+            u32::from_be_bytes([cpu_type[0], cpu_type[1], cpu_type[2], cpu_type[3]])
+        }
+        [0xce | 0xcf, 0xfa, 0xed, 0xfe] => {
+            u32::from_le_bytes([cpu_type[0], cpu_type[1], cpu_type[2], cpu_type[3]])
+        }
+        _ => return None,
+    };
+
+    Some(match cpu_type {
+        7 => Arch::X86,
+        0x0100_0007 => Arch::X86_64,
+        12 => Arch::Arm,
+        // `arm64_32` (watchOS) targets also have `target_arch = "aarch64"`
+        0x0100_000c | 0x0200_000c => Arch::AArch64,
+        18 => Arch::PowerPc,
+        0x0100_0012 => Arch::PowerPc64,
+        _ => return None,
+    })
+}
@@ -0,0 +1,243 @@
+//! Reads the object files in static libraries and rlibs, which are `ar`
+//! archives, so Rust code shipped as a static library can be audited.
+
+use crate::binary_format::BinaryFormat;
+
+/// Magic number of `ar` archives
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+
+/// Size of the header preceding each archive member
+const HEADER_SIZE: usize = 60;
+
+/// Members holding symbol tables and the table of long names rather than
+/// object files, in the GNU and BSD variants of the format
+const SPECIAL_MEMBERS: &[&str] = &["/", "//", "/SYM64/", "__.SYMDEF", "__.SYMDEF SORTED"];
+
+/// Object file in a static library
+pub struct Object<'a> {
+    /// Name of the archive member
+    pub name: String,
+
+    /// Contents of the object file
+    pub data: &'a [u8],
+}
+
+/// Get the object files in a static library (`.a`, or `.lib` for the GNU
+/// toolchain on Windows) or rlib. Returns `None` if the data isn't an `ar`
+/// archive.
+///
+/// Only object files are returned: other members, like the metadata in rlibs,
+/// can't contain dependency information. Members are recognized by their
+/// magic bytes alone, so archives nested in the archive aren't descended into.
+pub fn objects(data: &[u8]) -> Option<Result<Vec<Object<'_>>, &'static str>> {
+    // Debian packages are `ar` archives too, and are extracted separately
+    if !data.starts_with(AR_MAGIC) || data[AR_MAGIC.len()..].starts_with(b"debian-binary") {
+        return None;
+    }
+
+    let mut objects = vec![];
+    let mut long_names: &[u8] = &[];
+    let mut offset = AR_MAGIC.len();
+
+    while offset + HEADER_SIZE <= data.len() {
+        let header = &data[offset..offset + HEADER_SIZE];
+        let size: usize = match std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse().ok())
+        {
+            Some(size) => size,
+            None => return Some(Err("invalid member header")),
+        };
+
+        let start = offset + HEADER_SIZE;
+        let end = match start.checked_add(size) {
+            Some(end) => end,
+            None => return Some(Err("invalid member size")),
+        };
+        let member = match data.get(start..end) {
+            Some(member) => member,
+            None => return Some(Err("truncated member")),
+        };
+
+        // Members are aligned to even offsets
+        offset = end + size % 2;
+
+        let raw_name = String::from_utf8_lossy(&header[..16]);
+        let raw_name = raw_name.trim_end();
+
+        if raw_name == "//" {
+            long_names = member;
+            continue;
+        }
+
+        if SPECIAL_MEMBERS.contains(&raw_name) || raw_name.starts_with("__.SYMDEF") {
+            continue;
+        }
+
+        let (name, contents) = match member_name(raw_name, member, long_names) {
+            Some(name_and_contents) => name_and_contents,
+            None => return Some(Err("invalid member name")),
+        };
+
+        if object_format(contents).is_some() {
+            objects.push(Object {
+                name,
+                data: contents,
+            });
+        }
+    }
+
+    Some(Ok(objects))
+}
+
+/// Format of the first object file in a static library, if any
+pub fn format(data: &[u8]) -> Option<BinaryFormat> {
+    let objects = objects(data)?.ok()?;
+    objects
+        .first()
+        .and_then(|object| object_format(object.data))
+}
+
+/// Format of an object file, detected from its magic bytes only
+fn object_format(data: &[u8]) -> Option<BinaryFormat> {
+    match BinaryFormat::from(binfarce::detect_format(data)) {
+        BinaryFormat::Unknown => None,
+        format => Some(format),
+    }
+}
+
+/// Resolve the name of a member, returning it along with the contents of the
+/// member without the name, which precedes them in BSD archives
+fn member_name<'a>(
+    raw_name: &str,
+    member: &'a [u8],
+    long_names: &[u8],
+) -> Option<(String, &'a [u8])> {
+    // BSD: `#1/<length>`, with the name at the start of the member
+    if let Some(length) = raw_name.strip_prefix("#1/") {
+        let length: usize = length.parse().ok()?;
+        let name = member.get(..length)?;
+        let name = String::from_utf8_lossy(name)
+            .trim_end_matches('\0')
+            .to_owned();
+        return Some((name, &member[length..]));
+    }
+
+    // GNU: `/<offset>` into the table of long names, where names end with `/\n`
+    if let Some(name_offset) = raw_name.strip_prefix('/') {
+        let name_offset: usize = name_offset.parse().ok()?;
+        let rest = long_names.get(name_offset..)?;
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .unwrap_or(rest.len());
+        let name = String::from_utf8_lossy(&rest[..end])
+            .trim_end_matches('/')
+            .to_owned();
+        return Some((name, member));
+    }
+
+    // GNU names end with `/`, which BSD names don't
+    Some((raw_name.trim_end_matches('/').to_owned(), member))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start of a 64-bit little-endian ELF object file
+    const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0";
+
+    /// Build an `ar` archive from member names (as written in the header) and
+    /// contents
+    fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = AR_MAGIC.to_vec();
+
+        for (name, contents) in members {
+            let header = format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                0,
+                0,
+                644,
+                contents.len()
+            );
+            assert_eq!(header.len(), HEADER_SIZE);
+            data.extend_from_slice(header.as_bytes());
+            data.extend_from_slice(contents);
+
+            if contents.len() % 2 == 1 {
+                data.push(b'\n');
+            }
+        }
+
+        data
+    }
+
+    fn names(data: &[u8]) -> Vec<String> {
+        objects(data)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|object| object.name)
+            .collect()
+    }
+
+    #[test]
+    fn gnu_long_names() {
+        let data = archive(&[
+            ("/", b"symbols"),
+            (
+                "//",
+                b"a_rather_long_object_name.o/\nanother_long_object_name.o/\n",
+            ),
+            ("/0", ELF),
+            ("/29", ELF),
+            ("short.o/", ELF),
+            ("lib.rmeta/", b"metadata"),
+        ]);
+
+        assert_eq!(
+            names(&data),
+            [
+                "a_rather_long_object_name.o",
+                "another_long_object_name.o",
+                "short.o"
+            ]
+        );
+    }
+
+    #[test]
+    fn bsd_long_names() {
+        let mut member = b"a_rather_long_object_name.o\0".to_vec();
+        member.extend_from_slice(ELF);
+
+        let data = archive(&[("__.SYMDEF SORTED", b"symbols"), ("#1/28", &member)]);
+        let objects = objects(&data).unwrap().unwrap();
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, "a_rather_long_object_name.o");
+        assert_eq!(objects[0].data, ELF);
+    }
+
+    #[test]
+    fn nested_archives_are_skipped() {
+        let mut data = archive(&[("inner.o/", ELF)]);
+        for _ in 0..100 {
+            data = archive(&[("nested.a/", &data)]);
+        }
+
+        assert!(names(&data).is_empty());
+        assert_eq!(format(&data), None);
+    }
+
+    #[test]
+    fn invalid_member_size() {
+        let mut data = archive(&[("object.o/", ELF)]);
+        let size = format!("{:<10}", usize::MAX);
+        data[AR_MAGIC.len() + 48..AR_MAGIC.len() + 58].copy_from_slice(size.as_bytes());
+
+        assert!(objects(&data).unwrap().is_err());
+    }
+}
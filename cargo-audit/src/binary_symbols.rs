@@ -0,0 +1,199 @@
+//! Downgrades vulnerabilities whose affected functions were never compiled
+//! into a binary, by cross-referencing `affected.functions` of advisories
+//! against the demangled names in the binary's symbol table.
+//!
+//! This is a heuristic: functions which were inlined into all of their
+//! callers don't have a symbol of their own, so such vulnerabilities are
+//! downgraded to `uncompiled` warnings rather than dropped from the report.
+
+use std::collections::BTreeMap;
+
+use binfarce::{demangle::SymbolData, Format};
+use rustsec::{advisory::FunctionPath, Warning, WarningKind};
+
+/// Paths of the Rust functions in a binary's symbol table, by crate
+pub struct Symbols {
+    paths: BTreeMap<String, Vec<Vec<String>>>,
+}
+
+impl Symbols {
+    /// Load the symbols from the contents of a binary file. Returns `None`
+    /// if the binary has no symbol table with Rust symbols, e.g. because it
+    /// has been stripped.
+    pub fn load(data: &[u8]) -> rustsec::Result<Option<Self>> {
+        let symbols = match binfarce::detect_format(data) {
+            Format::Elf32 { byte_order } => {
+                binfarce::elf32::parse(data, byte_order).and_then(|elf| elf.symbols(".text"))
+            }
+            Format::Elf64 { byte_order } => {
+                binfarce::elf64::parse(data, byte_order).and_then(|elf| elf.symbols(".text"))
+            }
+            Format::Macho => binfarce::macho::parse(data).and_then(|macho| macho.symbols()),
+            Format::PE => binfarce::pe::parse(data).and_then(|pe| pe.symbols()),
+            Format::Unknown => return Ok(None),
+        };
+
+        // A missing symbol table is not an error: stripped binaries are common
+        match symbols {
+            Ok((symbols, _text_size)) => Ok(Self::from_symbols(&symbols)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Collect the paths of the Rust symbols
+    fn from_symbols(symbols: &[SymbolData]) -> Option<Self> {
+        let mut paths: BTreeMap<String, Vec<Vec<String>>> = BTreeMap::new();
+
+        for symbol in symbols {
+            let segments = path_segments(&normalize(&symbol.name.trimmed));
+            if segments.len() < 2 {
+                continue;
+            }
+            paths
+                .entry(segments[0].clone())
+                .or_default()
+                .push(segments[1..].to_vec());
+        }
+
+        if paths.is_empty() {
+            None
+        } else {
+            Some(Self { paths })
+        }
+    }
+
+    /// Is there a symbol for the given function, or for a method of the given
+    /// type? Re-exports mean advisories don't necessarily use the path a
+    /// function is defined at, so the segments of the advisory's path only
+    /// need to appear in the symbol's path in the same order.
+    pub fn contains(&self, function: &FunctionPath) -> bool {
+        let crate_name = function.crate_name().replace('-', "_");
+        let wanted: Vec<&str> = function.iter().skip(1).map(|id| id.as_str()).collect();
+
+        self.paths.get(&crate_name).map_or(false, |paths| {
+            paths.iter().any(|path| {
+                let mut segments = path.iter();
+                wanted
+                    .iter()
+                    .all(|wanted| segments.any(|segment| segment == wanted))
+            })
+        })
+    }
+}
+
+/// Move vulnerabilities whose affected functions have no symbol in the binary
+/// to `uncompiled` warnings. Vulnerabilities without information about the
+/// affected functions are left as they are.
+pub fn filter_report_by_symbols(symbols: &Symbols, report: &mut rustsec::Report) {
+    let vulns = &mut report.vulnerabilities;
+    let (compiled, uncompiled): (Vec<_>, Vec<_>) =
+        vulns
+            .list
+            .drain(..)
+            .partition(|vuln| match vuln.affected_functions() {
+                Some(functions) if !functions.is_empty() => {
+                    functions.iter().any(|function| symbols.contains(function))
+                }
+                _ => true,
+            });
+
+    vulns.list = compiled;
+    vulns.count = vulns.list.len();
+    vulns.found = !vulns.list.is_empty();
+
+    if !uncompiled.is_empty() {
+        report
+            .warnings
+            .entry(WarningKind::Uncompiled)
+            .or_default()
+            .extend(uncompiled.into_iter().map(Warning::uncompiled));
+    }
+}
+
+/// Normalize a demangled symbol name into a plain path, dropping generic
+/// arguments and trait qualifications, e.g.
+/// `<smallvec::SmallVec<A> as core::ops::drop::Drop>::drop` becomes
+/// `smallvec::SmallVec::drop`
+fn normalize(symbol: &str) -> String {
+    let stripped = strip_generic_args(symbol);
+    unqualify(&stripped)
+}
+
+/// Remove the generic arguments following path segments, including turbofish
+fn strip_generic_args(symbol: &str) -> String {
+    let mut out = String::with_capacity(symbol.len());
+    let mut depth = 0usize;
+    let mut prev = None;
+
+    for c in symbol.chars() {
+        if depth > 0 {
+            match c {
+                '<' => depth += 1,
+                '>' if prev != Some('-') => depth -= 1,
+                _ => (),
+            }
+        } else if c == '<' && out.ends_with(|p: char| p.is_alphanumeric() || p == '_') {
+            depth = 1;
+        } else if c == '<' && out.ends_with("::") {
+            out.truncate(out.len() - 2);
+            depth = 1;
+        } else {
+            out.push(c);
+        }
+        prev = Some(c);
+    }
+
+    out
+}
+
+/// Replace a leading `<Type as Trait>` or `<Type>` with `Type`
+fn unqualify(path: &str) -> String {
+    if !path.starts_with('<') {
+        return path.to_owned();
+    }
+
+    let mut depth = 0usize;
+    let mut prev = None;
+    for (i, c) in path.char_indices() {
+        let arrow = prev == Some('-');
+        prev = Some(c);
+        match c {
+            '<' => depth += 1,
+            '>' if !arrow => {
+                depth -= 1;
+                if depth == 0 {
+                    let inner = &path[1..i];
+                    let self_ty = top_level_split_as(inner);
+                    return format!("{}{}", unqualify(self_ty), &path[i + 1..]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    path.to_owned()
+}
+
+/// Get the `Type` of `Type as Trait`, ignoring `as` nested in angle brackets
+fn top_level_split_as(qualified: &str) -> &str {
+    let mut depth = 0usize;
+    for (i, c) in qualified.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            ' ' if depth == 0 && qualified[i..].starts_with(" as ") => return &qualified[..i],
+            _ => (),
+        }
+    }
+    qualified
+}
+
+/// Split a path into its segments, e.g. `&mut foo::Bar` into `foo` and `Bar`
+fn path_segments(path: &str) -> Vec<String> {
+    path.trim_start_matches(|c: char| c == '&' || c == '*' || c.is_whitespace())
+        .trim_start_matches("mut ")
+        .trim_start_matches("const ")
+        .split("::")
+        .map(ToOwned::to_owned)
+        .collect()
+}
@@ -0,0 +1,65 @@
+//! Detects the version of the Rust toolchain a binary was built with, so
+//! vulnerabilities in the standard library compiled into it can be reported.
+
+use cargo_lock::Package;
+use rustsec::Version;
+
+/// Marker preceding the compiler version in the `.comment` section of ELF
+/// binaries, e.g. `rustc version 1.75.0 (82e1608df 2023-12-21)`
+const RUSTC_VERSION_MARKER: &[u8] = b"rustc version ";
+
+/// Name of the package advisories about the standard library are filed under
+const STD_PACKAGE: &str = "std";
+
+/// Find the version of `rustc` recorded in a binary, if any.
+///
+/// `cargo auditable` doesn't record the compiler version, so this relies on
+/// the `.comment` section `rustc` writes to ELF binaries. Binaries linked
+/// from objects built by several compilers may record several versions, in
+/// which case the oldest one is used.
+pub fn rustc_version(data: &[u8]) -> Option<Version> {
+    let mut oldest: Option<Version> = None;
+
+    for start in find_all(data, RUSTC_VERSION_MARKER) {
+        let rest = &data[start + RUSTC_VERSION_MARKER.len()..];
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b' ' || byte == 0)
+            .unwrap_or(rest.len());
+
+        let version = match std::str::from_utf8(&rest[..end])
+            .ok()
+            .and_then(|version| Version::parse(version).ok())
+        {
+            Some(version) => version,
+            None => continue,
+        };
+
+        if oldest.as_ref().map_or(true, |oldest| version < *oldest) {
+            oldest = Some(version);
+        }
+    }
+
+    oldest
+}
+
+/// Package standing for the standard library of the given toolchain
+pub fn std_package(rustc_version: Version) -> Package {
+    Package {
+        name: STD_PACKAGE.parse().unwrap(),
+        version: rustc_version,
+        source: None,
+        checksum: None,
+        dependencies: Vec::new(),
+        replace: None,
+    }
+}
+
+/// Offsets of all occurrences of `needle` in `haystack`
+fn find_all<'a>(haystack: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == needle)
+        .map(|(offset, _)| offset)
+}
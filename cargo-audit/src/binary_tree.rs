@@ -0,0 +1,209 @@
+//! Discovers the executables under directory trees, e.g. the output of a
+//! release build, and loads them in parallel so they can be audited together
+//! and presented in one aggregated report.
+
+use std::{
+    collections::BTreeMap as Map,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use rustsec::{Error, ErrorKind, Lockfile, Report};
+use serde::Serialize;
+
+use crate::{binary_format::BinaryFormat, error::display_err_with_source};
+
+/// Binaries loaded from a file, along with their contents
+pub type Loaded = rustsec::Result<Vec<(PathBuf, Vec<u8>)>>;
+
+/// Aggregated report for all of the binaries found under directory trees
+#[derive(Debug, Default, Serialize)]
+pub struct TreeReport {
+    /// Outcome of the audit of each binary, by path
+    pub binaries: Map<String, BinaryOutcome>,
+
+    /// Number of binaries and findings across all binaries
+    pub summary: TreeSummary,
+}
+
+/// Outcome of the audit of a single binary
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BinaryOutcome {
+    /// The binary was audited
+    Scanned {
+        /// Report for the binary
+        report: Report,
+
+        /// Dependencies recovered from the binary
+        #[serde(skip)]
+        lockfile: Lockfile,
+    },
+
+    /// The binary couldn't be audited
+    Error {
+        /// Description of the error
+        message: String,
+    },
+}
+
+/// Counts of binaries and findings in a [`TreeReport`]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct TreeSummary {
+    /// Number of binaries found
+    pub binaries: usize,
+
+    /// Number of binaries with vulnerabilities
+    pub vulnerable: usize,
+
+    /// Number of binaries with warnings, whether or not they are vulnerable
+    pub with_warnings: usize,
+
+    /// Number of binaries without vulnerabilities or warnings
+    pub clean: usize,
+
+    /// Number of binaries which couldn't be audited
+    pub errors: usize,
+
+    /// Number of vulnerabilities across all binaries
+    pub vulnerabilities: usize,
+
+    /// Number of warnings across all binaries
+    pub warnings: usize,
+}
+
+impl TreeReport {
+    /// Add the outcome of the audit of a binary
+    pub fn add(&mut self, path: &Path, outcome: rustsec::Result<(Lockfile, Report)>) {
+        let summary = &mut self.summary;
+        summary.binaries += 1;
+
+        let outcome = match outcome {
+            Ok((lockfile, report)) => {
+                let warnings: usize = report.warnings.values().map(Vec::len).sum();
+
+                summary.vulnerabilities += report.vulnerabilities.count;
+                summary.warnings += warnings;
+
+                if report.vulnerabilities.found {
+                    summary.vulnerable += 1;
+                }
+
+                if warnings > 0 {
+                    summary.with_warnings += 1;
+                } else if !report.vulnerabilities.found {
+                    summary.clean += 1;
+                }
+
+                BinaryOutcome::Scanned { report, lockfile }
+            }
+            Err(e) => {
+                summary.errors += 1;
+                BinaryOutcome::Error {
+                    message: display_err_with_source(&e),
+                }
+            }
+        };
+
+        self.binaries.insert(path.display().to_string(), outcome);
+    }
+}
+
+/// Find the files under a directory, sorted by path. Symbolic links aren't
+/// followed, so binaries linked from several places are only audited once.
+/// A path to a file is returned as is.
+pub fn discover(root: &Path) -> rustsec::Result<Vec<PathBuf>> {
+    if !root.is_dir() {
+        return Ok(vec![root.to_owned()]);
+    }
+
+    let mut files = vec![];
+    let mut dirs = vec![root.to_owned()];
+
+    while let Some(dir) = dirs.pop() {
+        let read_error = |e: std::io::Error| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't read directory {}", dir.display()),
+                e,
+            )
+        };
+
+        for entry in std::fs::read_dir(&dir).map_err(read_error)? {
+            let entry = entry.map_err(read_error)?;
+            let file_type = entry.file_type().map_err(read_error)?;
+
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Load the executables in the given files on a pool of worker threads,
+/// passing each file's binaries to `consume` on the current thread as soon as
+/// they're loaded. Files which aren't executables (or archives of them) have
+/// no binaries.
+pub fn load_parallel(files: &[PathBuf], mut consume: impl FnMut(&Path, Loaded)) {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(files.len())
+        .max(1);
+
+    let next = AtomicUsize::new(0);
+
+    // Bounding the channel limits the number of loaded binaries kept in
+    // memory while waiting to be audited
+    let (sender, receiver) = mpsc::sync_channel(workers);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let next = &next;
+
+            scope.spawn(move || {
+                while let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if sender.send((path, load_executables(path))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        drop(sender);
+
+        for (path, loaded) in receiver {
+            consume(path, loaded);
+        }
+    });
+}
+
+/// Load the executables in a file: the file itself if it's an executable, or
+/// the executables inside it if it's an archive
+fn load_executables(path: &Path) -> Loaded {
+    let data = std::fs::read(path)?;
+
+    if BinaryFormat::detect(&data) != BinaryFormat::Unknown {
+        return Ok(vec![(path.to_owned(), data)]);
+    }
+
+    #[cfg(feature = "archive-scanning")]
+    if let Some(entries) = crate::binary_archive::executables(path, &data)? {
+        return Ok(entries
+            .into_iter()
+            .map(|entry| (entry.path, entry.contents))
+            .collect());
+    }
+
+    Ok(vec![])
+}
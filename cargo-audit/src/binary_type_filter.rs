@@ -1,15 +1,18 @@
 //! Filters a `rustsec::Report` to remove advisories not applicable to the given binary type.
 //! For example, Windows-only advisories should not be reported for ELF files.
 
-use std::collections::BTreeSet;
-use std::str::FromStr;
-
-use once_cell::sync::OnceCell;
-use rustsec::platforms::{platform::PlatformReq, OS};
+use rustsec::platforms::{target::Arch, ObjectFormat, OS};
 
 use crate::binary_format::BinaryFormat;
 
-pub fn filter_report_by_binary_type(binary_type: &BinaryFormat, report: &mut rustsec::Report) {
+/// Remove the vulnerabilities and warnings (unmaintained, unsound, etc.)
+/// whose advisories only affect operating systems which can't run binaries
+/// of the given format, or only other architectures than the binary's
+pub fn filter_report_by_binary_type(
+    binary_type: &BinaryFormat,
+    binary_arch: Option<Arch>,
+    report: &mut rustsec::Report,
+) {
     // Filter vulnerabilities
     let vulns = &mut report.vulnerabilities;
     assert_eq!(
@@ -19,56 +22,80 @@ pub fn filter_report_by_binary_type(binary_type: &BinaryFormat, report: &mut rus
     );
     vulns
         .list
-        .retain(|vuln| advisory_applicable_to_binary(binary_type, &vuln.affected));
+        .retain(|vuln| advisory_applicable_to_binary(binary_type, binary_arch, &vuln.affected));
     vulns.count = vulns.list.len();
     vulns.found = !vulns.list.is_empty();
 
-    // Filter warnings
+    // Filter warnings, dropping the kinds with no warnings left so they
+    // aren't reported as empty lists
     let warns = &mut report.warnings;
     warns.iter_mut().for_each(|(_kind, warnings)| {
-        warnings.retain(|w| advisory_applicable_to_binary(binary_type, &w.affected))
+        warnings.retain(|w| advisory_applicable_to_binary(binary_type, binary_arch, &w.affected))
     });
+    warns.retain(|_kind, warnings| !warnings.is_empty());
 }
 
 fn advisory_applicable_to_binary(
     binary_type: &BinaryFormat,
+    binary_arch: Option<Arch>,
     affected: &Option<rustsec::advisory::Affected>,
 ) -> bool {
     if let Some(affected) = affected {
-        if affected.os.is_empty() {
-            true // all platforms are affected if the "os" list is empty
-        } else {
-            at_least_one_os_runs_binary(binary_type, &affected.os)
-        }
+        // all platforms are affected if the "os" or "arch" list is empty
+        let os_applicable =
+            affected.os.is_empty() || at_least_one_os_runs_binary(binary_type, &affected.os);
+        let arch_applicable = match binary_arch {
+            Some(arch) => affected.arch.is_empty() || affected.arch.contains(&arch),
+            None => true, // the architecture couldn't be read from the header
+        };
+        os_applicable && arch_applicable
     } else {
         true // all platforms are affected if "affected" section is not specified in the TOML
     }
 }
 
 fn at_least_one_os_runs_binary(binary_type: &BinaryFormat, os_list: &[OS]) -> bool {
+    os_list.iter().any(|&os| os_runs_binary(os, binary_type))
+}
+
+/// Can binaries of the given format run on the OS? Advisories for operating
+/// systems whose binary format isn't known are assumed to apply.
+fn os_runs_binary(os: OS, binary_type: &BinaryFormat) -> bool {
     use BinaryFormat::*;
+    let format = match os.object_format() {
+        Some(format) => format,
+        None => return true,
+    };
+
     match binary_type {
-        PE => os_list.contains(&OS::Windows),
-        Macho => os_list.iter().any(|os| apple_OSs().contains(os)), // O(n*log(n))
-        Elf32 | Elf64 => {
-            // For now we'll assume it's affected if the list contains something other than Windows or Apple OSs
-            os_list
-                .iter()
-                .any(|os| os != &OS::Windows && !apple_OSs().contains(os))
-            // TODO: this could be improved if we somehow keep track of which OS uses elf and which doesn't.
-            // Sadly `rustc --print-cfg` doesn't expose this information.
-            // Perhaps we can make `platforms` expose the `family` which can be `windows` or `unix` or `unknown`?
-            // That way we can capture all the unix-likes as using ELF and discard everything else
-        }
+        Elf32 | Elf64 => format == ObjectFormat::Elf,
+        PE => format == ObjectFormat::Pe,
+        Macho => format == ObjectFormat::MachO,
+        Wasm => format == ObjectFormat::Wasm,
         Unknown => true, // might be possible for detection based on panic messages?
     }
 }
 
-#[allow(non_snake_case)]
-fn apple_OSs() -> &'static BTreeSet<OS> {
-    static INSTANCE: OnceCell<BTreeSet<OS>> = OnceCell::new();
-    INSTANCE.get_or_init(|| {
-        let req = PlatformReq::from_str("*apple*").unwrap();
-        req.matching_platforms().map(|p| p.target_os).collect()
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_runs_binary_of_its_format() {
+        assert!(os_runs_binary(OS::Linux, &BinaryFormat::Elf64));
+        assert!(os_runs_binary(OS::None, &BinaryFormat::Elf32));
+        assert!(os_runs_binary(OS::Windows, &BinaryFormat::PE));
+        assert!(os_runs_binary(OS::MacOS, &BinaryFormat::Macho));
+        assert!(os_runs_binary(OS::Wasi, &BinaryFormat::Wasm));
+
+        assert!(!os_runs_binary(OS::Wasi, &BinaryFormat::Elf64));
+        assert!(!os_runs_binary(OS::Windows, &BinaryFormat::Macho));
+        assert!(!os_runs_binary(OS::Linux, &BinaryFormat::Wasm));
+    }
+
+    #[test]
+    fn unknown_formats_are_assumed_to_run() {
+        assert!(os_runs_binary(OS::Unknown, &BinaryFormat::Elf64));
+        assert!(os_runs_binary(OS::Linux, &BinaryFormat::Unknown));
+    }
 }
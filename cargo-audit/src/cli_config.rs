@@ -5,11 +5,12 @@ use std::path::PathBuf;
 use abscissa_core::error::context::Context;
 use abscissa_core::error::framework::FrameworkErrorKind;
 use abscissa_core::FrameworkError;
+use clap::Args;
 use rustsec::platforms::target::{Arch, OS};
 
-use crate::config::{AuditConfig, DenyOption, OutputFormat};
+use crate::config::{AuditConfig, DenyOption, GroupBy, OsvMode, OutputFormat, TargetConfig};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CliConfig {
     /// Filesystem path to the advisory database git repository
     pub db: Option<PathBuf>,
@@ -26,15 +27,27 @@ pub struct CliConfig {
     /// Skip fetching the advisory database git repository
     pub no_fetch: bool,
 
+    /// Don't use the cached snapshot of the parsed advisory database
+    pub no_cache: bool,
+
+    /// Use the advisory database snapshot embedded in the binary
+    pub embedded_db: bool,
+
     /// Allow stale advisory databases that haven't been recently updated
     pub stale: bool,
 
+    /// Fail if the advisory database can't be fetched
+    pub require_fresh_db: bool,
+
     /// Target CPU architecture to find vulnerabilities for
     pub target_arch: Option<Arch>,
 
     /// Target OS to find vulnerabilities for
     pub target_os: Option<OS>,
 
+    /// Target triple to find vulnerabilities for
+    pub target: Option<String>,
+
     /// URL to the advisory database git repository
     pub url: Option<String>,
 
@@ -43,6 +56,95 @@ pub struct CliConfig {
 
     /// Output reports as JSON
     pub output_json: bool,
+
+    /// Output format
+    pub output_format: Option<OutputFormat>,
+
+    /// Show release information for affected packages
+    pub release_info: bool,
+
+    /// Suggest upgrades resolving vulnerabilities
+    pub suggest_upgrades: bool,
+
+    /// Group vulnerabilities in several versions of the same crate
+    pub group_versions: bool,
+
+    /// Only print the number of findings
+    pub summary: bool,
+
+    /// Group findings in the terminal output
+    pub group_by: Option<GroupBy>,
+
+    /// Treat soft conditions as errors
+    pub strict: bool,
+
+    /// Warn for crates which have been superseded by another crate
+    pub deprecated: bool,
+
+    /// Report withdrawn advisories
+    pub include_withdrawn: bool,
+
+    /// Downgrade vulnerabilities whose affected functions aren't in the binary
+    pub filter_symbols: bool,
+
+    /// Files and directories with separate debug info of binaries
+    pub debug_info: Vec<PathBuf>,
+
+    /// Report vulnerabilities regardless of enabled crate features
+    pub ignore_features: bool,
+
+    /// Exclude findings for dev-only dependencies
+    pub exclude_dev: bool,
+
+    /// Check patched versions against the requirements in the manifests
+    pub check_requirements: bool,
+
+    /// Query osv.dev for advisories
+    pub osv: Option<OsvMode>,
+
+    /// Include GitHub Security Advisories
+    pub ghsa: bool,
+
+    /// Enrich advisories with data from NVD
+    pub nvd: bool,
+
+    /// Directory of recorded API responses
+    pub vcr: Option<PathBuf>,
+
+    /// Record API responses instead of replaying them
+    pub vcr_record: bool,
+
+    /// Timeouts and retries for network requests
+    pub network: NetworkArgs,
+}
+
+/// Network options shared by the subcommands which fetch the advisory
+/// database or the crates.io index
+#[derive(Args, Clone, Debug, Default)]
+pub struct NetworkArgs {
+    /// Timeout for establishing connections, in seconds
+    #[arg(
+        long = "connect-timeout",
+        value_name = "SECS",
+        help = "Give up connecting to servers after SECS seconds"
+    )]
+    pub connect_timeout: Option<u64>,
+
+    /// Timeout for each request as a whole, in seconds
+    #[arg(
+        long = "request-timeout",
+        value_name = "SECS",
+        help = "Give up on requests which take longer than SECS seconds"
+    )]
+    pub request_timeout: Option<u64>,
+
+    /// Number of times failed requests are retried
+    #[arg(
+        long = "retries",
+        value_name = "N",
+        help = "Retry failed network requests up to N times, with exponential backoff"
+    )]
+    pub retries: Option<u32>,
 }
 
 // we cannot `impl Override<AuditConfig>` because this struct does not implement `abscissa::Command`
@@ -61,8 +163,42 @@ impl CliConfig {
         }
 
         config.advisories.ignore_source |= self.ignore_source;
+        config.advisories.deprecated_warnings |= self.deprecated;
+        config.advisories.include_withdrawn |= self.include_withdrawn;
+        config.advisories.filter_symbols |= self.filter_symbols;
+        config
+            .advisories
+            .debug_info
+            .extend(self.debug_info.iter().cloned());
+        config.advisories.ignore_features |= self.ignore_features;
+        config.advisories.exclude_dev |= self.exclude_dev;
+        config.advisories.check_requirements |= self.check_requirements;
         config.database.fetch |= !self.no_fetch;
+        if self.no_cache {
+            config.database.cache = Some(false);
+        }
+        config.database.embedded |= self.embedded_db;
         config.database.stale |= self.stale;
+        config.database.require_fresh |= self.require_fresh_db;
+        config.database.ghsa |= self.ghsa;
+        config.database.nvd |= self.nvd;
+        config.database.vcr_record |= self.vcr_record;
+
+        // A target triple given on the command line replaces the whole target
+        // from `audit.toml`, while `--target-arch` and `--target-os` still win
+        if let Some(target) = &self.target {
+            if rustsec::platforms::Platform::find(target).is_none() {
+                return Err(Context::new(
+                    FrameworkErrorKind::ParseError,
+                    Some(format!("unknown target triple: {}", target).into()),
+                )
+                .into());
+            }
+            config.target = TargetConfig {
+                triple: Some(target.clone()),
+                ..Default::default()
+            };
+        }
 
         if let Some(target_arch) = self.target_arch {
             config.target.arch = Some(target_arch);
@@ -76,6 +212,26 @@ impl CliConfig {
             config.database.url = Some(url.clone())
         }
 
+        if let Some(osv) = self.osv {
+            config.database.osv = osv;
+        }
+
+        if let Some(vcr) = &self.vcr {
+            config.database.vcr = Some(vcr.clone());
+        }
+
+        if let Some(secs) = self.network.connect_timeout {
+            config.network.connect_timeout = Some(secs);
+        }
+
+        if let Some(secs) = self.network.request_timeout {
+            config.network.request_timeout = Some(secs);
+        }
+
+        if let Some(retries) = self.network.retries {
+            config.network.retries = retries;
+        }
+
         for kind in &self.deny {
             if *kind == DenyOption::Warnings {
                 config.output.deny = DenyOption::all();
@@ -85,6 +241,18 @@ impl CliConfig {
         }
 
         config.output.quiet |= self.quiet;
+        config.output.release_info |= self.release_info;
+        config.output.suggest_upgrades |= self.suggest_upgrades;
+        config.output.group_versions |= self.group_versions;
+        config.output.summary |= self.summary;
+        if let Some(group_by) = self.group_by {
+            config.output.group_by = Some(group_by);
+        }
+        config.output.strict |= self.strict;
+
+        if let Some(format) = self.output_format {
+            config.output.format = format;
+        }
 
         if self.output_json {
             config.output.format = OutputFormat::Json;
@@ -93,3 +261,56 @@ impl CliConfig {
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(cli: CliConfig, audit_toml: &str) -> TargetConfig {
+        let config = toml::from_str(audit_toml).unwrap();
+        cli.override_config(config).unwrap().target
+    }
+
+    #[test]
+    fn target_triple_overrides_config() {
+        let audit_toml = "[target]\narch = \"x86_64\"\nos = \"windows\"\n";
+        let cli = CliConfig {
+            target: Some("aarch64-apple-darwin".to_owned()),
+            ..Default::default()
+        };
+
+        let target = target(cli, audit_toml);
+        assert_eq!(target.arch(), Some(Arch::AArch64));
+        assert_eq!(target.os(), Some(OS::MacOS));
+    }
+
+    #[test]
+    fn target_arch_and_os_override_triple() {
+        let audit_toml = "[target]\ntriple = \"x86_64-pc-windows-msvc\"\n";
+        let cli = CliConfig {
+            target: Some("aarch64-apple-darwin".to_owned()),
+            target_os: Some(OS::Linux),
+            ..Default::default()
+        };
+        let target = target(cli, audit_toml);
+        assert_eq!(target.arch(), Some(Arch::AArch64));
+        assert_eq!(target.os(), Some(OS::Linux));
+
+        let cli = CliConfig {
+            target_arch: Some(Arch::Arm),
+            ..Default::default()
+        };
+        let target = target(cli, audit_toml);
+        assert_eq!(target.arch(), Some(Arch::Arm));
+        assert_eq!(target.os(), Some(OS::Windows));
+    }
+
+    #[test]
+    fn unknown_target_triple() {
+        let cli = CliConfig {
+            target: Some("no-such-target".to_owned()),
+            ..Default::default()
+        };
+        assert!(cli.override_config(AuditConfig::default()).is_err());
+    }
+}
@@ -3,10 +3,13 @@
 mod audit;
 
 use self::audit::AuditCommand;
-use crate::config::AuditConfig;
-use abscissa_core::{config::Override, Command, Configurable, FrameworkError, Runnable};
+use crate::{config::AuditConfig, config_layers, prelude::*, shared_policy};
+use abscissa_core::{
+    config::Override, error::Context, Command, Configurable, FrameworkError, FrameworkErrorKind,
+    Runnable,
+};
 use clap::Parser;
-use std::{ops::Deref, path::PathBuf};
+use std::{env, ops::Deref, path::PathBuf};
 
 /// Name of the configuration file
 ///
@@ -41,35 +44,35 @@ impl Runnable for CargoAuditCommand {
 }
 
 impl Configurable<AuditConfig> for CargoAuditCommand {
-    /// Location of `audit.toml` (if it exists)
+    /// `audit.toml` is read from several layers (see [`config_layers`]) in
+    /// [`Configurable::process_config`] rather than from a single file
     fn config_path(&self) -> Option<PathBuf> {
-        // Check if the config file exists, and if it does not, ignore it.
-        //
-        // The order of precedence for which config file to use is:
-        // 1. The current project's `.cargo` configuration directory.
-        // 2. The current user's home directory configuration.
-
-        let project_config_filename = PathBuf::from("./.cargo").join(CONFIG_FILE);
-        if project_config_filename.exists() {
-            return Some(project_config_filename);
-        }
+        None
+    }
 
-        let home_config_filename = home::cargo_home()
-            .ok()
-            .map(|cargo_home| cargo_home.join(CONFIG_FILE))?;
+    /// Load and merge the configuration files and environment variables,
+    /// override the result with explicit command-line arguments, and merge
+    /// the shared policy (if any) under it.
+    ///
+    /// The shared policy is merged last so that it is fetched according to
+    /// the final configuration (e.g. not at all with `--no-fetch`). It only
+    /// fills in settings which aren't made locally, so local settings still
+    /// take precedence.
+    fn process_config(&self, _default: AuditConfig) -> Result<AuditConfig, FrameworkError> {
+        let (config, unknown_vars) =
+            config_layers::load_with_env(&config_layers::discover(), env::vars_os())
+                .map_err(|e| Context::new(FrameworkErrorKind::ConfigError, Some(Box::new(e))))?;
 
-        if home_config_filename.exists() {
-            Some(home_config_filename)
-        } else {
-            None
+        for name in unknown_vars {
+            status_warn!("ignoring {}, which isn't a configuration setting", name);
         }
-    }
 
-    /// Override loaded config with explicit command-line arguments
-    fn process_config(&self, config: AuditConfig) -> Result<AuditConfig, FrameworkError> {
-        match &self.cmd {
-            CargoAuditSubCommand::Audit(cmd) => cmd.override_config(config),
-        }
+        let config = match &self.cmd {
+            CargoAuditSubCommand::Audit(cmd) => cmd.override_config(config)?,
+        };
+
+        shared_policy::apply(config)
+            .map_err(|e| Context::new(FrameworkErrorKind::ConfigError, Some(Box::new(e))).into())
     }
 }
 
@@ -1,37 +1,68 @@
 //! The `cargo audit` subcommand
 
+mod advisory;
+mod config;
+mod db;
+mod diff;
+mod remote;
+mod search;
+mod show;
+
 #[cfg(feature = "fix")]
 mod fix;
 
 #[cfg(feature = "binary-scanning")]
 mod binary_scanning;
 
+#[cfg(feature = "archive-scanning")]
+mod image;
+
 use crate::{
     auditor::Auditor,
-    cli_config::CliConfig,
-    config::{AuditConfig, DenyOption},
+    cli_config::{CliConfig, NetworkArgs},
+    config::{AuditConfig, DenyOption, GroupBy, OsvMode, OutputFormat},
+    dependency_list::StdinFormat,
     error::display_err_with_source,
-    lockfile,
+    json_rpc, lockfile,
     prelude::*,
+    report_cache::ReportCache,
+    watch,
 };
 use abscissa_core::{config::Override, terminal::ColorChoice, FrameworkError};
 use clap::Parser;
 use rustsec::platforms::target::{Arch, OS};
-use std::{path::PathBuf, process::exit};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::exit,
+    time::Duration,
+};
+
+use self::{
+    advisory::AdvisoryCommand,
+    config::ConfigCommand,
+    db::DbCommand,
+    diff::{load_report, DiffCommand},
+    remote::RemoteCommand,
+    search::SearchCommand,
+    show::ShowCommand,
+};
+use clap::Subcommand;
 
 #[cfg(feature = "binary-scanning")]
 use self::binary_scanning::BinCommand;
 #[cfg(feature = "fix")]
 use self::fix::FixCommand;
-#[cfg(any(feature = "fix", feature = "binary-scanning"))]
-use clap::Subcommand;
+#[cfg(feature = "archive-scanning")]
+use self::image::ImageCommand;
 
 /// The `cargo audit` subcommand
 #[derive(Command, Clone, Default, Debug, Parser)]
 #[command(version)]
 pub struct AuditCommand {
-    /// Optional subcommand (used for `cargo audit fix` and `cargo audit bin`)
-    #[cfg(any(feature = "fix", feature = "binary-scanning"))]
+    /// Optional subcommand (used for `cargo audit fix`, `cargo audit bin`,
+    /// `cargo audit image`, `cargo audit db`, `cargo audit diff`,
+    /// `cargo audit show`, `cargo audit search` and `cargo audit remote`)
     #[command(subcommand)]
     subcommand: Option<AuditSubcommand>,
 
@@ -55,7 +86,7 @@ pub struct AuditCommand {
     #[arg(
         short = 'D',
         long = "deny",
-        help = "exit with an error on: warnings (any), unmaintained, unsound, yanked"
+        help = "exit with an error on: warnings (any), unmaintained, unsound, yanked, deprecated"
     )]
     deny: Vec<DenyOption>,
 
@@ -63,10 +94,28 @@ pub struct AuditCommand {
     #[arg(
         short = 'f',
         long = "file",
-        help = "Cargo lockfile to inspect (or `-` for STDIN, default: Cargo.lock)"
+        help = "Cargo lockfile to inspect (or `-` for STDIN, default: Cargo.lock of the current workspace)"
     )]
     file: Option<PathBuf>,
 
+    /// Path to `Cargo.toml`
+    #[arg(
+        long = "manifest-path",
+        value_name = "PATH",
+        conflicts_with = "file",
+        help = "Path to Cargo.toml of a package whose workspace's Cargo.lock is inspected"
+    )]
+    manifest_path: Option<PathBuf>,
+
+    /// Format of the dependency list read from STDIN
+    #[arg(
+        long = "stdin-format",
+        value_name = "FORMAT",
+        conflicts_with_all = ["file", "manifest_path"],
+        help = "Audit a dependency list read from STDIN: lockfile, list (`name version` lines), tree (`cargo tree --prefix none` output) or metadata (`cargo metadata` JSON)"
+    )]
+    stdin_format: Option<StdinFormat>,
+
     /// Advisory IDs to ignore
     #[arg(
         long = "ignore",
@@ -90,10 +139,32 @@ pub struct AuditCommand {
     )]
     no_fetch: bool,
 
+    /// Don't use the cached snapshot of the parsed advisory database, nor
+    /// cached reports
+    #[arg(
+        long = "no-cache",
+        help = "do not use the cached snapshot of the parsed advisory DB, nor cached reports"
+    )]
+    no_cache: bool,
+
+    /// Use the advisory database snapshot embedded in the binary
+    #[arg(
+        long = "embedded-db",
+        help = "use the advisory DB snapshot embedded in this binary instead of fetching it"
+    )]
+    embedded_db: bool,
+
     /// Allow stale advisory databases that haven't been recently updated
     #[arg(long = "stale", help = "allow stale database")]
     stale: bool,
 
+    /// Fail if the advisory database can't be fetched
+    #[arg(
+        long = "require-fresh-db",
+        help = "Fail if the advisory DB can't be fetched, instead of using the local copy"
+    )]
+    require_fresh_db: bool,
+
     /// Target CPU architecture to find vulnerabilities for
     #[arg(
         long = "target-arch",
@@ -108,6 +179,14 @@ pub struct AuditCommand {
     )]
     target_os: Option<OS>,
 
+    /// Target triple to find vulnerabilities for
+    #[arg(
+        long = "target",
+        value_name = "TRIPLE",
+        help = "filter vulnerabilities by the CPU and OS of a target triple, e.g. x86_64-unknown-linux-gnu (default: no filter)"
+    )]
+    target: Option<String>,
+
     /// URL to the advisory database git repository
     #[arg(short = 'u', long = "url", help = "URL for advisory database git repo")]
     url: Option<String>,
@@ -123,10 +202,213 @@ pub struct AuditCommand {
     /// Output reports as JSON
     #[arg(long = "json", help = "Output report in JSON format")]
     output_json: bool,
+
+    /// Output format
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        help = "Output format: terminal, json, ndjson (one JSON report per line) or gitlab (GitLab dependency scanning report)"
+    )]
+    output_format: Option<OutputFormat>,
+
+    /// Show release information for affected packages
+    #[arg(
+        long = "release-info",
+        help = "Show release dates and the number of newer releases of affected crates"
+    )]
+    release_info: bool,
+
+    /// Suggest upgrades resolving vulnerabilities
+    #[arg(
+        long = "suggest-upgrades",
+        help = "Suggest which dependencies to upgrade to resolve vulnerabilities"
+    )]
+    suggest_upgrades: bool,
+
+    /// Group vulnerabilities in several versions of the same crate
+    #[arg(
+        long = "group-versions",
+        help = "Show each advisory once for all affected versions of a crate"
+    )]
+    group_versions: bool,
+
+    /// Only print the number of findings
+    #[arg(
+        long = "summary",
+        help = "Only print the number of vulnerabilities by severity and warnings by kind"
+    )]
+    summary: bool,
+
+    /// Group findings in the terminal output
+    #[arg(
+        long = "group-by",
+        value_name = "GROUP",
+        help = "Group findings by severity, package or direct-dep (the direct dependency pulling them in)"
+    )]
+    group_by: Option<GroupBy>,
+
+    /// Treat soft conditions as errors
+    #[arg(
+        long = "strict",
+        help = "Fail on a stale database, incomplete dependency data or skipped checks, with dedicated exit codes"
+    )]
+    strict: bool,
+
+    /// Warn for crates which have been superseded by another crate
+    #[arg(
+        long = "deprecated",
+        help = "Warn for crates which have been deprecated in favour of another crate (e.g. structopt)"
+    )]
+    deprecated: bool,
+
+    /// Report withdrawn advisories
+    #[arg(
+        long = "include-withdrawn",
+        help = "Also report advisories which have been withdrawn"
+    )]
+    include_withdrawn: bool,
+
+    /// Report vulnerabilities regardless of enabled crate features
+    #[arg(
+        long = "ignore-features",
+        help = "Report vulnerabilities regardless of which crate features are enabled in the project"
+    )]
+    ignore_features: bool,
+
+    /// Exclude findings for dev-only dependencies
+    #[arg(
+        long = "exclude-dev",
+        help = "Skip advisories for crates which are only used as dev-dependencies"
+    )]
+    exclude_dev: bool,
+
+    /// Check patched versions against the requirements in the manifests
+    #[arg(
+        long = "check-requirements",
+        help = "Check patched versions against the version requirements in Cargo.toml (runs `cargo metadata`)"
+    )]
+    check_requirements: bool,
+
+    /// Query osv.dev for advisories
+    #[arg(
+        long = "osv",
+        value_name = "MODE",
+        help = "Query osv.dev for advisories: merge (with the advisory DB) or only (instead of it)"
+    )]
+    osv: Option<OsvMode>,
+
+    /// Include GitHub Security Advisories
+    #[arg(
+        long = "ghsa",
+        help = "Include GitHub Security Advisories which aren't in the advisory DB (needs GITHUB_TOKEN)"
+    )]
+    ghsa: bool,
+
+    /// Enrich advisories with data from NVD
+    #[arg(
+        long = "nvd",
+        help = "Fill in CVSS scores, CWEs and references from NVD for advisories with CVE aliases"
+    )]
+    nvd: bool,
+
+    /// Directory of recorded API responses
+    #[arg(
+        long = "vcr",
+        value_name = "DIR",
+        help = "Replay API responses recorded in DIR instead of using the network"
+    )]
+    vcr: Option<PathBuf>,
+
+    /// Record API responses
+    #[arg(
+        long = "vcr-record",
+        requires = "vcr",
+        help = "Record API responses into the --vcr directory instead of replaying them"
+    )]
+    vcr_record: bool,
+
+    /// Timeouts and retries for network requests
+    #[command(flatten)]
+    network: NetworkArgs,
+
+    /// Explain why an advisory does or does not match
+    #[arg(
+        long = "explain",
+        value_name = "ADVISORY_ID",
+        help = "Explain why the given advisory does or does not match the lockfile"
+    )]
+    explain: Option<String>,
+
+    /// Compare the findings with those of a baseline report
+    #[arg(
+        long = "compare-with",
+        value_name = "REPORT",
+        help = "Compare the findings with those of a baseline JSON report, only failing on newly introduced ones"
+    )]
+    compare_with: Option<PathBuf>,
+
+    /// Serve audit requests over stdin/stdout using JSON-RPC
+    #[arg(
+        long = "json-rpc",
+        help = "Serve audit requests over stdin/stdout using JSON-RPC (LSP-style framing)"
+    )]
+    json_rpc: bool,
+
+    /// Re-audit the lockfile whenever it changes
+    #[arg(
+        long = "watch",
+        help = "Stay resident, re-auditing Cargo.lock when it changes and printing only the changes"
+    )]
+    watch: bool,
+
+    /// How often to re-fetch the advisory database in watch mode
+    #[arg(
+        long = "watch-interval",
+        value_name = "SECONDS",
+        default_value = "3600",
+        help = "How often to re-fetch the advisory database with --watch, in seconds"
+    )]
+    watch_interval: u64,
+
+    /// Audit the dependencies `cargo-audit` itself was built with
+    #[cfg(feature = "binary-scanning")]
+    #[arg(
+        long = "self-audit",
+        help = "Audit the dependencies embedded in the cargo-audit executable instead of a lockfile"
+    )]
+    self_audit: bool,
+
+    /// Upload the JSON report to remote storage
+    #[cfg(feature = "upload")]
+    #[arg(
+        long = "upload",
+        value_name = "URL",
+        help = "Upload the JSON report to an s3://, gs:// or https:// URL"
+    )]
+    upload: Option<String>,
+
+    /// Compress the uploaded report
+    #[cfg(all(feature = "upload", feature = "compress"))]
+    #[arg(
+        long = "compress",
+        value_name = "FORMAT",
+        requires = "upload",
+        help = "Compress the uploaded report: gzip or zstd"
+    )]
+    compress: Option<crate::compression::Compression>,
+
+    /// Only upload findings which haven't been uploaded before
+    #[cfg(feature = "upload")]
+    #[arg(
+        long = "notify-state",
+        value_name = "FILE",
+        requires = "upload",
+        help = "Track uploaded findings in FILE and only upload new or changed ones"
+    )]
+    notify_state: Option<PathBuf>,
 }
 
 /// Subcommands of `cargo audit`
-#[cfg(any(feature = "fix", feature = "binary-scanning"))]
 #[derive(Subcommand, Clone, Debug, Runnable)]
 pub enum AuditSubcommand {
     /// `cargo audit fix` subcommand
@@ -144,9 +426,166 @@ Performs a complete scan if the binary is built with 'cargo auditable'.
 If not, recovers a part of the dependency list from panic messages."
     )]
     Bin(BinCommand),
+
+    /// `cargo audit image` subcommand
+    #[cfg(feature = "archive-scanning")]
+    #[command(
+        about = "scan the binaries in container images",
+        long_about = "Scan the binaries in OCI and Docker container images for known vulnerabilities.
+
+Takes image tarballs (e.g. from `docker save`) or references to images
+available to docker, and audits the executables in each layer."
+    )]
+    Image(ImageCommand),
+
+    /// `cargo audit db` subcommand
+    #[command(about = "operate on the advisory database")]
+    Db(DbCommand),
+
+    /// `cargo audit show` subcommand
+    #[command(about = "show an advisory from the advisory database in full")]
+    Show(ShowCommand),
+
+    /// `cargo audit diff` subcommand
+    #[command(
+        about = "compare the findings of two JSON reports",
+        long_about = "Compare the findings of two JSON reports (as printed by `cargo audit --json`),
+printing the findings which were introduced, resolved or persisted.
+
+Exits with a failure status only if findings were introduced."
+    )]
+    Diff(DiffCommand),
+
+    /// `cargo audit search` subcommand
+    #[command(
+        about = "search the advisory database",
+        long_about = "Search the advisory database by crate name, keyword, category, CWE and free text
+over advisory titles and descriptions, printing the matching advisories."
+    )]
+    Search(SearchCommand),
+
+    /// `cargo audit remote` subcommand
+    #[command(
+        about = "audit a lockfile fetched from a remote host over SSH",
+        long_about = "Audit a lockfile fetched from a remote host over SSH.
+
+Only Cargo.lock (and optionally Cargo.toml) is copied with `scp`, so no tooling
+needs to be installed on the remote host."
+    )]
+    Remote(RemoteCommand),
+
+    /// `cargo audit advisory` subcommand
+    #[command(about = "help with writing advisories for the advisory database")]
+    Advisory(AdvisoryCommand),
+
+    /// `cargo audit config` subcommand
+    #[command(
+        about = "inspect the configuration",
+        long_about = "Inspect the configuration, which is merged from the system, user and workspace
+`audit.toml` files, CARGO_AUDIT_* environment variables and command-line flags
+(in increasing order of precedence)."
+    )]
+    Config(ConfigCommand),
+}
+
+/// Notify the configured webhooks about the new findings of the report
+fn notify(lockfile_path: &Path, report: &rustsec::Report) -> Result<(), crate::error::Error> {
+    let config = APP.config();
+    if !config.output.notify.is_enabled() {
+        return Ok(());
+    }
+
+    let count = crate::notify::notify(&config.output.notify, lockfile_path, report)?;
+    if !config.output.is_quiet() {
+        if count == 0 {
+            status_ok!("Skipped", "notifications: no new or changed findings");
+        } else {
+            status_ok!(
+                "Notified",
+                "webhooks about {} new or changed findings",
+                count
+            );
+        }
+    }
+
+    Ok(())
 }
 
 impl AuditCommand {
+    /// Upload the report, or only its new and changed findings when a
+    /// notification state file is used
+    #[cfg(feature = "upload")]
+    fn announce_report(
+        &self,
+        url: &str,
+        lockfile_path: &Path,
+        report: &rustsec::Report,
+    ) -> Result<(), crate::error::Error> {
+        let quiet = APP.config().output.is_quiet();
+
+        let state_path = match &self.notify_state {
+            Some(state_path) => state_path,
+            None => {
+                self.upload_report(url, report)?;
+
+                if !quiet {
+                    status_ok!("Uploaded", "report to {}", url);
+                }
+
+                return Ok(());
+            }
+        };
+
+        let mut state = crate::notify_state::NotifyState::load(state_path)?;
+        let unannounced = state.unannounced(lockfile_path, report);
+
+        if unannounced.vulnerabilities.list.is_empty() && unannounced.warnings.is_empty() {
+            if !quiet {
+                status_ok!("Skipped", "upload: no new or changed findings");
+            }
+        } else {
+            self.upload_report(url, &unannounced)?;
+
+            if !quiet {
+                status_ok!(
+                    "Uploaded",
+                    "{} new or changed findings to {}",
+                    unannounced.vulnerabilities.count
+                        + unannounced.warnings.values().map(Vec::len).sum::<usize>(),
+                    url
+                );
+            }
+        }
+
+        // The state is only updated once the findings have been delivered,
+        // so failed uploads are retried on the next run
+        state.record(lockfile_path, report);
+        state.save(state_path)
+    }
+
+    /// Upload the JSON report, compressing it while it is serialized if
+    /// requested
+    #[cfg(feature = "upload")]
+    fn upload_report(
+        &self,
+        url: &str,
+        report: &rustsec::Report,
+    ) -> Result<(), crate::error::Error> {
+        #[cfg(feature = "compress")]
+        if let Some(compression) = self.compress {
+            let upload_error = |e: std::io::Error| crate::error::ErrorKind::Upload.context(e);
+
+            let mut encoder = compression.encoder(vec![]).map_err(upload_error)?;
+            serde_json::to_writer(&mut encoder, report).map_err(|e| upload_error(e.into()))?;
+            let body = encoder.finish().map_err(upload_error)?;
+
+            return crate::upload::upload(url, body, Some(compression.content_encoding()));
+        }
+
+        let body = serde_json::to_vec(report).expect("couldn't serialize report");
+        crate::upload::upload(url, body, None)
+    }
+
     /// Get the color configuration
     pub fn color_config(&self) -> Option<ColorChoice> {
         // suppress the warning that occurs with the `binary-scanning` feature disabled
@@ -154,7 +593,11 @@ impl AuditCommand {
         let mut raw_color_setting = self.color.as_ref();
         #[cfg(feature = "binary-scanning")]
         if let Some(AuditSubcommand::Bin(ref command)) = self.subcommand {
-            raw_color_setting = command.color.as_ref()
+            raw_color_setting = command.args.color.as_ref()
+        };
+        #[cfg(feature = "archive-scanning")]
+        if let Some(AuditSubcommand::Image(ref command)) = self.subcommand {
+            raw_color_setting = command.args.color.as_ref()
         };
         raw_color_setting.map(|colors| match colors.as_ref() {
             "always" => ColorChoice::Always,
@@ -173,12 +616,36 @@ impl From<AuditCommand> for CliConfig {
             ignore: c.ignore,
             ignore_source: c.ignore_source,
             no_fetch: c.no_fetch,
+            no_cache: c.no_cache,
+            embedded_db: c.embedded_db,
             stale: c.stale,
+            require_fresh_db: c.require_fresh_db,
             target_arch: c.target_arch,
             target_os: c.target_os,
+            target: c.target,
             url: c.url,
             quiet: c.quiet,
             output_json: c.output_json,
+            output_format: c.output_format,
+            release_info: c.release_info,
+            suggest_upgrades: c.suggest_upgrades,
+            group_versions: c.group_versions,
+            summary: c.summary,
+            group_by: c.group_by,
+            strict: c.strict,
+            deprecated: c.deprecated,
+            include_withdrawn: c.include_withdrawn,
+            filter_symbols: false,
+            debug_info: vec![],
+            ignore_features: c.ignore_features,
+            exclude_dev: c.exclude_dev,
+            check_requirements: c.check_requirements,
+            osv: c.osv,
+            ghsa: c.ghsa,
+            nvd: c.nvd,
+            vcr: c.vcr,
+            vcr_record: c.vcr_record,
+            network: c.network,
         }
     }
 }
@@ -189,7 +656,23 @@ impl Override<AuditConfig> for AuditCommand {
         if let Some(AuditSubcommand::Bin(bin)) = &self.subcommand {
             return CliConfig::from(bin.clone()).override_config(config);
         }
-        CliConfig::from(self.clone()).override_config(config)
+        #[cfg(feature = "archive-scanning")]
+        if let Some(AuditSubcommand::Image(image)) = &self.subcommand {
+            return CliConfig::from(image.clone()).override_config(config);
+        }
+        let mut config = CliConfig::from(self.clone()).override_config(config)?;
+
+        // Keep stdout reserved for protocol messages and exports
+        if self.json_rpc
+            || matches!(&self.subcommand, Some(AuditSubcommand::Db(db)) if db.writes_to_stdout())
+            || matches!(&self.subcommand, Some(AuditSubcommand::Show(show)) if show.writes_to_stdout())
+            || matches!(&self.subcommand, Some(AuditSubcommand::Search(search)) if search.writes_to_stdout())
+            || matches!(&self.subcommand, Some(AuditSubcommand::Advisory(advisory)) if advisory.writes_to_stdout())
+        {
+            config.output.quiet = true;
+        }
+
+        Ok(config)
     }
 }
 
@@ -207,21 +690,145 @@ impl Runnable for AuditCommand {
             exit(0)
         }
 
-        let maybe_path = self.file.as_deref();
+        #[cfg(feature = "archive-scanning")]
+        if let Some(AuditSubcommand::Image(image)) = &self.subcommand {
+            image.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Db(db)) = &self.subcommand {
+            db.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Remote(remote)) = &self.subcommand {
+            remote.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Show(show)) = &self.subcommand {
+            show.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Diff(diff)) = &self.subcommand {
+            diff.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Search(search)) = &self.subcommand {
+            search.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Advisory(advisory)) = &self.subcommand {
+            advisory.run();
+            exit(0)
+        }
+
+        if let Some(AuditSubcommand::Config(config)) = &self.subcommand {
+            config.run();
+            exit(0)
+        }
+
+        if self.json_rpc {
+            let mut auditor = self.auditor();
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            if let Err(e) = json_rpc::serve(&mut auditor, stdin.lock(), stdout.lock()) {
+                status_err!("JSON-RPC session failed: {}", e);
+                exit(2);
+            }
+            exit(0);
+        }
+
+        #[cfg(feature = "binary-scanning")]
+        if self.self_audit {
+            self.run_self_audit();
+        }
+
+        let maybe_path = match self.stdin_format {
+            Some(_) => Some(Path::new("-")),
+            None => self.file.as_deref(),
+        };
         // It is important to generate the lockfile before initializing the auditor,
         // otherwise we might deadlock because both need the Cargo package lock
-        let path = lockfile::locate_or_generate(maybe_path).unwrap_or_else(|e| {
-            status_err!("{}", display_err_with_source(&e));
-            exit(2);
-        });
+        let path = lockfile::locate_or_generate(maybe_path, self.manifest_path.as_deref())
+            .unwrap_or_else(|e| {
+                status_err!("{}", display_err_with_source(&e));
+                exit(2);
+            });
+
+        if self.watch {
+            // GitLab reports can't be updated incrementally
+            if APP.config().output.format == OutputFormat::Gitlab {
+                status_err!("`--format gitlab` can't be used with `--watch`");
+                exit(2);
+            }
+
+            watch::run(
+                &APP.config(),
+                &path,
+                Duration::from_secs(self.watch_interval),
+            );
+        }
+
         let mut auditor = self.auditor();
+
+        // The report for an unchanged project is reused, once the advisory
+        // database has been loaded (and fetched), so it is keyed on the
+        // database's current commit and checked for staleness
+        let cache = if self.explain.is_none() && self.compare_with.is_none() {
+            ReportCache::open(&APP.config(), &path, auditor.database())
+        } else {
+            None
+        };
+
+        if let Some(report) = cache.as_ref().and_then(ReportCache::load) {
+            self.present_cached_report(&mut auditor, &path, &report);
+        }
+
+        if let Some(advisory_id) = &self.explain {
+            let advisory_id = advisory_id.parse().unwrap_or_else(|e| {
+                status_err!("invalid advisory ID `{}`: {}", advisory_id, e);
+                exit(2);
+            });
+
+            if let Err(e) = auditor.explain_lockfile(&path, &advisory_id) {
+                status_err!("{}", display_err_with_source(&e));
+                exit(2);
+            }
+
+            exit(0);
+        }
+
+        if let Some(baseline) = &self.compare_with {
+            let baseline = load_report(baseline);
+
+            match auditor.compare_lockfile(&path, &baseline) {
+                Ok(diff) => {
+                    // Only findings which weren't in the baseline fail the audit
+                    if auditor.should_exit_with_failure(&diff.introduced) {
+                        exit(1);
+                    }
+                    exit(0);
+                }
+                Err(e) => {
+                    status_err!("{}", display_err_with_source(&e));
+                    exit(2);
+                }
+            }
+        }
+
         let report = auditor.audit_lockfile(&path);
         match report {
             Ok(report) => {
-                if auditor.should_exit_with_failure(&report) {
-                    exit(1);
+                if let Some(cache) = &cache {
+                    cache.store(&report);
                 }
-                exit(0);
+
+                let failed = auditor.should_exit_with_failure(&report);
+                self.finish(&path, &report, failed);
             }
             Err(e) => {
                 status_err!("{}", display_err_with_source(&e));
@@ -234,6 +841,76 @@ impl Runnable for AuditCommand {
 impl AuditCommand {
     /// Initialize `Auditor`
     pub fn auditor(&self) -> Auditor {
-        Auditor::new(&APP.config())
+        let mut auditor = Auditor::new(&APP.config());
+        if let Some(format) = self.stdin_format {
+            auditor.stdin_format(format);
+        }
+        auditor
+    }
+
+    /// Present a cached report for the lockfile at the given path
+    fn present_cached_report(
+        &self,
+        auditor: &mut Auditor,
+        path: &Path,
+        report: &rustsec::Report,
+    ) -> ! {
+        if !APP.config().output.is_quiet() {
+            status_ok!(
+                "Cached",
+                "report for unchanged lockfile and advisory database (use --no-cache to audit again)"
+            );
+        }
+
+        if let Err(e) = auditor.present_lockfile_report(path, report) {
+            status_err!("{}", display_err_with_source(&e));
+            exit(2);
+        }
+
+        let failed = auditor.should_exit_with_failure(report);
+        self.finish(path, report, failed)
+    }
+
+    /// Upload the report and send notifications if configured, then exit with
+    /// the status reflecting whether the audit failed
+    fn finish(&self, path: &Path, report: &rustsec::Report, failed: bool) -> ! {
+        #[cfg(feature = "upload")]
+        if let Some(url) = &self.upload {
+            if let Err(e) = self.announce_report(url, path, report) {
+                status_err!("couldn't upload report: {}", display_err_with_source(&e));
+                exit(2);
+            }
+        }
+
+        if let Err(e) = notify(path, report) {
+            status_err!(
+                "couldn't send notifications: {}",
+                display_err_with_source(&e)
+            );
+            exit(2);
+        }
+
+        if failed {
+            exit(1);
+        }
+        exit(0);
+    }
+
+    /// Audit the running executable as a binary, exiting with the result
+    #[cfg(feature = "binary-scanning")]
+    fn run_self_audit(&self) -> ! {
+        let exe = std::env::current_exe().unwrap_or_else(|e| {
+            status_err!("couldn't locate the cargo-audit executable: {}", e);
+            exit(2);
+        });
+
+        let report = self.auditor().audit_binaries(&[exe]);
+        if report.vulnerabilities_found {
+            exit(1)
+        } else if report.errors_encountered {
+            exit(2)
+        } else {
+            exit(0)
+        }
     }
 }
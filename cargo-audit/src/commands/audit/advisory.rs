@@ -0,0 +1,120 @@
+//! The `cargo audit advisory` subcommand
+
+use crate::prelude::*;
+use abscissa_core::{Command, Runnable};
+use clap::{Parser, Subcommand};
+use rustsec::{advisory::Informational, package, Advisory};
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    process::exit,
+};
+
+/// The `cargo audit advisory` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct AdvisoryCommand {
+    /// Operation to perform
+    #[command(subcommand)]
+    subcommand: AdvisorySubcommand,
+}
+
+/// Subcommands of `cargo audit advisory`
+#[derive(Subcommand, Clone, Debug, Runnable)]
+pub enum AdvisorySubcommand {
+    /// `cargo audit advisory new` subcommand
+    #[command(about = "create the skeleton of a new advisory for the advisory database")]
+    New(NewCommand),
+}
+
+/// The `cargo audit advisory new` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct NewCommand {
+    /// Name of the affected crate
+    #[arg(value_name = "CRATE", help = "Name of the affected crate")]
+    package: String,
+
+    /// Kind of advisory
+    #[arg(
+        long = "kind",
+        value_name = "KIND",
+        default_value = "vulnerability",
+        help = "Kind of advisory: vulnerability, unmaintained, unsound or notice"
+    )]
+    kind: String,
+
+    /// File to write the advisory to
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "FILE",
+        help = "File to write the advisory to, which must not exist yet (default: stdout)"
+    )]
+    output: Option<PathBuf>,
+}
+
+impl AdvisoryCommand {
+    /// Is the output of this command written to stdout?
+    pub fn writes_to_stdout(&self) -> bool {
+        match &self.subcommand {
+            AdvisorySubcommand::New(new) => new.output.is_none(),
+        }
+    }
+}
+
+impl Runnable for AdvisoryCommand {
+    fn run(&self) {
+        self.subcommand.run()
+    }
+}
+
+impl NewCommand {
+    /// Parse the kind of advisory: `None` for vulnerabilities
+    fn kind(&self) -> Result<Option<Informational>, String> {
+        match self.kind.as_str() {
+            "vulnerability" => Ok(None),
+            kind => match kind.parse() {
+                Ok(Informational::Other(_)) | Err(_) => Err(format!(
+                    "invalid advisory kind: {} (expected vulnerability, unmaintained, unsound or notice)",
+                    kind
+                )),
+                Ok(informational) => Ok(Some(informational)),
+            },
+        }
+    }
+}
+
+impl Runnable for NewCommand {
+    fn run(&self) {
+        let package: package::Name = self.package.parse().unwrap_or_else(|e| {
+            status_err!("invalid crate name {}: {}", self.package, e);
+            exit(2);
+        });
+
+        let kind = self.kind().unwrap_or_else(|e| {
+            status_err!("{}", e);
+            exit(2);
+        });
+
+        let template = Advisory::template(&package, kind.as_ref());
+
+        let result = match &self.output {
+            Some(path) => OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(template.as_bytes())),
+            None => io::stdout().lock().write_all(template.as_bytes()),
+        };
+
+        if let Err(e) = result {
+            status_err!("couldn't write advisory: {}", e);
+            exit(2);
+        }
+
+        if let Some(path) = &self.output {
+            status_ok!("Created", "advisory for {} in {}", package, path.display());
+        }
+    }
+}
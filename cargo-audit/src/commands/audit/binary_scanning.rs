@@ -1,21 +1,22 @@
 //! The `cargo audit bin` subcommand
 
 use crate::{
-    auditor::Auditor,
-    cli_config::CliConfig,
-    config::{AuditConfig, DenyOption},
+    auditor::{load_binaries, Auditor},
+    binary_dep_list::BinaryDeps,
+    cli_config::{CliConfig, NetworkArgs},
+    config::{AuditConfig, DenyOption, GroupBy, OsvMode, OutputFormat},
+    error::display_err_with_source,
     prelude::*,
 };
 use abscissa_core::{config::Override, FrameworkError};
-use clap::Parser;
+use clap::{Args, Parser};
 use rustsec::platforms::target::{Arch, OS};
 use std::{path::PathBuf, process::exit};
 
-#[cfg(feature = "binary-scanning")]
-/// The `cargo audit` subcommand
-#[derive(Command, Clone, Default, Debug, Parser)]
-#[command()]
-pub struct BinCommand {
+/// Options shared by the subcommands auditing binaries (`cargo audit bin`
+/// and `cargo audit image`)
+#[derive(Args, Clone, Default, Debug)]
+pub struct BinaryAuditArgs {
     /// Colored output configuration
     #[arg(
         short = 'c',
@@ -36,7 +37,7 @@ pub struct BinCommand {
     #[arg(
         short = 'D',
         long = "deny",
-        help = "exit with an error on: warnings (any), unmaintained, unsound, yanked"
+        help = "exit with an error on: warnings (any), unmaintained, unsound, yanked, deprecated"
     )]
     deny: Vec<DenyOption>,
 
@@ -63,10 +64,31 @@ pub struct BinCommand {
     )]
     no_fetch: bool,
 
+    /// Don't use the cached snapshot of the parsed advisory database
+    #[arg(
+        long = "no-cache",
+        help = "do not use the cached snapshot of the parsed advisory DB"
+    )]
+    no_cache: bool,
+
+    /// Use the advisory database snapshot embedded in the binary
+    #[arg(
+        long = "embedded-db",
+        help = "use the advisory DB snapshot embedded in this binary instead of fetching it"
+    )]
+    embedded_db: bool,
+
     /// Allow stale advisory databases that haven't been recently updated
     #[arg(long = "stale", help = "allow stale database")]
     stale: bool,
 
+    /// Fail if the advisory database can't be fetched
+    #[arg(
+        long = "require-fresh-db",
+        help = "Fail if the advisory DB can't be fetched, instead of using the local copy"
+    )]
+    require_fresh_db: bool,
+
     /// Target CPU architecture to find vulnerabilities for
     #[arg(
         long = "target-arch",
@@ -97,18 +119,238 @@ pub struct BinCommand {
     #[arg(long = "json", help = "Output report in JSON format")]
     output_json: bool,
 
+    /// Output format
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        help = "Output format: terminal, json, ndjson (one JSON report per line) or gitlab (GitLab dependency scanning report)"
+    )]
+    output_format: Option<OutputFormat>,
+
+    /// Show release information for affected packages
+    #[arg(
+        long = "release-info",
+        help = "Show release dates and the number of newer releases of affected crates"
+    )]
+    release_info: bool,
+
+    /// Suggest upgrades resolving vulnerabilities
+    #[arg(
+        long = "suggest-upgrades",
+        help = "Suggest which dependencies to upgrade to resolve vulnerabilities"
+    )]
+    suggest_upgrades: bool,
+
+    /// Group vulnerabilities in several versions of the same crate
+    #[arg(
+        long = "group-versions",
+        help = "Show each advisory once for all affected versions of a crate"
+    )]
+    group_versions: bool,
+
+    /// Only print the number of findings
+    #[arg(
+        long = "summary",
+        help = "Only print the number of vulnerabilities by severity and warnings by kind"
+    )]
+    summary: bool,
+
+    /// Group findings in the terminal output
+    #[arg(
+        long = "group-by",
+        value_name = "GROUP",
+        help = "Group findings by severity, package or direct-dep (the direct dependency pulling them in)"
+    )]
+    group_by: Option<GroupBy>,
+
+    /// Treat soft conditions as errors
+    #[arg(
+        long = "strict",
+        help = "Fail on a stale database, incomplete dependency data or skipped checks, with dedicated exit codes"
+    )]
+    strict: bool,
+
+    /// Warn for crates which have been superseded by another crate
+    #[arg(
+        long = "deprecated",
+        help = "Warn for crates which have been deprecated in favour of another crate (e.g. structopt)"
+    )]
+    deprecated: bool,
+
+    /// Report withdrawn advisories
+    #[arg(
+        long = "include-withdrawn",
+        help = "Also report advisories which have been withdrawn"
+    )]
+    include_withdrawn: bool,
+
+    /// Downgrade vulnerabilities whose affected functions aren't in the binary
+    #[arg(
+        long = "filter-symbols",
+        help = "Downgrade vulnerabilities whose affected functions have no symbols in the binary to warnings"
+    )]
+    filter_symbols: bool,
+
+    /// Query osv.dev for advisories
+    #[arg(
+        long = "osv",
+        value_name = "MODE",
+        help = "Query osv.dev for advisories: merge (with the advisory DB) or only (instead of it)"
+    )]
+    osv: Option<OsvMode>,
+
+    /// Include GitHub Security Advisories
+    #[arg(
+        long = "ghsa",
+        help = "Include GitHub Security Advisories which aren't in the advisory DB (needs GITHUB_TOKEN)"
+    )]
+    ghsa: bool,
+
+    /// Enrich advisories with data from NVD
+    #[arg(
+        long = "nvd",
+        help = "Fill in CVSS scores, CWEs and references from NVD for advisories with CVE aliases"
+    )]
+    nvd: bool,
+
+    /// Directory of recorded API responses
+    #[arg(
+        long = "vcr",
+        value_name = "DIR",
+        help = "Replay API responses recorded in DIR instead of using the network"
+    )]
+    vcr: Option<PathBuf>,
+
+    /// Record API responses
+    #[arg(
+        long = "vcr-record",
+        requires = "vcr",
+        help = "Record API responses into the --vcr directory instead of replaying them"
+    )]
+    vcr_record: bool,
+
+    /// Timeouts and retries for network requests
+    #[command(flatten)]
+    network: NetworkArgs,
+}
+
+impl BinaryAuditArgs {
+    /// Get the configuration given by these options
+    pub fn cli_config(self, debug_info: Vec<PathBuf>) -> CliConfig {
+        CliConfig {
+            db: self.db,
+            deny: self.deny,
+            ignore: self.ignore,
+            ignore_source: self.ignore_source,
+            no_fetch: self.no_fetch,
+            no_cache: self.no_cache,
+            embedded_db: self.embedded_db,
+            stale: self.stale,
+            require_fresh_db: self.require_fresh_db,
+            target_arch: self.target_arch,
+            target_os: self.target_os,
+            target: None,
+            url: self.url,
+            quiet: self.quiet,
+            output_json: self.output_json,
+            output_format: self.output_format,
+            release_info: self.release_info,
+            suggest_upgrades: self.suggest_upgrades,
+            group_versions: self.group_versions,
+            summary: self.summary,
+            group_by: self.group_by,
+            strict: self.strict,
+            deprecated: self.deprecated,
+            include_withdrawn: self.include_withdrawn,
+            filter_symbols: self.filter_symbols,
+            debug_info,
+            ignore_features: false,
+            exclude_dev: false,
+            check_requirements: false,
+            osv: self.osv,
+            ghsa: self.ghsa,
+            nvd: self.nvd,
+            vcr: self.vcr,
+            vcr_record: self.vcr_record,
+            network: self.network,
+        }
+    }
+}
+
+#[cfg(feature = "binary-scanning")]
+/// The `cargo audit` subcommand
+#[derive(Command, Clone, Default, Debug, Parser)]
+#[command()]
+pub struct BinCommand {
+    /// Options shared with `cargo audit image`
+    #[command(flatten)]
+    pub args: BinaryAuditArgs,
+
+    /// Separate debug info of stripped binaries
+    #[arg(
+        long = "debug-info",
+        value_name = "PATH",
+        help = "Debug info file, or directory to search for debug info of stripped binaries (can be specified multiple times)"
+    )]
+    debug_info: Vec<PathBuf>,
+
+    /// Group per-platform builds of the same artifact into a single report
+    #[arg(
+        long = "group-platforms",
+        help = "Merge reports for per-platform builds of the same artifact (e.g. myapp-x86_64-unknown-linux-gnu and myapp-aarch64-apple-darwin)"
+    )]
+    group_platforms: bool,
+
+    /// Audit all of the executables under the given directories
+    #[arg(
+        short = 'r',
+        long = "recursive",
+        conflicts_with = "group_platforms",
+        help = "Audit all executables (and archives) found under the given directories, in parallel, with one aggregated report"
+    )]
+    recursive: bool,
+
+    /// Print the dependencies recovered from the binaries instead of auditing them
+    #[arg(
+        long = "print-deps",
+        conflicts_with_all = ["group_platforms", "recursive"],
+        help = "Print the dependency graph recovered from the binaries (as JSON with --json) instead of auditing them"
+    )]
+    print_deps: bool,
+
     /// Paths to the binaries to be scanned
     #[arg(
         value_parser,
         required = true,
-        help = "Paths to the binaries to be scanned"
+        help = "Paths to the binaries (or archives containing them) to be scanned, or directories with --recursive"
     )]
     binary_paths: Vec<PathBuf>,
 }
 
 impl Runnable for BinCommand {
     fn run(&self) {
-        let report = self.auditor().audit_binaries(&self.binary_paths);
+        if self.print_deps {
+            self.print_binary_deps();
+        }
+
+        // GitLab reports describe the findings of a single dependency file
+        if (self.recursive || self.group_platforms)
+            && APP.config().output.format == OutputFormat::Gitlab
+        {
+            status_err!(
+                "`--format gitlab` can't be used with `--recursive` or `--group-platforms`"
+            );
+            exit(2);
+        }
+
+        let mut auditor = self.auditor();
+        let report = if self.recursive {
+            auditor.audit_binary_tree(&self.binary_paths)
+        } else if self.group_platforms {
+            auditor.audit_binary_groups(&self.binary_paths)
+        } else {
+            auditor.audit_binaries(&self.binary_paths)
+        };
         if report.vulnerabilities_found {
             exit(1)
         } else if report.errors_encountered {
@@ -121,25 +363,20 @@ impl Runnable for BinCommand {
 
 impl From<BinCommand> for CliConfig {
     fn from(c: BinCommand) -> Self {
-        CliConfig {
-            db: c.db,
-            deny: c.deny,
-            ignore: c.ignore,
-            ignore_source: c.ignore_source,
-            no_fetch: c.no_fetch,
-            stale: c.stale,
-            target_arch: c.target_arch,
-            target_os: c.target_os,
-            url: c.url,
-            quiet: c.quiet,
-            output_json: c.output_json,
-        }
+        c.args.cli_config(c.debug_info)
     }
 }
 
 impl Override<AuditConfig> for BinCommand {
     fn override_config(&self, config: AuditConfig) -> Result<AuditConfig, FrameworkError> {
-        CliConfig::from(self.clone()).override_config(config)
+        let mut config = CliConfig::from(self.clone()).override_config(config)?;
+
+        // Keep stdout reserved for the dependency lists
+        if self.print_deps {
+            config.output.quiet = true;
+        }
+
+        Ok(config)
     }
 }
 
@@ -148,4 +385,41 @@ impl BinCommand {
     pub fn auditor(&self) -> Auditor {
         Auditor::new(&APP.config())
     }
+
+    /// Print the dependencies recovered from each binary, without loading the
+    /// advisory database
+    fn print_binary_deps(&self) -> ! {
+        let config = APP.config();
+        let json = config.output.format == OutputFormat::Json;
+        let mut binaries = vec![];
+        let mut errors_encountered = false;
+
+        for path in &self.binary_paths {
+            let loaded = load_binaries(path).and_then(|loaded| {
+                loaded
+                    .iter()
+                    .map(|(path, data)| BinaryDeps::load(path, data, &config.advisories.debug_info))
+                    .collect::<Result<Vec<_>, _>>()
+            });
+
+            match loaded {
+                Ok(mut deps) => binaries.append(&mut deps),
+                Err(e) => {
+                    status_err!("{}", display_err_with_source(&e));
+                    errors_encountered = true;
+                }
+            }
+        }
+
+        if json {
+            let output = serde_json::json!({ "binaries": binaries });
+            println!("{}", output);
+        } else {
+            for deps in &binaries {
+                print!("{}", deps.to_text());
+            }
+        }
+
+        exit(if errors_encountered { 2 } else { 0 })
+    }
 }
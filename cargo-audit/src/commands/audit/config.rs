@@ -0,0 +1,72 @@
+//! The `cargo audit config` subcommand
+
+use crate::{
+    config_layers::{self, Layer},
+    prelude::*,
+};
+use abscissa_core::{Command, Runnable};
+use clap::{Parser, Subcommand};
+use std::{env, process::exit};
+
+/// The `cargo audit config` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct ConfigCommand {
+    /// Operation to perform
+    #[command(subcommand)]
+    subcommand: ConfigSubcommand,
+}
+
+/// Subcommands of `cargo audit config`
+#[derive(Subcommand, Clone, Debug, Runnable)]
+pub enum ConfigSubcommand {
+    /// `cargo audit config show` subcommand
+    #[command(about = "show the configuration files, or the effective configuration")]
+    Show(ShowConfigCommand),
+}
+
+/// The `cargo audit config show` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct ShowConfigCommand {
+    /// Print the effective configuration instead of the configuration files
+    #[arg(
+        long = "resolved",
+        help = "Print the effective configuration after merging all configuration files and command-line flags"
+    )]
+    resolved: bool,
+}
+
+impl Runnable for ConfigCommand {
+    fn run(&self) {
+        self.subcommand.run()
+    }
+}
+
+impl Runnable for ShowConfigCommand {
+    fn run(&self) {
+        if !self.resolved {
+            // From lowest to highest precedence
+            for file in config_layers::discover() {
+                let state = if file.exists() { "loaded" } else { "not found" };
+                println!(
+                    "{:<9} {} ({})",
+                    file.layer.as_str(),
+                    file.path.display(),
+                    state
+                );
+            }
+            for name in config_layers::env_var_names(env::vars_os()) {
+                println!("{:<9} {}", Layer::Environment.as_str(), name);
+            }
+            return;
+        }
+
+        match toml::to_string_pretty(&*APP.config()) {
+            Ok(toml) => print!("{}", toml),
+            Err(e) => {
+                status_err!("couldn't serialize configuration: {}", e);
+                exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! The `cargo audit db` subcommand
+
+use crate::{
+    auditor::{load_database, load_local_database},
+    config::SeverityBucket,
+    error::display_err_with_source,
+    export::{self, ExportFormat},
+    prelude::*,
+};
+use abscissa_core::{Command, Runnable};
+use clap::{Parser, Subcommand};
+use rustsec::repository::git::{Repository, DEFAULT_URL};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap as Map,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::exit,
+};
+
+/// The `cargo audit db` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct DbCommand {
+    /// Operation to perform on the advisory database
+    #[command(subcommand)]
+    subcommand: DbSubcommand,
+}
+
+/// Subcommands of `cargo audit db`
+#[derive(Subcommand, Clone, Debug, Runnable)]
+pub enum DbSubcommand {
+    /// `cargo audit db export` subcommand
+    #[command(about = "export the advisory database to another format")]
+    Export(ExportCommand),
+
+    /// `cargo audit db export-osv` subcommand
+    #[command(about = "export every advisory to a directory of OSV JSON files")]
+    ExportOsv(ExportOsvCommand),
+
+    /// `cargo audit db stats` subcommand
+    #[command(about = "print statistics about the advisory database")]
+    Stats(StatsCommand),
+
+    /// `cargo audit db status` subcommand
+    #[command(about = "show the state of the local advisory database")]
+    Status(StatusCommand),
+}
+
+/// The `cargo audit db export` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct ExportCommand {
+    /// Format to export the database in
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value = "sqlite",
+        help = "Format to export the database in"
+    )]
+    format: ExportFormat,
+
+    /// File to write the export to
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "FILE",
+        help = "File to write the export to (default: stdout)"
+    )]
+    output: Option<PathBuf>,
+
+    /// Compress the export
+    #[cfg(feature = "compress")]
+    #[arg(
+        long = "compress",
+        value_name = "FORMAT",
+        requires = "output",
+        help = "Compress the output file: gzip or zstd"
+    )]
+    compress: Option<crate::compression::Compression>,
+}
+
+/// The `cargo audit db export-osv` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct ExportOsvCommand {
+    /// Directory to write the OSV advisories to
+    #[arg(value_name = "DIR", help = "Directory to write the OSV advisories to")]
+    dir: PathBuf,
+}
+
+/// The `cargo audit db stats` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct StatsCommand {
+    /// Number of most-affected crates to list
+    #[arg(
+        long = "top",
+        value_name = "N",
+        default_value = "10",
+        help = "Number of most-affected crates to list"
+    )]
+    top: usize,
+
+    /// Print the statistics as JSON
+    #[arg(long = "json", help = "Print the statistics as JSON")]
+    json: bool,
+}
+
+/// The `cargo audit db status` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+pub struct StatusCommand {
+    /// Print the status as JSON
+    #[arg(long = "json", help = "Print the status as JSON")]
+    json: bool,
+}
+
+/// State of the local copy of the advisory database
+#[derive(Serialize)]
+struct Status {
+    /// Path of the local copy
+    path: PathBuf,
+
+    /// URL the database is fetched from
+    url: String,
+
+    /// Does the local copy exist?
+    exists: bool,
+
+    /// When the database was last fetched (in UTC), if known
+    last_fetched: Option<String>,
+
+    /// Latest commit of the local copy, if it is a git repository
+    commit: Option<CommitStats>,
+
+    /// Number of advisories, including withdrawn ones
+    advisories: Option<usize>,
+
+    /// Is the database stale? (i.e. its latest commit is over 90 days old)
+    stale: bool,
+
+    /// Does the configuration allow a stale database?
+    stale_allowed: bool,
+}
+
+impl Status {
+    /// Inspect the local copy of the advisory database, without fetching it
+    fn new(config: &crate::config::AuditConfig) -> Self {
+        let path = config
+            .database
+            .path
+            .clone()
+            .unwrap_or_else(Repository::default_path);
+
+        let url = config
+            .database
+            .url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_URL.to_owned());
+
+        let repo = Repository::open(&path).ok();
+        let latest_commit = repo.as_ref().and_then(|repo| repo.latest_commit().ok());
+
+        Self {
+            exists: path.is_dir(),
+            last_fetched: repo
+                .as_ref()
+                .and_then(Repository::last_fetched)
+                .map(format_time),
+            commit: latest_commit.as_ref().map(|commit| CommitStats {
+                hash: commit.commit_id.to_hex(),
+                date: commit.timestamp.date().to_string(),
+            }),
+            advisories: load_local_database(config, &path)
+                .ok()
+                .map(|db| db.iter().count()),
+            stale: latest_commit
+                .as_ref()
+                .map(|commit| !commit.is_fresh())
+                .unwrap_or(true),
+            stale_allowed: config.database.stale && !config.output.strict,
+            path,
+            url,
+        }
+    }
+
+    /// Is the database usable under the configured policy?
+    fn is_ok(&self) -> bool {
+        self.advisories.is_some() && (!self.stale || self.stale_allowed)
+    }
+
+    /// Print the status in a human-readable format
+    fn print(&self) {
+        let unknown = || "unknown".to_owned();
+
+        println!("Path:          {}", self.path.display());
+        println!("URL:           {}", self.url);
+
+        if !self.exists {
+            println!("Status:        missing (run `cargo audit` to fetch it)");
+            return;
+        }
+
+        println!(
+            "Last fetched:  {}",
+            self.last_fetched.clone().unwrap_or_else(unknown)
+        );
+        println!(
+            "Latest commit: {}",
+            self.commit
+                .as_ref()
+                .map(|commit| format!("{} ({})", commit.hash, commit.date))
+                .unwrap_or_else(unknown)
+        );
+        println!(
+            "Advisories:    {}",
+            self.advisories
+                .map(|count| count.to_string())
+                .unwrap_or_else(unknown)
+        );
+        println!(
+            "Status:        {}",
+            match (self.stale, self.stale_allowed) {
+                (false, _) => "fresh",
+                (true, true) => "stale (allowed by configuration)",
+                (true, false) => "stale",
+            }
+        );
+    }
+}
+
+/// Format a time as e.g. `2024-01-31T12:34:56Z`
+fn format_time(time: std::time::SystemTime) -> String {
+    format!("{}Z", crate::timestamp::format(time))
+}
+
+/// Statistics about the advisory database
+#[derive(Serialize)]
+struct Stats {
+    /// Number of advisories, including withdrawn ones
+    advisories: usize,
+
+    /// Number of withdrawn advisories
+    withdrawn: usize,
+
+    /// Number of advisories by the year they were published in
+    years: Map<u32, usize>,
+
+    /// Number of vulnerability (i.e. not informational) advisories by severity
+    severities: Map<SeverityBucket, usize>,
+
+    /// Number of informational advisories by kind
+    informational: Map<String, usize>,
+
+    /// Crates with the most advisories, most affected first
+    most_affected: Vec<CrateStats>,
+
+    /// Latest commit of the advisory database repository, if known
+    commit: Option<CommitStats>,
+}
+
+/// Number of advisories about a crate
+#[derive(Serialize)]
+struct CrateStats {
+    name: String,
+    advisories: usize,
+}
+
+/// Latest commit of the advisory database repository
+#[derive(Serialize)]
+struct CommitStats {
+    hash: String,
+    date: String,
+}
+
+impl Stats {
+    /// Compute the statistics of a database, listing the `top` most-affected
+    /// crates
+    fn new(db: &rustsec::Database, top: usize) -> Self {
+        let mut stats = Self {
+            advisories: 0,
+            withdrawn: 0,
+            years: Map::new(),
+            severities: Map::new(),
+            informational: Map::new(),
+            most_affected: vec![],
+            commit: db.latest_commit().map(|commit| CommitStats {
+                hash: commit.commit_id.to_hex(),
+                date: commit.timestamp.date().to_string(),
+            }),
+        };
+
+        let mut crates: Map<&str, usize> = Map::new();
+
+        for advisory in db.iter() {
+            let metadata = &advisory.metadata;
+            stats.advisories += 1;
+
+            if metadata.withdrawn.is_some() {
+                stats.withdrawn += 1;
+            }
+
+            *stats.years.entry(metadata.date.year()).or_default() += 1;
+
+            match &metadata.informational {
+                Some(informational) => {
+                    *stats
+                        .informational
+                        .entry(informational.as_str().to_owned())
+                        .or_default() += 1;
+                }
+                None => {
+                    let severity = metadata.primary_cvss().map(|cvss| cvss.severity());
+                    *stats
+                        .severities
+                        .entry(SeverityBucket::from(severity))
+                        .or_default() += 1;
+                }
+            }
+
+            *crates.entry(metadata.package.as_str()).or_default() += 1;
+        }
+
+        let mut crates: Vec<_> = crates.into_iter().collect();
+        crates.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        stats.most_affected = crates
+            .into_iter()
+            .take(top)
+            .map(|(name, advisories)| CrateStats {
+                name: name.to_owned(),
+                advisories,
+            })
+            .collect();
+
+        stats
+    }
+
+    /// Print the statistics in a human-readable format
+    fn print(&self) {
+        println!(
+            "Advisories:    {} ({} withdrawn)",
+            self.advisories, self.withdrawn
+        );
+
+        if let Some(commit) = &self.commit {
+            println!("Latest commit: {} ({})", commit.hash, commit.date);
+        }
+
+        println!("\nBy year:");
+        for (year, count) in &self.years {
+            println!("  {:<14} {}", year, count);
+        }
+
+        println!("\nBy severity:");
+        for (severity, count) in self.severities.iter().rev() {
+            println!("  {:<14} {}", severity.as_str(), count);
+        }
+
+        println!("\nInformational:");
+        for (kind, count) in &self.informational {
+            println!("  {:<14} {}", kind, count);
+        }
+
+        println!("\nMost affected crates:");
+        for krate in &self.most_affected {
+            println!("  {:<14} {}", krate.name, krate.advisories);
+        }
+    }
+}
+
+impl DbCommand {
+    /// Is the output of this command written to stdout?
+    pub fn writes_to_stdout(&self) -> bool {
+        match &self.subcommand {
+            DbSubcommand::Export(export) => export.output.is_none(),
+            DbSubcommand::ExportOsv(_) => false,
+            DbSubcommand::Stats(stats) => stats.json,
+            DbSubcommand::Status(status) => status.json,
+        }
+    }
+}
+
+impl Runnable for DbCommand {
+    fn run(&self) {
+        self.subcommand.run()
+    }
+}
+
+impl Runnable for ExportCommand {
+    fn run(&self) {
+        let format = self.format;
+        let db = load_database(&APP.config());
+
+        let result = match &self.output {
+            Some(path) => File::create(path).and_then(|file| {
+                let mut writer = BufWriter::new(file);
+
+                #[cfg(feature = "compress")]
+                if let Some(compression) = self.compress {
+                    let mut encoder = compression.encoder(writer)?;
+                    export::export(&db, format, &mut encoder)?;
+                    return encoder.finish()?.flush();
+                }
+
+                export::export(&db, format, &mut writer)?;
+                writer.flush()
+            }),
+            None => {
+                let stdout = io::stdout();
+                export::export(&db, format, &mut stdout.lock())
+            }
+        };
+
+        if let Err(e) = result {
+            status_err!("couldn't export advisory database: {}", e);
+            exit(2);
+        }
+
+        if let Some(path) = &self.output {
+            status_ok!(
+                "Exported",
+                "{} advisories to {}",
+                db.iter().count(),
+                path.display()
+            );
+        }
+    }
+}
+
+impl Runnable for ExportOsvCommand {
+    fn run(&self) {
+        let db = load_database(&APP.config());
+
+        let count = rustsec::osv::write_database(&db, &self.dir).unwrap_or_else(|e| {
+            status_err!(
+                "couldn't export advisory database: {}",
+                display_err_with_source(&e)
+            );
+            exit(2);
+        });
+
+        status_ok!("Exported", "{} advisories to {}", count, self.dir.display());
+    }
+}
+
+impl Runnable for StatsCommand {
+    fn run(&self) {
+        let db = load_database(&APP.config());
+        let stats = Stats::new(&db, self.top);
+
+        if self.json {
+            serde_json::to_writer(io::stdout(), &stats).unwrap();
+            println!();
+        } else {
+            stats.print();
+        }
+    }
+}
+
+impl Runnable for StatusCommand {
+    fn run(&self) {
+        let status = Status::new(&APP.config());
+
+        if self.json {
+            serde_json::to_writer(io::stdout(), &status).unwrap();
+            println!();
+        } else {
+            status.print();
+        }
+
+        // Missing or (disallowed) stale databases fail, so that CI can
+        // check the database separately from auditing
+        if !status.is_ok() {
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::database::AdvisoryBuilder;
+
+    #[test]
+    fn stats() {
+        let db = rustsec::Database::builder()
+            .advisories([
+                AdvisoryBuilder::new("RUSTSEC-2020-0001", "foo")
+                    .metadata(r#"cvss = "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H""#)
+                    .build()
+                    .unwrap(),
+                AdvisoryBuilder::new("RUSTSEC-2021-0001", "foo")
+                    .metadata(r#"informational = "unmaintained""#)
+                    .build()
+                    .unwrap(),
+                AdvisoryBuilder::new("RUSTSEC-2021-0002", "bar")
+                    .metadata(r#"withdrawn = "2021-06-01""#)
+                    .build()
+                    .unwrap(),
+            ])
+            .build()
+            .unwrap();
+
+        let stats = Stats::new(&db, 1);
+        assert_eq!(stats.advisories, 3);
+        assert_eq!(stats.withdrawn, 1);
+        assert_eq!(stats.years, Map::from([(2020, 1), (2021, 2)]));
+        assert_eq!(
+            stats.severities,
+            Map::from([(SeverityBucket::Unscored, 1), (SeverityBucket::Critical, 1)])
+        );
+        assert_eq!(
+            stats.informational,
+            Map::from([("unmaintained".to_owned(), 1)])
+        );
+        assert_eq!(stats.most_affected.len(), 1);
+        assert_eq!(stats.most_affected[0].name, "foo");
+        assert_eq!(stats.most_affected[0].advisories, 2);
+        assert!(stats.commit.is_none());
+    }
+
+    /// Crates with as many advisories are listed by name
+    #[test]
+    fn most_affected_ties() {
+        let db = rustsec::Database::builder()
+            .advisories([
+                AdvisoryBuilder::new("RUSTSEC-2020-0001", "foo")
+                    .build()
+                    .unwrap(),
+                AdvisoryBuilder::new("RUSTSEC-2020-0002", "bar")
+                    .build()
+                    .unwrap(),
+            ])
+            .build()
+            .unwrap();
+
+        let names: Vec<_> = Stats::new(&db, 10)
+            .most_affected
+            .into_iter()
+            .map(|stats| stats.name)
+            .collect();
+        assert_eq!(names, ["bar", "foo"]);
+    }
+}
@@ -0,0 +1,66 @@
+//! The `cargo audit diff` subcommand
+
+use crate::{config::OutputFormat, prelude::*, presenter::Presenter};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use rustsec::{
+    report::{diff::ReportDiff, serialization},
+    Report,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+/// The `cargo audit diff` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct DiffCommand {
+    /// Baseline report
+    #[arg(
+        value_name = "OLD",
+        help = "Baseline report, as printed by `cargo audit --json`"
+    )]
+    old: PathBuf,
+
+    /// Report to compare with the baseline
+    #[arg(value_name = "NEW", help = "Report to compare with the baseline")]
+    new: PathBuf,
+}
+
+impl Runnable for DiffCommand {
+    fn run(&self) {
+        // GitLab reports list the dependencies from a lockfile
+        let config = APP.config();
+        if config.output.format == OutputFormat::Gitlab {
+            status_err!("`cargo audit diff` can't output GitLab reports");
+            exit(2);
+        }
+
+        let old = load_report(&self.old);
+        let new = load_report(&self.new);
+        let diff = ReportDiff::new(&old, &new);
+
+        let presenter = Presenter::new(&config.output);
+        presenter.print_report_diff(&diff);
+
+        // Only findings which weren't in the baseline fail the comparison
+        if presenter.should_exit_with_failure(&diff.introduced) {
+            exit(1);
+        }
+    }
+}
+
+/// Load a JSON report, exiting if it can't be read or parsed
+pub(super) fn load_report(path: &Path) -> Report {
+    let json = fs::read_to_string(path).unwrap_or_else(|e| {
+        status_err!("couldn't read {}: {}", path.display(), e);
+        exit(2);
+    });
+
+    serialization::from_str(&json).unwrap_or_else(|e| {
+        status_err!("couldn't parse {}: {}", path.display(), e);
+        exit(2);
+    })
+}
@@ -1,14 +1,25 @@
 //! The `cargo audit fix` subcommand
 
-use crate::{auditor::Auditor, lockfile, prelude::*};
+use crate::{
+    auditor::Auditor,
+    error::display_err_with_source,
+    lockfile,
+    manifest::{self, ManifestEdit, RequirementEdit, Upgrade},
+    metadata::Metadata,
+    prelude::*,
+    text_diff::unified_diff,
+};
 use abscissa_core::{Command, Runnable};
 use cargo_lock::Lockfile;
 use clap::Parser;
-use rustsec::{advisory::Id, Fixer};
+use is_terminal::IsTerminal;
+use rustsec::{advisory::Id, package, Changes, Fixer, Version, Vulnerability};
+use serde::Serialize;
 use std::{
     collections::BTreeSet,
+    io::{self, Write},
     path::{Path, PathBuf},
-    process::exit,
+    process::{self, exit},
 };
 
 #[derive(Command, Clone, Default, Debug, Parser)]
@@ -18,9 +29,54 @@ pub struct FixCommand {
     #[arg(short = 'f', long = "file", help = "Cargo lockfile to inspect")]
     file: Option<PathBuf>,
 
+    /// Path to `Cargo.toml`
+    #[arg(
+        long = "manifest-path",
+        value_name = "PATH",
+        conflicts_with = "file",
+        help = "Path to Cargo.toml of a package whose workspace's Cargo.lock is fixed"
+    )]
+    manifest_path: Option<PathBuf>,
+
     /// Perform a dry run
-    #[arg(long = "dry-run", help = "perform a dry run for the fix")]
+    #[arg(
+        long = "dry-run",
+        help = "perform a dry run for the fix, printing a diff of the planned changes"
+    )]
     dry_run: bool,
+
+    /// Output the planned changes of a dry run as JSON
+    #[arg(
+        long = "json",
+        requires = "dry_run",
+        help = "output the planned changes of a dry run as JSON"
+    )]
+    json: bool,
+
+    /// Choose which fixes to apply
+    #[arg(
+        long = "interactive",
+        conflicts_with = "dry_run",
+        help = "walk through each fix, choosing to accept, skip or pin a version"
+    )]
+    interactive: bool,
+}
+
+/// Command applying a fix, along with the name of the package it updates
+type Fix = (package::Name, process::Command);
+
+/// Changes planned by a dry run, as output with `--json`
+#[derive(Serialize)]
+struct Plan<'a> {
+    /// Changes to the packages in `Cargo.lock`
+    #[serde(flatten)]
+    changes: &'a Changes,
+
+    /// Changes to requirements in `Cargo.toml` files
+    requirements: Vec<&'a RequirementEdit>,
+
+    /// Advisories without patched versions, which can't be fixed
+    unpatchable: &'a BTreeSet<Id>,
 }
 
 impl FixCommand {
@@ -33,26 +89,279 @@ impl FixCommand {
     pub fn cargo_lock_path(&self) -> Option<&Path> {
         self.file.as_deref()
     }
+
+    /// Print the changes the fixes would make without applying them.
+    ///
+    /// The changes to `Cargo.lock` are those reported by
+    /// `cargo update --dry-run`, which never writes it.
+    fn dry_run(
+        &self,
+        fixer: &Fixer,
+        path: &Path,
+        patchable: &[Vulnerability],
+        unpatchable: &BTreeSet<Id>,
+        manifest_edits: &[ManifestEdit],
+    ) {
+        let changes = self
+            .planned_changes(fixer, patchable)
+            .unwrap_or_else(|message| {
+                status_err!("{}", message);
+                exit(2);
+            });
+
+        if self.json {
+            let plan = Plan {
+                changes: &changes,
+                requirements: manifest_edits
+                    .iter()
+                    .flat_map(|edit| &edit.requirements)
+                    .collect(),
+                unpatchable,
+            };
+            println!("{}", serde_json::to_string(&plan).unwrap());
+            return self.exit_after_dry_run(unpatchable);
+        }
+
+        for edit in manifest_edits {
+            print!(
+                "{}",
+                unified_diff(&edit.path.display().to_string(), &edit.old, &edit.new)
+            );
+        }
+
+        if changes.is_empty() {
+            if manifest_edits.is_empty() {
+                status_warn!("No changes would be made to {}", path.display());
+            }
+        } else {
+            println!("{}:", path.display());
+            for bump in &changes.bumps {
+                println!("  update {} {} -> {}", bump.name, bump.from, bump.to);
+            }
+            for package in &changes.added {
+                println!("  add {} {}", package.name, package.version);
+            }
+            for package in &changes.removed {
+                println!("  remove {} {}", package.name, package.version);
+            }
+        }
+
+        if !manifest_edits.is_empty() {
+            status_warn!(
+                "{} will also be re-resolved after changing requirements",
+                path.display()
+            );
+        }
+
+        self.exit_after_dry_run(unpatchable)
+    }
+
+    /// Run the `cargo update --dry-run` commands for the fixes of the given
+    /// vulnerabilities, collecting the changes they report. Cargo's output is
+    /// only shown if one fails.
+    fn planned_changes(
+        &self,
+        fixer: &Fixer,
+        patchable: &[Vulnerability],
+    ) -> Result<Changes, String> {
+        let mut changes = Changes::default();
+
+        for vulnerability in patchable {
+            for mut command in fixer.get_fix_commands(vulnerability, true) {
+                if let Some(dir) = self.command_dir() {
+                    command.current_dir(dir);
+                }
+
+                let output = command
+                    .output()
+                    .map_err(|e| format!("Failed to run `cargo update`: {}", e))?;
+
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.status.success() {
+                    return Err(format!("`cargo update` failed:\n{}", stderr.trim_end()));
+                }
+
+                changes.merge(Changes::from_dry_run(&stderr));
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Exit after a dry run
+    fn exit_after_dry_run(&self, unpatchable: &BTreeSet<Id>) -> ! {
+        // When performing a dry run, the exit status is determined by whether we had any issues along the way
+        if !unpatchable.is_empty() {
+            exit(1);
+        } else {
+            exit(0)
+        }
+    }
+
+    /// Walk through the fixes for each vulnerable package, asking whether to
+    /// apply the proposed update, skip it, or update to a specific version
+    fn choose_fixes(&self, fixer: &Fixer, patchable: &[Vulnerability]) -> Vec<Fix> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            status_err!("`--interactive` requires a terminal");
+            exit(2);
+        }
+
+        let mut commands = vec![];
+
+        for vulnerability in patchable {
+            for package in fixer.vulnerable_packages(vulnerability) {
+                let proposed = fixer.proposed_version(vulnerability, package);
+
+                println!();
+                println!(
+                    "{}: {}",
+                    vulnerability.advisory.id, vulnerability.advisory.title
+                );
+                println!("  Crate:    {}", package.name);
+                println!("  Current:  {}", package.version);
+                println!(
+                    "  Patched:  {}",
+                    vulnerability
+                        .versions
+                        .patched()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" OR ")
+                );
+                match &proposed {
+                    Some(version) => println!("  Proposed: {}", version),
+                    None => println!(
+                        "  Proposed: latest semver-compatible version (may not be patched)"
+                    ),
+                }
+
+                loop {
+                    let answer = prompt("Apply this fix? [a]ccept, [s]kip, [p]in a version: ")
+                        .map(|answer| answer.to_lowercase());
+                    match answer.as_deref() {
+                        Some("a") | Some("accept") => {
+                            commands.push((
+                                package.name.clone(),
+                                fixer.get_update_command(package, proposed.as_ref(), false),
+                            ));
+                            break;
+                        }
+                        Some("s") | Some("skip") | None => break,
+                        Some("p") | Some("pin") => {
+                            let version = prompt("Version to update to: ");
+                            match version.as_deref().map(str::parse::<Version>) {
+                                Some(Ok(version)) => {
+                                    commands.push((
+                                        package.name.clone(),
+                                        fixer.get_update_command(package, Some(&version), false),
+                                    ));
+                                    break;
+                                }
+                                Some(Err(e)) => status_warn!("invalid version: {}", e),
+                                None => break,
+                            }
+                        }
+                        Some(_) => continue,
+                    }
+                }
+            }
+        }
+
+        commands
+    }
+
+    /// Plan changes to the requirements in the workspace's manifests for
+    /// vulnerable packages which have no semver-compatible patched version
+    fn plan_manifest_edits(
+        &self,
+        fixer: &Fixer,
+        path: &Path,
+        patchable: &[Vulnerability],
+    ) -> Vec<ManifestEdit> {
+        let upgrades: Vec<Upgrade> = patchable
+            .iter()
+            .flat_map(|vulnerability| {
+                fixer
+                    .vulnerable_packages(vulnerability)
+                    .filter(|package| fixer.proposed_version(vulnerability, package).is_none())
+                    .filter_map(|package| {
+                        Some(Upgrade {
+                            name: package.name.clone(),
+                            current: package.version.clone(),
+                            target: fixer.upgrade_version(vulnerability, package)?,
+                        })
+                    })
+            })
+            .collect();
+
+        if upgrades.is_empty() {
+            return vec![];
+        }
+
+        let result = Metadata::load(path)
+            .and_then(|metadata| manifest::plan(&metadata.manifests(), &upgrades));
+
+        match result {
+            Ok(edits) => edits,
+            Err(e) => {
+                status_warn!(
+                    "Couldn't find requirements to change in Cargo.toml: {}",
+                    display_err_with_source(&e)
+                );
+                vec![]
+            }
+        }
+    }
+
+    /// Directory to run `cargo update` in, if the path to `Cargo.lock` or
+    /// `Cargo.toml` has been specified explicitly
+    fn command_dir(&self) -> Option<PathBuf> {
+        let path = self.cargo_lock_path().or(self.manifest_path.as_deref())?;
+
+        // documentation on .current_dir() recommends canonicalizing the path
+        let canonical_path = path.canonicalize().unwrap_or_else(|e| {
+            status_err!("Failed to resolve {}: {}", path.display(), e);
+            exit(2);
+        });
+        canonical_path.parent().map(Path::to_owned)
+    }
 }
 
 impl Runnable for FixCommand {
     fn run(&self) {
-        let path = lockfile::locate_or_generate(self.cargo_lock_path()).unwrap_or_else(|e| {
-            status_err!("{}", e);
-            exit(2);
-        });
+        let path =
+            lockfile::locate_or_generate(self.cargo_lock_path(), self.manifest_path.as_deref())
+                .unwrap_or_else(|e| {
+                    status_err!("{}", display_err_with_source(&e));
+                    exit(2);
+                });
 
-        let report = self.auditor().audit_lockfile(&path);
+        // The JSON output of a dry run is all that's printed to stdout
+        let report = if self.json {
+            self.auditor().report_lockfile(&path)
+        } else {
+            self.auditor().audit_lockfile(&path)
+        };
         let report = match report {
             Ok(report) => {
                 // TODO: also handle warnings
                 if report.vulnerabilities.list.is_empty() {
+                    if self.json {
+                        let plan = Plan {
+                            changes: &Changes::default(),
+                            requirements: vec![],
+                            unpatchable: &BTreeSet::new(),
+                        };
+                        println!("{}", serde_json::to_string(&plan).unwrap());
+                    }
                     exit(0);
                 }
                 report
             }
             Err(e) => {
-                status_err!("{}", e);
+                status_err!("{}", display_err_with_source(&e));
                 exit(2);
             }
         };
@@ -65,102 +374,279 @@ impl Runnable for FixCommand {
         let fixer = Fixer::new(lockfile, None, path_to_cargo);
 
         let dry_run = self.dry_run;
-        if dry_run {
+        if dry_run && !self.json {
             status_warn!("Performing a dry run, the fixes will not be applied");
         }
 
         let mut unpatchable_vulns: BTreeSet<Id> = BTreeSet::new();
-        let mut failed_patches = 0;
+        let mut patchable_vulns = vec![];
 
         for vulnerability in &report.vulnerabilities.list {
             if vulnerability.versions.patched().is_empty() {
                 unpatchable_vulns.insert(vulnerability.advisory.id.clone());
+                if !self.json {
+                    status_warn!(
+                        "No patched versions available for {} in crate {}",
+                        vulnerability.advisory.id,
+                        vulnerability.package.name
+                    );
+                }
+            } else {
+                patchable_vulns.push(vulnerability.clone());
+            }
+        }
+
+        // Requirements are only changed outside of interactive mode, where
+        // the versions to update to can be chosen instead
+        let manifest_edits = if self.interactive {
+            vec![]
+        } else {
+            self.plan_manifest_edits(&fixer, &path, &patchable_vulns)
+        };
+
+        if dry_run {
+            self.dry_run(
+                &fixer,
+                &path,
+                &patchable_vulns,
+                &unpatchable_vulns,
+                &manifest_edits,
+            );
+        }
+
+        // Packages whose requirements change are updated when re-resolving
+        // the lockfile once all changes have been made
+        let upgraded: BTreeSet<&package::Name> = manifest_edits
+            .iter()
+            .flat_map(|edit| &edit.requirements)
+            .map(|requirement| &requirement.name)
+            .collect();
+
+        let fixes: Vec<Fix> = if self.interactive {
+            self.choose_fixes(&fixer, &patchable_vulns)
+        } else {
+            patchable_vulns
+                .iter()
+                .filter(|vulnerability| !upgraded.contains(&vulnerability.package.name))
+                .flat_map(|vulnerability| {
+                    fixer
+                        .get_fix_commands(vulnerability, false)
+                        .into_iter()
+                        .map(|command| (vulnerability.package.name.clone(), command))
+                })
+                .collect()
+        };
+
+        if fixes.is_empty() && manifest_edits.is_empty() {
+            status_warn!("No fixes were applied");
+            exit(1);
+        }
+
+        for (i, edit) in manifest_edits.iter().enumerate() {
+            if let Err(e) = edit.apply() {
+                status_err!("Failed to write {}: {}", edit.path.display(), e);
+                revert_manifests(&manifest_edits[..i]);
+                exit(2);
+            }
+
+            for requirement in &edit.requirements {
+                status_ok!(
+                    "Updating",
+                    "requirement on {} in {}: {} -> {}",
+                    requirement.name,
+                    edit.path.display(),
+                    requirement.from,
+                    requirement.to
+                );
+            }
+        }
+
+        let mut failed_patches = 0;
+
+        for (package_name, mut command) in fixes {
+            // If the path to Cargo.lock has been specified explicitly,
+            // run the `cargo update` command in that directory
+            if let Some(dir) = self.command_dir() {
+                command.current_dir(dir);
+            }
+            // When calling `.status()` the stdout and stderr are inherited from the parent,
+            // so any status or error messages from `cargo update` will automatically be forwarded
+            // to the user of `cargo audit fix`.
+            let status = command.status();
+            if let Err(e) = status {
+                failed_patches += 1;
                 status_warn!(
-                    "No patched versions available for {} in crate {}",
-                    vulnerability.advisory.id,
-                    vulnerability.package.name
+                    "Failed to run `cargo update` for package {}: {}",
+                    package_name,
+                    e
                 );
-            } else {
-                let mut command = fixer.get_fix_command(vulnerability, dry_run);
-                // If the path to Cargo.lock has been specified explicitly,
-                // run the `cargo update` command in that directory
-                if let Some(path) = self.cargo_lock_path() {
-                    // documentation on .current_dir() recommends canonicalizing the path
-                    let canonical_path = path.canonicalize().unwrap();
-                    let dir = canonical_path.parent().unwrap();
-                    command.current_dir(dir);
+            }
+        }
+
+        if !manifest_edits.is_empty() {
+            let mut command = fixer.get_resolve_command();
+            if let Some(dir) = self.command_dir() {
+                command.current_dir(dir);
+            }
+            let resolved = match command.status() {
+                Ok(status) if status.success() => true,
+                Ok(status) => {
+                    status_warn!("`cargo update --workspace` failed: {}", status);
+                    false
                 }
-                // When calling `.status()` the stdout and stderr are inherited from the parent,
-                // so any status or error messages from `cargo update` will automatically be forwarded
-                // to the user of `cargo audit fix`.
-                let status = command.status();
-                if let Err(e) = status {
-                    failed_patches += 1;
-                    status_warn!(
-                        "Failed to run `cargo update` for package {}: {}",
-                        vulnerability.package.name,
-                        e
-                    );
+                Err(e) => {
+                    status_warn!("Failed to run `cargo update --workspace`: {}", e);
+                    false
                 }
+            };
+
+            // The changed requirements can't be resolved, so they're undone
+            if !resolved {
+                failed_patches += 1;
+                revert_manifests(&manifest_edits);
             }
         }
 
         if failed_patches != 0 {
             exit(2);
         }
-        if dry_run {
-            // When performing a dry run, the exit status is determined by whether we had any issues along the way
-            if !unpatchable_vulns.is_empty() {
-                exit(1);
-            } else {
-                exit(0)
+
+        // It is possible that some vulns we tried to fix actually weren't fixed,
+        // either because a fix was skipped, because other packages in the lockfile
+        // constrain the vulnerable one, or because Cargo.toml version specification
+        // is too restrictive (uses e.g. `=` or `=<` operators).
+        status_ok!(
+            "Verifying",
+            "that the vulnerabilities are fixed after updating dependencies"
+        );
+        let mut config = (*APP.config()).to_owned();
+        config.output.quiet = true;
+        let mut auditor = Auditor::new(&config);
+
+        let report_after_fix = match auditor.report_lockfile(&path) {
+            Ok(report) => report,
+            Err(e) => {
+                status_err!(
+                    "Couldn't audit {} after fixing: {}",
+                    path.display(),
+                    display_err_with_source(&e)
+                );
+                exit(2);
             }
-        } else {
-            // It is possible that some vulns we tried to fix actually weren't fixed,
-            // either because there is no semver-compatible fix or because Cargo.toml version specification
-            // is too restrictive (uses e.g. `=` or `=<` operators).
+        };
+
+        let verification = Verification::new(
+            &report.vulnerabilities.list,
+            &report_after_fix.vulnerabilities.list,
+        );
+
+        for vuln in &verification.resolved {
             status_ok!(
-                "Verifying",
-                "that the vulnerabilities are fixed after updating dependencies"
+                "Resolved",
+                "{} in {} {}",
+                vuln.advisory.id,
+                vuln.package.name,
+                vuln.package.version
             );
-            let mut config = (*APP.config()).to_owned();
-            config.output.quiet = true;
-            let mut auditor = Auditor::new(&config);
+        }
 
-            let report_after_fix = auditor.audit_lockfile(&path).unwrap();
-            let vulns_after_fix = &report_after_fix.vulnerabilities.list;
-            let fixable_but_unfixed: Vec<String> = vulns_after_fix
-                .iter()
-                .filter(|vuln| !unpatchable_vulns.contains(&vuln.advisory.id))
-                .map(|vuln| vuln.advisory.id.to_string())
-                .collect();
-            if !fixable_but_unfixed.is_empty() {
+        for vuln in &verification.remaining {
+            if unpatchable_vulns.contains(&vuln.advisory.id) {
                 status_warn!(
-                    "The following advisories have patched versions but could not be fixed:\n    {}\n\
-                    This usually occurs when the fixed version is not semver-compatible,\n\
-                    or the version range specified in your `Cargo.toml` is too restrictive\n\
-                    (e.g. uses `=` or `=<` operators) so the fixed version would not match it.",
-                    fixable_but_unfixed.join(", ")
+                    "{} in {} {} remains: no patched version is available",
+                    vuln.advisory.id,
+                    vuln.package.name,
+                    vuln.package.version
+                );
+            } else {
+                status_warn!(
+                    "{} in {} {} remains: the patched version is not semver-compatible, \
+                    or is excluded by requirements in `Cargo.toml` or by other packages",
+                    vuln.advisory.id,
+                    vuln.package.name,
+                    vuln.package.version
                 );
             }
+        }
 
-            let remaining_vulns_count = report_after_fix.vulnerabilities.list.len();
-            let fixed_vulns_count = report
-                .vulnerabilities
-                .list
-                .len()
-                .saturating_sub(remaining_vulns_count);
-            if fixed_vulns_count != 0 {
-                if remaining_vulns_count == 0 {
-                    status_ok!("Fixed", "{} vulnerabilities", fixed_vulns_count);
-                } else {
-                    status_warn!(
-                        "Fixed {} vulnerabilities but {} remain",
-                        fixed_vulns_count,
-                        remaining_vulns_count
-                    );
-                }
-            }
+        for vuln in &verification.introduced {
+            status_warn!(
+                "{} in {} {} was introduced by the fix",
+                vuln.advisory.id,
+                vuln.package.name,
+                vuln.package.version
+            );
         }
+
+        let remaining_count = verification.remaining.len() + verification.introduced.len();
+        if remaining_count == 0 {
+            status_ok!("Fixed", "{} vulnerabilities", verification.resolved.len());
+        } else {
+            status_err!(
+                "Fixed {} vulnerabilities but {} remain",
+                verification.resolved.len(),
+                remaining_count
+            );
+            exit(1);
+        }
+    }
+}
+
+/// Outcome of re-auditing the lockfile after applying fixes
+struct Verification<'a> {
+    /// Vulnerabilities found before the fix which are gone after it
+    resolved: Vec<&'a Vulnerability>,
+
+    /// Vulnerabilities found both before and after the fix
+    remaining: Vec<&'a Vulnerability>,
+
+    /// Vulnerabilities which were only found after the fix
+    introduced: Vec<&'a Vulnerability>,
+}
+
+impl<'a> Verification<'a> {
+    /// Compare the vulnerabilities found before and after the fix. Findings
+    /// are matched by advisory and package name, since the fix is expected
+    /// to change the package's version.
+    fn new(before: &'a [Vulnerability], after: &'a [Vulnerability]) -> Self {
+        let key = |vuln: &Vulnerability| (vuln.advisory.id.clone(), vuln.package.name.clone());
+        let keys_before: BTreeSet<_> = before.iter().map(key).collect();
+        let keys_after: BTreeSet<_> = after.iter().map(key).collect();
+
+        let (remaining, introduced) = after
+            .iter()
+            .partition(|vuln| keys_before.contains(&key(vuln)));
+
+        Self {
+            resolved: before
+                .iter()
+                .filter(|vuln| !keys_after.contains(&key(vuln)))
+                .collect(),
+            remaining,
+            introduced,
+        }
+    }
+}
+
+/// Restore the manifests changed by the given edits
+fn revert_manifests(edits: &[ManifestEdit]) {
+    for edit in edits {
+        match edit.revert() {
+            Ok(()) => status_warn!("Restored {}", edit.path.display()),
+            Err(e) => status_err!("Failed to restore {}: {}", edit.path.display(), e),
+        }
+    }
+}
+
+/// Ask the user a question, returning their trimmed answer, or `None` if
+/// there is no more input
+fn prompt(question: &str) -> Option<String> {
+    print!("{}", question);
+    io::stdout().flush().unwrap();
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_owned()),
     }
 }
@@ -0,0 +1,57 @@
+//! The `cargo audit image` subcommand
+
+use super::binary_scanning::BinaryAuditArgs;
+use crate::{auditor::Auditor, cli_config::CliConfig, config::AuditConfig, prelude::*};
+use abscissa_core::{config::Override, FrameworkError};
+use clap::Parser;
+use std::process::exit;
+
+/// The `cargo audit image` subcommand
+#[derive(Command, Clone, Default, Debug, Parser)]
+#[command()]
+pub struct ImageCommand {
+    /// Options shared with `cargo audit bin`
+    #[command(flatten)]
+    pub args: BinaryAuditArgs,
+
+    /// Images to be scanned
+    #[arg(
+        value_parser,
+        required = true,
+        value_name = "IMAGE",
+        help = "Image tarballs (e.g. from `docker save`) or references to images available to docker"
+    )]
+    images: Vec<String>,
+}
+
+impl Runnable for ImageCommand {
+    fn run(&self) {
+        let report = self.auditor().audit_images(&self.images);
+        if report.vulnerabilities_found {
+            exit(1)
+        } else if report.errors_encountered {
+            exit(2)
+        } else {
+            exit(0)
+        }
+    }
+}
+
+impl From<ImageCommand> for CliConfig {
+    fn from(c: ImageCommand) -> Self {
+        c.args.cli_config(vec![])
+    }
+}
+
+impl Override<AuditConfig> for ImageCommand {
+    fn override_config(&self, config: AuditConfig) -> Result<AuditConfig, FrameworkError> {
+        CliConfig::from(self.clone()).override_config(config)
+    }
+}
+
+impl ImageCommand {
+    /// Initialize `Auditor`
+    pub fn auditor(&self) -> Auditor {
+        Auditor::new(&APP.config())
+    }
+}
@@ -0,0 +1,75 @@
+//! The `cargo audit remote` subcommand
+
+use crate::{
+    auditor::Auditor,
+    error::display_err_with_source,
+    prelude::*,
+    remote::{self, RemoteUrl},
+};
+use abscissa_core::{Command, Runnable};
+use clap::Parser;
+use std::process::exit;
+
+/// The `cargo audit remote` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct RemoteCommand {
+    /// Location of the lockfile on the remote host
+    #[arg(
+        value_name = "URL",
+        help = "ssh:// or sftp:// URL of a remote Cargo.lock or the directory containing it"
+    )]
+    url: String,
+
+    /// Also fetch `Cargo.toml`
+    #[arg(
+        long = "manifest",
+        help = "Also fetch Cargo.toml, generating a lockfile locally if the remote host has none"
+    )]
+    manifest: bool,
+}
+
+impl Runnable for RemoteCommand {
+    fn run(&self) {
+        let url: RemoteUrl = self.url.parse().unwrap_or_else(|e| {
+            status_err!("{}", e);
+            exit(2);
+        });
+
+        // Nothing fetched from the remote host is kept around: the directory
+        // is removed when it's dropped
+        let dir = tempfile::Builder::new()
+            .prefix("cargo-audit-remote-")
+            .tempdir()
+            .unwrap_or_else(|e| {
+                status_err!("couldn't create a temporary directory: {}", e);
+                exit(2);
+            });
+
+        let result = remote::fetch(&url, dir.path(), self.manifest)
+            .map_err(|e| display_err_with_source(&e))
+            .and_then(|path| {
+                if !APP.config().output.is_quiet() {
+                    status_ok!("Fetched", "{}", url);
+                }
+
+                let mut auditor = Auditor::new(&APP.config());
+                auditor
+                    .audit_lockfile(&path)
+                    .map(|report| auditor.should_exit_with_failure(&report))
+                    .map_err(|e| display_err_with_source(&e))
+            });
+
+        // Exiting skips destructors
+        drop(dir);
+
+        match result {
+            Ok(true) => exit(1),
+            Ok(false) => exit(0),
+            Err(message) => {
+                status_err!("{}", message);
+                exit(2);
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! The `cargo audit search` subcommand
+
+use crate::{auditor::load_database, config::OutputFormat, prelude::*};
+use abscissa_core::{terminal, Command, Runnable};
+use clap::Parser;
+use rustsec::{
+    advisory::{Category, Keyword},
+    cwe::Cwe,
+    database::Query,
+    package, Collection,
+};
+use std::{io, process::exit};
+
+/// The `cargo audit search` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct SearchCommand {
+    /// Words to search for in advisory titles and descriptions
+    #[arg(
+        value_name = "TEXT",
+        help = "Words which must all occur in the title or description of matching advisories"
+    )]
+    text: Vec<String>,
+
+    /// Crate the advisories are for
+    #[arg(
+        long = "crate",
+        value_name = "CRATE",
+        help = "Only match advisories for the given crate"
+    )]
+    package: Option<package::Name>,
+
+    /// Keywords the advisories must have
+    #[arg(
+        long = "keyword",
+        value_name = "KEYWORD",
+        help = "Only match advisories with the given keyword (may be given several times)"
+    )]
+    keywords: Vec<Keyword>,
+
+    /// Category the advisories must be in
+    #[arg(
+        long = "category",
+        value_name = "CATEGORY",
+        help = "Only match advisories in the given category, e.g. memory-corruption"
+    )]
+    category: Option<Category>,
+
+    /// CWE weakness class the advisories must be in
+    #[arg(
+        long = "cwe",
+        value_name = "CWE",
+        help = "Only match advisories in the given CWE weakness class or its subclasses, e.g. 119 or CWE-119"
+    )]
+    cwe: Option<String>,
+
+    /// Collection the advisories must be in
+    #[arg(
+        long = "collection",
+        value_name = "COLLECTION",
+        help = "Only match advisories in the given collection: crates, rust or native (default: all)"
+    )]
+    collection: Option<Collection>,
+
+    /// Include withdrawn advisories
+    #[arg(long = "withdrawn", help = "Include withdrawn advisories")]
+    withdrawn: bool,
+
+    /// Output format
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        help = "Output format: terminal or json (default: terminal)"
+    )]
+    format: Option<OutputFormat>,
+}
+
+impl SearchCommand {
+    /// Is the output of this command written to stdout in a machine-readable
+    /// format?
+    pub fn writes_to_stdout(&self) -> bool {
+        self.format == Some(OutputFormat::Json)
+    }
+
+    /// Build the database query for the given search criteria
+    fn query(&self) -> Query {
+        let mut query = Query::new();
+
+        if let Some(collection) = self.collection {
+            query = query.collection(collection);
+        }
+
+        if !self.withdrawn {
+            query = query.withdrawn(false);
+        }
+
+        if let Some(package) = &self.package {
+            query = query.package_name(package.clone());
+        }
+
+        for keyword in &self.keywords {
+            query = query.keyword(keyword.clone());
+        }
+
+        if let Some(category) = &self.category {
+            query = query.category(category.clone());
+        }
+
+        if let Some(cwe) = &self.cwe {
+            match cwe.parse::<Cwe>() {
+                Ok(cwe) => query = query.cwe(cwe.id()),
+                Err(_) => {
+                    status_err!("invalid CWE: {} (expected e.g. 119 or CWE-119)", cwe);
+                    exit(1);
+                }
+            }
+        }
+
+        for text in &self.text {
+            query = query.text(text);
+        }
+
+        query
+    }
+
+    /// Were any search criteria given?
+    fn has_criteria(&self) -> bool {
+        !self.text.is_empty()
+            || self.package.is_some()
+            || !self.keywords.is_empty()
+            || self.category.is_some()
+            || self.cwe.is_some()
+            || self.collection.is_some()
+    }
+}
+
+impl Runnable for SearchCommand {
+    fn run(&self) {
+        if !self.has_criteria() {
+            status_err!("no search criteria given (see `cargo audit search --help`)");
+            exit(1);
+        }
+
+        let query = self.query();
+        let db = load_database(&APP.config());
+        let mut advisories = db.query(&query);
+        advisories.sort_by(|a, b| a.metadata.id.cmp(&b.metadata.id));
+
+        if self.format == Some(OutputFormat::Json) {
+            serde_json::to_writer(io::stdout(), &advisories).unwrap();
+            println!();
+            return;
+        }
+
+        if advisories.is_empty() {
+            status_warn!("no matching advisories found");
+            return;
+        }
+
+        for advisory in &advisories {
+            let metadata = &advisory.metadata;
+            terminal::status::Status::new()
+                .bold()
+                .status(format!("{:<17}", metadata.id))
+                .print_stdout(format!("{}: {}", metadata.package, metadata.title))
+                .unwrap();
+        }
+
+        status_ok!(
+            "Found",
+            "{} matching {}",
+            advisories.len(),
+            if advisories.len() == 1 {
+                "advisory"
+            } else {
+                "advisories"
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::database::AdvisoryBuilder;
+
+    #[test]
+    fn collections() {
+        let crates = AdvisoryBuilder::new("RUSTSEC-2021-0001", "foo")
+            .collection(Collection::Crates)
+            .title("Use after free")
+            .build()
+            .unwrap();
+        let std = AdvisoryBuilder::new("RUSTSEC-2021-0002", "std")
+            .collection(Collection::Rust)
+            .title("Use after free")
+            .build()
+            .unwrap();
+
+        let query = SearchCommand::parse_from(["search", "free"]).query();
+        assert!(query.matches(&crates));
+        assert!(query.matches(&std));
+
+        let query = SearchCommand::parse_from(["search", "--collection", "rust", "free"]).query();
+        assert!(!query.matches(&crates));
+        assert!(query.matches(&std));
+
+        let query = SearchCommand::parse_from(["search", "--collection", "crates", "free"]).query();
+        assert!(query.matches(&crates));
+        assert!(!query.matches(&std));
+    }
+}
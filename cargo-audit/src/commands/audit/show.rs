@@ -0,0 +1,506 @@
+//! The `cargo audit show` subcommand
+
+use crate::{auditor::load_database, config::OutputFormat, prelude::*};
+use abscissa_core::{terminal, Command, Runnable};
+use clap::Parser;
+use rustsec::{advisory::License, Advisory};
+use std::{io, process::exit};
+
+/// Width the description is wrapped to, not counting its indentation
+const DESCRIPTION_WIDTH: usize = 78;
+
+/// The `cargo audit show` subcommand
+#[derive(Command, Clone, Debug, Parser)]
+#[command(author, version, about)]
+pub struct ShowCommand {
+    /// ID of the advisory to show
+    #[arg(
+        value_name = "ADVISORY_ID",
+        help = "ID of the advisory to show, or one of its aliases (e.g. a CVE or GHSA ID)"
+    )]
+    id: String,
+
+    /// Output format
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        help = "Output format: terminal or json (default: terminal)"
+    )]
+    format: Option<OutputFormat>,
+}
+
+impl ShowCommand {
+    /// Is the output of this command written to stdout in a machine-readable
+    /// format?
+    pub fn writes_to_stdout(&self) -> bool {
+        self.format == Some(OutputFormat::Json)
+    }
+}
+
+impl Runnable for ShowCommand {
+    fn run(&self) {
+        let db = load_database(&APP.config());
+        let advisories = db.find_by_alias(&self.id);
+
+        if advisories.is_empty() {
+            status_err!("no advisory found with ID {}", self.id);
+            exit(1);
+        }
+
+        if self.format == Some(OutputFormat::Json) {
+            serde_json::to_writer(io::stdout(), &advisories).unwrap();
+            println!();
+            return;
+        }
+
+        for (i, advisory) in advisories.into_iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+
+            let superseded_by = db.superseded_by(&advisory.metadata.id);
+            print_advisory(advisory, &superseded_by);
+        }
+    }
+}
+
+/// Print an advisory in full
+fn print_advisory(advisory: &Advisory, superseded_by: &[&Advisory]) {
+    let metadata = &advisory.metadata;
+
+    terminal::status::Status::new()
+        .bold()
+        .status(format!("{}:", metadata.id))
+        .print_stdout(&metadata.title)
+        .unwrap();
+    println!();
+
+    print_attr("Crate:", &metadata.package);
+    print_attr("Date:", &metadata.date);
+
+    if let Some(withdrawn) = &metadata.withdrawn {
+        print_attr("Withdrawn:", withdrawn);
+    }
+
+    if let Some(informational) = &metadata.informational {
+        print_attr(
+            "Kind:",
+            format!("informational ({})", informational.as_str()),
+        );
+    }
+
+    if let Some(cvss) = metadata.primary_cvss() {
+        print_attr(
+            "Severity:",
+            format!(
+                "{} ({}) {}",
+                cvss.score().value(),
+                cvss.score().severity(),
+                cvss
+            ),
+        );
+    }
+
+    for score in &metadata.additional_cvss {
+        if Some(&score.vector) == metadata.primary_cvss() {
+            continue;
+        }
+
+        let mut notes = vec![];
+        if let Some(source) = &score.source {
+            notes.push(format!("by {}", source));
+        }
+        if let Some(applies_to) = &score.applies_to {
+            notes.push(format!("for {}", applies_to));
+        }
+
+        let mut value = format!(
+            "{} ({}) {}",
+            score.vector.score().value(),
+            score.vector.score().severity(),
+            score.vector
+        );
+        if !notes.is_empty() {
+            value.push_str(&format!(" [{}]", notes.join(", ")));
+        }
+
+        print_attr("Also scored:", value);
+    }
+
+    print_list("Aliases:", &metadata.aliases);
+    print_list("Related:", &metadata.related);
+    print_list("Supersedes:", &metadata.supersedes);
+    print_list(
+        "Superseded by:",
+        superseded_by.iter().map(|advisory| &advisory.metadata.id),
+    );
+    print_list("Categories:", &metadata.categories);
+    print_list(
+        "Keywords:",
+        metadata.keywords.iter().map(|keyword| keyword.as_str()),
+    );
+    for cwe in metadata.cwes() {
+        print_attr("Weakness:", cwe);
+    }
+
+    print_list("Patched:", advisory.versions.patched());
+    print_list("Unaffected:", advisory.versions.unaffected());
+
+    if let Some(affected) = &advisory.affected {
+        print_list("OS:", &affected.os);
+        print_list("Arch:", &affected.arch);
+        print_list("Features:", &affected.features);
+
+        for (function, versions) in &affected.functions {
+            let versions: Vec<_> = versions.iter().map(ToString::to_string).collect();
+            print_attr(
+                "Function:",
+                format!("{} ({})", function, versions.join(", ")),
+            );
+        }
+    }
+
+    if let Some(url) = metadata.id.url() {
+        print_attr("URL:", url);
+    }
+
+    if let Some(url) = &metadata.url {
+        print_attr("Details:", url);
+    }
+
+    for reference in &metadata.references {
+        print_attr("Reference:", reference);
+    }
+
+    if metadata.license != License::CcZero10 {
+        print_attr("License:", metadata.license.spdx());
+    }
+
+    let description = render_markdown(&metadata.description, DESCRIPTION_WIDTH);
+    if !description.is_empty() {
+        println!();
+        for line in description {
+            if line.is_empty() {
+                println!();
+            } else {
+                println!("  {}", line);
+            }
+        }
+    }
+}
+
+/// Print an attribute of an advisory
+fn print_attr(attr: &str, value: impl ToString) {
+    terminal::status::Status::new()
+        .bold()
+        .status(format!("{:<14}", attr))
+        .print_stdout(value.to_string())
+        .unwrap();
+}
+
+/// Print a list attribute of an advisory, if it isn't empty
+fn print_list<T: ToString>(attr: &str, values: impl IntoIterator<Item = T>) {
+    let values: Vec<_> = values.into_iter().map(|value| value.to_string()).collect();
+
+    if !values.is_empty() {
+        print_attr(attr, values.join(", "));
+    }
+}
+
+/// Render the Markdown description of an advisory as plain text lines for
+/// the terminal: paragraphs and list items are wrapped to `width`, inline
+/// markup is removed (links keep their URL), and code blocks are indented.
+/// Blocks are separated by empty lines.
+fn render_markdown(markdown: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut prefix = "";
+    let mut code_block = false;
+    let mut in_list = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush_paragraph(&mut lines, &mut paragraph, prefix, width, &mut in_list);
+            if !code_block {
+                start_block(&mut lines);
+                in_list = false;
+            }
+            code_block = !code_block;
+            continue;
+        }
+
+        if code_block {
+            lines.push(format!("    {}", line).trim_end().to_owned());
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut lines, &mut paragraph, prefix, width, &mut in_list);
+            continue;
+        }
+
+        if let Some(heading) = heading_text(trimmed) {
+            flush_paragraph(&mut lines, &mut paragraph, prefix, width, &mut in_list);
+            start_block(&mut lines);
+            lines.push(render_inline(heading));
+            in_list = false;
+            continue;
+        }
+
+        // Each list item or quote starts a block of its own
+        let (item_prefix, text) = match list_item(trimmed) {
+            Some((item_prefix, text)) => (Some(item_prefix), text),
+            None => match trimmed.strip_prefix('>') {
+                Some(text) => (Some("> "), text.trim_start()),
+                None => (None, trimmed),
+            },
+        };
+
+        if let Some(item_prefix) = item_prefix {
+            let continues_quote = item_prefix == "> " && prefix == "> ";
+            if !continues_quote {
+                flush_paragraph(&mut lines, &mut paragraph, prefix, width, &mut in_list);
+                prefix = item_prefix;
+            }
+        } else if paragraph.is_empty() {
+            prefix = "";
+        }
+
+        paragraph.push(text);
+    }
+
+    flush_paragraph(&mut lines, &mut paragraph, prefix, width, &mut in_list);
+    lines
+}
+
+/// Separate a new block from the previous one with an empty line
+fn start_block(lines: &mut Vec<String>) {
+    if lines.last().map_or(false, |line| !line.is_empty()) {
+        lines.push(String::new());
+    }
+}
+
+/// Wrap the lines of a paragraph, list item or quote collected so far
+fn flush_paragraph(
+    lines: &mut Vec<String>,
+    paragraph: &mut Vec<&str>,
+    prefix: &str,
+    width: usize,
+    in_list: &mut bool,
+) {
+    if paragraph.is_empty() {
+        return;
+    }
+
+    // Consecutive list items aren't separated by empty lines
+    let is_item = !prefix.is_empty() && prefix != "> ";
+    if !(is_item && *in_list) {
+        start_block(lines);
+    }
+    *in_list = is_item;
+
+    let text = render_inline(&paragraph.join(" "));
+    let continuation = if prefix == "> " { "> " } else { "  " };
+    let indent = if prefix.is_empty() { "" } else { continuation };
+
+    let mut line = prefix.to_owned();
+    let mut line_is_empty = true;
+    for word in text.split_whitespace() {
+        if !line_is_empty && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = indent.to_owned();
+            line_is_empty = true;
+        }
+        if !line_is_empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        line_is_empty = false;
+    }
+    lines.push(line);
+
+    paragraph.clear();
+}
+
+/// Get the text of an ATX heading (e.g. `## Impact`)
+fn heading_text(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+
+    if (1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')) {
+        Some(text.trim().trim_end_matches('#').trim_end())
+    } else {
+        None
+    }
+}
+
+/// Split a list item (e.g. `- item` or `1. item`) into the prefix it's
+/// rendered with and its text
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some(("- ", text.trim_start()));
+        }
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(text) = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+        {
+            return Some(("- ", text.trim_start()));
+        }
+    }
+
+    None
+}
+
+/// Remove inline markup: code spans keep their contents verbatim, emphasis
+/// markers are dropped, and links are shown as their text followed by their
+/// URL
+fn render_inline(text: &str) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '`' => {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let fence = &rest[..ticks];
+                match rest[ticks..].find(fence) {
+                    Some(end) => {
+                        rendered.push_str(rest[ticks..ticks + end].trim());
+                        rest = &rest[2 * ticks + end..];
+                    }
+                    None => {
+                        rendered.push_str(fence);
+                        rest = &rest[ticks..];
+                    }
+                }
+            }
+            '*' => rest = rest.trim_start_matches('*'),
+            '[' => match link(rest) {
+                Some((link_text, url, len)) => {
+                    let link_text = render_inline(link_text);
+                    if link_text == url {
+                        rendered.push_str(url);
+                    } else {
+                        rendered.push_str(&format!("{} ({})", link_text, url));
+                    }
+                    rest = &rest[len..];
+                }
+                None => {
+                    rendered.push('[');
+                    rest = &rest[1..];
+                }
+            },
+            '<' => match rest[1..].find('>') {
+                Some(end) if rest[1..end + 1].contains("://") => {
+                    rendered.push_str(&rest[1..end + 1]);
+                    rest = &rest[end + 2..];
+                }
+                _ => {
+                    rendered.push('<');
+                    rest = &rest[1..];
+                }
+            },
+            _ => {
+                rendered.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    rendered
+}
+
+/// Parse an inline link (`[text](url)`) at the start of the given text into
+/// its text, URL and length
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let text_end = text.find("](")?;
+    let url_end = text_end + 2 + text[text_end + 2..].find(')')?;
+    let url = text[text_end + 2..url_end].split_whitespace().next()?;
+
+    Some((&text[1..text_end], url, url_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_markup() {
+        assert_eq!(
+            render_inline("Calling `Vec::from_raw_parts` is **unsound**"),
+            "Calling Vec::from_raw_parts is unsound"
+        );
+        assert_eq!(
+            render_inline("See [the issue](https://example.com/1) and <https://example.com/2>"),
+            "See the issue (https://example.com/1) and https://example.com/2"
+        );
+        assert_eq!(
+            render_inline("[https://example.com](https://example.com)"),
+            "https://example.com"
+        );
+
+        // Code spans are kept verbatim, and unmatched markup is left as is
+        assert_eq!(render_inline("``a `*` b``"), "a `*` b");
+        assert_eq!(render_inline("a[0] < b, `c"), "a[0] < b, `c");
+        assert_eq!(render_inline("snake_case_name"), "snake_case_name");
+    }
+
+    #[test]
+    fn blocks() {
+        let markdown = "\
+# Heading
+
+First paragraph, which is
+wrapped **again**.
+
+- item one
+- item two which
+  continues
+
+```rust
+let x = 1;
+```
+- item after code
+
+> quoted
+> text
+";
+
+        assert_eq!(
+            render_markdown(markdown, 20),
+            [
+                "Heading",
+                "",
+                "First paragraph,",
+                "which is wrapped",
+                "again.",
+                "",
+                "- item one",
+                "- item two which",
+                "  continues",
+                "",
+                "    let x = 1;",
+                "",
+                "- item after code",
+                "",
+                "> quoted text",
+            ]
+        );
+    }
+
+    #[test]
+    fn long_words() {
+        assert_eq!(
+            render_markdown("see https://example.com/a/very/long/url", 10),
+            ["see", "https://example.com/a/very/long/url"]
+        );
+        assert!(render_markdown("  \n\n", 10).is_empty());
+    }
+}
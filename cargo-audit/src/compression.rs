@@ -0,0 +1,97 @@
+//! Compression of file outputs and uploads (`--compress`)
+//!
+//! Data is compressed as it is written, so compressing a report or export
+//! never requires holding an uncompressed copy in memory.
+
+use rustsec::{Error, ErrorKind};
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// zstd compression level (the library's default)
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compression format
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Compression {
+    /// gzip (RFC 1952)
+    Gzip,
+
+    /// Zstandard (RFC 8878)
+    Zstd,
+}
+
+impl Compression {
+    /// Value of the `Content-Encoding` header for this format
+    pub fn content_encoding(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Wrap a writer in an encoder for this format
+    pub fn encoder<W: Write>(self, writer: W) -> io::Result<Encoder<W>> {
+        Ok(match self {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(writer, ZSTD_LEVEL)?),
+        })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(Error::new(
+                ErrorKind::Parse,
+                &format!(
+                    "invalid compression format: {} (expected gzip or zstd)",
+                    other
+                ),
+            )),
+        }
+    }
+}
+
+/// Streaming encoder for a [`Compression`] format
+pub enum Encoder<W: Write> {
+    /// gzip encoder
+    Gzip(flate2::write::GzEncoder<W>),
+
+    /// Zstandard encoder
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Write the remaining compressed data, returning the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
@@ -2,18 +2,24 @@
 
 use rustsec::{
     advisory,
-    platforms::target::{Arch, OS},
+    platforms::{
+        target::{Arch, OS},
+        Platform,
+    },
     report, Error, ErrorKind, WarningKind,
 };
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap as Map, mem, path::PathBuf, str::FromStr, time::Duration};
 
 /// `cargo audit` configuration:
 ///
-/// An optional TOML config file located in `~/.cargo/audit.toml` or
-/// `.cargo/audit.toml`.
+/// Optional TOML config files located in `/etc/cargo-audit/audit.toml`,
+/// `~/.cargo/audit.toml`, `~/.config/cargo-audit/audit.toml` and
+/// `.cargo/audit.toml`, which are merged in that order (see
+/// [`crate::config_layers`]).
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct AuditConfig {
     /// Advisory-related configuration
     #[serde(default)]
@@ -23,6 +29,10 @@ pub struct AuditConfig {
     #[serde(default)]
     pub database: DatabaseConfig,
 
+    /// Network configuration
+    #[serde(default)]
+    pub network: NetworkConfig,
+
     /// Output configuration
     #[serde(default)]
     pub output: OutputConfig,
@@ -34,18 +44,99 @@ pub struct AuditConfig {
     /// Configuration for auditing for yanked crates
     #[serde(default)]
     pub yanked: YankedConfig,
+
+    /// Configuration for specific packages
+    #[serde(default)]
+    pub packages: PackagesConfig,
+
+    /// URL of an organization-wide policy file in the same format, whose
+    /// policy settings are merged under this configuration
+    #[serde(default)]
+    pub policy: Option<String>,
+
+    /// Expected SHA-256 checksum of the shared policy file (hex)
+    #[serde(default)]
+    pub policy_sha256: Option<String>,
 }
 
 impl AuditConfig {
+    /// Merge the policy settings of a shared policy file under this
+    /// configuration: ignored advisories, severity overrides, denied warning
+    /// kinds and package overrides are combined, and settings made in this
+    /// configuration take precedence over those of the shared policy. Other
+    /// settings of the shared policy are ignored.
+    pub fn merge_policy(&mut self, policy: AuditConfig) {
+        let advisories = policy.advisories;
+
+        for advisory_id in advisories.ignore {
+            if !self.advisories.ignore.contains(&advisory_id) {
+                self.advisories.ignore.push(advisory_id);
+            }
+        }
+
+        if self.advisories.severity_threshold.is_none() {
+            self.advisories.severity_threshold = advisories.severity_threshold;
+        }
+
+        if self.advisories.informational_warnings.is_none() {
+            self.advisories.informational_warnings = advisories.informational_warnings;
+        }
+
+        // Later overrides replace earlier ones for the same advisory
+        let local = mem::replace(
+            &mut self.advisories.severity_overrides,
+            advisories.severity_overrides,
+        );
+        self.advisories.severity_overrides.extend(local);
+
+        for deny in policy.output.deny {
+            if !self.output.deny.contains(&deny) {
+                self.output.deny.push(deny);
+            }
+        }
+
+        let shared = policy.output.policy;
+        let local = &mut self.output.policy;
+        merge_missing(&mut local.warnings, shared.warnings);
+        merge_missing(&mut local.severity, shared.severity);
+
+        for (package, shared) in shared.packages {
+            let local = local.packages.entry(package).or_default();
+            merge_missing(&mut local.warnings, shared.warnings);
+            merge_missing(&mut local.severity, shared.severity);
+        }
+
+        self.packages.overrides.extend(policy.packages.overrides);
+    }
+
     /// Get audit report settings from the configuration
     pub fn report_settings(&self) -> report::Settings {
-        let mut settings = rustsec::report::Settings {
-            ignore: self.advisories.ignore.clone(),
-            severity: self.advisories.severity_threshold,
-            target_arch: self.target.arch,
-            target_os: self.target.os,
-            ..Default::default()
-        };
+        let mut builder = report::Settings::builder()
+            .deprecated_warnings(self.advisories.deprecated_warnings)
+            .include_withdrawn(self.advisories.include_withdrawn)
+            .group_versions(self.output.group_versions);
+
+        for advisory_id in &self.advisories.ignore {
+            builder = builder.ignore(advisory_id.clone());
+        }
+
+        if let Some(severity) = self.advisories.severity_threshold {
+            builder = builder.severity(severity);
+        }
+
+        for severity_override in &self.advisories.severity_overrides {
+            builder = builder.severity_override(severity_override.clone());
+        }
+
+        if let Some(arch) = self.target.arch() {
+            builder = builder.target_arch(arch);
+        }
+
+        if let Some(os) = self.target.os() {
+            builder = builder.target_os(os);
+        }
+
+        let mut settings = builder.build();
 
         if let Some(informational_warnings) = &self.advisories.informational_warnings {
             settings.informational_warnings = informational_warnings.clone();
@@ -83,16 +174,69 @@ impl AuditConfig {
                 }
                 DenyOption::Unsound => insert_if_not_present(advisory::Informational::Unsound),
                 DenyOption::Yanked => continue,
+                DenyOption::Deprecated => settings.deprecated_warnings = true,
             };
         }
 
         settings
     }
+
+    /// Get the timeouts and retries for network operations from the
+    /// configuration
+    pub fn network_settings(&self) -> rustsec::network::NetworkConfig {
+        let mut settings = rustsec::network::NetworkConfig::new()
+            .retries(self.network.retries)
+            .backoff(Duration::from_secs(self.network.retry_backoff));
+
+        if let Some(secs) = self.network.connect_timeout {
+            settings = settings.connect_timeout(Duration::from_secs(secs));
+        }
+
+        if let Some(secs) = self.network.request_timeout {
+            settings = settings.request_timeout(Duration::from_secs(secs));
+        }
+
+        settings
+    }
+}
+
+/// Configuration for specific packages
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct PackagesConfig {
+    /// Warnings to allow for specific packages
+    #[serde(default)]
+    pub overrides: Vec<PackageOverride>,
+}
+
+impl PackagesConfig {
+    /// Is the given kind of warning allowed for the given package?
+    pub fn allows(&self, package: &rustsec::package::Name, kind: WarningKind) -> bool {
+        self.overrides
+            .iter()
+            .any(|rule| &rule.name == package && rule.allow.contains(&kind))
+    }
+}
+
+/// Kinds of warnings to allow for a package which has been accepted
+/// despite them, e.g. an unmaintained crate without a replacement
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct PackageOverride {
+    /// Name of the package
+    pub name: rustsec::package::Name,
+
+    /// Kinds of warnings which aren't reported for the package
+    #[serde(default)]
+    pub allow: Vec<WarningKind>,
 }
 
 /// Advisory-related configuration.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct AdvisoryConfig {
     /// Ignore advisories for the given IDs
     #[serde(default)]
@@ -110,6 +254,44 @@ pub struct AdvisoryConfig {
     /// Vulnerabilities with explicit CVSS info which have a severity below
     /// this threshold will be ignored.
     pub severity_threshold: Option<advisory::Severity>,
+
+    /// Severities to use instead of those of the CVSS vectors of particular
+    /// advisories, for the severity threshold and severity-based policies
+    #[serde(default, alias = "severity-overrides")]
+    pub severity_overrides: Vec<report::SeverityOverride>,
+
+    /// Warn for crates which have been superseded by another crate
+    #[serde(default)]
+    pub deprecated_warnings: bool,
+
+    /// Report advisories which have been withdrawn
+    #[serde(default)]
+    pub include_withdrawn: bool,
+
+    /// When auditing binaries, downgrade vulnerabilities whose affected
+    /// functions have no symbols in the binary to `uncompiled` warnings
+    #[serde(default)]
+    pub filter_symbols: bool,
+
+    /// When auditing stripped binaries, files and directories to look for
+    /// their separate debug info in, in addition to the conventional places
+    #[serde(default)]
+    pub debug_info: Vec<PathBuf>,
+
+    /// Report vulnerabilities regardless of the crate features enabled in the
+    /// project, instead of downgrading those in code gated by disabled features
+    #[serde(default)]
+    pub ignore_features: bool,
+
+    /// Exclude findings for packages which are only used as dev-dependencies
+    #[serde(default)]
+    pub exclude_dev: bool,
+
+    /// Check whether patched versions are compatible with the version
+    /// requirements in the project's manifests (which runs `cargo metadata`),
+    /// instead of assuming Cargo's default requirements
+    #[serde(default)]
+    pub check_requirements: bool,
 }
 
 /// Advisory Database configuration.
@@ -118,6 +300,7 @@ pub struct AdvisoryConfig {
 /// configuration stores settings related to it.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct DatabaseConfig {
     /// Path to the local copy of advisory database's git repo (default: ~/.cargo/advisory-db)
     pub path: Option<PathBuf>,
@@ -130,11 +313,122 @@ pub struct DatabaseConfig {
 
     /// Allow a stale advisory database? (i.e. one which hasn't been updated in 90 days)
     pub stale: bool,
+
+    /// Cache a parsed snapshot of the advisory database, and the last report
+    /// for each lockfile, between runs (default: true)
+    #[serde(default)]
+    pub cache: Option<bool>,
+
+    /// Use the advisory database snapshot embedded in the binary
+    /// (requires the `embedded-db` feature)
+    #[serde(default)]
+    pub embedded: bool,
+
+    /// Fail if fetching the advisory database fails, instead of using the
+    /// local copy (or the embedded snapshot)
+    #[serde(default)]
+    pub require_fresh: bool,
+
+    /// Clone the advisory database with only its latest commit, which is
+    /// faster and smaller than a full clone (existing full clones are kept)
+    #[serde(default)]
+    pub shallow: bool,
+
+    /// Query osv.dev for advisories affecting the audited packages
+    #[serde(default)]
+    pub osv: OsvMode,
+
+    /// Include GitHub Security Advisories which aren't in the advisory
+    /// database (requires a token in `GITHUB_TOKEN`)
+    #[serde(default)]
+    pub ghsa: bool,
+
+    /// Fill in CVSS scores, CWEs and references from NVD for advisories
+    /// which have a CVE alias but no CVSS score (an API key can be given in
+    /// `NVD_API_KEY`)
+    #[serde(default)]
+    pub nvd: bool,
+
+    /// Directory of OSV advisories about native libraries (e.g. an osv.dev
+    /// dump of the `OSS-Fuzz` ecosystem) to match against `-sys` crates
+    #[serde(default)]
+    pub native_advisories: Option<PathBuf>,
+
+    /// Additional mappings of native libraries to `-sys` crates, extending
+    /// the built-in ones
+    #[serde(default)]
+    pub sys_mapping: Option<PathBuf>,
+
+    /// Directory of recorded API responses to replay instead of making
+    /// requests, so that audits are deterministic and work offline
+    #[serde(default)]
+    pub vcr: Option<PathBuf>,
+
+    /// Record API responses into the `vcr` directory instead of replaying
+    /// them
+    #[serde(default)]
+    pub vcr_record: bool,
+}
+
+impl DatabaseConfig {
+    /// Should parsed snapshots of the advisory database and reports be
+    /// cached between runs?
+    pub fn cache_enabled(&self) -> bool {
+        self.cache.unwrap_or(true)
+    }
+}
+
+/// Network configuration, for fetching the advisory database and the
+/// crates.io index
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct NetworkConfig {
+    /// Timeout for establishing connections, in seconds
+    #[serde(default)]
+    pub connect_timeout: Option<u64>,
+
+    /// Timeout for each request as a whole, in seconds
+    #[serde(default)]
+    pub request_timeout: Option<u64>,
+
+    /// Number of times failed requests are retried (default: 0)
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Delay before the first retry in seconds, doubled for each further
+    /// retry (default: 1)
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            request_timeout: None,
+            retries: 0,
+            retry_backoff: default_retry_backoff(),
+        }
+    }
+}
+
+/// Add the entries of `shared` whose keys aren't in `local` yet
+fn merge_missing<K: Ord, V>(local: &mut Map<K, V>, shared: Map<K, V>) {
+    for (key, value) in shared {
+        local.entry(key).or_insert(value);
+    }
+}
+
+/// Helper function for returning the default delay before the first retry
+fn default_retry_backoff() -> u64 {
+    1
 }
 
 /// Output configuration
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct OutputConfig {
     /// Disallow advisories which trigger warnings
     #[serde(default)]
@@ -145,16 +439,390 @@ pub struct OutputConfig {
     pub format: OutputFormat,
 
     /// Enable quiet mode
+    #[serde(default)]
     pub quiet: bool,
 
     /// Show inverse dependency trees along with advisories (default: true)
     pub show_tree: Option<bool>,
+
+    /// Show release dates and the number of newer releases of affected packages
+    #[serde(default)]
+    pub release_info: bool,
+
+    /// Suggest which dependencies to upgrade to resolve vulnerabilities
+    #[serde(default)]
+    pub suggest_upgrades: bool,
+
+    /// Show each advisory once for all of the affected versions of a crate,
+    /// rather than once per version
+    #[serde(default)]
+    pub group_versions: bool,
+
+    /// Only print the number of vulnerabilities by severity and warnings by
+    /// kind, instead of the details of each finding
+    #[serde(default)]
+    pub summary: bool,
+
+    /// Group findings in the terminal output, rather than listing them in
+    /// lockfile order
+    pub group_by: Option<GroupBy>,
+
+    /// Fail on soft conditions such as a stale database or skipped checks
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Rules mapping crates to the components which own them, used to group
+    /// findings in reports
+    #[serde(default)]
+    pub components: Vec<ComponentRule>,
+
+    /// Whether each class of findings is allowed, reported as a warning or
+    /// fails the audit, overriding `deny`
+    #[serde(default)]
+    pub policy: PolicyConfig,
+
+    /// Colors and characters used to display reports in the terminal
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    /// Webhooks to notify about new findings
+    #[serde(default)]
+    pub notify: NotifyConfig,
 }
 
 impl OutputConfig {
     /// Is quiet mode enabled?
     pub fn is_quiet(&self) -> bool {
-        self.quiet || self.format == OutputFormat::Json
+        self.quiet || self.summary || self.format != OutputFormat::Terminal
+    }
+
+    /// Get the component owning the crate with the given name, according to
+    /// the first matching rule, or [`ComponentRule::UNASSIGNED`] if none match
+    pub fn component_of(&self, crate_name: &str) -> &str {
+        self.components
+            .iter()
+            .find(|rule| rule.matches(crate_name))
+            .map_or(ComponentRule::UNASSIGNED, |rule| rule.name.as_str())
+    }
+}
+
+/// Webhooks to notify about the findings which are new since the last run
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct NotifyConfig {
+    /// URLs to `POST` the JSON report of new findings to
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+
+    /// Slack incoming webhook URLs to post a message about new findings to
+    #[serde(default)]
+    pub slack: Vec<String>,
+
+    /// File keeping track of the findings which have already been notified
+    /// (default: `.cargo-audit-notify.json`)
+    pub state: Option<PathBuf>,
+}
+
+impl NotifyConfig {
+    /// Are any webhooks configured?
+    pub fn is_enabled(&self) -> bool {
+        !self.webhooks.is_empty() || !self.slack.is_empty()
+    }
+}
+
+/// Colors and characters used to display reports in the terminal
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ThemeConfig {
+    /// Palette to start from
+    #[serde(default)]
+    pub preset: ThemePreset,
+
+    /// Color of findings which fail the audit, overriding the preset
+    pub error: Option<ThemeColor>,
+
+    /// Color of findings which are only reported, overriding the preset
+    pub warning: Option<ThemeColor>,
+
+    /// Color of findings which were resolved, overriding the preset
+    pub resolved: Option<ThemeColor>,
+
+    /// Only use ASCII characters, e.g. to draw dependency trees
+    #[serde(default)]
+    pub ascii: bool,
+}
+
+impl ThemeConfig {
+    /// Get the color of findings which fail the audit, if any
+    pub fn error_color(&self) -> Option<ThemeColor> {
+        self.error.or(match self.preset {
+            ThemePreset::Default => Some(ThemeColor::Red),
+            ThemePreset::Colorblind => Some(ThemeColor::Magenta),
+            ThemePreset::Monochrome => None,
+        })
+    }
+
+    /// Get the color of findings which are only reported, if any
+    pub fn warning_color(&self) -> Option<ThemeColor> {
+        self.warning.or(match self.preset {
+            ThemePreset::Default => Some(ThemeColor::Yellow),
+            ThemePreset::Colorblind => Some(ThemeColor::Cyan),
+            ThemePreset::Monochrome => None,
+        })
+    }
+
+    /// Get the color of findings which were resolved, if any
+    pub fn resolved_color(&self) -> Option<ThemeColor> {
+        self.resolved.or(match self.preset {
+            ThemePreset::Default => Some(ThemeColor::Green),
+            ThemePreset::Colorblind => Some(ThemeColor::Blue),
+            ThemePreset::Monochrome => None,
+        })
+    }
+}
+
+/// Palette used to display reports
+#[derive(Default, Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ThemePreset {
+    /// Red for errors, yellow for warnings and green for resolved findings
+    #[serde(rename = "default")]
+    #[default]
+    Default,
+
+    /// Magenta for errors, cyan for warnings and blue for resolved findings,
+    /// which are distinguishable with the common forms of color blindness
+    #[serde(rename = "colorblind")]
+    Colorblind,
+
+    /// No colors
+    #[serde(rename = "monochrome")]
+    Monochrome,
+}
+
+/// Terminal color
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ThemeColor {
+    /// Black
+    #[serde(rename = "black")]
+    Black,
+
+    /// Blue
+    #[serde(rename = "blue")]
+    Blue,
+
+    /// Green
+    #[serde(rename = "green")]
+    Green,
+
+    /// Red
+    #[serde(rename = "red")]
+    Red,
+
+    /// Cyan
+    #[serde(rename = "cyan")]
+    Cyan,
+
+    /// Magenta
+    #[serde(rename = "magenta")]
+    Magenta,
+
+    /// Yellow
+    #[serde(rename = "yellow")]
+    Yellow,
+
+    /// White
+    #[serde(rename = "white")]
+    White,
+}
+
+impl From<ThemeColor> for abscissa_core::terminal::Color {
+    fn from(color: ThemeColor) -> Self {
+        use abscissa_core::terminal::Color;
+
+        match color {
+            ThemeColor::Black => Color::Black,
+            ThemeColor::Blue => Color::Blue,
+            ThemeColor::Green => Color::Green,
+            ThemeColor::Red => Color::Red,
+            ThemeColor::Cyan => Color::Cyan,
+            ThemeColor::Magenta => Color::Magenta,
+            ThemeColor::Yellow => Color::Yellow,
+            ThemeColor::White => Color::White,
+        }
+    }
+}
+
+/// Rule assigning crates to a logical component of a product (e.g. `runtime`,
+/// `cli` or a vendored dependency)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ComponentRule {
+    /// Name of the component
+    pub name: String,
+
+    /// Glob patterns matching crate names, where `*` matches any number of
+    /// characters and `?` matches a single character
+    pub crates: Vec<String>,
+}
+
+impl ComponentRule {
+    /// Component of crates which don't match any rule
+    pub const UNASSIGNED: &'static str = "other";
+
+    /// Does this rule match the crate with the given name?
+    pub fn matches(&self, crate_name: &str) -> bool {
+        self.crates
+            .iter()
+            .any(|pattern| glob_matches(pattern.as_bytes(), crate_name.as_bytes()))
+    }
+}
+
+/// Policy for the findings of an audit: the action to take for each kind of
+/// warning and each severity of vulnerabilities, optionally per package
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct PolicyConfig {
+    /// Actions for kinds of warnings
+    #[serde(default)]
+    pub warnings: Map<WarningKind, PolicyAction>,
+
+    /// Actions for vulnerabilities by severity
+    #[serde(default)]
+    pub severity: Map<SeverityBucket, PolicyAction>,
+
+    /// Actions for findings about specific packages, taking precedence over
+    /// the actions above
+    #[serde(default)]
+    pub packages: Map<String, PackagePolicy>,
+}
+
+impl PolicyConfig {
+    /// Get the configured action for a warning of the given kind about the
+    /// given package, if any
+    pub fn warning_action(&self, package: &str, kind: WarningKind) -> Option<PolicyAction> {
+        self.packages
+            .get(package)
+            .and_then(|policy| policy.warnings.get(&kind))
+            .or_else(|| self.warnings.get(&kind))
+            .copied()
+    }
+
+    /// Get the configured action for a vulnerability with the given severity
+    /// in the given package, if any
+    pub fn vulnerability_action(
+        &self,
+        package: &str,
+        severity: Option<advisory::Severity>,
+    ) -> Option<PolicyAction> {
+        let bucket = SeverityBucket::from(severity);
+
+        self.packages
+            .get(package)
+            .and_then(|policy| policy.severity.get(&bucket))
+            .or_else(|| self.severity.get(&bucket))
+            .copied()
+    }
+}
+
+/// Policy for the findings about a specific package
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct PackagePolicy {
+    /// Actions for kinds of warnings
+    #[serde(default)]
+    pub warnings: Map<WarningKind, PolicyAction>,
+
+    /// Actions for vulnerabilities by severity
+    #[serde(default)]
+    pub severity: Map<SeverityBucket, PolicyAction>,
+}
+
+/// Action to take for a class of findings
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PolicyAction {
+    /// Leave the findings out of the terminal report
+    #[serde(rename = "allow")]
+    Allow,
+
+    /// Report the findings without failing the audit
+    #[serde(rename = "warn")]
+    Warn,
+
+    /// Report the findings and fail the audit
+    #[serde(rename = "deny")]
+    Deny,
+}
+
+/// Severity of a vulnerability, as used by the [`PolicyConfig`]
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum SeverityBucket {
+    /// The advisory has no CVSS score
+    #[serde(rename = "unscored")]
+    Unscored,
+
+    /// CVSS severity "none"
+    #[serde(rename = "none")]
+    None,
+
+    /// CVSS severity "low"
+    #[serde(rename = "low")]
+    Low,
+
+    /// CVSS severity "medium"
+    #[serde(rename = "medium")]
+    Medium,
+
+    /// CVSS severity "high"
+    #[serde(rename = "high")]
+    High,
+
+    /// CVSS severity "critical"
+    #[serde(rename = "critical")]
+    Critical,
+}
+
+impl SeverityBucket {
+    /// Get the name of this severity, as used in the configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeverityBucket::Unscored => "unscored",
+            SeverityBucket::None => "none",
+            SeverityBucket::Low => "low",
+            SeverityBucket::Medium => "medium",
+            SeverityBucket::High => "high",
+            SeverityBucket::Critical => "critical",
+        }
+    }
+}
+
+impl From<Option<advisory::Severity>> for SeverityBucket {
+    fn from(severity: Option<advisory::Severity>) -> Self {
+        match severity {
+            None => SeverityBucket::Unscored,
+            Some(advisory::Severity::None) => SeverityBucket::None,
+            Some(advisory::Severity::Low) => SeverityBucket::Low,
+            Some(advisory::Severity::Medium) => SeverityBucket::Medium,
+            Some(advisory::Severity::High) => SeverityBucket::High,
+            Some(advisory::Severity::Critical) => SeverityBucket::Critical,
+        }
+    }
+}
+
+/// Match a name against a glob pattern supporting `*` and `?`
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            glob_matches(rest, name) || (!name.is_empty() && glob_matches(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => glob_matches(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && glob_matches(rest, name_rest),
+        _ => false,
     }
 }
 
@@ -176,6 +844,10 @@ pub enum DenyOption {
     /// Deny yanked dependency warnings
     #[serde(rename = "yanked")]
     Yanked,
+
+    /// Deny dependencies which have been superseded by another crate
+    #[serde(rename = "deprecated")]
+    Deprecated,
 }
 
 impl DenyOption {
@@ -186,6 +858,7 @@ impl DenyOption {
             DenyOption::Unmaintained,
             DenyOption::Unsound,
             DenyOption::Yanked,
+            DenyOption::Deprecated,
         ]
     }
     /// Get the warning::Kind that corresponds to self, if applicable
@@ -199,6 +872,7 @@ impl DenyOption {
             DenyOption::Unmaintained => &[WarningKind::Unmaintained],
             DenyOption::Unsound => &[WarningKind::Unsound],
             DenyOption::Yanked => &[WarningKind::Yanked],
+            DenyOption::Deprecated => &[WarningKind::Deprecated],
         }
     }
 }
@@ -212,6 +886,7 @@ impl FromStr for DenyOption {
             "unmaintained" => Ok(DenyOption::Unmaintained),
             "unsound" => Ok(DenyOption::Unsound),
             "yanked" => Ok(DenyOption::Yanked),
+            "deprecated" => Ok(DenyOption::Deprecated),
             other => Err(Error::new(
                 ErrorKind::Parse,
                 &format!("invalid deny option: {}", other),
@@ -231,22 +906,160 @@ pub enum OutputFormat {
     #[serde(rename = "terminal")]
     #[default]
     Terminal,
+
+    /// GitLab dependency scanning report (JSON)
+    #[serde(rename = "gitlab")]
+    Gitlab,
+
+    /// Newline-delimited JSON, with one line per report, printed as soon as
+    /// each report is ready
+    #[serde(rename = "ndjson")]
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "terminal" => Ok(OutputFormat::Terminal),
+            "gitlab" => Ok(OutputFormat::Gitlab),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            other => Err(Error::new(
+                ErrorKind::Parse,
+                &format!("invalid output format: {}", other),
+            )),
+        }
+    }
+}
+
+/// How to group findings in the terminal output
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum GroupBy {
+    /// Group vulnerabilities by severity, most severe first, followed by
+    /// warnings grouped by kind
+    #[serde(rename = "severity")]
+    Severity,
+
+    /// Group findings by the affected package
+    #[serde(rename = "package")]
+    Package,
+
+    /// Group findings by the direct dependencies of the root packages which
+    /// pull in the affected package
+    #[serde(rename = "direct-dep")]
+    DirectDep,
+}
+
+impl FromStr for GroupBy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "severity" => Ok(GroupBy::Severity),
+            "package" => Ok(GroupBy::Package),
+            "direct-dep" => Ok(GroupBy::DirectDep),
+            other => Err(Error::new(
+                ErrorKind::Parse,
+                &format!("invalid grouping: {}", other),
+            )),
+        }
+    }
+}
+
+/// Whether to query osv.dev for advisories
+#[derive(Default, Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum OsvMode {
+    /// Only use the advisory database
+    #[serde(rename = "off")]
+    #[default]
+    Off,
+
+    /// Merge advisories from osv.dev with the advisory database
+    #[serde(rename = "merge")]
+    Merge,
+
+    /// Only use advisories from osv.dev
+    #[serde(rename = "only")]
+    Only,
+}
+
+impl FromStr for OsvMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "off" => Ok(OsvMode::Off),
+            "merge" => Ok(OsvMode::Merge),
+            "only" => Ok(OsvMode::Only),
+            other => Err(Error::new(
+                ErrorKind::Parse,
+                &format!("invalid osv.dev query mode: {}", other),
+            )),
+        }
+    }
 }
 
 /// Target configuration
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct TargetConfig {
     /// Target architecture to find vulnerabilities for
     pub arch: Option<Arch>,
 
     /// Target OS to find vulnerabilities for
     pub os: Option<OS>,
+
+    /// Target triple to find vulnerabilities for, which sets the architecture
+    /// and OS unless they're given explicitly
+    #[serde(default, deserialize_with = "deserialize_triple")]
+    pub triple: Option<String>,
+}
+
+impl TargetConfig {
+    /// Target architecture, either given explicitly or that of the target triple
+    pub fn arch(&self) -> Option<Arch> {
+        self.arch
+            .or_else(|| self.platform().map(|platform| platform.target_arch))
+    }
+
+    /// Target OS, either given explicitly or that of the target triple
+    pub fn os(&self) -> Option<OS> {
+        self.os
+            .or_else(|| self.platform().map(|platform| platform.target_os))
+    }
+
+    /// Platform of the target triple
+    fn platform(&self) -> Option<&'static Platform> {
+        self.triple.as_deref().and_then(Platform::find)
+    }
+}
+
+/// Parse a target triple, checking that it's a known target
+fn deserialize_triple<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let triple = Option::<String>::deserialize(deserializer)?;
+    if let Some(triple) = &triple {
+        if Platform::find(triple).is_none() {
+            return Err(D::Error::custom(format!(
+                "unknown target triple: {}",
+                triple
+            )));
+        }
+    }
+    Ok(triple)
 }
 
 /// Configuration for auditing for yanked crates
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+#[non_exhaustive]
 pub struct YankedConfig {
     /// Is auditing for yanked crates enabled?
     #[serde(default = "default_true")]
@@ -270,3 +1083,49 @@ impl Default for YankedConfig {
 fn default_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_triple() {
+        let target: TargetConfig = toml::from_str("triple = \"x86_64-unknown-linux-gnu\"").unwrap();
+        assert_eq!(target.arch(), Some(Arch::X86_64));
+        assert_eq!(target.os(), Some(OS::Linux));
+
+        let target: TargetConfig =
+            toml::from_str("triple = \"x86_64-unknown-linux-gnu\"\nos = \"android\"").unwrap();
+        assert_eq!(target.arch(), Some(Arch::X86_64));
+        assert_eq!(target.os(), Some(OS::Android));
+
+        assert!(toml::from_str::<TargetConfig>("triple = \"no-such-target\"").is_err());
+    }
+
+    #[test]
+    fn group_versions() {
+        let mut config = AuditConfig::default();
+        assert!(!config.report_settings().group_versions);
+
+        config.output.group_versions = true;
+        assert!(config.report_settings().group_versions);
+    }
+
+    #[test]
+    fn theme_colors() {
+        let theme = |toml: &str| toml::from_str::<ThemeConfig>(toml).unwrap();
+
+        let default = theme("");
+        assert_eq!(default.error_color(), Some(ThemeColor::Red));
+        assert_eq!(default.warning_color(), Some(ThemeColor::Yellow));
+        assert_eq!(default.resolved_color(), Some(ThemeColor::Green));
+
+        let colorblind = theme("preset = \"colorblind\"\nresolved = \"white\"");
+        assert_eq!(colorblind.error_color(), Some(ThemeColor::Magenta));
+        assert_eq!(colorblind.resolved_color(), Some(ThemeColor::White));
+
+        let monochrome = theme("preset = \"monochrome\"");
+        assert_eq!(monochrome.warning_color(), None);
+        assert_eq!(monochrome.resolved_color(), None);
+    }
+}
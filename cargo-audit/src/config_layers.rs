@@ -0,0 +1,427 @@
+//! Layered configuration files
+//!
+//! `audit.toml` is read from several locations, in increasing order of
+//! precedence:
+//!
+//! 1. system: `/etc/cargo-audit/audit.toml` (`%PROGRAMDATA%\cargo-audit\audit.toml`
+//!    on Windows)
+//! 2. user: `$CARGO_HOME/audit.toml`, then `~/.config/cargo-audit/audit.toml`
+//!    (or `$XDG_CONFIG_HOME/cargo-audit/audit.toml`)
+//! 3. workspace: the nearest `.cargo/audit.toml` in the current directory or
+//!    one of its parents
+//! 4. environment: `CARGO_AUDIT_*` variables, named after the path to the
+//!    setting (e.g. `CARGO_AUDIT_ADVISORIES_SEVERITY_THRESHOLD`)
+//!
+//! Command-line flags take precedence over all of them. Tables are merged key
+//! by key, while any other value (including arrays) replaces the value of the
+//! same key in the layers before it.
+
+use crate::{commands::CONFIG_FILE, config::AuditConfig};
+use rustsec::{Error, ErrorKind};
+use std::{
+    env,
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Name of the directory holding system and user configuration files
+const CONFIG_DIR: &str = "cargo-audit";
+
+/// Prefix of the environment variables which override configuration settings
+pub const ENV_PREFIX: &str = "CARGO_AUDIT_";
+
+/// Environment variables with [`ENV_PREFIX`] which aren't configuration
+/// settings
+const RESERVED_VARS: &[&str] = &["CARGO_AUDIT_EMBEDDED_DB", "CARGO_AUDIT_UPLOAD_TOKEN"];
+
+/// Level a configuration file applies at
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Layer {
+    /// Settings for every user of the machine
+    System,
+
+    /// Settings for every project of the current user
+    User,
+
+    /// Settings for the current workspace
+    Workspace,
+
+    /// Settings from `CARGO_AUDIT_*` environment variables
+    Environment,
+}
+
+impl Layer {
+    /// Get a `str` representing this layer
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Layer::System => "system",
+            Layer::User => "user",
+            Layer::Workspace => "workspace",
+            Layer::Environment => "env",
+        }
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Location of a configuration file, which may or may not exist
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    /// Layer the file belongs to
+    pub layer: Layer,
+
+    /// Path to the file
+    pub path: PathBuf,
+}
+
+impl ConfigFile {
+    /// Does this file exist?
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+}
+
+/// Locations of the configuration files, from lowest to highest precedence
+pub fn discover() -> Vec<ConfigFile> {
+    let mut files = vec![];
+
+    if let Some(path) = system_config_path() {
+        files.push(ConfigFile {
+            layer: Layer::System,
+            path,
+        });
+    }
+
+    let cargo_home_config = home::cargo_home()
+        .ok()
+        .map(|cargo_home| cargo_home.join(CONFIG_FILE));
+
+    if let Some(path) = &cargo_home_config {
+        files.push(ConfigFile {
+            layer: Layer::User,
+            path: path.clone(),
+        });
+    }
+
+    if let Some(path) = user_config_path() {
+        files.push(ConfigFile {
+            layer: Layer::User,
+            path,
+        });
+    }
+
+    files.push(ConfigFile {
+        layer: Layer::Workspace,
+        path: workspace_config_path(cargo_home_config.as_deref()),
+    });
+
+    files
+}
+
+/// Load and merge the given configuration files (skipping the ones which
+/// don't exist)
+pub fn load(files: &[ConfigFile]) -> Result<AuditConfig, Error> {
+    into_config(load_files(files)?)
+}
+
+/// Load and merge the given configuration files, and override them with the
+/// `CARGO_AUDIT_*` variables among the given environment variables.
+///
+/// Variables which don't name a configuration setting (e.g. ones used by
+/// other tools) are skipped, and their names are returned along with the
+/// configuration so they can be reported.
+pub fn load_with_env<I>(files: &[ConfigFile], vars: I) -> Result<(AuditConfig, Vec<String>), Error>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let mut merged = load_files(files)?;
+    let env = env_settings(vars)?;
+    merge(&mut merged, env.table);
+    Ok((into_config(merged)?, env.unknown))
+}
+
+/// Names of the `CARGO_AUDIT_*` variables among the given environment
+/// variables which override configuration settings
+pub fn env_var_names<I>(vars: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let defaults = default_table();
+    let mut names: Vec<_> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+        .filter(|(name, _)| is_config_var(name))
+        .filter(|(name, value)| {
+            let setting = env_setting(&defaults, &env_key(name), &value.to_string_lossy());
+            !matches!(setting, Err(e) if is_unknown_field(&e))
+        })
+        .map(|(name, _)| name)
+        .collect();
+
+    names.sort();
+    names
+}
+
+/// Load and merge the given configuration files into a TOML table
+fn load_files(files: &[ConfigFile]) -> Result<Table, Error> {
+    let mut merged = Table::new();
+
+    for file in files.iter().filter(|file| file.exists()) {
+        let table = read_table(&file.path)?;
+
+        // Check each file on its own, so errors point at the file they're in
+        Value::Table(table.clone())
+            .try_into::<AuditConfig>()
+            .map_err(|e| invalid_config(&file.path, e))?;
+
+        merge(&mut merged, table);
+    }
+
+    Ok(merged)
+}
+
+/// Convert the merged layers into the configuration
+fn into_config(merged: Table) -> Result<AuditConfig, Error> {
+    Value::Table(merged).try_into().map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "invalid merged configuration".to_owned(),
+            e,
+        )
+    })
+}
+
+/// Settings made by `CARGO_AUDIT_*` environment variables
+struct EnvSettings {
+    /// The settings, as a TOML table
+    table: Table,
+
+    /// Names of the variables which don't name a configuration setting
+    unknown: Vec<String>,
+}
+
+/// Could the given environment variable be a configuration setting?
+fn is_config_var(name: &str) -> bool {
+    name.len() > ENV_PREFIX.len() && name.starts_with(ENV_PREFIX) && !RESERVED_VARS.contains(&name)
+}
+
+/// Key of the setting an environment variable may set, e.g.
+/// `output_theme_preset` for `CARGO_AUDIT_OUTPUT_THEME_PRESET`
+fn env_key(name: &str) -> String {
+    name[ENV_PREFIX.len()..].to_ascii_lowercase()
+}
+
+/// The default configuration as a TOML table, which tells which settings are
+/// tables
+fn default_table() -> Table {
+    match Value::try_from(AuditConfig::default()) {
+        Ok(Value::Table(defaults)) => defaults,
+        _ => Table::new(),
+    }
+}
+
+/// Convert the `CARGO_AUDIT_*` variables among the given environment
+/// variables into a TOML table
+fn env_settings<I>(vars: I) -> Result<EnvSettings, Error>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let defaults = default_table();
+
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+        .filter(|(name, _)| is_config_var(name))
+        .collect();
+    vars.sort();
+
+    let mut settings = EnvSettings {
+        table: Table::new(),
+        unknown: vec![],
+    };
+
+    for (name, value) in vars {
+        let invalid_value = |e: Option<toml::de::Error>| {
+            let description = format!("invalid value for {}", name);
+            match e {
+                Some(e) => Error::with_source(ErrorKind::Parse, description, e),
+                None => Error::new(ErrorKind::Parse, &description),
+            }
+        };
+
+        let value = value.into_string().map_err(|_| invalid_value(None))?;
+
+        // Check each variable on its own, so errors point at the variable
+        match env_setting(&defaults, &env_key(&name), &value) {
+            Ok(table) => merge(&mut settings.table, table),
+            Err(e) if is_unknown_field(&e) => settings.unknown.push(name),
+            Err(e) => return Err(invalid_value(Some(e))),
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Convert the value of an environment variable into a table with the
+/// setting named by `key`.
+///
+/// How the value is parsed depends on the type of the setting, as checked by
+/// deserializing the configuration: strings don't need to be quoted, and
+/// lists can be given comma-separated as well as in TOML syntax.
+fn env_setting(defaults: &Table, key: &str, value: &str) -> Result<Table, toml::de::Error> {
+    let error = match checked_setting(defaults, key, parse_literal(value)) {
+        Ok(table) => return Ok(table),
+        Err(e) => e,
+    };
+
+    if is_unknown_field(&error) || value.trim_start().starts_with('[') {
+        return Err(error);
+    }
+
+    let list = Value::Array(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(parse_literal)
+            .collect(),
+    );
+
+    [Value::String(value.to_owned()), list]
+        .into_iter()
+        .find_map(|value| checked_setting(defaults, key, value).ok())
+        .ok_or(error)
+}
+
+/// Build a table with the setting named by `key` set to `value`, checking
+/// that it is a valid configuration
+fn checked_setting(defaults: &Table, key: &str, value: Value) -> Result<Table, toml::de::Error> {
+    let mut table = Table::new();
+    insert_env_value(&mut table, defaults, key, value);
+    Value::Table(table.clone()).try_into::<AuditConfig>()?;
+    Ok(table)
+}
+
+/// Is the error about a setting which doesn't exist?
+fn is_unknown_field(error: &toml::de::Error) -> bool {
+    error.message().starts_with("unknown field")
+}
+
+/// Insert a value into `table` at the path spelled by the key of an
+/// environment variable, e.g. `output_theme_preset` for `preset` in the
+/// `[output.theme]` table
+fn insert_env_value(table: &mut Table, defaults: &Table, key: &str, value: Value) {
+    // Descend into the longest table name the key starts with, as names of
+    // settings contain underscores too
+    let section = defaults
+        .iter()
+        .filter_map(|(name, default)| match default {
+            Value::Table(defaults) => {
+                let rest = key.strip_prefix(name.as_str())?.strip_prefix('_')?;
+                Some((name, defaults, rest))
+            }
+            _ => None,
+        })
+        .max_by_key(|(name, _, _)| name.len());
+
+    match section {
+        Some((name, defaults, rest)) => {
+            let mut section = Table::new();
+            insert_env_value(&mut section, defaults, rest, value);
+            table.insert(name.clone(), Value::Table(section));
+        }
+        None => {
+            table.insert(key.to_owned(), value);
+        }
+    }
+}
+
+/// Parse a TOML value (e.g. `true`, `42` or `["a", "b"]`), falling back to a
+/// string with the given contents
+fn parse_literal(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_owned()))
+}
+
+/// Merge the `overlay` table into `base`, with the values in `overlay`
+/// taking precedence
+pub fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(overlay_table)) => {
+                merge(base_table, overlay_table)
+            }
+            (Some(base_value), value) => *base_value = value,
+            (None, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Read a configuration file as a TOML table
+fn read_table(path: &Path) -> Result<Table, Error> {
+    let toml = fs::read_to_string(path).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Io,
+            format!("couldn't read {}", path.display()),
+            e,
+        )
+    })?;
+
+    toml::from_str(&toml).map_err(|e| invalid_config(path, e))
+}
+
+/// Error for an invalid configuration file
+fn invalid_config(path: &Path, e: toml::de::Error) -> Error {
+    Error::with_source(
+        ErrorKind::Parse,
+        format!("invalid configuration file {}", path.display()),
+        e,
+    )
+}
+
+/// Location of the system-wide configuration file
+fn system_config_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA").map(|dir| PathBuf::from(dir).join(CONFIG_DIR).join(CONFIG_FILE))
+    } else {
+        Some(Path::new("/etc").join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+}
+
+/// Location of the configuration file in the user's configuration directory
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".config"),
+    };
+
+    Some(config_dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+/// Location of the nearest workspace configuration file, or the one in the
+/// current directory if there is none
+fn workspace_config_path(cargo_home_config: Option<&Path>) -> PathBuf {
+    let default = PathBuf::from(".cargo").join(CONFIG_FILE);
+    let cwd = match env::current_dir() {
+        Ok(cwd) => cwd,
+        Err(_) => return default,
+    };
+
+    cwd.ancestors()
+        .map(|dir| dir.join(".cargo").join(CONFIG_FILE))
+        // `~/.cargo/audit.toml` is the user's configuration file, even when
+        // auditing a project in the home directory
+        .filter(|path| Some(path.as_path()) != cargo_home_config)
+        .find(|path| path.is_file())
+        .unwrap_or(default)
+}
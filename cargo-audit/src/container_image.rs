@@ -0,0 +1,658 @@
+//! Extracts executables from the layers of OCI and Docker container images,
+//! either from an image tarball or by exporting a local image with
+//! `docker save`.
+//!
+//! Images are read from disk as needed rather than loaded into memory: the
+//! output of `docker save` and compressed image tarballs are spooled to
+//! temporary files, and only the executables found in the layers are kept.
+//!
+//! Layers are applied in order, honoring whiteout files, so only the
+//! executables present in the final filesystem of the image are returned.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use rustsec::{Error, ErrorKind};
+use serde::Deserialize;
+
+use crate::binary_archive::{
+    decoder, extract_error, is_compressed, is_executable, too_large, ArchiveEntry, Budget, MAX_SIZE,
+};
+
+/// Number of characters of layer digests to show in paths
+const LAYER_ID_LEN: usize = 12;
+
+/// Maximum depth of nested OCI image indexes to follow
+const MAX_INDEX_DEPTH: usize = 4;
+
+/// Maximum size of the JSON documents describing an image
+const MAX_DOCUMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Maximum size of decompressed image tarballs, which are spooled to disk
+const MAX_TARBALL_SIZE: u64 = 8 * 1024 * 1024 * 1024;
+
+/// Prefix of whiteout files, which delete the file of the same name from the
+/// layers below
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// Whiteout file marking a directory as opaque, which hides the contents of
+/// the directory in the layers below
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// Entry of `manifest.json` in tarballs written by `docker save`
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerManifest {
+    layers: Vec<String>,
+}
+
+/// OCI image index or image manifest, of which only the references to other
+/// manifests and to layers are needed
+#[derive(Deserialize)]
+struct OciDocument {
+    #[serde(default)]
+    manifests: Vec<OciDescriptor>,
+
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+}
+
+/// Reference to a blob in an OCI image layout
+#[derive(Deserialize)]
+struct OciDescriptor {
+    digest: String,
+
+    /// Platform of the image, for manifests in an image index
+    #[serde(default)]
+    platform: Option<OciPlatform>,
+}
+
+/// Platform an image in an image index was built for
+#[derive(Deserialize)]
+struct OciPlatform {
+    architecture: String,
+    os: String,
+}
+
+impl OciDescriptor {
+    /// Path of the blob in the image layout
+    fn blob_path(&self) -> String {
+        format!("blobs/{}", self.digest.replacen(':', "/", 1))
+    }
+
+    /// Is this the manifest of an image for the given OS and architecture
+    /// (as named by OCI, e.g. `linux` and `amd64`)?
+    fn is_for(&self, os: &str, architecture: &str) -> bool {
+        self.platform.as_ref().map_or(false, |platform| {
+            platform.os == os && platform.architecture == architecture
+        })
+    }
+
+    /// Is this the manifest of an attestation (e.g. a build provenance)
+    /// rather than of an image?
+    fn is_attestation(&self) -> bool {
+        self.platform
+            .as_ref()
+            .map_or(false, |platform| platform.os == "unknown")
+    }
+}
+
+/// Files in an image tarball, which are read on demand
+struct ImageTarball {
+    /// Name of the image, for error messages
+    image: String,
+
+    /// The (uncompressed) tarball
+    file: File,
+
+    /// Offset and size of each regular file in the tarball
+    files: BTreeMap<String, (u64, u64)>,
+}
+
+impl ImageTarball {
+    /// Index the files in an image tarball
+    fn open(image: &str, mut file: File) -> rustsec::Result<Self> {
+        let path = Path::new(image);
+        let mut files = BTreeMap::new();
+        let mut archive = tar::Archive::new(&mut file);
+
+        for entry in archive.entries().map_err(|e| extract_error(path, e))? {
+            let entry = entry.map_err(|e| extract_error(path, e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let name = entry.path().map_err(|e| extract_error(path, e))?;
+            files.insert(
+                normalize(&name.to_string_lossy()).to_owned(),
+                (entry.raw_file_position(), entry.size()),
+            );
+        }
+
+        Ok(Self {
+            image: image.to_owned(),
+            file,
+            files,
+        })
+    }
+
+    /// Reader for a file in the tarball, or `None` if there is no such file
+    fn reader(&mut self, name: &str) -> rustsec::Result<Option<impl Read + '_>> {
+        let (offset, size) = match self.files.get(name) {
+            Some(&location) => location,
+            None => return Ok(None),
+        };
+
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| extract_error(Path::new(&self.image), e))?;
+
+        Ok(Some((&mut self.file).take(size)))
+    }
+
+    /// Parse a JSON document in the tarball, or `None` if there is no such file
+    fn document<T: serde::de::DeserializeOwned>(
+        &mut self,
+        name: &str,
+    ) -> rustsec::Result<Option<T>> {
+        let image = self.image.clone();
+        let reader = match self.reader(name)? {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+
+        let mut data = vec![];
+        reader
+            .take(MAX_DOCUMENT_SIZE + 1)
+            .read_to_end(&mut data)
+            .map_err(|e| extract_error(Path::new(&image), e))?;
+
+        if data.len() as u64 > MAX_DOCUMENT_SIZE {
+            return Err(invalid_image(&image, &format!("{} is too large", name)));
+        }
+
+        serde_json::from_slice(&data).map(Some).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("invalid {} in image {}", name, image),
+                e,
+            )
+        })
+    }
+}
+
+/// Load the executables in the layers of an image, given either the path to
+/// an image tarball or a reference to an image available to `docker`. Paths
+/// of the executables are made of the image, the layer and the path inside
+/// the layer, e.g. `myapp:latest/layer-3-1a2b3c4d5e6f/usr/local/bin/myapp`.
+pub fn load_image(image: &str) -> rustsec::Result<Vec<ArchiveEntry>> {
+    let path = Path::new(image);
+    let file = if path.is_file() {
+        File::open(path)?
+    } else {
+        export_image(image)?
+    };
+
+    let mut tarball = ImageTarball::open(image, uncompressed(path, file)?)?;
+    let layers = layers(&mut tarball)?;
+
+    let mut budget = Budget::new();
+    let mut executables = BTreeMap::new();
+
+    for (index, layer) in layers.iter().enumerate() {
+        let layer_path =
+            PathBuf::from(image).join(format!("layer-{}-{}", index + 1, layer_id(layer)));
+
+        let reader = tarball
+            .reader(layer)?
+            .ok_or_else(|| invalid_image(image, &format!("missing layer {}", layer)))?;
+
+        let reader = decoder(&layer_path, BufReader::new(reader))?;
+        read_layer(&layer_path, reader, &mut budget)?.apply(&layer_path, &mut executables);
+    }
+
+    Ok(executables.into_values().collect())
+}
+
+/// Get an uncompressed copy of an image tarball: the file itself, or a
+/// temporary file it's decompressed into
+fn uncompressed(path: &Path, mut file: File) -> rustsec::Result<File> {
+    let mut magic = [0; 6];
+    let read = read_up_to(&mut file, &mut magic).map_err(|e| extract_error(path, e))?;
+    file.rewind().map_err(|e| extract_error(path, e))?;
+
+    if !is_compressed(&magic[..read]) {
+        return Ok(file);
+    }
+
+    let mut decompressed = tempfile::tempfile().map_err(|e| extract_error(path, e))?;
+    let copied = io::copy(
+        &mut decoder(path, BufReader::new(file))?.take(MAX_TARBALL_SIZE + 1),
+        &mut decompressed,
+    )
+    .map_err(|e| extract_error(path, e))?;
+
+    if copied > MAX_TARBALL_SIZE {
+        return Err(too_large(path));
+    }
+
+    decompressed.rewind().map_err(|e| extract_error(path, e))?;
+    Ok(decompressed)
+}
+
+/// Fill as much of the buffer as possible, returning the number of bytes read
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+
+    while read < buf.len() {
+        match reader.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+
+    Ok(read)
+}
+
+/// Export an image with `docker save` into a temporary file
+fn export_image(image: &str) -> rustsec::Result<File> {
+    let file = tempfile::tempfile()?;
+
+    let output = Command::new("docker")
+        .args(["save", "--", image])
+        .stdout(file.try_clone()?)
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't run `docker save {}`", image),
+                e,
+            )
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            &format!(
+                "couldn't export image {} (pull it first, or pass the path to an image tarball): {}",
+                image,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(file)
+}
+
+/// Get the paths of the layer blobs in an image tarball, from the lowest
+/// layer to the topmost one
+fn layers(tarball: &mut ImageTarball) -> rustsec::Result<Vec<String>> {
+    // Tarballs written by `docker save` have a `manifest.json` listing the
+    // layers, even when they are OCI image layouts as well
+    if let Some(manifests) = tarball.document::<Vec<DockerManifest>>("manifest.json")? {
+        return Ok(manifests
+            .into_iter()
+            .flat_map(|manifest| manifest.layers)
+            .map(|layer| normalize(&layer).to_owned())
+            .collect());
+    }
+
+    // Otherwise follow the image index of an OCI image layout to the image
+    // manifest for the platform
+    let mut document = oci_document(tarball, "index.json")?;
+
+    for _ in 0..MAX_INDEX_DEPTH {
+        if !document.layers.is_empty() {
+            return Ok(document
+                .layers
+                .iter()
+                .map(OciDescriptor::blob_path)
+                .collect());
+        }
+
+        let descriptor = select_manifest(&document.manifests)
+            .ok_or_else(|| invalid_image(&tarball.image, "no image manifest"))?;
+        document = oci_document(tarball, &descriptor.blob_path())?;
+    }
+
+    Err(invalid_image(
+        &tarball.image,
+        "too many nested image indexes",
+    ))
+}
+
+/// Pick the manifest of the image to audit from an image index: the image
+/// for the platform `cargo audit` runs on (on Linux), or else the first image
+/// which isn't an attestation
+fn select_manifest(manifests: &[OciDescriptor]) -> Option<&OciDescriptor> {
+    let architecture = oci_architecture(std::env::consts::ARCH);
+
+    manifests
+        .iter()
+        .find(|manifest| manifest.is_for("linux", architecture))
+        .or_else(|| manifests.iter().find(|manifest| !manifest.is_attestation()))
+}
+
+/// Name of a CPU architecture in OCI image indexes, which follow Go
+fn oci_architecture(arch: &str) -> &str {
+    match arch {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "powerpc64" => "ppc64le",
+        other => other,
+    }
+}
+
+/// Parse an OCI image index or manifest in an image tarball
+fn oci_document(tarball: &mut ImageTarball, name: &str) -> rustsec::Result<OciDocument> {
+    tarball.document(name)?.ok_or_else(|| {
+        invalid_image(
+            &tarball.image,
+            &format!("no {} (expected an OCI or Docker image)", name),
+        )
+    })
+}
+
+/// Changes made by a layer to the filesystem of the layers below
+#[derive(Default)]
+struct LayerChanges {
+    /// Paths deleted by whiteout files, along with everything under them
+    deleted: Vec<String>,
+
+    /// Directories whose contents in the layers below are hidden
+    opaque: Vec<String>,
+
+    /// Paths of the entries added by the layer which aren't directories
+    replaced: Vec<String>,
+
+    /// Executables added by the layer, by path
+    executables: Vec<(String, Vec<u8>)>,
+}
+
+impl LayerChanges {
+    /// Apply the changes to the executables of the layers below
+    fn apply(self, layer_path: &Path, executables: &mut BTreeMap<String, ArchiveEntry>) {
+        for dir in &self.opaque {
+            executables.retain(|path, _| !is_under(path, dir));
+        }
+
+        for path in self.deleted.iter().chain(&self.replaced) {
+            executables.retain(|existing, _| existing != path && !is_under(existing, path));
+        }
+
+        for (path, contents) in self.executables {
+            executables.insert(
+                path.clone(),
+                ArchiveEntry {
+                    path: layer_path.join(&path),
+                    contents,
+                },
+            );
+        }
+    }
+}
+
+/// Read the changes made by a layer from its (uncompressed) tarball. Files
+/// are read one at a time and only executables are kept, which are charged
+/// to the budget of the image.
+fn read_layer(
+    layer_path: &Path,
+    reader: impl Read,
+    budget: &mut Budget,
+) -> rustsec::Result<LayerChanges> {
+    let mut changes = LayerChanges::default();
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive
+        .entries()
+        .map_err(|e| extract_error(layer_path, e))?
+    {
+        let mut entry = entry.map_err(|e| extract_error(layer_path, e))?;
+        let name = entry.path().map_err(|e| extract_error(layer_path, e))?;
+        let name = normalize(&name.to_string_lossy()).to_owned();
+
+        let (dir, file_name) = match name.rsplit_once('/') {
+            Some((dir, file_name)) => (dir, file_name),
+            None => ("", name.as_str()),
+        };
+
+        if file_name == OPAQUE_WHITEOUT {
+            changes.opaque.push(dir.to_owned());
+            continue;
+        }
+
+        if let Some(deleted) = file_name.strip_prefix(WHITEOUT_PREFIX) {
+            changes.deleted.push(join(dir, deleted));
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            continue;
+        }
+
+        changes.replaced.push(name.clone());
+
+        if entry_type.is_file() {
+            let mut contents = vec![];
+            (&mut entry)
+                .take(MAX_SIZE + 1)
+                .read_to_end(&mut contents)
+                .map_err(|e| extract_error(layer_path, e))?;
+
+            if contents.len() as u64 > MAX_SIZE {
+                return Err(too_large(layer_path));
+            }
+
+            if is_executable(&contents) {
+                budget.spend(layer_path, contents.len() as u64)?;
+                changes.executables.push((name, contents));
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Strip the `./` or `/` prefix and any trailing `/` of a path in a tarball
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./")
+        .trim_start_matches('/')
+        .trim_end_matches('/')
+}
+
+/// Join a directory and a name within it
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Is the path inside the given directory? Every path is inside the root
+/// directory, given as an empty string.
+fn is_under(path: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || path
+            .strip_prefix(dir)
+            .map_or(false, |rest| rest.starts_with('/'))
+}
+
+/// Short identifier of a layer: the start of its digest, taken from a blob
+/// path (`blobs/sha256/<digest>`) or a `docker save` layer path
+/// (`<digest>/layer.tar`)
+fn layer_id(layer: &str) -> &str {
+    let digest = layer
+        .rsplit('/')
+        .find(|segment| !segment.contains('.'))
+        .unwrap_or(layer);
+
+    digest.get(..LAYER_ID_LEN).unwrap_or(digest)
+}
+
+/// Error for an image which couldn't be read
+fn invalid_image(image: &str, reason: &str) -> Error {
+    Error::new(
+        ErrorKind::Parse,
+        &format!("invalid image {}: {}", image, reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Start of a 64-bit little-endian ELF executable
+    fn elf(marker: u8) -> Vec<u8> {
+        let mut data = b"\x7fELF\x02\x01\x01".to_vec();
+        data.resize(64, 0);
+        data.push(marker);
+        data
+    }
+
+    /// Build a tarball with the given regular files, or symlinks for entries
+    /// without contents
+    fn tar(files: &[(&str, Option<&[u8]>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        for (name, contents) in files {
+            let mut header = tar::Header::new_ustar();
+            match contents {
+                Some(contents) => {
+                    header.set_size(contents.len() as u64);
+                    header.set_mode(0o755);
+                    header.set_cksum();
+                    builder.append_data(&mut header, name, *contents).unwrap();
+                }
+                None => {
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_size(0);
+                    builder.append_link(&mut header, name, "/bin/true").unwrap();
+                }
+            }
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    /// Write a `docker save` style image tarball with the given layers
+    fn docker_image(layers: &[Vec<u8>]) -> tempfile::NamedTempFile {
+        let names: Vec<String> = (0..layers.len())
+            .map(|index| format!("layer{:064}/layer.tar", index))
+            .collect();
+        let manifest =
+            serde_json::json!([{ "Config": "config.json", "Layers": names }]).to_string();
+
+        let mut files = vec![("manifest.json", Some(manifest.as_bytes()))];
+        for (name, layer) in names.iter().zip(layers) {
+            files.push((name.as_str(), Some(layer.as_slice())));
+        }
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&tar(&files)).unwrap();
+        file
+    }
+
+    fn paths(entries: &[ArchiveEntry], image: &Path) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .path
+                    .strip_prefix(image)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apply_whiteouts() {
+        let lower = tar(&[
+            ("usr/bin/app", Some(&elf(1)[..])),
+            ("usr/bin/removed", Some(&elf(2)[..])),
+            ("opt/tool/bin/tool", Some(&elf(3)[..])),
+            ("usr/local/bin/linked", Some(&elf(4)[..])),
+            ("usr/share/doc/readme", Some(&b"not an executable"[..])),
+        ]);
+        let upper = tar(&[
+            ("usr/bin/.wh.removed", Some(&b""[..])),
+            ("opt/tool/.wh..wh..opq", Some(&b""[..])),
+            ("opt/tool/bin/new-tool", Some(&elf(5)[..])),
+            ("usr/local/bin/linked", None),
+            ("usr/bin/app", Some(&elf(6)[..])),
+        ]);
+
+        let image = docker_image(&[lower, upper]);
+        let path = image.path().to_str().unwrap();
+        let entries = load_image(path).unwrap();
+
+        assert_eq!(
+            paths(&entries, image.path()),
+            [
+                "layer-2-layer0000000/opt/tool/bin/new-tool",
+                "layer-2-layer0000000/usr/bin/app",
+            ]
+        );
+        assert_eq!(entries[1].contents, elf(6));
+    }
+
+    #[test]
+    fn read_compressed_image() {
+        let layer = tar(&[("usr/bin/app", Some(&elf(1)[..]))]);
+        let image = docker_image(&[layer]);
+
+        let mut encoder = flate2::write::GzEncoder::new(
+            tempfile::NamedTempFile::new().unwrap(),
+            Default::default(),
+        );
+        encoder
+            .write_all(&std::fs::read(image.path()).unwrap())
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let entries = load_image(compressed.path().to_str().unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn select_manifest_for_platform() {
+        let index: OciDocument = serde_json::from_str(
+            r#"{"manifests": [
+                {"digest": "sha256:attestation", "platform": {"architecture": "unknown", "os": "unknown"}},
+                {"digest": "sha256:s390x", "platform": {"architecture": "s390x", "os": "linux"}},
+                {"digest": "sha256:host", "platform": {"architecture": "HOST", "os": "linux"}}
+            ]}"#
+            .replace("HOST", oci_architecture(std::env::consts::ARCH))
+            .as_str(),
+        )
+        .unwrap();
+
+        let manifest = select_manifest(&index.manifests).unwrap();
+        if std::env::consts::ARCH != "s390x" {
+            assert_eq!(manifest.digest, "sha256:host");
+        }
+
+        // Without a manifest for the host, attestations are skipped
+        let manifest = select_manifest(&index.manifests[..2]).unwrap();
+        assert_eq!(manifest.digest, "sha256:s390x");
+    }
+
+    #[test]
+    fn paths_under_directories() {
+        assert!(is_under("usr/bin/app", "usr/bin"));
+        assert!(is_under("usr/bin/app", ""));
+        assert!(!is_under("usr/bin2/app", "usr/bin"));
+        assert!(!is_under("usr/bin", "usr/bin"));
+    }
+}
@@ -0,0 +1,400 @@
+//! Dependency lists read from STDIN with `--stdin-format`, for auditing sets
+//! of packages which don't come with a `Cargo.lock`
+//!
+//! Packages without a registry or git source (i.e. path dependencies, such as
+//! workspace members) can't have advisories, so they're skipped.
+
+use rustsec::{
+    cargo_lock::ResolveVersion,
+    package::{Name, Package},
+    Error, ErrorKind, Lockfile, SourceId, Version,
+};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+/// Source of packages from crates.io, which is assumed for packages listed
+/// without one
+const CRATES_IO_INDEX: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+/// Format of the dependency list read from STDIN
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum StdinFormat {
+    /// A `Cargo.lock` file
+    #[default]
+    Lockfile,
+
+    /// `name version` lines (`name@version` works too), optionally followed
+    /// by the source of the package, with `#` comments
+    List,
+
+    /// Output of `cargo tree --prefix none`
+    Tree,
+
+    /// Output of `cargo metadata --format-version 1`
+    Metadata,
+}
+
+impl StdinFormat {
+    /// Get a `str` representing this format
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StdinFormat::Lockfile => "lockfile",
+            StdinFormat::List => "list",
+            StdinFormat::Tree => "tree",
+            StdinFormat::Metadata => "metadata",
+        }
+    }
+
+    /// Parse the dependency list into a lockfile listing its packages
+    pub fn parse(self, input: &str) -> Result<Lockfile, Error> {
+        match self {
+            StdinFormat::Lockfile => Ok(input.parse()?),
+            StdinFormat::List => parse_list(input),
+            StdinFormat::Tree => parse_tree(input),
+            StdinFormat::Metadata => parse_metadata(input),
+        }
+    }
+}
+
+impl fmt::Display for StdinFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StdinFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "lockfile" => Ok(StdinFormat::Lockfile),
+            "list" => Ok(StdinFormat::List),
+            "tree" => Ok(StdinFormat::Tree),
+            "metadata" => Ok(StdinFormat::Metadata),
+            other => Err(Error::new(
+                ErrorKind::Parse,
+                &format!(
+                    "invalid STDIN format: {} (expected lockfile, list, tree or metadata)",
+                    other
+                ),
+            )),
+        }
+    }
+}
+
+/// Parse `name version` lines, optionally followed by a source such as
+/// `registry+https://github.com/rust-lang/crates.io-index`
+fn parse_list(input: &str) -> Result<Lockfile, Error> {
+    let mut packages = vec![];
+
+    for (index, line) in input.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid_line = || {
+            Error::new(
+                ErrorKind::Parse,
+                &format!(
+                    "invalid dependency on line {}: {} (expected `name version [source]`)",
+                    index + 1,
+                    line
+                ),
+            )
+        };
+
+        let mut fields = line.split_whitespace();
+        let first = fields.next().ok_or_else(invalid_line)?;
+        let (name, version) = match first.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (first, fields.next()),
+        };
+
+        let name = name.parse().map_err(|_| invalid_line())?;
+        let version = version
+            .ok_or_else(invalid_line)?
+            .trim_start_matches('v')
+            .parse()
+            .map_err(|_| invalid_line())?;
+        let source = match fields.next() {
+            Some(source) => SourceId::from_url(source).map_err(|_| invalid_line())?,
+            None => crates_io(),
+        };
+
+        if fields.next().is_some() {
+            return Err(invalid_line());
+        }
+
+        packages.push(package(name, version, source));
+    }
+
+    Ok(lockfile(packages))
+}
+
+/// Strip the `#` comment from a line of a dependency list. Comments start at
+/// the beginning of the line or after whitespace, as the sources of git
+/// packages contain a `#` followed by the commit.
+fn strip_comment(line: &str) -> &str {
+    let start = line
+        .char_indices()
+        .find(|&(index, c)| {
+            c == '#'
+                && line[..index]
+                    .chars()
+                    .last()
+                    .map_or(true, char::is_whitespace)
+        })
+        .map_or(line.len(), |(index, _)| index);
+
+    &line[..start]
+}
+
+/// Parse the output of `cargo tree --prefix none`, e.g.
+/// `serde v1.0.130 (*)` or `foo v0.1.0 (/path/to/foo)`
+fn parse_tree(input: &str) -> Result<Lockfile, Error> {
+    let mut packages = vec![];
+    let mut listed = 0;
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+
+        // Blank lines separate the trees of workspace members, and headers
+        // such as `[build-dependencies]` precede the dependencies of a kind
+        if line.is_empty() || (line.starts_with('[') && line.ends_with(']')) {
+            continue;
+        }
+
+        let invalid_line = || {
+            Error::new(
+                ErrorKind::Parse,
+                &format!(
+                    "invalid `cargo tree` output on line {}: {} (expected `name vversion`)",
+                    index + 1,
+                    line
+                ),
+            )
+        };
+
+        let mut tokens = line.split_whitespace();
+        let name = tokens
+            .next()
+            .and_then(|name| name.parse().ok())
+            .ok_or_else(invalid_line)?;
+        let version = tokens
+            .next()
+            .and_then(|version| version.strip_prefix('v'))
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(invalid_line)?;
+        listed += 1;
+
+        // Packages from crates.io are shown without their source, while
+        // path, git and alternative registry packages have it in parens
+        let has_source =
+            tokens.any(|token| token.starts_with('(') && token != "(*)" && token != "(proc-macro)");
+
+        if !has_source {
+            packages.push(package(name, version, crates_io()));
+        }
+    }
+
+    if listed == 0 {
+        return Err(Error::new(
+            ErrorKind::Parse,
+            &"no packages in `cargo tree` output",
+        ));
+    }
+
+    Ok(lockfile(packages))
+}
+
+/// Subset of the output of `cargo metadata --format-version 1`
+#[derive(Deserialize)]
+struct MetadataOutput {
+    packages: Vec<MetadataPackage>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    name: Name,
+    version: Version,
+    source: Option<String>,
+}
+
+/// Parse the output of `cargo metadata --format-version 1`
+fn parse_metadata(input: &str) -> Result<Lockfile, Error> {
+    let output: MetadataOutput = serde_json::from_str(input).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "invalid `cargo metadata` output".to_owned(),
+            e,
+        )
+    })?;
+
+    let mut packages = vec![];
+
+    for metadata_package in output.packages {
+        // Path dependencies have no source
+        let source = match metadata_package.source {
+            Some(source) => SourceId::from_url(&source)?,
+            None => continue,
+        };
+
+        packages.push(package(
+            metadata_package.name,
+            metadata_package.version,
+            source,
+        ));
+    }
+
+    Ok(lockfile(packages))
+}
+
+/// Source of packages from crates.io
+fn crates_io() -> SourceId {
+    SourceId::from_url(CRATES_IO_INDEX).expect("crates.io source is valid")
+}
+
+/// Package with the given name, version and source
+fn package(name: Name, version: Version, source: SourceId) -> Package {
+    Package {
+        name,
+        version,
+        source: Some(source),
+        checksum: None,
+        dependencies: vec![],
+        replace: None,
+    }
+}
+
+/// Lockfile listing the given packages once each
+fn lockfile(mut packages: Vec<Package>) -> Lockfile {
+    packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    packages.dedup_by(|a, b| a.name == b.name && a.version == b.version);
+
+    Lockfile {
+        version: ResolveVersion::V2,
+        packages,
+        root: None,
+        metadata: Default::default(),
+        patch: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(lockfile: &Lockfile) -> Vec<String> {
+        lockfile
+            .packages
+            .iter()
+            .map(|package| format!("{} {}", package.name, package.version))
+            .collect()
+    }
+
+    #[test]
+    fn list() {
+        let lockfile =
+            parse_list("# dependencies\nserde 1.0.130\nlibc@0.2.100 # pinned\n\nserde v1.0.130\n")
+                .unwrap();
+        assert_eq!(names(&lockfile), ["libc 0.2.100", "serde 1.0.130"]);
+        assert_eq!(lockfile.packages[0].source, Some(crates_io()));
+
+        let err = parse_list("serde\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        assert!(parse_list("serde one\n").is_err());
+        assert!(parse_list("serde 1.0.130 crates.io extra\n").is_err());
+    }
+
+    #[test]
+    fn list_sources() {
+        let registry = "sparse+https://my-registry.example/index/";
+        let lockfile = parse_list(&format!(
+            "private 0.2.0 {}\n  serde@1.0.130 {}\n",
+            registry, CRATES_IO_INDEX
+        ))
+        .unwrap();
+        assert_eq!(names(&lockfile), ["private 0.2.0", "serde 1.0.130"]);
+        assert_eq!(
+            lockfile.packages[0].source,
+            Some(SourceId::from_url(registry).unwrap())
+        );
+        assert_eq!(lockfile.packages[1].source, Some(crates_io()));
+
+        assert!(parse_list("serde 1.0.130 not-a-source\n").is_err());
+
+        let git = "git+https://github.com/example/util#0123456789abcdef0123456789abcdef01234567";
+        let lockfile = parse_list(&format!("util 0.2.0 {} # pinned\n", git)).unwrap();
+        assert_eq!(lockfile.packages[0].source, Some(git.parse().unwrap()));
+    }
+
+    #[test]
+    fn tree() {
+        let output = "\
+app v0.1.0 (/home/user/app)
+serde v1.0.130
+serde_derive v1.0.130 (proc-macro)
+serde v1.0.130 (*)
+[build-dependencies]
+cc v1.0.70
+[dev-dependencies]
+private v0.2.0 (registry `my-registry`)
+
+member v0.1.0 (/home/user/app/member)
+libc v0.2.100
+";
+        let lockfile = parse_tree(output).unwrap();
+        assert_eq!(
+            names(&lockfile),
+            [
+                "cc 1.0.70",
+                "libc 0.2.100",
+                "serde 1.0.130",
+                "serde_derive 1.0.130",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_errors() {
+        let err = parse_tree("serde v1.0.130\nerror: no such package\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        assert!(parse_tree("").is_err());
+        assert!(parse_tree("\n\n").is_err());
+    }
+
+    #[test]
+    fn metadata() {
+        let output = r#"{
+            "packages": [
+                {
+                    "name": "app",
+                    "version": "0.1.0",
+                    "source": null
+                },
+                {
+                    "name": "serde",
+                    "version": "1.0.130",
+                    "source": "registry+https://github.com/rust-lang/crates.io-index"
+                },
+                {
+                    "name": "tool",
+                    "version": "0.3.0",
+                    "source": "git+https://github.com/example/tool#0123456789abcdef0123456789abcdef01234567"
+                }
+            ],
+            "workspace_members": []
+        }"#;
+
+        let lockfile = parse_metadata(output).unwrap();
+        assert_eq!(names(&lockfile), ["serde 1.0.130", "tool 0.3.0"]);
+        assert!(lockfile.packages[1]
+            .source
+            .as_ref()
+            .map_or(false, SourceId::is_git));
+
+        assert!(parse_metadata("{}").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! Snapshot of the advisory database embedded at build time
+//! (`embedded-db` feature).
+//!
+//! See `build.rs` for how the snapshot is generated.
+
+use flate2::read::GzDecoder;
+use rustsec::{Advisory, Database, Error, ErrorKind};
+use std::{
+    io::Read,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Compressed JSON snapshot of all advisories in the database
+static SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/advisory-db.json.gz"));
+
+/// Advisory database commit the snapshot was taken from
+pub const COMMIT: &str = env!("CARGO_AUDIT_EMBEDDED_DB_COMMIT");
+
+/// Commit date (`YYYY-MM-DD`) of the snapshot
+pub const DATE: &str = env!("CARGO_AUDIT_EMBEDDED_DB_DATE");
+
+/// Commit timestamp (seconds since the Unix epoch) of the snapshot
+const TIMESTAMP: &str = env!("CARGO_AUDIT_EMBEDDED_DB_TIMESTAMP");
+
+/// Snapshots older than this are considered stale (same as for git checkouts)
+const STALE_AFTER: Duration = Duration::from_secs(90 * 86400);
+
+/// Load the embedded advisory database snapshot
+pub fn load() -> rustsec::Result<Database> {
+    let mut json = Vec::new();
+    GzDecoder::new(SNAPSHOT).read_to_end(&mut json)?;
+
+    let advisories: Vec<Advisory> = serde_json::from_slice(&json).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "invalid embedded advisory database snapshot".to_owned(),
+            e,
+        )
+    })?;
+
+    Database::from_advisories(advisories)
+}
+
+/// Time the snapshot's commit was made
+pub fn timestamp() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(TIMESTAMP.parse().unwrap_or(0))
+}
+
+/// Is the snapshot older than the staleness threshold?
+pub fn is_stale() -> bool {
+    SystemTime::now()
+        .duration_since(timestamp())
+        .map_or(false, |age| age > STALE_AFTER)
+}
+
+/// Age of the snapshot in whole days
+pub fn age_days() -> u64 {
+    SystemTime::now()
+        .duration_since(timestamp())
+        .map_or(0, |age| age.as_secs() / 86400)
+}
@@ -32,6 +32,14 @@ pub enum ErrorKind {
     #[error("version error")]
     Version,
 
+    /// Errors uploading reports
+    #[error("upload error")]
+    Upload,
+
+    /// Errors sending notifications to webhooks
+    #[error("notification error")]
+    Notify,
+
     /// Other kinds of errors
     #[error("other error")]
     Other,
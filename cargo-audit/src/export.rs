@@ -0,0 +1,38 @@
+//! Export the advisory database to other formats (`cargo audit db export`)
+
+mod sql;
+
+use clap::ValueEnum;
+use rustsec::Database;
+use std::io;
+
+/// Formats the advisory database can be exported to
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// SQL script which creates and populates a normalized SQLite database
+    #[value(name = "sqlite", alias = "sql")]
+    Sqlite,
+}
+
+/// Export all advisories in the database in the given format
+pub fn export(db: &Database, format: ExportFormat, output: &mut dyn io::Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Sqlite => sql::write(db, output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_names() {
+        for name in ["sqlite", "sql"] {
+            assert_eq!(
+                ExportFormat::from_str(name, false),
+                Ok(ExportFormat::Sqlite)
+            );
+        }
+        assert!(ExportFormat::from_str("csv", false).is_err());
+    }
+}
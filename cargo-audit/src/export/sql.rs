@@ -0,0 +1,250 @@
+//! Export the advisory database as a SQL script for SQLite
+//!
+//! The script creates a normalized schema and populates it, e.g.:
+//!
+//! ```text
+//! $ cargo audit db export --format sqlite | sqlite3 advisories.db
+//! ```
+
+use rustsec::{Advisory, Database};
+use std::io::{self, Write};
+
+/// Schema of the exported database
+const SCHEMA: &str = r#"CREATE TABLE advisories (
+    id TEXT PRIMARY KEY NOT NULL,
+    package TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    date TEXT NOT NULL,
+    url TEXT,
+    source TEXT,
+    informational TEXT,
+    cvss TEXT,
+    cvss_score REAL,
+    severity TEXT,
+    withdrawn TEXT,
+    license TEXT NOT NULL
+);
+CREATE INDEX advisories_package ON advisories (package);
+
+CREATE TABLE versions (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    kind TEXT NOT NULL CHECK (kind IN ('patched', 'unaffected')),
+    requirement TEXT NOT NULL
+);
+CREATE INDEX versions_advisory_id ON versions (advisory_id);
+
+CREATE TABLE aliases (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    alias TEXT NOT NULL
+);
+CREATE INDEX aliases_alias ON aliases (alias);
+
+CREATE TABLE related (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    related_id TEXT NOT NULL
+);
+
+CREATE TABLE "references" (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    url TEXT NOT NULL
+);
+
+CREATE TABLE categories (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    category TEXT NOT NULL
+);
+
+CREATE TABLE keywords (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    keyword TEXT NOT NULL
+);
+
+CREATE TABLE affected_arch (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    arch TEXT NOT NULL
+);
+
+CREATE TABLE affected_os (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    os TEXT NOT NULL
+);
+
+CREATE TABLE affected_functions (
+    advisory_id TEXT NOT NULL REFERENCES advisories (id),
+    path TEXT NOT NULL,
+    requirement TEXT NOT NULL
+);
+"#;
+
+/// Write a SQL script which creates and populates the database
+pub fn write(db: &Database, output: &mut dyn Write) -> io::Result<()> {
+    output.write_all(SCHEMA.as_bytes())?;
+    writeln!(output, "\nBEGIN TRANSACTION;")?;
+
+    for advisory in db.iter() {
+        write_advisory(advisory, output)?;
+    }
+
+    writeln!(output, "COMMIT;")?;
+    output.flush()
+}
+
+/// Write the rows for a single advisory
+fn write_advisory(advisory: &Advisory, output: &mut dyn Write) -> io::Result<()> {
+    let metadata = &advisory.metadata;
+    let id = text(&metadata.id);
+
+    writeln!(
+        output,
+        "INSERT INTO advisories VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {});",
+        id,
+        text(&metadata.package),
+        text(metadata.collection.unwrap_or(rustsec::Collection::Crates)),
+        text(&metadata.title),
+        text(&metadata.description),
+        text(&metadata.date),
+        opt_text(metadata.url.as_ref()),
+        opt_text(metadata.source.as_ref()),
+        opt_text(metadata.informational.as_ref()),
+        opt_text(metadata.primary_cvss()),
+        metadata.primary_cvss().map_or_else(
+            || "NULL".to_owned(),
+            |cvss| cvss.score().value().to_string()
+        ),
+        opt_text(metadata.primary_cvss().map(|cvss| cvss.severity())),
+        opt_text(metadata.withdrawn.as_ref()),
+        text(&metadata.license),
+    )?;
+
+    for req in advisory.versions.patched() {
+        insert(output, "versions", &[&id, "'patched'", &text(req)])?;
+    }
+
+    for req in advisory.versions.unaffected() {
+        insert(output, "versions", &[&id, "'unaffected'", &text(req)])?;
+    }
+
+    for alias in &metadata.aliases {
+        insert(output, "aliases", &[&id, &text(alias)])?;
+    }
+
+    for related in &metadata.related {
+        insert(output, "related", &[&id, &text(related)])?;
+    }
+
+    for url in &metadata.references {
+        insert(output, "\"references\"", &[&id, &text(url)])?;
+    }
+
+    for category in &metadata.categories {
+        insert(output, "categories", &[&id, &text(category)])?;
+    }
+
+    for keyword in &metadata.keywords {
+        insert(output, "keywords", &[&id, &text(keyword.as_str())])?;
+    }
+
+    if let Some(affected) = &advisory.affected {
+        for arch in &affected.arch {
+            insert(output, "affected_arch", &[&id, &text(arch)])?;
+        }
+
+        for os in &affected.os {
+            insert(output, "affected_os", &[&id, &text(os)])?;
+        }
+
+        for (path, reqs) in &affected.functions {
+            for req in reqs {
+                insert(
+                    output,
+                    "affected_functions",
+                    &[&id, &text(path), &text(req)],
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an `INSERT` statement with the given (already quoted) values
+fn insert(output: &mut dyn Write, table: &str, values: &[&str]) -> io::Result<()> {
+    writeln!(
+        output,
+        "INSERT INTO {} VALUES ({});",
+        table,
+        values.join(", ")
+    )
+}
+
+/// Quote a value as a SQL string literal
+fn text(value: impl ToString) -> String {
+    format!("'{}'", value.to_string().replace('\'', "''"))
+}
+
+/// Quote an optional value as a SQL string literal, or `NULL`
+fn opt_text(value: Option<impl ToString>) -> String {
+    value.map_or_else(|| "NULL".to_owned(), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVISORY: &str = r#"```toml
+id = "RUSTSEC-2001-2101"
+package = "base"
+date = "2001-02-03"
+aliases = ["CVE-2001-2101"]
+keywords = ["o'clock"]
+
+[versions]
+patched = [">= 1.2.3"]
+
+[affected]
+functions = { "base::belongs::All" = ["< 1.2.3"] }
+```
+
+# All your base are belong to us
+
+You have no chance to survive. Make your time.
+"#;
+
+    fn export() -> String {
+        let advisory = ADVISORY.parse().unwrap();
+        let db = Database::from_advisories(vec![advisory]).unwrap();
+        let mut output = Vec::new();
+        write(&db, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(text("it's"), "'it''s'");
+        assert_eq!(opt_text(Some("x")), "'x'");
+        assert_eq!(opt_text(None::<&str>), "NULL");
+    }
+
+    #[test]
+    fn script() {
+        let script = export();
+        assert!(script.starts_with("CREATE TABLE advisories ("));
+        assert!(script.ends_with("COMMIT;\n"));
+        assert!(script.contains(
+            "INSERT INTO advisories VALUES ('RUSTSEC-2001-2101', 'base', 'crates', \
+             'All your base are belong to us', 'You have no chance to survive. Make your time.', \
+             '2001-02-03', NULL, NULL, NULL, NULL, NULL, NULL, NULL, 'CC0-1.0');"
+        ));
+        assert!(script
+            .contains("INSERT INTO versions VALUES ('RUSTSEC-2001-2101', 'patched', '>=1.2.3');"));
+        assert!(
+            script.contains("INSERT INTO aliases VALUES ('RUSTSEC-2001-2101', 'CVE-2001-2101');")
+        );
+        assert!(script.contains("INSERT INTO keywords VALUES ('RUSTSEC-2001-2101', 'o''clock');"));
+        assert!(script.contains(
+            "INSERT INTO affected_functions VALUES ('RUSTSEC-2001-2101', 'base::belongs::All', '<1.2.3');"
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+//! GitLab [dependency scanning report] output (`--format gitlab`), so that
+//! findings show up in GitLab's security dashboard and merge request widgets
+//!
+//! [dependency scanning report]: https://docs.gitlab.com/ee/development/integrations/secure.html#report
+
+use rustsec::{
+    advisory::{self, Severity},
+    package::Package,
+    Lockfile, Vulnerability, Warning,
+};
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Version of the GitLab security report schema the reports conform to
+const SCHEMA_VERSION: &str = "15.0.7";
+
+/// Information about `cargo audit` itself, as both the analyzer and scanner
+const TOOL: Tool = Tool {
+    id: "cargo-audit",
+    name: "cargo-audit",
+    version: env!("CARGO_PKG_VERSION"),
+    vendor: Vendor { name: "RustSec" },
+};
+
+/// GitLab dependency scanning report
+#[derive(Serialize)]
+pub struct GitlabReport {
+    version: &'static str,
+    scan: Scan,
+    vulnerabilities: Vec<Finding>,
+    dependency_files: Vec<DependencyFile>,
+}
+
+impl GitlabReport {
+    /// Create a report for the given findings in the dependency file at
+    /// `path` (e.g. `Cargo.lock`)
+    pub fn new(
+        vulnerabilities: &[&Vulnerability],
+        warnings: &[&Warning],
+        lockfile: &Lockfile,
+        path: &str,
+    ) -> Self {
+        let now = crate::timestamp::format(SystemTime::now());
+
+        let findings = vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                Finding::new(
+                    &vulnerability.advisory,
+                    vulnerability_severity(vulnerability),
+                    &vulnerability.versions,
+                    &vulnerability.package,
+                    path,
+                )
+            })
+            .chain(warnings.iter().filter_map(|warning| {
+                // Warnings without an advisory (e.g. yanked crates) have
+                // nothing to identify them by
+                let advisory = warning.advisory.as_ref()?;
+                let versions = warning.versions.clone().unwrap_or_default();
+                Some(Finding::new(
+                    advisory,
+                    "Info",
+                    &versions,
+                    &warning.package,
+                    path,
+                ))
+            }))
+            .collect();
+
+        Self {
+            version: SCHEMA_VERSION,
+            scan: Scan {
+                analyzer: TOOL,
+                scanner: TOOL,
+                kind: "dependency_scanning",
+                start_time: now.clone(),
+                end_time: now,
+                status: "success",
+            },
+            vulnerabilities: findings,
+            dependency_files: vec![DependencyFile {
+                path: path.to_owned(),
+                package_manager: "cargo",
+                dependencies: lockfile.packages.iter().map(Dependency::new).collect(),
+            }],
+        }
+    }
+}
+
+/// Information about the scan
+#[derive(Serialize)]
+struct Scan {
+    analyzer: Tool,
+    scanner: Tool,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    start_time: String,
+    end_time: String,
+    status: &'static str,
+}
+
+/// Tool which performed the scan
+#[derive(Serialize)]
+struct Tool {
+    id: &'static str,
+    name: &'static str,
+    version: &'static str,
+    vendor: Vendor,
+}
+
+/// Vendor of a tool
+#[derive(Serialize)]
+struct Vendor {
+    name: &'static str,
+}
+
+/// Vulnerability (or informational advisory) affecting a dependency
+#[derive(Serialize)]
+struct Finding {
+    id: String,
+    name: String,
+    description: String,
+    severity: &'static str,
+    solution: String,
+    identifiers: Vec<Identifier>,
+    links: Vec<Link>,
+    location: Location,
+}
+
+impl Finding {
+    /// Describe an advisory affecting the given package
+    fn new(
+        advisory: &advisory::Metadata,
+        severity: &'static str,
+        versions: &advisory::Versions,
+        package: &Package,
+        path: &str,
+    ) -> Self {
+        let identifiers = std::iter::once(&advisory.id)
+            .chain(&advisory.aliases)
+            .map(Identifier::new)
+            .collect();
+
+        let links = advisory
+            .url
+            .iter()
+            .chain(&advisory.references)
+            .map(|url| Link {
+                url: url.to_string(),
+            })
+            .collect();
+
+        let solution = if versions.patched().is_empty() {
+            "No fixed upgrade is available!".to_owned()
+        } else {
+            format!(
+                "Upgrade to {}",
+                versions
+                    .patched()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            )
+        };
+
+        Self {
+            id: format!("{}:{}:{}", advisory.id, package.name, package.version),
+            name: advisory.title.clone(),
+            description: advisory.description.trim().to_owned(),
+            severity,
+            solution,
+            identifiers,
+            links,
+            location: Location {
+                file: path.to_owned(),
+                dependency: Dependency::new(package),
+            },
+        }
+    }
+}
+
+/// Identifier of a finding, e.g. a RustSec or CVE ID
+#[derive(Serialize)]
+struct Identifier {
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+impl Identifier {
+    /// Create an identifier for an advisory ID, using its prefix (e.g. `cve`)
+    /// as the type
+    fn new(id: &advisory::Id) -> Self {
+        let kind = id
+            .as_str()
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        Self {
+            kind,
+            name: id.to_string(),
+            value: id.to_string(),
+            url: id.url(),
+        }
+    }
+}
+
+/// Link to more information about a finding
+#[derive(Serialize)]
+struct Link {
+    url: String,
+}
+
+/// Location of a finding
+#[derive(Serialize)]
+struct Location {
+    file: String,
+    dependency: Dependency,
+}
+
+/// Dependency, i.e. a package in the lockfile
+#[derive(Serialize)]
+struct Dependency {
+    package: PackageName,
+    version: String,
+}
+
+impl Dependency {
+    /// Describe a package from the lockfile
+    fn new(package: &Package) -> Self {
+        Self {
+            package: PackageName {
+                name: package.name.to_string(),
+            },
+            version: package.version.to_string(),
+        }
+    }
+}
+
+/// Name of a package
+#[derive(Serialize)]
+struct PackageName {
+    name: String,
+}
+
+/// Dependency file which was scanned, with all of its dependencies
+#[derive(Serialize)]
+struct DependencyFile {
+    path: String,
+    package_manager: &'static str,
+    dependencies: Vec<Dependency>,
+}
+
+/// Map the effective severity of a vulnerability to a GitLab severity
+fn vulnerability_severity(vulnerability: &Vulnerability) -> &'static str {
+    match vulnerability.severity() {
+        None => "Unknown",
+        Some(Severity::None) => "Info",
+        Some(Severity::Low) => "Low",
+        Some(Severity::Medium) => "Medium",
+        Some(Severity::High) => "High",
+        Some(Severity::Critical) => "Critical",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::{database::AdvisoryBuilder, WarningKind};
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "h2"
+version = "0.3.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "yanked"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn findings() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let h2 = &lockfile.packages[0];
+
+        let advisory = |id: &str| {
+            AdvisoryBuilder::new(id, "h2")
+                .metadata(r#"aliases = ["CVE-2024-0001"]"#)
+                .metadata(r#"url = "https://example.com/advisory""#)
+                .title(&format!("Title of {}", id))
+                .description(&format!("Description of {}", id))
+        };
+
+        let vulnerable = advisory("RUSTSEC-2024-0001")
+            .patched(">= 0.3.26")
+            .build()
+            .unwrap();
+        let vulnerability = Vulnerability::new(&vulnerable, h2);
+        let notice = advisory("RUSTSEC-2024-0002")
+            .metadata(r#"informational = "unsound""#)
+            .build()
+            .unwrap();
+        let unsound = Warning::new(
+            WarningKind::Unsound,
+            h2,
+            Some(notice.metadata.clone()),
+            None,
+            Some(notice.versions.clone()),
+        );
+        let yanked = Warning::new(WarningKind::Yanked, &lockfile.packages[1], None, None, None);
+
+        let report = GitlabReport::new(
+            &[&vulnerability],
+            &[&unsound, &yanked],
+            &lockfile,
+            "Cargo.lock",
+        );
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["version"], SCHEMA_VERSION);
+        assert_eq!(json["scan"]["type"], "dependency_scanning");
+        assert_eq!(json["scan"]["scanner"]["id"], "cargo-audit");
+
+        // The yanked crate has no advisory to report
+        let findings = json["vulnerabilities"].as_array().unwrap();
+        assert_eq!(findings.len(), 2);
+
+        let finding = &findings[0];
+        assert_eq!(finding["id"], "RUSTSEC-2024-0001:h2:0.3.0");
+        assert_eq!(finding["name"], "Title of RUSTSEC-2024-0001");
+        assert_eq!(finding["description"], "Description of RUSTSEC-2024-0001");
+        assert_eq!(finding["severity"], "Unknown");
+        assert_eq!(finding["solution"], "Upgrade to >=0.3.26");
+        assert_eq!(finding["identifiers"][0]["type"], "rustsec");
+        assert_eq!(finding["identifiers"][0]["value"], "RUSTSEC-2024-0001");
+        assert_eq!(finding["identifiers"][1]["type"], "cve");
+        assert_eq!(finding["links"][0]["url"], "https://example.com/advisory");
+        assert_eq!(finding["location"]["file"], "Cargo.lock");
+        assert_eq!(finding["location"]["dependency"]["package"]["name"], "h2");
+        assert_eq!(finding["location"]["dependency"]["version"], "0.3.0");
+
+        let finding = &findings[1];
+        assert_eq!(finding["severity"], "Info");
+        assert_eq!(finding["solution"], "No fixed upgrade is available!");
+
+        let files = json["dependency_files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["path"], "Cargo.lock");
+        assert_eq!(files[0]["package_manager"], "cargo");
+        assert_eq!(files[0]["dependencies"].as_array().unwrap().len(), 2);
+    }
+}
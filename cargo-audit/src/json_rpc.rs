@@ -0,0 +1,299 @@
+//! Language-server-style JSON-RPC mode (`cargo audit --json-rpc`)
+//!
+//! Requests and responses are JSON-RPC 2.0 messages framed with
+//! `Content-Length` headers (as in the Language Server Protocol) and exchanged
+//! over stdin/stdout. The advisory database and crates.io index are loaded
+//! once and kept warm across requests.
+//!
+//! Supported methods:
+//!
+//! - `audit`: `{"lockfile": "<Cargo.lock contents>"}`, returns a report, or
+//!   an error if the audit fails (e.g. because of an anomaly in strict mode)
+//! - `shutdown`: returns `null` and stops serving after the response is sent
+//! - `exit`: notification which stops serving immediately
+
+use crate::auditor::Auditor;
+use rustsec::Lockfile;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, Read, Write};
+
+/// Invalid JSON was received
+const PARSE_ERROR: i64 = -32700;
+
+/// The JSON sent is not a valid request object
+const INVALID_REQUEST: i64 = -32600;
+
+/// The method does not exist
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Invalid method parameters
+const INVALID_PARAMS: i64 = -32602;
+
+/// Internal JSON-RPC error
+const INTERNAL_ERROR: i64 = -32603;
+
+/// The audit failed, e.g. because of an anomaly in strict mode (a
+/// server-defined error code)
+const AUDIT_FAILED: i64 = -32000;
+
+/// Largest message accepted, which leaves plenty of room for the lockfiles of
+/// large workspaces
+const MAX_MESSAGE_SIZE: usize = 8 * 1024 * 1024;
+
+/// Longest header line accepted
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// JSON-RPC request
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Request ID (absent for notifications)
+    #[serde(default)]
+    id: Option<Value>,
+
+    /// Name of the method to invoke
+    method: String,
+
+    /// Method parameters
+    #[serde(default)]
+    params: Value,
+}
+
+/// Parameters of the `audit` method
+#[derive(Debug, Deserialize)]
+struct AuditParams {
+    /// Contents of a `Cargo.lock` file
+    lockfile: String,
+}
+
+/// JSON-RPC response
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Serialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl Response {
+    fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn error(id: Value, code: i64, message: impl ToString) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(ResponseError {
+                code,
+                message: message.to_string(),
+            }),
+        }
+    }
+}
+
+/// Serve JSON-RPC requests read from `input`, writing responses to `output`,
+/// until the client disconnects or sends `shutdown`/`exit`.
+pub fn serve<R: BufRead, W: Write>(
+    auditor: &mut Auditor,
+    mut input: R,
+    mut output: W,
+) -> io::Result<()> {
+    while let Some(body) = read_message(&mut input)? {
+        let request = match serde_json::from_slice::<Value>(&body) {
+            Ok(value) => value,
+            Err(e) => {
+                write_message(&mut output, &Response::error(Value::Null, PARSE_ERROR, e))?;
+                continue;
+            }
+        };
+
+        let request: Request = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(e) => {
+                write_message(
+                    &mut output,
+                    &Response::error(Value::Null, INVALID_REQUEST, e),
+                )?;
+                continue;
+            }
+        };
+
+        let id = request.id.clone().unwrap_or(Value::Null);
+
+        let response = match request.method.as_str() {
+            "audit" => audit(auditor, id, request.params),
+            "shutdown" => {
+                write_message(&mut output, &Response::result(id, Value::Null))?;
+                return Ok(());
+            }
+            "exit" => return Ok(()),
+            other => Response::error(id, METHOD_NOT_FOUND, format!("unknown method: {}", other)),
+        };
+
+        // Notifications never receive a response
+        if request.id.is_some() {
+            write_message(&mut output, &response)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the `audit` method
+fn audit(auditor: &mut Auditor, id: Value, params: Value) -> Response {
+    let params: AuditParams = match serde_json::from_value(params) {
+        Ok(params) => params,
+        Err(e) => return Response::error(id, INVALID_PARAMS, e),
+    };
+
+    let lockfile: Lockfile = match params.lockfile.parse() {
+        Ok(lockfile) => lockfile,
+        Err(e) => return Response::error(id, INVALID_PARAMS, e),
+    };
+
+    let report = match auditor.report(&lockfile) {
+        Ok(report) => report,
+        Err(failure) => return Response::error(id, AUDIT_FAILED, failure),
+    };
+
+    match serde_json::to_value(&report) {
+        Ok(value) => Response::result(id, value),
+        Err(e) => Response::error(id, INTERNAL_ERROR, e),
+    }
+}
+
+/// Read a single `Content-Length` framed message, returning `None` on EOF
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        let limit = MAX_HEADER_SIZE as u64 + 1;
+
+        match input.by_ref().take(limit).read_line(&mut line)? {
+            0 => return Ok(None),
+            len if len > MAX_HEADER_SIZE => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("header exceeds the limit of {} bytes", MAX_HEADER_SIZE),
+                ))
+            }
+            _ => (),
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(value.trim().parse::<usize>().map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("bad Content-Length: {}", e),
+                    )
+                })?);
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+
+    if content_length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "message of {} bytes exceeds the limit of {} bytes",
+                content_length, MAX_MESSAGE_SIZE
+            ),
+        ));
+    }
+
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// Write a single `Content-Length` framed message
+fn write_message<W: Write>(output: &mut W, response: &Response) -> io::Result<()> {
+    let body = serde_json::to_vec(response)?;
+    write!(output, "Content-Length: {}\r\n\r\n", body.len())?;
+    output.write_all(&body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_framed_messages() {
+        let mut input = &b"Content-Length: 2\r\nContent-Type: application/json\r\n\r\n{}content-length: 4\r\n\r\nnull"[..];
+
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), b"{}");
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), b"null");
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn reject_missing_or_bad_content_length() {
+        let mut input = &b"Content-Type: application/json\r\n\r\n{}"[..];
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut input = &b"Content-Length: -1\r\n\r\n{}"[..];
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reject_oversized_message() {
+        let header = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_SIZE + 1);
+        let err = read_message(&mut header.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reject_oversized_header() {
+        let header = format!("X-Padding: {}\r\n", "a".repeat(MAX_HEADER_SIZE));
+        let err = read_message(&mut header.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Long headers within the limit are fine
+        let header = format!(
+            "X-Padding: {}\r\nContent-Length: 2\r\n\r\n{{}}",
+            "a".repeat(MAX_HEADER_SIZE - 20)
+        );
+        assert_eq!(
+            read_message(&mut header.as_bytes()).unwrap().unwrap(),
+            b"{}"
+        );
+    }
+
+    #[test]
+    fn reject_truncated_message() {
+        let mut input = &b"Content-Length: 10\r\n\r\n{}"[..];
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -21,19 +21,63 @@
 #![warn(missing_docs, rust_2018_idioms, trivial_casts, unused_qualifications)]
 
 pub mod application;
+#[cfg(feature = "binary-scanning")]
+mod artifact_group;
 pub mod auditor;
+#[cfg(feature = "archive-scanning")]
+mod binary_archive;
+#[cfg(feature = "binary-scanning")]
+mod binary_debug_info;
+#[cfg(feature = "binary-scanning")]
+mod binary_dep_list;
 #[cfg(feature = "binary-scanning")]
 mod binary_deps;
+#[cfg(feature = "binary-scanning")]
+mod binary_fat;
 mod binary_format;
 #[cfg(feature = "binary-scanning")]
+mod binary_static_lib;
+#[cfg(feature = "binary-scanning")]
+mod binary_symbols;
+#[cfg(feature = "binary-scanning")]
+mod binary_toolchain;
+#[cfg(feature = "binary-scanning")]
+mod binary_tree;
+#[cfg(feature = "binary-scanning")]
 mod binary_type_filter;
 mod cli_config;
 pub mod commands;
+#[cfg(feature = "compress")]
+mod compression;
 pub mod config;
+pub mod config_layers;
+#[cfg(feature = "archive-scanning")]
+mod container_image;
+mod dependency_list;
+#[cfg(feature = "embedded-db")]
+mod embedded_db;
 pub mod error;
+mod export;
+mod gitlab;
+mod json_rpc;
 pub mod lockfile;
+#[cfg(feature = "fix")]
+mod manifest;
+mod metadata;
+mod notify;
+mod notify_state;
 mod prelude;
 pub mod presenter;
+mod progress;
+mod remote;
+mod report_cache;
+mod shared_policy;
+mod strict;
+mod text_diff;
+mod timestamp;
+#[cfg(feature = "upload")]
+mod upload;
+mod watch;
 
 /// Current version of the `cargo-audit` crate
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
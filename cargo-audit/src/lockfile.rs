@@ -2,6 +2,7 @@
 
 use rustsec::{Error, ErrorKind};
 use std::{
+    env,
     path::{Path, PathBuf},
     process::Command,
 };
@@ -10,26 +11,117 @@ use std::{
 const CARGO_LOCK_FILE: &str = "Cargo.lock";
 
 /// Tries to locate the lockfile at the specified file path. If it's missing, tries to generate it from `Cargo.toml`.
-/// Defaults to `Cargo.lock` in the current directory if passed `None` as the path.
-pub fn locate_or_generate(maybe_lockfile_path: Option<&Path>) -> rustsec::Result<PathBuf> {
-    match maybe_lockfile_path {
-        Some(p) => Ok(p.into()),
-        None => {
-            let path = Path::new(CARGO_LOCK_FILE);
-            if !path.exists() && Path::new("Cargo.toml").exists() {
-                generate()?;
-            }
-            Ok(path.into())
-        }
+///
+/// Without a path, the lockfile of the workspace containing the given
+/// `Cargo.toml` (or the current directory) is used, as found by
+/// `cargo locate-project`. Defaults to `Cargo.lock` in the current directory
+/// if no workspace can be found, or if the current directory has a
+/// `Cargo.lock` but no `Cargo.toml` (e.g. a lockfile copied out of a build).
+pub fn locate_or_generate(
+    maybe_lockfile_path: Option<&Path>,
+    manifest_path: Option<&Path>,
+) -> rustsec::Result<PathBuf> {
+    if let Some(p) = maybe_lockfile_path {
+        return Ok(p.into());
     }
+
+    if manifest_path.is_none() && has_standalone_lockfile(Path::new("")) {
+        return Ok(PathBuf::from(CARGO_LOCK_FILE));
+    }
+
+    let workspace_manifest = match locate_workspace(manifest_path) {
+        Ok(workspace_manifest) => Some(workspace_manifest),
+        // An explicitly given manifest has to be usable
+        Err(e) if manifest_path.is_some() => return Err(e),
+        Err(_) => None,
+    };
+
+    let path = match &workspace_manifest {
+        Some(workspace_manifest) => relative_to_current_dir(
+            &workspace_manifest
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join(CARGO_LOCK_FILE),
+        ),
+        None => PathBuf::from(CARGO_LOCK_FILE),
+    };
+
+    let manifest_exists = match &workspace_manifest {
+        Some(workspace_manifest) => workspace_manifest.exists(),
+        None => Path::new("Cargo.toml").exists(),
+    };
+
+    if !path.exists() && manifest_exists {
+        generate(workspace_manifest.as_deref())?;
+    }
+
+    Ok(path)
+}
+
+/// Does the given directory have a `Cargo.lock` but no `Cargo.toml`?
+fn has_standalone_lockfile(dir: &Path) -> bool {
+    dir.join(CARGO_LOCK_FILE).is_file() && !dir.join("Cargo.toml").exists()
+}
+
+/// Command running the `cargo` which invoked `cargo audit` (or the one in
+/// `PATH`)
+fn cargo_command() -> Command {
+    Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+}
+
+/// Find the `Cargo.toml` of the root of the workspace containing the given
+/// `Cargo.toml` (or the current directory) with `cargo locate-project`
+fn locate_workspace(manifest_path: Option<&Path>) -> rustsec::Result<PathBuf> {
+    let mut command = cargo_command();
+    command.args(["locate-project", "--workspace", "--message-format", "plain"]);
+
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    let output = command.output().map_err(|e| {
+        Error::with_source(
+            ErrorKind::Io,
+            "couldn't run `cargo locate-project` to find the workspace".to_string(),
+            e,
+        )
+    })?;
+
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorKind::Io,
+            &format!(
+                "couldn't find the workspace: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+    ))
+}
+
+/// Make the given path relative to the current directory if it's inside it,
+/// so messages mention `Cargo.lock` rather than its absolute path
+fn relative_to_current_dir(path: &Path) -> PathBuf {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
 }
 
-/// Run `cargo generate-lockfile`
-pub fn generate() -> rustsec::Result<()> {
-    let status = Command::new("cargo")
-        .arg("update")
-        .arg("--workspace")
-        .status();
+/// Run `cargo generate-lockfile` for the workspace with the given `Cargo.toml`
+/// (or the one in the current directory)
+pub fn generate(manifest_path: Option<&Path>) -> rustsec::Result<()> {
+    let mut command = cargo_command();
+    command.arg("update").arg("--workspace");
+
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    let status = command.status();
 
     if let Err(e) = status {
         return Err(Error::with_source(
@@ -53,3 +145,67 @@ pub fn generate() -> rustsec::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const LOCKFILE: &str = "version = 3\n";
+
+    /// Write a workspace with a member to the given directory
+    fn workspace(dir: &Path) {
+        fs::create_dir_all(dir.join("member").join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("member").join("Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("member").join("src").join("lib.rs"), "").unwrap();
+        fs::write(dir.join(CARGO_LOCK_FILE), LOCKFILE).unwrap();
+    }
+
+    #[test]
+    fn explicit_lockfile() {
+        let path = Path::new("elsewhere/Cargo.lock");
+        assert_eq!(locate_or_generate(Some(path), None).unwrap(), path);
+    }
+
+    #[test]
+    fn lockfile_of_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        workspace(&root);
+
+        let manifest = root.join("member").join("Cargo.toml");
+        let lockfile = locate_or_generate(None, Some(&manifest)).unwrap();
+        assert_eq!(
+            fs::canonicalize(lockfile).unwrap(),
+            root.join(CARGO_LOCK_FILE)
+        );
+    }
+
+    #[test]
+    fn invalid_manifest_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        assert!(locate_or_generate(None, Some(&manifest)).is_err());
+    }
+
+    #[test]
+    fn standalone_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!has_standalone_lockfile(dir.path()));
+
+        fs::write(dir.path().join(CARGO_LOCK_FILE), LOCKFILE).unwrap();
+        assert!(has_standalone_lockfile(dir.path()));
+
+        workspace(dir.path());
+        assert!(!has_standalone_lockfile(dir.path()));
+    }
+}
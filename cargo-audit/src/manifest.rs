@@ -0,0 +1,288 @@
+//! Editing dependency requirements in the `Cargo.toml` files of a workspace,
+//! for fixes which need semver-incompatible upgrades
+
+use crate::error::{Error, ErrorKind};
+use rustsec::{package::Name, Version, VersionReq};
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{Document, Item, TableLike, Value};
+
+/// Tables declaring dependencies, at the top level of a manifest or in a
+/// `[target.'cfg(..)']` table
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Upgrade of a vulnerable package which the requirements on it must allow
+pub struct Upgrade {
+    /// Name of the package
+    pub name: Name,
+
+    /// Vulnerable version currently in use
+    pub current: Version,
+
+    /// Patched version to upgrade to
+    pub target: Version,
+}
+
+/// Change to a dependency requirement in a manifest
+#[derive(Clone, Debug, Serialize)]
+pub struct RequirementEdit {
+    /// Manifest declaring the requirement
+    pub manifest: PathBuf,
+
+    /// Name of the dependency
+    pub name: Name,
+
+    /// Requirement before the change
+    pub from: String,
+
+    /// Requirement after the change
+    pub to: String,
+}
+
+/// Changes to the contents of a manifest
+pub struct ManifestEdit {
+    /// Path to the manifest
+    pub path: PathBuf,
+
+    /// Contents of the manifest before the changes
+    pub old: String,
+
+    /// Contents of the manifest after the changes
+    pub new: String,
+
+    /// Requirements which were changed
+    pub requirements: Vec<RequirementEdit>,
+}
+
+impl ManifestEdit {
+    /// Write the changed manifest
+    pub fn apply(&self) -> Result<(), Error> {
+        fs::write(&self.path, &self.new).map_err(|e| ErrorKind::Io.context(e).into())
+    }
+
+    /// Restore the manifest's contents from before the changes
+    pub fn revert(&self) -> Result<(), Error> {
+        fs::write(&self.path, &self.old).map_err(|e| ErrorKind::Io.context(e).into())
+    }
+}
+
+/// Find the requirements in the given manifests which allow the current
+/// version of an upgraded package but not its target version, and plan to
+/// change them to require the target version instead.
+///
+/// Dependencies inherited with `workspace = true` are skipped, since their
+/// requirements are declared in `[workspace.dependencies]` of the workspace
+/// root's manifest.
+pub fn plan(manifests: &[PathBuf], upgrades: &[Upgrade]) -> Result<Vec<ManifestEdit>, Error> {
+    let mut edits = vec![];
+
+    for path in manifests {
+        let old = fs::read_to_string(path).map_err(|e| ErrorKind::Io.context(e))?;
+        let mut document: Document = old.parse().map_err(|e| ErrorKind::Parse.context(e))?;
+        let mut requirements = vec![];
+
+        let mut edit_table =
+            |table: &mut dyn TableLike| edit_dependencies(path, table, upgrades, &mut requirements);
+
+        for &key in DEPENDENCY_TABLES {
+            if let Some(table) = document.get_mut(key).and_then(Item::as_table_like_mut) {
+                edit_table(table);
+            }
+        }
+
+        if let Some(targets) = document.get_mut("target").and_then(Item::as_table_like_mut) {
+            for (_, target) in targets.iter_mut() {
+                if let Some(target) = target.as_table_like_mut() {
+                    for &key in DEPENDENCY_TABLES {
+                        if let Some(table) = target.get_mut(key).and_then(Item::as_table_like_mut) {
+                            edit_table(table);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(table) = document
+            .get_mut("workspace")
+            .and_then(Item::as_table_like_mut)
+            .and_then(|workspace| workspace.get_mut("dependencies"))
+            .and_then(Item::as_table_like_mut)
+        {
+            edit_table(table);
+        }
+
+        if !requirements.is_empty() {
+            edits.push(ManifestEdit {
+                path: path.clone(),
+                old,
+                new: document.to_string(),
+                requirements,
+            });
+        }
+    }
+
+    Ok(edits)
+}
+
+/// Change the requirements on upgraded packages in a table of dependencies
+fn edit_dependencies(
+    manifest: &Path,
+    table: &mut dyn TableLike,
+    upgrades: &[Upgrade],
+    requirements: &mut Vec<RequirementEdit>,
+) {
+    for (key, item) in table.iter_mut() {
+        // Renamed dependencies name the package they refer to
+        let name = item
+            .as_table_like()
+            .and_then(|dependency| dependency.get("package"))
+            .and_then(Item::as_str)
+            .unwrap_or_else(|| key.get())
+            .to_owned();
+
+        let requirement = if item.is_str() {
+            item.as_value_mut()
+        } else {
+            match item.as_table_like_mut() {
+                Some(dependency) if dependency.get("workspace").is_none() => {
+                    dependency.get_mut("version").and_then(Item::as_value_mut)
+                }
+                _ => None,
+            }
+        };
+
+        let value = match requirement {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let from = match value.as_str() {
+            Some(from) => from.to_owned(),
+            None => continue,
+        };
+
+        let req: VersionReq = match from.parse() {
+            Ok(req) => req,
+            Err(_) => continue,
+        };
+
+        let upgrade = match upgrades.iter().find(|upgrade| {
+            upgrade.name.as_str() == name
+                && req.matches(&upgrade.current)
+                && !req.matches(&upgrade.target)
+        }) {
+            Some(upgrade) => upgrade,
+            None => continue,
+        };
+
+        let to = upgrade.target.to_string();
+
+        // Keep the formatting around the value, e.g. trailing comments
+        let decor = value.decor().clone();
+        *value = Value::from(to.as_str());
+        *value.decor_mut() = decor;
+
+        requirements.push(RequirementEdit {
+            manifest: manifest.to_owned(),
+            name: upgrade.name.clone(),
+            from,
+            to,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+h2 = "0.3" # pinned for now
+http = { version = "0.2.1", features = ["std"] }
+renamed = { package = "h2", version = "0.3.1" }
+inherited = { workspace = true }
+recent = { package = "h2", version = ">=0.3, <0.5" }
+unrelated = "1"
+
+[target.'cfg(unix)'.build-dependencies]
+h2 = "~0.3.0"
+
+[workspace.dependencies]
+http = "0.2"
+"#;
+
+    fn upgrades() -> Vec<Upgrade> {
+        let upgrade = |name: &str, current, target| Upgrade {
+            name: name.parse().unwrap(),
+            current,
+            target,
+        };
+
+        vec![
+            upgrade("h2", Version::new(0, 3, 1), Version::new(0, 4, 4)),
+            upgrade("http", Version::new(0, 2, 1), Version::new(1, 0, 0)),
+        ]
+    }
+
+    #[test]
+    fn plan_requirement_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, MANIFEST).unwrap();
+
+        let edits = plan(&[path.clone()], &upgrades()).unwrap();
+        assert_eq!(edits.len(), 1);
+
+        let edit = &edits[0];
+        assert_eq!(edit.path, path);
+        assert_eq!(edit.old, MANIFEST);
+
+        let changes: Vec<_> = edit
+            .requirements
+            .iter()
+            .map(|req| (req.name.as_str(), req.from.as_str(), req.to.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("h2", "0.3", "0.4.4"),
+                ("http", "0.2.1", "1.0.0"),
+                ("h2", "0.3.1", "0.4.4"),
+                ("h2", "~0.3.0", "0.4.4"),
+                ("http", "0.2", "1.0.0"),
+            ]
+        );
+
+        assert!(edit.new.contains(r#"h2 = "0.4.4" # pinned for now"#));
+        assert!(edit
+            .new
+            .contains(r#"http = { version = "1.0.0", features = ["std"] }"#));
+        assert!(edit.new.contains("inherited = { workspace = true }"));
+        assert!(edit
+            .new
+            .contains(r#"recent = { package = "h2", version = ">=0.3, <0.5" }"#));
+
+        // Nothing is written until the edit is applied
+        assert_eq!(fs::read_to_string(&path).unwrap(), MANIFEST);
+        edit.apply().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), edit.new);
+        edit.revert().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), MANIFEST);
+    }
+
+    #[test]
+    fn plan_without_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, "[dependencies]\nh2 = \"0.4\"\n").unwrap();
+
+        assert!(plan(&[path], &upgrades()).unwrap().is_empty());
+        assert!(plan(&[dir.path().join("missing.toml")], &upgrades()).is_err());
+    }
+}
@@ -0,0 +1,531 @@
+//! Integration with `cargo metadata`, for information about how a project is
+//! built which isn't recorded in `Cargo.lock`, such as the enabled features
+//! and the kinds of dependencies
+
+use crate::error::{Error, ErrorKind};
+use rustsec::{
+    package::{Name, Package},
+    Lockfile, SourceId, Version, VersionReq,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Name, version and source of a package, which tell apart e.g. a crate on
+/// crates.io and a git fork with the same version
+type PackageKey = (Name, Version, Option<SourceId>);
+
+/// Information about the packages of a project as resolved by Cargo
+#[derive(Debug, Default)]
+pub struct Metadata {
+    /// Enabled features of each package
+    features: BTreeMap<PackageKey, BTreeSet<String>>,
+
+    /// Packages which are reachable from the workspace members through
+    /// normal or build dependencies, i.e. which are used outside of tests
+    production: BTreeSet<PackageKey>,
+
+    /// Root directory of the workspace
+    workspace_root: PathBuf,
+
+    /// Paths to the manifests of the workspace members
+    member_manifests: Vec<PathBuf>,
+
+    /// Version requirements of the packages depending on each package
+    requirements: BTreeMap<Name, Vec<VersionReq>>,
+}
+
+/// Subset of the output of `cargo metadata --format-version 1`
+#[derive(Deserialize)]
+struct Output {
+    packages: Vec<OutputPackage>,
+    workspace_members: Vec<String>,
+    workspace_root: PathBuf,
+    resolve: Option<OutputResolve>,
+}
+
+#[derive(Deserialize)]
+struct OutputPackage {
+    id: String,
+    name: Name,
+    version: Version,
+    /// Source in the same form as in `Cargo.lock`, or `null` for path
+    /// dependencies
+    #[serde(default)]
+    source: Option<String>,
+    manifest_path: PathBuf,
+    #[serde(default)]
+    dependencies: Vec<OutputDependency>,
+}
+
+#[derive(Deserialize)]
+struct OutputDependency {
+    /// Name of the package, even if the dependency is renamed
+    name: Name,
+    req: String,
+}
+
+#[derive(Deserialize)]
+struct OutputResolve {
+    nodes: Vec<OutputNode>,
+}
+
+#[derive(Deserialize)]
+struct OutputNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<OutputDep>,
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OutputDep {
+    pkg: String,
+    #[serde(default)]
+    dep_kinds: Vec<OutputDepKind>,
+}
+
+#[derive(Deserialize)]
+struct OutputDepKind {
+    /// `dev`, `build` or `null` for normal dependencies
+    kind: Option<String>,
+}
+
+impl OutputDep {
+    /// Is this dependency used other than as a dev-dependency? Cargo versions
+    /// before 1.41 don't report dependency kinds, so nothing is assumed then.
+    fn is_production(&self) -> bool {
+        self.dep_kinds.is_empty()
+            || self
+                .dep_kinds
+                .iter()
+                .any(|dep_kind| dep_kind.kind.as_deref() != Some("dev"))
+    }
+}
+
+impl Metadata {
+    /// Run `cargo metadata` for the workspace the given lockfile belongs to
+    pub fn load(lockfile_path: &Path) -> Result<Self, Error> {
+        Self::load_for_target(lockfile_path, None)
+    }
+
+    /// Run `cargo metadata` for the workspace the given lockfile belongs to,
+    /// resolving only the dependencies used on the given target if any
+    pub fn load_for_target(lockfile_path: &Path, target: Option<&str>) -> Result<Self, Error> {
+        let manifest_path = lockfile_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("Cargo.toml");
+
+        if !manifest_path.exists() {
+            return Err(ErrorKind::Io
+                .context(format!("no Cargo.toml next to {}", lockfile_path.display()))
+                .into());
+        }
+
+        let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let mut command = Command::new(cargo);
+        command
+            .args([
+                "metadata",
+                "--format-version",
+                "1",
+                "--locked",
+                "--manifest-path",
+            ])
+            .arg(&manifest_path);
+
+        if let Some(target) = target {
+            command.args(["--filter-platform", target]);
+        }
+
+        let output = command.output().map_err(|e| ErrorKind::Io.context(e))?;
+
+        if !output.status.success() {
+            return Err(ErrorKind::Io
+                .context(format!(
+                    "cargo metadata failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+                .into());
+        }
+
+        Self::from_json(&output.stdout)
+    }
+
+    /// Parse the output of `cargo metadata --format-version 1`
+    pub fn from_json(json: &[u8]) -> Result<Self, Error> {
+        let output: Output =
+            serde_json::from_slice(json).map_err(|e| ErrorKind::Parse.context(e))?;
+
+        let member_manifests = output
+            .packages
+            .iter()
+            .filter(|package| output.workspace_members.contains(&package.id))
+            .map(|package| package.manifest_path.clone())
+            .collect();
+
+        let mut requirements: BTreeMap<Name, Vec<VersionReq>> = BTreeMap::new();
+        for dependency in output
+            .packages
+            .iter()
+            .flat_map(|package| &package.dependencies)
+        {
+            if let Ok(req) = dependency.req.parse() {
+                let reqs = requirements.entry(dependency.name.clone()).or_default();
+                if !reqs.contains(&req) {
+                    reqs.push(req);
+                }
+            }
+        }
+
+        let packages: BTreeMap<_, _> = output
+            .packages
+            .into_iter()
+            .map(|package| {
+                let source = package.source.and_then(|source| source.parse().ok());
+                (package.id, (package.name, package.version, source))
+            })
+            .collect();
+
+        let nodes: BTreeMap<_, _> = output
+            .resolve
+            .map(|resolve| resolve.nodes)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect();
+
+        let features = nodes
+            .values()
+            .filter_map(|node| {
+                let package = packages.get(&node.id)?.clone();
+                Some((package, node.features.iter().cloned().collect()))
+            })
+            .collect();
+
+        // Walk the dependency graph from the workspace members, skipping
+        // dev-dependencies
+        let mut reachable = BTreeSet::new();
+        let mut pending: Vec<&str> = output
+            .workspace_members
+            .iter()
+            .map(String::as_str)
+            .collect();
+
+        while let Some(id) = pending.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(node) = nodes.get(id) {
+                pending.extend(
+                    node.deps
+                        .iter()
+                        .filter(|dep| dep.is_production())
+                        .map(|dep| dep.pkg.as_str()),
+                );
+            }
+        }
+
+        let production = reachable
+            .into_iter()
+            .filter_map(|id| packages.get(id).cloned())
+            .collect();
+
+        Ok(Self {
+            features,
+            production,
+            workspace_root: output.workspace_root,
+            member_manifests,
+            requirements,
+        })
+    }
+
+    /// Get the features enabled for the given package, if it is part of the
+    /// resolved dependency graph
+    pub fn features(&self, package: &Package) -> Option<&BTreeSet<String>> {
+        self.features
+            .get(&key(&package.name, &package.version, &package.source))
+    }
+
+    /// Get the version requirements the packages depending on the given
+    /// package have on it, leaving out those on other versions of it
+    pub fn requirements(&self, name: &Name, version: &Version) -> Vec<VersionReq> {
+        self.requirements
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|req| req.matches(version))
+            .cloned()
+            .collect()
+    }
+
+    /// Paths to the manifests which may declare requirements on dependencies:
+    /// those of the workspace members, along with the workspace root's
+    /// manifest, which may have a `[workspace.dependencies]` table
+    pub fn manifests(&self) -> Vec<PathBuf> {
+        let mut manifests = self.member_manifests.clone();
+        let root_manifest = self.workspace_root.join("Cargo.toml");
+
+        if !manifests.contains(&root_manifest) && root_manifest.exists() {
+            manifests.push(root_manifest);
+        }
+
+        manifests
+    }
+
+    /// Remove the packages of a lockfile which aren't part of the resolved
+    /// dependency graph, e.g. those only used on other platforms than the
+    /// target the metadata was loaded for, along with the dependencies on them
+    pub fn prune(&self, lockfile: &Lockfile) -> Lockfile {
+        let mut pruned = lockfile.clone();
+
+        // Nothing is known to be unused without a resolved graph
+        if self.features.is_empty() {
+            return pruned;
+        }
+
+        pruned.packages.retain(|package| {
+            self.features
+                .contains_key(&key(&package.name, &package.version, &package.source))
+        });
+
+        // Dependencies refer to git sources without their exact revision
+        let dependency_key = |name: &Name, version: &Version, source: &Option<SourceId>| {
+            let source = source.as_ref().map(|source| {
+                source
+                    .normalize_git_source_for_dependency()
+                    .with_precise(None)
+            });
+            (name.clone(), version.clone(), source)
+        };
+
+        let resolved: BTreeSet<PackageKey> = pruned
+            .packages
+            .iter()
+            .map(|package| dependency_key(&package.name, &package.version, &package.source))
+            .collect();
+
+        for package in &mut pruned.packages {
+            package.dependencies.retain(|dependency| {
+                resolved.contains(&dependency_key(
+                    &dependency.name,
+                    &dependency.version,
+                    &dependency.source,
+                ))
+            });
+        }
+
+        pruned
+    }
+
+    /// Is the given package only used as a dev-dependency? Packages which
+    /// aren't part of the resolved dependency graph are assumed not to be.
+    pub fn is_dev_only(&self, package: &Package) -> bool {
+        let package = key(&package.name, &package.version, &package.source);
+        self.features.contains_key(&package) && !self.production.contains(&package)
+    }
+}
+
+/// Key of a package in the metadata
+fn key(name: &Name, version: &Version, source: &Option<SourceId>) -> PackageKey {
+    (name.clone(), version.clone(), source.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = r#"{
+        "packages": [
+            {
+                "id": "app 0.1.0 (path+file:///app)",
+                "name": "app",
+                "version": "0.1.0",
+                "manifest_path": "/app/Cargo.toml",
+                "dependencies": [
+                    { "name": "serde", "req": "^1.0.100" },
+                    { "name": "old_serde", "rename": "serde_old", "req": "~0.9" },
+                    { "name": "libc", "req": "=0.2.100" }
+                ]
+            },
+            {
+                "id": "tool 0.1.0 (path+file:///app/tool)",
+                "name": "tool",
+                "version": "0.1.0",
+                "manifest_path": "/app/tool/Cargo.toml",
+                "dependencies": [
+                    { "name": "serde", "req": "^1.0.100" },
+                    { "name": "serde", "req": "^0.9" }
+                ]
+            }
+        ],
+        "workspace_members": [
+            "app 0.1.0 (path+file:///app)",
+            "tool 0.1.0 (path+file:///app/tool)"
+        ],
+        "workspace_root": "/app"
+    }"#;
+
+    #[test]
+    fn requirements() {
+        let metadata = Metadata::from_json(OUTPUT.as_bytes()).unwrap();
+        let requirements = |name: &str, version: &str| {
+            metadata
+                .requirements(&name.parse().unwrap(), &version.parse().unwrap())
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(requirements("serde", "1.0.130"), ["^1.0.100"]);
+        assert_eq!(requirements("serde", "0.9.15"), ["^0.9"]);
+        assert_eq!(requirements("old_serde", "0.9.1"), ["~0.9"]);
+        assert_eq!(requirements("libc", "0.2.100"), ["=0.2.100"]);
+        assert!(requirements("libc", "0.2.101").is_empty());
+        assert!(requirements("tool", "0.1.0").is_empty());
+    }
+
+    /// `app` depends on `foo` from crates.io, and on a git fork of it with
+    /// the same version as a dev-dependency. The fork's `bar` dependency and
+    /// another fork are only used on other platforms.
+    const RESOLVED: &str = r#"{
+        "packages": [
+            {
+                "id": "app 0.1.0 (path+file:///app)",
+                "name": "app",
+                "version": "0.1.0",
+                "source": null,
+                "manifest_path": "/app/Cargo.toml"
+            },
+            {
+                "id": "foo 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                "name": "foo",
+                "version": "1.0.0",
+                "source": "registry+https://github.com/rust-lang/crates.io-index",
+                "manifest_path": "/registry/foo-1.0.0/Cargo.toml"
+            },
+            {
+                "id": "foo 1.0.0 (git+https://github.com/fork/foo#0123456789abcdef0123456789abcdef01234567)",
+                "name": "foo",
+                "version": "1.0.0",
+                "source": "git+https://github.com/fork/foo#0123456789abcdef0123456789abcdef01234567",
+                "manifest_path": "/git/foo/Cargo.toml"
+            }
+        ],
+        "workspace_members": ["app 0.1.0 (path+file:///app)"],
+        "workspace_root": "/app",
+        "resolve": {
+            "nodes": [
+                {
+                    "id": "app 0.1.0 (path+file:///app)",
+                    "deps": [
+                        {
+                            "pkg": "foo 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                            "dep_kinds": [{ "kind": null }]
+                        },
+                        {
+                            "pkg": "foo 1.0.0 (git+https://github.com/fork/foo#0123456789abcdef0123456789abcdef01234567)",
+                            "dep_kinds": [{ "kind": "dev" }]
+                        }
+                    ]
+                },
+                {
+                    "id": "foo 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                    "features": ["std"]
+                },
+                {
+                    "id": "foo 1.0.0 (git+https://github.com/fork/foo#0123456789abcdef0123456789abcdef01234567)"
+                }
+            ]
+        }
+    }"#;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "foo 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+ "foo 1.0.0 (git+https://github.com/fork/foo)",
+]
+
+[[package]]
+name = "bar"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "git+https://github.com/fork/foo#0123456789abcdef0123456789abcdef01234567"
+dependencies = [
+ "bar",
+]
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "git+https://github.com/other/foo#89abcdef0123456789abcdef0123456789abcdef"
+"#;
+
+    #[test]
+    fn packages_from_different_sources() {
+        let metadata = Metadata::from_json(RESOLVED.as_bytes()).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let foo = |source: &str| {
+            lockfile
+                .packages
+                .iter()
+                .find(|package| {
+                    package.name.as_str() == "foo"
+                        && package
+                            .source
+                            .as_ref()
+                            .unwrap()
+                            .to_string()
+                            .starts_with(source)
+                })
+                .unwrap()
+        };
+
+        let (registry_foo, git_foo) = (foo("registry+"), foo("git+"));
+        assert_eq!(
+            metadata.features(registry_foo).unwrap(),
+            &BTreeSet::from(["std".to_owned()])
+        );
+        assert!(metadata.features(git_foo).unwrap().is_empty());
+        assert!(!metadata.is_dev_only(registry_foo));
+        assert!(metadata.is_dev_only(git_foo));
+
+        let pruned = metadata.prune(&lockfile);
+        let packages: Vec<_> = pruned
+            .packages
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect();
+        assert_eq!(packages, ["app", "foo", "foo"]);
+        assert_eq!(pruned.packages[0].dependencies.len(), 2);
+        assert!(pruned.packages[2].dependencies.is_empty());
+    }
+
+    /// Without a resolved graph, nothing is pruned
+    #[test]
+    fn prune_unresolved() {
+        let metadata = Metadata::from_json(OUTPUT.as_bytes()).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        assert_eq!(metadata.prune(&lockfile).packages, lockfile.packages);
+    }
+}
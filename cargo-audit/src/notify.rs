@@ -0,0 +1,220 @@
+//! Notify webhooks about new findings (`[output.notify]`)
+//!
+//! Supported webhooks:
+//!
+//! - `webhooks`: generic webhooks, which receive the JSON report of the new
+//!   findings in a `POST` request.
+//! - `slack`: Slack incoming webhooks, which receive a message listing the
+//!   new findings.
+//!
+//! Only the findings which are new or have changed since the last run are
+//! sent, according to the state file, and nothing is sent if there are none.
+//! The state file keeps track of the findings of each lockfile separately, so
+//! one file can be shared by the audits of several projects.
+//!
+//! Webhook URLs must use `https`, except for hosts on the loopback interface.
+
+use crate::{
+    config::NotifyConfig,
+    error::{Error, ErrorKind},
+    notify_state::NotifyState,
+};
+use rustsec::{
+    transport::{HttpTransport, Request, Transport},
+    Report,
+};
+use serde::Serialize;
+use std::{fmt::Write as _, path::Path, time::Duration};
+
+/// Timeout for each webhook request
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default file keeping track of the findings which have been notified
+const DEFAULT_STATE_FILE: &str = ".cargo-audit-notify.json";
+
+/// Message sent to Slack incoming webhooks
+#[derive(Serialize)]
+struct SlackMessage {
+    text: String,
+}
+
+/// Notify the configured webhooks about the findings of the report for the
+/// given lockfile which are new since the last run, returning the number of
+/// findings sent
+pub fn notify(
+    config: &NotifyConfig,
+    lockfile_path: &Path,
+    report: &Report,
+) -> Result<usize, Error> {
+    let state_path = config
+        .state
+        .as_deref()
+        .unwrap_or_else(|| Path::new(DEFAULT_STATE_FILE));
+
+    for url in config.webhooks.iter().chain(&config.slack) {
+        if !is_secure_url(url) {
+            return Err(ErrorKind::Notify
+                .context(format!(
+                    "webhook {} must use https (http is only allowed for localhost)",
+                    url
+                ))
+                .into());
+        }
+    }
+
+    let mut state = NotifyState::load(state_path)?;
+    let unannounced = state.unannounced(lockfile_path, report);
+    let count = unannounced.vulnerabilities.list.len()
+        + unannounced.warnings.values().map(Vec::len).sum::<usize>();
+
+    if count > 0 {
+        let transport =
+            HttpTransport::new(NOTIFY_TIMEOUT).map_err(|e| ErrorKind::Notify.context(e))?;
+
+        for url in &config.webhooks {
+            post_json(&transport, url, &unannounced)?;
+        }
+
+        let message = SlackMessage {
+            text: slack_text(lockfile_path, &unannounced, count),
+        };
+        for url in &config.slack {
+            post_json(&transport, url, &message)?;
+        }
+    }
+
+    // The state is only updated once the findings have been delivered, so
+    // failed notifications are retried on the next run
+    state.record(lockfile_path, report);
+    state.save(state_path)?;
+    Ok(count)
+}
+
+/// Is the given URL safe to send findings to, i.e. does it use `https`, or
+/// `http` to a host on the loopback interface (e.g. a local test server)?
+pub(crate) fn is_secure_url(url: &str) -> bool {
+    if url.starts_with("https://") {
+        return true;
+    }
+
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        // IPv6 address, e.g. `[::1]:8080`
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .map_or(false, |ip| ip.is_loopback())
+}
+
+/// `POST` the given value as JSON to a webhook
+fn post_json(transport: &HttpTransport, url: &str, body: &impl Serialize) -> Result<(), Error> {
+    let body = serde_json::to_vec(body).expect("couldn't serialize notification");
+
+    transport
+        .send(&Request::post_json(url, body))
+        .and_then(|response| response.into_body())
+        .map_err(|e| ErrorKind::Notify.context(e))?;
+
+    Ok(())
+}
+
+/// Format the Slack message listing the findings of a report
+fn slack_text(lockfile_path: &Path, report: &Report, count: usize) -> String {
+    let mut text = format!(
+        "cargo audit found {} new {} in `{}`:",
+        count,
+        if count == 1 { "finding" } else { "findings" },
+        slack_escape(&lockfile_path.display().to_string())
+    );
+
+    for vulnerability in &report.vulnerabilities.list {
+        let advisory = &vulnerability.advisory;
+        write!(
+            text,
+            "\n• *{}* `{} {}`: {}",
+            slack_link(advisory.id.as_str(), advisory.id.url()),
+            vulnerability.package.name,
+            vulnerability.package.version,
+            slack_escape(&advisory.title)
+        )
+        .unwrap();
+    }
+
+    for warning in report.warnings.values().flatten() {
+        write!(
+            text,
+            "\n• {} `{} {}`",
+            warning.kind.as_str(),
+            warning.package.name,
+            warning.package.version
+        )
+        .unwrap();
+
+        if let Some(advisory) = &warning.advisory {
+            write!(
+                text,
+                " ({}): {}",
+                slack_link(advisory.id.as_str(), advisory.id.url()),
+                slack_escape(&advisory.title)
+            )
+            .unwrap();
+        }
+    }
+
+    text
+}
+
+/// Format a Slack link to the given URL, if any
+fn slack_link(label: &str, url: Option<String>) -> String {
+    match url {
+        Some(url) => format!("<{}|{}>", slack_escape(&url), slack_escape(label)),
+        None => slack_escape(label),
+    }
+}
+
+/// Escape the characters which Slack interprets as control sequences, so that
+/// e.g. advisory titles can't inject links or mentions
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_slack_control_characters() {
+        assert_eq!(
+            slack_escape("<!channel> & <https://example.com|click>"),
+            "&lt;!channel&gt; &amp; &lt;https://example.com|click&gt;"
+        );
+        assert_eq!(
+            slack_link("A&B", Some("https://example.com/?a=1&b=2".to_owned())),
+            "<https://example.com/?a=1&amp;b=2|A&amp;B>"
+        );
+    }
+
+    #[test]
+    fn require_secure_webhooks() {
+        assert!(is_secure_url("https://hooks.slack.com/services/T0/B0/X"));
+        assert!(is_secure_url("http://localhost:8080/hook"));
+        assert!(is_secure_url("http://127.0.0.1/hook"));
+        assert!(is_secure_url("http://[::1]:8080/hook"));
+        assert!(!is_secure_url("http://example.com/hook"));
+        assert!(!is_secure_url("http://localhost.example.com/hook"));
+        assert!(!is_secure_url("http://localhost@example.com/hook"));
+        assert!(!is_secure_url("ftp://localhost/hook"));
+    }
+}
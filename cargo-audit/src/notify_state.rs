@@ -0,0 +1,251 @@
+//! State of the findings which have already been announced, so that recurring
+//! scheduled runs only announce new or changed findings
+//!
+//! Findings are tracked separately for each lockfile, so the audits of several
+//! projects can share a state file.
+
+use crate::error::{Error, ErrorKind};
+use rustsec::{Report, Vulnerability, Warning};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Version of the state file format
+const STATE_VERSION: u32 = 2;
+
+/// Findings which have been announced, by key, along with a fingerprint of
+/// the details which make a finding worth announcing again when they change
+type Findings = BTreeMap<String, String>;
+
+/// Announced findings of each lockfile
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NotifyState {
+    version: u32,
+
+    /// Findings by lockfile path
+    lockfiles: BTreeMap<PathBuf, Findings>,
+}
+
+impl Default for NotifyState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            lockfiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl NotifyState {
+    /// Load the state from the given file, starting afresh if it doesn't exist
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ErrorKind::Io.context(e).into()),
+        };
+
+        let state: Self = serde_json::from_str(&json).map_err(|e| ErrorKind::Parse.context(e))?;
+
+        if state.version != STATE_VERSION {
+            return Err(ErrorKind::Parse
+                .context(format!(
+                    "unsupported notification state version {} in {}",
+                    state.version,
+                    path.display()
+                ))
+                .into());
+        }
+
+        Ok(state)
+    }
+
+    /// Save the state to the given file
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).expect("couldn't serialize state");
+        fs::write(path, json).map_err(|e| ErrorKind::Io.context(e))?;
+        Ok(())
+    }
+
+    /// Get the part of the report for the given lockfile with the findings
+    /// which are new or have changed since they were last announced
+    pub fn unannounced(&self, lockfile_path: &Path, report: &Report) -> Report {
+        let empty = Findings::new();
+        let findings = self
+            .lockfiles
+            .get(&lockfile_key(lockfile_path))
+            .unwrap_or(&empty);
+        let is_unannounced =
+            |(key, fingerprint): (String, String)| findings.get(&key) != Some(&fingerprint);
+
+        let mut unannounced = report.clone();
+
+        unannounced
+            .vulnerabilities
+            .list
+            .retain(|vulnerability| is_unannounced(vulnerability_finding(vulnerability)));
+        unannounced.vulnerabilities.count = unannounced.vulnerabilities.list.len();
+        unannounced.vulnerabilities.found = !unannounced.vulnerabilities.list.is_empty();
+
+        for warnings in unannounced.warnings.values_mut() {
+            warnings.retain(|warning| is_unannounced(warning_finding(warning)));
+        }
+        unannounced
+            .warnings
+            .retain(|_, warnings| !warnings.is_empty());
+
+        unannounced
+    }
+
+    /// Record the findings of the report for the given lockfile as announced.
+    /// Findings which are no longer present are forgotten, so they are
+    /// announced again if they reappear.
+    pub fn record(&mut self, lockfile_path: &Path, report: &Report) {
+        let findings = report
+            .vulnerabilities
+            .list
+            .iter()
+            .map(vulnerability_finding)
+            .chain(report.warnings.values().flatten().map(warning_finding))
+            .collect();
+
+        self.lockfiles.insert(lockfile_key(lockfile_path), findings);
+    }
+}
+
+/// Key identifying a lockfile in the state file: its canonical path, so that
+/// runs from different working directories agree
+fn lockfile_key(lockfile_path: &Path) -> PathBuf {
+    fs::canonicalize(lockfile_path).unwrap_or_else(|_| lockfile_path.to_owned())
+}
+
+/// Key and fingerprint of a vulnerability
+fn vulnerability_finding(vulnerability: &Vulnerability) -> (String, String) {
+    let key = format!(
+        "{}:{}:{}",
+        vulnerability.advisory.id, vulnerability.package.name, vulnerability.package.version
+    );
+
+    let fingerprint = format!(
+        "patched={};cvss={};withdrawn={}",
+        join(vulnerability.versions.patched()),
+        vulnerability
+            .advisory
+            .primary_cvss()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        vulnerability.advisory.withdrawn.is_some()
+    );
+
+    (key, fingerprint)
+}
+
+/// Key and fingerprint of a warning
+fn warning_finding(warning: &Warning) -> (String, String) {
+    let key = match &warning.advisory {
+        Some(advisory) => format!(
+            "{}:{}:{}",
+            advisory.id, warning.package.name, warning.package.version
+        ),
+        None => format!(
+            "{}:{}:{}",
+            warning.kind.as_str(),
+            warning.package.name,
+            warning.package.version
+        ),
+    };
+
+    let fingerprint = format!(
+        "kind={};patched={}",
+        warning.kind.as_str(),
+        warning
+            .versions
+            .as_ref()
+            .map(|versions| join(versions.patched()))
+            .unwrap_or_default()
+    );
+
+    (key, fingerprint)
+}
+
+/// Join version requirements into a string
+fn join(reqs: &[rustsec::VersionReq]) -> String {
+    reqs.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::{report::Settings, warning::WarningKind, Database, Lockfile};
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "example"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    /// Report with a warning about a yanked crate
+    fn report() -> Report {
+        let database = Database::from_advisories(vec![]).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let mut report = Report::generate(&database, &lockfile, &Settings::default());
+
+        let warning = Warning::new(WarningKind::Yanked, &lockfile.packages[0], None, None, None);
+        report
+            .warnings
+            .entry(WarningKind::Yanked)
+            .or_default()
+            .push(warning);
+
+        report
+    }
+
+    fn count(report: &Report) -> usize {
+        report.warnings.values().map(Vec::len).sum()
+    }
+
+    #[test]
+    fn track_findings_per_lockfile() {
+        let report = report();
+        let mut state = NotifyState::default();
+        let first = Path::new("first/Cargo.lock");
+        let second = Path::new("second/Cargo.lock");
+
+        assert_eq!(count(&state.unannounced(first, &report)), 1);
+        state.record(first, &report);
+        assert_eq!(count(&state.unannounced(first, &report)), 0);
+
+        // The same finding in another project hasn't been announced yet
+        assert_eq!(count(&state.unannounced(second, &report)), 1);
+        state.record(second, &report);
+
+        // Recording the findings of one lockfile keeps those of the other
+        let mut resolved = report.clone();
+        resolved.warnings.clear();
+        state.record(first, &resolved);
+        assert_eq!(count(&state.unannounced(first, &report)), 1);
+        assert_eq!(count(&state.unannounced(second, &report)), 0);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let lockfile = Path::new("Cargo.lock");
+
+        let mut state = NotifyState::load(&path).unwrap();
+        state.record(lockfile, &report());
+        state.save(&path).unwrap();
+
+        let state = NotifyState::load(&path).unwrap();
+        assert_eq!(count(&state.unannounced(lockfile, &report())), 0);
+    }
+}
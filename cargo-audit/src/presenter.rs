@@ -1,26 +1,110 @@
 //! Presenter for `rustsec::Report` information.
 
 use crate::{
-    config::{DenyOption, OutputConfig, OutputFormat},
+    config::{
+        ComponentRule, DenyOption, GroupBy, OutputConfig, OutputFormat, PolicyAction,
+        SeverityBucket,
+    },
+    gitlab::GitlabReport,
     prelude::*,
 };
-use abscissa_core::terminal::{
-    self,
-    Color::{self, Red, Yellow},
-};
+use abscissa_core::terminal::{self, Color};
 use rustsec::{
     advisory::License,
     cargo_lock::{
-        dependency::{self, graph::EdgeDirection, Dependency},
+        dependency::{self, graph::EdgeDirection, tree::Symbols, Dependency},
         Lockfile, Package,
     },
-    WarningKind,
+    database::Query,
+    report::{diff::ReportDiff, Aggregates, VulnerabilityGroup},
+    DependencyPath, ReleaseInfo, WarningKind,
+};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap as Map, BTreeSet as Set},
+    io,
+    path::{Path, PathBuf},
 };
-use std::{collections::BTreeSet as Set, io, path::Path};
 use std::{io::Write as _, string::ToString as _};
 
 #[cfg(feature = "binary-scanning")]
-use crate::binary_deps::BinaryReport;
+use crate::{
+    artifact_group::ArtifactReport,
+    binary_deps::BinaryReport,
+    binary_tree::{BinaryOutcome, TreeReport},
+};
+
+/// Report with the number of findings in each component, used for JSON output
+/// when components are configured
+#[derive(Serialize)]
+struct ComponentReport<'a> {
+    #[serde(flatten)]
+    report: &'a rustsec::Report,
+
+    components: Map<&'a str, ComponentCounts>,
+}
+
+/// Line of newline-delimited JSON output: the report for one audited
+/// target, or the error which kept it from being audited
+#[derive(Serialize)]
+struct NdjsonRecord<'a> {
+    /// Lockfile or binary the report is for
+    target: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<&'a rustsec::Report>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Line of newline-delimited JSON output: a finding in the builds of an
+/// artifact
+#[cfg(feature = "binary-scanning")]
+#[derive(Serialize)]
+struct NdjsonFinding<'a> {
+    /// Name of the artifact
+    target: &'a str,
+
+    #[serde(flatten)]
+    finding: ArtifactFinding<'a>,
+}
+
+/// Vulnerability or warning in the builds of an artifact
+#[cfg(feature = "binary-scanning")]
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ArtifactFinding<'a> {
+    Vulnerability(&'a crate::artifact_group::Finding<rustsec::Vulnerability>),
+    Warning(&'a crate::artifact_group::Finding<rustsec::Warning>),
+}
+
+/// Number of findings in a component
+#[derive(Default, Serialize)]
+struct ComponentCounts {
+    vulnerabilities: usize,
+    warnings: usize,
+}
+
+/// Findings of a report which aren't allowed, as counted for `--summary`
+#[derive(Debug, Default)]
+struct FindingCounts<'a> {
+    /// Number of vulnerabilities of each severity
+    severities: Map<SeverityBucket, u64>,
+
+    /// Are any of the vulnerabilities denied?
+    vulnerabilities_denied: bool,
+
+    /// Vulnerabilities which were counted
+    vulnerabilities: Vec<&'a rustsec::Vulnerability>,
+
+    /// Number of warnings of each kind
+    kinds: Map<WarningKind, u64>,
+
+    /// Are any of the warnings denied?
+    warnings_denied: bool,
+}
 
 /// Vulnerability information presenter
 #[derive(Clone, Debug)]
@@ -32,6 +116,9 @@ pub struct Presenter {
     /// Keep track of the warning kinds that correspond to deny-warnings options
     deny_warning_kinds: Set<WarningKind>,
 
+    /// Path of the lockfile being audited, if any
+    lockfile_path: Option<PathBuf>,
+
     /// Output configuration
     config: OutputConfig,
 }
@@ -47,12 +134,15 @@ impl Presenter {
                 .flat_map(|k| k.get_warning_kind())
                 .copied()
                 .collect(),
+            lockfile_path: None,
             config: config.clone(),
         }
     }
 
     /// Information to display before a report is generated
     pub fn before_report(&mut self, path: &Path, lockfile: &Lockfile) {
+        self.lockfile_path = Some(path.to_owned());
+
         if !self.config.is_quiet() {
             status_ok!(
                 "Scanning",
@@ -66,7 +156,24 @@ impl Presenter {
     #[cfg(feature = "binary-scanning")]
     /// Information to display before a binary file is scanned
     pub fn binary_scan_report(&mut self, report: &BinaryReport, path: &Path) {
-        use crate::binary_deps::BinaryReport::*;
+        use crate::{
+            binary_deps::BinaryReport::*,
+            strict::{self, Anomaly},
+        };
+
+        if let Incomplete(lockfile) = report {
+            strict::report(
+                self.config.strict,
+                self.config.is_quiet(),
+                Anomaly::IncompleteData,
+                format_args!(
+                    "{} was not built with 'cargo auditable', the report will be incomplete ({} dependencies recovered)",
+                    path.display(),
+                    lockfile.packages.len()
+                ),
+            );
+        }
+
         if !self.config.is_quiet() {
             match report {
                 Complete(lockfile) => status_ok!(
@@ -75,11 +182,7 @@ impl Presenter {
                     path.display(),
                     lockfile.packages.len()
                 ),
-                Incomplete(lockfile) => {
-                    status_warn!(
-                        "{} was not built with 'cargo auditable', the report will be incomplete ({} dependencies recovered)",
-                        path.display(), lockfile.packages.len());
-                }
+                Incomplete(_) => (),
                 None => status_err!(
                     "No dependency information found in {}! Is it a Rust program built with cargo?",
                     path.display(),
@@ -88,6 +191,158 @@ impl Presenter {
         }
     }
 
+    #[cfg(feature = "binary-scanning")]
+    /// Print the merged reports for per-platform builds of the same
+    /// artifacts: as a JSON array of reports, or as one NDJSON line per
+    /// finding
+    pub fn print_artifact_reports(&mut self, reports: &[ArtifactReport]) {
+        if self.streams_reports() {
+            for record in reports.iter().flat_map(ndjson_findings) {
+                print_ndjson(&record);
+            }
+            return;
+        }
+
+        if self.config.format != OutputFormat::Terminal {
+            serde_json::to_writer(io::stdout(), reports).unwrap();
+            io::stdout().flush().unwrap();
+            return;
+        }
+
+        for report in reports {
+            self.print_artifact_report(report);
+        }
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Print the merged report for per-platform builds of the same artifact
+    fn print_artifact_report(&mut self, report: &ArtifactReport) {
+        status_ok!(
+            "Artifact",
+            "{} ({} builds: {})",
+            report.artifact,
+            report.platforms.len(),
+            report.platforms.join(", ")
+        );
+
+        let color = self.warning_color(true);
+        for vulnerability in &report.vulnerabilities {
+            self.print_attr(color, "Crate:    ", &vulnerability.finding.package.name);
+            self.print_attr(
+                color,
+                "Version:  ",
+                vulnerability.finding.package.version.to_string(),
+            );
+            self.print_metadata(&vulnerability.finding.advisory, color);
+            self.print_platforms(color, &vulnerability.platforms, report);
+            println!();
+        }
+
+        for warning in &report.warnings {
+            let color =
+                self.warning_color(self.warning_action(&warning.finding) == PolicyAction::Deny);
+
+            self.print_attr(color, "Crate:    ", &warning.finding.package.name);
+            self.print_attr(
+                color,
+                "Version:  ",
+                warning.finding.package.version.to_string(),
+            );
+            self.print_attr(color, "Warning:  ", warning.finding.kind.as_str());
+
+            if let Some(metadata) = &warning.finding.advisory {
+                self.print_metadata(metadata, color)
+            }
+
+            self.print_platforms(color, &warning.platforms, report);
+            println!();
+        }
+
+        match report.vulnerabilities.len() {
+            0 => (),
+            1 => status_err!("1 vulnerability found in {}", report.artifact),
+            count => status_err!("{} vulnerabilities found in {}", count, report.artifact),
+        }
+
+        let num_warnings = report.warnings.len() as u64;
+
+        if num_warnings > 0 {
+            status_warn!(
+                "{} {} found in {}",
+                num_warnings,
+                self.warning_word(num_warnings),
+                report.artifact
+            );
+        }
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Print the aggregated report for the binaries under directory trees,
+    /// followed by the number of binaries and findings
+    pub fn print_tree_report(&mut self, report: &TreeReport) {
+        // The report for each binary was already printed when it was ready
+        if self.streams_reports() {
+            return;
+        }
+
+        if self.config.format != OutputFormat::Terminal {
+            serde_json::to_writer(io::stdout(), &report).unwrap();
+            io::stdout().flush().unwrap();
+            return;
+        }
+
+        for (path, outcome) in &report.binaries {
+            match outcome {
+                BinaryOutcome::Scanned { report, lockfile } => {
+                    if !self.config.is_quiet() {
+                        status_ok!("Binary", "{}", path);
+                    }
+                    self.print_report(report, lockfile, Some(Path::new(path)));
+                }
+                BinaryOutcome::Error { message } => status_err!("{}: {}", path, message),
+            }
+        }
+
+        let summary = &report.summary;
+
+        status_ok!(
+            "Summary",
+            "{} binaries scanned: {} vulnerable, {} with warnings, {} clean, {} failed",
+            summary.binaries,
+            summary.vulnerable,
+            summary.with_warnings,
+            summary.clean,
+            summary.errors
+        );
+
+        if summary.vulnerabilities > 0 {
+            status_err!(
+                "{} vulnerabilities found in {} binaries",
+                summary.vulnerabilities,
+                summary.vulnerable
+            );
+        }
+
+        if summary.warnings > 0 {
+            status_warn!(
+                "{} {} found in {} binaries",
+                summary.warnings,
+                self.warning_word(summary.warnings as u64),
+                summary.with_warnings
+            );
+        }
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    /// Print the platforms a finding in an artifact report applies to
+    fn print_platforms(&self, color: Option<Color>, platforms: &[String], report: &ArtifactReport) {
+        if platforms.len() == report.platforms.len() {
+            self.print_attr(color, "Platform: ", "all builds");
+        } else {
+            self.print_attr(color, "Platform: ", platforms.join(", "));
+        }
+    }
+
     fn warning_word(&self, count: u64) -> &str {
         if count != 1 {
             "warnings"
@@ -104,43 +359,92 @@ impl Presenter {
         path: Option<&Path>,
     ) {
         if self.config.format == OutputFormat::Json {
-            serde_json::to_writer(io::stdout(), &report).unwrap();
+            if self.config.components.is_empty() {
+                serde_json::to_writer(io::stdout(), &report).unwrap();
+            } else {
+                let report = ComponentReport {
+                    report,
+                    components: self.component_counts(report),
+                };
+                serde_json::to_writer(io::stdout(), &report).unwrap();
+            }
             io::stdout().flush().unwrap();
             return;
         }
 
+        if self.config.format == OutputFormat::Gitlab {
+            self.print_gitlab_report(report, lockfile, path);
+            return;
+        }
+
+        if self.config.format == OutputFormat::Ndjson {
+            self.print_ndjson_report(report, path);
+            return;
+        }
+
+        if self.config.summary {
+            self.print_summary(report, path);
+            return;
+        }
+
         let tree = lockfile
             .dependency_tree()
             .expect("invalid Cargo.lock dependency tree");
 
         // NOTE: when modifying the following logic, be sure to also update should_exit_with_failure()
 
-        // Print out vulnerabilities and warnings
-        for vulnerability in &report.vulnerabilities.list {
-            self.print_vulnerability(vulnerability, &tree);
+        // Print out vulnerabilities and warnings, grouped by component if configured
+        if self.config.components.is_empty() {
+            let vulnerabilities: Vec<_> = report
+                .vulnerabilities
+                .list
+                .iter()
+                .filter(|vuln| self.vulnerability_action(vuln) != PolicyAction::Allow)
+                .collect();
+
+            let warnings: Vec<_> = report
+                .warnings
+                .values()
+                .flatten()
+                .filter(|warning| self.warning_action(warning) != PolicyAction::Allow)
+                .collect();
+
+            self.print_findings(&vulnerabilities, &warnings, &tree);
+        } else {
+            self.print_components(report, &tree);
         }
 
-        for warnings in report.warnings.values() {
-            for warning in warnings.iter() {
-                self.print_warning(warning, &tree)
+        let (num_denied, num_not_denied) = self.count_vulnerabilities(report);
+
+        if num_denied == 1 {
+            match path {
+                Some(path) => status_err!("1 vulnerability found in {}", path.display()),
+                None => status_err!("1 vulnerability found!"),
+            }
+        } else if num_denied > 1 {
+            match path {
+                Some(path) => {
+                    status_err!("{} vulnerabilities found in {}", num_denied, path.display())
+                }
+                None => status_err!("{} vulnerabilities found!", num_denied),
             }
         }
 
-        if report.vulnerabilities.found {
-            if report.vulnerabilities.count == 1 {
-                match path {
-                    Some(path) => status_err!("1 vulnerability found in {}", path.display()),
-                    None => status_err!("1 vulnerability found!"),
-                }
+        if num_not_denied > 0 {
+            let word = if num_not_denied == 1 {
+                "vulnerability"
             } else {
-                match path {
-                    Some(path) => status_err!(
-                        "{} vulnerabilities found in {}",
-                        report.vulnerabilities.count,
-                        path.display()
-                    ),
-                    None => status_err!("{} vulnerabilities found!", report.vulnerabilities.count),
-                }
+                "vulnerabilities"
+            };
+
+            match path {
+                Some(path) => status_warn!(
+                    "{} {} found in {} (not denied)",
+                    num_not_denied,
+                    word,
+                    path.display()
+                ),
+                None => status_warn!("{} {} found (not denied)", num_not_denied, word),
             }
         }
 
@@ -165,13 +469,13 @@ impl Presenter {
             if num_not_denied > 0 {
                 match path {
                     Some(path) => status_warn!(
-                        "{} allowed {} found in {}",
+                        "{} {} found in {} (not denied)",
                         num_not_denied,
                         self.warning_word(num_not_denied),
                         path.display(),
                     ),
                     None => status_warn!(
-                        "{} allowed {} found",
+                        "{} {} found (not denied)",
                         num_not_denied,
                         self.warning_word(num_not_denied)
                     ),
@@ -180,6 +484,346 @@ impl Presenter {
         }
     }
 
+    /// Are reports printed one at a time as soon as they are ready, rather
+    /// than once all targets have been audited?
+    pub fn streams_reports(&self) -> bool {
+        self.config.format == OutputFormat::Ndjson
+    }
+
+    /// Print a report as a line of newline-delimited JSON, along with the
+    /// lockfile or binary it is for
+    fn print_ndjson_report(&self, report: &rustsec::Report, path: Option<&Path>) {
+        let target = path
+            .or(self.lockfile_path.as_deref())
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+
+        print_ndjson(&NdjsonRecord {
+            target,
+            report: Some(report),
+            error: None,
+        });
+    }
+
+    /// Print an error which kept a lockfile or binary from being audited:
+    /// as a line of its own when streaming reports, so that consumers of the
+    /// stream can tell which targets failed, and to stderr otherwise
+    pub fn print_error(&self, target: &Path, message: &str) {
+        if !self.streams_reports() {
+            status_err!("{}", message);
+            return;
+        }
+
+        print_ndjson(&NdjsonRecord {
+            target: target.display().to_string(),
+            report: None,
+            error: Some(message),
+        });
+    }
+
+    /// Print the findings of a report which aren't allowed as a GitLab
+    /// dependency scanning report
+    fn print_gitlab_report(
+        &self,
+        report: &rustsec::Report,
+        lockfile: &Lockfile,
+        path: Option<&Path>,
+    ) {
+        let vulnerabilities: Vec<_> = report
+            .vulnerabilities
+            .list
+            .iter()
+            .filter(|vuln| self.vulnerability_action(vuln) != PolicyAction::Allow)
+            .collect();
+
+        let warnings: Vec<_> = report
+            .warnings
+            .values()
+            .flatten()
+            .filter(|warning| self.warning_action(warning) != PolicyAction::Allow)
+            .collect();
+
+        let path = path
+            .or(self.lockfile_path.as_deref())
+            .unwrap_or_else(|| Path::new("Cargo.lock"));
+
+        let report = GitlabReport::new(
+            &vulnerabilities,
+            &warnings,
+            lockfile,
+            &path.display().to_string(),
+        );
+        serde_json::to_writer(io::stdout(), &report).unwrap();
+        io::stdout().flush().unwrap();
+    }
+
+    /// Count the vulnerabilities by severity and the warnings by kind,
+    /// leaving out the findings which are allowed
+    fn finding_counts<'a>(&self, report: &'a rustsec::Report) -> FindingCounts<'a> {
+        let mut counts = FindingCounts::default();
+
+        for vulnerability in &report.vulnerabilities.list {
+            let action = self.vulnerability_action(vulnerability);
+            if action == PolicyAction::Allow {
+                continue;
+            }
+
+            counts.vulnerabilities_denied |= action == PolicyAction::Deny;
+            *counts
+                .severities
+                .entry(severity_of(vulnerability))
+                .or_default() += 1;
+            counts.vulnerabilities.push(vulnerability);
+        }
+
+        for warning in report.warnings.values().flatten() {
+            let action = self.warning_action(warning);
+            if action == PolicyAction::Allow {
+                continue;
+            }
+
+            counts.warnings_denied |= action == PolicyAction::Deny;
+            *counts.kinds.entry(warning.kind).or_default() += 1;
+        }
+
+        counts
+    }
+
+    /// Print the number of vulnerabilities by severity and of warnings by
+    /// kind, leaving out the findings which are allowed
+    fn print_summary(&self, report: &rustsec::Report, path: Option<&Path>) {
+        let FindingCounts {
+            severities,
+            vulnerabilities_denied,
+            vulnerabilities,
+            kinds,
+            warnings_denied,
+        } = self.finding_counts(report);
+
+        if let Some(path) = path {
+            self.print_attr(None, "Path:           ", path.display().to_string());
+        }
+
+        let color = if severities.is_empty() {
+            None
+        } else {
+            self.warning_color(vulnerabilities_denied)
+        };
+        self.print_attr(
+            color,
+            "Vulnerabilities:",
+            summary_counts(severities.iter().rev().map(|(s, &n)| (s.as_str(), n))),
+        );
+
+        let aggregates = Aggregates::new(vulnerabilities);
+        if !aggregates.by_category.is_empty() {
+            self.print_attr(
+                None,
+                "Categories:     ",
+                aggregate_counts(&aggregates.by_category),
+            );
+        }
+        if !aggregates.by_cwe.is_empty() {
+            let cwes: Map<_, _> = aggregates
+                .by_cwe
+                .iter()
+                .map(|(cwe, &n)| (format!("{:#}", cwe), n))
+                .collect();
+            self.print_attr(None, "Weaknesses:     ", aggregate_counts(&cwes));
+        }
+
+        let color = if kinds.is_empty() {
+            None
+        } else {
+            self.warning_color(warnings_denied)
+        };
+        self.print_attr(
+            color,
+            "Warnings:       ",
+            summary_counts(kinds.iter().map(|(kind, &n)| (kind.as_str(), n))),
+        );
+    }
+
+    /// Print the findings of a report grouped by component, followed by the
+    /// number of findings in each component
+    fn print_components(&mut self, report: &rustsec::Report, tree: &dependency::Tree) {
+        let config = self.config.clone();
+
+        for component in self.component_names() {
+            let vulnerabilities: Vec<_> = report
+                .vulnerabilities
+                .list
+                .iter()
+                .filter(|vuln| config.component_of(vuln.package.name.as_str()) == component)
+                .filter(|vuln| self.vulnerability_action(vuln) != PolicyAction::Allow)
+                .collect();
+
+            let warnings: Vec<_> = report
+                .warnings
+                .values()
+                .flatten()
+                .filter(|warning| config.component_of(warning.package.name.as_str()) == component)
+                .filter(|warning| self.warning_action(warning) != PolicyAction::Allow)
+                .collect();
+
+            if vulnerabilities.is_empty() && warnings.is_empty() {
+                continue;
+            }
+
+            status_info!("Component", "{}", component);
+            println!();
+
+            self.print_findings(&vulnerabilities, &warnings, tree);
+        }
+
+        for (component, counts) in self.component_counts(report) {
+            if counts.vulnerabilities == 0 && counts.warnings == 0 {
+                continue;
+            }
+
+            status_info!(
+                "Component",
+                "{}: {} {}, {} {}",
+                component,
+                counts.vulnerabilities,
+                if counts.vulnerabilities == 1 {
+                    "vulnerability"
+                } else {
+                    "vulnerabilities"
+                },
+                counts.warnings,
+                self.warning_word(counts.warnings as u64)
+            );
+        }
+    }
+
+    /// Names of the configured components in order, followed by the component
+    /// of crates which don't match any rule
+    fn component_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+
+        for rule in &self.config.components {
+            if !names.contains(&rule.name) {
+                names.push(rule.name.clone());
+            }
+        }
+
+        if !names.iter().any(|name| name == ComponentRule::UNASSIGNED) {
+            names.push(ComponentRule::UNASSIGNED.to_owned());
+        }
+
+        names
+    }
+
+    /// Count the findings in each component
+    fn component_counts<'a>(&'a self, report: &rustsec::Report) -> Map<&'a str, ComponentCounts> {
+        let mut counts: Map<&'a str, ComponentCounts> = Map::new();
+
+        for vulnerability in &report.vulnerabilities.list {
+            let component = self
+                .config
+                .component_of(vulnerability.package.name.as_str());
+            counts.entry(component).or_default().vulnerabilities += 1;
+        }
+
+        for warning in report.warnings.values().flatten() {
+            let component = self.config.component_of(warning.package.name.as_str());
+            counts.entry(component).or_default().warnings += 1;
+        }
+
+        counts
+    }
+
+    /// Print the report generated by an audit followed by how its findings
+    /// differ from those of a baseline report, or only the differences as
+    /// JSON
+    pub fn print_compared_report(
+        &mut self,
+        report: &rustsec::Report,
+        lockfile: &Lockfile,
+        diff: &ReportDiff,
+    ) {
+        match self.config.format {
+            OutputFormat::Terminal => {
+                self.print_report(report, lockfile, None);
+                println!();
+                self.print_report_diff(diff);
+            }
+            OutputFormat::Json | OutputFormat::Ndjson => self.print_report_diff(diff),
+            // GitLab compares the findings with those of the target branch
+            // itself, so it needs the full report
+            OutputFormat::Gitlab => self.print_report(report, lockfile, None),
+        }
+    }
+
+    /// Print the findings which were introduced, resolved and persisted
+    /// between two reports
+    pub fn print_report_diff(&self, diff: &ReportDiff) {
+        if self.config.format != OutputFormat::Terminal {
+            serde_json::to_writer(io::stdout(), diff).unwrap();
+            io::stdout().flush().unwrap();
+            return;
+        }
+
+        self.print_diff_findings(self.warning_color(true), "Introduced", &diff.introduced);
+        let resolved_color = self.config.theme.resolved_color().map(Color::from);
+        self.print_diff_findings(resolved_color, "Resolved", &diff.resolved);
+        self.print_diff_findings(None, "Persisting", &diff.persisting);
+
+        let count = |report: &rustsec::Report| {
+            report.vulnerabilities.list.len()
+                + report.warnings.values().map(Vec::len).sum::<usize>()
+        };
+
+        status_ok!(
+            "Compared",
+            "{} introduced, {} resolved, {} persisting",
+            count(&diff.introduced),
+            count(&diff.resolved),
+            count(&diff.persisting)
+        );
+    }
+
+    /// Print one line per finding of a part of a report diff
+    fn print_diff_findings(&self, color: Option<Color>, label: &str, report: &rustsec::Report) {
+        let print = |message: String| {
+            status(color)
+                .justified()
+                .status(label)
+                .print_stdout(message)
+                .unwrap();
+        };
+
+        for vulnerability in &report.vulnerabilities.list {
+            print(format!(
+                "{} in {} {}: {}",
+                vulnerability.advisory.id,
+                vulnerability.package.name,
+                vulnerability.package.version,
+                vulnerability.advisory.title
+            ));
+        }
+
+        for warning in report.warnings.values().flatten() {
+            print(match &warning.advisory {
+                Some(advisory) => format!(
+                    "{} ({}) in {} {}: {}",
+                    advisory.id,
+                    warning.kind.as_str(),
+                    warning.package.name,
+                    warning.package.version,
+                    advisory.title
+                ),
+                None => format!(
+                    "{} {} is {}",
+                    warning.package.name,
+                    warning.package.version,
+                    warning.kind.as_str()
+                ),
+            });
+        }
+    }
+
     /// Print the vulnerability report for cargo-audit
     pub fn print_self_report(&mut self, self_advisories: &[rustsec::Advisory]) {
         if self_advisories.is_empty() {
@@ -204,14 +848,82 @@ impl Presenter {
         println!();
     }
 
+    /// Print a trace of why the given advisory does or does not match
+    /// each of the given packages
+    pub fn print_explanation(
+        &self,
+        advisory: &rustsec::Advisory,
+        query: &Query,
+        packages: &[&Package],
+        ignored: bool,
+        informational_warning: bool,
+    ) {
+        let id = &advisory.metadata.id;
+
+        if packages.is_empty() {
+            status_info!(
+                "Explain",
+                "{} does not apply: no `{}` package in Cargo.lock",
+                id,
+                advisory.metadata.package
+            );
+            return;
+        }
+
+        for package in packages {
+            status_info!(
+                "Explain",
+                "{} against {} {}",
+                id,
+                package.name,
+                package.version
+            );
+
+            let checks = query.clone().package(package).explain(advisory);
+
+            for check in &checks {
+                println!("    {}", check);
+            }
+
+            let matched = checks.iter().all(|check| check.passed);
+            let is_informational = advisory.metadata.informational.is_some();
+
+            let verdict = if matched && ignored {
+                "vulnerability, ignored by configuration"
+            } else if matched {
+                "reported as a vulnerability"
+            } else if is_informational && advisory.versions.is_vulnerable(&package.version) {
+                // Informational advisories are excluded from the vulnerability
+                // query, but may still be reported as warnings
+                if ignored {
+                    "informational, ignored by configuration"
+                } else if informational_warning {
+                    "reported as a warning"
+                } else {
+                    "informational, warnings of this kind are not enabled"
+                }
+            } else {
+                "not reported"
+            };
+
+            self.print_attr(
+                self.warning_color(matched && !ignored),
+                "Result:   ",
+                verdict,
+            );
+            println!();
+        }
+    }
+
     /// Determines whether the process should exit with failure based on configuration
     /// such as --deny=warnings
     #[must_use]
     pub fn should_exit_with_failure(&self, report: &rustsec::Report) -> bool {
-        if report.vulnerabilities.found {
+        let (denied, _warned) = self.count_vulnerabilities(report);
+        if denied != 0 {
             return true;
         }
-        let (denied, _allowed) = self.count_warnings(report);
+        let (denied, _warned) = self.count_warnings(report);
         if denied != 0 {
             return true;
         }
@@ -228,37 +940,176 @@ impl Presenter {
         !self_advisories.is_empty() && self.config.deny.contains(&DenyOption::Warnings)
     }
 
-    /// Count up the warnings, sorting into denied and allowed.
-    /// Returns `(denied, allowed)`
+    /// Count up the vulnerabilities, sorting into denied and only warned
+    /// about. Allowed vulnerabilities aren't counted. Returns `(denied, warned)`
+    fn count_vulnerabilities(&self, report: &rustsec::Report) -> (u64, u64) {
+        let mut num_denied: u64 = 0;
+        let mut num_not_denied: u64 = 0;
+
+        for vulnerability in &report.vulnerabilities.list {
+            match self.vulnerability_action(vulnerability) {
+                PolicyAction::Deny => num_denied += 1,
+                PolicyAction::Warn => num_not_denied += 1,
+                PolicyAction::Allow => (),
+            }
+        }
+        (num_denied, num_not_denied)
+    }
+
+    /// Count up the warnings, sorting into denied and only warned about.
+    /// Allowed warnings aren't counted. Returns `(denied, warned)`
     fn count_warnings(&self, report: &rustsec::Report) -> (u64, u64) {
         let mut num_denied: u64 = 0;
         let mut num_not_denied: u64 = 0;
 
-        for (kind, warnings) in report.warnings.iter() {
-            if self.deny_warning_kinds.contains(kind) {
-                num_denied += warnings.len() as u64;
-            } else {
-                num_not_denied += warnings.len() as u64;
+        for warning in report.warnings.values().flatten() {
+            match self.warning_action(warning) {
+                PolicyAction::Deny => num_denied += 1,
+                PolicyAction::Warn => num_not_denied += 1,
+                PolicyAction::Allow => (),
             }
         }
         (num_denied, num_not_denied)
     }
 
-    /// Print information about the given vulnerability
+    /// Get the action to take for a vulnerability: vulnerabilities are denied
+    /// unless the policy says otherwise
+    fn vulnerability_action(&self, vulnerability: &rustsec::Vulnerability) -> PolicyAction {
+        self.config
+            .policy
+            .vulnerability_action(
+                vulnerability.package.name.as_str(),
+                vulnerability.severity(),
+            )
+            .unwrap_or(PolicyAction::Deny)
+    }
+
+    /// Get the action to take for a warning: warnings of the kinds listed in
+    /// `deny` are denied unless the policy says otherwise
+    fn warning_action(&self, warning: &rustsec::Warning) -> PolicyAction {
+        self.config
+            .policy
+            .warning_action(warning.package.name.as_str(), warning.kind)
+            .unwrap_or(if self.deny_warning_kinds.contains(&warning.kind) {
+                PolicyAction::Deny
+            } else {
+                PolicyAction::Warn
+            })
+    }
+
+    /// Print the given vulnerabilities followed by the given warnings, in
+    /// groups if configured
+    fn print_findings(
+        &mut self,
+        vulnerabilities: &[&rustsec::Vulnerability],
+        warnings: &[&rustsec::Warning],
+        tree: &dependency::Tree,
+    ) {
+        let group_by = match self.config.group_by {
+            Some(group_by) => group_by,
+            None => {
+                self.print_vulnerabilities(vulnerabilities, tree);
+                for &warning in warnings {
+                    self.print_warning(warning, tree);
+                }
+                return;
+            }
+        };
+
+        let groups = group_findings(group_by, vulnerabilities, warnings, tree);
+
+        for (group, (vulnerabilities, warnings)) in groups {
+            match group {
+                FindingGroup::Severity(Reverse(severity)) => {
+                    status_info!("Severity", "{}", severity.as_str())
+                }
+                FindingGroup::WarningKind(kind) => status_info!("Warning", "{}", kind.as_str()),
+                FindingGroup::Packages(packages) if group_by == GroupBy::Package => {
+                    status_info!("Package", "{}", packages)
+                }
+                FindingGroup::Packages(packages) => status_info!("Dependency", "{}", packages),
+            }
+            println!();
+
+            self.print_vulnerabilities(&vulnerabilities, tree);
+            for warning in warnings {
+                self.print_warning(warning, tree);
+            }
+        }
+    }
+
+    /// Print information about the given vulnerabilities, grouping the
+    /// versions of a crate affected by the same advisory if configured
+    fn print_vulnerabilities(
+        &mut self,
+        vulnerabilities: &[&rustsec::Vulnerability],
+        tree: &dependency::Tree,
+    ) {
+        if !self.config.group_versions {
+            for &vulnerability in vulnerabilities {
+                self.print_vulnerability(&[vulnerability], tree);
+            }
+            return;
+        }
+
+        for group in VulnerabilityGroup::group(vulnerabilities.iter().copied()) {
+            self.print_vulnerability(&group, tree);
+        }
+    }
+
+    /// Print information about a vulnerability in one or more versions of a
+    /// crate, all affected by the same advisory
     fn print_vulnerability(
         &mut self,
-        vulnerability: &rustsec::Vulnerability,
+        versions: &[&rustsec::Vulnerability],
         tree: &dependency::Tree,
     ) {
-        self.print_attr(Red, "Crate:    ", &vulnerability.package.name);
-        self.print_attr(Red, "Version:  ", vulnerability.package.version.to_string());
-        self.print_metadata(&vulnerability.advisory, Red);
+        let vulnerability = versions[0];
+        let deny = versions
+            .iter()
+            .any(|vuln| self.vulnerability_action(vuln) == PolicyAction::Deny);
+        let color = self.warning_color(deny);
+
+        self.print_attr(color, "Crate:    ", &vulnerability.package.name);
+
+        if let [vulnerability] = versions {
+            self.print_attr(
+                color,
+                "Version:  ",
+                vulnerability.package.version.to_string(),
+            );
+            if let Some(release) = &vulnerability.release {
+                self.print_release(color, None, release);
+            }
+        } else {
+            let list: Vec<_> = versions
+                .iter()
+                .map(|vuln| vuln.package.version.to_string())
+                .collect();
+            self.print_attr(color, "Versions: ", list.join(", "));
+
+            for vuln in versions {
+                if let Some(release) = &vuln.release {
+                    self.print_release(color, Some(&vuln.package.version), release);
+                }
+            }
+        }
+
+        self.print_metadata(&vulnerability.advisory, color);
+
+        if let Some(severity_override) = &vulnerability.severity_override {
+            let mut value = format!("severity {}", severity_override.severity);
+            if let Some(reason) = &severity_override.reason {
+                value.push_str(&format!(" ({})", reason));
+            }
+            self.print_attr(color, "Override: ", value);
+        }
 
         if vulnerability.versions.patched().is_empty() {
-            self.print_attr(Red, "Solution: ", "No fixed upgrade is available!");
+            self.print_attr(color, "Solution: ", "No fixed upgrade is available!");
         } else {
             self.print_attr(
-                Red,
+                color,
                 "Solution: ",
                 format!(
                     "Upgrade to {}",
@@ -274,41 +1125,109 @@ impl Presenter {
             );
         }
 
-        self.print_tree(Red, &vulnerability.package, tree);
+        for vuln in versions {
+            if let Some(patched) = vuln.patched() {
+                if versions.len() == 1 {
+                    self.print_attr(color, "Fix:      ", patched.to_string());
+                } else {
+                    let fix = format!("{}: {}", vuln.package.version, patched);
+                    self.print_attr(color, "Fix:      ", fix);
+                }
+            }
+        }
+
+        for path in versions.iter().flat_map(|vuln| &vuln.paths) {
+            self.print_attr(color, "Path:     ", path.to_string());
+        }
+
+        // Several versions may be resolved by the same upgrade
+        let mut suggestions = vec![];
+
+        for suggestion in versions.iter().flat_map(|vuln| &vuln.suggestions) {
+            if !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
+            }
+        }
+
+        for suggestion in suggestions {
+            self.print_attr(color, "Action:   ", suggestion.to_string());
+        }
+
+        for vuln in versions {
+            self.print_tree(color, &vuln.package, tree);
+        }
         println!();
     }
 
     /// Print information about a given warning
     fn print_warning(&mut self, warning: &rustsec::Warning, tree: &dependency::Tree) {
-        let color = self.warning_color(self.deny_warning_kinds.contains(&warning.kind));
+        let color = self.warning_color(self.warning_action(warning) == PolicyAction::Deny);
 
         self.print_attr(color, "Crate:    ", &warning.package.name);
         self.print_attr(color, "Version:  ", warning.package.version.to_string());
+        if let Some(release) = &warning.release {
+            self.print_release(color, None, release);
+        }
         self.print_attr(color, "Warning:  ", warning.kind.as_str());
 
         if let Some(metadata) = &warning.advisory {
             self.print_metadata(metadata, color)
         }
 
+        if let Some(deprecation) = &warning.deprecation {
+            self.print_attr(color, "Use:      ", &deprecation.replacement);
+            self.print_attr(color, "Reason:   ", &deprecation.reason);
+
+            if let Some(url) = &deprecation.url {
+                self.print_attr(color, "URL:      ", url);
+            }
+        }
+
         self.print_tree(color, &warning.package, tree);
         println!();
     }
 
-    /// Get the color to use when displaying warnings
-    fn warning_color(&self, deny_warning: bool) -> Color {
-        if deny_warning {
-            Red
+    /// Get the color to use when displaying findings, if any
+    fn warning_color(&self, deny_warning: bool) -> Option<Color> {
+        let theme = &self.config.theme;
+        let color = if deny_warning {
+            theme.error_color()
         } else {
-            Yellow
-        }
+            theme.warning_color()
+        };
+
+        color.map(Color::from)
+    }
+
+    /// Display how far behind the latest release a package is, prefixed with
+    /// its version when several versions are listed together
+    fn print_release(
+        &self,
+        color: Option<Color>,
+        version: Option<&rustsec::Version>,
+        release: &ReleaseInfo,
+    ) {
+        self.print_attr(color, "Released: ", release_text(version, release));
     }
 
     /// Print a warning about a particular advisory
-    fn print_metadata(&self, metadata: &rustsec::advisory::Metadata, color: Color) {
+    fn print_metadata(&self, metadata: &rustsec::advisory::Metadata, color: Option<Color>) {
         self.print_attr(color, "Title:    ", &metadata.title);
         self.print_attr(color, "Date:     ", &metadata.date);
         self.print_attr(color, "ID:       ", &metadata.id);
 
+        if let Some(withdrawn) = &metadata.withdrawn {
+            self.print_attr(color, "Withdrawn:", withdrawn);
+        }
+
+        for superseded in &metadata.supersedes {
+            self.print_attr(color, "Supersedes:", superseded);
+        }
+
+        for successor in &metadata.superseded_by {
+            self.print_attr(color, "Superseded by:", successor);
+        }
+
         if metadata.license == License::CcBy40 {
             // We must preserve the original URL from the `url` field
             if let Some(url) = &metadata.url {
@@ -326,27 +1245,29 @@ impl Presenter {
             }
         }
 
-        if let Some(cvss) = &metadata.cvss {
+        if let Some(cvss) = metadata.primary_cvss() {
             self.print_attr(
                 color,
                 "Severity: ",
                 format!("{} ({})", cvss.score().value(), cvss.score().severity()),
             );
         }
+
+        for cwe in metadata.cwes() {
+            self.print_attr(color, "Weakness: ", cwe.to_string());
+        }
     }
 
     /// Display an attribute of a particular vulnerability
-    fn print_attr(&self, color: Color, attr: &str, content: impl AsRef<str>) {
-        terminal::status::Status::new()
-            .bold()
-            .color(color)
+    fn print_attr(&self, color: Option<Color>, attr: &str, content: impl AsRef<str>) {
+        status(color)
             .status(attr)
             .print_stdout(content.as_ref())
             .unwrap();
     }
 
     /// Print the inverse dependency tree to standard output
-    fn print_tree(&mut self, color: Color, package: &Package, tree: &dependency::Tree) {
+    fn print_tree(&mut self, color: Option<Color>, package: &Package, tree: &dependency::Tree) {
         // Only show the tree once per package
         if !self.displayed_packages.insert(Dependency::from(package)) {
             return;
@@ -356,20 +1277,490 @@ impl Presenter {
             return;
         }
 
-        terminal::status::Status::new()
-            .bold()
-            .color(color)
+        // Packages which aren't dependencies, like the standard library of
+        // scanned binaries, have no tree
+        let package_node = match tree.nodes().get(&Dependency::from(package)) {
+            Some(&node) => node,
+            None => return,
+        };
+
+        status(color)
             .status("Dependency tree:\n")
             .print_stdout("")
             .unwrap();
 
-        let package_node = tree.nodes()[&Dependency::from(package)];
-        tree.render(
+        let symbols = if self.config.theme.ascii {
+            Symbols::ascii()
+        } else {
+            Symbols::default()
+        };
+
+        tree.render_with_symbols(
             &mut io::stdout(),
             package_node,
             EdgeDirection::Incoming,
+            &symbols,
             false,
         )
         .unwrap();
     }
 }
+
+/// Group of findings when grouping is configured. Groups are printed in
+/// this order: vulnerabilities by severity, most severe first, then warnings
+/// by kind, then findings grouped by package names.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum FindingGroup {
+    /// Vulnerabilities of the given severity
+    Severity(Reverse<SeverityBucket>),
+
+    /// Warnings of the given kind
+    WarningKind(WarningKind),
+
+    /// Findings about a package, or pulled in by the direct dependencies
+    /// with the given (comma-separated) names
+    Packages(String),
+}
+
+/// Vulnerabilities and warnings in a [`FindingGroup`]
+type FindingGroupMembers<'a> = (Vec<&'a rustsec::Vulnerability>, Vec<&'a rustsec::Warning>);
+
+/// Sort findings into the groups they're printed in
+fn group_findings<'a>(
+    group_by: GroupBy,
+    vulnerabilities: &[&'a rustsec::Vulnerability],
+    warnings: &[&'a rustsec::Warning],
+    tree: &dependency::Tree,
+) -> Map<FindingGroup, FindingGroupMembers<'a>> {
+    let mut groups: Map<FindingGroup, FindingGroupMembers<'a>> = Map::new();
+
+    for &vulnerability in vulnerabilities {
+        let group = match group_by {
+            GroupBy::Severity => FindingGroup::Severity(Reverse(severity_of(vulnerability))),
+            GroupBy::Package => FindingGroup::Packages(vulnerability.package.name.to_string()),
+            GroupBy::DirectDep => {
+                FindingGroup::Packages(direct_dependencies(tree, &vulnerability.package))
+            }
+        };
+        groups.entry(group).or_default().0.push(vulnerability);
+    }
+
+    for &warning in warnings {
+        let group = match group_by {
+            GroupBy::Severity => FindingGroup::WarningKind(warning.kind),
+            GroupBy::Package => FindingGroup::Packages(warning.package.name.to_string()),
+            GroupBy::DirectDep => {
+                FindingGroup::Packages(direct_dependencies(tree, &warning.package))
+            }
+        };
+        groups.entry(group).or_default().1.push(warning);
+    }
+
+    groups
+}
+
+/// Get the effective severity of a vulnerability, taking overrides into account
+fn severity_of(vulnerability: &rustsec::Vulnerability) -> SeverityBucket {
+    SeverityBucket::from(vulnerability.severity())
+}
+
+/// Names of the direct dependencies of the root packages (e.g. workspace
+/// members) which pull in the given package, or the name of the package
+/// itself if it's a root
+fn direct_dependencies(tree: &dependency::Tree, package: &Package) -> String {
+    let mut names: Vec<String> = DependencyPath::shortest_from_roots(tree, package)
+        .iter()
+        .filter_map(|path| path.packages().get(1))
+        .map(|dependency| dependency.name.to_string())
+        .collect();
+
+    if names.is_empty() {
+        names.push(package.name.to_string());
+    }
+
+    names.sort();
+    names.dedup();
+    names.join(", ")
+}
+
+/// Format a total followed by its breakdown, e.g. `3 (high: 2, low: 1)`
+fn summary_counts<'a>(counts: impl Iterator<Item = (&'a str, u64)>) -> String {
+    let counts: Vec<_> = counts
+        .map(|(name, n)| (n, format!("{}: {}", name, n)))
+        .collect();
+    if counts.is_empty() {
+        return "0".to_owned();
+    }
+
+    let total: u64 = counts.iter().map(|(n, _)| n).sum();
+    let breakdown: Vec<_> = counts.into_iter().map(|(_, count)| count).collect();
+    format!("{} ({})", total, breakdown.join(", "))
+}
+
+/// Format counts which may overlap, e.g. `memory-corruption: 2, thread-safety: 1`
+fn aggregate_counts<K: std::fmt::Display>(counts: &Map<K, usize>) -> String {
+    let counts: Vec<_> = counts
+        .iter()
+        .map(|(key, n)| format!("{}: {}", key, n))
+        .collect();
+    counts.join(", ")
+}
+
+/// Print a line of newline-delimited JSON
+fn print_ndjson<T: Serialize>(record: &T) {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, record).unwrap();
+    writeln!(stdout).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Lines of newline-delimited JSON output for an artifact report: one for
+/// each of its findings
+#[cfg(feature = "binary-scanning")]
+fn ndjson_findings(report: &ArtifactReport) -> Vec<NdjsonFinding<'_>> {
+    let vulnerabilities = report
+        .vulnerabilities
+        .iter()
+        .map(ArtifactFinding::Vulnerability);
+    let warnings = report.warnings.iter().map(ArtifactFinding::Warning);
+
+    vulnerabilities
+        .chain(warnings)
+        .map(|finding| NdjsonFinding {
+            target: &report.artifact,
+            finding,
+        })
+        .collect()
+}
+
+/// Bold status in the given color, if any
+fn status(color: Option<Color>) -> terminal::status::Status {
+    let status = terminal::status::Status::new().bold();
+
+    match color {
+        Some(color) => status.color(color),
+        None => status,
+    }
+}
+
+/// Describe how far behind the latest release a package is
+fn release_text(version: Option<&rustsec::Version>, release: &ReleaseInfo) -> String {
+    let newer = match (release.newer_releases, &release.latest) {
+        (0, _) => "latest release".to_owned(),
+        (1, Some(latest)) => format!("1 newer release (latest: {})", latest),
+        (count, Some(latest)) => format!("{} newer releases (latest: {})", count, latest),
+        (count, None) => format!("{} newer releases", count),
+    };
+
+    let text = match &release.released {
+        Some(date) => format!("{}, {}", date, newer),
+        None => newer,
+    };
+
+    match version {
+        Some(version) => format!("{}: {}", version, text),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "yanked"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    /// Critical advisory about `vulnerable`
+    const ADVISORY: &str = r#"```toml
+id = "RUSTSEC-2001-2101"
+package = "vulnerable"
+date = "2001-02-03"
+cvss = "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H"
+
+[versions]
+patched = [">= 2.0.0"]
+```
+
+# Remote code execution
+"#;
+
+    const VULNERABLE_LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "vulnerable",
+ "wrapper",
+]
+
+[[package]]
+name = "tool"
+version = "0.1.0"
+dependencies = [
+ "wrapper",
+]
+
+[[package]]
+name = "vulnerable"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "wrapper"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "vulnerable",
+]
+"#;
+
+    /// Report with a critical vulnerability in `vulnerable` and `yanked`
+    /// warnings about `vulnerable` and `wrapper`
+    fn vulnerable_report() -> (Lockfile, rustsec::Report) {
+        let advisory = ADVISORY.parse().unwrap();
+        let database = rustsec::Database::from_advisories(vec![advisory]).unwrap();
+        let lockfile: Lockfile = VULNERABLE_LOCKFILE.parse().unwrap();
+        let mut report = rustsec::Report::generate(&database, &lockfile, &Default::default());
+
+        report.warnings.insert(
+            WarningKind::Yanked,
+            lockfile.packages[2..]
+                .iter()
+                .map(|package| {
+                    rustsec::Warning::new(WarningKind::Yanked, package, None, None, None)
+                })
+                .collect(),
+        );
+
+        (lockfile, report)
+    }
+
+    #[test]
+    fn summary_counts_with_breakdown() {
+        assert_eq!(summary_counts(std::iter::empty()), "0");
+        assert_eq!(
+            summary_counts([("high", 2), ("low", 1)].into_iter()),
+            "3 (high: 2, low: 1)"
+        );
+    }
+
+    #[test]
+    fn finding_counts() {
+        let (_, report) = vulnerable_report();
+
+        let presenter = Presenter::new(&OutputConfig::default());
+        let counts = presenter.finding_counts(&report);
+        assert_eq!(
+            counts.severities,
+            Map::from([(SeverityBucket::Critical, 1)])
+        );
+        assert!(counts.vulnerabilities_denied);
+        assert_eq!(counts.vulnerabilities.len(), 1);
+        assert_eq!(counts.kinds, Map::from([(WarningKind::Yanked, 2)]));
+        assert!(!counts.warnings_denied);
+
+        // Allowed findings aren't counted, and the policy decides which
+        // findings are denied
+        let config: OutputConfig = toml::from_str(
+            r#"
+            deny = ["yanked"]
+
+            [policy.severity]
+            critical = "warn"
+
+            [policy.packages.wrapper.warnings]
+            yanked = "allow"
+            "#,
+        )
+        .unwrap();
+
+        let counts = Presenter::new(&config).finding_counts(&report);
+        assert_eq!(
+            counts.severities,
+            Map::from([(SeverityBucket::Critical, 1)])
+        );
+        assert!(!counts.vulnerabilities_denied);
+        assert_eq!(counts.kinds, Map::from([(WarningKind::Yanked, 1)]));
+        assert!(counts.warnings_denied);
+    }
+
+    /// Vulnerabilities are grouped by severity, most severe first, ahead of
+    /// warnings and packages
+    #[test]
+    fn finding_group_order() {
+        let mut groups = vec![
+            FindingGroup::Packages("app".to_owned()),
+            FindingGroup::WarningKind(WarningKind::Yanked),
+            FindingGroup::Severity(Reverse(SeverityBucket::Low)),
+            FindingGroup::WarningKind(WarningKind::Unmaintained),
+            FindingGroup::Severity(Reverse(SeverityBucket::Critical)),
+            FindingGroup::Severity(Reverse(SeverityBucket::Unscored)),
+        ];
+        groups.sort();
+
+        assert_eq!(
+            groups,
+            [
+                FindingGroup::Severity(Reverse(SeverityBucket::Critical)),
+                FindingGroup::Severity(Reverse(SeverityBucket::Low)),
+                FindingGroup::Severity(Reverse(SeverityBucket::Unscored)),
+                FindingGroup::WarningKind(WarningKind::Unmaintained),
+                FindingGroup::WarningKind(WarningKind::Yanked),
+                FindingGroup::Packages("app".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn direct_dependency_names() {
+        let (lockfile, _) = vulnerable_report();
+        let tree = lockfile.dependency_tree().unwrap();
+        let names = |index: usize| direct_dependencies(&tree, &lockfile.packages[index]);
+
+        // `app` depends on `vulnerable` directly, and `tool` through `wrapper`
+        assert_eq!(names(2), "vulnerable, wrapper");
+        assert_eq!(names(3), "wrapper");
+
+        // Roots are named after themselves
+        assert_eq!(names(0), "app");
+    }
+
+    #[test]
+    fn grouped_findings() {
+        let (lockfile, report) = vulnerable_report();
+        let tree = lockfile.dependency_tree().unwrap();
+        let vulnerabilities: Vec<_> = report.vulnerabilities.list.iter().collect();
+        let warnings: Vec<_> = report.warnings.values().flatten().collect();
+        let groups = |group_by| {
+            group_findings(group_by, &vulnerabilities, &warnings, &tree)
+                .into_iter()
+                .map(|(group, (vulnerabilities, warnings))| {
+                    (group, vulnerabilities.len(), warnings.len())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            groups(GroupBy::Severity),
+            [
+                (
+                    FindingGroup::Severity(Reverse(SeverityBucket::Critical)),
+                    1,
+                    0
+                ),
+                (FindingGroup::WarningKind(WarningKind::Yanked), 0, 2),
+            ]
+        );
+        assert_eq!(
+            groups(GroupBy::Package),
+            [
+                (FindingGroup::Packages("vulnerable".to_owned()), 1, 1),
+                (FindingGroup::Packages("wrapper".to_owned()), 0, 1),
+            ]
+        );
+        assert_eq!(
+            groups(GroupBy::DirectDep),
+            [
+                (
+                    FindingGroup::Packages("vulnerable, wrapper".to_owned()),
+                    1,
+                    1
+                ),
+                (FindingGroup::Packages("wrapper".to_owned()), 0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn release_texts() {
+        let mut release = ReleaseInfo::default();
+        assert_eq!(release_text(None, &release), "latest release");
+
+        release.newer_releases = 2;
+        release.latest = Some("1.2.0".parse().unwrap());
+        assert_eq!(
+            release_text(None, &release),
+            "2 newer releases (latest: 1.2.0)"
+        );
+
+        // Grouped versions are told apart by their version
+        let version = "1.0.0".parse().unwrap();
+        assert_eq!(
+            release_text(Some(&version), &release),
+            "1.0.0: 2 newer releases (latest: 1.2.0)"
+        );
+    }
+
+    #[test]
+    fn ndjson_records() {
+        let record = NdjsonRecord {
+            target: "target/release/app".to_owned(),
+            report: None,
+            error: Some("no dependency information found"),
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"target":"target/release/app","error":"no dependency information found"}"#
+        );
+
+        let database = rustsec::Database::from_advisories(vec![]).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let report = rustsec::Report::generate(&database, &lockfile, &Default::default());
+        let record = NdjsonRecord {
+            target: "Cargo.lock".to_owned(),
+            report: Some(&report),
+            error: None,
+        };
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["target"], "Cargo.lock");
+        assert_eq!(json["report"]["vulnerabilities"]["found"], false);
+        assert!(json.get("error").is_none());
+    }
+
+    #[cfg(feature = "binary-scanning")]
+    #[test]
+    fn ndjson_artifact_findings() {
+        use crate::artifact_group::Finding;
+
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let yanked =
+            rustsec::Warning::new(WarningKind::Yanked, &lockfile.packages[0], None, None, None);
+
+        let report = ArtifactReport {
+            artifact: "app".to_owned(),
+            platforms: vec![
+                "x86_64-unknown-linux-gnu".to_owned(),
+                "aarch64-apple-darwin".to_owned(),
+            ],
+            vulnerabilities: vec![],
+            warnings: vec![Finding {
+                finding: yanked,
+                platforms: vec!["aarch64-apple-darwin".to_owned()],
+            }],
+        };
+
+        let lines: Vec<_> = ndjson_findings(&report)
+            .iter()
+            .map(|record| serde_json::to_value(record).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["target"], "app");
+        assert_eq!(lines[0]["warning"]["kind"], "yanked");
+        assert_eq!(lines[0]["warning"]["package"]["name"], "yanked");
+        assert_eq!(lines[0]["warning"]["platforms"][0], "aarch64-apple-darwin");
+
+        let report = ArtifactReport {
+            warnings: vec![],
+            ..report
+        };
+        assert!(ndjson_findings(&report).is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+//! Progress bar shown on STDERR during long-running operations, such as
+//! fetching the advisory database or the crates.io index
+
+use abscissa_core::terminal;
+use is_terminal::IsTerminal;
+use rustsec::progress::{Counter, Progress};
+use std::{
+    io::{self, Write},
+    sync::{atomic::Ordering, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use termcolor::WriteColor;
+
+/// How often the progress bar is redrawn
+const TICK: Duration = Duration::from_millis(100);
+
+/// Width of the bar itself, in characters
+const WIDTH: usize = 25;
+
+/// Whether a progress bar is drawn on the last line of STDERR. The lock is
+/// held while the bar is drawn, and while other output is [`suspend`]ed.
+static DRAWN: Mutex<bool> = Mutex::new(false);
+
+/// Run `f` with the progress bar (if any) cleared and not redrawn until it
+/// returns, so that status lines printed by it aren't mixed into the bar
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    let mut drawn = DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+    if *drawn {
+        clear();
+        *drawn = false;
+    }
+
+    f()
+}
+
+/// Clear the line the progress bar is drawn on
+fn clear() {
+    let mut stderr = terminal::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[2K");
+    let _ = stderr.flush();
+}
+
+/// Progress bar which is redrawn in place on STDERR
+pub struct ProgressBar {
+    /// Status shown before the bar, e.g. `Fetching`
+    label: &'static str,
+
+    /// Current stage of the operation, if one is in progress
+    stage: Mutex<Option<Stage>>,
+}
+
+/// Stage of an operation
+struct Stage {
+    name: String,
+    total: Option<usize>,
+    done: Counter,
+}
+
+impl Stage {
+    /// Render the progress of this stage, e.g. `[=====     ] 5/10 name`
+    fn line(&self) -> String {
+        let done = self.done.load(Ordering::Relaxed);
+        match self.total {
+            Some(total) if total > 0 => {
+                let filled = WIDTH * done.min(total) / total;
+                format!(
+                    "[{}{}] {}/{} {}",
+                    "=".repeat(filled),
+                    " ".repeat(WIDTH - filled),
+                    done,
+                    total,
+                    self.name
+                )
+            }
+            _ => format!("{} {}", done, self.name),
+        }
+    }
+}
+
+impl ProgressBar {
+    /// Create a progress bar with the given status label, unless STDERR
+    /// isn't a terminal (e.g. in CI logs, where it would only add noise) or
+    /// colors are disabled (e.g. with `--color never`), since the bar is
+    /// redrawn with escape codes
+    pub fn new(label: &'static str) -> Option<Arc<Self>> {
+        if !io::stderr().is_terminal() || !terminal::stderr().supports_color() {
+            return None;
+        }
+
+        let bar = Arc::new(Self {
+            label,
+            stage: Mutex::new(None),
+        });
+
+        // Redraw the bar until it is dropped
+        let weak = Arc::downgrade(&bar);
+        thread::spawn(move || {
+            while let Some(bar) = weak.upgrade() {
+                bar.draw();
+                drop(bar);
+                thread::sleep(TICK);
+            }
+        });
+
+        Some(bar)
+    }
+
+    /// Draw the current stage, if any
+    fn draw(&self) {
+        let mut drawn = DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+        let stage = self.stage.lock().unwrap();
+        let stage = match stage.as_ref() {
+            Some(stage) => stage,
+            None => return,
+        };
+
+        let mut stderr = terminal::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{:>12} {}", self.label, stage.line());
+        let _ = stderr.flush();
+        *drawn = true;
+    }
+}
+
+impl Progress for ProgressBar {
+    fn stage(&self, name: &str, total: Option<usize>, done: Counter) {
+        *self.stage.lock().unwrap() = Some(Stage {
+            name: name.to_owned(),
+            total,
+            done,
+        });
+    }
+
+    fn finish(&self) {
+        // The stage is taken before clearing the bar, so it isn't redrawn
+        let mut drawn = DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+        if self.stage.lock().unwrap().take().is_some() && *drawn {
+            clear();
+            *drawn = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(total: Option<usize>, done: usize) -> Stage {
+        Stage {
+            name: "objects".to_owned(),
+            total,
+            done: Counter::new(done.into()),
+        }
+    }
+
+    #[test]
+    fn stage_line() {
+        assert_eq!(
+            stage(Some(10), 5).line(),
+            format!("[{}{}] 5/10 objects", "=".repeat(12), " ".repeat(13))
+        );
+        assert_eq!(
+            stage(Some(10), 20).line(),
+            format!("[{}] 20/10 objects", "=".repeat(WIDTH))
+        );
+        assert_eq!(stage(None, 3).line(), "3 objects");
+        assert_eq!(stage(Some(0), 0).line(), "0 objects");
+    }
+}
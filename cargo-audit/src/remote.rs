@@ -0,0 +1,345 @@
+//! Fetching lockfiles from remote hosts (`cargo audit remote`)
+//!
+//! Files are copied with `scp`. Paths which only consist of characters no
+//! shell interprets are copied with the default protocol of the local `scp`,
+//! which only needs an SSH server with SFTP enabled on the remote host when
+//! it's OpenSSH 9.0 or newer.
+//!
+//! Depending on its version and protocol, `scp` may pass remote paths through
+//! the shell of the remote host. Other paths are therefore quoted, and copied
+//! with the original `scp` protocol (`-O`) so that they're always unquoted by
+//! a shell. This needs `scp` to be installed on the remote host, and OpenSSH
+//! 8.7 or newer locally.
+
+use crate::error::{Error, ErrorKind};
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+/// Message `scp` prints when the remote file doesn't exist, with either
+/// protocol
+const NO_SUCH_FILE: &str = "No such file or directory";
+
+/// Name of `Cargo.lock`
+const CARGO_LOCK_FILE: &str = "Cargo.lock";
+
+/// Name of `Cargo.toml`
+const CARGO_TOML_FILE: &str = "Cargo.toml";
+
+/// Location of a lockfile on a remote host, given as
+/// `ssh://[user@]host[:port]/path` or `sftp://[user@]host[:port]/path`.
+/// IPv6 addresses are enclosed in brackets, e.g. `ssh://[::1]:2222/path`.
+///
+/// The path may point to a `Cargo.lock` file or to the directory containing it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemoteUrl {
+    /// Host to connect to, optionally prefixed with `user@`
+    destination: String,
+
+    /// Port the SSH server listens on, if not the default
+    port: Option<u16>,
+
+    /// Directory on the remote host containing `Cargo.lock`
+    dir: String,
+
+    /// Name of the lockfile within `dir`
+    lockfile: String,
+}
+
+impl RemoteUrl {
+    /// Path to the lockfile on the remote host
+    pub fn lockfile_path(&self) -> String {
+        format!("{}/{}", self.dir, self.lockfile)
+    }
+
+    /// Path to `Cargo.toml` on the remote host
+    pub fn manifest_path(&self) -> String {
+        format!("{}/{}", self.dir, CARGO_TOML_FILE)
+    }
+
+    /// Copy a file from the remote host to a local path
+    fn copy(&self, remote_path: &str, local_path: &Path) -> Result<(), Error> {
+        if self.copy_if_exists(remote_path, local_path)? {
+            Ok(())
+        } else {
+            Err(ErrorKind::Io
+                .context(format!(
+                    "{}:{} doesn't exist",
+                    self.destination, remote_path
+                ))
+                .into())
+        }
+    }
+
+    /// Copy a file from the remote host to a local path, returning `false`
+    /// if it doesn't exist on the remote host
+    fn copy_if_exists(&self, remote_path: &str, local_path: &Path) -> Result<bool, Error> {
+        let mut command = Command::new("scp");
+        command.arg("-q");
+
+        if let Some(port) = self.port {
+            command.arg("-P").arg(port.to_string());
+        }
+
+        let remote_arg = match shell_quote(remote_path) {
+            Some(quoted) => {
+                command.arg("-O");
+                quoted
+            }
+            None => remote_path.to_owned(),
+        };
+
+        // Errors are captured to tell missing files apart, while passwords
+        // and passphrases are still prompted for on the terminal
+        let output = command
+            .arg("--")
+            .arg(format!("{}:{}", self.destination, remote_arg))
+            .arg(local_path)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| {
+                ErrorKind::Io.context(format!(
+                    "couldn't run `scp` to fetch files from the remote host: {}",
+                    e
+                ))
+            })?;
+
+        if output.status.success() {
+            return Ok(true);
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains(NO_SUCH_FILE) {
+            return Ok(false);
+        }
+
+        Err(ErrorKind::Io
+            .context(format!(
+                "couldn't fetch {}:{}: {}",
+                self.destination,
+                remote_path,
+                stderr.trim()
+            ))
+            .into())
+    }
+}
+
+impl FromStr for RemoteUrl {
+    type Err = Error;
+
+    fn from_str(url: &str) -> Result<Self, Error> {
+        let rest = url
+            .strip_prefix("ssh://")
+            .or_else(|| url.strip_prefix("sftp://"))
+            .ok_or_else(|| {
+                Error::from(ErrorKind::Parse.context(format!(
+                    "unsupported URL `{}` (expected ssh:// or sftp://)",
+                    url
+                )))
+            })?;
+
+        let (authority, path) = match rest.find('/') {
+            Some(idx) if idx > 0 && rest.len() > idx + 1 => rest.split_at(idx),
+            _ => {
+                return Err(ErrorKind::Parse
+                    .context(format!("URL `{}` is missing a host or path", url))
+                    .into())
+            }
+        };
+
+        // Keep the user when splitting off the port, e.g. `user@host:2222`
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user), host_port),
+            None => (None, authority),
+        };
+
+        let invalid = |what: &str| {
+            Error::from(ErrorKind::Parse.context(format!("invalid {} in URL `{}`", what, url)))
+        };
+
+        // IPv6 addresses are enclosed in brackets, which `scp` expects too
+        let (host, port) = if host_port.starts_with('[') {
+            let end = host_port.find(']').ok_or_else(|| invalid("IPv6 address"))?;
+            let (host, rest) = host_port.split_at(end + 1);
+            match rest.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if rest.is_empty() => (host, None),
+                None => return Err(invalid("host")),
+            }
+        } else {
+            match host_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            }
+        };
+
+        if host.is_empty() || host == "[]" {
+            return Err(invalid("host"));
+        }
+
+        let port = port
+            .map(|port| port.parse::<u16>().map_err(|_| invalid("port")))
+            .transpose()?;
+
+        let destination = match user {
+            Some(user) => format!("{}@{}", user, host),
+            None => host.to_owned(),
+        };
+
+        // `ssh://host/~/app` refers to a path relative to the home directory
+        let path = path.strip_prefix("/~/").map_or(path, |relative| relative);
+        let path = path.trim_end_matches('/');
+
+        let (dir, lockfile) = if path.ends_with(".lock") {
+            path.rsplit_once('/').unwrap_or((".", path))
+        } else {
+            (path, CARGO_LOCK_FILE)
+        };
+
+        Ok(Self {
+            destination,
+            port,
+            dir: dir.to_owned(),
+            lockfile: lockfile.to_owned(),
+        })
+    }
+}
+
+impl Display for RemoteUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.destination, port)?,
+            None => write!(f, "{}", self.destination)?,
+        }
+
+        write!(f, ":{}", self.lockfile_path())
+    }
+}
+
+/// Fetch the lockfile (and optionally `Cargo.toml`) into the given local
+/// directory, returning the path to the local copy of the lockfile.
+///
+/// If `Cargo.toml` was fetched but the remote host has no lockfile, one is
+/// generated locally from the manifest. Other failures to fetch the lockfile
+/// are reported as errors.
+pub fn fetch(url: &RemoteUrl, dir: &Path, with_manifest: bool) -> Result<PathBuf, Error> {
+    let lockfile_path = dir.join(CARGO_LOCK_FILE);
+
+    if !with_manifest {
+        url.copy(&url.lockfile_path(), &lockfile_path)?;
+        return Ok(lockfile_path);
+    }
+
+    let manifest_path = dir.join(CARGO_TOML_FILE);
+    url.copy(&url.manifest_path(), &manifest_path)?;
+
+    if !url.copy_if_exists(&url.lockfile_path(), &lockfile_path)? {
+        generate_lockfile(&manifest_path)?;
+    }
+
+    Ok(lockfile_path)
+}
+
+/// Quote a remote path for the shell of the remote host, or `None` if it only
+/// consists of characters which no shell interprets
+fn shell_quote(path: &str) -> Option<String> {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "/._-+,=@%".contains(c);
+
+    if path.chars().all(is_plain) {
+        None
+    } else {
+        Some(format!("'{}'", path.replace('\'', "'\\''")))
+    }
+}
+
+/// Run `cargo generate-lockfile` for the given manifest
+fn generate_lockfile(manifest_path: &Path) -> Result<(), Error> {
+    let status = Command::new("cargo")
+        .arg("generate-lockfile")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .status()
+        .map_err(|e| {
+            ErrorKind::Io.context(format!(
+                "couldn't run `cargo generate-lockfile` for the remote manifest: {}",
+                e
+            ))
+        })?;
+
+    if !status.success() {
+        return Err(ErrorKind::Io
+            .context(
+                "the remote host has no lockfile, and one couldn't be generated from its Cargo.toml",
+            )
+            .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> RemoteUrl {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_urls() {
+        let url = parse("ssh://deploy@example.com/srv/app");
+        assert_eq!(url.destination, "deploy@example.com");
+        assert_eq!(url.port, None);
+        assert_eq!(url.lockfile_path(), "/srv/app/Cargo.lock");
+        assert_eq!(url.manifest_path(), "/srv/app/Cargo.toml");
+
+        let url = parse("sftp://example.com:2222/~/app/Cargo.lock");
+        assert_eq!(url.destination, "example.com");
+        assert_eq!(url.port, Some(2222));
+        assert_eq!(url.lockfile_path(), "app/Cargo.lock");
+    }
+
+    #[test]
+    fn parse_ipv6_urls() {
+        let url = parse("ssh://deploy@[::1]:2222/srv/app/");
+        assert_eq!(url.destination, "deploy@[::1]");
+        assert_eq!(url.port, Some(2222));
+        assert_eq!(url.lockfile_path(), "/srv/app/Cargo.lock");
+
+        let url = parse("ssh://[fe80::1]/srv/app");
+        assert_eq!(url.destination, "[fe80::1]");
+        assert_eq!(url.port, None);
+    }
+
+    #[test]
+    fn reject_invalid_urls() {
+        for url in [
+            "https://example.com/srv/app",
+            "ssh://example.com",
+            "ssh:///srv/app",
+            "ssh://example.com:port/srv/app",
+            "ssh://[::1/srv/app",
+            "ssh://::1/srv/app",
+        ] {
+            assert!(url.parse::<RemoteUrl>().is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn quote_remote_paths() {
+        assert_eq!(shell_quote("/srv/app-1.0/Cargo.lock"), None);
+        assert_eq!(
+            shell_quote("/srv/my app/Cargo.lock").as_deref(),
+            Some("'/srv/my app/Cargo.lock'")
+        );
+        assert_eq!(
+            shell_quote("/srv/$(reboot)'/Cargo.lock").as_deref(),
+            Some("'/srv/$(reboot)'\\''/Cargo.lock'")
+        );
+    }
+}
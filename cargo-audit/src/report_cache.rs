@@ -0,0 +1,371 @@
+//! Cache of the last report for each lockfile, so that auditing an unchanged
+//! project against an unchanged advisory database with the same
+//! configuration (e.g. once per package of a monorepo) skips generating the
+//! report again.
+//!
+//! The cache is only consulted once the advisory database has been loaded
+//! (and fetched, if enabled), so the checks for stale databases always run.
+//! Cached reports are keyed by the contents of the lockfile and of the
+//! manifests of the workspace next to it and of its path dependencies
+//! (which determine crate features and dependency kinds), the commit of the
+//! advisory database and the configuration. They also expire after [`MAX_AGE`], which bounds how stale
+//! the sources which aren't part of the key can be, such as the crates.io
+//! index used to find yanked crates.
+
+use crate::config::{AuditConfig, OsvMode};
+use rustsec::{Database, Report};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use toml::{Table, Value};
+
+/// How long cached reports are used for
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Cache of the last report for a lockfile
+pub struct ReportCache {
+    /// File the report is cached in
+    path: PathBuf,
+
+    /// What the report for the lockfile depends on
+    key: CacheKey,
+}
+
+/// What a cached report depends on
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct CacheKey {
+    /// Version of `cargo audit` which generated the report
+    version: String,
+
+    /// Contents of the lockfile
+    lockfile: String,
+
+    /// Paths and contents of the `Cargo.toml` files of the project
+    manifests: Vec<(PathBuf, String)>,
+
+    /// Commit of the advisory database
+    database: String,
+
+    /// Configuration, serialized as JSON
+    config: String,
+}
+
+/// Cached report along with what it depends on
+#[derive(Deserialize, Serialize)]
+struct CacheEntry {
+    key: CacheKey,
+
+    /// When the report was generated, in seconds since the UNIX epoch
+    created: u64,
+
+    report: Report,
+}
+
+impl ReportCache {
+    /// Open the cache for the lockfile at the given path, audited against
+    /// the given (already loaded) advisory database.
+    ///
+    /// Returns `None` if caching is disabled, the database isn't a git
+    /// checkout, or the report doesn't only depend on the local advisory
+    /// database (e.g. when querying osv.dev or GitHub). Strict mode also
+    /// bypasses the cache, since the checks it makes fatal run while the
+    /// report is generated.
+    pub fn open(config: &AuditConfig, lockfile_path: &Path, database: &Database) -> Option<Self> {
+        let database_config = &config.database;
+        if !database_config.cache_enabled()
+            || database_config.embedded
+            || database_config.osv != OsvMode::Off
+            || database_config.ghsa
+            || database_config.native_advisories.is_some()
+            || database_config.vcr.is_some()
+            || config.output.strict
+            || lockfile_path == Path::new("-")
+        {
+            return None;
+        }
+
+        let commit = database.latest_commit()?;
+        let database_path = database_config
+            .path
+            .clone()
+            .unwrap_or_else(rustsec::repository::git::Repository::default_path);
+
+        let project_dir = match lockfile_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let key = CacheKey {
+            version: crate::VERSION.to_owned(),
+            lockfile: fs::read_to_string(lockfile_path).ok()?,
+            manifests: manifests(project_dir),
+            database: commit.commit_id.to_hex(),
+            config: serde_json::to_string(config).ok()?,
+        };
+
+        // One report is kept per lockfile
+        let lockfile_path =
+            fs::canonicalize(lockfile_path).unwrap_or_else(|_| lockfile_path.to_owned());
+
+        let mut dir = database_path.into_os_string();
+        dir.push(".reports");
+        let path = PathBuf::from(dir).join(format!(
+            "{:016x}.json",
+            stable_hash(lockfile_path.to_string_lossy().as_bytes())
+        ));
+
+        Some(Self { path, key })
+    }
+
+    /// Load the cached report, if it is still valid
+    pub fn load(&self) -> Option<Report> {
+        let json = fs::read_to_string(&self.path).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&json).ok()?;
+
+        if entry.key != self.key || now().saturating_sub(entry.created) > MAX_AGE.as_secs() {
+            return None;
+        }
+
+        Some(entry.report)
+    }
+
+    /// Store the report generated for the lockfile. This is only a
+    /// performance optimization, so errors are ignored.
+    pub fn store(&self, report: &Report) {
+        let entry = CacheEntry {
+            key: self.key.clone(),
+            created: now(),
+            report: report.clone(),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        if let Ok(json) = serde_json::to_string(&entry) {
+            let _ = fs::write(&self.path, json);
+        }
+    }
+}
+
+/// Find the `Cargo.toml` files the report depends on: the one in the given
+/// directory, and those of the workspace members and path dependencies it
+/// declares, transitively. Sorted by path.
+fn manifests(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut manifests = BTreeMap::new();
+    let mut dirs = vec![dir.to_owned()];
+
+    while let Some(dir) = dirs.pop() {
+        // Path dependencies may refer back to each other through `..`
+        let dir = match fs::canonicalize(&dir) {
+            Ok(dir) => dir,
+            Err(_) => continue,
+        };
+
+        let path = dir.join("Cargo.toml");
+        if manifests.contains_key(&path) {
+            continue;
+        }
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+
+        if let Ok(manifest) = toml::from_str::<Table>(&contents) {
+            for local in local_packages(&manifest) {
+                match local.strip_suffix("/*") {
+                    // Members are commonly given as all subdirectories
+                    Some(parent) => dirs.extend(
+                        fs::read_dir(dir.join(parent))
+                            .into_iter()
+                            .flatten()
+                            .flatten()
+                            .map(|entry| entry.path()),
+                    ),
+                    None => dirs.push(dir.join(local)),
+                }
+            }
+        }
+
+        manifests.insert(path, contents);
+    }
+
+    manifests.into_iter().collect()
+}
+
+/// Get the directories of the workspace members and path dependencies
+/// declared in a manifest, relative to it
+fn local_packages(manifest: &Table) -> Vec<&str> {
+    const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+    let workspace = manifest.get("workspace").and_then(Value::as_table);
+    let mut tables: Vec<&Table> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|key| manifest.get(*key))
+        .chain(workspace.and_then(|workspace| workspace.get("dependencies")))
+        .chain(manifest.get("replace"))
+        .filter_map(Value::as_table)
+        .collect();
+
+    // Target-specific dependencies and patches are nested one level deeper
+    for target in subtables(manifest, "target") {
+        tables.extend(
+            DEPENDENCY_TABLES
+                .iter()
+                .filter_map(|key| target.get(*key))
+                .filter_map(Value::as_table),
+        );
+    }
+    tables.extend(subtables(manifest, "patch"));
+
+    let members = workspace
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+
+    tables
+        .into_iter()
+        .flat_map(Table::values)
+        .filter_map(|dependency| dependency.get("path"))
+        .filter_map(Value::as_str)
+        .chain(members)
+        .collect()
+}
+
+/// Get the tables nested in the table with the given key, if any
+fn subtables<'a>(table: &'a Table, key: &str) -> impl Iterator<Item = &'a Table> {
+    table
+        .get(key)
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(Table::values)
+        .filter_map(Value::as_table)
+}
+
+/// 64-bit FNV-1a hash, which unlike the standard library's hashers is
+/// guaranteed to be the same across Rust versions and platforms
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Current time in seconds since the UNIX epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::{report::Settings, Lockfile};
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "example"
+version = "0.1.0"
+"#;
+
+    fn key(manifest: &str) -> CacheKey {
+        CacheKey {
+            version: crate::VERSION.to_owned(),
+            lockfile: LOCKFILE.to_owned(),
+            manifests: vec![(PathBuf::from("Cargo.toml"), manifest.to_owned())],
+            database: "0123456789abcdef".to_owned(),
+            config: "{}".to_owned(),
+        }
+    }
+
+    fn report() -> Report {
+        let database = Database::from_advisories(vec![]).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        Report::generate(&database, &lockfile, &Settings::default())
+    }
+
+    #[test]
+    fn stable_hash_is_fnv1a() {
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn load_stored_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reports").join("report.json");
+
+        let cache = ReportCache {
+            path: path.clone(),
+            key: key("[package]\nname = \"example\"\n"),
+        };
+        assert!(cache.load().is_none());
+
+        cache.store(&report());
+        assert!(cache.load().is_some());
+
+        // Editing a manifest invalidates the cached report
+        let edited = ReportCache {
+            path,
+            key: key("[package]\nname = \"example\"\n[features]\ndefault = [\"std\"]\n"),
+        };
+        assert!(edited.load().is_none());
+    }
+
+    #[test]
+    fn find_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+
+        let files = [
+            (
+                "Cargo.toml",
+                "[workspace]\nmembers = [\"crates/*\", \"app\"]\n",
+            ),
+            (
+                "app/Cargo.toml",
+                "[target.'cfg(unix)'.dependencies]\nlocal = { path = \"../../local\" }\n",
+            ),
+            (
+                "crates/a/Cargo.toml",
+                "[dependencies]\napp = { path = \"../../app\" }\n",
+            ),
+            ("crates/b/Cargo.toml", "[package]\nname = \"b\"\n"),
+            ("vendor/c/Cargo.toml", "[package]\nname = \"c\"\n"),
+            ("target/package/Cargo.toml", "[package]\nname = \"d\"\n"),
+        ];
+
+        let project = root.join("project");
+        for (path, contents) in files {
+            let path = project.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::create_dir_all(root.join("local")).unwrap();
+        fs::write(root.join("local/Cargo.toml"), "").unwrap();
+
+        let paths: Vec<_> = manifests(&project)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                root.join("local/Cargo.toml"),
+                project.join("Cargo.toml"),
+                project.join("app/Cargo.toml"),
+                project.join("crates/a/Cargo.toml"),
+                project.join("crates/b/Cargo.toml"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Organization-wide policy files shared between projects
+//!
+//! `policy = "https://..."` in `audit.toml` points at a file in the same
+//! format as `audit.toml`, whose policy settings are merged under the local
+//! configuration (see [`AuditConfig::merge_policy`]). This keeps ignored
+//! advisories, severity thresholds and warning dispositions in sync across
+//! many repositories.
+//!
+//! A shared policy can ignore advisories, so it must either be fetched over
+//! HTTPS or be pinned with `policy_sha256`.
+//!
+//! Fetched policy files are cached in `$CARGO_HOME/audit-policies`. The cached
+//! copy is used when the policy can't be fetched or fetching is disabled
+//! (`--no-fetch`), and instead of fetching when it matches the checksum
+//! pinned with `policy_sha256`.
+
+use crate::{config::AuditConfig, prelude::*};
+use abscissa_core::Config;
+use rustsec::{
+    transport::{HttpTransport, Request, Transport},
+    Error, ErrorKind,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Name of the directory in `$CARGO_HOME` where policy files are cached
+const CACHE_DIR: &str = "audit-policies";
+
+/// Timeout for fetching policy files, unless `[network] request_timeout` is set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Merge the shared policy referenced by the given configuration (if any)
+/// under it
+pub fn apply(mut config: AuditConfig) -> Result<AuditConfig, Error> {
+    let url = match &config.policy {
+        Some(url) => url.clone(),
+        None => return Ok(config),
+    };
+
+    let pinned = config
+        .policy_sha256
+        .as_deref()
+        .map(|checksum| checksum.trim().to_ascii_lowercase());
+
+    let fetch_policy = |url: &str| fetch(url, &config);
+    let fetcher: Option<&dyn Fn(&str) -> Result<String, Error>> = if config.database.fetch {
+        Some(&fetch_policy)
+    } else {
+        None
+    };
+
+    let toml = load(
+        &url,
+        pinned.as_deref(),
+        cache_path(&url).as_deref(),
+        fetcher,
+    )?;
+
+    let policy = AuditConfig::load_toml(&toml).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            format!("invalid shared policy {}", url),
+            e,
+        )
+    })?;
+
+    config.merge_policy(policy);
+    Ok(config)
+}
+
+/// Get the contents of the policy file at the given URL, verifying them
+/// against the `pinned` checksum (if any).
+///
+/// The copy cached at `cache_path` is used if it matches the pinned checksum,
+/// or if the policy can't be fetched (`fetch` is `None` when fetching is
+/// disabled). Fetched policies are written to the cache.
+fn load(
+    url: &str,
+    pinned: Option<&str>,
+    cache_path: Option<&Path>,
+    fetch: Option<&dyn Fn(&str) -> Result<String, Error>>,
+) -> Result<String, Error> {
+    if !url.starts_with("https://") && pinned.is_none() {
+        return Err(Error::new(
+            ErrorKind::BadParam,
+            &format!(
+                "shared policy {} must be fetched over https, or pinned with policy_sha256",
+                url
+            ),
+        ));
+    }
+
+    let cached = cache_path.and_then(|path| fs::read_to_string(path).ok());
+
+    // The pinned checksum identifies the contents, so they can't be stale
+    if let (Some(pinned), Some(cached)) = (pinned, &cached) {
+        if sha256_hex(cached) == pinned {
+            return Ok(cached.clone());
+        }
+    }
+
+    let fetched = match fetch {
+        Some(fetch) => fetch(url),
+        None => Err(Error::new(
+            ErrorKind::NotFound,
+            &format!("fetching shared policy {} is disabled", url),
+        )),
+    };
+
+    let toml = match fetched {
+        Ok(toml) => toml,
+        Err(e) => {
+            return match cached {
+                // A cached copy which doesn't match the pinned checksum is
+                // never used
+                Some(cached) if pinned.is_none() => {
+                    if fetch.is_some() {
+                        status_warn!(
+                            "couldn't fetch shared policy {}, using cached copy: {}",
+                            url,
+                            e
+                        );
+                    }
+                    Ok(cached)
+                }
+                _ => Err(e),
+            };
+        }
+    };
+
+    if let Some(pinned) = pinned {
+        let actual = sha256_hex(&toml);
+        if actual != pinned {
+            return Err(Error::new(
+                ErrorKind::Parse,
+                &format!(
+                    "checksum mismatch for shared policy {}: expected {}, got {}",
+                    url, pinned, actual
+                ),
+            ));
+        }
+    }
+
+    // The cache only makes later runs faster or offline-capable, so failing
+    // to write it isn't an error
+    if let Some(path) = cache_path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, &toml);
+    }
+
+    Ok(toml)
+}
+
+/// Fetch the policy file at the given URL
+fn fetch(url: &str, config: &AuditConfig) -> Result<String, Error> {
+    let timeout = config
+        .network
+        .request_timeout
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+
+    HttpTransport::new(timeout)?
+        .send(&Request::get(url))?
+        .into_body()
+        .map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't fetch shared policy {}", url),
+                e,
+            )
+        })
+}
+
+/// Location of the cached copy of the policy file at the given URL
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let name = sha256_hex(url);
+    let dir = home::cargo_home().ok()?.join(CACHE_DIR);
+    Some(dir.join(format!("{}.toml", &name[..16])))
+}
+
+/// Lowercase hex SHA-256 digest of the given string
+fn sha256_hex(data: &str) -> String {
+    Sha256::digest(data.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/policy.toml";
+    const POLICY: &str = "[advisories]\nignore = [\"RUSTSEC-2023-0001\"]\n";
+
+    fn fetch_policy(_url: &str) -> Result<String, Error> {
+        Ok(POLICY.to_owned())
+    }
+
+    fn fetch_other_policy(_url: &str) -> Result<String, Error> {
+        Ok("[advisories]\nignore = [\"RUSTSEC-2023-0002\"]\n".to_owned())
+    }
+
+    fn fetch_fails(_url: &str) -> Result<String, Error> {
+        Err(Error::new(ErrorKind::Io, &"connection refused"))
+    }
+
+    #[test]
+    fn sha256() {
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn fetch_and_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("policy.toml");
+
+        let toml = load(URL, None, Some(&cache), Some(&fetch_policy)).unwrap();
+        assert_eq!(toml, POLICY);
+        assert_eq!(fs::read_to_string(&cache).unwrap(), POLICY);
+    }
+
+    #[test]
+    fn reject_checksum_mismatch() {
+        let pinned = sha256_hex(POLICY);
+        let err = load(URL, Some(&pinned), None, Some(&fetch_other_policy)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn use_pinned_cached_copy_without_fetching() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("policy.toml");
+        fs::write(&cache, POLICY).unwrap();
+
+        let pinned = sha256_hex(POLICY);
+        let toml = load(URL, Some(&pinned), Some(&cache), Some(&fetch_fails)).unwrap();
+        assert_eq!(toml, POLICY);
+    }
+
+    #[test]
+    fn fall_back_to_cached_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("policy.toml");
+        fs::write(&cache, POLICY).unwrap();
+
+        let toml = load(URL, None, Some(&cache), Some(&fetch_fails)).unwrap();
+        assert_eq!(toml, POLICY);
+
+        // A cached copy which doesn't match the pinned checksum isn't used
+        let pinned = sha256_hex("something else");
+        assert!(load(URL, Some(&pinned), Some(&cache), Some(&fetch_fails)).is_err());
+    }
+
+    #[test]
+    fn no_fetch_uses_cached_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("policy.toml");
+        assert!(load(URL, None, Some(&cache), None).is_err());
+
+        fs::write(&cache, POLICY).unwrap();
+        assert_eq!(load(URL, None, Some(&cache), None).unwrap(), POLICY);
+    }
+
+    #[test]
+    fn require_https_or_checksum() {
+        let url = "http://example.com/policy.toml";
+        let err = load(url, None, None, Some(&fetch_policy)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadParam);
+
+        let pinned = sha256_hex(POLICY);
+        assert_eq!(
+            load(url, Some(&pinned), None, Some(&fetch_policy)).unwrap(),
+            POLICY
+        );
+    }
+}
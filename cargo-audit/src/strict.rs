@@ -0,0 +1,89 @@
+//! Strict mode (`--strict`)
+//!
+//! Conditions which normally only degrade the audit with a warning become
+//! fatal errors, each with a dedicated exit code.
+
+use crate::{prelude::*, progress};
+use std::{fmt, process::exit};
+
+/// Conditions which are only warnings unless strict mode is enabled
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Anomaly {
+    /// The advisory database is stale, or a snapshot was used in its place
+    StaleDatabase,
+
+    /// Dependency information is incomplete, e.g. binaries which weren't
+    /// built with `cargo auditable` or lockfiles with missing checksums
+    IncompleteData,
+
+    /// Some checks were skipped, e.g. yanked crates couldn't be looked up
+    SkippedChecks,
+}
+
+impl Anomaly {
+    /// Exit code used when this condition is encountered in strict mode
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Anomaly::StaleDatabase => 3,
+            Anomaly::IncompleteData => 4,
+            Anomaly::SkippedChecks => 5,
+        }
+    }
+}
+
+/// Condition which ends an audit, e.g. an anomaly in strict mode, along with
+/// the exit code it ends the process with
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Failure {
+    /// Exit code of the process
+    pub exit_code: i32,
+
+    /// Message explaining the failure
+    pub message: String,
+}
+
+impl Failure {
+    /// Report the failure to the user, and exit
+    pub fn exit(self) -> ! {
+        progress::suspend(|| status_err!("{}", self.message));
+        exit(self.exit_code)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Report a soft condition: a warning normally (unless `quiet`), and a
+/// fatal error in strict mode. Conditions may be reported while a progress
+/// bar is shown, so it's suspended meanwhile.
+pub fn report(strict: bool, quiet: bool, anomaly: Anomaly, message: fmt::Arguments<'_>) {
+    if let Err(failure) = check(strict, quiet, anomaly, message) {
+        failure.exit()
+    }
+}
+
+/// Like [`report`], but the condition is returned as a [`Failure`] in strict
+/// mode instead of exiting, for callers which keep running (e.g. the
+/// JSON-RPC server)
+pub fn check(
+    strict: bool,
+    quiet: bool,
+    anomaly: Anomaly,
+    message: fmt::Arguments<'_>,
+) -> Result<(), Failure> {
+    if strict {
+        return Err(Failure {
+            exit_code: anomaly.exit_code(),
+            message: format!("{} (strict mode)", message),
+        });
+    }
+
+    if !quiet {
+        progress::suspend(|| status_warn!("{}", message));
+    }
+
+    Ok(())
+}
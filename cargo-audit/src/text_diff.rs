@@ -0,0 +1,318 @@
+//! Line-based diffs of text files, e.g. of the manifests and lockfiles
+//! changed by `cargo audit fix`
+
+/// Lines of context around changes in diffs
+const DIFF_CONTEXT: usize = 3;
+
+/// Change to a line in a diff
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Edit {
+    /// Line of the old version which is kept
+    Keep(usize),
+
+    /// Line of the old version which is removed
+    Delete(usize),
+
+    /// Line of the new version which is inserted
+    Insert(usize),
+}
+
+/// Find the shortest sequence of line edits turning `old` into `new`, using
+/// Myers' algorithm after skipping the lines both have in common at the start
+/// and the end
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut edits: Vec<Edit> = (0..prefix).map(Edit::Keep).collect();
+    edits.extend(
+        myers(old_middle, new_middle)
+            .into_iter()
+            .map(|edit| match edit {
+                Edit::Keep(i) => Edit::Keep(prefix + i),
+                Edit::Delete(i) => Edit::Delete(prefix + i),
+                Edit::Insert(i) => Edit::Insert(prefix + i),
+            }),
+    );
+    edits.extend((old.len() - suffix..old.len()).map(Edit::Keep));
+    edits
+}
+
+/// Myers' diff algorithm, keeping the furthest reaching paths of each
+/// number of edits to trace the shortest edit sequence back
+fn myers(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let index = |k: isize| (k + offset) as usize;
+
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = vec![];
+
+    'search: for d in 0..=(n + m) {
+        trace.push(furthest.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+                furthest[index(k + 1)]
+            } else {
+                furthest[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            furthest[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = vec![];
+    let (mut x, mut y) = (n, m);
+
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = furthest[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Keep(x as usize));
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(prev_y as usize));
+            } else {
+                edits.push(Edit::Delete(prev_x as usize));
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+/// Render a unified diff between two versions of a file
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old, &new);
+
+    let mut diff = String::new();
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&i| !matches!(edits[i], Edit::Keep(_)))
+        .collect();
+    if changed.is_empty() {
+        return diff;
+    }
+
+    diff.push_str(&format!("--- a/{}\n+++ b/{}\n", path, path));
+
+    // Group changes whose context overlaps into hunks of edits
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changed {
+        let start = i.saturating_sub(DIFF_CONTEXT);
+        let end = (i + DIFF_CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Line numbers in the old and new version before each edit
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for edit in &edits {
+        positions.push((old_line, new_line));
+        match edit {
+            Edit::Keep(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Edit::Delete(_) => old_line += 1,
+            Edit::Insert(_) => new_line += 1,
+        }
+    }
+
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+
+        // Empty ranges start at the line before them
+        let (old_start, new_start) = positions[start];
+        let line_number = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            line_number(old_start, old_len),
+            old_len,
+            line_number(new_start, new_len),
+            new_len
+        ));
+
+        for edit in hunk {
+            match *edit {
+                Edit::Keep(i) => diff.push_str(&format!(" {}\n", old[i])),
+                Edit::Delete(i) => diff.push_str(&format!("-{}\n", old[i])),
+                Edit::Insert(i) => diff.push_str(&format!("+{}\n", new[i])),
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_changed_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nL\nm\n";
+
+        assert_eq!(
+            unified_diff("Cargo.lock", old, new),
+            "\
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -9,5 +9,5 @@
+ i
+ j
+ k
+-l
++L
+ m
+"
+        );
+
+        assert_eq!(unified_diff("Cargo.lock", old, old), "");
+    }
+
+    #[test]
+    fn diff_added_and_removed_lines() {
+        let old = "\
+[[package]]
+name = \"h2\"
+version = \"0.3.0\"
+checksum = \"aaaa\"
+
+[[package]]
+name = \"old\"
+version = \"0.1.0\"
+";
+        let new = "\
+[[package]]
+name = \"h2\"
+version = \"0.3.26\"
+checksum = \"bbbb\"
+dependencies = [
+ \"new\",
+]
+
+[[package]]
+name = \"new\"
+version = \"0.2.0\"
+";
+
+        assert_eq!(
+            unified_diff("Cargo.lock", old, new),
+            "\
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,8 +1,11 @@
+ [[package]]
+ name = \"h2\"
+-version = \"0.3.0\"
+-checksum = \"aaaa\"
++version = \"0.3.26\"
++checksum = \"bbbb\"
++dependencies = [
++ \"new\",
++]
+ 
+ [[package]]
+-name = \"old\"
+-version = \"0.1.0\"
++name = \"new\"
++version = \"0.2.0\"
+"
+        );
+    }
+
+    #[test]
+    fn diff_into_empty_file() {
+        assert_eq!(
+            unified_diff("Cargo.toml", "a\nb\n", ""),
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,2 +0,0 @@\n-a\n-b\n"
+        );
+        assert_eq!(
+            unified_diff("Cargo.toml", "", "a\n"),
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn shortest_edits() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let edits = diff_lines(&old, &new);
+
+        // The edit distance between these is 5
+        let changes = edits
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Keep(_)))
+            .count();
+        assert_eq!(changes, 5);
+
+        // Applying the edits gives the new version
+        let result: Vec<&str> = edits
+            .iter()
+            .filter_map(|edit| match *edit {
+                Edit::Keep(i) => Some(old[i]),
+                Edit::Delete(_) => None,
+                Edit::Insert(i) => Some(new[i]),
+            })
+            .collect();
+        assert_eq!(result, new);
+    }
+}
@@ -0,0 +1,31 @@
+//! Timestamps in reports and status output
+
+use rustsec::advisory::Date;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format a time (in UTC) as e.g. `2024-01-31T12:34:56`
+pub fn format(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() % 86_400);
+
+    format!(
+        "{}T{:02}:{:02}:{:02}",
+        Date::from_system_time(time),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn format_utc() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_369_353_600 + 3723);
+        assert_eq!(format(time), "2013-05-24T01:02:03");
+    }
+}
@@ -0,0 +1,236 @@
+//! Watch mode: stays resident and audits a lockfile again whenever it
+//! changes, re-fetching the advisory database periodically, and prints the
+//! findings which appeared or were resolved since the previous audit
+
+use crate::{
+    auditor::Auditor,
+    config::{AuditConfig, OutputFormat},
+    error::display_err_with_source,
+    prelude::*,
+};
+use abscissa_core::terminal::{self, Color};
+use rustsec::report::diff::ReportDiff;
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the lockfile is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Audit the lockfile at the given path whenever it changes, fetching the
+/// advisory database again when `refresh_interval` has passed.
+///
+/// The first audit prints the full report, and later ones only the changes:
+/// one line per finding in the terminal, or one [`ReportDiff`] per audit
+/// with JSON output.
+pub fn run(config: &AuditConfig, lockfile_path: &Path, refresh_interval: Duration) -> ! {
+    let mut previous: Option<rustsec::Report> = None;
+    let mut modified = None;
+    let mut fetched_at: Option<Instant> = None;
+
+    loop {
+        let lockfile_modified = modified_time(lockfile_path);
+        let refresh = match fetched_at {
+            Some(at) => at.elapsed() >= refresh_interval,
+            None => true,
+        };
+
+        if refresh || lockfile_modified != modified {
+            modified = lockfile_modified;
+
+            let mut config = config.clone();
+            if refresh {
+                fetched_at = Some(Instant::now());
+            } else {
+                config.database.fetch = false;
+                config.yanked.update_index = false;
+            }
+
+            // Only the first audit prints the progress and the full report
+            if previous.is_some() {
+                config.output.quiet = true;
+            }
+
+            // A new auditor is used for each audit, so that Cargo's package
+            // lock is released in between and e.g. `cargo update` can run
+            let mut auditor = Auditor::new(&config);
+            let result = match previous {
+                None => auditor.audit_lockfile(lockfile_path),
+                Some(_) => auditor.report_lockfile(lockfile_path),
+            };
+            drop(auditor);
+
+            match result {
+                Ok(report) => {
+                    match &previous {
+                        Some(previous) => {
+                            print_changes(&config, &ReportDiff::new(previous, &report))
+                        }
+                        None if config.output.format == OutputFormat::Terminal => status_info!(
+                            "Watching",
+                            "{} for changes (press Ctrl-C to stop)",
+                            lockfile_path.display()
+                        ),
+                        None => (),
+                    }
+                    previous = Some(report);
+                }
+                Err(e) => status_err!("{}", display_err_with_source(&e)),
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Get the time the file at the given path was last modified, if it exists
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Print the findings which weren't in the previous audit, followed by the
+/// ones which were resolved since
+fn print_changes(config: &AuditConfig, diff: &ReportDiff) {
+    if !diff.has_changes() {
+        return;
+    }
+
+    if config.output.format != OutputFormat::Terminal {
+        println!("{}", serde_json::to_string(diff).unwrap());
+        return;
+    }
+
+    let theme = &config.output.theme;
+    let added_color = theme.error_color().map(Color::from);
+    let resolved_color = theme.resolved_color().map(Color::from);
+
+    for message in finding_lines(&diff.introduced) {
+        print_change(added_color, "Added", &message);
+    }
+
+    for message in finding_lines(&diff.resolved) {
+        print_change(resolved_color, "Resolved", &message);
+    }
+}
+
+/// Describe each finding of a report on one line, e.g.
+/// `RUSTSEC-2023-0044 in openssl 0.10.50: title`
+fn finding_lines(report: &rustsec::Report) -> Vec<String> {
+    let vulnerabilities = report.vulnerabilities.list.iter().map(|vulnerability| {
+        (
+            &vulnerability.package,
+            vulnerability.advisory.id.to_string(),
+            vulnerability.advisory.title.as_str(),
+        )
+    });
+
+    let warnings = report
+        .warnings
+        .values()
+        .flatten()
+        .map(|warning| match &warning.advisory {
+            Some(advisory) => (
+                &warning.package,
+                advisory.id.to_string(),
+                advisory.title.as_str(),
+            ),
+            None => (&warning.package, warning.kind.as_str().to_owned(), ""),
+        });
+
+    vulnerabilities
+        .chain(warnings)
+        .map(|(package, id, title)| {
+            let mut message = format!("{} in {} {}", id, package.name, package.version);
+            if !title.is_empty() {
+                message = format!("{}: {}", message, title);
+            }
+            message
+        })
+        .collect()
+}
+
+/// Print a finding which was added or resolved
+fn print_change(color: Option<Color>, status: &str, message: &str) {
+    let mut line = terminal::status::Status::new().justified().bold();
+    if let Some(color) = color {
+        line = line.color(color);
+    }
+
+    line.status(status).print_stdout(message).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustsec::{Lockfile, Warning, WarningKind};
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "left-pad"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "right-pad"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    /// Report with `yanked` warnings about the given packages
+    fn report(yanked: &[usize]) -> rustsec::Report {
+        let database = rustsec::Database::from_advisories(vec![]).unwrap();
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let mut report = rustsec::Report::generate(&database, &lockfile, &Default::default());
+
+        report.warnings.insert(
+            WarningKind::Yanked,
+            yanked
+                .iter()
+                .map(|&index| {
+                    Warning::new(
+                        WarningKind::Yanked,
+                        &lockfile.packages[index],
+                        None,
+                        None,
+                        None,
+                    )
+                })
+                .collect(),
+        );
+        report
+    }
+
+    #[test]
+    fn changed_findings() {
+        let diff = ReportDiff::new(&report(&[0]), &report(&[1]));
+        assert_eq!(
+            finding_lines(&diff.introduced),
+            ["yanked in right-pad 2.0.0"]
+        );
+        assert_eq!(finding_lines(&diff.resolved), ["yanked in left-pad 1.0.0"]);
+
+        let diff = ReportDiff::new(&report(&[0]), &report(&[0]));
+        assert!(!diff.has_changes());
+        assert!(finding_lines(&diff.introduced).is_empty());
+    }
+
+    /// With JSON output, each audit with changes prints a report diff
+    #[test]
+    fn json_changes() {
+        let diff = ReportDiff::new(&report(&[]), &report(&[1]));
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&diff).unwrap()).unwrap();
+        assert_eq!(
+            json["introduced"]["warnings"]["yanked"][0]["package"]["name"],
+            "right-pad"
+        );
+        assert!(json["resolved"]["warnings"].as_object().unwrap().is_empty());
+    }
+}
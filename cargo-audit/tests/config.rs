@@ -1,7 +1,10 @@
 //! Configuration file tests
 
-use cargo_audit::config::AuditConfig;
-use std::{fs, path::Path};
+use cargo_audit::{
+    config::AuditConfig,
+    config_layers::{self, ConfigFile, Layer},
+};
+use std::{ffi::OsString, fs, path::Path};
 
 /// Ensure `audit.toml.example` parses as a valid config file
 #[test]
@@ -18,3 +21,246 @@ fn parse_audit_toml_example() {
         "https://github.com/RustSec/advisory-db.git"
     );
 }
+
+/// Ensure shared policies are merged under the local configuration
+#[test]
+fn merge_shared_policy() {
+    let mut config: AuditConfig = toml::from_str(
+        r#"
+        policy = "https://example.com/rust-audit-policy.toml"
+
+        [advisories]
+        ignore = ["RUSTSEC-2020-0001"]
+        severity_threshold = "high"
+
+        [output.policy.warnings]
+        unmaintained = "warn"
+        "#,
+    )
+    .unwrap();
+
+    let policy: AuditConfig = toml::from_str(
+        r#"
+        [advisories]
+        ignore = ["RUSTSEC-2020-0001", "RUSTSEC-2021-0002"]
+        severity_threshold = "low"
+        informational_warnings = ["unsound"]
+
+        [output]
+        deny = ["unsound"]
+        quiet = true
+
+        [output.policy.warnings]
+        unmaintained = "deny"
+        yanked = "allow"
+        "#,
+    )
+    .unwrap();
+
+    config.merge_policy(policy);
+
+    let ignore: Vec<_> = config
+        .advisories
+        .ignore
+        .iter()
+        .map(|id| id.as_str())
+        .collect();
+    assert_eq!(ignore, ["RUSTSEC-2020-0001", "RUSTSEC-2021-0002"]);
+    assert_eq!(
+        config.advisories.severity_threshold,
+        Some(rustsec::advisory::Severity::High)
+    );
+    assert_eq!(
+        config.advisories.informational_warnings,
+        Some(vec![rustsec::advisory::Informational::Unsound])
+    );
+    assert_eq!(config.output.deny.len(), 1);
+    assert_eq!(config.output.policy.warnings.len(), 2);
+    assert_eq!(
+        config.output.policy.warnings[&rustsec::WarningKind::Unmaintained],
+        cargo_audit::config::PolicyAction::Warn
+    );
+
+    // Settings other than the policy aren't taken from the shared file
+    assert!(!config.output.quiet);
+}
+
+/// Ensure configuration layers are merged with the later files taking precedence
+#[test]
+fn merge_config_layers() {
+    let dir = tempfile::tempdir().unwrap();
+    let user = dir.path().join("user.toml");
+    let workspace = dir.path().join("workspace.toml");
+
+    fs::write(
+        &user,
+        r#"
+        [advisories]
+        ignore = ["RUSTSEC-2020-0001"]
+        severity_threshold = "high"
+
+        [output]
+        deny = ["unmaintained"]
+        "#,
+    )
+    .unwrap();
+
+    fs::write(
+        &workspace,
+        r#"
+        [advisories]
+        ignore = ["RUSTSEC-2020-0002"]
+        "#,
+    )
+    .unwrap();
+
+    let files = [
+        ConfigFile {
+            layer: Layer::System,
+            path: dir.path().join("missing.toml"),
+        },
+        ConfigFile {
+            layer: Layer::User,
+            path: user,
+        },
+        ConfigFile {
+            layer: Layer::Workspace,
+            path: workspace,
+        },
+    ];
+
+    let config = config_layers::load(&files).unwrap();
+    let ignore: Vec<_> = config
+        .advisories
+        .ignore
+        .iter()
+        .map(|id| id.as_str())
+        .collect();
+
+    assert_eq!(ignore, ["RUSTSEC-2020-0002"]);
+    assert_eq!(
+        config.advisories.severity_threshold,
+        Some(rustsec::advisory::Severity::High)
+    );
+    assert_eq!(config.output.deny.len(), 1);
+}
+
+/// Ensure `CARGO_AUDIT_*` environment variables override configuration files
+#[test]
+fn env_config_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = dir.path().join("workspace.toml");
+
+    fs::write(
+        &workspace,
+        r#"
+        [advisories]
+        ignore = ["RUSTSEC-2020-0001"]
+        severity_threshold = "high"
+        "#,
+    )
+    .unwrap();
+
+    let files = [ConfigFile {
+        layer: Layer::Workspace,
+        path: workspace,
+    }];
+
+    let vars = [
+        (
+            "CARGO_AUDIT_ADVISORIES_IGNORE",
+            "RUSTSEC-2020-0002, RUSTSEC-2020-0003",
+        ),
+        ("CARGO_AUDIT_ADVISORIES_IGNORE_SOURCE", "true"),
+        (
+            "CARGO_AUDIT_DATABASE_URL",
+            "https://example.com/advisory-db.git",
+        ),
+        ("CARGO_AUDIT_NETWORK_RETRIES", "3"),
+        ("CARGO_AUDIT_OUTPUT_FORMAT", "json"),
+        ("CARGO_AUDIT_OUTPUT_THEME_PRESET", "colorblind"),
+        ("CARGO_AUDIT_UPLOAD_TOKEN", "secret"),
+        ("PATH", "/usr/bin"),
+    ]
+    .map(|(name, value)| (OsString::from(name), OsString::from(value)));
+
+    let (config, unknown) = config_layers::load_with_env(&files, vars).unwrap();
+    assert!(unknown.is_empty());
+    let ignore: Vec<_> = config
+        .advisories
+        .ignore
+        .iter()
+        .map(|id| id.as_str())
+        .collect();
+
+    assert_eq!(ignore, ["RUSTSEC-2020-0002", "RUSTSEC-2020-0003"]);
+    assert!(config.advisories.ignore_source);
+    assert_eq!(
+        config.advisories.severity_threshold,
+        Some(rustsec::advisory::Severity::High)
+    );
+    assert_eq!(
+        config.database.url.as_deref(),
+        Some("https://example.com/advisory-db.git")
+    );
+    assert_eq!(config.network.retries, 3);
+    assert_eq!(
+        config.output.format,
+        cargo_audit::config::OutputFormat::Json
+    );
+    assert_eq!(
+        config.output.theme.preset,
+        cargo_audit::config::ThemePreset::Colorblind
+    );
+
+    let invalid = [(
+        OsString::from("CARGO_AUDIT_OUTPUT_FORMAT"),
+        OsString::from("xml"),
+    )];
+    let err = config_layers::load_with_env(&[], invalid).unwrap_err();
+    assert!(err.to_string().contains("CARGO_AUDIT_OUTPUT_FORMAT"));
+}
+
+/// Ensure the type of each setting decides how environment variables are
+/// parsed, and that variables which aren't settings are skipped
+#[test]
+fn env_config_types_and_unknown_vars() {
+    let vars = [
+        (
+            "CARGO_AUDIT_ADVISORIES_INFORMATIONAL_WARNINGS",
+            "unmaintained, unsound",
+        ),
+        ("CARGO_AUDIT_ADVISORIES_IGNORE", "RUSTSEC-2020-0001"),
+        ("CARGO_AUDIT_DATABASE_PATH", "/tmp/advisory-db"),
+        ("CARGO_AUDIT_VERSION", "0.21.0"),
+        ("CARGO_AUDIT_OUTPUT_COLOUR", "always"),
+    ]
+    .map(|(name, value)| (OsString::from(name), OsString::from(value)));
+
+    let (config, unknown) = config_layers::load_with_env(&[], vars.clone()).unwrap();
+    assert_eq!(
+        config.advisories.informational_warnings,
+        Some(vec![
+            rustsec::advisory::Informational::Unmaintained,
+            rustsec::advisory::Informational::Unsound,
+        ])
+    );
+    assert_eq!(config.advisories.ignore.len(), 1);
+    assert_eq!(
+        config.database.path.as_deref(),
+        Some(std::path::Path::new("/tmp/advisory-db"))
+    );
+    assert_eq!(
+        unknown,
+        ["CARGO_AUDIT_OUTPUT_COLOUR", "CARGO_AUDIT_VERSION"]
+    );
+
+    assert_eq!(
+        config_layers::env_var_names(vars),
+        [
+            "CARGO_AUDIT_ADVISORIES_IGNORE",
+            "CARGO_AUDIT_ADVISORIES_INFORMATIONAL_WARNINGS",
+            "CARGO_AUDIT_DATABASE_PATH",
+        ]
+    );
+}
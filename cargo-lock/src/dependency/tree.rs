@@ -113,6 +113,19 @@ impl Default for Symbols {
     }
 }
 
+impl Symbols {
+    /// Symbols which only use ASCII characters, for terminals which can't
+    /// display the default box-drawing characters
+    pub fn ascii() -> Symbols {
+        Self {
+            down: "|",
+            tee: "|",
+            ell: "`",
+            right: "-",
+        }
+    }
+}
+
 /// Dependency tree presenter
 struct Presenter<'g, 's> {
     /// Dependency graph being displayed
@@ -0,0 +1,12 @@
+//! Parsing and linting of advisories in the Markdown format with TOML front matter
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(data) = std::str::from_utf8(data) {
+        let _ = data.parse::<rustsec::Advisory>();
+        let _ = rustsec::advisory::Linter::lint_string(data);
+    }
+});
@@ -0,0 +1,17 @@
+//! Recovery of dependencies from binaries, as done by `cargo audit bin`:
+//! decoding the data embedded by `cargo auditable`, falling back to panic messages
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// Same limit on the decompressed audit data as `cargo audit bin`
+const OUTPUT_LIMIT: usize = 8 * 1024 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(info) = auditable_info::audit_info_from_slice(data, OUTPUT_LIMIT) {
+        let _ = cargo_lock::Lockfile::try_from(&info);
+    }
+
+    let _ = quitters::versions(data);
+});
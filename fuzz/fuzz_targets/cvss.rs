@@ -0,0 +1,23 @@
+//! Parsing and scoring of CVSS v3 vector strings
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let base = match data.parse::<cvss::v3::Base>() {
+        Ok(base) => base,
+        Err(_) => return,
+    };
+
+    let score = base.score().value();
+    assert!(
+        (0.0..=10.0).contains(&score),
+        "score out of range: {}",
+        score
+    );
+
+    // Parsed vectors round-trip through their string representation
+    let reparsed: cvss::v3::Base = base.to_string().parse().unwrap();
+    assert_eq!(base, reparsed);
+});
@@ -0,0 +1,11 @@
+//! Parsing of `Cargo.lock` files
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    if let Ok(lockfile) = data.parse::<cargo_lock::Lockfile>() {
+        let _ = lockfile.to_string();
+    }
+});
@@ -0,0 +1,17 @@
+//! Import of advisories in the OSV format, as fetched from osv.dev and GitHub
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustsec::osv::OsvAdvisory;
+
+fuzz_target!(|data: &[u8]| {
+    let osv = match serde_json::from_slice::<OsvAdvisory>(data) {
+        Ok(osv) => osv,
+        Err(_) => return,
+    };
+
+    let _ = osv.rustsec_refs_imported();
+    let _ = osv.crates();
+    let _ = osv.into_rustsec();
+});
@@ -21,11 +21,13 @@
 extern crate std;
 
 pub(crate) mod error;
+pub mod object_format;
 pub mod platform;
 pub mod target;
 
 pub use crate::{
     error::Error,
+    object_format::ObjectFormat,
     platform::{Platform, Tier},
     target::{Arch, Endian, Env, PointerWidth, OS},
 };
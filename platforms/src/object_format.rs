@@ -0,0 +1,101 @@
+//! Object file formats of the binaries of operating systems
+
+use crate::target::OS;
+
+/// Format of the executables and libraries built for an operating system.
+///
+/// `rustc --print cfg` doesn't expose this, and `target_family` doesn't tell
+/// Mach-O and ELF apart (both are `unix`), so this is a mapping of its own.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ObjectFormat {
+    /// Executable and Linkable Format, used by Linux, the BSDs and most
+    /// other operating systems, as well as bare-metal targets
+    Elf,
+
+    /// Mach-O, used by Apple's operating systems
+    MachO,
+
+    /// Portable Executable, used by Windows and UEFI
+    Pe,
+
+    /// WebAssembly modules
+    Wasm,
+
+    /// Extended Common Object File Format, used by AIX
+    Xcoff,
+
+    /// Parallel Thread Execution assembly, used by CUDA
+    Ptx,
+}
+
+impl OS {
+    /// Get the format of the binaries built for this operating system, if
+    /// it's known
+    pub fn object_format(self) -> Option<ObjectFormat> {
+        match self {
+            OS::Windows | OS::Uefi => Some(ObjectFormat::Pe),
+            OS::MacOS | OS::iOS | OS::TvOS | OS::WatchOS => Some(ObjectFormat::MachO),
+            OS::Wasi | OS::Emscripten => Some(ObjectFormat::Wasm),
+            OS::Aix => Some(ObjectFormat::Xcoff),
+            OS::Cuda => Some(ObjectFormat::Ptx),
+            OS::Android
+            | OS::Dragonfly
+            | OS::Espidf
+            | OS::FreeBSD
+            | OS::Fuchsia
+            | OS::Haiku
+            | OS::Hermit
+            | OS::Horizon
+            | OS::Hurd
+            | OS::IllumOS
+            | OS::L4re
+            | OS::Linux
+            | OS::NetBSD
+            | OS::None
+            | OS::Nto
+            | OS::OpenBSD
+            | OS::Psp
+            | OS::Redox
+            | OS::Solaris
+            | OS::SolidAsp3
+            | OS::TeeOS
+            | OS::Vita
+            | OS::VxWorks
+            | OS::Xous
+            | OS::Zkvm => Some(ObjectFormat::Elf),
+            // `unknown` and operating systems added after this mapping was
+            // written
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Platform;
+
+    #[test]
+    fn object_formats() {
+        assert_eq!(OS::Linux.object_format(), Some(ObjectFormat::Elf));
+        assert_eq!(OS::None.object_format(), Some(ObjectFormat::Elf));
+        assert_eq!(OS::Windows.object_format(), Some(ObjectFormat::Pe));
+        assert_eq!(OS::iOS.object_format(), Some(ObjectFormat::MachO));
+        assert_eq!(OS::Wasi.object_format(), Some(ObjectFormat::Wasm));
+        assert_eq!(OS::Unknown.object_format(), None);
+    }
+
+    /// Every platform other than those for an unknown OS has a known format
+    #[test]
+    fn platforms_have_object_formats() {
+        for platform in Platform::ALL {
+            assert_eq!(
+                platform.target_os.object_format().is_some(),
+                platform.target_os != OS::Unknown,
+                "{}",
+                platform.target_triple
+            );
+        }
+    }
+}
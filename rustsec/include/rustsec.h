@@ -0,0 +1,42 @@
+/*
+ * C API for auditing Cargo.lock files against the RustSec advisory database.
+ *
+ * Build the library with:
+ *
+ *     cargo rustc -p rustsec --release --features capi --crate-type cdylib
+ *
+ * Functions which fail return NULL, and rustsec_last_error() describes why.
+ * Strings returned by the library must be freed with rustsec_string_free(),
+ * and databases with rustsec_db_free().
+ */
+
+#ifndef RUSTSEC_H
+#define RUSTSEC_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Advisory database */
+typedef struct rustsec_db rustsec_db;
+
+/* Open the advisory database in the given local directory */
+rustsec_db *rustsec_db_open(const char *path);
+
+/* Free a database returned by rustsec_db_open() */
+void rustsec_db_free(rustsec_db *db);
+
+/* Audit a Cargo.lock file, returning the report as JSON */
+char *rustsec_audit_lockfile(const rustsec_db *db, const char *lockfile_path);
+
+/* Describe the last error on the calling thread, or return NULL */
+char *rustsec_last_error(void);
+
+/* Free a string returned by the library */
+void rustsec_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUSTSEC_H */
@@ -2,6 +2,7 @@
 
 pub mod affected;
 mod category;
+mod cvss_score;
 mod date;
 mod id;
 mod informational;
@@ -15,6 +16,7 @@ pub(crate) mod versions;
 pub use self::{
     affected::Affected,
     category::Category,
+    cvss_score::CvssScore,
     date::Date,
     id::{Id, IdKind},
     informational::Informational,
@@ -29,10 +31,10 @@ pub use cvss::Severity;
 
 use crate::{
     error::{Error, ErrorKind},
-    fs,
+    fs, package,
 };
 use serde::{Deserialize, Serialize};
-use std::{path::Path, str::FromStr};
+use std::{fmt::Write, path::Path, str::FromStr};
 
 /// RustSec Security Advisories
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -53,12 +55,23 @@ impl Advisory {
     pub fn load_file(path: impl AsRef<Path>) -> Result<Self, Error> {
         let path = path.as_ref();
 
-        let advisory_data = fs::read_to_string(path)
-            .map_err(|e| format_err!(ErrorKind::Io, "couldn't open {}: {}", path.display(), e))?;
+        let advisory_data = fs::read_to_string(path).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't open {}", path.display()),
+                e,
+            )
+            .with_path(path)
+        })?;
 
-        advisory_data
-            .parse()
-            .map_err(|e| format_err!(ErrorKind::Parse, "error parsing {}: {}", path.display(), e))
+        advisory_data.parse().map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("error parsing {}", path.display()),
+                e,
+            )
+            .with_path(path)
+        })
     }
 
     /// Get advisory ID
@@ -81,15 +94,115 @@ impl Advisory {
         &self.metadata.date
     }
 
-    /// Get the severity of this advisory if it has a CVSS v3 associated
+    /// Get the severity of this advisory if it has a CVSS v3 associated,
+    /// according to its primary CVSS vector (see [`Metadata::primary_cvss`])
     pub fn severity(&self) -> Option<Severity> {
-        self.metadata.cvss.as_ref().map(|cvss| cvss.severity())
+        self.metadata.primary_cvss().map(|cvss| cvss.severity())
     }
 
     /// Whether the advisory has been withdrawn, i.e. soft-deleted
     pub fn withdrawn(&self) -> bool {
         self.metadata.withdrawn.is_some()
     }
+
+    /// Serialize this advisory in the format of the advisory database, i.e.
+    /// as Markdown with TOML front matter, which parses back into an equal
+    /// advisory.
+    ///
+    /// The front matter is canonical: its keys are in a fixed order, and
+    /// empty lists, unset fields, the inferred collection and the default
+    /// license are omitted.
+    pub fn to_markdown(&self) -> Result<String, Error> {
+        let metadata = &self.metadata;
+        let mut front_matter = FrontMatter::default();
+
+        front_matter.table("advisory");
+        front_matter.field("id", &metadata.id)?;
+        front_matter.field("package", &metadata.package)?;
+        front_matter.field("date", &metadata.date)?;
+        front_matter.optional("url", &metadata.url)?;
+        front_matter.list("references", &metadata.references)?;
+        front_matter.optional("informational", &metadata.informational)?;
+        front_matter.list("categories", &metadata.categories)?;
+        front_matter.list("keywords", &metadata.keywords)?;
+        front_matter.list("aliases", &metadata.aliases)?;
+        front_matter.list("related", &metadata.related)?;
+        front_matter.list("supersedes", &metadata.supersedes)?;
+        front_matter.list("superseded_by", &metadata.superseded_by)?;
+        front_matter.optional("cvss", &metadata.cvss)?;
+        front_matter.list("additional_cvss", &metadata.additional_cvss)?;
+        front_matter.optional("source", &metadata.source)?;
+        front_matter.optional("withdrawn", &metadata.withdrawn)?;
+
+        if metadata.license != License::default() {
+            front_matter.field("license", &metadata.license)?;
+        }
+
+        front_matter.table("versions");
+        front_matter.field("patched", self.versions.patched())?;
+        front_matter.list("unaffected", self.versions.unaffected())?;
+
+        if let Some(affected) = &self.affected {
+            front_matter.table("affected");
+            front_matter.list("arch", &affected.arch)?;
+            front_matter.list("os", &affected.os)?;
+
+            if !affected.functions.is_empty() {
+                front_matter.field("functions", &affected.functions)?;
+            }
+
+            front_matter.list("features", &affected.features)?;
+        }
+
+        let mut markdown = format!("```toml\n{}```\n\n# {}\n", front_matter.0, self.title());
+
+        if !self.description().is_empty() {
+            writeln!(markdown, "\n{}", self.description()).expect("writing to a string");
+        }
+
+        Ok(markdown)
+    }
+
+    /// Create the skeleton of a new advisory about the given package, to be
+    /// filled in and submitted to the advisory database.
+    ///
+    /// The advisory is about a vulnerability, unless an informational `kind`
+    /// is given. It has the placeholder ID (`RUSTSEC-0000-0000`), today's
+    /// date and empty `[affected]` and `[versions]` sections, and parses as
+    /// an advisory as-is.
+    pub fn template(package: &package::Name, kind: Option<&Informational>) -> String {
+        let mut front_matter = format!(
+            "[advisory]\nid = {}\npackage = {}\ndate = {}\n",
+            toml_string(Id::PLACEHOLDER),
+            toml_string(package.as_str()),
+            toml_string(Date::today().as_str()),
+        );
+
+        front_matter.push_str("# url = \"https://github.com/owner/repo/issues/123\"\n");
+
+        match kind {
+            Some(kind) => {
+                front_matter.push_str(&format!("informational = {}\n", toml_string(kind.as_str())));
+            }
+            None => {
+                front_matter.push_str("categories = []\n");
+                front_matter
+                    .push_str("# cvss = \"CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N\"\n");
+            }
+        }
+
+        front_matter.push_str("keywords = []\n");
+        front_matter.push_str("# aliases = [\"CVE-YYYY-NNNN\"]\n");
+        front_matter.push_str("\n[affected]\nfunctions = {}\n");
+        front_matter.push_str("\n[versions]\npatched = []\nunaffected = []\n");
+
+        format!(
+            "```toml\n{}```\n\n# Title of the advisory\n\n\
+             Description of the issue: what is affected, how it can be triggered,\n\
+             and how to work around it until a fixed version is released.\n",
+            front_matter
+        )
+    }
 }
 
 impl FromStr for Advisory {
@@ -127,3 +240,48 @@ impl FromStr for Advisory {
         Ok(advisory)
     }
 }
+
+/// Quote a string for use as a TOML value
+fn toml_string(string: &str) -> String {
+    toml::Value::String(string.to_owned()).to_string()
+}
+
+/// Builder for the TOML front matter of advisories, with fields in the order
+/// they are added
+#[derive(Default)]
+struct FrontMatter(String);
+
+impl FrontMatter {
+    /// Start a table
+    fn table(&mut self, name: &str) {
+        if !self.0.is_empty() {
+            self.0.push('\n');
+        }
+
+        writeln!(self.0, "[{}]", name).expect("writing to a string");
+    }
+
+    /// Add a field
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        let value = toml::Value::try_from(value)?;
+        writeln!(self.0, "{} = {}", key, value).expect("writing to a string");
+        Ok(())
+    }
+
+    /// Add a field, unless it is unset
+    fn optional<T: Serialize>(&mut self, key: &str, value: &Option<T>) -> Result<(), Error> {
+        match value {
+            Some(value) => self.field(key, value),
+            None => Ok(()),
+        }
+    }
+
+    /// Add a field, unless it is an empty list
+    fn list<T: Serialize>(&mut self, key: &str, values: &[T]) -> Result<(), Error> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.field(key, values)
+    }
+}
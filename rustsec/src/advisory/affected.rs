@@ -34,6 +34,11 @@ pub struct Affected {
     /// (e.g. `mycrate::path::to::VulnerableStruct::vulnerable_func`)
     #[serde(default)]
     pub functions: Map<FunctionPath, Vec<VersionReq>>,
+
+    /// Features of the crate which gate the vulnerable code. The vulnerability
+    /// only applies if at least one of them is enabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
 }
 
 /// Canonical Rust Paths (sans parameters) to vulnerable types and/or functions
@@ -72,6 +72,25 @@ impl Category {
             Category::Other(other) => other,
         }
     }
+
+    /// Get the [CWE] weakness class which best corresponds to this category,
+    /// if there is one.
+    ///
+    /// [CWE]: https://cwe.mitre.org/
+    pub fn cwe(&self) -> Option<u32> {
+        match self {
+            Category::CodeExecution => Some(94),
+            Category::CryptoFailure => Some(310),
+            Category::DenialOfService => Some(400),
+            Category::FileDisclosure => Some(22),
+            Category::FormatInjection => Some(74),
+            Category::MemoryCorruption => Some(119),
+            Category::MemoryExposure => Some(200),
+            Category::PrivilegeEscalation => Some(269),
+            Category::ThreadSafety => Some(362),
+            Category::Other(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for Category {
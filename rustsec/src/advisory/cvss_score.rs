@@ -0,0 +1,33 @@
+//! Additional CVSS scores of advisories
+
+use serde::{Deserialize, Serialize};
+
+/// CVSS vector scoring an advisory in addition to its primary `cvss` vector,
+/// e.g. as assessed by another source or for a specific configuration of the
+/// affected crate
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CvssScore {
+    /// CVSS v3.1 Base Metrics vector string
+    pub vector: cvss::v3::Base,
+
+    /// Who assessed the score (e.g. `NVD` or `GHSA`), if not the author of
+    /// the advisory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// Configuration the score applies to (e.g. `windows` or a crate
+    /// feature), if it doesn't apply to every affected configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applies_to: Option<String>,
+}
+
+impl From<cvss::v3::Base> for CvssScore {
+    fn from(vector: cvss::v3::Base) -> Self {
+        Self {
+            vector,
+            source: None,
+            applies_to: None,
+        }
+    }
+}
@@ -5,6 +5,7 @@ use serde::{de, Deserialize, Serialize};
 use std::{
     fmt::{self, Display},
     str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 /// Minimum allowed year on advisory dates
@@ -18,6 +19,38 @@ pub(crate) const YEAR_MAX: u32 = YEAR_MIN + 100;
 pub struct Date(String);
 
 impl Date {
+    /// Get the current date (in UTC)
+    pub fn today() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// Get the date (in UTC) of the given time
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let days = time
+            .duration_since(UNIX_EPOCH)
+            .expect("time is before the Unix epoch")
+            .as_secs()
+            / 86_400;
+
+        Self::from_days(days)
+    }
+
+    /// Get the date the given number of days after the Unix epoch, see
+    /// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    fn from_days(days: u64) -> Self {
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+
+        Date(format!("{:04}-{:02}-{:02}", year, month, day))
+    }
+
     /// Get the year for this date
     pub fn year(&self) -> u32 {
         self.component(0).expect("has year")
@@ -142,4 +175,16 @@ mod tests {
         assert_eq!(date.month(), 1);
         assert_eq!(date.day(), 2);
     }
+
+    #[test]
+    fn from_days_test() {
+        assert_eq!(Date::from_days(10_957).as_str(), "2000-01-01");
+        assert_eq!(Date::from_days(11_016).as_str(), "2000-02-29");
+        assert_eq!(Date::from_days(19_782).as_str(), "2024-02-29");
+        assert_eq!(Date::from_days(20_742).as_str(), "2026-10-16");
+        assert!(Date::from_str(Date::today().as_str()).is_ok());
+
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_369_439_999);
+        assert_eq!(Date::from_system_time(time).as_str(), "2013-05-24");
+    }
 }
@@ -3,11 +3,14 @@
 //!
 //! This is run in CI at the time advisories are submitted.
 
-use super::{parts, Advisory, Category};
+use super::{parts, Advisory, Category, Id};
 use crate::advisory::license::License;
-use crate::fs;
+use crate::osv::{ranges_for_advisory, OsvRange};
+use crate::{fs, Database};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::{fmt, path::Path};
+use url::Url;
 
 /// Lint information about a particular advisory
 #[derive(Debug)]
@@ -76,6 +79,75 @@ impl Linter {
         self.errors.as_slice()
     }
 
+    /// Opt-in lint: check that the `url` and `references` of the advisory use
+    /// the canonical form of well-known links (see [`canonical_url`]).
+    ///
+    /// Whether the URLs actually resolve isn't checked here, since that
+    /// requires network access.
+    pub fn lint_url_canonicalization(&mut self) {
+        let url = self.advisory.metadata.url.iter().map(|url| ("url", url));
+        let references = self
+            .advisory
+            .metadata
+            .references
+            .iter()
+            .map(|url| ("references", url));
+
+        for (key, url) in url.chain(references) {
+            if let Some(canonical) = canonical_url(url) {
+                self.errors.push(Error {
+                    kind: ErrorKind::NonCanonicalUrl {
+                        name: key.to_owned(),
+                        url: url.to_string(),
+                        canonical: canonical.to_string(),
+                    },
+                    section: Some("advisory"),
+                    message: None,
+                });
+            }
+        }
+    }
+
+    /// Opt-in lint: check that the advisories this advisory refers to in
+    /// `related`, `supersedes` and `superseded_by` exist in the given
+    /// database.
+    ///
+    /// Only RustSec IDs are looked up in `related`, since it may also list
+    /// e.g. CVE IDs. `supersedes` and `superseded_by` must only list RustSec
+    /// advisories, and no advisory may refer to itself.
+    pub fn lint_references(&mut self, database: &Database) {
+        let metadata = &self.advisory.metadata;
+        let references = [
+            ("related", &metadata.related),
+            ("supersedes", &metadata.supersedes),
+            ("superseded_by", &metadata.superseded_by),
+        ];
+
+        for (key, ids) in references {
+            for id in ids {
+                let message = if id == &metadata.id {
+                    "advisory refers to itself"
+                } else if !id.is_rustsec() {
+                    if key == "related" {
+                        continue;
+                    }
+
+                    "expected a RustSec advisory ID"
+                } else if database.get(id).is_none() {
+                    "no such advisory in the database"
+                } else {
+                    continue;
+                };
+
+                self.errors.push(Error {
+                    kind: ErrorKind::value(key, id.to_string()),
+                    section: Some("advisory"),
+                    message: Some(message),
+                });
+            }
+        }
+    }
+
     /// Lint the provided TOML value as the toplevel table of an advisory
     fn lint_advisory(&mut self, advisory: &toml::Value) {
         if let Some(table) = advisory.as_table() {
@@ -216,8 +288,9 @@ impl Linter {
                             }
                         }
                     }
-                    "aliases" | "cvss" | "keywords" | "package" | "references" | "related"
-                    | "title" | "withdrawn" | "description" => (),
+                    "additional_cvss" | "aliases" | "cvss" | "keywords" | "package"
+                    | "references" | "related" | "supersedes" | "superseded_by" | "title"
+                    | "withdrawn" | "description" => (),
                     _ => self.errors.push(Error {
                         kind: ErrorKind::key(key),
                         section: Some("advisory"),
@@ -270,6 +343,21 @@ impl Linter {
                             }
                         }
                     }
+                    "features" => {
+                        for feature in &self.advisory.affected.as_ref().unwrap().features {
+                            let valid = !feature.is_empty()
+                                && feature.chars().all(|c| {
+                                    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.')
+                                });
+                            if !valid {
+                                self.errors.push(Error {
+                                    kind: ErrorKind::value("features", feature.clone()),
+                                    section: Some("affected"),
+                                    message: Some("invalid feature name"),
+                                });
+                            }
+                        }
+                    }
                     "arch" | "os" => (),
                     _ => self.errors.push(Error {
                         kind: ErrorKind::key(key),
@@ -282,6 +370,215 @@ impl Linter {
     }
 }
 
+/// Get the canonical form of a well-known link, or `None` if it is already
+/// canonical (or not a link we know about).
+///
+/// - GitHub and crates.io links use `https://` and no `www.` prefix
+/// - GitHub issue and pull request links point at the issue or pull request
+///   itself (e.g. not at `/files`), without query string
+/// - crates.io links don't use the legacy `/#/` form or a trailing slash
+pub fn canonical_url(url: &Url) -> Option<Url> {
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    if host != "github.com" && host != "crates.io" {
+        return None;
+    }
+
+    let mut canonical = url.clone();
+    canonical.set_scheme("https").ok()?;
+    canonical.set_host(Some(host)).ok()?;
+
+    if host == "github.com" {
+        let segments: Vec<_> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+        if segments.len() > 4 && matches!(segments[2], "issues" | "pull") {
+            canonical.set_path(&format!("/{}", segments[..4].join("/")));
+        } else if url.path().ends_with('/') && segments.len() >= 4 {
+            canonical.set_path(&format!("/{}", segments.join("/")));
+        }
+
+        if matches!(segments.get(2), Some(&"issues") | Some(&"pull")) {
+            canonical.set_query(None);
+        }
+    } else {
+        // Links from before crates.io used the history API
+        if let Some(path) = url.fragment().filter(|f| f.starts_with('/')) {
+            if url.path() == "/" {
+                canonical.set_path(path);
+                canonical.set_fragment(None);
+            }
+        }
+
+        let path = canonical.path().to_owned();
+        if path.len() > 1 && path.ends_with('/') {
+            canonical.set_path(path.trim_end_matches('/'));
+        }
+    }
+
+    if &canonical == url {
+        None
+    } else {
+        Some(canonical)
+    }
+}
+
+/// Pair of advisories in a database which appear to describe the same
+/// vulnerability
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Duplicate {
+    /// Advisory which was seen first
+    pub first: Id,
+
+    /// Advisory which appears to duplicate it
+    pub second: Id,
+
+    /// Why the advisories are considered duplicates
+    pub reason: DuplicateReason,
+}
+
+/// Reasons for considering two advisories duplicates
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DuplicateReason {
+    /// Both advisories list the same CVE or GHSA alias
+    Alias(Id),
+
+    /// Both advisories are for the same crate and overlapping versions, and
+    /// either affect the same function or have identical version ranges and
+    /// dates
+    SameBug,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            DuplicateReason::Alias(alias) => write!(
+                f,
+                "{} and {} share the alias {}",
+                self.first, self.second, alias
+            ),
+            DuplicateReason::SameBug => write!(
+                f,
+                "{} and {} appear to describe the same bug",
+                self.first, self.second
+            ),
+        }
+    }
+}
+
+/// Find advisories which duplicate one another across the whole database.
+///
+/// Per-file linting can't see these: two advisories sharing a CVE or GHSA
+/// alias, or describing the same bug in the same crate. Withdrawn advisories
+/// are ignored, as are aliases already listed as `related` by the other
+/// advisory.
+pub fn find_duplicates(db: &Database) -> Vec<Duplicate> {
+    let advisories: Vec<&Advisory> = db
+        .iter()
+        .filter(|advisory| advisory.metadata.withdrawn.is_none())
+        .collect();
+
+    let mut duplicates = vec![];
+    let mut aliases: BTreeMap<&Id, &Advisory> = BTreeMap::new();
+
+    for advisory in &advisories {
+        for alias in &advisory.metadata.aliases {
+            if !(alias.is_cve() || alias.is_ghsa()) {
+                continue;
+            }
+
+            match aliases.get(alias) {
+                Some(first) if !is_related(first, advisory) => duplicates.push(Duplicate {
+                    first: first.id().clone(),
+                    second: advisory.id().clone(),
+                    reason: DuplicateReason::Alias(alias.clone()),
+                }),
+                Some(_) => (),
+                None => {
+                    aliases.insert(alias, advisory);
+                }
+            }
+        }
+    }
+
+    let mut by_package: BTreeMap<&str, Vec<&Advisory>> = BTreeMap::new();
+
+    for advisory in &advisories {
+        by_package
+            .entry(advisory.metadata.package.as_str())
+            .or_default()
+            .push(advisory);
+    }
+
+    for package_advisories in by_package.values() {
+        for (i, first) in package_advisories.iter().enumerate() {
+            for second in &package_advisories[i + 1..] {
+                let already_reported = duplicates.iter().any(|duplicate| {
+                    duplicate.first == *first.id() && duplicate.second == *second.id()
+                });
+
+                if !already_reported && !is_related(first, second) && is_same_bug(first, second) {
+                    duplicates.push(Duplicate {
+                        first: first.id().clone(),
+                        second: second.id().clone(),
+                        reason: DuplicateReason::SameBug,
+                    });
+                }
+            }
+        }
+    }
+
+    duplicates
+}
+
+/// Does either advisory list the other as related?
+fn is_related(a: &Advisory, b: &Advisory) -> bool {
+    a.metadata.related.contains(b.id()) || b.metadata.related.contains(a.id())
+}
+
+/// Do two advisories for the same crate appear to describe the same bug?
+fn is_same_bug(a: &Advisory, b: &Advisory) -> bool {
+    if a.metadata.collection != b.metadata.collection
+        || a.metadata.informational != b.metadata.informational
+    {
+        return false;
+    }
+
+    let a_ranges = ranges_for_advisory(&a.versions);
+    let b_ranges = ranges_for_advisory(&b.versions);
+
+    let overlapping = a_ranges.iter().any(|a_range| {
+        b_ranges
+            .iter()
+            .any(|b_range| ranges_overlap(a_range, b_range))
+    });
+
+    if !overlapping {
+        return false;
+    }
+
+    let shared_function = match (&a.affected, &b.affected) {
+        (Some(a_affected), Some(b_affected)) => a_affected
+            .functions
+            .keys()
+            .any(|path| b_affected.functions.contains_key(path)),
+        _ => false,
+    };
+
+    shared_function || (a.versions == b.versions && a.metadata.date == b.metadata.date)
+}
+
+/// Do two `[introduced, fixed)` ranges share any versions?
+fn ranges_overlap(a: &OsvRange, b: &OsvRange) -> bool {
+    let starts_before_end = |start: &Option<_>, end: &Option<_>| match (start, end) {
+        (Some(start), Some(end)) => start < end,
+        _ => true,
+    };
+
+    starts_before_end(&a.introduced, &b.fixed) && starts_before_end(&b.introduced, &a.fixed)
+}
+
 /// Lint errors
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Error {
@@ -351,6 +648,18 @@ pub enum ErrorKind {
         /// Invalid value
         value: String,
     },
+
+    /// Link which isn't in its canonical form
+    NonCanonicalUrl {
+        /// Name of the key
+        name: String,
+
+        /// URL as given
+        url: String,
+
+        /// Canonical form of the URL
+        canonical: String,
+    },
 }
 
 impl ErrorKind {
@@ -378,6 +687,15 @@ impl fmt::Display for ErrorKind {
             ErrorKind::InvalidValue { name, value } => {
                 write!(f, "invalid value `{}` for key `{}`", value, name)
             }
+            ErrorKind::NonCanonicalUrl {
+                name,
+                url,
+                canonical,
+            } => write!(
+                f,
+                "non-canonical URL `{}` for key `{}` (use `{}`)",
+                url, name, canonical
+            ),
         }
     }
 }
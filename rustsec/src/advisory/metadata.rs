@@ -1,10 +1,11 @@
 //! Advisory information (i.e. the `[advisory]` section)
 
 use super::{
-    category::Category, date::Date, id::Id, informational::Informational, keyword::Keyword,
+    category::Category, cvss_score::CvssScore, date::Date, id::Id, informational::Informational,
+    keyword::Keyword,
 };
 use crate::advisory::license::License;
-use crate::{collection::Collection, package, SourceId};
+use crate::{collection::Collection, cwe::Cwe, package, SourceId};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -37,6 +38,18 @@ pub struct Metadata {
     #[serde(default)]
     pub related: Vec<Id>,
 
+    /// Advisory IDs which this advisory supersedes, e.g. when an advisory is
+    /// re-filed with a corrected scope. Superseded advisories are omitted from
+    /// queries which exclude them, in favor of this advisory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supersedes: Vec<Id>,
+
+    /// Advisory IDs which supersede this advisory, i.e. the reverse of
+    /// `supersedes`, for advisories which are replaced without the
+    /// replacement declaring it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub superseded_by: Vec<Id>,
+
     /// Collection this advisory belongs to. This isn't intended to be
     /// explicitly specified in the advisory, but rather is auto-populated
     /// based on the location
@@ -60,6 +73,17 @@ pub struct Metadata {
     /// ```
     pub cvss: Option<cvss::v3::Base>,
 
+    /// CVSS vectors in addition to the primary `cvss` vector, e.g. with
+    /// different scores per affected configuration or per source.
+    ///
+    /// Example:
+    ///
+    /// ```text
+    /// additional_cvss = [{ vector = "CVSS:3.1/AV:N/AC:H/PR:N/UI:R/S:U/C:L/I:L/A:N", source = "NVD" }]
+    /// ```
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_cvss: Vec<CvssScore>,
+
     /// Informational advisories can be used to warn users about issues
     /// affecting a particular crate without failing the build.
     pub informational: Option<Informational>,
@@ -87,3 +111,49 @@ pub struct Metadata {
     #[serde(default)]
     pub license: License,
 }
+
+impl Metadata {
+    /// Get the primary CVSS vector of this advisory: the `cvss` vector if
+    /// set, otherwise the highest-scoring of the `additional_cvss` vectors
+    /// (i.e. the worst case).
+    pub fn primary_cvss(&self) -> Option<&cvss::v3::Base> {
+        self.cvss.as_ref().or_else(|| {
+            self.additional_cvss
+                .iter()
+                .map(|score| &score.vector)
+                .max_by(|a, b| a.score().value().total_cmp(&b.score().value()))
+        })
+    }
+
+    /// Iterate over all CVSS vectors of this advisory, starting with the
+    /// `cvss` vector (if set)
+    pub fn cvss_vectors(&self) -> impl Iterator<Item = &cvss::v3::Base> {
+        self.cvss
+            .iter()
+            .chain(self.additional_cvss.iter().map(|score| &score.vector))
+    }
+
+    /// Get the [CWE] weaknesses of this advisory, derived from its categories
+    /// (see [`Category::cwe`]) and its `cwe-<id>` keywords, sorted by id.
+    ///
+    /// [CWE]: https://cwe.mitre.org/
+    pub fn cwes(&self) -> Vec<Cwe> {
+        let mut cwes: Vec<Cwe> = self
+            .categories
+            .iter()
+            .filter_map(Category::cwe)
+            .map(Cwe::new)
+            .chain(self.keywords.iter().filter_map(|keyword| {
+                let keyword = keyword.as_str();
+                keyword
+                    .get(..4)
+                    .filter(|prefix| prefix.eq_ignore_ascii_case("cwe-"))
+                    .and_then(|_| keyword.parse().ok())
+            }))
+            .collect();
+
+        cwes.sort();
+        cwes.dedup();
+        cwes
+    }
+}
@@ -23,10 +23,10 @@ impl<'a> Parts<'a> {
     /// Parse a Markdown advisory into its component parts
     pub fn parse(advisory_data: &'a str) -> Result<Self, Error> {
         if !advisory_data.starts_with("```toml") {
-            let context = if advisory_data.len() > 20 {
-                &advisory_data[..20]
-            } else {
-                advisory_data
+            // Take up to 20 characters, never splitting a multi-byte character
+            let context = match advisory_data.char_indices().nth(20) {
+                Some((end, _)) => &advisory_data[..end],
+                None => advisory_data,
             };
 
             fail!(
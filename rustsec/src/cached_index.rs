@@ -1,12 +1,21 @@
 //! An efficient way to check whether a given package has been yanked
 use std::{
     collections::{BTreeSet, HashMap},
+    sync::{atomic::Ordering, Arc},
+    thread,
     time::Duration,
 };
 
 use crate::{
+    advisory,
+    dependency_path::{DependencyPath, PathPackage},
     error::{Error, ErrorKind},
+    network::{self, NetworkConfig},
     package::{self, Package},
+    progress::{Counter, GixProgress, Progress},
+    release::ReleaseInfo,
+    upgrade::UpgradeSuggestion,
+    Version, VersionReq, Vulnerability,
 };
 
 pub use tame_index::external::reqwest::ClientBuilder;
@@ -37,6 +46,16 @@ impl Index {
     }
 }
 
+/// Information about a release of a crate in the index
+struct IndexRelease {
+    /// Has the release been yanked?
+    yanked: bool,
+
+    /// Names of the crates the release depends on, along with the version
+    /// requirements on them (unparsed, like the versions above)
+    dependencies: Vec<(String, String)>,
+}
+
 /// Provides an efficient way to check if the given package has been yanked.
 ///
 /// Operations on crates.io index are rather slow.
@@ -48,12 +67,16 @@ impl Index {
 /// will not be possible while this lock is held.
 pub struct CachedIndex {
     index: Index,
-    /// The inner hash map is logically HashMap<Version, IsYanked>
+    /// The inner hash map is logically HashMap<Version, IndexRelease>
     /// but we don't parse semver because crates.io registry contains invalid semver:
     /// <https://github.com/rustsec/rustsec/issues/759>
-    cache: HashMap<package::Name, Result<Option<HashMap<String, bool>>, Error>>,
+    cache: HashMap<package::Name, Result<Option<HashMap<String, IndexRelease>>, Error>>,
     /// The lock we hold on the Cargo cache directory
     lock: FileLock,
+    /// Where to report the progress of fetching the index to, if anywhere
+    progress: Option<Arc<dyn Progress>>,
+    /// Timeouts and retries for fetching the index
+    network: NetworkConfig,
 }
 
 impl CachedIndex {
@@ -73,31 +96,92 @@ impl CachedIndex {
     /// If `lock_timeout` is set to `std::time::Duration::from_secs(0)`, it will not wait at all,
     /// and instead return an error immediately if it fails to aquire the lock.
     pub fn fetch(client: Option<ClientBuilder>, lock_timeout: Duration) -> Result<Self, Error> {
-        Self::fetch_inner(client, lock_timeout).map_err(Error::from_tame)
+        Self::fetch_with_options(client, lock_timeout, NetworkConfig::default(), None)
+    }
+
+    /// Same as [`CachedIndex::fetch`], but reports the progress of fetching
+    /// the index to `progress`: objects received for a git index, or crates
+    /// fetched for a sparse index (when they are looked up later on).
+    pub fn fetch_with_progress(
+        client: Option<ClientBuilder>,
+        lock_timeout: Duration,
+        progress: Arc<dyn Progress>,
+    ) -> Result<Self, Error> {
+        Self::fetch_with_options(
+            client,
+            lock_timeout,
+            NetworkConfig::default(),
+            Some(progress),
+        )
+    }
+
+    /// Same as [`CachedIndex::fetch`], but with the given timeouts and
+    /// retries for fetching the index, and optionally reporting progress to
+    /// `progress`.
+    ///
+    /// The timeouts only apply to the default client, i.e. if `client` is
+    /// `None`. Crates which can't be fetched from a sparse index after the
+    /// configured retries are reported with the
+    /// [`ErrorKind::RetriesExhausted`] kind.
+    pub fn fetch_with_options(
+        client: Option<ClientBuilder>,
+        lock_timeout: Duration,
+        network: NetworkConfig,
+        progress: Option<Arc<dyn Progress>>,
+    ) -> Result<Self, Error> {
+        let result = Self::fetch_inner(client, lock_timeout, network, progress.clone());
+
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
+        result
     }
 
     fn fetch_inner(
         client: Option<ClientBuilder>,
         lock_timeout: Duration,
-    ) -> Result<Self, tame_index::Error> {
-        let index = tame_index::index::ComboIndexCache::new(tame_index::IndexLocation::new(
-            tame_index::IndexUrl::crates_io(None, None, None)?,
-        ))?;
+        network: NetworkConfig,
+        progress: Option<Arc<dyn Progress>>,
+    ) -> Result<Self, Error> {
+        let index = tame_index::IndexUrl::crates_io(None, None, None)
+            .and_then(|url| {
+                tame_index::index::ComboIndexCache::new(tame_index::IndexLocation::new(url))
+            })
+            .map_err(Error::from_tame)?;
 
-        let lock = acquire_cargo_package_lock(lock_timeout)?;
+        let lock = acquire_cargo_package_lock(lock_timeout).map_err(Error::from_tame)?;
 
         let index = match index {
             tame_index::index::ComboIndexCache::Git(gi) => {
-                let mut rgi = tame_index::index::RemoteGitIndex::new(gi, &lock)?;
-                rgi.fetch(&lock)?;
+                let should_interrupt = &tame_index::external::gix::interrupt::IS_INTERRUPTED;
+                let mut rgi = tame_index::index::RemoteGitIndex::with_options(
+                    gi,
+                    GixProgress::new(progress.clone()),
+                    should_interrupt,
+                    &lock,
+                )
+                .map_err(Error::from_tame)?;
+
+                network.retry("updating the crates.io index", || {
+                    rgi.fetch_with_options(
+                        GixProgress::new(progress.clone()),
+                        should_interrupt,
+                        &lock,
+                    )
+                    .map_err(Error::from_tame)
+                })?;
+
                 Index::Git(rgi)
             }
             tame_index::index::ComboIndexCache::Sparse(si) => {
-                let client_builder = client.unwrap_or_default();
+                let client_builder = client.unwrap_or_else(|| network.client_builder());
                 // note: this would need to change if rustsec ever adds the capability
                 // to query other indices that _might_ not support HTTP/2, but
                 // hopefully that would never need to happen
-                let client = client_builder.build().map_err(tame_index::Error::from)?;
+                let client = client_builder
+                    .build()
+                    .map_err(|err| Error::from_tame(err.into()))?;
 
                 Index::SparseRemote(tame_index::index::AsyncRemoteSparseIndex::new(si, client))
             }
@@ -108,6 +192,8 @@ impl CachedIndex {
             index,
             cache: Default::default(),
             lock,
+            progress,
+            network,
         })
     }
 
@@ -149,6 +235,8 @@ impl CachedIndex {
             index,
             cache: Default::default(),
             lock,
+            progress: None,
+            network: NetworkConfig::default(),
         })
     }
 
@@ -156,19 +244,44 @@ impl CachedIndex {
     fn populate_cache(&mut self, mut packages: BTreeSet<&package::Name>) -> Result<(), Error> {
         // only look up info on packages that aren't yet cached
         packages.retain(|pkg| !self.cache.contains_key(pkg));
+        if packages.is_empty() {
+            return Ok(());
+        }
+
+        let fetched = Counter::default();
+        if let Some(progress) = &self.progress {
+            progress.stage("fetching crates", Some(packages.len()), fetched.clone());
+        }
+
+        let result = self.fetch_crates(packages, &fetched);
+
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+
+        result
+    }
+
+    /// Fetch the index entries of the given crates, counting them in `fetched`
+    fn fetch_crates(
+        &mut self,
+        packages: BTreeSet<&package::Name>,
+        fetched: &Counter,
+    ) -> Result<(), Error> {
         match &self.index {
             Index::Git(_) | Index::SparseCached(_) => {
                 for pkg in packages {
                     self.insert(pkg.to_owned(), self.index.krate(pkg, &self.lock));
+                    fetched.fetch_add(1, Ordering::Relaxed);
                 }
             }
             Index::SparseRemote(rsi) => {
                 // Ensure we have a runtime
                 let rt = tame_index::external::tokio::runtime::Runtime::new().map_err(|err| {
-                    format_err!(
+                    Error::with_source(
                         ErrorKind::Registry,
-                        "unable to start a tokio runtime: {}",
-                        err
+                        "unable to start a tokio runtime".to_owned(),
+                        err,
                     )
                 })?;
                 let _rt = rt.enter();
@@ -178,29 +291,65 @@ impl CachedIndex {
                 /// this time limit is reached
                 const REQUEST_TIMEOUT: Option<Duration> = Some(Duration::from_secs(10));
 
-                let results = rsi
-                    .krates_blocking(
-                        packages
-                            .into_iter()
-                            .map(|p| p.as_str().to_owned())
-                            .collect(),
-                        true,
-                        REQUEST_TIMEOUT,
-                        &self.lock,
-                    )
-                    .map_err(|err| {
-                        format_err!(
-                            ErrorKind::Registry,
-                            "unable to acquire tokio runtime: {}",
-                            err
+                let mut pending: BTreeSet<String> = packages
+                    .into_iter()
+                    .map(|p| p.as_str().to_owned())
+                    .collect();
+                let mut finished = Vec::with_capacity(pending.len());
+                let mut retry = 0;
+
+                while !pending.is_empty() {
+                    let results = rsi
+                        .krates_blocking(
+                            std::mem::take(&mut pending),
+                            true,
+                            REQUEST_TIMEOUT,
+                            &self.lock,
                         )
-                    })?;
+                        .map_err(|err| {
+                            Error::with_source(
+                                ErrorKind::Registry,
+                                "unable to acquire tokio runtime".to_owned(),
+                                err,
+                            )
+                        })?;
+
+                    for (name, res) in results {
+                        let res = res.map_err(Error::from_tame);
+
+                        // Crates which failed for a reason which may be
+                        // transient are requested again in the next round
+                        if let Err(err) = &res {
+                            if network::is_transient(err.kind()) && retry < self.network.retries {
+                                pending.insert(name);
+                                continue;
+                            }
+                        }
+
+                        let res = res.map_err(|err| {
+                            if network::is_transient(err.kind()) && self.network.retries > 0 {
+                                network::exhausted(
+                                    &format!("fetching `{}` from the crates.io index", name),
+                                    self.network.retries,
+                                    err,
+                                )
+                            } else {
+                                err
+                            }
+                        });
+
+                        finished.push((name, res));
+                        fetched.fetch_add(1, Ordering::Relaxed);
+                    }
+
+                    if !pending.is_empty() {
+                        retry += 1;
+                        thread::sleep(self.network.delay(retry));
+                    }
+                }
 
-                for (name, res) in results {
-                    self.insert(
-                        name.parse().expect("this was a package name before"),
-                        res.map_err(Error::from_tame),
-                    );
+                for (name, res) in finished {
+                    self.insert(name.parse().expect("this was a package name before"), res);
                 }
             }
         }
@@ -218,7 +367,17 @@ impl CachedIndex {
             ik.map(|ik| {
                 ik.versions
                     .into_iter()
-                    .map(|v| (v.version.to_string(), v.is_yanked()))
+                    .map(|v| {
+                        let release = IndexRelease {
+                            yanked: v.is_yanked(),
+                            dependencies: v
+                                .dependencies()
+                                .iter()
+                                .map(|dep| (dep.crate_name().to_owned(), dep.req.to_string()))
+                                .collect(),
+                        };
+                        (v.version.to_string(), release)
+                    })
                     .collect()
             })
         });
@@ -226,39 +385,216 @@ impl CachedIndex {
         self.cache.insert(package, krate_res);
     }
 
-    /// Is the given package yanked?
-    fn is_yanked(&mut self, package: &Package) -> Result<bool, Error> {
-        if !self.cache.contains_key(&package.name) {
-            self.insert(
-                package.name.to_owned(),
-                self.index.krate(&package.name, &self.lock),
-            );
+    /// Get the releases of the given crate in the index, by version
+    fn versions(&mut self, name: &package::Name) -> Result<&HashMap<String, IndexRelease>, Error> {
+        if !self.cache.contains_key(name) {
+            self.insert(name.to_owned(), self.index.krate(name, &self.lock));
         }
 
-        match &self.cache[&package.name] {
-            Ok(Some(ik)) => match ik.get(&package.version.to_string()) {
-                Some(is_yanked) => Ok(*is_yanked),
-                None => Err(format_err!(
-                    ErrorKind::NotFound,
-                    "No such version in crates.io index: {} {}",
-                    &package.name,
-                    &package.version
-                )),
-            },
+        match &self.cache[name] {
+            Ok(Some(ik)) => Ok(ik),
             Ok(None) => Err(format_err!(
                 ErrorKind::NotFound,
                 "No such crate in crates.io index: {}",
-                &package.name,
+                name,
             )),
             Err(err) => Err(format_err!(
                 ErrorKind::Registry,
                 "Failed to retrieve {} from crates.io index: {}",
-                &package.name,
+                name,
                 err,
             )),
         }
     }
 
+    /// Is the given package yanked?
+    fn is_yanked(&mut self, package: &Package) -> Result<bool, Error> {
+        match self
+            .versions(&package.name)?
+            .get(&package.version.to_string())
+        {
+            Some(release) => Ok(release.yanked),
+            None => Err(format_err!(
+                ErrorKind::NotFound,
+                "No such version in crates.io index: {} {}",
+                &package.name,
+                &package.version
+            )),
+        }
+    }
+
+    /// Find out how the given package's version relates to the other releases
+    /// of the same crate in the index
+    fn lookup_release_info(&mut self, package: &Package) -> Result<ReleaseInfo, Error> {
+        // Skip versions which aren't valid semver (see above)
+        let releases = self
+            .versions(&package.name)?
+            .iter()
+            .filter(|(_, release)| !release.yanked)
+            .filter_map(|(version, _)| version.parse().ok());
+
+        Ok(ReleaseInfo::new(&package.version, releases))
+    }
+
+    /// Look up how each of the provided packages relates to the other releases
+    /// of the same crate (i.e. how many newer releases there are), returning
+    /// the results in the same order as the packages.
+    ///
+    /// Release dates are not part of the index, see
+    /// [`CratesIoClient::release_date`](crate::registry::CratesIoClient::release_date).
+    ///
+    /// Like [`CachedIndex::find_yanked`], this should be called with many
+    /// packages at once rather than one by one.
+    pub fn release_info<'a, I>(&mut self, packages: I) -> Vec<Result<ReleaseInfo, Error>>
+    where
+        I: IntoIterator<Item = &'a Package>,
+    {
+        let packages: Vec<&Package> = packages.into_iter().collect();
+        let package_names: BTreeSet<&package::Name> = packages.iter().map(|p| &p.name).collect();
+
+        // If this fails, packages are looked up individually below, which
+        // reports an error for each package which is still missing
+        let _ = self.populate_cache(package_names);
+
+        packages
+            .iter()
+            .map(|package| self.lookup_release_info(package))
+            .collect()
+    }
+
+    /// Releases of the given package which aren't yanked and aren't older
+    /// than the version in use, along with their dependencies
+    fn upgrade_candidates(
+        &mut self,
+        package: &PathPackage,
+    ) -> Result<Vec<(Version, Vec<(String, String)>)>, Error> {
+        let mut candidates = vec![];
+
+        for (version, release) in self.versions(&package.name)? {
+            if release.yanked {
+                continue;
+            }
+
+            // Skip versions which aren't valid semver (see above)
+            let version: Version = match version.parse() {
+                Ok(version) => version,
+                Err(_) => continue,
+            };
+
+            // Only consider pre-releases for packages which are on a pre-release
+            if !version.pre.is_empty() && package.version.pre.is_empty() {
+                continue;
+            }
+
+            if version >= package.version {
+                candidates.push((version, release.dependencies.clone()));
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Suggest the smallest upgrade along the given dependency path which
+    /// allows a version of the vulnerable package at its end that isn't
+    /// affected according to `versions`.
+    ///
+    /// Starting at the vulnerable package, this walks the path towards its
+    /// root until it finds a package whose version in use already allows an
+    /// upgraded version of its dependency on the path, which is then the
+    /// package to upgrade. The root package (e.g. a workspace member) is
+    /// usually not published, so its requirements are assumed to allow
+    /// upgrading its direct dependency.
+    fn suggest_upgrade(
+        &mut self,
+        path: &DependencyPath,
+        versions: &advisory::Versions,
+    ) -> Result<Option<UpgradeSuggestion>, Error> {
+        let packages = path.packages();
+        if packages.len() < 2 {
+            return Ok(None);
+        }
+
+        // Position of the package to upgrade along the path
+        let mut position = packages.len() - 1;
+
+        // Versions the package could be upgraded to
+        let mut acceptable: Vec<Version> = self
+            .upgrade_candidates(&packages[position])?
+            .into_iter()
+            .map(|(version, _)| version)
+            .filter(|version| !versions.is_vulnerable(version))
+            .collect();
+
+        while position > 1 && !acceptable.is_empty() {
+            let dependent = &packages[position - 1];
+            let dependency = packages[position].name.as_str();
+
+            let allows = |dependencies: &[(String, String)]| {
+                dependencies.iter().any(|(name, req)| {
+                    name == dependency
+                        && req.parse::<VersionReq>().map_or(false, |req| {
+                            acceptable.iter().any(|version| req.matches(version))
+                        })
+                })
+            };
+
+            let candidates = self.upgrade_candidates(dependent)?;
+
+            if candidates.iter().any(|(version, dependencies)| {
+                version == &dependent.version && allows(dependencies)
+            }) {
+                break;
+            }
+
+            acceptable = candidates
+                .iter()
+                .filter(|(_, dependencies)| allows(dependencies))
+                .map(|(version, _)| version.clone())
+                .collect();
+
+            position -= 1;
+        }
+
+        Ok(Some(UpgradeSuggestion {
+            dependent: packages[position - 1].name.clone(),
+            package: packages[position].name.clone(),
+            current: packages[position].version.clone(),
+            upgrade_to: acceptable.into_iter().min(),
+        }))
+    }
+
+    /// Suggest upgrades which resolve the given vulnerability, one for each
+    /// of its dependency paths (see [`Vulnerability::paths`]), using the
+    /// dependency requirements of the releases in the index.
+    ///
+    /// Vulnerabilities without dependency paths get no suggestions.
+    pub fn suggest_upgrades(
+        &mut self,
+        vulnerability: &Vulnerability,
+    ) -> Result<Vec<UpgradeSuggestion>, Error> {
+        // Root packages are skipped since they usually aren't published. If
+        // this fails, packages are looked up individually below.
+        let package_names: BTreeSet<&package::Name> = vulnerability
+            .paths
+            .iter()
+            .flat_map(|path| path.packages().iter().skip(1))
+            .map(|package| &package.name)
+            .collect();
+        let _ = self.populate_cache(package_names);
+
+        let mut suggestions = vec![];
+
+        for path in &vulnerability.paths {
+            if let Some(suggestion) = self.suggest_upgrade(path, &vulnerability.versions)? {
+                if !suggestions.contains(&suggestion) {
+                    suggestions.push(suggestion);
+                }
+            }
+        }
+
+        Ok(suggestions)
+    }
+
     /// Iterate over the provided packages, returning a vector of the
     /// packages which have been yanked.
     ///
@@ -288,7 +624,9 @@ impl CachedIndex {
             match self.is_yanked(package) {
                 Ok(false) => {} // not yanked, nothing to report
                 Ok(true) => yanked.push(Ok(package)),
-                Err(error) => yanked.push(Err(error)),
+                Err(error) => yanked.push(Err(error
+                    .with_package(package.name.clone())
+                    .with_version(package.version.to_string()))),
             }
         }
 
@@ -0,0 +1,197 @@
+//! C API for auditing lockfiles from other languages, e.g. in build systems
+//! and IDE plugins which can't depend on `cargo audit` being installed.
+//!
+//! Build it as a shared or static library with:
+//!
+//! ```text
+//! cargo rustc -p rustsec --release --features capi --crate-type cdylib
+//! ```
+//!
+//! The functions are declared in `include/rustsec.h`. Functions which fail
+//! return a null pointer, and [`rustsec_last_error`] describes why. Panics
+//! never unwind into the caller: they're reported as failures too. Strings
+//! returned by these functions must be released with [`rustsec_string_free`],
+//! and databases with [`rustsec_db_free`].
+
+#![allow(unsafe_code)]
+
+use crate::{
+    error::{Error, ErrorKind},
+    report, Database, Lockfile, Report,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+};
+
+thread_local! {
+    /// Message of the last error which occurred on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Open the advisory database in the given local directory, e.g. a clone of
+/// the advisory database repository.
+///
+/// Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rustsec_db_open(path: *const c_char) -> *mut Database {
+    catch(|| {
+        let db = path_arg(path).and_then(Database::open)?;
+        Ok(Box::into_raw(Box::new(db)))
+    })
+}
+
+/// Free a database opened with [`rustsec_db_open`].
+///
+/// # Safety
+///
+/// `db` must be null or a database returned by [`rustsec_db_open`] which
+/// hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rustsec_db_free(db: *mut Database) {
+    if !db.is_null() {
+        catch(|| {
+            drop(Box::from_raw(db));
+            Ok(ptr::null_mut::<()>())
+        });
+    }
+}
+
+/// Audit the given `Cargo.lock` file against the database, returning the
+/// report as JSON, in the same format as `cargo audit --json`.
+///
+/// Returns null on failure.
+///
+/// # Safety
+///
+/// `db` must be null or a database returned by [`rustsec_db_open`] which
+/// hasn't been freed yet, and `lockfile_path` must be null or point to a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rustsec_audit_lockfile(
+    db: *const Database,
+    lockfile_path: *const c_char,
+) -> *mut c_char {
+    catch(|| {
+        let db = db
+            .as_ref()
+            .ok_or_else(|| format_err!(ErrorKind::BadParam, "database is null"))?;
+
+        let json = audit_lockfile(db, path_arg(lockfile_path)?)?;
+        Ok(json.into_raw())
+    })
+}
+
+/// Get a description of the last error which occurred on the calling thread,
+/// or null if there was none.
+///
+/// The returned string must be freed with [`rustsec_string_free`].
+#[no_mangle]
+pub extern "C" fn rustsec_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library which hasn't
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rustsec_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        catch(|| {
+            drop(CString::from_raw(string));
+            Ok(ptr::null_mut::<()>())
+        });
+    }
+}
+
+/// Audit a lockfile, serializing the report as JSON
+fn audit_lockfile(db: &Database, lockfile_path: &Path) -> Result<CString, Error> {
+    let lockfile = Lockfile::load(lockfile_path)?;
+    let report = Report::generate(db, &lockfile, &report::Settings::default());
+
+    let json = serde_json::to_string(&report).map_err(|e| {
+        Error::with_source(ErrorKind::Parse, "couldn't serialize report".to_owned(), e)
+    })?;
+
+    // Serialized JSON never contains NUL bytes, which are escaped in strings
+    Ok(CString::new(json).expect("NUL byte in JSON"))
+}
+
+/// Convert a path passed from C
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string.
+unsafe fn path_arg<'a>(path: *const c_char) -> Result<&'a Path, Error> {
+    if path.is_null() {
+        fail!(ErrorKind::BadParam, "path is null");
+    }
+
+    Ok(Path::new(CStr::from_ptr(path).to_str()?))
+}
+
+/// Run the body of an exported function, turning errors and panics into a
+/// null return value and the last error on this thread
+fn catch<T>(f: impl FnOnce() -> Result<*mut T, Error>) -> *mut T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => fail_with(e),
+        Err(payload) => fail_with(format_err!(
+            ErrorKind::Panic,
+            panic_message(payload.as_ref())
+        )),
+    }
+}
+
+/// Message of a panic, if it has one
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown cause"
+    }
+}
+
+/// Record an error as the last error on this thread, returning null
+fn fail_with<T>(error: Error) -> *mut T {
+    // NUL bytes can't be represented in C strings
+    let message = error.to_string().replace('\0', "");
+    let message = CString::new(message).expect("NUL byte in error message");
+
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_errors() {
+        let result = catch(|| -> Result<*mut (), Error> { panic!("index out of bounds") });
+        assert!(result.is_null());
+
+        let message = rustsec_last_error();
+        assert!(!message.is_null());
+        let owned = unsafe { CString::from_raw(message) };
+        assert_eq!(owned.to_str().unwrap(), "panic: index out of bounds");
+    }
+}
@@ -7,20 +7,27 @@ use std::{fmt, str::FromStr};
 /// Collections of packages (`crates` vs `rust`).
 ///
 /// Advisories are either filed against crates published to <https://crates.io>
-/// or packages provided by the Rust language itself (e.g. `std`, `rustdoc`)
+/// or packages provided by the Rust language itself (e.g. `std`, `rustdoc`).
+/// Advisories about native libraries wrapped by `-sys` crates, imported from
+/// other ecosystems, are kept in a collection of their own.
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum Collection {
     /// Crates published through crates.io
     Crates,
 
     /// Rust core vulnerabilities
     Rust,
+
+    /// Vulnerabilities in native libraries, filed against the `-sys` crates
+    /// which build or link them
+    Native,
 }
 
 impl Collection {
     /// Get all collections as a slice
     pub fn all() -> &'static [Self] {
-        &[Collection::Crates, Collection::Rust]
+        &[Collection::Crates, Collection::Rust, Collection::Native]
     }
 
     /// Get a `str` representing the kind of package
@@ -28,6 +35,7 @@ impl Collection {
         match self {
             Collection::Crates => "crates",
             Collection::Rust => "rust",
+            Collection::Native => "native",
         }
     }
 }
@@ -45,6 +53,7 @@ impl FromStr for Collection {
         Ok(match s {
             "crates" => Collection::Crates,
             "rust" => Collection::Rust,
+            "native" => Collection::Native,
             other => fail!(ErrorKind::Parse, "invalid package type: {}", other),
         })
     }
@@ -0,0 +1,226 @@
+//! Client for the crates.io web API, used to look up information which
+//! isn't part of the registry index
+
+use crate::{
+    advisory::Date,
+    error::{Error, ErrorKind},
+    package,
+    transport::{HttpTransport, Request, Response, Transport},
+    Version,
+};
+use serde::Deserialize;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Base URL of the crates.io web API
+const API_URL: &str = "https://crates.io/api/v1/crates";
+
+/// Timeout for each request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of requests sent at once, to stay within the crates.io
+/// API's crawler policy
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Client for the crates.io web API
+#[derive(Clone, Debug)]
+pub struct CratesIoClient {
+    transport: Arc<dyn Transport>,
+}
+
+/// Response to a request for a particular version of a crate
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: VersionInfo,
+}
+
+/// Information about a particular version of a crate
+#[derive(Deserialize)]
+struct VersionInfo {
+    /// RFC 3339 timestamp of when the version was published
+    created_at: String,
+}
+
+impl CratesIoClient {
+    /// Create a new crates.io API client
+    pub fn new() -> Result<Self, Error> {
+        let transport = HttpTransport::new(REQUEST_TIMEOUT).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Registry,
+                "couldn't create crates.io API client".to_owned(),
+                e,
+            )
+        })?;
+
+        Ok(Self {
+            transport: Arc::new(transport),
+        })
+    }
+
+    /// Send requests through the given transport, e.g. to replay recorded
+    /// responses
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Get the date the given version of a crate was published
+    pub fn release_date(&self, name: &package::Name, version: &Version) -> Result<Date, Error> {
+        let url = format!("{}/{}/{}", API_URL, name, version);
+
+        let body = self
+            .transport
+            .send(&Request::get(url.as_str()))
+            .and_then(Response::into_body)
+            .map_err(|e| {
+                Error::with_source(
+                    ErrorKind::Registry,
+                    format!("couldn't fetch {} {} from crates.io", name, version),
+                    e,
+                )
+                .with_url(&url)
+                .with_package(name.clone())
+                .with_version(version.to_string())
+            })?;
+
+        parse_release_date(&body).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("invalid crates.io response for {} {}", name, version),
+                e,
+            )
+        })
+    }
+
+    /// Get the dates the given versions of crates were published, returning
+    /// the results in the same order as the packages.
+    ///
+    /// Up to a few requests are sent at once.
+    pub fn release_dates<'a, I>(&self, packages: I) -> Vec<Result<Date, Error>>
+    where
+        I: IntoIterator<Item = (&'a package::Name, &'a Version)>,
+    {
+        let packages: Vec<_> = packages.into_iter().collect();
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(MAX_CONCURRENT_REQUESTS)
+            .min(packages.len());
+
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<Result<Date, Error>>>> =
+            Mutex::new(packages.iter().map(|_| None).collect());
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let (name, version) = match packages.get(index) {
+                        Some(package) => *package,
+                        None => break,
+                    };
+
+                    let result = self.release_date(name, version);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("every package is looked up"))
+            .collect()
+    }
+}
+
+/// Parse the publication date out of a crates.io API response for a version
+fn parse_release_date(body: &str) -> Result<Date, Error> {
+    let response: VersionResponse = serde_json::from_str(body)
+        .map_err(|e| Error::with_source(ErrorKind::Parse, "invalid JSON".to_owned(), e))?;
+
+    // Timestamps look like `2019-03-04T12:34:56.789012+00:00`
+    let date = response
+        .version
+        .created_at
+        .split('T')
+        .next()
+        .unwrap_or_default();
+
+    date.parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transport which answers with a release date derived from the URL's
+    /// version, or fails for version `0.0.0`
+    #[derive(Debug)]
+    struct Stub;
+
+    impl Transport for Stub {
+        fn send(&self, request: &Request) -> Result<Response, Error> {
+            let version = request.url.rsplit('/').next().unwrap();
+            let day: u32 = version.rsplit('.').next().unwrap().parse().unwrap();
+            if day == 0 {
+                return Ok(Response {
+                    status: 404,
+                    body: String::new(),
+                });
+            }
+
+            Ok(Response {
+                status: 200,
+                body: format!(
+                    r#"{{"version":{{"created_at":"2023-01-{:02}T12:34:56.789012+00:00"}}}}"#,
+                    day
+                ),
+            })
+        }
+    }
+
+    #[test]
+    fn parse_dates() {
+        let date = parse_release_date(
+            r#"{"version":{"num":"1.0.0","created_at":"2019-03-04T12:34:56.789012+00:00"}}"#,
+        )
+        .unwrap();
+        assert_eq!(date.as_str(), "2019-03-04");
+
+        assert!(parse_release_date(r#"{"version":{}}"#).is_err());
+        assert!(parse_release_date(r#"{"version":{"created_at":"yesterday"}}"#).is_err());
+    }
+
+    /// Results are in the same order as the packages, whichever request
+    /// finishes first
+    #[test]
+    fn release_dates_in_order() {
+        let client = CratesIoClient::new().unwrap().transport(Arc::new(Stub));
+        let name: package::Name = "left-pad".parse().unwrap();
+        let versions: Vec<Version> = (0..10)
+            .map(|day| format!("1.0.{}", day).parse().unwrap())
+            .collect();
+
+        let dates = client.release_dates(versions.iter().map(|version| (&name, version)));
+        assert_eq!(dates.len(), versions.len());
+        assert!(dates[0].is_err());
+        for (day, date) in dates.iter().enumerate().skip(1) {
+            assert_eq!(
+                date.as_ref().unwrap().as_str(),
+                format!("2023-01-{:02}", day)
+            );
+        }
+
+        assert!(client
+            .release_dates(Vec::<(&package::Name, &Version)>::new())
+            .is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+//! Common Weakness Enumeration ([CWE]) taxonomy
+//!
+//! RustSec advisories reference CWE weaknesses through their categories
+//! (see [`Category::cwe`]) and through `cwe-<id>` keywords. This module
+//! ships a table of the weaknesses most commonly found in Rust advisories
+//! along with their names and their place in the CWE hierarchy, so they
+//! can be displayed as e.g. `CWE-416: Use After Free` and queried by class.
+//!
+//! [CWE]: https://cwe.mitre.org/
+//! [`Category::cwe`]: crate::advisory::Category::cwe
+
+use crate::error::{Error, ErrorKind};
+use serde::{de, ser, Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A [CWE] weakness identifier, e.g. `CWE-416`
+///
+/// Any identifier can be represented. Names and parent classes are only
+/// available for the weaknesses in this crate's table (see [`Cwe::all`]).
+///
+/// [CWE]: https://cwe.mitre.org/
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Cwe(u32);
+
+impl Cwe {
+    /// Create a CWE from its numeric identifier
+    pub const fn new(id: u32) -> Self {
+        Cwe(id)
+    }
+
+    /// Get the numeric identifier of this CWE
+    pub fn id(self) -> u32 {
+        self.0
+    }
+
+    /// Get the name of this CWE, if it is a known weakness
+    pub fn name(self) -> Option<&'static str> {
+        entry(self.0).map(|&(_, name, _)| name)
+    }
+
+    /// Get the class this CWE is a child of in the CWE Research Concepts
+    /// view (CWE-1000), if it is a known weakness which has one.
+    ///
+    /// Top-level "pillar" weaknesses have no parent.
+    pub fn parent(self) -> Option<Cwe> {
+        entry(self.0).and_then(|&(_, _, parent)| parent).map(Cwe)
+    }
+
+    /// Iterate over the classes this CWE belongs to, from its immediate
+    /// parent up to the top of the hierarchy.
+    pub fn ancestors(self) -> impl Iterator<Item = Cwe> {
+        std::iter::successors(self.parent(), |cwe| cwe.parent())
+    }
+
+    /// Is this CWE the given weakness class, or a descendant of it?
+    ///
+    /// For example CWE-787 (Out-of-bounds Write) is in the class CWE-119
+    /// (Improper Restriction of Operations within the Bounds of a Memory
+    /// Buffer).
+    pub fn is_in_class(self, class: Cwe) -> bool {
+        self == class || self.ancestors().any(|ancestor| ancestor == class)
+    }
+
+    /// Iterate over all weaknesses with a known name
+    pub fn all() -> impl Iterator<Item = Cwe> {
+        WEAKNESSES.iter().map(|&(id, _, _)| Cwe(id))
+    }
+
+    /// Get the URL of this weakness' definition on the CWE website
+    pub fn url(self) -> String {
+        format!("https://cwe.mitre.org/data/definitions/{}.html", self.0)
+    }
+}
+
+impl From<u32> for Cwe {
+    fn from(id: u32) -> Cwe {
+        Cwe(id)
+    }
+}
+
+impl From<Cwe> for u32 {
+    fn from(cwe: Cwe) -> u32 {
+        cwe.0
+    }
+}
+
+/// Displays as `CWE-<id>: <name>` for known weaknesses, or `CWE-<id>`
+/// otherwise. Use the alternate format (`{:#}`) to always get `CWE-<id>`.
+impl fmt::Display for Cwe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CWE-{}", self.0)?;
+
+        match self.name() {
+            Some(name) if !f.alternate() => write!(f, ": {}", name),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses `CWE-<id>` (case insensitive) or a bare numeric identifier
+impl FromStr for Cwe {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let id = match s.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("cwe-") => &s[4..],
+            _ => s,
+        };
+
+        id.parse().map(Cwe).map_err(|_| {
+            format_err!(
+                ErrorKind::Parse,
+                "invalid CWE: {} (expected e.g. 119 or CWE-119)",
+                s
+            )
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Cwe {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use de::Error;
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Cwe {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("CWE-{}", self.0))
+    }
+}
+
+/// Look up a weakness in the table
+fn entry(id: u32) -> Option<&'static (u32, &'static str, Option<u32>)> {
+    WEAKNESSES
+        .binary_search_by_key(&id, |&(id, _, _)| id)
+        .ok()
+        .map(|index| &WEAKNESSES[index])
+}
+
+/// Known weaknesses: identifier, name and parent in the Research Concepts
+/// view (CWE-1000), sorted by identifier.
+///
+/// Where a weakness has several parents, the one most relevant to Rust code
+/// was picked. CWE-310 is a category rather than a weakness, but is included
+/// since the `crypto-failure` advisory category maps to it.
+const WEAKNESSES: &[(u32, &str, Option<u32>)] = &[
+    (20, "Improper Input Validation", Some(707)),
+    (22, "Improper Limitation of a Pathname to a Restricted Directory ('Path Traversal')", Some(706)),
+    (74, "Improper Neutralization of Special Elements in Output Used by a Downstream Component ('Injection')", Some(707)),
+    (77, "Improper Neutralization of Special Elements used in a Command ('Command Injection')", Some(74)),
+    (78, "Improper Neutralization of Special Elements used in an OS Command ('OS Command Injection')", Some(77)),
+    (79, "Improper Neutralization of Input During Web Page Generation ('Cross-site Scripting')", Some(74)),
+    (89, "Improper Neutralization of Special Elements used in an SQL Command ('SQL Injection')", Some(943)),
+    (93, "Improper Neutralization of CRLF Sequences ('CRLF Injection')", Some(74)),
+    (94, "Improper Control of Generation of Code ('Code Injection')", Some(74)),
+    (113, "Improper Neutralization of CRLF Sequences in HTTP Headers ('HTTP Request/Response Splitting')", Some(93)),
+    (118, "Incorrect Access of Indexable Resource ('Range Error')", Some(664)),
+    (119, "Improper Restriction of Operations within the Bounds of a Memory Buffer", Some(118)),
+    (120, "Buffer Copy without Checking Size of Input ('Classic Buffer Overflow')", Some(787)),
+    (121, "Stack-based Buffer Overflow", Some(787)),
+    (122, "Heap-based Buffer Overflow", Some(787)),
+    (125, "Out-of-bounds Read", Some(119)),
+    (126, "Buffer Over-read", Some(125)),
+    (129, "Improper Validation of Array Index", Some(1285)),
+    (131, "Incorrect Calculation of Buffer Size", Some(682)),
+    (134, "Use of Externally-Controlled Format String", Some(668)),
+    (190, "Integer Overflow or Wraparound", Some(682)),
+    (191, "Integer Underflow (Wrap or Wraparound)", Some(682)),
+    (200, "Exposure of Sensitive Information to an Unauthorized Actor", Some(668)),
+    (203, "Observable Discrepancy", Some(200)),
+    (208, "Observable Timing Discrepancy", Some(203)),
+    (248, "Uncaught Exception", Some(705)),
+    (252, "Unchecked Return Value", Some(754)),
+    (269, "Improper Privilege Management", Some(284)),
+    (284, "Improper Access Control", None),
+    (285, "Improper Authorization", Some(284)),
+    (287, "Improper Authentication", Some(284)),
+    (295, "Improper Certificate Validation", Some(284)),
+    (310, "Cryptographic Issues", None),
+    (311, "Missing Encryption of Sensitive Data", Some(693)),
+    (326, "Inadequate Encryption Strength", Some(693)),
+    (327, "Use of a Broken or Risky Cryptographic Algorithm", Some(693)),
+    (330, "Use of Insufficiently Random Values", Some(693)),
+    (345, "Insufficient Verification of Data Authenticity", Some(693)),
+    (347, "Improper Verification of Cryptographic Signature", Some(345)),
+    (352, "Cross-Site Request Forgery (CSRF)", Some(345)),
+    (362, "Concurrent Execution using Shared Resource with Improper Synchronization ('Race Condition')", Some(691)),
+    (366, "Race Condition within a Thread", Some(362)),
+    (367, "Time-of-check Time-of-use (TOCTOU) Race Condition", Some(362)),
+    (369, "Divide By Zero", Some(682)),
+    (400, "Uncontrolled Resource Consumption", Some(664)),
+    (401, "Missing Release of Memory after Effective Lifetime", Some(772)),
+    (404, "Improper Resource Shutdown or Release", Some(664)),
+    (405, "Asymmetric Resource Consumption (Amplification)", Some(400)),
+    (407, "Inefficient Algorithmic Complexity", Some(405)),
+    (409, "Improper Handling of Highly Compressed Data (Data Amplification)", Some(405)),
+    (415, "Double Free", Some(825)),
+    (416, "Use After Free", Some(825)),
+    (441, "Unintended Proxy or Intermediary ('Confused Deputy')", Some(610)),
+    (457, "Use of Uninitialized Variable", Some(908)),
+    (459, "Incomplete Cleanup", Some(404)),
+    (476, "NULL Pointer Dereference", Some(754)),
+    (502, "Deserialization of Untrusted Data", Some(913)),
+    (601, "URL Redirection to Untrusted Site ('Open Redirect')", Some(610)),
+    (610, "Externally Controlled Reference to a Resource in Another Sphere", Some(664)),
+    (611, "Improper Restriction of XML External Entity Reference", Some(610)),
+    (617, "Reachable Assertion", Some(670)),
+    (662, "Improper Synchronization", Some(691)),
+    (664, "Improper Control of a Resource Through its Lifetime", None),
+    (665, "Improper Initialization", Some(664)),
+    (666, "Operation on Resource in Wrong Phase of Lifetime", Some(664)),
+    (667, "Improper Locking", Some(662)),
+    (668, "Exposure of Resource to Wrong Sphere", Some(664)),
+    (670, "Always-Incorrect Control Flow Implementation", Some(691)),
+    (672, "Operation on a Resource after Expiration or Release", Some(666)),
+    (674, "Uncontrolled Recursion", Some(834)),
+    (681, "Incorrect Conversion between Numeric Types", Some(704)),
+    (682, "Incorrect Calculation", None),
+    (691, "Insufficient Control Flow Management", None),
+    (693, "Protection Mechanism Failure", None),
+    (703, "Improper Check or Handling of Exceptional Conditions", None),
+    (704, "Incorrect Type Conversion or Cast", Some(664)),
+    (705, "Incorrect Control Flow Scoping", Some(691)),
+    (706, "Use of Incorrectly-Resolved Name or Reference", Some(664)),
+    (707, "Improper Neutralization", None),
+    (710, "Improper Adherence to Coding Standards", None),
+    (754, "Improper Check for Unusual or Exceptional Conditions", Some(703)),
+    (755, "Improper Handling of Exceptional Conditions", Some(703)),
+    (763, "Release of Invalid Pointer or Reference", Some(404)),
+    (770, "Allocation of Resources Without Limits or Throttling", Some(400)),
+    (772, "Missing Release of Resource after Effective Lifetime", Some(404)),
+    (787, "Out-of-bounds Write", Some(119)),
+    (789, "Memory Allocation with Excessive Size Value", Some(770)),
+    (824, "Access of Uninitialized Pointer", Some(119)),
+    (825, "Expired Pointer Dereference", Some(672)),
+    (834, "Excessive Iteration", Some(691)),
+    (835, "Loop with Unreachable Exit Condition ('Infinite Loop')", Some(834)),
+    (843, "Access of Resource Using Incompatible Type ('Type Confusion')", Some(704)),
+    (908, "Use of Uninitialized Resource", Some(665)),
+    (909, "Missing Initialization of Resource", Some(665)),
+    (913, "Improper Control of Dynamically-Managed Code Resources", Some(664)),
+    (918, "Server-Side Request Forgery (SSRF)", Some(441)),
+    (943, "Improper Neutralization of Special Elements in Data Query Logic", Some(74)),
+    (1284, "Improper Validation of Specified Quantity in Input", Some(20)),
+    (1285, "Improper Validation of Specified Index, Position, or Offset in Input", Some(20)),
+    (1333, "Inefficient Regular Expression Complexity", Some(407)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{Cwe, WEAKNESSES};
+
+    #[test]
+    fn table_is_sorted_and_parents_are_known() {
+        assert!(WEAKNESSES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        for cwe in Cwe::all() {
+            if let Some(parent) = cwe.parent() {
+                assert!(parent.name().is_some(), "unknown parent of {:#}", cwe);
+            }
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("CWE-416".parse::<Cwe>().unwrap(), Cwe::new(416));
+        assert_eq!("cwe-416".parse::<Cwe>().unwrap(), Cwe::new(416));
+        assert_eq!("416".parse::<Cwe>().unwrap(), Cwe::new(416));
+        assert!("CWE-".parse::<Cwe>().is_err());
+        assert!("use-after-free".parse::<Cwe>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Cwe::new(416).to_string(), "CWE-416: Use After Free");
+        assert_eq!(format!("{:#}", Cwe::new(416)), "CWE-416");
+        assert_eq!(Cwe::new(99999).to_string(), "CWE-99999");
+    }
+
+    #[test]
+    fn classes() {
+        assert!(Cwe::new(120).is_in_class(Cwe::new(119)));
+        assert!(Cwe::new(125).is_in_class(Cwe::new(119)));
+        assert!(Cwe::new(119).is_in_class(Cwe::new(119)));
+        assert!(!Cwe::new(416).is_in_class(Cwe::new(119)));
+        assert!(Cwe::new(416).is_in_class(Cwe::new(664)));
+        assert!(!Cwe::new(99999).is_in_class(Cwe::new(119)));
+    }
+}
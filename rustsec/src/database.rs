@@ -1,25 +1,42 @@
 //! Database containing `RustSec` security advisories
 
+#[cfg(feature = "git")]
+mod cache;
 mod entries;
 mod index;
+mod lazy;
 mod query;
+mod snapshot;
 
-pub use self::query::Query;
+pub use self::{
+    lazy::LazyDatabase,
+    query::{Query, QueryCheck},
+};
 
-use self::{entries::Entries, index::Index};
+use self::{
+    entries::Entries,
+    index::{AliasIndex, Index},
+};
 use crate::{
     advisory::{self, Advisory},
     collection::Collection,
     error::Error,
     fs,
+    progress::{Counter, Progress},
     vulnerability::Vulnerability,
     Lockfile,
 };
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
 
 #[cfg(feature = "git")]
 use crate::repository::git;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 /// Iterator over entries in the database
 pub type Iter<'a> = std::slice::Iter<'a, Advisory>;
 
@@ -35,6 +52,15 @@ pub struct Database {
     /// Index of third party crates
     crate_index: Index,
 
+    /// Index of native library vulnerabilities, by `-sys` crate
+    native_index: Index,
+
+    /// Index of advisory aliases and related IDs (e.g. CVE and GHSA IDs)
+    alias_index: AliasIndex,
+
+    /// Index of superseded advisory IDs to the advisories superseding them
+    superseded_index: AliasIndex,
+
     /// Information about the last git commit to the database
     #[cfg(feature = "git")]
     latest_commit: Option<git::Commit>,
@@ -42,8 +68,25 @@ pub struct Database {
 
 impl Database {
     /// Open [`Database`] located at the given local path
+    ///
+    /// With the `parallel` feature enabled, advisories are read and parsed
+    /// on the rayon thread pool. Either way they are inserted in order of
+    /// their paths, so the resulting database doesn't depend on the order in
+    /// which the filesystem lists directory entries.
     pub fn open(path: &Path) -> Result<Self, Error> {
-        let mut advisory_paths = vec![];
+        Self::open_inner(path, None)
+    }
+
+    /// Same as [`Database::open`], but reports the number of advisories
+    /// parsed to `progress`.
+    pub fn open_with_progress(path: &Path, progress: &dyn Progress) -> Result<Self, Error> {
+        let result = Self::open_inner(path, Some(progress));
+        progress.finish();
+        result
+    }
+
+    fn open_inner(path: &Path, progress: Option<&dyn Progress>) -> Result<Self, Error> {
+        let mut package_dirs = vec![];
 
         for collection in Collection::all() {
             let collection_path = path.join(collection.as_str());
@@ -51,47 +94,179 @@ impl Database {
             if let Ok(collection_entry) = fs::read_dir(&collection_path) {
                 for dir_entry in collection_entry {
                     let dir_entry = dir_entry?;
-                    if !dir_entry.file_type()?.is_dir() {
-                        continue;
-                    }
-                    for advisory_entry in fs::read_dir(dir_entry.path())? {
-                        let advisory_path = advisory_entry?.path();
-                        let file_name = advisory_path.file_name().and_then(|f| f.to_str());
-                        // skip dotfiles like .DS_Store
-                        if file_name.map_or(false, |f| f.starts_with('.')) {
-                            continue;
-                        }
-                        advisory_paths.push(advisory_path);
+                    if dir_entry.file_type()?.is_dir() {
+                        package_dirs.push(dir_entry.path());
                     }
                 }
             }
         }
 
+        #[cfg(feature = "parallel")]
+        let advisory_paths = package_dirs
+            .par_iter()
+            .map(|dir| advisory_files(dir))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        #[cfg(not(feature = "parallel"))]
+        let advisory_paths = package_dirs
+            .iter()
+            .map(|dir| advisory_files(dir))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut advisory_paths: Vec<_> = advisory_paths.into_iter().flatten().collect();
+        advisory_paths.sort();
+
+        let parsed_count = Counter::default();
+        if let Some(progress) = progress {
+            progress.stage(
+                "parsing advisories",
+                Some(advisory_paths.len()),
+                parsed_count.clone(),
+            );
+        }
+
+        let parse_file = |path: &PathBuf| {
+            let parsed = Entries::parse_file(path);
+            parsed_count.fetch_add(1, Ordering::Relaxed);
+            parsed
+        };
+
+        #[cfg(feature = "parallel")]
+        let parsed = advisory_paths
+            .par_iter()
+            .map(parse_file)
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        #[cfg(not(feature = "parallel"))]
+        let parsed = advisory_paths
+            .iter()
+            .map(parse_file)
+            .collect::<Result<Vec<_>, Error>>()?;
+
         let mut advisories = Entries::new();
+
+        for advisory in parsed {
+            advisories.insert(advisory)?;
+        }
+
+        Ok(Self::from_entries(advisories))
+    }
+
+    /// Create a [`DatabaseBuilder`] for constructing a [`Database`] in memory,
+    /// e.g. in tests which shouldn't depend on fixture directories.
+    pub fn builder() -> DatabaseBuilder {
+        DatabaseBuilder::default()
+    }
+
+    /// Create a [`Database`] from advisories which have already been loaded,
+    /// e.g. from a snapshot embedded in a binary.
+    ///
+    /// Advisories without a collection are assumed to be about crates.
+    pub fn from_advisories(advisories: impl IntoIterator<Item = Advisory>) -> Result<Self, Error> {
+        let mut entries = Entries::new();
+
+        for mut advisory in advisories {
+            advisory
+                .metadata
+                .collection
+                .get_or_insert(Collection::Crates);
+            entries.insert(advisory)?;
+        }
+
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Load a [`Database`] from a snapshot created with [`Database::snapshot`].
+    ///
+    /// This doesn't need access to the filesystem or network, so it can be
+    /// used to audit lockfiles from WebAssembly (e.g. in a browser) against a
+    /// snapshot fetched by the host.
+    pub fn from_snapshot(data: &[u8]) -> Result<Self, Error> {
+        let snapshot = snapshot::decode(data)?;
+        let mut advisories = Entries::new();
+
+        for advisory in snapshot.advisories {
+            advisories.insert(advisory)?;
+        }
+
+        Ok(Self::from_entries(advisories))
+    }
+
+    /// Serialize the advisories in this database as a snapshot, which can be
+    /// loaded with [`Database::from_snapshot`].
+    pub fn snapshot(&self) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "git")]
+        let commit = self
+            .latest_commit
+            .as_ref()
+            .map(|commit| commit.commit_id.to_hex())
+            .unwrap_or_default();
+
+        #[cfg(not(feature = "git"))]
+        let commit = String::new();
+
+        snapshot::encode(&commit, self.iter())
+    }
+
+    /// Build the collection indexes for the given entries
+    fn from_entries(advisories: Entries) -> Self {
         let mut rust_index = Index::new();
         let mut crate_index = Index::new();
+        let mut native_index = Index::new();
+        let mut alias_index = AliasIndex::new();
+        let mut superseded_index = AliasIndex::new();
 
-        for path in &advisory_paths {
-            if let Some(slot) = advisories.load_file(path)? {
-                let advisory = advisories.get(slot).unwrap();
-                match advisory.metadata.collection.unwrap() {
-                    Collection::Crates => {
-                        crate_index.insert(&advisory.metadata.package, slot);
-                    }
-                    Collection::Rust => {
-                        rust_index.insert(&advisory.metadata.package, slot);
+        for (slot, advisory) in advisories.slots() {
+            // A withdrawn advisory doesn't replace the ones it supersedes
+            if advisory.metadata.withdrawn.is_none() {
+                for superseded in &advisory.metadata.supersedes {
+                    superseded_index.insert(superseded, slot);
+                }
+            }
+
+            // The superseded advisory may declare its replacements instead
+            for successor in &advisory.metadata.superseded_by {
+                if let Some(successor_slot) = advisories.find_slot(successor) {
+                    let successor = advisories.get(successor_slot).unwrap();
+
+                    if successor_slot != slot && successor.metadata.withdrawn.is_none() {
+                        superseded_index.insert(advisory.id(), successor_slot);
                     }
                 }
             }
+
+            for alias in advisory
+                .metadata
+                .aliases
+                .iter()
+                .chain(&advisory.metadata.related)
+            {
+                alias_index.insert(alias, slot);
+            }
+
+            match advisory.metadata.collection.unwrap_or(Collection::Crates) {
+                Collection::Crates => {
+                    crate_index.insert(&advisory.metadata.package, slot);
+                }
+                Collection::Rust => {
+                    rust_index.insert(&advisory.metadata.package, slot);
+                }
+                Collection::Native => {
+                    native_index.insert(&advisory.metadata.package, slot);
+                }
+            }
         }
 
-        Ok(Self {
+        Self {
             advisories,
             crate_index,
             rust_index,
+            native_index,
+            alias_index,
+            superseded_index,
             #[cfg(feature = "git")]
             latest_commit: None,
-        })
+        }
     }
 
     /// Load [`Database`] from the given [`git::Repository`]
@@ -102,39 +277,213 @@ impl Database {
         Ok(db)
     }
 
+    /// Load [`Database`] from the given [`git::Repository`], reusing a snapshot
+    /// of the parsed advisories stored in `cache_dir` when one exists for the
+    /// repository's latest commit.
+    ///
+    /// If there is no usable snapshot, the advisories are parsed from the
+    /// repository as in [`Database::load_from_repo`] and a new snapshot is
+    /// written, replacing any snapshots of older commits.
+    #[cfg(feature = "git")]
+    pub fn load_from_repo_cached(repo: &git::Repository, cache_dir: &Path) -> Result<Self, Error> {
+        let latest_commit = repo.latest_commit()?;
+        let commit = latest_commit.commit_id.to_hex();
+
+        let mut db = match cache::load(cache_dir, &commit) {
+            Some(cached) => {
+                let mut advisories = Entries::new();
+
+                for advisory in cached {
+                    advisories.insert(advisory)?;
+                }
+
+                Self::from_entries(advisories)
+            }
+            None => {
+                let db = Self::open(repo.path())?;
+
+                // The cache is only an optimization: failing to write it
+                // shouldn't prevent using the database
+                let _ = cache::store(cache_dir, &commit, db.iter());
+                db
+            }
+        };
+
+        db.latest_commit = Some(latest_commit);
+        Ok(db)
+    }
+
     /// Fetch the default advisory database from GitHub
     #[cfg(feature = "git")]
     pub fn fetch() -> Result<Self, Error> {
         git::Repository::fetch_default_repo().and_then(|repo| Self::load_from_repo(&repo))
     }
 
+    /// Same as [`Database::fetch`], but reports the progress of fetching the
+    /// repository and parsing the advisories to `progress`.
+    #[cfg(feature = "git")]
+    pub fn fetch_with_progress(progress: std::sync::Arc<dyn Progress>) -> Result<Self, Error> {
+        let repo = git::Repository::fetch_with_progress(
+            git::DEFAULT_URL,
+            git::Repository::default_path(),
+            true,
+            git::DEFAULT_LOCK_TIMEOUT,
+            progress.clone(),
+        )?;
+
+        let mut db = Self::open_with_progress(repo.path(), progress.as_ref())?;
+        db.latest_commit = Some(repo.latest_commit()?);
+        Ok(db)
+    }
+
     /// Look up an advisory by an advisory ID (e.g. "RUSTSEC-YYYY-XXXX")
     pub fn get(&self, id: &advisory::Id) -> Option<&Advisory> {
         self.advisories.find_by_id(id)
     }
 
+    /// Find advisories which list the given ID (e.g. "CVE-2023-12345" or
+    /// "GHSA-xxxx-xxxx-xxxx") among their aliases or related IDs.
+    ///
+    /// The lookup is case-insensitive. An advisory whose own ID matches is
+    /// also returned, so RustSec IDs can be passed as well.
+    pub fn find_by_alias(&self, alias: &str) -> Vec<&Advisory> {
+        let own = alias
+            .parse::<advisory::Id>()
+            .ok()
+            .and_then(|id| self.get(&id));
+
+        let aliased = self
+            .alias_index
+            .get(alias)
+            .into_iter()
+            .flatten()
+            .map(|slot| self.advisories.get(*slot).unwrap())
+            .filter(|advisory| own.map_or(true, |own| own.id() != advisory.id()));
+
+        own.into_iter().chain(aliased).collect()
+    }
+
+    /// Find the advisories which supersede the advisory with the given ID.
+    ///
+    /// Withdrawn advisories don't supersede anything, so they're never
+    /// returned.
+    pub fn superseded_by(&self, id: &advisory::Id) -> Vec<&Advisory> {
+        self.superseded_index
+            .get(id.as_str())
+            .into_iter()
+            .flatten()
+            .map(|slot| self.advisories.get(*slot).unwrap())
+            .collect()
+    }
+
+    /// Is the given advisory superseded by another advisory in the database?
+    pub fn is_superseded(&self, advisory: &Advisory) -> bool {
+        self.superseded_index.get(advisory.id().as_str()).is_some()
+    }
+
+    /// Create a new [`Database`] with the advisories in this one along with
+    /// advisories from another source (e.g. osv.dev).
+    ///
+    /// Advisories which are already known, either by their own ID or by one
+    /// of their aliases, are skipped. Information about the latest commit is
+    /// kept.
+    pub fn merge(&self, advisories: impl IntoIterator<Item = Advisory>) -> Result<Self, Error> {
+        #[allow(unused_mut)]
+        let mut db = Self::from_advisories(self.iter().cloned())?;
+
+        #[cfg(feature = "git")]
+        {
+            db.latest_commit = self.latest_commit.clone();
+        }
+
+        db.extend(advisories)?;
+        Ok(db)
+    }
+
+    /// Add advisories from another source (e.g. osv.dev) to this database in
+    /// place, without copying the advisories it already contains.
+    ///
+    /// Advisories which are already known, either by their own ID or by one
+    /// of their aliases, are skipped.
+    pub fn extend(&mut self, advisories: impl IntoIterator<Item = Advisory>) -> Result<(), Error> {
+        let mut seen = crate::Set::new();
+
+        let extra: Vec<_> = advisories
+            .into_iter()
+            .filter(|advisory| {
+                let ids = || std::iter::once(advisory.id()).chain(&advisory.metadata.aliases);
+
+                let known = ids().any(|id| {
+                    seen.contains(id.as_str()) || !self.find_by_alias(id.as_str()).is_empty()
+                });
+
+                seen.extend(ids().map(|id| id.as_str().to_owned()));
+                !known
+            })
+            .collect();
+
+        if extra.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries = std::mem::take(&mut self.advisories);
+        let result = extra.into_iter().try_for_each(|mut advisory| {
+            advisory
+                .metadata
+                .collection
+                .get_or_insert(Collection::Crates);
+            entries.insert(advisory).map(|_| ())
+        });
+
+        // The indexes are rebuilt even on error, so they match the entries
+        #[allow(unused_mut)]
+        let mut db = Self::from_entries(entries);
+
+        #[cfg(feature = "git")]
+        {
+            db.latest_commit = self.latest_commit.take();
+        }
+
+        *self = db;
+        result
+    }
+
     /// Query the database according to the given query object
     pub fn query(&self, query: &Query) -> Vec<&Advisory> {
+        self.query_iter(query).collect()
+    }
+
+    /// Iterate over all advisories in the database which match the given query
+    pub fn query_iter<'a: 'q, 'q>(
+        &'a self,
+        query: &'q Query,
+    ) -> impl Iterator<Item = &'a Advisory> + 'q {
         // Use indexes if we know a package name and collection
-        if let Some(name) = &query.package_name {
-            if let Some(collection) = query.collection {
-                return match collection {
-                    Collection::Crates => self.crate_index.get(name),
-                    Collection::Rust => self.rust_index.get(name),
+        let candidates: Box<dyn Iterator<Item = &'a Advisory> + 'q> =
+            match (&query.package_name, query.collection) {
+                (Some(name), Some(collection)) => {
+                    let index = match collection {
+                        Collection::Crates => &self.crate_index,
+                        Collection::Rust => &self.rust_index,
+                        Collection::Native => &self.native_index,
+                    };
+
+                    Box::new(
+                        index
+                            .get(name)
+                            .into_iter()
+                            .flatten()
+                            .map(move |slot| self.advisories.get(*slot).unwrap()),
+                    )
                 }
-                .map(|slots| {
-                    slots
-                        .map(|slot| self.advisories.get(*slot).unwrap())
-                        .filter(|advisory| query.matches(advisory))
-                        .collect()
-                })
-                .unwrap_or_else(Vec::new);
-            }
-        }
+                _ => Box::new(self.iter()),
+            };
 
-        self.iter()
-            .filter(|advisory| query.matches(advisory))
-            .collect()
+        candidates.filter(move |advisory| {
+            query.superseded.map_or(true, |superseded| {
+                superseded == self.is_superseded(advisory)
+            }) && query.matches(advisory)
+        })
     }
 
     /// Find vulnerabilities in the provided `Lockfile` which match a given query.
@@ -171,6 +520,139 @@ impl Database {
     }
 }
 
+/// Builder for constructing a [`Database`] from advisories in memory.
+///
+/// Advisories can be parsed from strings with [`str::parse`] or constructed
+/// directly.
+#[derive(Clone, Debug, Default)]
+pub struct DatabaseBuilder {
+    advisories: Vec<Advisory>,
+}
+
+impl DatabaseBuilder {
+    /// Add an advisory to the database
+    pub fn advisory(mut self, advisory: Advisory) -> Self {
+        self.advisories.push(advisory);
+        self
+    }
+
+    /// Add several advisories to the database
+    pub fn advisories(mut self, advisories: impl IntoIterator<Item = Advisory>) -> Self {
+        self.advisories.extend(advisories);
+        self
+    }
+
+    /// Build the [`Database`], failing if any advisory IDs are duplicated
+    pub fn build(self) -> Result<Database, Error> {
+        Database::from_advisories(self.advisories)
+    }
+}
+
+/// Builder for minimal advisories to add to a [`DatabaseBuilder`].
+///
+/// Only the ID and package are required: the date defaults to January 1st
+/// of the year in the ID, and the title and description to placeholders.
+#[derive(Clone, Debug)]
+pub struct AdvisoryBuilder {
+    id: String,
+    package: String,
+    date: Option<String>,
+    metadata: Vec<String>,
+    patched: Vec<String>,
+    collection: Option<Collection>,
+    title: String,
+    description: String,
+}
+
+impl AdvisoryBuilder {
+    /// Start building an advisory with the given ID about the given package
+    pub fn new(id: &str, package: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            package: package.to_owned(),
+            date: None,
+            metadata: vec![],
+            patched: vec![],
+            collection: None,
+            title: "Title".to_owned(),
+            description: "Description".to_owned(),
+        }
+    }
+
+    /// Set the date the advisory was reported
+    pub fn date(mut self, date: &str) -> Self {
+        self.date = Some(date.to_owned());
+        self
+    }
+
+    /// Add a line of TOML to the `[advisory]` table, e.g. `cvss = "..."`
+    pub fn metadata(mut self, toml: &str) -> Self {
+        self.metadata.push(toml.to_owned());
+        self
+    }
+
+    /// Add a requirement for versions which are patched
+    pub fn patched(mut self, req: &str) -> Self {
+        self.patched.push(req.to_owned());
+        self
+    }
+
+    /// Set the collection the advisory belongs to
+    pub fn collection(mut self, collection: Collection) -> Self {
+        self.collection = Some(collection);
+        self
+    }
+
+    /// Set the title of the advisory
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Set the description of the advisory
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_owned();
+        self
+    }
+
+    /// Build the [`Advisory`], failing if any of the fields are invalid
+    pub fn build(self) -> Result<Advisory, Error> {
+        let date = self.date.unwrap_or_else(|| {
+            let year = self.id.split('-').nth(1).unwrap_or_default();
+            format!("{}-01-01", year)
+        });
+        let patched = toml::Value::from(self.patched);
+
+        let mut advisory: Advisory = format!(
+            "```toml\n\
+             [advisory]\n\
+             id = {}\n\
+             package = {}\n\
+             date = {}\n\
+             {}\n\
+             [versions]\n\
+             patched = {}\n\
+             ```\n\n\
+             # {}\n\n\
+             {}\n",
+            toml::Value::from(self.id),
+            toml::Value::from(self.package),
+            toml::Value::from(date),
+            self.metadata.join("\n"),
+            patched,
+            self.title,
+            self.description
+        )
+        .parse()?;
+
+        if self.collection.is_some() {
+            advisory.metadata.collection = self.collection;
+        }
+
+        Ok(advisory)
+    }
+}
+
 impl IntoIterator for Database {
     type Item = Advisory;
 
@@ -180,3 +662,20 @@ impl IntoIterator for Database {
         self.advisories.into_iter()
     }
 }
+
+/// Paths of the advisory files in the given package directory
+fn advisory_files(package_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut advisory_paths = vec![];
+
+    for advisory_entry in fs::read_dir(package_dir)? {
+        let advisory_path = advisory_entry?.path();
+        let file_name = advisory_path.file_name().and_then(|f| f.to_str());
+        // skip dotfiles like .DS_Store
+        if file_name.map_or(false, |f| f.starts_with('.')) {
+            continue;
+        }
+        advisory_paths.push(advisory_path);
+    }
+
+    Ok(advisory_paths)
+}
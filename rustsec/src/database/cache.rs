@@ -0,0 +1,121 @@
+//! On-disk cache of parsed advisory databases
+//!
+//! Parsing hundreds of advisory files on every run is comparatively slow, so
+//! the parsed advisories can be stored as a snapshot keyed by the commit hash
+//! of the advisory database repository they were parsed from.
+
+use super::snapshot;
+use crate::{advisory::Advisory, error::Error, fs};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+/// File extension used for snapshots
+const EXTENSION: &str = "json";
+
+/// Load the advisories cached for the given commit, if there is a usable snapshot.
+///
+/// Snapshots written by a different version of this crate are ignored, since
+/// the parsed representation may have changed in the meantime.
+pub(crate) fn load(cache_dir: &Path, commit: &str) -> Option<Vec<Advisory>> {
+    let data = fs::read(snapshot_path(cache_dir, commit)).ok()?;
+    let snapshot = snapshot::decode(&data).ok()?;
+
+    if snapshot.rustsec_version != crate::VERSION || snapshot.commit != commit {
+        return None;
+    }
+
+    Some(snapshot.advisories)
+}
+
+/// Store a snapshot of the given advisories for the given commit, removing
+/// snapshots of any other commits.
+pub(crate) fn store<'a>(
+    cache_dir: &Path,
+    commit: &str,
+    advisories: impl Iterator<Item = &'a Advisory>,
+) -> Result<(), Error> {
+    fs::create_dir_all(cache_dir)?;
+
+    // Snapshots of other commits will never be used again
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+
+        if path.extension() == Some(OsStr::new(EXTENSION)) {
+            fs::remove_file(path)?;
+        }
+    }
+
+    let data = snapshot::encode(commit, advisories)?;
+
+    // Write to a temporary file first so concurrent readers never observe
+    // a partially written snapshot
+    let path = snapshot_path(cache_dir, commit);
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Path of the snapshot for the given commit
+fn snapshot_path(cache_dir: &Path, commit: &str) -> PathBuf {
+    cache_dir.join(commit).with_extension(EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+    const OTHER_COMMIT: &str = "89abcdef0123456789abcdef0123456789abcdef";
+
+    fn advisory() -> Advisory {
+        Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap()
+    }
+
+    #[test]
+    fn store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let advisory = advisory();
+        assert!(load(dir.path(), COMMIT).is_none());
+
+        store(dir.path(), COMMIT, [&advisory].into_iter()).unwrap();
+        let advisories = load(dir.path(), COMMIT).unwrap();
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].id(), advisory.id());
+
+        // Snapshots are only used for the commit they were parsed from
+        assert!(load(dir.path(), OTHER_COMMIT).is_none());
+    }
+
+    #[test]
+    fn store_removes_other_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let advisory = advisory();
+
+        store(dir.path(), COMMIT, [&advisory].into_iter()).unwrap();
+        store(dir.path(), OTHER_COMMIT, [&advisory].into_iter()).unwrap();
+
+        assert!(!snapshot_path(dir.path(), COMMIT).exists());
+        assert!(load(dir.path(), OTHER_COMMIT).is_some());
+    }
+
+    #[test]
+    fn ignore_invalid_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(snapshot_path(dir.path(), COMMIT), "not a snapshot").unwrap();
+        assert!(load(dir.path(), COMMIT).is_none());
+
+        // A snapshot stored under the wrong name isn't used either
+        let advisory = advisory();
+        store(dir.path(), OTHER_COMMIT, [&advisory].into_iter()).unwrap();
+        fs::rename(
+            snapshot_path(dir.path(), OTHER_COMMIT),
+            snapshot_path(dir.path(), COMMIT),
+        )
+        .unwrap();
+        assert!(load(dir.path(), COMMIT).is_none());
+    }
+}
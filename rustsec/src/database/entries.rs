@@ -33,11 +33,19 @@ impl Entries {
         Self::default()
     }
 
-    /// Load an advisory from a file and insert it into the database entry table
+    /// Load an advisory from a file, ensuring it is located at the expected
+    /// path within the database, without inserting it into the entry table
     // TODO(tarcieri): factor more of this into `advisory.rs`?
-    pub fn load_file(&mut self, path: &Path) -> Result<Option<Slot>, Error> {
-        let mut advisory = Advisory::load_file(path)?;
+    pub fn parse_file(path: &Path) -> Result<Advisory, Error> {
+        let advisory = Advisory::load_file(path)?;
+        let id = advisory.metadata.id.clone();
+
+        Self::locate(path, advisory).map_err(|e| e.with_path(path).with_advisory_id(id))
+    }
 
+    /// Ensure an advisory is located at the expected path within the
+    /// database, and set its collection according to its path
+    fn locate(path: &Path, mut advisory: Advisory) -> Result<Advisory, Error> {
         // TODO(tarcieri): deprecate and remove legacy TOML-based advisory format
         let expected_filename = match path.extension().and_then(|ext| ext.to_str()) {
             Some("md") => OsString::from(format!("{}.md", advisory.metadata.id)),
@@ -94,6 +102,8 @@ impl Entries {
             Collection::Crates
         } else if collection_dir == OsStr::new(Collection::Rust.as_str()) {
             Collection::Rust
+        } else if collection_dir == OsStr::new(Collection::Native.as_str()) {
+            Collection::Native
         } else {
             fail!(
                 ErrorKind::Repo,
@@ -115,6 +125,11 @@ impl Entries {
             None => advisory.metadata.collection = Some(collection),
         }
 
+        Ok(advisory)
+    }
+
+    /// Insert an already parsed advisory into the database entry table
+    pub fn insert(&mut self, advisory: Advisory) -> Result<Option<Slot>, Error> {
         // Ensure placeholder advisories load and parse correctly, but
         // don't actually insert them into the advisory database
         if advisory.metadata.id.is_placeholder() {
@@ -130,7 +145,12 @@ impl Entries {
                 entry.insert(slot);
             }
             map::Entry::Occupied(entry) => {
-                fail!(ErrorKind::Parse, "duplicate advisory ID: {}", entry.key())
+                return Err(format_err!(
+                    ErrorKind::Parse,
+                    "duplicate advisory ID: {}",
+                    entry.key()
+                )
+                .with_advisory_id(entry.key().clone()));
             }
         }
 
@@ -142,6 +162,11 @@ impl Entries {
         self.index.get(id).and_then(|slot| self.get(*slot))
     }
 
+    /// Find the [`Slot`] of an advisory by its `advisory::Id`
+    pub fn find_slot(&self, id: &advisory::Id) -> Option<Slot> {
+        self.index.get(id).copied()
+    }
+
     /// Get an advisory from the database by its [`Slot`]
     pub fn get(&self, slot: Slot) -> Option<&Advisory> {
         self.advisories.get(slot.0)
@@ -151,6 +176,14 @@ impl Entries {
     pub fn iter(&self) -> Iter<'_> {
         self.advisories.iter()
     }
+
+    /// Iterate over all of the entries in the database along with their slots
+    pub fn slots(&self) -> impl Iterator<Item = (Slot, &Advisory)> {
+        self.advisories
+            .iter()
+            .enumerate()
+            .map(|(i, advisory)| (Slot(i), advisory))
+    }
 }
 
 impl IntoIterator for Entries {
@@ -3,7 +3,7 @@
 pub use crate::set::Iter;
 
 use super::entries::Slot;
-use crate::{map, package, Map, Set};
+use crate::{advisory, map, package, Map, Set};
 
 /// Database index which maps package names to a set of advisory IDs
 #[derive(Debug, Default)]
@@ -30,3 +30,32 @@ impl Index {
         self.0.get(key).map(|set| set.iter())
     }
 }
+
+/// Database index which maps aliases of advisories (e.g. CVE and GHSA IDs)
+/// to the advisories which reference them.
+///
+/// Lookups are case-insensitive.
+#[derive(Debug, Default)]
+pub(crate) struct AliasIndex(Map<String, Set<Slot>>);
+
+impl AliasIndex {
+    /// Create a new index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert an alias into the index
+    pub fn insert(&mut self, alias: &advisory::Id, slot: Slot) -> bool {
+        self.0
+            .entry(alias.as_str().to_ascii_uppercase())
+            .or_default()
+            .insert(slot)
+    }
+
+    /// Get an iterator over advisories referencing the given alias
+    pub fn get(&self, alias: &str) -> Option<Iter<'_, Slot>> {
+        self.0
+            .get(&alias.to_ascii_uppercase())
+            .map(|set| set.iter())
+    }
+}
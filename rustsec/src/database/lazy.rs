@@ -0,0 +1,215 @@
+//! Lazily loaded advisory database
+
+use super::{advisory_files, entries::Entries, Query};
+use crate::{
+    advisory::Advisory,
+    collection::Collection,
+    error::{Error, ErrorKind},
+    fs,
+    vulnerability::Vulnerability,
+    Lockfile, Map,
+};
+use std::path::{Path, PathBuf};
+
+/// Advisory database which indexes advisory files by package name when it's
+/// opened, but only parses a package's advisories once they're queried.
+///
+/// Auditing a lockfile against it only parses the advisories for packages
+/// which are actually in the lockfile, rather than the entire database.
+#[derive(Debug, Default)]
+pub struct LazyDatabase {
+    /// Advisory files for each package, by collection and package name
+    packages: Map<Collection, Map<String, PackageEntry>>,
+}
+
+/// Advisory files for a particular package
+#[derive(Debug)]
+struct PackageEntry {
+    /// Paths to the package's advisory files
+    paths: Vec<PathBuf>,
+
+    /// Advisories parsed from the files above, once they've been loaded
+    advisories: Option<Vec<Advisory>>,
+}
+
+impl LazyDatabase {
+    /// Index the advisory database located at the given local path.
+    ///
+    /// No advisories are parsed until they're queried.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut packages = Map::new();
+
+        for &collection in Collection::all() {
+            let collection_path = path.join(collection.as_str());
+            let mut collection_packages = Map::new();
+
+            if let Ok(collection_entry) = fs::read_dir(&collection_path) {
+                for dir_entry in collection_entry {
+                    let dir_entry = dir_entry?;
+                    if !dir_entry.file_type()?.is_dir() {
+                        continue;
+                    }
+
+                    let package_dir = dir_entry.path();
+                    let package_name = dir_entry.file_name().into_string().map_err(|_| {
+                        format_err!(
+                            ErrorKind::Repo,
+                            "invalid package directory: {}",
+                            package_dir.display()
+                        )
+                    })?;
+
+                    let mut paths = advisory_files(&package_dir)?;
+                    paths.sort();
+
+                    collection_packages.insert(
+                        package_name,
+                        PackageEntry {
+                            paths,
+                            advisories: None,
+                        },
+                    );
+                }
+            }
+
+            packages.insert(collection, collection_packages);
+        }
+
+        Ok(Self { packages })
+    }
+
+    /// Load the advisories for the given package, parsing them if they
+    /// haven't been loaded already
+    pub fn load(&mut self, collection: Collection, package: &str) -> Result<&[Advisory], Error> {
+        let entry = match self
+            .packages
+            .get_mut(&collection)
+            .and_then(|packages| packages.get_mut(package))
+        {
+            Some(entry) => entry,
+            None => return Ok(&[]),
+        };
+
+        if entry.advisories.is_none() {
+            let mut advisories = Vec::with_capacity(entry.paths.len());
+
+            for path in &entry.paths {
+                let advisory = Entries::parse_file(path)?;
+
+                // Placeholders are parsed but not part of the database
+                if !advisory.metadata.id.is_placeholder() {
+                    advisories.push(advisory);
+                }
+            }
+
+            entry.advisories = Some(advisories);
+        }
+
+        Ok(entry.advisories.as_deref().unwrap_or_default())
+    }
+
+    /// Find advisories that match the given query, loading them as needed.
+    ///
+    /// Queries which don't specify a package name load every advisory in
+    /// the queried collections. Since advisories are loaded per package,
+    /// only advisories about the same package are considered when checking
+    /// whether an advisory is superseded (see [`Query::superseded`]).
+    pub fn query(&mut self, query: &Query) -> Result<Vec<&Advisory>, Error> {
+        let collections = match query.collection {
+            Some(collection) => vec![collection],
+            None => Collection::all().to_vec(),
+        };
+
+        for &collection in &collections {
+            match &query.package_name {
+                Some(name) => {
+                    self.load(collection, name.as_str())?;
+                }
+                None => {
+                    let names: Vec<_> = self
+                        .packages
+                        .get(&collection)
+                        .map(|packages| packages.keys().cloned().collect())
+                        .unwrap_or_default();
+
+                    for name in &names {
+                        self.load(collection, name)?;
+                    }
+                }
+            }
+        }
+
+        let mut results = vec![];
+
+        for collection in &collections {
+            let packages = match self.packages.get(collection) {
+                Some(packages) => packages,
+                None => continue,
+            };
+
+            for (name, entry) in packages {
+                if let Some(package_name) = &query.package_name {
+                    if package_name.as_str() != name.as_str() {
+                        continue;
+                    }
+                }
+
+                let advisories = entry.advisories.as_deref().unwrap_or_default();
+
+                results.extend(advisories.iter().filter(|advisory| {
+                    query.superseded.map_or(true, |superseded| {
+                        superseded == is_superseded(advisory, advisories)
+                    }) && query.matches(advisory)
+                }));
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Find vulnerabilities in the provided `Lockfile` which match a given
+    /// query, only loading advisories for the packages it contains.
+    pub fn query_vulnerabilities(
+        &mut self,
+        lockfile: &Lockfile,
+        query: &Query,
+    ) -> Result<Vec<Vulnerability>, Error> {
+        let mut vulns = vec![];
+
+        for package in &lockfile.packages {
+            let advisories = self.query(&query.clone().package(package))?;
+
+            vulns.extend(
+                advisories
+                    .iter()
+                    .map(|advisory| Vulnerability::new(advisory, package)),
+            );
+        }
+
+        Ok(vulns)
+    }
+
+    /// Scan for vulnerabilities in the provided `Lockfile`.
+    pub fn vulnerabilities(&mut self, lockfile: &Lockfile) -> Result<Vec<Vulnerability>, Error> {
+        self.query_vulnerabilities(lockfile, &Query::crate_scope())
+    }
+
+    /// Number of packages whose advisories have been parsed so far
+    pub fn loaded_packages(&self) -> usize {
+        self.packages
+            .values()
+            .flat_map(Map::values)
+            .filter(|entry| entry.advisories.is_some())
+            .count()
+    }
+}
+
+/// Is the given advisory superseded by one of the given advisories?
+fn is_superseded(advisory: &Advisory, others: &[Advisory]) -> bool {
+    others.iter().any(|other| {
+        other.metadata.withdrawn.is_none()
+            && other.id() != advisory.id()
+            && (other.metadata.supersedes.contains(advisory.id())
+                || advisory.metadata.superseded_by.contains(other.id()))
+    })
+}
@@ -1,13 +1,16 @@
 //! Queries against the RustSec database
 //!
 use crate::{
-    advisory::{Advisory, Severity},
+    advisory::{self, Advisory, Category, Date, Informational, Keyword, Severity},
     collection::Collection,
+    cwe::Cwe,
+    osv,
     package::{self, Package},
-    SourceId,
+    Map, SourceId,
 };
 use platforms::target::{Arch, OS};
 use semver::Version;
+use std::fmt;
 
 /// Queries against the RustSec database
 #[derive(Clone, Debug)]
@@ -27,6 +30,26 @@ pub struct Query {
     /// Severity threshold (i.e. minimum severity)
     severity: Option<Severity>,
 
+    /// Maximum severity
+    max_severity: Option<Severity>,
+
+    /// Severities to use instead of those of the CVSS vectors of the given
+    /// advisories
+    severity_overrides: Map<advisory::Id, Severity>,
+
+    /// CWE weakness class
+    cwe: Option<u32>,
+
+    /// Category the advisory must be in
+    category: Option<Category>,
+
+    /// Keywords the advisory must have
+    keywords: Vec<Keyword>,
+
+    /// Words which must occur in the advisory's title or description
+    /// (lowercased)
+    text: Vec<String>,
+
     /// Target architecture
     target_arch: Option<Arch>,
 
@@ -41,8 +64,23 @@ pub struct Query {
     /// as opposed to yanked crates)
     withdrawn: Option<bool>,
 
+    /// Query for superseded advisories (i.e. advisories which another
+    /// advisory in the database declares it supersedes). Whether an advisory
+    /// is superseded depends on the rest of the database, so this is applied
+    /// by the database being queried rather than [`Query::matches`].
+    pub(super) superseded: Option<bool>,
+
     /// Query for informational advisories
     informational: Option<bool>,
+
+    /// Kind of informational advisory
+    informational_kind: Option<Informational>,
+
+    /// Earliest advisory date (inclusive)
+    since: Option<Date>,
+
+    /// Latest advisory date (inclusive)
+    until: Option<Date>,
 }
 
 impl Query {
@@ -62,11 +100,21 @@ impl Query {
             package_version: None,
             package_source: None,
             severity: None,
+            max_severity: None,
+            severity_overrides: Map::new(),
+            cwe: None,
+            category: None,
+            keywords: vec![],
+            text: vec![],
             target_arch: None,
             target_os: None,
             year: None,
             withdrawn: None,
+            superseded: None,
             informational: None,
+            informational_kind: None,
+            since: None,
+            until: None,
         }
     }
 
@@ -74,11 +122,13 @@ impl Query {
     ///
     /// - Only `Collection::Crates`
     /// - Ignore withdrawn advisories
+    /// - Ignore superseded advisories
     /// - Ignore informational advisories
     pub fn crate_scope() -> Self {
         Self::new()
             .collection(Collection::Crates)
             .withdrawn(false)
+            .superseded(false)
             .informational(false)
     }
 
@@ -124,6 +174,65 @@ impl Query {
         self
     }
 
+    /// Use the given severity for the advisory with the given ID when
+    /// applying [`Query::severity`] and [`Query::max_severity`], instead of
+    /// the severity of its CVSS vector (if any).
+    pub fn severity_override(mut self, id: advisory::Id, severity: Severity) -> Self {
+        self.severity_overrides.insert(id, severity);
+        self
+    }
+
+    /// Set maximum severity according to the CVSS Qualitative Severity
+    /// Rating Scale.
+    ///
+    /// Combined with [`Query::severity`] this selects a severity range.
+    /// Vulnerabilities without associated CVSS information will always
+    /// match regardless of what this is set to.
+    pub fn max_severity(mut self, severity: Severity) -> Self {
+        self.max_severity = Some(severity);
+        self
+    }
+
+    /// Query for advisories in the given [CWE] weakness class, e.g. `119`.
+    ///
+    /// Advisories match if one of their weaknesses (see
+    /// [`Metadata::cwes`][crate::advisory::Metadata::cwes]) is this CWE or
+    /// one of its descendants in the [`Cwe`] hierarchy, e.g. CWE-787
+    /// (Out-of-bounds Write) matches a query for CWE-119.
+    ///
+    /// [CWE]: https://cwe.mitre.org/
+    pub fn cwe(mut self, cwe: u32) -> Self {
+        self.cwe = Some(cwe);
+        self
+    }
+
+    /// Query for advisories in the given category
+    pub fn category(mut self, category: Category) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    /// Query for advisories with the given keyword.
+    ///
+    /// May be called several times, in which case advisories must have all
+    /// of the given keywords.
+    pub fn keyword(mut self, keyword: Keyword) -> Self {
+        self.keywords.push(keyword);
+        self
+    }
+
+    /// Query for advisories whose title or description contains the given
+    /// text.
+    ///
+    /// The text is split into words, all of which must occur in either the
+    /// title or the description, ignoring case. May be called several times
+    /// to add further words.
+    pub fn text(mut self, text: &str) -> Self {
+        self.text
+            .extend(text.split_whitespace().map(str::to_lowercase));
+        self
+    }
+
     /// Set target architecture
     pub fn target_arch(mut self, arch: Arch) -> Self {
         self.target_arch = Some(arch);
@@ -150,6 +259,29 @@ impl Query {
         self
     }
 
+    /// Match advisories whether or not they were withdrawn, undoing
+    /// [`Query::withdrawn`] (e.g. as set by [`Query::crate_scope`]).
+    pub fn include_withdrawn(mut self) -> Self {
+        self.withdrawn = None;
+        self
+    }
+
+    /// Query for superseded advisories.
+    ///
+    /// By default they will be omitted from query results, in favor of the
+    /// advisories superseding them.
+    pub fn superseded(mut self, setting: bool) -> Self {
+        self.superseded = Some(setting);
+        self
+    }
+
+    /// Match advisories whether or not they were superseded, undoing
+    /// [`Query::superseded`] (e.g. as set by [`Query::crate_scope`]).
+    pub fn include_superseded(mut self) -> Self {
+        self.superseded = None;
+        self
+    }
+
     /// Query for informational advisories. By default they will be omitted
     /// from query results.
     pub fn informational(mut self, setting: bool) -> Self {
@@ -157,22 +289,101 @@ impl Query {
         self
     }
 
+    /// Query for informational advisories of the given kind,
+    /// e.g. [`Informational::Unmaintained`].
+    ///
+    /// This implies [`Query::informational`]`(true)`.
+    pub fn informational_kind(mut self, kind: Informational) -> Self {
+        self.informational = Some(true);
+        self.informational_kind = Some(kind);
+        self
+    }
+
+    /// Query for advisories published on or after the given date
+    pub fn since(mut self, date: Date) -> Self {
+        self.since = Some(date);
+        self
+    }
+
+    /// Query for advisories published on or before the given date
+    pub fn until(mut self, date: Date) -> Self {
+        self.until = Some(date);
+        self
+    }
+
     /// Does this query match a given advisory?
     pub fn matches(&self, advisory: &Advisory) -> bool {
+        self.evaluate(advisory, None)
+    }
+
+    /// Explain why this query does or does not match a given advisory.
+    ///
+    /// Returns the outcome of every filter this query applies, in the order
+    /// they are evaluated. The query matches if and only if all of them passed.
+    pub fn explain(&self, advisory: &Advisory) -> Vec<QueryCheck> {
+        let mut checks = vec![];
+        self.evaluate(advisory, Some(&mut checks));
+        checks
+    }
+
+    /// Evaluate all filters against the given advisory.
+    ///
+    /// If `trace` is `None` this stops at the first filter which fails,
+    /// otherwise all filters are evaluated and their outcomes recorded.
+    fn evaluate(&self, advisory: &Advisory, mut trace: Option<&mut Vec<QueryCheck>>) -> bool {
+        let mut matches = true;
+
+        // Record the outcome of a filter, returning `true` if evaluation should stop
+        let mut check = |filter: &'static str, passed: bool, detail: &dyn Fn() -> String| {
+            matches &= passed;
+
+            match trace.as_mut() {
+                Some(trace) => {
+                    trace.push(QueryCheck {
+                        filter,
+                        passed,
+                        detail: detail(),
+                    });
+                    false
+                }
+                None => !passed,
+            }
+        };
+
         if let Some(collection) = self.collection {
-            if Some(collection) != advisory.metadata.collection {
+            let passed = Some(collection) == advisory.metadata.collection;
+            let detail = || match advisory.metadata.collection {
+                Some(c) => format!(
+                    "advisory is in the `{}` collection (query: `{}`)",
+                    c, collection
+                ),
+                None => format!("advisory has no collection (query: `{}`)", collection),
+            };
+
+            if check("collection", passed, &detail) {
                 return false;
             }
         }
 
         if let Some(package_name) = &self.package_name {
-            if package_name != &advisory.metadata.package {
+            let passed = package_name == &advisory.metadata.package;
+            let detail = || {
+                format!(
+                    "advisory is for `{}` (query: `{}`)",
+                    advisory.metadata.package, package_name
+                )
+            };
+
+            if check("package_name", passed, &detail) {
                 return false;
             }
         }
 
         if let Some(package_version) = &self.package_version {
-            if !advisory.versions.is_vulnerable(package_version) {
+            let passed = advisory.versions.is_vulnerable(package_version);
+            let detail = || explain_version(advisory, package_version);
+
+            if check("package_version", passed, &detail) {
                 return false;
             }
         }
@@ -186,56 +397,295 @@ impl Query {
                 .unwrap_or_default();
 
             // TODO(tarcieri): better source comparison?
-            if advisory_source.kind() != package_source.kind()
-                || advisory_source.url() != package_source.url()
-            {
+            let passed = advisory_source.kind() == package_source.kind()
+                && advisory_source.url() == package_source.url();
+            let detail = || {
+                format!(
+                    "advisory source is `{}` (package source: `{}`)",
+                    advisory_source, package_source
+                )
+            };
+
+            if check("package_source", passed, &detail) {
                 return false;
             }
         }
 
+        let advisory_severity = self
+            .severity_overrides
+            .get(&advisory.metadata.id)
+            .copied()
+            .or_else(|| advisory.severity());
+
         if let Some(severity_threshold) = self.severity {
-            if let Some(advisory_severity) = advisory.severity() {
-                if advisory_severity < severity_threshold {
-                    return false;
+            let passed = advisory_severity.map_or(true, |s| s >= severity_threshold);
+            let detail = || match advisory_severity {
+                Some(s) => format!(
+                    "advisory severity is {} (threshold: {})",
+                    s, severity_threshold
+                ),
+                None => format!(
+                    "advisory has no CVSS severity, always matches (threshold: {})",
+                    severity_threshold
+                ),
+            };
+
+            if check("severity", passed, &detail) {
+                return false;
+            }
+        }
+
+        if let Some(max_severity) = self.max_severity {
+            let passed = advisory_severity.map_or(true, |s| s <= max_severity);
+            let detail = || match advisory_severity {
+                Some(s) => format!("advisory severity is {} (maximum: {})", s, max_severity),
+                None => format!(
+                    "advisory has no CVSS severity, always matches (maximum: {})",
+                    max_severity
+                ),
+            };
+
+            if check("max_severity", passed, &detail) {
+                return false;
+            }
+        }
+
+        if let Some(cwe) = self.cwe {
+            let class = Cwe::new(cwe);
+            let cwes = advisory.metadata.cwes();
+            let passed = cwes.iter().any(|cwe| cwe.is_in_class(class));
+            let detail = || {
+                let cwes: Vec<_> = cwes.iter().map(|cwe| format!("{:#}", cwe)).collect();
+                format!("advisory weaknesses are {:?} (query: {:#})", cwes, class)
+            };
+
+            if check("cwe", passed, &detail) {
+                return false;
+            }
+        }
+
+        if let Some(category) = &self.category {
+            let passed = advisory.metadata.categories.contains(category);
+            let detail = || {
+                format!(
+                    "advisory categories are {:?} (query: `{}`)",
+                    advisory
+                        .metadata
+                        .categories
+                        .iter()
+                        .map(Category::name)
+                        .collect::<Vec<_>>(),
+                    category
+                )
+            };
+
+            if check("category", passed, &detail) {
+                return false;
+            }
+        }
+
+        if !self.keywords.is_empty() {
+            let passed = self
+                .keywords
+                .iter()
+                .all(|keyword| advisory.metadata.keywords.contains(keyword));
+            let detail = || {
+                format!(
+                    "advisory keywords are {:?} (query: {:?})",
+                    advisory
+                        .metadata
+                        .keywords
+                        .iter()
+                        .map(Keyword::as_str)
+                        .collect::<Vec<_>>(),
+                    self.keywords
+                        .iter()
+                        .map(Keyword::as_str)
+                        .collect::<Vec<_>>()
+                )
+            };
+
+            if check("keywords", passed, &detail) {
+                return false;
+            }
+        }
+
+        if !self.text.is_empty() {
+            let title = advisory.metadata.title.to_lowercase();
+            let description = advisory.metadata.description.to_lowercase();
+            let missing: Vec<&str> = self
+                .text
+                .iter()
+                .filter(|word| {
+                    !title.contains(word.as_str()) && !description.contains(word.as_str())
+                })
+                .map(String::as_str)
+                .collect();
+            let passed = missing.is_empty();
+            let detail = || {
+                if passed {
+                    format!("advisory text contains {:?}", self.text)
+                } else {
+                    format!("advisory text doesn't contain {:?}", missing)
                 }
+            };
+
+            if check("text", passed, &detail) {
+                return false;
             }
         }
 
         if let Some(affected) = &advisory.affected {
             if let Some(target_arch) = self.target_arch {
-                if !affected.arch.is_empty() && !affected.arch.contains(&target_arch) {
+                let passed = affected.arch.is_empty() || affected.arch.contains(&target_arch);
+                let detail = || {
+                    format!(
+                        "advisory affects arch {:?} (target: {})",
+                        affected.arch, target_arch
+                    )
+                };
+
+                if check("target_arch", passed, &detail) {
                     return false;
                 }
             }
 
             if let Some(target_os) = self.target_os {
-                if !affected.os.is_empty() && !affected.os.contains(&target_os) {
+                let passed = affected.os.is_empty() || affected.os.contains(&target_os);
+                let detail = || {
+                    format!(
+                        "advisory affects OS {:?} (target: {})",
+                        affected.os, target_os
+                    )
+                };
+
+                if check("target_os", passed, &detail) {
                     return false;
                 }
             }
         }
 
         if let Some(query_year) = self.year {
-            if let Some(advisory_year) = advisory.metadata.id.year() {
-                if query_year != advisory_year {
-                    return false;
-                }
+            let advisory_year = advisory.metadata.id.year();
+            let passed = advisory_year.map_or(true, |year| year == query_year);
+            let detail = || match advisory_year {
+                Some(year) => format!("advisory ID is from {} (query: {})", year, query_year),
+                None => format!("advisory ID has no year (query: {})", query_year),
+            };
+
+            if check("year", passed, &detail) {
+                return false;
             }
         }
 
         if let Some(withdrawn) = self.withdrawn {
-            if withdrawn != advisory.metadata.withdrawn.is_some() {
+            let passed = withdrawn == advisory.metadata.withdrawn.is_some();
+            let detail = || match &advisory.metadata.withdrawn {
+                Some(date) => format!("advisory was withdrawn on {} (query: {})", date, withdrawn),
+                None => format!("advisory is not withdrawn (query: {})", withdrawn),
+            };
+
+            if check("withdrawn", passed, &detail) {
                 return false;
             }
         }
 
         if let Some(informational) = self.informational {
-            if informational != advisory.metadata.informational.is_some() {
+            let passed = informational == advisory.metadata.informational.is_some();
+            let detail = || match &advisory.metadata.informational {
+                Some(kind) => format!(
+                    "advisory is informational ({}) (query: {})",
+                    kind, informational
+                ),
+                None => format!("advisory is not informational (query: {})", informational),
+            };
+
+            if check("informational", passed, &detail) {
+                return false;
+            }
+        }
+
+        if let Some(kind) = &self.informational_kind {
+            let passed = advisory.metadata.informational.as_ref() == Some(kind);
+            let detail = || match &advisory.metadata.informational {
+                Some(advisory_kind) => format!(
+                    "advisory is informational ({}) (query: {})",
+                    advisory_kind, kind
+                ),
+                None => format!("advisory is not informational (query: {})", kind),
+            };
+
+            if check("informational_kind", passed, &detail) {
                 return false;
             }
         }
 
-        true
+        if self.since.is_some() || self.until.is_some() {
+            let date = &advisory.metadata.date;
+            let passed = self.since.as_ref().map_or(true, |since| date >= since)
+                && self.until.as_ref().map_or(true, |until| date <= until);
+            let detail = || {
+                let bound = |d: &Option<Date>| d.as_ref().map_or("*", Date::as_str).to_owned();
+                format!(
+                    "advisory date is {} (query: {} to {})",
+                    date,
+                    bound(&self.since),
+                    bound(&self.until)
+                )
+            };
+
+            if check("date", passed, &detail) {
+                return false;
+            }
+        }
+
+        matches
+    }
+}
+
+/// Outcome of evaluating a single [`Query`] filter against an advisory
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryCheck {
+    /// Name of the filter, e.g. `package_version`
+    pub filter: &'static str,
+
+    /// Did the advisory pass this filter?
+    pub passed: bool,
+
+    /// Human-readable description of the comparison which was performed
+    pub detail: String,
+}
+
+impl fmt::Display for QueryCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = if self.passed { "pass" } else { "FAIL" };
+        write!(f, "[{}] {}: {}", outcome, self.filter, self.detail)
+    }
+}
+
+/// Describe how the given version compares to the advisory's affected ranges
+fn explain_version(advisory: &Advisory, version: &Version) -> String {
+    let ranges = osv::ranges_for_advisory(&advisory.versions);
+
+    let describe = |range: &osv::OsvRange| {
+        let introduced = range
+            .introduced
+            .as_ref()
+            .map_or_else(|| "*".to_owned(), |v| format!(">={}", v));
+        let fixed = range
+            .fixed
+            .as_ref()
+            .map_or_else(|| "*".to_owned(), |v| format!("<{}", v));
+        format!("[{}, {})", introduced, fixed)
+    };
+
+    match ranges.iter().find(|range| range.affects(version)) {
+        Some(range) => format!("{} is within affected range {}", version, describe(range)),
+        None => format!(
+            "{} is outside all affected ranges: {}",
+            version,
+            ranges.iter().map(describe).collect::<Vec<_>>().join(", ")
+        ),
     }
 }
 
@@ -0,0 +1,85 @@
+//! Serialized snapshots of parsed advisory databases
+//!
+//! A snapshot holds the parsed advisories of a database as JSON, so they can
+//! be loaded without parsing the advisory files again (see the on-disk cache),
+//! or without any access to the filesystem or network, e.g. when auditing
+//! lockfiles from WebAssembly against a database fetched by the host.
+
+use crate::{
+    advisory::Advisory,
+    error::{Error, ErrorKind},
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the snapshot format: bump this when its layout changes
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Snapshot of a parsed advisory database
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
+pub(crate) struct Snapshot {
+    /// Snapshot format version
+    pub format: u32,
+
+    /// Version of the `rustsec` crate which wrote the snapshot
+    pub rustsec_version: String,
+
+    /// Commit the advisories were parsed from, if known
+    #[serde(default)]
+    pub commit: String,
+
+    /// Parsed advisories
+    pub advisories: Vec<Advisory>,
+}
+
+/// Borrowed counterpart of [`Snapshot`] used for serialization
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    format: u32,
+    rustsec_version: &'a str,
+    commit: &'a str,
+    advisories: Vec<&'a Advisory>,
+}
+
+/// Parse a snapshot, failing if it was written in a different format
+pub(crate) fn decode(data: &[u8]) -> Result<Snapshot, Error> {
+    let snapshot: Snapshot = serde_json::from_slice(data).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "invalid advisory database snapshot".to_owned(),
+            e,
+        )
+    })?;
+
+    if snapshot.format != FORMAT_VERSION {
+        fail!(
+            ErrorKind::Version,
+            "unsupported advisory database snapshot format: {} (expected {})",
+            snapshot.format,
+            FORMAT_VERSION
+        );
+    }
+
+    Ok(snapshot)
+}
+
+/// Serialize the given advisories, parsed from the given commit, as a snapshot
+pub(crate) fn encode<'a>(
+    commit: &str,
+    advisories: impl Iterator<Item = &'a Advisory>,
+) -> Result<Vec<u8>, Error> {
+    let snapshot = SnapshotRef {
+        format: FORMAT_VERSION,
+        rustsec_version: crate::VERSION,
+        commit,
+        advisories: advisories.collect(),
+    };
+
+    serde_json::to_vec(&snapshot).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "couldn't serialize advisory database snapshot".to_owned(),
+            e,
+        )
+    })
+}
@@ -0,0 +1,171 @@
+//! Dependency paths: chains of dependencies leading from a root package
+//! (e.g. a workspace member) to one of its dependencies
+
+use crate::package;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "dependency-tree")]
+use cargo_lock::dependency::{
+    graph::{EdgeDirection, NodeIndex},
+    Dependency, Tree,
+};
+
+/// Package along a dependency path
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct PathPackage {
+    /// Name of the package
+    pub name: package::Name,
+
+    /// Version of the package
+    pub version: Version,
+}
+
+/// Chain of dependencies, starting with a root package and ending with the
+/// dependency the path leads to, e.g. `myapp -> hyper -> h2`
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct DependencyPath(Vec<PathPackage>);
+
+impl DependencyPath {
+    /// Create a path from the packages along it, starting with the root
+    pub fn new(packages: Vec<PathPackage>) -> Self {
+        Self(packages)
+    }
+
+    /// Packages along the path, starting with the root
+    pub fn packages(&self) -> &[PathPackage] {
+        &self.0
+    }
+
+    /// Root package the path starts from
+    pub fn root(&self) -> Option<&PathPackage> {
+        self.0.first()
+    }
+
+    /// Number of packages along the path
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the path empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Find the shortest path from each root package of the dependency tree
+    /// (i.e. each package which isn't used as a dependency) to the given
+    /// package. Paths are sorted by length, shortest first.
+    #[cfg(feature = "dependency-tree")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dependency-tree")))]
+    pub fn shortest_from_roots(tree: &Tree, package: &package::Package) -> Vec<Self> {
+        use std::collections::{btree_map, BTreeMap, VecDeque};
+
+        let target = match tree.nodes().get(&Dependency::from(package)) {
+            Some(&node) => node,
+            None => return vec![],
+        };
+
+        let graph = tree.graph();
+
+        // Breadth-first search towards the dependents of the package, keeping
+        // track of the next node on the shortest way back to the package
+        let mut next: BTreeMap<NodeIndex, Option<NodeIndex>> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        next.insert(target, None);
+        queue.push_back(target);
+
+        while let Some(node) = queue.pop_front() {
+            for dependent in graph.neighbors_directed(node, EdgeDirection::Incoming) {
+                if let btree_map::Entry::Vacant(entry) = next.entry(dependent) {
+                    entry.insert(Some(node));
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let mut paths: Vec<Self> = tree
+            .roots()
+            .into_iter()
+            .filter(|root| *root != target && next.contains_key(root))
+            .map(|root| {
+                let mut packages = vec![];
+                let mut node = Some(root);
+
+                while let Some(index) = node {
+                    let package = &graph[index];
+                    packages.push(PathPackage {
+                        name: package.name.clone(),
+                        version: package.version.clone(),
+                    });
+                    node = next[&index];
+                }
+
+                Self(packages)
+            })
+            .collect();
+
+        paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        paths
+    }
+}
+
+impl fmt::Display for DependencyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, package) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" -> ")?;
+            }
+            write!(f, "{}", package.name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "dependency-tree"))]
+mod tests {
+    use super::*;
+    use crate::Lockfile;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["hyper", "h2"]
+
+[[package]]
+name = "tool"
+version = "0.1.0"
+dependencies = ["hyper"]
+
+[[package]]
+name = "hyper"
+version = "0.14.0"
+dependencies = ["h2"]
+
+[[package]]
+name = "h2"
+version = "0.3.0"
+"#;
+
+    #[test]
+    fn shortest_from_roots() {
+        let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+        let tree = lockfile.dependency_tree().unwrap();
+        let h2 = lockfile
+            .packages
+            .iter()
+            .find(|package| package.name.as_str() == "h2")
+            .unwrap();
+
+        let paths: Vec<String> = DependencyPath::shortest_from_roots(&tree, h2)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(paths, ["app -> h2", "tool -> hyper -> h2"]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Crates which have been deprecated in favour of, or renamed to, another crate
+//!
+//! A built-in dataset is shipped with this crate, and additional entries can
+//! be loaded from TOML files in the same format.
+
+use crate::{
+    error::Error,
+    fs,
+    package::{self, Package},
+    Lockfile, Map,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+/// Built-in dataset of deprecated crates
+const BUILTIN: &str = include_str!("deprecation/crates.toml");
+
+/// A crate which has been superseded by another crate
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Deprecation {
+    /// Name of the deprecated crate
+    pub package: package::Name,
+
+    /// Crate which supersedes it
+    pub replacement: package::Name,
+
+    /// Why the crate was superseded
+    pub reason: String,
+
+    /// Link to migration instructions
+    pub url: Option<Url>,
+}
+
+/// Deprecated crates, indexed by name
+#[derive(Clone, Debug, Default)]
+pub struct Deprecations {
+    crates: Map<package::Name, Deprecation>,
+}
+
+/// Layout of deprecation dataset files
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Dataset {
+    #[serde(rename = "crate", default)]
+    crates: Vec<Deprecation>,
+}
+
+impl Deprecations {
+    /// Load the dataset of deprecated crates which ships with this crate
+    pub fn builtin() -> Self {
+        BUILTIN
+            .parse()
+            .expect("built-in deprecation dataset is valid")
+    }
+
+    /// Load a deprecation dataset from a TOML file
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Add the entries from another dataset, replacing existing entries for
+    /// the same crates
+    pub fn extend(&mut self, other: Deprecations) {
+        self.crates.extend(other.crates);
+    }
+
+    /// Look up a deprecated crate by name
+    pub fn get(&self, name: &package::Name) -> Option<&Deprecation> {
+        self.crates.get(name)
+    }
+
+    /// Iterate over all deprecated crates in the dataset
+    pub fn iter(&self) -> impl Iterator<Item = &Deprecation> {
+        self.crates.values()
+    }
+
+    /// Find packages in the lockfile which have been deprecated
+    pub fn find<'a>(&'a self, lockfile: &'a Lockfile) -> Vec<(&'a Package, &'a Deprecation)> {
+        lockfile
+            .packages
+            .iter()
+            .filter_map(|package| Some((package, self.get(&package.name)?)))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for Deprecations {
+    type Err = Error;
+
+    /// Parse a deprecation dataset from TOML
+    fn from_str(toml: &str) -> Result<Self, Error> {
+        let dataset: Dataset = toml::from_str(toml).map_err(Error::from_toml)?;
+
+        Ok(Self {
+            crates: dataset
+                .crates
+                .into_iter()
+                .map(|deprecation| (deprecation.package.clone(), deprecation))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deprecations;
+
+    #[test]
+    fn builtin_dataset_parses() {
+        let deprecations = Deprecations::builtin();
+        let structopt = deprecations.get(&"structopt".parse().unwrap()).unwrap();
+        assert_eq!(structopt.replacement.as_str(), "clap");
+    }
+}
@@ -0,0 +1,104 @@
+//! Embedding audit reports in binaries at build time
+//!
+//! A build script audits the lockfile of the crate being built and writes the
+//! report to `OUT_DIR`, where [`rustsec_embed_audit!`](crate::rustsec_embed_audit)
+//! picks it up, so a deployed service can report the status of its last audit
+//! (e.g. via a health check endpoint) without access to the advisory database.
+//!
+//! In `build.rs`:
+//!
+//! ```no_run
+//! fn main() {
+//!     rustsec::embed::write_report(&Default::default()).unwrap();
+//! }
+//! ```
+//!
+//! And in the crate itself:
+//!
+//! ```ignore
+//! /// Audit report (JSON) from when this binary was built
+//! const AUDIT_REPORT: &str = rustsec::rustsec_embed_audit!();
+//! ```
+
+use crate::{
+    error::{Error, ErrorKind},
+    fs,
+    report::{self, serialization},
+    repository::git::Repository,
+    Database, Lockfile, Report,
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Name of the report file written to `OUT_DIR`
+pub const REPORT_FILE: &str = "rustsec-audit.json";
+
+/// Audit the lockfile of the crate being built and write the report as JSON
+/// to `OUT_DIR`, returning the path of the report.
+///
+/// Meant to be called from a build script. The lockfile is found by looking
+/// in `CARGO_MANIFEST_DIR` and its parents (for workspaces). The advisory
+/// database is fetched, falling back to an existing local copy when offline.
+pub fn write_report(settings: &report::Settings) -> Result<PathBuf, Error> {
+    let out_dir = env_path("OUT_DIR")?;
+    let manifest_dir = env_path("CARGO_MANIFEST_DIR")?;
+
+    let lockfile_path = find_lockfile(&manifest_dir).ok_or_else(|| {
+        format_err!(
+            ErrorKind::NotFound,
+            "couldn't find Cargo.lock for {}",
+            manifest_dir.display()
+        )
+    })?;
+
+    // Build scripts are rerun when their outputs would change
+    println!("cargo:rerun-if-changed={}", lockfile_path.display());
+
+    let lockfile = Lockfile::load(&lockfile_path)?;
+    let db = Database::fetch().or_else(|_| {
+        Repository::open(Repository::default_path())
+            .and_then(|repo| Database::load_from_repo(&repo))
+    })?;
+
+    let report = Report::generate(&db, &lockfile, settings);
+    let path = out_dir.join(REPORT_FILE);
+
+    let json = serialization::to_string(&report)?;
+
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Parse a report embedded with [`rustsec_embed_audit!`](crate::rustsec_embed_audit)
+pub fn parse_report(json: &str) -> Result<Report, Error> {
+    serialization::from_str(json)
+}
+
+/// Get a path from an environment variable set by Cargo for build scripts
+fn env_path(var: &str) -> Result<PathBuf, Error> {
+    env::var_os(var).map(PathBuf::from).ok_or_else(|| {
+        format_err!(
+            ErrorKind::NotFound,
+            "{} is not set (embedded audits must be run from a build script)",
+            var
+        )
+    })
+}
+
+/// Find `Cargo.lock` in the given directory or its parents
+fn find_lockfile(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists())
+}
+
+/// Embed the audit report written by [`embed::write_report`](crate::embed::write_report)
+/// in a build script as a `&'static str` of JSON.
+#[macro_export]
+macro_rules! rustsec_embed_audit {
+    () => {
+        include_str!(concat!(env!("OUT_DIR"), "/rustsec-audit.json"))
+    };
+}
@@ -1,8 +1,10 @@
 //! Error types used by this crate
 
+use crate::{advisory, package};
 use std::{
     fmt::{self, Display},
     io,
+    path::{Path, PathBuf},
     str::Utf8Error,
 };
 use thiserror::Error;
@@ -49,6 +51,30 @@ pub struct Error {
     /// The specific type of this error should not be considered part of the stable interface of
     /// this crate.
     source: Option<Box<dyn std::error::Error + Send + Sync>>,
+
+    /// What the error is about, e.g. the file or URL which couldn't be read.
+    ///
+    /// Boxed since most errors don't have any.
+    context: Option<Box<Context>>,
+}
+
+/// What an [`Error`](struct@Error) is about
+#[derive(Debug, Default)]
+struct Context {
+    /// File or directory
+    path: Option<PathBuf>,
+
+    /// Advisory
+    advisory_id: Option<advisory::Id>,
+
+    /// URL of a resource or repository
+    url: Option<String>,
+
+    /// Package
+    package: Option<package::Name>,
+
+    /// Version or version requirement, which may not be valid
+    version: Option<String>,
 }
 
 impl Error {
@@ -64,6 +90,7 @@ impl Error {
             kind,
             msg: description.to_string(),
             source: None,
+            context: None,
         }
     }
 
@@ -72,7 +99,7 @@ impl Error {
     /// `msg` should describe the operation which failed so as to give context for how `source`
     /// is a meaningful error. For example, if `source` is a [`std::io::Error`] from trying to
     /// read a file, then `msg` should include the path of the file and why the file is relevant.
-    pub fn with_source<E: std::error::Error + Send + Sync + 'static>(
+    pub fn with_source<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
         kind: ErrorKind,
         msg: String,
         source: E,
@@ -80,7 +107,8 @@ impl Error {
         Self {
             kind,
             msg,
-            source: Some(Box::new(source)),
+            source: Some(source.into()),
+            context: None,
         }
     }
 
@@ -88,6 +116,82 @@ impl Error {
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
+
+    /// Set the path of the file or directory this error is about
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.context_mut().path = Some(path.into());
+        self
+    }
+
+    /// Set the ID of the advisory this error is about
+    pub fn with_advisory_id(mut self, id: advisory::Id) -> Self {
+        self.context_mut().advisory_id = Some(id);
+        self
+    }
+
+    /// Set the URL of the resource or repository this error is about
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.context_mut().url = Some(url.into());
+        self
+    }
+
+    /// Set the name of the package this error is about
+    pub fn with_package(mut self, package: package::Name) -> Self {
+        self.context_mut().package = Some(package);
+        self
+    }
+
+    /// Set the version or version requirement this error is about
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.context_mut().version = Some(version.into());
+        self
+    }
+
+    /// Path of the file or directory this error is about, e.g. an advisory
+    /// which couldn't be parsed.
+    ///
+    /// Like the other context of the error, this is looked up in the sources
+    /// of the error too, as long as they're [`Error`](struct@Error)s.
+    pub fn path(&self) -> Option<&Path> {
+        self.contexts().find_map(|context| context.path.as_deref())
+    }
+
+    /// ID of the advisory this error is about
+    pub fn advisory_id(&self) -> Option<&advisory::Id> {
+        self.contexts()
+            .find_map(|context| context.advisory_id.as_ref())
+    }
+
+    /// URL of the resource or repository this error is about, e.g. one which
+    /// couldn't be fetched
+    pub fn url(&self) -> Option<&str> {
+        self.contexts().find_map(|context| context.url.as_deref())
+    }
+
+    /// Name of the package this error is about
+    pub fn package(&self) -> Option<&package::Name> {
+        self.contexts().find_map(|context| context.package.as_ref())
+    }
+
+    /// Version or version requirement this error is about, e.g. one which
+    /// couldn't be parsed
+    pub fn version(&self) -> Option<&str> {
+        self.contexts()
+            .find_map(|context| context.version.as_deref())
+    }
+
+    /// Get the context of this error, creating it if there is none yet
+    fn context_mut(&mut self) -> &mut Context {
+        self.context.get_or_insert_with(Default::default)
+    }
+
+    /// Iterate over the context of this error and of its sources
+    fn contexts(&self) -> impl Iterator<Item = &Context> {
+        std::iter::successors(Some(self), |error| {
+            error.source.as_deref()?.downcast_ref::<Error>()
+        })
+        .filter_map(|error| error.context.as_deref())
+    }
 }
 
 impl Display for Error {
@@ -99,8 +203,11 @@ impl Display for Error {
 impl std::error::Error for Error {
     /// The lower-level source of this error, if any.
     ///
-    /// The specific type of the returned error should not be considered part of the stable
-    /// interface of this crate; prefer to use this only for displaying error information.
+    /// Errors from the standard library and from `semver`, `toml` and `cargo-lock` are kept as
+    /// sources, so they can be inspected with `downcast_ref`, e.g. to get the [`io::ErrorKind`]
+    /// of an I/O error. The types of other sources (e.g. errors from git or the crates.io index)
+    /// should not be considered part of the stable interface of this crate; prefer to use them
+    /// only for displaying error information.
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self.source {
             Some(boxed_error) => Some(&**boxed_error),
@@ -135,6 +242,10 @@ pub enum ErrorKind {
     #[error("unable to acquire filesystem lock")]
     LockTimeout,
 
+    /// A panic was caught before it could unwind into foreign code
+    #[error("panic")]
+    Panic,
+
     /// Couldn't parse response data
     #[error("parse error")]
     Parse,
@@ -147,6 +258,10 @@ pub enum ErrorKind {
     #[error("git operation failed")]
     Repo,
 
+    /// A network operation kept failing after being retried
+    #[error("gave up after retrying")]
+    RetriesExhausted,
+
     /// Errors related to versions
     #[error("bad version")]
     Version,
@@ -154,25 +269,25 @@ pub enum ErrorKind {
 
 impl From<Utf8Error> for Error {
     fn from(other: Utf8Error) -> Self {
-        format_err!(ErrorKind::Parse, &other)
+        Self::with_source(ErrorKind::Parse, "invalid UTF-8".to_owned(), other)
     }
 }
 
 impl From<cargo_lock::Error> for Error {
     fn from(other: cargo_lock::Error) -> Self {
-        format_err!(ErrorKind::Io, &other)
+        Self::with_source(ErrorKind::Io, "couldn't load lockfile".to_owned(), other)
     }
 }
 
 impl From<fmt::Error> for Error {
     fn from(other: fmt::Error) -> Self {
-        format_err!(ErrorKind::Io, &other)
+        Self::with_source(ErrorKind::Io, "formatting failed".to_owned(), other)
     }
 }
 
 impl From<io::Error> for Error {
     fn from(other: io::Error) -> Self {
-        format_err!(ErrorKind::Io, &other)
+        Self::with_source(ErrorKind::Io, "I/O error".to_owned(), other)
     }
 }
 
@@ -188,15 +303,18 @@ impl Error {
     pub(crate) fn from_tame(err: tame_index::Error) -> Self {
         // Separate lock timeouts into their own LockTimeout variant.
         use tame_index::utils::flock::LockError;
-        match err {
-            tame_index::Error::Lock(lock_err) => match &lock_err.source {
-                LockError::TimedOut | LockError::Contested => {
-                    format_err!(ErrorKind::LockTimeout, "{}", lock_err)
-                }
-                _ => format_err!(ErrorKind::Io, "{}", lock_err),
-            },
-            other => format_err!(ErrorKind::Registry, "{}", other),
-        }
+        let (kind, msg) = match &err {
+            tame_index::Error::Lock(lock_err) => {
+                let kind = match &lock_err.source {
+                    LockError::TimedOut | LockError::Contested => ErrorKind::LockTimeout,
+                    _ => ErrorKind::Io,
+                };
+                (kind, "couldn't lock the crates.io index")
+            }
+            _ => (ErrorKind::Registry, "crates.io index error"),
+        };
+
+        Self::with_source(kind, msg.to_owned(), err)
     }
 
     /// Converts from [`toml::de::Error`] to our `Error`.
@@ -204,12 +322,16 @@ impl Error {
     /// This is used so rarely that there is no need to `impl From`,
     /// and this way we can avoid leaking it into the public API.
     pub(crate) fn from_toml(other: toml::de::Error) -> Self {
-        format_err!(crate::ErrorKind::Parse, &other)
+        Self::with_source(ErrorKind::Parse, "invalid TOML".to_owned(), other)
     }
 }
 
 impl From<toml::ser::Error> for Error {
     fn from(other: toml::ser::Error) -> Self {
-        format_err!(ErrorKind::Parse, &other)
+        Self::with_source(
+            ErrorKind::Parse,
+            "couldn't serialize TOML".to_owned(),
+            other,
+        )
     }
 }
@@ -2,8 +2,9 @@
 //!
 //! This module is **experimental**, and its behavior may change in the future.
 
-use crate::vulnerability::Vulnerability;
+use crate::{package, vulnerability::Vulnerability, Map, Set, Version, VersionReq};
 use cargo_lock::{Lockfile, Package};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -43,7 +44,6 @@ impl Fixer {
     /// It may remain if no semver-compatible fix was available.
     pub fn get_fix_command(&self, vulnerability: &Vulnerability, dry_run: bool) -> Command {
         let cargo_path: &Path = self.path_to_cargo.as_deref().unwrap_or(Path::new("cargo"));
-        let pkg_name = &vulnerability.package.name;
         let mut command = Command::new(cargo_path);
         command.arg("update");
         if let Some(path) = self.manifest_path.as_ref() {
@@ -53,15 +53,292 @@ impl Fixer {
             command.arg("--dry-run");
         }
         // there can be more than one version of a given package in the lockfile, so we need to iterate over all of them
-        for pkg in self.lockfile.packages.iter().filter(|pkg| {
-            &pkg.name == pkg_name && vulnerability.versions.is_vulnerable(&pkg.version)
-        }) {
+        for pkg in self.vulnerable_packages(vulnerability) {
             let pkgid = pkgid(pkg);
             command.arg(&pkgid);
         }
 
         command
     }
+
+    /// Returns commands which call `cargo update` to fix this vulnerability,
+    /// one for each affected version of the package in the lockfile.
+    ///
+    /// When a patched version is semver-compatible with the version in use,
+    /// the package is updated to the lowest such version with `--precise`.
+    /// This only changes `Cargo.lock`, since the requirements in `Cargo.toml`
+    /// already allow it. Otherwise the package is updated to the latest
+    /// compatible version, which may not fix the vulnerability.
+    pub fn get_fix_commands(&self, vulnerability: &Vulnerability, dry_run: bool) -> Vec<Command> {
+        self.vulnerable_packages(vulnerability)
+            .map(|pkg| {
+                let precise = self.proposed_version(vulnerability, pkg);
+                self.get_update_command(pkg, precise.as_ref(), dry_run)
+            })
+            .collect()
+    }
+
+    /// Returns a command that calls `cargo update` for a single package in
+    /// the lockfile, optionally updating it to a `precise` version.
+    pub fn get_update_command(
+        &self,
+        package: &Package,
+        precise: Option<&Version>,
+        dry_run: bool,
+    ) -> Command {
+        let cargo_path: &Path = self.path_to_cargo.as_deref().unwrap_or(Path::new("cargo"));
+        let mut command = Command::new(cargo_path);
+        command.arg("update");
+        if let Some(path) = self.manifest_path.as_ref() {
+            command.arg("--manifest-path").arg(path);
+        }
+        if dry_run {
+            command.arg("--dry-run");
+        }
+        command.arg(pkgid(package));
+        if let Some(version) = precise {
+            command.arg("--precise").arg(version.to_string());
+        }
+        command
+    }
+
+    /// Version the given package should be updated to in order to fix the
+    /// vulnerability: the lowest patched version which is semver-compatible
+    /// with the version in use, if there is one.
+    pub fn proposed_version(
+        &self,
+        vulnerability: &Vulnerability,
+        package: &Package,
+    ) -> Option<Version> {
+        lowest_patched_version(vulnerability, &package.version, true)
+    }
+
+    /// Lowest patched version the given package could be upgraded to, even
+    /// if it isn't semver-compatible with the version in use. Upgrading to
+    /// such a version requires changing the requirements in `Cargo.toml`.
+    pub fn upgrade_version(
+        &self,
+        vulnerability: &Vulnerability,
+        package: &Package,
+    ) -> Option<Version> {
+        lowest_patched_version(vulnerability, &package.version, false)
+    }
+
+    /// Returns a command that calls `cargo update --workspace`, which
+    /// re-resolves `Cargo.lock` after requirements in `Cargo.toml` files have
+    /// changed, without updating any other dependencies.
+    pub fn get_resolve_command(&self) -> Command {
+        let cargo_path: &Path = self.path_to_cargo.as_deref().unwrap_or(Path::new("cargo"));
+        let mut command = Command::new(cargo_path);
+        command.arg("update");
+        if let Some(path) = self.manifest_path.as_ref() {
+            command.arg("--manifest-path").arg(path);
+        }
+        command.arg("--workspace");
+        command
+    }
+
+    /// Packages in the lockfile which are affected by the given vulnerability
+    pub fn vulnerable_packages<'a>(
+        &'a self,
+        vulnerability: &'a Vulnerability,
+    ) -> impl Iterator<Item = &'a Package> + 'a {
+        self.lockfile.packages.iter().filter(move |pkg| {
+            pkg.name == vulnerability.package.name
+                && vulnerability.versions.is_vulnerable(&pkg.version)
+        })
+    }
+}
+
+/// Change to the version of a package in `Cargo.lock` made by a fix
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Bump {
+    /// Name of the package
+    pub name: package::Name,
+
+    /// Version of the package before the fix
+    pub from: Version,
+
+    /// Version of the package after the fix
+    pub to: Version,
+}
+
+/// Package added to or removed from `Cargo.lock` by a fix
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct LockedPackage {
+    /// Name of the package
+    pub name: package::Name,
+
+    /// Version of the package
+    pub version: Version,
+}
+
+/// Changes to the packages in `Cargo.lock` made by a fix
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Changes {
+    /// Packages whose version changed
+    pub bumps: Vec<Bump>,
+
+    /// Packages which were added
+    pub added: Vec<LockedPackage>,
+
+    /// Packages which were removed
+    pub removed: Vec<LockedPackage>,
+}
+
+impl Changes {
+    /// Compare the packages in `Cargo.lock` before and after a fix.
+    ///
+    /// When versions of a package are both removed and added, they are
+    /// paired up in order as version changes, and the rest are listed as
+    /// added or removed.
+    pub fn between(before: &Lockfile, after: &Lockfile) -> Self {
+        let before = locked_versions(before);
+        let after = locked_versions(after);
+        let names: Set<_> = before.keys().chain(after.keys()).collect();
+        let mut changes = Changes::default();
+
+        for name in names {
+            let none = Set::new();
+            let old = before.get(name).unwrap_or(&none);
+            let new = after.get(name).unwrap_or(&none);
+            let mut removed = old.difference(new);
+            let mut added = new.difference(old);
+
+            loop {
+                let locked = |version: &Version| LockedPackage {
+                    name: (*name).clone(),
+                    version: version.clone(),
+                };
+
+                match (removed.next(), added.next()) {
+                    (Some(from), Some(to)) => changes.bumps.push(Bump {
+                        name: (*name).clone(),
+                        from: (*from).clone(),
+                        to: (*to).clone(),
+                    }),
+                    (Some(version), None) => changes.removed.push(locked(version)),
+                    (None, Some(version)) => changes.added.push(locked(version)),
+                    (None, None) => break,
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Parse the changes `cargo update --dry-run` reports on its standard
+    /// error, e.g. `Updating h2 v0.3.0 -> v0.3.26` or `Adding foo v1.0.0`
+    pub fn from_dry_run(output: &str) -> Self {
+        let version = |word: &&str| word.strip_prefix('v')?.parse::<Version>().ok();
+        let mut changes = Changes::default();
+
+        for line in output.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (name, from) = match (words.get(1), words.get(2).and_then(version)) {
+                (Some(name), Some(from)) => match name.parse::<package::Name>() {
+                    Ok(name) => (name, from),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+
+            match (words[0], words.get(3), words.get(4).and_then(version)) {
+                ("Updating" | "Downgrading", Some(&"->"), Some(to)) => {
+                    changes.bumps.push(Bump { name, from, to })
+                }
+                ("Adding", _, _) => changes.added.push(LockedPackage {
+                    name,
+                    version: from,
+                }),
+                ("Removing", _, _) => changes.removed.push(LockedPackage {
+                    name,
+                    version: from,
+                }),
+                _ => (),
+            }
+        }
+
+        changes
+    }
+
+    /// Add the changes made by another fix, skipping those already included
+    pub fn merge(&mut self, other: Changes) {
+        for bump in other.bumps {
+            if !self.bumps.contains(&bump) {
+                self.bumps.push(bump);
+            }
+        }
+
+        for package in other.added {
+            if !self.added.contains(&package) {
+                self.added.push(package);
+            }
+        }
+
+        for package in other.removed {
+            if !self.removed.contains(&package) {
+                self.removed.push(package);
+            }
+        }
+    }
+
+    /// Are there no changes?
+    pub fn is_empty(&self) -> bool {
+        self.bumps.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Versions of each package in a lockfile
+fn locked_versions(lockfile: &Lockfile) -> Map<&package::Name, Set<&Version>> {
+    let mut versions: Map<_, Set<_>> = Map::new();
+    for package in &lockfile.packages {
+        versions
+            .entry(&package.name)
+            .or_default()
+            .insert(&package.version);
+    }
+    versions
+}
+
+/// Find the lowest patched version newer than the given version. With
+/// `compatible_only`, only versions which are semver-compatible with it are
+/// considered, i.e. which `cargo update` can switch to without changes to
+/// `Cargo.toml`.
+///
+/// Candidates are the lower bounds of the patched version requirements,
+/// which are the versions the fixes were released in.
+fn lowest_patched_version(
+    vulnerability: &Vulnerability,
+    current: &Version,
+    compatible_only: bool,
+) -> Option<Version> {
+    let compatible = VersionReq::parse(&format!("^{}", current)).ok()?;
+
+    vulnerability
+        .versions
+        .patched()
+        .iter()
+        .flat_map(|req| &req.comparators)
+        .filter(|comparator| {
+            matches!(
+                comparator.op,
+                semver::Op::Exact | semver::Op::GreaterEq | semver::Op::Tilde | semver::Op::Caret
+            )
+        })
+        .map(|comparator| Version {
+            major: comparator.major,
+            minor: comparator.minor.unwrap_or(0),
+            patch: comparator.patch.unwrap_or(0),
+            pre: comparator.pre.clone(),
+            build: Default::default(),
+        })
+        .filter(|version| {
+            version > current
+                && (!compatible_only || compatible.matches(version))
+                && !vulnerability.versions.is_vulnerable(version)
+        })
+        .min()
 }
 
 /// Returns a Cargo unique identifier for a package.
@@ -75,3 +352,171 @@ fn pkgid(pkg: &Package) -> String {
         None => format!("{}@{}", pkg.name, pkg.version),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_patched_versions() {
+        let advisory: crate::Advisory = r#"```toml
+[advisory]
+id = "RUSTSEC-2001-2101"
+package = "h2"
+date = "2001-02-03"
+
+[versions]
+patched = [">= 0.3.26, < 0.4", ">= 0.4.4"]
+```
+
+# Title
+"#
+        .parse()
+        .unwrap();
+
+        let package = |version: &str| Package {
+            name: "h2".parse().unwrap(),
+            version: version.parse().unwrap(),
+            source: None,
+            checksum: None,
+            dependencies: vec![],
+            replace: None,
+        };
+
+        let compatible = |version: &str| {
+            let package = package(version);
+            let vulnerability = Vulnerability::new(&advisory, &package);
+            lowest_patched_version(&vulnerability, &package.version, true)
+        };
+
+        assert_eq!(compatible("0.3.1"), Some(Version::new(0, 3, 26)));
+        assert_eq!(compatible("0.4.0"), Some(Version::new(0, 4, 4)));
+        assert_eq!(compatible("0.2.0"), None);
+
+        let package = package("0.2.0");
+        let vulnerability = Vulnerability::new(&advisory, &package);
+        assert_eq!(
+            lowest_patched_version(&vulnerability, &package.version, false),
+            Some(Version::new(0, 3, 26))
+        );
+    }
+
+    #[test]
+    fn changes_between_lockfiles() {
+        let before: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "foo"
+version = "1.2.0"
+
+[[package]]
+name = "h2"
+version = "0.3.0"
+
+[[package]]
+name = "old"
+version = "0.1.0"
+
+[[package]]
+name = "same"
+version = "1.0.0"
+"#
+        .parse()
+        .unwrap();
+
+        let after: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "foo"
+version = "1.1.0"
+
+[[package]]
+name = "h2"
+version = "0.3.26"
+
+[[package]]
+name = "h2"
+version = "0.4.4"
+
+[[package]]
+name = "same"
+version = "1.0.0"
+"#
+        .parse()
+        .unwrap();
+
+        let locked = |name: &str, version| LockedPackage {
+            name: name.parse().unwrap(),
+            version,
+        };
+
+        let changes = Changes::between(&before, &after);
+        assert_eq!(
+            changes,
+            Changes {
+                bumps: vec![
+                    Bump {
+                        name: "foo".parse().unwrap(),
+                        from: Version::new(1, 2, 0),
+                        to: Version::new(1, 1, 0),
+                    },
+                    Bump {
+                        name: "h2".parse().unwrap(),
+                        from: Version::new(0, 3, 0),
+                        to: Version::new(0, 3, 26),
+                    },
+                ],
+                added: vec![locked("h2", Version::new(0, 4, 4))],
+                removed: vec![locked("old", Version::new(0, 1, 0))],
+            }
+        );
+
+        assert!(Changes::between(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn changes_from_dry_run() {
+        let output = "    Updating crates.io index
+     Locking 2 packages to latest compatible versions
+    Updating h2 v0.3.0 -> v0.3.26
+ Downgrading foo v1.2.0 -> v1.1.0 (available: v1.3.0)
+      Adding new v0.1.0
+    Removing old v0.1.0
+warning: not updating lockfile due to dry run
+";
+
+        let mut changes = Changes::from_dry_run(output);
+        assert_eq!(
+            changes,
+            Changes {
+                bumps: vec![
+                    Bump {
+                        name: "h2".parse().unwrap(),
+                        from: Version::new(0, 3, 0),
+                        to: Version::new(0, 3, 26),
+                    },
+                    Bump {
+                        name: "foo".parse().unwrap(),
+                        from: Version::new(1, 2, 0),
+                        to: Version::new(1, 1, 0),
+                    },
+                ],
+                added: vec![LockedPackage {
+                    name: "new".parse().unwrap(),
+                    version: Version::new(0, 1, 0),
+                }],
+                removed: vec![LockedPackage {
+                    name: "old".parse().unwrap(),
+                    version: Version::new(0, 1, 0),
+                }],
+            }
+        );
+
+        // Fixes which update the same packages are only counted once
+        let before = changes.clone();
+        changes.merge(before.clone());
+        assert_eq!(changes, before);
+    }
+}
@@ -0,0 +1,433 @@
+//! Import of GitHub Security Advisories (GHSA) for the Rust ecosystem
+//!
+//! Some advisories about Rust crates are only published as GHSAs. They are
+//! fetched from the [GitHub GraphQL API] and converted to RustSec advisories,
+//! which can be combined with the advisory database using
+//! [`Database::merge`](crate::Database::merge). That skips GHSAs which are
+//! already known by one of their aliases.
+//!
+//! [GitHub GraphQL API]: https://docs.github.com/en/graphql
+
+use crate::{
+    advisory::{Id, License, Metadata},
+    error::{Error, ErrorKind},
+    osv::{self, End},
+    transport::{self, HttpTransport, Response as HttpResponse, Transport},
+    Advisory, Collection, Map, Version,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc, time::Duration};
+use url::Url;
+
+/// URL of the GitHub GraphQL API
+pub const DEFAULT_API_URL: &str = "https://api.github.com/graphql";
+
+/// Timeout for each request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Query for a page of vulnerabilities in the Rust ecosystem
+const QUERY: &str = r#"query($cursor: String) {
+  securityVulnerabilities(ecosystem: RUST, first: 100, after: $cursor) {
+    pageInfo { hasNextPage endCursor }
+    nodes {
+      package { name }
+      vulnerableVersionRange
+      advisory {
+        ghsaId
+        summary
+        description
+        publishedAt
+        withdrawnAt
+        permalink
+        identifiers { type value }
+        references { url }
+        cvssSeverities { cvssV3 { vectorString } }
+      }
+    }
+  }
+}"#;
+
+/// Client for the GitHub GraphQL API
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "ghsa")))]
+pub struct GhsaClient {
+    transport: Arc<dyn Transport>,
+    api_url: String,
+    token: String,
+}
+
+// The token is deliberately left out
+impl fmt::Debug for GhsaClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GhsaClient")
+            .field("api_url", &self.api_url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// GraphQL request body
+#[derive(Serialize)]
+struct Request<'a> {
+    query: &'static str,
+    variables: Variables<'a>,
+}
+
+/// Variables of the GraphQL query
+#[derive(Serialize)]
+struct Variables<'a> {
+    cursor: Option<&'a str>,
+}
+
+/// GraphQL response body
+#[derive(Deserialize)]
+struct Response {
+    data: Option<Data>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+/// Error reported by the GraphQL API
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Data {
+    security_vulnerabilities: Vulnerabilities,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Vulnerabilities {
+    page_info: PageInfo,
+    nodes: Vec<Vulnerability>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// A range of versions of a package affected by an advisory
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Vulnerability {
+    package: Package,
+    vulnerable_version_range: String,
+    advisory: GhsaAdvisory,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhsaAdvisory {
+    ghsa_id: String,
+    summary: String,
+    description: String,
+    published_at: String,
+    withdrawn_at: Option<String>,
+    permalink: Option<Url>,
+    #[serde(default)]
+    identifiers: Vec<Identifier>,
+    #[serde(default)]
+    references: Vec<Reference>,
+    cvss_severities: Option<CvssSeverities>,
+}
+
+#[derive(Clone, Deserialize)]
+struct Identifier {
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+#[derive(Clone, Deserialize)]
+struct Reference {
+    url: Url,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CvssSeverities {
+    cvss_v3: Option<Cvss>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cvss {
+    vector_string: Option<String>,
+}
+
+impl GhsaClient {
+    /// Create a new client authenticated with the given GitHub token
+    pub fn new(token: impl Into<String>) -> Result<Self, Error> {
+        Self::with_api_url(DEFAULT_API_URL, token)
+    }
+
+    /// Create a new client for a GitHub (Enterprise) GraphQL API at the given URL
+    pub fn with_api_url(api_url: &str, token: impl Into<String>) -> Result<Self, Error> {
+        let transport = HttpTransport::new(REQUEST_TIMEOUT).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                "couldn't create GitHub API client".to_owned(),
+                e,
+            )
+        })?;
+
+        Ok(Self {
+            transport: Arc::new(transport),
+            api_url: api_url.to_owned(),
+            token: token.into(),
+        })
+    }
+
+    /// Send requests through the given transport, e.g. to replay recorded
+    /// responses
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Fetch all GHSAs about crates and convert them to RustSec advisories.
+    ///
+    /// Withdrawn advisories are skipped, as are advisories about several
+    /// crates at once and advisories with version ranges which can't be
+    /// represented.
+    pub fn fetch_advisories(&self) -> Result<Vec<Advisory>, Error> {
+        let mut vulnerabilities = vec![];
+        let mut cursor = None;
+
+        loop {
+            let page = self.fetch_page(cursor.as_deref())?;
+            vulnerabilities.extend(page.nodes);
+
+            match page.page_info.end_cursor {
+                Some(end_cursor) if page.page_info.has_next_page => cursor = Some(end_cursor),
+                _ => break,
+            }
+        }
+
+        // Each advisory is listed once per affected version range
+        let mut grouped: Map<String, Vec<Vulnerability>> = Map::new();
+
+        for vulnerability in vulnerabilities {
+            grouped
+                .entry(vulnerability.advisory.ghsa_id.clone())
+                .or_default()
+                .push(vulnerability);
+        }
+
+        let mut advisories = vec![];
+
+        for vulnerabilities in grouped.into_values() {
+            match convert(vulnerabilities) {
+                Ok(Some(advisory)) => advisories.push(advisory),
+                Ok(None) => (),
+                Err(e) if e.kind() == ErrorKind::BadParam => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(advisories)
+    }
+
+    /// Fetch a single page of vulnerabilities
+    fn fetch_page(&self, cursor: Option<&str>) -> Result<Vulnerabilities, Error> {
+        let request = serde_json::to_vec(&Request {
+            query: QUERY,
+            variables: Variables { cursor },
+        })
+        .expect("couldn't serialize GraphQL query");
+
+        let request = transport::Request::post_json(&self.api_url, request)
+            .header("authorization", format!("Bearer {}", self.token));
+
+        let body = self
+            .transport
+            .send(&request)
+            .and_then(HttpResponse::into_body)
+            .map_err(|e| {
+                Error::with_source(
+                    ErrorKind::Io,
+                    "couldn't query GitHub security advisories".to_owned(),
+                    e,
+                )
+                .with_url(&self.api_url)
+            })?;
+
+        let response: Response = serde_json::from_str(&body).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                "invalid GitHub GraphQL response".to_owned(),
+                e,
+            )
+        })?;
+
+        if let Some(error) = response.errors.first() {
+            fail!(
+                ErrorKind::Io,
+                "GitHub GraphQL query failed: {}",
+                error.message
+            );
+        }
+
+        response
+            .data
+            .map(|data| data.security_vulnerabilities)
+            .ok_or_else(|| format_err!(ErrorKind::Parse, "empty GitHub GraphQL response"))
+    }
+}
+
+/// Convert the vulnerable ranges of a single GHSA to a RustSec advisory, or
+/// `None` if it was withdrawn
+fn convert(vulnerabilities: Vec<Vulnerability>) -> Result<Option<Advisory>, Error> {
+    let ghsa = vulnerabilities[0].advisory.clone();
+
+    if ghsa.withdrawn_at.is_some() {
+        return Ok(None);
+    }
+
+    let package = &vulnerabilities[0].package.name;
+    if vulnerabilities
+        .iter()
+        .any(|vulnerability| &vulnerability.package.name != package)
+    {
+        fail!(
+            ErrorKind::BadParam,
+            "{}: advisory affects more than one crate",
+            ghsa.ghsa_id
+        );
+    }
+
+    let intervals = vulnerabilities
+        .iter()
+        .map(|vulnerability| parse_range(&vulnerability.vulnerable_version_range))
+        .collect::<Result<_, _>>()?;
+
+    let (versions, _) = osv::versions_for_intervals(intervals).map_err(|e| {
+        Error::with_source(
+            ErrorKind::BadParam,
+            format!("{}: invalid version ranges", ghsa.ghsa_id),
+            e,
+        )
+    })?;
+
+    let cvss = ghsa
+        .cvss_severities
+        .and_then(|severities| severities.cvss_v3)
+        .and_then(|cvss| cvss.vector_string)
+        .and_then(|vector| vector.parse().ok());
+
+    let metadata = Metadata {
+        id: ghsa.ghsa_id.parse()?,
+        package: package.parse()?,
+        title: ghsa.summary,
+        description: ghsa.description,
+        date: ghsa.published_at.get(..10).unwrap_or_default().parse()?,
+        aliases: ghsa
+            .identifiers
+            .iter()
+            .filter(|identifier| identifier.kind != "GHSA")
+            .map(|identifier| identifier.value.parse())
+            .collect::<Result<Vec<Id>, _>>()?,
+        related: vec![],
+        supersedes: vec![],
+        superseded_by: vec![],
+        collection: Some(Collection::Crates),
+        categories: vec![],
+        keywords: vec![],
+        cvss,
+        additional_cvss: vec![],
+        informational: None,
+        references: ghsa
+            .references
+            .into_iter()
+            .map(|reference| reference.url)
+            .filter(|url| Some(url) != ghsa.permalink.as_ref())
+            .collect(),
+        source: None,
+        url: ghsa.permalink,
+        withdrawn: None,
+        // Content of the GitHub Advisory Database is licensed under CC-BY 4.0
+        license: License::CcBy40,
+    };
+
+    Ok(Some(Advisory {
+        metadata,
+        affected: None,
+        versions,
+    }))
+}
+
+/// Parse a vulnerable version range such as `>= 1.0, < 1.2.3`
+fn parse_range(range: &str) -> Result<(Option<Version>, End), Error> {
+    let mut start = None;
+    let mut end = End::Unbounded;
+
+    for comparator in range.split(',').map(str::trim) {
+        if let Some(version) = comparator.strip_prefix(">=") {
+            start = Some(parse_version(version)?);
+        } else if let Some(version) = comparator.strip_prefix("<=") {
+            end = End::LastAffected(parse_version(version)?);
+        } else if let Some(version) = comparator.strip_prefix('<') {
+            end = End::Fixed(parse_version(version)?);
+        } else if let Some(version) = comparator.strip_prefix('=') {
+            let version = parse_version(version)?;
+            start = Some(version.clone());
+            end = End::LastAffected(version);
+        } else {
+            fail!(ErrorKind::BadParam, "unsupported version range: {}", range);
+        }
+    }
+
+    Ok((start, end))
+}
+
+/// Parse a version, allowing the minor and patch components to be omitted
+fn parse_version(version: &str) -> Result<Version, Error> {
+    let version = version.trim();
+    let padded = match version.matches('.').count() {
+        0 => format!("{}.0.0", version),
+        1 => format!("{}.0", version),
+        _ => version.to_owned(),
+    };
+
+    padded.parse().map_err(|e| {
+        Error::with_source(
+            ErrorKind::Version,
+            format!("invalid version {}", version),
+            e,
+        )
+        .with_version(version)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_range, End};
+
+    #[test]
+    fn parse_vulnerable_ranges() {
+        let (start, end) = parse_range(">= 1.0, < 1.2.3").unwrap();
+        assert_eq!(start.unwrap().to_string(), "1.0.0");
+        assert!(matches!(end, End::Fixed(v) if v.to_string() == "1.2.3"));
+
+        let (start, end) = parse_range("<= 0.4").unwrap();
+        assert!(start.is_none());
+        assert!(matches!(end, End::LastAffected(v) if v.to_string() == "0.4.0"));
+
+        let (start, end) = parse_range("= 2.0.1").unwrap();
+        assert_eq!(start.unwrap().to_string(), "2.0.1");
+        assert!(matches!(end, End::LastAffected(v) if v.to_string() == "2.0.1"));
+
+        assert!(parse_range("> 1.0").is_err());
+    }
+}
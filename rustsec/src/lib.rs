@@ -1,19 +1,35 @@
 #![doc = include_str!("../README.md")]
 #![doc(html_logo_url = "https://raw.githubusercontent.com/RustSec/logos/main/rustsec-logo-lg.png")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
-#![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "capi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "capi", deny(unsafe_code))]
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
 
 #[macro_use]
 mod error;
 
 pub mod advisory;
+#[cfg(feature = "capi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "capi")))]
+pub mod capi;
 mod collection;
+pub mod cwe;
 pub mod database;
+pub mod dependency_path;
+pub mod deprecation;
 mod fixer;
+#[cfg(feature = "ghsa")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ghsa")))]
+pub mod ghsa;
+#[cfg(feature = "nvd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "nvd")))]
+pub mod nvd;
 pub mod osv;
+pub mod progress;
+mod release;
 pub mod report;
 pub mod repository;
+mod upgrade;
 mod vulnerability;
 mod warning;
 
@@ -21,11 +37,30 @@ mod warning;
 #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
 mod cached_index;
 
+#[cfg(feature = "git")]
+#[cfg_attr(docsrs, doc(cfg(feature = "git")))]
+mod crates_io;
+
+#[cfg(feature = "git")]
+#[cfg_attr(docsrs, doc(cfg(feature = "git")))]
+pub mod embed;
+
+#[cfg(feature = "git")]
+#[cfg_attr(docsrs, doc(cfg(feature = "git")))]
+pub mod network;
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub mod nonblocking;
+
+pub mod transport;
+
 #[cfg(feature = "git")]
 #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
 pub mod registry {
     //! Support for interacting with the local crates.io registry index
     pub use super::cached_index::{CachedIndex, ClientBuilder};
+    pub use super::crates_io::CratesIoClient;
 }
 
 pub use cargo_lock::{self, package, Lockfile, SourceId};
@@ -37,13 +72,16 @@ pub use crate::{
     advisory::Advisory,
     collection::Collection,
     database::Database,
+    dependency_path::DependencyPath,
     error::{Error, ErrorKind, Result},
+    release::ReleaseInfo,
     report::Report,
+    upgrade::{PatchedVersion, UpgradeSuggestion},
     vulnerability::Vulnerability,
     warning::{Warning, WarningKind},
 };
 
-pub use crate::fixer::Fixer;
+pub use crate::fixer::{Bump, Changes, Fixer, LockedPackage};
 
 #[cfg(feature = "git")]
 pub use crate::repository::git::Repository;
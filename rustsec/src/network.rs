@@ -0,0 +1,188 @@
+//! Timeouts and retries for network operations, such as fetching the
+//! advisory database or the crates.io index
+
+use crate::error::{Error, ErrorKind};
+use std::{thread, time::Duration};
+
+/// Timeouts and retries for network operations.
+///
+/// By default, operations use the timeouts of the underlying HTTP clients and
+/// aren't retried.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct NetworkConfig {
+    /// Timeout for establishing connections
+    pub connect_timeout: Option<Duration>,
+
+    /// Timeout for each request as a whole, including receiving the response
+    pub request_timeout: Option<Duration>,
+
+    /// Number of times failed operations are retried
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for each further retry
+    pub backoff: Duration,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            request_timeout: None,
+            retries: 0,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Create the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout for establishing connections
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for each request as a whole, including receiving the
+    /// response
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the number of times failed operations are retried
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry, which is doubled for each
+    /// further retry
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Create a client for HTTP requests with the configured timeouts
+    pub fn client_builder(&self) -> tame_index::external::reqwest::ClientBuilder {
+        let mut builder = tame_index::external::reqwest::ClientBuilder::new();
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        builder
+    }
+
+    /// Delay before the given retry (starting at 1)
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+
+    /// Run a network operation, retrying it with exponential backoff when it
+    /// fails for a reason which may be transient (e.g. I/O errors, but not
+    /// invalid parameters or contested locks).
+    ///
+    /// Once the retries are exhausted, the last error is returned with the
+    /// [`ErrorKind::RetriesExhausted`] kind. Without retries, errors are
+    /// returned as-is.
+    pub(crate) fn retry<T>(
+        &self,
+        operation: &str,
+        mut f: impl FnMut() -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut retry = 0;
+
+        loop {
+            let err = match f() {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+
+            if !is_transient(err.kind()) || self.retries == 0 {
+                return Err(err);
+            }
+
+            if retry == self.retries {
+                return Err(exhausted(operation, self.retries, err));
+            }
+
+            retry += 1;
+            thread::sleep(self.delay(retry));
+        }
+    }
+}
+
+/// Could an error of the given kind go away when retrying?
+pub fn is_transient(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::Io | ErrorKind::Registry | ErrorKind::Repo)
+}
+
+/// Wrap the last error of an operation which was retried `retries` times
+pub(crate) fn exhausted(operation: &str, retries: u32, err: Error) -> Error {
+    Error::with_source(
+        ErrorKind::RetriesExhausted,
+        format!(
+            "gave up {} after {} {}",
+            operation,
+            retries,
+            if retries == 1 { "retry" } else { "retries" },
+        ),
+        err,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_per_retry() {
+        let network = NetworkConfig::new().backoff(Duration::from_millis(100));
+        assert_eq!(network.delay(1), Duration::from_millis(100));
+        assert_eq!(network.delay(2), Duration::from_millis(200));
+        assert_eq!(network.delay(3), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn retry_until_exhausted() {
+        let network = NetworkConfig::new().retries(2).backoff(Duration::ZERO);
+        let mut attempts = 0;
+        let err = network
+            .retry("testing", || -> Result<(), Error> {
+                attempts += 1;
+                Err(Error::new(ErrorKind::Io, &"connection reset"))
+            })
+            .unwrap_err();
+
+        assert_eq!(attempts, 3);
+        assert_eq!(err.kind(), ErrorKind::RetriesExhausted);
+        assert!(err.to_string().ends_with("gave up testing after 2 retries"));
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.to_string().ends_with("connection reset"));
+    }
+
+    #[test]
+    fn no_retry_for_permanent_errors() {
+        let network = NetworkConfig::new().retries(2).backoff(Duration::ZERO);
+        let mut attempts = 0;
+        let err = network
+            .retry("testing", || -> Result<(), Error> {
+                attempts += 1;
+                Err(Error::new(ErrorKind::BadParam, &"invalid URL"))
+            })
+            .unwrap_err();
+
+        assert_eq!(attempts, 1);
+        assert_eq!(err.kind(), ErrorKind::BadParam);
+    }
+}
@@ -0,0 +1,126 @@
+//! Async API for fetching the advisory database and querying the crates.io
+//! index, for services built on [tokio](https://tokio.rs).
+//!
+//! Fetching the database and looking packages up in the index do blocking
+//! network and filesystem I/O (and the sparse index client drives its own
+//! runtime, which can't be nested in another one). The functions in this
+//! module run these operations on tokio's blocking thread pool, so they must
+//! be called from within a tokio runtime.
+
+use crate::{
+    error::{Error, ErrorKind},
+    package::Package,
+    registry::{CachedIndex, ClientBuilder},
+    repository::git::Repository,
+    Database,
+};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Fetch the default advisory database from GitHub.
+///
+/// See [`Database::fetch`].
+pub async fn fetch_database() -> Result<Database, Error> {
+    blocking(Database::fetch).await
+}
+
+/// Open the advisory database in a local repository, without fetching it.
+///
+/// See [`Database::open`].
+pub async fn open_database(path: PathBuf) -> Result<Database, Error> {
+    blocking(move || Database::open(&path)).await
+}
+
+/// Fetch an advisory database repository and load the database from it.
+///
+/// See [`Repository::fetch`] and [`Database::load_from_repo`].
+pub async fn fetch_repository(
+    url: String,
+    into_path: PathBuf,
+    ensure_fresh: bool,
+    lock_timeout: Duration,
+) -> Result<Database, Error> {
+    blocking(move || {
+        let repo = Repository::fetch(&url, into_path, ensure_fresh, lock_timeout)?;
+        Database::load_from_repo(&repo)
+    })
+    .await
+}
+
+/// Handle to the crates.io index for checking whether packages have been
+/// yanked from async code.
+///
+/// Clones of the handle share the same index, along with its cache of the
+/// crates which were looked up. Like [`CachedIndex`], the index holds Cargo's
+/// package lock until the last handle is dropped.
+#[derive(Clone)]
+pub struct AsyncCachedIndex {
+    index: Arc<Mutex<CachedIndex>>,
+}
+
+impl AsyncCachedIndex {
+    /// Open the crates.io index, fetching it first if it's a git index.
+    ///
+    /// See [`CachedIndex::fetch`].
+    pub async fn fetch(
+        client: Option<ClientBuilder>,
+        lock_timeout: Duration,
+    ) -> Result<Self, Error> {
+        let index = blocking(move || CachedIndex::fetch(client, lock_timeout)).await?;
+        Ok(Self::from(index))
+    }
+
+    /// Open the local crates.io index.
+    ///
+    /// See [`CachedIndex::open`].
+    pub async fn open(lock_timeout: Duration) -> Result<Self, Error> {
+        let index = blocking(move || CachedIndex::open(lock_timeout)).await?;
+        Ok(Self::from(index))
+    }
+
+    /// Find which of the given packages have been yanked, returning them
+    /// along with the errors encountered while looking them up.
+    ///
+    /// See [`CachedIndex::find_yanked`].
+    pub async fn find_yanked(&self, packages: Vec<Package>) -> Vec<Result<Package, Error>> {
+        let index = Arc::clone(&self.index);
+
+        let yanked = blocking(move || {
+            let mut index = index.lock().unwrap_or_else(PoisonError::into_inner);
+            Ok(index
+                .find_yanked(&packages)
+                .into_iter()
+                .map(|result| result.map(Package::clone))
+                .collect())
+        })
+        .await;
+
+        yanked.unwrap_or_else(|e| vec![Err(e)])
+    }
+}
+
+impl From<CachedIndex> for AsyncCachedIndex {
+    fn from(index: CachedIndex) -> Self {
+        Self {
+            index: Arc::new(Mutex::new(index)),
+        }
+    }
+}
+
+/// Run a blocking operation on tokio's blocking thread pool
+async fn blocking<T, F>(operation: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(operation).await.map_err(|e| {
+        Error::with_source(
+            ErrorKind::Io,
+            "blocking operation didn't complete".to_owned(),
+            e,
+        )
+    })?
+}
@@ -0,0 +1,520 @@
+//! Enrichment of advisories with data from the [National Vulnerability Database]
+//!
+//! Advisories which carry a CVE alias but no CVSS score can be filled in with
+//! the CVSS vector, CWE assignments and reference URLs NVD publishes for the
+//! CVE, so that they can be prioritized along with the rest. Responses are
+//! cached on disk, since NVD heavily rate limits unauthenticated clients.
+//!
+//! [National Vulnerability Database]: https://nvd.nist.gov/
+
+use crate::{
+    advisory::{CvssScore, Id, Metadata},
+    error::{Error, ErrorKind},
+    fs,
+    transport::{HttpTransport, Request, Response as HttpResponse, Transport},
+    Report,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use url::Url;
+
+/// URL of the NVD CVE API
+pub const DEFAULT_API_URL: &str = "https://services.nvd.nist.gov/rest/json/cves/2.0";
+
+/// How long cached responses are used before being fetched again
+pub const CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Source recorded for the CVSS vectors added from NVD
+pub const NVD_SOURCE: &str = "NVD";
+
+/// Timeout for each request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimum interval between requests without an API key
+/// (NVD allows 5 requests in a rolling 30 second window)
+const REQUEST_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Minimum interval between requests with an API key
+/// (NVD allows 50 requests in a rolling 30 second window)
+const REQUEST_INTERVAL_WITH_KEY: Duration = Duration::from_millis(600);
+
+/// Data about a CVE published by NVD
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(docsrs, doc(cfg(feature = "nvd")))]
+pub struct NvdRecord {
+    /// CVSS v3 base score vector, preferring NVD's own (primary) assessment
+    pub cvss: Option<cvss::v3::Base>,
+
+    /// [CWE] weakness IDs assigned to the CVE
+    ///
+    /// [CWE]: https://cwe.mitre.org/
+    pub cwes: Vec<u32>,
+
+    /// Reference URLs
+    pub references: Vec<Url>,
+}
+
+/// Client for the NVD CVE API which caches responses in a local directory
+#[cfg_attr(docsrs, doc(cfg(feature = "nvd")))]
+pub struct NvdClient {
+    transport: Arc<dyn Transport>,
+    api_url: String,
+    api_key: Option<String>,
+    cache_dir: PathBuf,
+    last_request: Mutex<Option<Instant>>,
+}
+
+// The API key is deliberately left out
+impl std::fmt::Debug for NvdClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NvdClient")
+            .field("api_url", &self.api_url)
+            .field("cache_dir", &self.cache_dir)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Response of the CVE API
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    vulnerabilities: Vec<Vulnerability>,
+}
+
+#[derive(Deserialize)]
+struct Vulnerability {
+    cve: Cve,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cve {
+    #[serde(default)]
+    metrics: Metrics,
+    #[serde(default)]
+    weaknesses: Vec<Weakness>,
+    #[serde(default)]
+    references: Vec<Reference>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Metrics {
+    #[serde(default)]
+    cvss_metric_v31: Vec<CvssMetric>,
+    #[serde(default)]
+    cvss_metric_v30: Vec<CvssMetric>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CvssMetric {
+    #[serde(rename = "type")]
+    kind: String,
+    cvss_data: CvssData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CvssData {
+    vector_string: String,
+}
+
+#[derive(Deserialize)]
+struct Weakness {
+    #[serde(default)]
+    description: Vec<WeaknessDescription>,
+}
+
+#[derive(Deserialize)]
+struct WeaknessDescription {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Reference {
+    url: String,
+}
+
+impl NvdClient {
+    /// Create a new client for the public NVD API, caching responses in the
+    /// given directory
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::with_api_url(DEFAULT_API_URL, cache_dir)
+    }
+
+    /// Create a new client for an NVD-compatible API at the given URL
+    pub fn with_api_url(api_url: &str, cache_dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let transport = HttpTransport::new(REQUEST_TIMEOUT).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                "couldn't create NVD API client".to_owned(),
+                e,
+            )
+        })?;
+
+        Ok(Self {
+            transport: Arc::new(transport),
+            api_url: api_url.to_owned(),
+            api_key: None,
+            cache_dir: cache_dir.into(),
+            last_request: Mutex::new(None),
+        })
+    }
+
+    /// Authenticate with an NVD API key, which raises the rate limit
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Send requests through the given transport, e.g. to replay recorded
+    /// responses
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Look up a CVE, using the cached response if it is recent enough.
+    ///
+    /// Returns an empty record for CVEs which NVD doesn't know about.
+    pub fn lookup(&self, cve: &Id) -> Result<NvdRecord, Error> {
+        // The ID is used as a file name in the cache, so it must not contain
+        // anything but the parts of a well-formed CVE ID
+        if !is_well_formed_cve(cve.as_str()) {
+            fail!(ErrorKind::BadParam, "not a CVE ID: {}", cve);
+        }
+
+        let cache_path = self.cache_dir.join(format!("{}.json", cve));
+
+        if let Some(record) = load_cached(&cache_path) {
+            return Ok(record);
+        }
+
+        let record = self.fetch(cve)?;
+
+        // The cache is only an optimization: failing to write it shouldn't
+        // prevent using the record
+        if fs::create_dir_all(&self.cache_dir).is_ok() {
+            if let Ok(json) = serde_json::to_vec(&record) {
+                let _ = fs::write(&cache_path, json);
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Fill in missing data for an advisory with a CVE alias from NVD.
+    ///
+    /// Only advisories without a CVSS score are looked up. NVD's CVSS vector
+    /// is added as an additional vector with `NVD` as its source, CWE
+    /// assignments are added as `cwe-<id>` keywords, and references are added
+    /// unless already present. Returns whether anything changed.
+    pub fn enrich(&self, metadata: &mut Metadata) -> Result<bool, Error> {
+        if metadata.primary_cvss().is_some() {
+            return Ok(false);
+        }
+
+        let cve = match std::iter::once(&metadata.id)
+            .chain(&metadata.aliases)
+            .find(|id| id.is_cve())
+        {
+            Some(cve) => cve.clone(),
+            None => return Ok(false),
+        };
+
+        let record = self.lookup(&cve)?;
+        let mut changed = false;
+
+        if let Some(cvss) = record.cvss {
+            metadata.additional_cvss.push(CvssScore {
+                vector: cvss,
+                source: Some(NVD_SOURCE.to_owned()),
+                applies_to: None,
+            });
+            changed = true;
+        }
+
+        for cwe in record.cwes {
+            let keyword = format!("cwe-{}", cwe);
+
+            if !metadata
+                .keywords
+                .iter()
+                .any(|existing| existing.as_str() == keyword)
+            {
+                metadata.keywords.push(keyword.parse()?);
+                changed = true;
+            }
+        }
+
+        for url in record.references {
+            if !metadata.references.contains(&url) && metadata.url.as_ref() != Some(&url) {
+                metadata.references.push(url);
+                changed = true;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Enrich the advisories of all vulnerabilities in a report.
+    ///
+    /// Advisories without a CVSS score always pass the severity threshold of
+    /// the report settings, so it is applied again to the enriched
+    /// advisories. Failing to look up one advisory doesn't prevent enriching
+    /// the others: the errors are returned instead.
+    pub fn enrich_report(&self, report: &mut Report) -> Vec<Error> {
+        let mut errors = vec![];
+
+        for vulnerability in &mut report.vulnerabilities.list {
+            if let Err(err) = self.enrich(&mut vulnerability.advisory) {
+                errors.push(err);
+            }
+        }
+
+        if let Some(threshold) = report.settings.severity {
+            let vulnerabilities = &mut report.vulnerabilities;
+            vulnerabilities
+                .list
+                .retain(|vulnerability| vulnerability.severity().map_or(true, |s| s >= threshold));
+            vulnerabilities.count = vulnerabilities.list.len();
+            vulnerabilities.found = !vulnerabilities.list.is_empty();
+        }
+
+        errors
+    }
+
+    /// Fetch a CVE from the NVD API
+    fn fetch(&self, cve: &Id) -> Result<NvdRecord, Error> {
+        self.wait_for_rate_limit();
+
+        let mut url = Url::parse(&self.api_url).map_err(|e| {
+            Error::with_source(
+                ErrorKind::BadParam,
+                format!("invalid NVD API URL: {}", self.api_url),
+                e,
+            )
+        })?;
+        url.query_pairs_mut().append_pair("cveId", cve.as_str());
+
+        let mut request = Request::get(url.as_str());
+
+        if let Some(api_key) = &self.api_key {
+            request = request.header("apiKey", api_key);
+        }
+
+        let body = self
+            .transport
+            .send(&request)
+            .and_then(HttpResponse::into_body)
+            .map_err(|e| {
+                Error::with_source(ErrorKind::Io, format!("couldn't fetch {} from NVD", cve), e)
+                    .with_url(url.as_str())
+            })?;
+
+        let response: Response = serde_json::from_str(&body).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("invalid NVD response for {}", cve),
+                e,
+            )
+        })?;
+
+        Ok(response
+            .vulnerabilities
+            .into_iter()
+            .next()
+            .map(|vulnerability| vulnerability.cve.into())
+            .unwrap_or_default())
+    }
+
+    /// Sleep until another request is allowed by NVD's rate limit
+    fn wait_for_rate_limit(&self) {
+        let interval = if self.api_key.is_some() {
+            REQUEST_INTERVAL_WITH_KEY
+        } else {
+            REQUEST_INTERVAL
+        };
+
+        let mut last_request = self.last_request.lock().unwrap();
+
+        if let Some(elapsed) = last_request.map(|instant| instant.elapsed()) {
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+
+        *last_request = Some(Instant::now());
+    }
+}
+
+impl From<Cve> for NvdRecord {
+    fn from(cve: Cve) -> Self {
+        // CVSS v3.1 is preferred over v3.0, and NVD's own assessment over
+        // those of other scoring providers
+        let mut metrics: Vec<_> = cve
+            .metrics
+            .cvss_metric_v31
+            .into_iter()
+            .chain(cve.metrics.cvss_metric_v30)
+            .collect();
+        metrics.sort_by_key(|metric| metric.kind != "Primary");
+
+        let cvss = metrics
+            .iter()
+            .find_map(|metric| metric.cvss_data.vector_string.parse().ok());
+
+        let mut cwes: Vec<u32> = cve
+            .weaknesses
+            .iter()
+            .flat_map(|weakness| &weakness.description)
+            .filter_map(|description| description.value.strip_prefix("CWE-")?.parse().ok())
+            .collect();
+        cwes.sort_unstable();
+        cwes.dedup();
+
+        let references = cve
+            .references
+            .iter()
+            .filter_map(|reference| reference.url.parse().ok())
+            .collect();
+
+        NvdRecord {
+            cvss,
+            cwes,
+            references,
+        }
+    }
+}
+
+/// Is the given ID a well-formed CVE ID, i.e. `CVE-YYYY-NNNN...`?
+fn is_well_formed_cve(id: &str) -> bool {
+    let mut parts = match id.strip_prefix("CVE-") {
+        Some(rest) => rest.splitn(2, '-'),
+        None => return false,
+    };
+
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+
+    match (parts.next(), parts.next()) {
+        (Some(year), Some(number)) => year.len() == 4 && is_digits(year) && is_digits(number),
+        _ => false,
+    }
+}
+
+/// Load a cached record if it exists and hasn't expired
+fn load_cached(path: &Path) -> Option<NvdRecord> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    let age = SystemTime::now().duration_since(modified).ok()?;
+
+    if age > CACHE_TTL {
+        return None;
+    }
+
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_well_formed_cve, NvdClient, NvdRecord, Response, NVD_SOURCE};
+    use crate::Advisory;
+
+    #[test]
+    fn well_formed_cve_ids() {
+        assert!(is_well_formed_cve("CVE-2021-45710"));
+        assert!(is_well_formed_cve("CVE-2021-1234567"));
+
+        assert!(!is_well_formed_cve("CVE-2021-"));
+        assert!(!is_well_formed_cve("CVE-21-45710"));
+        assert!(!is_well_formed_cve("CVE-2021-45710-x"));
+        assert!(!is_well_formed_cve("CVE-2021-../../etc/passwd"));
+        assert!(!is_well_formed_cve("GHSA-2021-45710"));
+    }
+
+    #[test]
+    fn parse_cve_response() {
+        let json = r#"{
+          "vulnerabilities": [{
+            "cve": {
+              "id": "CVE-2021-45710",
+              "metrics": {
+                "cvssMetricV31": [
+                  {
+                    "type": "Secondary",
+                    "cvssData": { "vectorString": "CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:N/I:N/A:H" }
+                  },
+                  {
+                    "type": "Primary",
+                    "cvssData": { "vectorString": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H" }
+                  }
+                ]
+              },
+              "weaknesses": [
+                { "description": [{ "lang": "en", "value": "CWE-416" }] },
+                { "description": [{ "lang": "en", "value": "NVD-CWE-Other" }] }
+              ],
+              "references": [{ "url": "https://rustsec.org/advisories/RUSTSEC-2021-0124.html" }]
+            }
+          }]
+        }"#;
+
+        let response: Response = serde_json::from_str(json).unwrap();
+        let record: NvdRecord = response
+            .vulnerabilities
+            .into_iter()
+            .next()
+            .unwrap()
+            .cve
+            .into();
+
+        assert_eq!(
+            record.cvss.unwrap().to_string(),
+            "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"
+        );
+        assert_eq!(record.cwes, vec![416]);
+        assert_eq!(record.references.len(), 1);
+    }
+
+    #[test]
+    fn enrich_from_cached_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = NvdRecord {
+            cvss: Some(
+                "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"
+                    .parse()
+                    .unwrap(),
+            ),
+            cwes: vec![416],
+            references: vec![],
+        };
+        std::fs::write(
+            dir.path().join("CVE-2001-2101.json"),
+            serde_json::to_vec(&record).unwrap(),
+        )
+        .unwrap();
+
+        let client = NvdClient::new(dir.path()).unwrap();
+        let mut advisory = Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+        let metadata = &mut advisory.metadata;
+
+        // Advisories which already have a CVSS vector aren't looked up
+        assert!(!client.enrich(metadata).unwrap());
+
+        metadata.cvss = None;
+        assert!(client.enrich(metadata).unwrap());
+        assert!(metadata.cvss.is_none());
+        assert_eq!(metadata.additional_cvss.len(), 1);
+        assert_eq!(
+            metadata.additional_cvss[0].source.as_deref(),
+            Some(NVD_SOURCE)
+        );
+        assert_eq!(metadata.primary_cvss(), record.cvss.as_ref());
+        assert!(metadata.keywords.iter().any(|k| k.as_str() == "cwe-416"));
+    }
+}
@@ -1,5 +1,5 @@
-//! Provides support for exporting to the interchange format defined by
-//! <https://github.com/google/osv>
+//! Provides support for exporting to and importing from the interchange
+//! format defined by <https://github.com/google/osv>
 //!
 //! We also use OSV-style ranges for version matching in RustSec crate
 //! because it allows handling pre-releases correctly,
@@ -10,9 +10,36 @@
 #[cfg(feature = "osv-export")]
 mod advisory;
 
+#[cfg(feature = "osv-export")]
+mod client;
+
+#[cfg(feature = "osv-export")]
+mod export;
+
+#[cfg(feature = "osv-export")]
+mod import;
+
+#[cfg(feature = "osv-export")]
+mod native;
+
 #[cfg(feature = "osv-export")]
 pub use advisory::OsvAdvisory;
 
+#[cfg(feature = "osv-export")]
+pub use client::{OsvClient, DEFAULT_API_URL};
+
+#[cfg(feature = "osv-export")]
+pub use export::{export_database, write_database};
+
+#[cfg(feature = "osv-export")]
+pub use import::{import_dir, import_file};
+
+#[cfg(feature = "osv-export")]
+pub use native::{import_native_dir, SysLibrary, SysMapping};
+
+#[cfg(feature = "ghsa")]
+pub(crate) use import::{versions_for_intervals, End};
+
 // The rest are enabled unconditionally because the OSV range format
 // is used for determining whether a given version is affected or not
 
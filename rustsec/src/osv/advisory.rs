@@ -5,12 +5,16 @@
 
 use tame_index::external::gix;
 
-use super::ranges_for_advisory;
+use super::{import, ranges_for_advisory};
 use crate::advisory::Versions;
 use crate::{
-    advisory::{affected::FunctionPath, Affected, Category, Id, Informational},
+    advisory::{
+        affected::FunctionPath, Affected, Category, CvssScore, Id, Informational, License, Metadata,
+    },
+    error::{Error, ErrorKind},
+    package,
     repository::git::{self, GitModificationTimes, GitPath},
-    Advisory,
+    Advisory, Collection,
 };
 use serde::{Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
@@ -32,9 +36,11 @@ pub struct OsvAdvisory {
     aliases: Vec<Id>,
     #[serde(default)]
     related: Vec<Id>,
+    #[serde(default)]
     summary: String,
-    details: String,
     #[serde(default)]
+    details: String,
+    #[serde(default, deserialize_with = "deserialize_severity")]
     severity: Vec<OsvSeverity>,
     #[serde(default)]
     affected: Vec<OsvAffected>,
@@ -72,17 +78,60 @@ pub enum OsvSeverity {
     CVSS_V3(cvss::v3::Base),
 }
 
+/// Deserialize severities, skipping scoring systems other than CVSS v3
+/// (e.g. `CVSS_V4` in records from other databases)
+fn deserialize_severity<'de, D>(deserializer: D) -> Result<Vec<OsvSeverity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct RawSeverity {
+        #[serde(rename = "type")]
+        kind: String,
+        score: String,
+    }
+
+    Vec::<RawSeverity>::deserialize(deserializer)?
+        .into_iter()
+        .filter(|severity| severity.kind == "CVSS_V3")
+        .map(|severity| {
+            severity
+                .score
+                .parse()
+                .map(OsvSeverity::CVSS_V3)
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
 impl From<cvss::v3::Base> for OsvSeverity {
     fn from(cvss: cvss::v3::Base) -> Self {
         OsvSeverity::CVSS_V3(cvss)
     }
 }
 
+/// Get the CVSS vectors of an OSV advisory other than the `primary` one (set
+/// in the RustSec-specific data, if at all).
+///
+/// OSV doesn't tell which of several severities is the primary one, so they
+/// all become additional vectors, and the highest-scoring one is used when
+/// there is no primary vector (see [`Metadata::primary_cvss`]).
+fn additional_cvss(primary: Option<&cvss::v3::Base>, severity: Vec<OsvSeverity>) -> Vec<CvssScore> {
+    severity
+        .into_iter()
+        .map(|OsvSeverity::CVSS_V3(cvss)| cvss)
+        .filter(|cvss| Some(cvss) != primary)
+        .map(CvssScore::from)
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OsvAffected {
     pub(crate) package: OsvPackage,
     ecosystem_specific: Option<OsvEcosystemSpecific>,
+    #[serde(default)]
     database_specific: OsvDatabaseSpecific,
+    #[serde(default, deserialize_with = "deserialize_ranges")]
     ranges: Option<Vec<OsvJsonRange>>,
     // FIXME deserialize with deserialize_semver_compat
     versions: Option<Vec<String>>,
@@ -92,8 +141,8 @@ pub struct OsvAffected {
 pub struct OsvJsonRange {
     // 'type' is a reserved keyword in Rust
     #[serde(rename = "type")]
-    kind: String,
-    events: Vec<OsvTimelineEvent>,
+    pub(crate) kind: String,
+    pub(crate) events: Vec<OsvTimelineEvent>,
     // 'repo' field is not used because we don't track or export git commit data
 }
 
@@ -110,6 +159,25 @@ pub enum OsvTimelineEvent {
     LastAffected(semver::Version),
 }
 
+/// Deserialize ranges, skipping `GIT` ranges since their events are commit
+/// hashes rather than versions
+fn deserialize_ranges<'de, D>(deserializer: D) -> Result<Option<Vec<OsvJsonRange>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ranges = match Option::<Vec<serde_json::Value>>::deserialize(deserializer)? {
+        Some(ranges) => ranges,
+        None => return Ok(None),
+    };
+
+    ranges
+        .into_iter()
+        .filter(|range| range.get("type").and_then(|kind| kind.as_str()) != Some("GIT"))
+        .map(|range| serde_json::from_value(range).map_err(serde::de::Error::custom))
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
 fn deserialize_semver_compat<'de, D>(deserializer: D) -> Result<semver::Version, D::Error>
 where
     D: Deserializer<'de>,
@@ -146,14 +214,24 @@ pub enum OsvReferenceKind {
     ADVISORY,
     #[allow(dead_code)]
     ARTICLE,
+    #[allow(dead_code)]
+    DETECTION,
+    #[allow(dead_code)]
+    DISCUSSION,
     REPORT,
     #[allow(dead_code)]
     FIX,
+    #[allow(dead_code)]
+    INTRODUCED,
+    #[allow(dead_code)]
+    GIT,
     PACKAGE,
+    #[allow(dead_code)]
+    EVIDENCE,
     WEB,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OsvEcosystemSpecific {
     affects: Option<OsvEcosystemSpecificAffected>,
     affected_functions: Option<Vec<FunctionPath>>,
@@ -166,6 +244,8 @@ pub struct OsvEcosystemSpecificAffected {
     /// We include function names only in order to allow changing
     /// the way versions are specified without an API break
     functions: Vec<FunctionPath>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
 }
 
 impl From<Affected> for OsvEcosystemSpecificAffected {
@@ -174,11 +254,12 @@ impl From<Affected> for OsvEcosystemSpecificAffected {
             arch: a.arch,
             os: a.os,
             functions: a.functions.into_keys().collect(),
+            features: a.features,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OsvDatabaseSpecific {
     #[serde(default)]
     categories: Vec<Category>,
@@ -190,6 +271,10 @@ pub struct OsvDatabaseSpecific {
 pub struct MainOsvDatabaseSpecific {
     #[serde(default)]
     license: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    supersedes: Vec<Id>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    superseded_by: Vec<Id>,
 }
 
 impl OsvAdvisory {
@@ -210,7 +295,28 @@ impl OsvAdvisory {
         mod_times: &GitModificationTimes,
         path: GitPath<'_>,
     ) -> Self {
+        Self::convert(advisory, git_time_to_rfc3339(mod_times.for_path(path)))
+    }
+
+    /// Converts a single RustSec advisory which was loaded into a
+    /// [`Database`](crate::Database) to OSV format.
+    ///
+    /// Databases don't track when individual advisory files last changed, so
+    /// the time of the given commit (normally the database's latest commit) is
+    /// used as the modification time, or the publication date if there is none.
+    pub fn from_advisory(advisory: Advisory, latest_commit: Option<&git::Commit>) -> Self {
+        let modified = match latest_commit {
+            Some(commit) => time_to_rfc3339(commit.timestamp),
+            None => rustsec_date_to_rfc3339(&advisory.metadata.date),
+        };
+
+        Self::convert(advisory, modified)
+    }
+
+    /// Converts a RustSec advisory with the given modification time (RFC 3339)
+    fn convert(advisory: Advisory, modified: String) -> Self {
         let metadata = advisory.metadata;
+        let severity = metadata.cvss_vectors().cloned().map(Into::into).collect();
 
         // Assemble the URLs to put into 'references' field
         let mut reference_urls: Vec<Url> = Vec::new();
@@ -233,7 +339,7 @@ impl OsvAdvisory {
         OsvAdvisory {
             schema_version: None,
             id: metadata.id,
-            modified: git_time_to_rfc3339(mod_times.for_path(path)),
+            modified,
             published: rustsec_date_to_rfc3339(&metadata.date),
             affected: vec![OsvAffected {
                 package: (&metadata.package).into(),
@@ -253,26 +359,244 @@ impl OsvAdvisory {
             aliases: metadata.aliases,
             related: metadata.related,
             summary: metadata.title,
-            severity: metadata.cvss.into_iter().map(|s| s.into()).collect(),
+            severity,
             details: metadata.description,
             references: osv_references(reference_urls),
             database_specific: MainOsvDatabaseSpecific {
                 license: Some(metadata.license.spdx().to_string()),
+                supersedes: metadata.supersedes,
+                superseded_by: metadata.superseded_by,
             },
         }
     }
 
+    /// Converts an OSV advisory about a single crate to a RustSec advisory.
+    ///
+    /// Affected version ranges are converted to `patched` and `unaffected`
+    /// version requirements. Fails if the advisory doesn't affect exactly one
+    /// crate on crates.io, or has no version ranges.
+    pub fn into_rustsec(self) -> Result<Advisory, Error> {
+        let mut affected = self
+            .affected
+            .into_iter()
+            .filter(|affected| affected.package.ecosystem == ECOSYSTEM);
+
+        let affected = match (affected.next(), affected.next()) {
+            (Some(affected), None) => affected,
+            (None, _) => fail!(
+                ErrorKind::BadParam,
+                "{}: advisory doesn't affect any crates.io packages",
+                self.id
+            ),
+            (Some(_), Some(_)) => fail!(
+                ErrorKind::BadParam,
+                "{}: advisory affects more than one crates.io package",
+                self.id
+            ),
+        };
+
+        let ranges = affected.ranges.unwrap_or_default();
+        if ranges.is_empty() {
+            fail!(
+                ErrorKind::BadParam,
+                "{}: advisory has no version ranges",
+                self.id
+            );
+        }
+
+        let (versions, vulnerable) = import::versions_for_ranges(&ranges).map_err(|e| {
+            Error::with_source(
+                ErrorKind::BadParam,
+                format!("{}: invalid version ranges", self.id),
+                e,
+            )
+        })?;
+
+        let package: package::Name = affected.package.name.parse()?;
+        let ecosystem_specific = affected.ecosystem_specific.unwrap_or_default();
+
+        let (arch, os, affected_functions, features) = match ecosystem_specific.affects {
+            Some(affects) => (
+                affects.arch,
+                affects.os,
+                affects.functions,
+                affects.features,
+            ),
+            None => (
+                vec![],
+                vec![],
+                ecosystem_specific.affected_functions.unwrap_or_default(),
+                vec![],
+            ),
+        };
+
+        // Links added when exporting RustSec advisories are dropped
+        let own_url = format!("https://rustsec.org/advisories/{}.html", self.id);
+        let package_url = format!("https://crates.io/crates/{}", package);
+        let references = self
+            .references
+            .into_iter()
+            .map(|reference| reference.url)
+            .filter(|url| {
+                let url = url.as_str().trim_end_matches('/');
+                url != own_url && url != package_url
+            })
+            .collect();
+
+        let cvss = affected.database_specific.cvss;
+        let additional_cvss = additional_cvss(cvss.as_ref(), self.severity);
+
+        let license = match &self.database_specific.license {
+            Some(license) => license.parse()?,
+            None => License::default(),
+        };
+
+        let metadata = Metadata {
+            id: self.id,
+            package,
+            title: self.summary,
+            description: self.details,
+            date: rfc3339_to_rustsec_date(&self.published)?,
+            aliases: self.aliases,
+            related: self.related,
+            supersedes: self.database_specific.supersedes,
+            superseded_by: self.database_specific.superseded_by,
+            collection: Some(Collection::Crates),
+            categories: affected.database_specific.categories,
+            keywords: vec![],
+            cvss,
+            additional_cvss,
+            informational: affected.database_specific.informational,
+            references,
+            source: None,
+            url: None,
+            withdrawn: self
+                .withdrawn
+                .as_deref()
+                .map(rfc3339_to_rustsec_date)
+                .transpose()?,
+            license,
+        };
+
+        let affected = if arch.is_empty()
+            && os.is_empty()
+            && affected_functions.is_empty()
+            && features.is_empty()
+        {
+            None
+        } else {
+            Some(Affected {
+                arch,
+                os,
+                functions: affected_functions
+                    .into_iter()
+                    .map(|path| (path, vulnerable.clone()))
+                    .collect(),
+                features,
+            })
+        };
+
+        Ok(Advisory {
+            metadata,
+            affected,
+            versions,
+        })
+    }
+
+    /// Names of the projects affected by this advisory in the given ecosystem
+    pub(crate) fn affected_projects<'a>(
+        &'a self,
+        ecosystem: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.affected
+            .iter()
+            .filter(move |affected| affected.package.ecosystem == ecosystem)
+            .map(|affected| affected.package.name.as_str())
+    }
+
+    /// Ecosystems of the packages affected by this advisory
+    pub(crate) fn affected_ecosystems(&self) -> impl Iterator<Item = &str> {
+        self.affected
+            .iter()
+            .map(|affected| affected.package.ecosystem.as_str())
+    }
+
+    /// Converts an OSV advisory about a native library to a RustSec advisory
+    /// in the [`Collection::Native`] collection, filed against the `-sys`
+    /// crate which wraps the library.
+    ///
+    /// Versions of the library don't correspond to versions of the crate, so
+    /// every version of the crate matches: [`Report::generate`] reports these
+    /// advisories as [`WarningKind::Native`] warnings rather than as
+    /// vulnerabilities.
+    ///
+    /// [`Report::generate`]: crate::Report::generate
+    /// [`WarningKind::Native`]: crate::warning::WarningKind::Native
+    pub(crate) fn into_native(self, package: package::Name) -> Result<Advisory, Error> {
+        let additional_cvss = additional_cvss(None, self.severity);
+
+        let title = if self.summary.is_empty() {
+            format!("Vulnerability in native library wrapped by {}", package)
+        } else {
+            self.summary
+        };
+
+        let metadata = Metadata {
+            id: self.id,
+            package,
+            title,
+            description: self.details,
+            date: rfc3339_to_rustsec_date(&self.published)?,
+            aliases: self.aliases,
+            related: self.related,
+            supersedes: self.database_specific.supersedes,
+            superseded_by: self.database_specific.superseded_by,
+            collection: Some(Collection::Native),
+            categories: vec![],
+            keywords: vec![],
+            cvss: None,
+            additional_cvss,
+            informational: None,
+            references: self
+                .references
+                .into_iter()
+                .map(|reference| reference.url)
+                .collect(),
+            source: None,
+            url: None,
+            withdrawn: self
+                .withdrawn
+                .as_deref()
+                .map(rfc3339_to_rustsec_date)
+                .transpose()?,
+            license: match &self.database_specific.license {
+                Some(license) => license.parse()?,
+                // osv.dev data is licensed under CC-BY 4.0 unless noted
+                None => License::CcBy40,
+            },
+        };
+
+        Ok(Advisory {
+            metadata,
+            affected: None,
+            versions: Versions::default(),
+        })
+    }
+
     /// Try to extract RustSec alias id from OSV advisory metadata
     pub fn rustsec_refs_imported(&self) -> Vec<Id> {
+        // References come from untrusted input, so malformed URLs are skipped
         let mut refs: Vec<Id> = self
             .references
             .iter()
-            .filter(|r| {
-                r.url
+            .filter_map(|r| {
+                let path = r
+                    .url
                     .as_str()
-                    .starts_with("https://rustsec.org/advisories/")
+                    .strip_prefix("https://rustsec.org/advisories/")?;
+                let id = path.split(|c| c == '.' || c == '/').next()?;
+                Id::from_str(id).ok().filter(Id::is_rustsec)
             })
-            .map(|r| Id::from_str(&r.url.as_str()[31..48]).expect("Invalid rustsec url"))
             .collect();
         refs.sort();
         refs.dedup();
@@ -352,12 +676,23 @@ fn timeline_for_advisory(versions: &Versions) -> OsvJsonRange {
 }
 
 fn git_time_to_rfc3339(time: gix::date::Time) -> String {
-    git::gix_time_to_time(time)
+    time_to_rfc3339(git::gix_time_to_time(time))
+}
+
+fn time_to_rfc3339(timestamp: time::OffsetDateTime) -> String {
+    timestamp
         .to_offset(time::UtcOffset::UTC)
         .format(&time::format_description::well_known::Rfc3339)
         .expect("well-known format to heap never fails")
 }
 
+fn rfc3339_to_rustsec_date(timestamp: &str) -> Result<crate::advisory::Date, Error> {
+    timestamp
+        .get(..10)
+        .ok_or_else(|| format_err!(ErrorKind::Parse, "invalid timestamp: {}", timestamp))?
+        .parse()
+}
+
 fn rustsec_date_to_rfc3339(d: &crate::advisory::Date) -> String {
     format!("{}-{:02}-{:02}T12:00:00Z", d.year(), d.month(), d.day())
 }
@@ -0,0 +1,213 @@
+//! Client for the [osv.dev](https://osv.dev) API, used to look up advisories
+//! which may not have landed in the RustSec advisory database yet
+
+use super::OsvAdvisory;
+use crate::{
+    error::{Error, ErrorKind},
+    transport::{HttpTransport, Request, Response, Transport},
+    Advisory, Lockfile, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+
+/// Base URL of the osv.dev API
+pub const DEFAULT_API_URL: &str = "https://api.osv.dev/v1";
+
+/// Maximum number of queries in a single batch request
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// Timeout for each request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Ecosystem name of crates.io in OSV
+const ECOSYSTEM: &str = "crates.io";
+
+/// Client for the osv.dev API
+#[derive(Clone, Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub struct OsvClient {
+    transport: Arc<dyn Transport>,
+    api_url: String,
+}
+
+/// Body of a `querybatch` request
+#[derive(Serialize)]
+struct BatchQuery<'a> {
+    queries: &'a [Query<'a>],
+}
+
+/// Query for the advisories affecting a particular version of a crate
+#[derive(Serialize)]
+struct Query<'a> {
+    package: QueryPackage<'a>,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<String>,
+}
+
+/// Package a query is about
+#[derive(Serialize)]
+struct QueryPackage<'a> {
+    name: &'a str,
+    ecosystem: &'static str,
+}
+
+/// Response to a `querybatch` request
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    results: Vec<QueryResult>,
+}
+
+/// Advisories matching a single query
+#[derive(Deserialize)]
+struct QueryResult {
+    #[serde(default)]
+    vulns: Vec<VulnId>,
+    next_page_token: Option<String>,
+}
+
+/// Advisory as listed in `querybatch` results
+#[derive(Deserialize)]
+struct VulnId {
+    id: String,
+}
+
+impl OsvClient {
+    /// Create a new client for the public osv.dev API
+    pub fn new() -> Result<Self, Error> {
+        Self::with_api_url(DEFAULT_API_URL)
+    }
+
+    /// Create a new client for an osv.dev-compatible API at the given URL
+    pub fn with_api_url(api_url: &str) -> Result<Self, Error> {
+        let transport = HttpTransport::new(REQUEST_TIMEOUT).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                "couldn't create osv.dev API client".to_owned(),
+                e,
+            )
+        })?;
+
+        Ok(Self {
+            transport: Arc::new(transport),
+            api_url: api_url.trim_end_matches('/').to_owned(),
+        })
+    }
+
+    /// Send requests through the given transport, e.g. to replay recorded
+    /// responses
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Find the advisories on osv.dev affecting crates.io packages in the
+    /// lockfile, converted to RustSec advisories.
+    ///
+    /// Advisories which can't be represented as RustSec advisories (e.g.
+    /// because they're about several crates at once) and withdrawn
+    /// advisories are skipped.
+    pub fn query_lockfile(&self, lockfile: &Lockfile) -> Result<Vec<Advisory>, Error> {
+        let mut queries: Vec<Query<'_>> = lockfile
+            .packages
+            .iter()
+            .filter(|package| {
+                package
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| source.is_default_registry())
+            })
+            .map(|package| Query {
+                package: QueryPackage {
+                    name: package.name.as_str(),
+                    ecosystem: ECOSYSTEM,
+                },
+                version: package.version.to_string(),
+                page_token: None,
+            })
+            .collect();
+
+        let mut ids = Set::new();
+
+        while !queries.is_empty() {
+            let batch: Vec<_> = queries.drain(..queries.len().min(MAX_BATCH_SIZE)).collect();
+            let response = self.query_batch(&batch)?;
+
+            for (query, result) in batch.into_iter().zip(response.results) {
+                ids.extend(result.vulns.into_iter().map(|vuln| vuln.id));
+
+                // Queries with many results are paginated
+                if let Some(page_token) = result.next_page_token {
+                    queries.push(Query {
+                        page_token: Some(page_token),
+                        ..query
+                    });
+                }
+            }
+        }
+
+        let mut advisories = vec![];
+
+        for id in ids {
+            match self.get(&id)?.into_rustsec() {
+                Ok(advisory) if !advisory.withdrawn() => advisories.push(advisory),
+                Ok(_) => (),
+                Err(e) if e.kind() == ErrorKind::BadParam => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(advisories)
+    }
+
+    /// Get an advisory from osv.dev by its ID
+    pub fn get(&self, id: &str) -> Result<OsvAdvisory, Error> {
+        let url = format!("{}/vulns/{}", self.api_url, id);
+
+        let body = self
+            .transport
+            .send(&Request::get(url.as_str()))
+            .and_then(Response::into_body)
+            .map_err(|e| {
+                Error::with_source(
+                    ErrorKind::Io,
+                    format!("couldn't fetch {} from osv.dev", id),
+                    e,
+                )
+                .with_url(&url)
+            })?;
+
+        serde_json::from_str(&body).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("invalid osv.dev response for {}", id),
+                e,
+            )
+        })
+    }
+
+    /// Perform a single `querybatch` request
+    fn query_batch(&self, queries: &[Query<'_>]) -> Result<BatchResponse, Error> {
+        let url = format!("{}/querybatch", self.api_url);
+        let request =
+            serde_json::to_vec(&BatchQuery { queries }).expect("couldn't serialize osv.dev query");
+
+        let body = self
+            .transport
+            .send(&Request::post_json(url.as_str(), request))
+            .and_then(Response::into_body)
+            .map_err(|e| {
+                Error::with_source(ErrorKind::Io, "couldn't query osv.dev".to_owned(), e)
+                    .with_url(&url)
+            })?;
+
+        serde_json::from_str(&body).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                "invalid osv.dev querybatch response".to_owned(),
+                e,
+            )
+        })
+    }
+}
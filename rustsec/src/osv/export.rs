@@ -0,0 +1,65 @@
+//! Bulk export of a loaded advisory database to OSV format
+
+use super::OsvAdvisory;
+use crate::{
+    advisory::Informational,
+    error::{Error, ErrorKind},
+    fs, Advisory, Collection, Database,
+};
+use std::{io, path::Path};
+
+/// Convert every advisory in the database which osv.dev accepts to OSV format.
+///
+/// Like the advisories published to osv.dev, this only includes crate
+/// advisories, and skips informational advisories other than notices,
+/// unmaintained and unsound crates.
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub fn export_database(db: &Database) -> Vec<OsvAdvisory> {
+    db.iter()
+        .filter(|advisory| is_exportable(advisory))
+        .map(|advisory| OsvAdvisory::from_advisory(advisory.clone(), db.latest_commit()))
+        .collect()
+}
+
+/// Write every advisory in the database which osv.dev accepts to
+/// `<dir>/<ID>.json`, returning the number of advisories written.
+///
+/// See [`export_database`] for which advisories are exported.
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub fn write_database(db: &Database, dir: &Path) -> Result<usize, Error> {
+    fs::create_dir_all(dir)?;
+
+    let advisories = export_database(db);
+
+    for osv in &advisories {
+        let path = dir.join(format!("{}.json", osv.id()));
+        let writer = io::BufWriter::new(fs::File::create(&path)?);
+
+        serde_json::to_writer_pretty(writer, osv).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't write {}", path.display()),
+                e,
+            )
+        })?;
+    }
+
+    Ok(advisories.len())
+}
+
+/// Should the given advisory be exported?
+fn is_exportable(advisory: &Advisory) -> bool {
+    if advisory.metadata.collection.unwrap_or(Collection::Crates) != Collection::Crates {
+        return false;
+    }
+
+    // Other kinds of informational advisories aren't exported to keep the
+    // output format stable; adding new kinds requires a version bump
+    match &advisory.metadata.informational {
+        None
+        | Some(Informational::Notice)
+        | Some(Informational::Unmaintained)
+        | Some(Informational::Unsound) => true,
+        Some(_) => false,
+    }
+}
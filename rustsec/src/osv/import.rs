@@ -0,0 +1,222 @@
+//! Import of advisories in OSV format, e.g. from osv.dev dumps
+
+use super::{
+    advisory::{OsvJsonRange, OsvTimelineEvent},
+    OsvAdvisory,
+};
+use crate::{
+    advisory::Versions,
+    error::{Error, ErrorKind},
+    fs, Advisory,
+};
+use semver::{Version, VersionReq};
+use std::path::Path;
+
+/// Load an OSV advisory from a JSON file and convert it to a RustSec advisory.
+///
+/// See [`OsvAdvisory::into_rustsec`] for which advisories can be converted.
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub fn import_file(path: &Path) -> Result<Advisory, Error> {
+    let json = fs::read_to_string(path).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Io,
+            format!("couldn't open {}", path.display()),
+            e,
+        )
+        .with_path(path)
+    })?;
+
+    let osv: OsvAdvisory = serde_json::from_str(&json).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            format!("error parsing {}", path.display()),
+            e,
+        )
+        .with_path(path)
+    })?;
+
+    osv.into_rustsec().map_err(|e| e.with_path(path))
+}
+
+/// Import every `*.json` OSV advisory in the given directory which is about
+/// a crate on crates.io, e.g. an unpacked osv.dev dump of the `crates.io`
+/// ecosystem.
+///
+/// Withdrawn advisories are skipped, as are advisories about other
+/// ecosystems or about several crates at once.
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub fn import_dir(dir: &Path) -> Result<Vec<Advisory>, Error> {
+    let mut paths = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    let mut advisories = vec![];
+
+    for path in paths {
+        match import_file(&path) {
+            Ok(advisory) if !advisory.withdrawn() => advisories.push(advisory),
+            Ok(_) => (),
+            Err(e) if e.kind() == ErrorKind::BadParam => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(advisories)
+}
+
+/// Start of an affected interval (inclusive), or `None` for all versions
+pub(crate) type Start = Option<Version>;
+
+/// End of an affected interval
+#[derive(Clone, Debug)]
+pub(crate) enum End {
+    /// Versions up to but excluding the `fixed` version are affected
+    Fixed(Version),
+
+    /// Versions up to and including the `last_affected` version are affected
+    LastAffected(Version),
+
+    /// All later versions are affected
+    Unbounded,
+}
+
+/// Convert the events in OSV ranges to the `[versions]` of a RustSec
+/// advisory, along with requirements matching the affected versions.
+pub(super) fn versions_for_ranges(
+    ranges: &[OsvJsonRange],
+) -> Result<(Versions, Vec<VersionReq>), Error> {
+    versions_for_intervals(affected_intervals(ranges)?)
+}
+
+/// Convert affected intervals to the `[versions]` of a RustSec advisory,
+/// along with requirements matching the affected versions.
+pub(crate) fn versions_for_intervals(
+    mut intervals: Vec<(Start, End)>,
+) -> Result<(Versions, Vec<VersionReq>), Error> {
+    if intervals.is_empty() {
+        fail!(ErrorKind::BadParam, "no affected versions");
+    }
+
+    // Starting from the lowest possible version is the same as having no start
+    for (start, _) in &mut intervals {
+        if start.as_ref().map_or(false, is_zero) {
+            *start = None;
+        }
+    }
+
+    intervals.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut patched = vec![];
+    let mut unaffected = vec![];
+
+    // Versions before the first affected version were never affected
+    if let Some((Some(first), _)) = intervals.first() {
+        unaffected.push(req(&format!("< {}", first))?);
+    }
+
+    for (idx, (_, end)) in intervals.iter().enumerate() {
+        let lower = match end {
+            End::Fixed(fixed) => format!(">= {}", fixed),
+            End::LastAffected(last) => format!("> {}", last),
+            End::Unbounded if idx + 1 == intervals.len() => continue,
+            End::Unbounded => fail!(ErrorKind::BadParam, "overlapping version ranges"),
+        };
+
+        match intervals.get(idx + 1) {
+            Some((Some(next), _)) => patched.push(req(&format!("{}, < {}", lower, next))?),
+            Some((None, _)) => fail!(ErrorKind::BadParam, "overlapping version ranges"),
+            None => patched.push(req(&lower)?),
+        }
+    }
+
+    let vulnerable = intervals
+        .iter()
+        .map(|(start, end)| {
+            let mut bounds = vec![];
+
+            if let Some(start) = start {
+                bounds.push(format!(">= {}", start));
+            }
+
+            match end {
+                End::Fixed(fixed) => bounds.push(format!("< {}", fixed)),
+                End::LastAffected(last) => bounds.push(format!("<= {}", last)),
+                End::Unbounded => (),
+            }
+
+            if bounds.is_empty() {
+                Ok(VersionReq::STAR)
+            } else {
+                req(&bounds.join(", "))
+            }
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok((Versions::new(patched, unaffected)?, vulnerable))
+}
+
+/// Collect the affected `[start, end)` intervals from the events in
+/// `SEMVER` and `ECOSYSTEM` ranges
+fn affected_intervals(ranges: &[OsvJsonRange]) -> Result<Vec<(Start, End)>, Error> {
+    let mut intervals = vec![];
+
+    for range in ranges {
+        if range.kind != "SEMVER" && range.kind != "ECOSYSTEM" {
+            continue;
+        }
+
+        let mut introduced: Option<Start> = None;
+
+        for event in &range.events {
+            match event {
+                OsvTimelineEvent::Introduced(version) => {
+                    introduced = Some(Some(version.clone()));
+                }
+                OsvTimelineEvent::Fixed(version) => {
+                    if let Some(start) = introduced.take() {
+                        intervals.push((start, End::Fixed(version.clone())));
+                    }
+                }
+                OsvTimelineEvent::LastAffected(version) => {
+                    if let Some(start) = introduced.take() {
+                        intervals.push((start, End::LastAffected(version.clone())));
+                    }
+                }
+            }
+        }
+
+        if let Some(start) = introduced {
+            intervals.push((start, End::Unbounded));
+        }
+    }
+
+    Ok(intervals)
+}
+
+/// Is this the lowest possible version, i.e. `0.0.0` or `0.0.0-0`?
+fn is_zero(version: &Version) -> bool {
+    version.major == 0
+        && version.minor == 0
+        && version.patch == 0
+        && (version.pre.is_empty() || version.pre.as_str() == "0")
+}
+
+/// Parse a version requirement assembled from event versions
+fn req(s: &str) -> Result<VersionReq, Error> {
+    s.parse().map_err(|e| {
+        Error::with_source(
+            ErrorKind::Version,
+            format!("invalid version requirement {}", s),
+            e,
+        )
+        .with_version(s)
+    })
+}
@@ -0,0 +1,185 @@
+//! Import of advisories about native libraries (e.g. from OSS-Fuzz) which
+//! are wrapped by `-sys` crates
+//!
+//! OSV records from other ecosystems are filtered to the projects listed in a
+//! [`SysMapping`] and converted to advisories in the
+//! [`Collection::Native`](crate::Collection::Native) collection, filed against
+//! the mapped crate. A built-in mapping is shipped with this crate, and
+//! additional entries can be loaded from TOML files in the same format.
+
+use super::OsvAdvisory;
+use crate::{
+    error::{Error, ErrorKind},
+    fs, package, Advisory, Map,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Built-in mapping of native libraries to `-sys` crates
+const BUILTIN: &str = include_str!("native/sys-crates.toml");
+
+/// A native library and the `-sys` crate which wraps it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SysLibrary {
+    /// OSV ecosystem the library's advisories are published in
+    /// (e.g. `OSS-Fuzz`)
+    pub ecosystem: String,
+
+    /// Name of the library's project in that ecosystem
+    pub project: String,
+
+    /// Crate which builds or links the library
+    #[serde(rename = "crate")]
+    pub package: package::Name,
+}
+
+/// Mapping of native libraries to `-sys` crates, indexed by ecosystem and
+/// project
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub struct SysMapping {
+    libraries: Map<(String, String), SysLibrary>,
+}
+
+/// Layout of mapping files
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Dataset {
+    #[serde(rename = "library", default)]
+    libraries: Vec<SysLibrary>,
+}
+
+impl SysMapping {
+    /// Load the mapping which ships with this crate
+    pub fn builtin() -> Self {
+        BUILTIN
+            .parse()
+            .expect("built-in -sys crate mapping is valid")
+    }
+
+    /// Load a mapping from a TOML file
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Add the entries from another mapping, replacing existing entries for
+    /// the same projects
+    pub fn extend(&mut self, other: SysMapping) {
+        self.libraries.extend(other.libraries);
+    }
+
+    /// Look up the crate wrapping a project in the given ecosystem.
+    ///
+    /// Ecosystem suffixes (e.g. the release in `Debian:12`) are ignored.
+    pub fn get(&self, ecosystem: &str, project: &str) -> Option<&package::Name> {
+        let ecosystem = ecosystem.split(':').next().unwrap_or(ecosystem);
+
+        self.libraries
+            .get(&(ecosystem.to_owned(), project.to_owned()))
+            .map(|library| &library.package)
+    }
+
+    /// Iterate over all libraries in the mapping
+    pub fn iter(&self) -> impl Iterator<Item = &SysLibrary> {
+        self.libraries.values()
+    }
+
+    /// Convert an OSV advisory about a mapped library to a native advisory,
+    /// or `None` if it isn't about any of them
+    pub fn convert(&self, osv: OsvAdvisory) -> Result<Option<Advisory>, Error> {
+        let package = osv.affected_ecosystems().find_map(|ecosystem| {
+            osv.affected_projects(ecosystem)
+                .find_map(|project| self.get(ecosystem, project))
+        });
+
+        match package.cloned() {
+            Some(package) => osv.into_native(package).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl std::str::FromStr for SysMapping {
+    type Err = Error;
+
+    /// Parse a mapping from TOML
+    fn from_str(toml: &str) -> Result<Self, Error> {
+        let dataset: Dataset = toml::from_str(toml).map_err(Error::from_toml)?;
+
+        Ok(Self {
+            libraries: dataset
+                .libraries
+                .into_iter()
+                .map(|library| {
+                    (
+                        (library.ecosystem.clone(), library.project.clone()),
+                        library,
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Import every `*.json` OSV advisory in the given directory which is about a
+/// library in the mapping, e.g. an unpacked osv.dev dump of the `OSS-Fuzz`
+/// ecosystem.
+///
+/// Withdrawn advisories are skipped, as are advisories about unmapped projects.
+#[cfg_attr(docsrs, doc(cfg(feature = "osv-export")))]
+pub fn import_native_dir(dir: &Path, mapping: &SysMapping) -> Result<Vec<Advisory>, Error> {
+    let mut paths = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    let mut advisories = vec![];
+
+    for path in paths {
+        let json = fs::read_to_string(&path).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Io,
+                format!("couldn't open {}", path.display()),
+                e,
+            )
+            .with_path(&path)
+        })?;
+
+        let osv: OsvAdvisory = serde_json::from_str(&json).map_err(|e| {
+            Error::with_source(
+                ErrorKind::Parse,
+                format!("error parsing {}", path.display()),
+                e,
+            )
+            .with_path(&path)
+        })?;
+
+        match mapping.convert(osv).map_err(|e| e.with_path(&path))? {
+            Some(advisory) if !advisory.withdrawn() => advisories.push(advisory),
+            _ => (),
+        }
+    }
+
+    Ok(advisories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SysMapping;
+
+    #[test]
+    fn builtin_mapping_parses() {
+        let mapping = SysMapping::builtin();
+        let package = mapping.get("OSS-Fuzz", "openssl").unwrap();
+        assert_eq!(package.as_str(), "openssl-sys");
+        assert!(mapping.get("OSS-Fuzz", "not-a-project").is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! Progress reporting for long-running operations, such as fetching the
+//! advisory database or the crates.io index
+
+use std::sync::{atomic::AtomicUsize, Arc};
+
+#[cfg(feature = "git")]
+use {std::sync::atomic::Ordering, tame_index::external::gix};
+
+/// Number of units of work done in a stage of an operation
+pub type Counter = Arc<AtomicUsize>;
+
+/// Receives the progress of long-running operations, e.g. to display a
+/// progress bar.
+///
+/// Operations consist of stages (e.g. receiving objects, then checking out
+/// files). As the work may be done on other threads, the amount of work done
+/// is shared through a [`Counter`], which should be polled (e.g. whenever a
+/// progress bar is redrawn) rather than expecting to be notified of updates.
+pub trait Progress: Send + Sync {
+    /// A stage of the operation started. `done` counts the units of work done
+    /// so far, out of `total` if it is known.
+    fn stage(&self, name: &str, total: Option<usize>, done: Counter);
+
+    /// The operation finished, successfully or not (e.g. to hide the
+    /// progress bar until the next operation starts)
+    fn finish(&self) {}
+}
+
+/// Adapter reporting the progress of `gix` operations to a [`Progress`]
+#[cfg(feature = "git")]
+pub(crate) struct GixProgress {
+    /// Where to report progress to, if anywhere
+    progress: Option<Arc<dyn Progress>>,
+
+    /// Name of the current task
+    name: Option<String>,
+
+    /// Total amount of work in the current task, if known
+    max: Option<usize>,
+
+    /// Unit of the work in the current task
+    unit: Option<gix::progress::Unit>,
+
+    /// Amount of work done in the current task
+    done: Counter,
+}
+
+#[cfg(feature = "git")]
+impl GixProgress {
+    /// Report the progress of `gix` operations to the given [`Progress`], if any
+    pub(crate) fn new(progress: Option<Arc<dyn Progress>>) -> Self {
+        Self {
+            progress,
+            name: None,
+            max: None,
+            unit: None,
+            done: Counter::default(),
+        }
+    }
+}
+
+#[cfg(feature = "git")]
+impl gix::Count for GixProgress {
+    fn set(&self, step: gix::progress::Step) {
+        self.done.store(step, Ordering::Relaxed);
+    }
+
+    fn step(&self) -> gix::progress::Step {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    fn inc_by(&self, step: gix::progress::Step) {
+        self.done.fetch_add(step, Ordering::Relaxed);
+    }
+
+    fn counter(&self) -> gix::progress::StepShared {
+        self.done.clone()
+    }
+}
+
+#[cfg(feature = "git")]
+impl gix::Progress for GixProgress {
+    fn init(&mut self, max: Option<gix::progress::Step>, unit: Option<gix::progress::Unit>) {
+        self.max = max;
+        self.unit = unit;
+
+        if let (Some(progress), Some(name)) = (&self.progress, &self.name) {
+            progress.stage(name, max, self.done.clone());
+        }
+    }
+
+    fn unit(&self) -> Option<gix::progress::Unit> {
+        self.unit.clone()
+    }
+
+    fn max(&self) -> Option<gix::progress::Step> {
+        self.max
+    }
+
+    fn set_max(&mut self, max: Option<gix::progress::Step>) -> Option<gix::progress::Step> {
+        std::mem::replace(&mut self.max, max)
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn id(&self) -> gix::progress::Id {
+        gix::progress::UNKNOWN
+    }
+
+    fn message(&self, _level: gix::progress::MessageLevel, _message: String) {}
+}
+
+#[cfg(feature = "git")]
+impl gix::NestedProgress for GixProgress {
+    type SubProgress = Self;
+
+    fn add_child(&mut self, name: impl Into<String>) -> Self::SubProgress {
+        self.add_child_with_id(name, gix::progress::UNKNOWN)
+    }
+
+    fn add_child_with_id(
+        &mut self,
+        name: impl Into<String>,
+        _id: gix::progress::Id,
+    ) -> Self::SubProgress {
+        Self {
+            name: Some(name.into()),
+            ..Self::new(self.progress.clone())
+        }
+    }
+}
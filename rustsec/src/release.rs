@@ -0,0 +1,96 @@
+//! Release metadata for packages
+
+use crate::advisory::Date;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// How a particular version of a package relates to its other releases,
+/// giving a sense of how far behind a project is.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ReleaseInfo {
+    /// Date the version was published, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released: Option<Date>,
+
+    /// Number of newer releases which haven't been yanked
+    pub newer_releases: usize,
+
+    /// Latest release which hasn't been yanked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<Version>,
+}
+
+impl ReleaseInfo {
+    /// Compare the given version of a package to its releases which haven't
+    /// been yanked.
+    ///
+    /// Pre-releases are only counted if `version` is a pre-release itself.
+    #[cfg_attr(not(feature = "git"), allow(dead_code))]
+    pub(crate) fn new(version: &Version, releases: impl IntoIterator<Item = Version>) -> Self {
+        let mut info = Self::default();
+
+        for release in releases {
+            if !release.pre.is_empty() && version.pre.is_empty() {
+                continue;
+            }
+
+            if &release > version {
+                info.newer_releases += 1;
+            }
+
+            if info
+                .latest
+                .as_ref()
+                .map_or(true, |latest| &release > latest)
+            {
+                info.latest = Some(release);
+            }
+        }
+
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReleaseInfo;
+    use semver::Version;
+
+    fn releases(versions: &[&str]) -> Vec<Version> {
+        versions.iter().map(|v| v.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn newer_releases() {
+        let version = "1.1.0".parse().unwrap();
+        let info = ReleaseInfo::new(&version, releases(&["1.0.0", "1.2.0", "1.1.0", "2.0.0"]));
+        assert_eq!(info.newer_releases, 2);
+        assert_eq!(info.latest, Some("2.0.0".parse().unwrap()));
+        assert_eq!(info.released, None);
+
+        let info = ReleaseInfo::new(&version, releases(&["1.0.0", "1.1.0"]));
+        assert_eq!(info.newer_releases, 0);
+        assert_eq!(info.latest, Some(version));
+    }
+
+    #[test]
+    fn pre_releases() {
+        let stable = "1.0.0".parse().unwrap();
+        let versions = releases(&["1.0.0", "2.0.0-beta.1"]);
+        let info = ReleaseInfo::new(&stable, versions.clone());
+        assert_eq!(info.newer_releases, 0);
+        assert_eq!(info.latest, Some(stable));
+
+        let pre = "2.0.0-alpha.1".parse().unwrap();
+        let info = ReleaseInfo::new(&pre, versions);
+        assert_eq!(info.newer_releases, 1);
+        assert_eq!(info.latest, Some("2.0.0-beta.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn no_releases() {
+        let info = ReleaseInfo::new(&"1.0.0".parse().unwrap(), vec![]);
+        assert_eq!(info, ReleaseInfo::default());
+    }
+}
@@ -5,18 +5,39 @@
 
 use crate::{
     advisory,
+    collection::Collection,
+    cwe::Cwe,
     database::{Database, Query},
-    map,
+    dependency_path::DependencyPath,
+    deprecation::Deprecations,
+    map, package,
     platforms::target::{Arch, OS},
     vulnerability::Vulnerability,
     warning::{self, Warning},
-    Lockfile, Map,
+    Lockfile, Map, Version,
 };
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+pub mod diff;
+pub mod serialization;
 
 /// Vulnerability report for a given lockfile
-#[derive(Clone, Debug, Deserialize, Serialize)]
+///
+/// New fields may be added in minor releases, so reports can only be created
+/// with [`Report::generate`] outside of this crate.
+///
+/// Serialized reports include the [`Aggregates`] of their vulnerabilities,
+/// which are counted from the list of vulnerabilities as it is when the
+/// report is serialized. The same goes for the [`VulnerabilityGroup`]s
+/// listed when [`Settings::group_versions`] is set.
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
 pub struct Report {
+    /// Version of the [report schema](serialization::SCHEMA), or 0 for
+    /// reports which predate versioning
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// Information about the advisory database
     #[cfg(feature = "git")]
     #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
@@ -38,15 +59,45 @@ pub struct Report {
 impl Report {
     /// Generate a report for the given advisory database and lockfile
     pub fn generate(db: &Database, lockfile: &Lockfile, settings: &Settings) -> Self {
-        let vulnerabilities = db
+        let mut vulnerabilities: Vec<Vulnerability> = db
             .query_vulnerabilities(lockfile, &settings.query())
             .into_iter()
             .filter(|vuln| !settings.ignore.contains(&vuln.advisory.id))
             .collect();
 
-        let warnings = find_warnings(db, lockfile, settings);
+        for vuln in &mut vulnerabilities {
+            vuln.severity_override = settings.severity_override(&vuln.advisory.id).cloned();
+        }
+
+        #[cfg(feature = "dependency-tree")]
+        if let Ok(tree) = lockfile.dependency_tree() {
+            for vuln in &mut vulnerabilities {
+                vuln.paths = DependencyPath::shortest_from_roots(&tree, &vuln.package);
+            }
+        }
+
+        let mut warnings = find_warnings(db, lockfile, settings);
+
+        // Advisories about native libraries are filed against every version
+        // of the `-sys` crates wrapping them, as versions of the library don't
+        // correspond to versions of the crate, so they're only warnings
+        let native_query = settings.query().collection(Collection::Native);
+        let mut native: Vec<Warning> = db
+            .query_vulnerabilities(lockfile, &native_query)
+            .into_iter()
+            .filter(|vuln| !settings.ignore.contains(&vuln.advisory.id))
+            .map(Warning::native)
+            .collect();
+
+        if !native.is_empty() {
+            warnings
+                .entry(warning::WarningKind::Native)
+                .or_default()
+                .append(&mut native);
+        }
 
         Self {
+            schema_version: serialization::SCHEMA_VERSION,
             #[cfg(feature = "git")]
             database: DatabaseInfo::new(db),
             lockfile: LockfileInfo::new(lockfile),
@@ -55,10 +106,44 @@ impl Report {
             warnings,
         }
     }
+
+    /// Count the vulnerabilities in this report by severity, category and
+    /// CWE weakness
+    pub fn aggregates(&self) -> Aggregates {
+        Aggregates::new(&self.vulnerabilities.list)
+    }
+}
+
+impl Serialize for Report {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = if cfg!(feature = "git") { 7 } else { 6 };
+        let mut report = serializer.serialize_struct("Report", fields)?;
+        report.serialize_field("schema_version", &self.schema_version)?;
+        #[cfg(feature = "git")]
+        report.serialize_field("database", &self.database)?;
+        report.serialize_field("lockfile", &self.lockfile)?;
+        report.serialize_field("settings", &self.settings)?;
+        if self.settings.group_versions {
+            let vulnerabilities = GroupedVulnerabilityInfo {
+                info: &self.vulnerabilities,
+                groups: self.vulnerabilities.groups(),
+            };
+            report.serialize_field("vulnerabilities", &vulnerabilities)?;
+        } else {
+            report.serialize_field("vulnerabilities", &self.vulnerabilities)?;
+        }
+        report.serialize_field("warnings", &self.warnings)?;
+        report.serialize_field("aggregates", &self.aggregates())?;
+        report.end()
+    }
 }
 
 /// Options to use when generating the report
+///
+/// New options may be added in minor releases: use [`Settings::builder`] (or
+/// [`Settings::default`] and assign fields) rather than a struct literal.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct Settings {
     /// CPU architecture
     pub target_arch: Option<Arch>,
@@ -74,9 +159,35 @@ pub struct Settings {
 
     /// Types of informational advisories to generate warnings for
     pub informational_warnings: Vec<advisory::Informational>,
+
+    /// Generate warnings for crates which have been superseded by another
+    /// crate (according to [`Deprecations::builtin`])
+    #[serde(default)]
+    pub deprecated_warnings: bool,
+
+    /// Report withdrawn advisories too, which are skipped by default
+    #[serde(default)]
+    pub include_withdrawn: bool,
+
+    /// Severities to use instead of those of the CVSS vectors of particular
+    /// advisories, e.g. for vulnerabilities which aren't exploitable in the
+    /// way a project uses the affected crate
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub severity_overrides: Vec<SeverityOverride>,
+
+    /// Also list vulnerabilities in several versions of a crate which are
+    /// affected by the same advisory as one [`VulnerabilityGroup`] in
+    /// serialized reports
+    #[serde(default)]
+    pub group_versions: bool,
 }
 
 impl Settings {
+    /// Create a [`SettingsBuilder`] starting from the default settings
+    pub fn builder() -> SettingsBuilder {
+        SettingsBuilder::default()
+    }
+
     /// Get a query which corresponds to the configured report settings.
     /// Note that queries can't filter ignored advisories, so this happens in
     /// a separate pass
@@ -95,14 +206,141 @@ impl Settings {
             query = query.severity(severity);
         }
 
+        if self.include_withdrawn {
+            query = query.include_withdrawn();
+        }
+
+        for severity_override in &self.severity_overrides {
+            query =
+                query.severity_override(severity_override.id.clone(), severity_override.severity);
+        }
+
         query
     }
+
+    /// Get the severity override for the advisory with the given ID, if any
+    pub fn severity_override(&self, id: &advisory::Id) -> Option<&SeverityOverride> {
+        self.severity_overrides
+            .iter()
+            .find(|severity_override| &severity_override.id == id)
+    }
+}
+
+/// Severity to use for an advisory instead of the severity of its CVSS vector
+///
+/// Overrides change the effective severity of the advisory's vulnerabilities
+/// (see [`Vulnerability::severity`]), which is used for the severity threshold
+/// and by policies based on severity. They are recorded in the report next to
+/// the vulnerabilities they apply to.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct SeverityOverride {
+    /// ID of the advisory
+    pub id: advisory::Id,
+
+    /// Severity to use for the advisory
+    pub severity: advisory::Severity,
+
+    /// Why the severity was overridden, e.g. "not exploitable in our usage"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl SeverityOverride {
+    /// Override the severity of the advisory with the given ID
+    pub fn new(id: advisory::Id, severity: advisory::Severity) -> Self {
+        Self {
+            id,
+            severity,
+            reason: None,
+        }
+    }
+
+    /// Record why the severity was overridden
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+/// Builder for report [`Settings`]
+#[derive(Clone, Debug, Default)]
+pub struct SettingsBuilder {
+    settings: Settings,
+}
+
+impl SettingsBuilder {
+    /// Only report vulnerabilities affecting the given CPU architecture
+    pub fn target_arch(mut self, arch: Arch) -> Self {
+        self.settings.target_arch = Some(arch);
+        self
+    }
+
+    /// Only report vulnerabilities affecting the given operating system
+    pub fn target_os(mut self, os: OS) -> Self {
+        self.settings.target_os = Some(os);
+        self
+    }
+
+    /// Only report vulnerabilities at or above the given severity
+    pub fn severity(mut self, severity: advisory::Severity) -> Self {
+        self.settings.severity = Some(severity);
+        self
+    }
+
+    /// Ignore the advisory with the given ID
+    pub fn ignore(mut self, id: advisory::Id) -> Self {
+        self.settings.ignore.push(id);
+        self
+    }
+
+    /// Generate warnings for the given kind of informational advisories
+    pub fn informational_warning(mut self, kind: advisory::Informational) -> Self {
+        if !self.settings.informational_warnings.contains(&kind) {
+            self.settings.informational_warnings.push(kind);
+        }
+        self
+    }
+
+    /// Generate warnings for crates which have been superseded by another crate
+    pub fn deprecated_warnings(mut self, setting: bool) -> Self {
+        self.settings.deprecated_warnings = setting;
+        self
+    }
+
+    /// Report withdrawn advisories too
+    pub fn include_withdrawn(mut self, setting: bool) -> Self {
+        self.settings.include_withdrawn = setting;
+        self
+    }
+
+    /// Override the severity of an advisory, replacing any earlier override
+    /// for the same advisory
+    pub fn severity_override(mut self, severity_override: SeverityOverride) -> Self {
+        self.settings
+            .severity_overrides
+            .retain(|existing| existing.id != severity_override.id);
+        self.settings.severity_overrides.push(severity_override);
+        self
+    }
+
+    /// Group the vulnerable versions of a crate by advisory
+    pub fn group_versions(mut self, setting: bool) -> Self {
+        self.settings.group_versions = setting;
+        self
+    }
+
+    /// Build the [`Settings`]
+    pub fn build(self) -> Settings {
+        self.settings
+    }
 }
 
 /// Information about the advisory database
 #[cfg(feature = "git")]
 #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct DatabaseInfo {
     /// Number of advisories in the database
     #[serde(rename = "advisory-count")]
@@ -148,6 +386,7 @@ impl LockfileInfo {
 
 /// Information about detected vulnerabilities
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct VulnerabilityInfo {
     /// Were any vulnerabilities found?
     pub found: bool,
@@ -168,6 +407,136 @@ impl VulnerabilityInfo {
             list,
         }
     }
+
+    /// Group the vulnerabilities by advisory and crate
+    pub fn groups(&self) -> Vec<VulnerabilityGroup> {
+        VulnerabilityGroup::group(&self.list)
+            .iter()
+            .map(|group| VulnerabilityGroup::new(group))
+            .collect()
+    }
+}
+
+/// Vulnerability info along with its groups, for serialization
+#[derive(Serialize)]
+struct GroupedVulnerabilityInfo<'a> {
+    #[serde(flatten)]
+    info: &'a VulnerabilityInfo,
+    groups: Vec<VulnerabilityGroup>,
+}
+
+/// Vulnerable versions of a crate which are affected by the same advisory
+///
+/// The vulnerability of each version, with e.g. its release information, is
+/// still listed separately in [`VulnerabilityInfo::list`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct VulnerabilityGroup {
+    /// ID of the advisory
+    pub advisory: advisory::Id,
+
+    /// Name of the vulnerable crate
+    pub package: package::Name,
+
+    /// Vulnerable versions of the crate
+    pub versions: Vec<Version>,
+
+    /// Shortest dependency paths from the root packages to each of the
+    /// vulnerable versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<DependencyPath>,
+}
+
+impl VulnerabilityGroup {
+    /// Group the given vulnerabilities by advisory and crate, keeping the
+    /// groups in the order of their first vulnerability
+    pub fn group<'a>(
+        vulnerabilities: impl IntoIterator<Item = &'a Vulnerability>,
+    ) -> Vec<Vec<&'a Vulnerability>> {
+        let mut groups: Vec<Vec<&Vulnerability>> = vec![];
+
+        for vulnerability in vulnerabilities {
+            let group = groups.iter_mut().find(|group| {
+                group[0].advisory.id == vulnerability.advisory.id
+                    && group[0].package.name == vulnerability.package.name
+            });
+
+            match group {
+                Some(group) => group.push(vulnerability),
+                None => groups.push(vec![vulnerability]),
+            }
+        }
+
+        groups
+    }
+
+    /// Describe a group of vulnerabilities (see [`VulnerabilityGroup::group`])
+    fn new(group: &[&Vulnerability]) -> Self {
+        Self {
+            advisory: group[0].advisory.id.clone(),
+            package: group[0].package.name.clone(),
+            versions: group
+                .iter()
+                .map(|vuln| vuln.package.version.clone())
+                .collect(),
+            paths: group
+                .iter()
+                .flat_map(|vuln| vuln.paths.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+/// Number of vulnerabilities in a report by severity, category and [CWE]
+/// weakness, for e.g. security dashboards
+///
+/// A vulnerability is counted once for each of the categories and weaknesses
+/// of its advisory (see [`advisory::Metadata::cwes`]), so these counts may
+/// add up to more or less than the number of vulnerabilities.
+///
+/// [CWE]: https://cwe.mitre.org/
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Aggregates {
+    /// Number of vulnerabilities by their effective severity (see
+    /// [`Vulnerability::severity`])
+    pub by_severity: Map<advisory::Severity, usize>,
+
+    /// Number of vulnerabilities without a severity, i.e. whose advisory has
+    /// no CVSS vector and whose severity isn't overridden
+    pub unscored: usize,
+
+    /// Number of vulnerabilities by advisory category
+    pub by_category: Map<advisory::Category, usize>,
+
+    /// Number of vulnerabilities by CWE weakness
+    pub by_cwe: Map<Cwe, usize>,
+}
+
+impl Aggregates {
+    /// Count the given vulnerabilities
+    pub fn new<'a>(vulnerabilities: impl IntoIterator<Item = &'a Vulnerability>) -> Self {
+        let mut aggregates = Self::default();
+
+        for vulnerability in vulnerabilities {
+            let metadata = &vulnerability.advisory;
+
+            match vulnerability.severity() {
+                Some(severity) => *aggregates.by_severity.entry(severity).or_default() += 1,
+                None => aggregates.unscored += 1,
+            }
+
+            for category in &metadata.categories {
+                *aggregates.by_category.entry(category.clone()).or_default() += 1;
+            }
+
+            for cwe in metadata.cwes() {
+                *aggregates.by_cwe.entry(cwe).or_default() += 1;
+            }
+        }
+
+        aggregates
+    }
 }
 
 /// Information about warnings
@@ -219,5 +588,26 @@ pub fn find_warnings(db: &Database, lockfile: &Lockfile, settings: &Settings) ->
         }
     }
 
+    if settings.deprecated_warnings {
+        let mut deprecated = find_deprecated(&Deprecations::builtin(), lockfile);
+
+        if !deprecated.is_empty() {
+            warnings
+                .entry(warning::WarningKind::Deprecated)
+                .or_default()
+                .append(&mut deprecated);
+        }
+    }
+
     warnings
 }
+
+/// Find packages in the [`Lockfile`] which have been superseded by another
+/// crate according to the given [`Deprecations`]
+pub fn find_deprecated(deprecations: &Deprecations, lockfile: &Lockfile) -> Vec<Warning> {
+    deprecations
+        .find(lockfile)
+        .into_iter()
+        .map(|(package, deprecation)| Warning::deprecated(package, deprecation.clone()))
+        .collect()
+}
@@ -0,0 +1,104 @@
+//! Differences between two [`Report`]s, e.g. to only fail CI on findings
+//! which were introduced since a baseline report
+
+use super::{Report, VulnerabilityInfo};
+use crate::{vulnerability::Vulnerability, warning::Warning, Set};
+use serde::{Deserialize, Serialize};
+
+/// Findings which were introduced, resolved, or persisted between an old
+/// (baseline) report and a new one.
+///
+/// Vulnerabilities are identified by their advisory and the affected package
+/// and version, and warnings by their kind, advisory (if any) and package and
+/// version. Each part is a copy of the report it was taken from, restricted
+/// to the corresponding findings.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct ReportDiff {
+    /// Findings of the new report which aren't in the old one
+    pub introduced: Report,
+
+    /// Findings of the old report which are no longer in the new one
+    pub resolved: Report,
+
+    /// Findings which are in both reports (as found in the new one)
+    pub persisting: Report,
+}
+
+impl ReportDiff {
+    /// Compare the findings of an old report with those of a new one
+    pub fn new(old: &Report, new: &Report) -> Self {
+        let old_findings = findings(old);
+        let new_findings = findings(new);
+
+        Self {
+            introduced: restrict(new, |finding| !old_findings.contains(finding)),
+            resolved: restrict(old, |finding| !new_findings.contains(finding)),
+            persisting: restrict(new, |finding| old_findings.contains(finding)),
+        }
+    }
+
+    /// Were any findings introduced or resolved?
+    pub fn has_changes(&self) -> bool {
+        has_findings(&self.introduced) || has_findings(&self.resolved)
+    }
+}
+
+/// Identify a vulnerability across reports
+fn vulnerability_finding(vulnerability: &Vulnerability) -> String {
+    format!(
+        "vulnerability:{}:{}:{}",
+        vulnerability.advisory.id, vulnerability.package.name, vulnerability.package.version
+    )
+}
+
+/// Identify a warning across reports
+fn warning_finding(warning: &Warning) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        warning.kind.as_str(),
+        warning
+            .advisory
+            .as_ref()
+            .map(|advisory| advisory.id.as_str())
+            .unwrap_or_default(),
+        warning.package.name,
+        warning.package.version
+    )
+}
+
+/// Collect the findings of a report
+fn findings(report: &Report) -> Set<String> {
+    report
+        .vulnerabilities
+        .list
+        .iter()
+        .map(vulnerability_finding)
+        .chain(report.warnings.values().flatten().map(warning_finding))
+        .collect()
+}
+
+/// Copy a report, only keeping the findings for which `keep` returns `true`
+fn restrict(report: &Report, keep: impl Fn(&String) -> bool) -> Report {
+    let mut report = report.clone();
+
+    let list = report
+        .vulnerabilities
+        .list
+        .drain(..)
+        .filter(|vulnerability| keep(&vulnerability_finding(vulnerability)))
+        .collect();
+    report.vulnerabilities = VulnerabilityInfo::new(list);
+
+    for warnings in report.warnings.values_mut() {
+        warnings.retain(|warning| keep(&warning_finding(warning)));
+    }
+    report.warnings.retain(|_, warnings| !warnings.is_empty());
+
+    report
+}
+
+/// Does the report have any findings?
+fn has_findings(report: &Report) -> bool {
+    !report.vulnerabilities.list.is_empty() || !report.warnings.is_empty()
+}
@@ -0,0 +1,132 @@
+//! Versioned JSON serialization of [`Report`]s
+//!
+//! Reports printed by `cargo audit --json` embed a `schema_version` field
+//! identifying the [JSON schema](SCHEMA) they conform to. Fields may be added
+//! to reports without changing the schema version, but fields are only
+//! removed, renamed or changed in meaning along with a new schema version.
+//!
+//! Use [`from_str`] to parse reports rather than deserializing them
+//! directly: it rejects reports from newer schema versions, which this
+//! version of the crate may misinterpret.
+
+use super::Report;
+use crate::error::{Error, ErrorKind};
+use serde::Deserialize;
+
+/// Version of the report schema produced by this version of the crate
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON schema describing reports with [`SCHEMA_VERSION`]
+pub const SCHEMA: &str = include_str!("../../schema/report-v1.schema.json");
+
+/// Only the schema version of a report, to check it before parsing the rest
+#[derive(Deserialize)]
+struct Versioned {
+    /// Reports which predate versioning have no `schema_version`, but the
+    /// same structure as version 1
+    #[serde(default)]
+    schema_version: u32,
+}
+
+/// Serialize a report as JSON
+pub fn to_string(report: &Report) -> Result<String, Error> {
+    serde_json::to_string(report).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "couldn't serialize audit report".to_owned(),
+            e,
+        )
+    })
+}
+
+/// Serialize a report as indented JSON
+pub fn to_string_pretty(report: &Report) -> Result<String, Error> {
+    serde_json::to_string_pretty(report).map_err(|e| {
+        Error::with_source(
+            ErrorKind::Parse,
+            "couldn't serialize audit report".to_owned(),
+            e,
+        )
+    })
+}
+
+/// Parse a report from JSON, checking that its schema version is supported
+pub fn from_str(json: &str) -> Result<Report, Error> {
+    let versioned: Versioned = serde_json::from_str(json)
+        .map_err(|e| Error::with_source(ErrorKind::Parse, "invalid audit report".to_owned(), e))?;
+
+    if versioned.schema_version > SCHEMA_VERSION {
+        fail!(
+            ErrorKind::Version,
+            "unsupported audit report schema version {} (supported up to {})",
+            versioned.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+
+    let mut report: Report = serde_json::from_str(json)
+        .map_err(|e| Error::with_source(ErrorKind::Parse, "invalid audit report".to_owned(), e))?;
+
+    // Reports are upgraded to the current schema version once parsed
+    report.schema_version = SCHEMA_VERSION;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"{
+        "lockfile": { "dependency-count": 1 },
+        "settings": {
+            "target_arch": null,
+            "target_os": null,
+            "severity": null,
+            "ignore": [],
+            "informational_warnings": []
+        },
+        "vulnerabilities": { "found": false, "count": 0, "list": [] },
+        "warnings": {}
+    }"#;
+
+    /// Add database information to a report, when the feature requires it
+    fn with_database(json: &str) -> String {
+        if cfg!(feature = "git") {
+            json.replacen(
+                '{',
+                r#"{ "database": { "advisory-count": 0, "last-commit": null, "last-updated": null },"#,
+                1,
+            )
+        } else {
+            json.to_owned()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let report = from_str(&with_database(REPORT)).unwrap();
+        assert_eq!(report.schema_version, SCHEMA_VERSION);
+
+        let json = to_string(&report).unwrap();
+        assert!(json.contains(r#""schema_version":1"#));
+
+        let parsed = from_str(&json).unwrap();
+        assert_eq!(to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn newer_schema_version() {
+        let json = with_database(&REPORT.replacen('{', r#"{ "schema_version": 2,"#, 1));
+        let err = from_str(&json).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Version);
+    }
+
+    #[test]
+    fn schema_is_valid_json() {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+    }
+}
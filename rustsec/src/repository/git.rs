@@ -8,6 +8,7 @@ mod gitpath;
 mod modification_time;
 mod repository;
 
+pub(crate) use self::repository::DEFAULT_LOCK_TIMEOUT;
 pub use self::{commit::Commit, commit_hash::CommitHash, repository::Repository};
 use tame_index::external::gix;
 
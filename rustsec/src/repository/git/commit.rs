@@ -17,7 +17,7 @@ const STALE_AFTER: Duration = Duration::from_secs(90 * 86400);
 
 /// Information about a commit to the Git repository
 #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Commit {
     /// ID (i.e. SHA-1 hash) of the latest commit
     pub commit_id: CommitHash,
@@ -42,17 +42,20 @@ pub struct Commit {
 impl Commit {
     /// Get information about HEAD
     pub(crate) fn from_repo_head(repo: &Repository) -> Result<Self, Error> {
-        let commit = repo
-            .repo
-            .head_commit()
-            .map_err(|err| format_err!(ErrorKind::Repo, "unable to locate head commit: {}", err))?;
+        let commit = repo.repo.head_commit().map_err(|err| {
+            Error::with_source(
+                ErrorKind::Repo,
+                "unable to locate head commit".to_owned(),
+                err,
+            )
+        })?;
 
         // Since we are pulling multiple pieces from the commit it's better to do this once
         let cref = commit.decode().map_err(|err| {
-            format_err!(
+            Error::with_source(
                 ErrorKind::Repo,
-                "unable to decode commit information: {}",
-                err
+                "unable to decode commit information".to_owned(),
+                err,
             )
         })?;
 
@@ -127,17 +130,20 @@ impl Commit {
 
         let root_tree = repo
             .find_object(self.commit_id.to_gix())
-            .map_err(|err| format_err!(ErrorKind::Repo, "unable to locate commit: {}", err))?
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "unable to locate commit".to_owned(), err)
+            })?
             .peel_to_tree()
-            .map_err(|err| format_err!(ErrorKind::Repo, "unable to peel to tree: {}", err))?
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "unable to peel to tree".to_owned(), err)
+            })?
             .id;
 
         let index = gix::index::State::from_tree(&root_tree, &repo.objects).map_err(|err| {
-            format_err!(
+            Error::with_source(
                 ErrorKind::Repo,
-                "failed to create index from tree '{}': {}",
-                root_tree,
-                err
+                format!("failed to create index from tree '{}'", root_tree),
+                err,
             )
         })?;
 
@@ -158,11 +164,11 @@ impl Commit {
             &gix::interrupt::IS_INTERRUPTED,
             opts,
         )
-        .map_err(|err| format_err!(ErrorKind::Repo, "failed to checkout: {}", err))?;
+        .map_err(|err| Error::with_source(ErrorKind::Repo, "failed to checkout".to_owned(), err))?;
 
-        index
-            .write(Default::default())
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to write index: {}", err))?;
+        index.write(Default::default()).map_err(|err| {
+            Error::with_source(ErrorKind::Repo, "failed to write index".to_owned(), err)
+        })?;
 
         Ok(())
     }
@@ -33,15 +33,27 @@ impl GitModificationTimes {
         let mut mtimes: HashMap<PathBuf, Time> = HashMap::new();
         let mut ctimes: HashMap<PathBuf, Time> = HashMap::new();
 
+        // The history of a shallow clone is truncated, which would make every
+        // file look like it was created in the oldest available commit
+        if repo.is_shallow() {
+            fail!(
+                ErrorKind::Repo,
+                "modification times require the full repository history; \
+                 call `Repository::unshallow` on this shallow clone first"
+            );
+        }
+
         let repo = &repo.repo;
 
         let walk = repo
             .rev_walk(Some(repo.head_id().map_err(|err| {
-                format_err!(ErrorKind::Repo, "unable to find head id: {}", err)
+                Error::with_source(ErrorKind::Repo, "unable to find head id".to_owned(), err)
             })?))
             .sorting(gix::traverse::commit::Sorting::ByCommitTimeNewestFirst)
             .all()
-            .map_err(|err| format_err!(ErrorKind::Repo, "unable to walk commits: {}", err))?;
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "unable to walk commits".to_owned(), err)
+            })?;
 
         let db = &repo.objects;
 
@@ -49,7 +61,11 @@ impl GitModificationTimes {
         let mut buf2 = Vec::new();
         for info in walk {
             let info = info.map_err(|err| {
-                format_err!(ErrorKind::Repo, "failed to retrieve commit info: {}", err)
+                Error::with_source(
+                    ErrorKind::Repo,
+                    "failed to retrieve commit info".to_owned(),
+                    err,
+                )
             })?;
 
             let parent_commit_id = match info.parent_ids.len() {
@@ -64,11 +80,10 @@ impl GitModificationTimes {
                 let commit = db
                     .try_find(&info.id, &mut buf)
                     .map_err(|err| {
-                        format_err!(
+                        Error::with_source(
                             ErrorKind::Repo,
-                            "failed to find commit '{}': {}",
-                            info.id,
-                            err
+                            format!("failed to find commit '{}'", info.id),
+                            err,
                         )
                     })?
                     .ok_or_else(|| {
@@ -76,11 +91,10 @@ impl GitModificationTimes {
                     })?
                     .decode()
                     .map_err(|err| {
-                        format_err!(
+                        Error::with_source(
                             ErrorKind::Repo,
-                            "unable to decode commit '{}': {}",
-                            info.id,
-                            err
+                            format!("unable to decode commit '{}'", info.id),
+                            err,
                         )
                     })?
                     .into_commit()
@@ -91,11 +105,10 @@ impl GitModificationTimes {
             let current_tree = db
                 .try_find(&main_tree_id, &mut buf)
                 .map_err(|err| {
-                    format_err!(
+                    Error::with_source(
                         ErrorKind::Repo,
-                        "failed to find tree for commit '{}': {}",
-                        info.id,
-                        err
+                        format!("failed to find tree for commit '{}'", info.id),
+                        err,
                     )
                 })?
                 .expect("main tree present")
@@ -120,12 +133,13 @@ impl GitModificationTimes {
                     &mut recorder,
                 )
                 .map_err(|err| {
-                    format_err!(
+                    Error::with_source(
                         ErrorKind::Repo,
-                        "failed to diff commit {} to its parent {:?}: {}",
-                        info.id,
-                        parent_commit_id,
-                        err
+                        format!(
+                            "failed to diff commit {} to its parent {:?}",
+                            info.id, parent_commit_id
+                        ),
+                        err,
                     )
                 })?;
 
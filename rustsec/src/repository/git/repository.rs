@@ -5,10 +5,14 @@ use super::{Commit, DEFAULT_URL};
 use crate::{
     error::{Error, ErrorKind},
     fs,
+    network::NetworkConfig,
+    progress::{GixProgress, Progress},
 };
 use std::{
+    num::NonZeroU32,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 /// Directory under `~/.cargo` where the advisory-db repo will be kept
@@ -20,7 +24,7 @@ const REF_SPEC: &str = "+HEAD:refs/remotes/origin/HEAD";
 /// The direction of the remote
 const DIR: gix::remote::Direction = gix::remote::Direction::Fetch;
 
-const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
 /// Git repository for a Rust advisory DB.
 #[cfg_attr(docsrs, doc(cfg(feature = "git")))]
@@ -69,6 +73,138 @@ impl Repository {
         into_path: P,
         ensure_fresh: bool,
         lock_timeout: Duration,
+    ) -> Result<Self, Error> {
+        Self::fetch_with_shallow(
+            url,
+            into_path,
+            ensure_fresh,
+            lock_timeout,
+            gix::remote::fetch::Shallow::NoChange,
+            None,
+            NetworkConfig::default(),
+        )
+    }
+
+    /// Same as [`Repository::fetch`], but reports the progress of the fetch
+    /// (e.g. objects received and files checked out) to `progress`.
+    pub fn fetch_with_progress<P: Into<PathBuf>>(
+        url: &str,
+        into_path: P,
+        ensure_fresh: bool,
+        lock_timeout: Duration,
+        progress: Arc<dyn Progress>,
+    ) -> Result<Self, Error> {
+        Self::fetch_with_options(
+            url,
+            into_path,
+            ensure_fresh,
+            lock_timeout,
+            NetworkConfig::default(),
+            Some(progress),
+        )
+    }
+
+    /// Same as [`Repository::fetch`], but with the given timeouts and retries
+    /// for the network operations, and optionally reporting progress to
+    /// `progress`.
+    ///
+    /// Failed fetches are retried as configured, after which the error has
+    /// the [`ErrorKind::RetriesExhausted`] kind.
+    pub fn fetch_with_options<P: Into<PathBuf>>(
+        url: &str,
+        into_path: P,
+        ensure_fresh: bool,
+        lock_timeout: Duration,
+        network: NetworkConfig,
+        progress: Option<Arc<dyn Progress>>,
+    ) -> Result<Self, Error> {
+        let result = Self::fetch_with_shallow(
+            url,
+            into_path,
+            ensure_fresh,
+            lock_timeout,
+            gix::remote::fetch::Shallow::NoChange,
+            progress.clone(),
+            network,
+        );
+
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
+        result
+    }
+
+    /// Same as [`Repository::fetch_with_options`], but a new clone only gets
+    /// the latest commit (i.e. it is cloned with `depth=1`), and a shallow
+    /// clone is kept at that depth when it's updated.
+    ///
+    /// This is considerably faster and uses less disk space than a full clone
+    /// on the first run, but operations which need the repository history (such
+    /// as computing advisory modification times) will fail until
+    /// [`Repository::unshallow`] is called.
+    ///
+    /// Existing full clones are updated without truncating their history.
+    ///
+    /// ## Locking
+    ///
+    /// Locking behaves the same way as in [`Repository::fetch`].
+    pub fn fetch_shallow<P: Into<PathBuf>>(
+        url: &str,
+        into_path: P,
+        ensure_fresh: bool,
+        lock_timeout: Duration,
+        network: NetworkConfig,
+        progress: Option<Arc<dyn Progress>>,
+    ) -> Result<Self, Error> {
+        let result = Self::fetch_with_shallow(
+            url,
+            into_path,
+            ensure_fresh,
+            lock_timeout,
+            gix::remote::fetch::Shallow::DepthAtRemote(NonZeroU32::new(1).expect("1 is non-zero")),
+            progress.clone(),
+            network,
+        );
+
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
+        result
+    }
+
+    fn fetch_with_shallow<P: Into<PathBuf>>(
+        url: &str,
+        into_path: P,
+        ensure_fresh: bool,
+        lock_timeout: Duration,
+        shallow: gix::remote::fetch::Shallow,
+        progress: Option<Arc<dyn Progress>>,
+        network: NetworkConfig,
+    ) -> Result<Self, Error> {
+        let path = into_path.into();
+
+        Self::fetch_into(
+            url,
+            path.clone(),
+            ensure_fresh,
+            lock_timeout,
+            shallow,
+            progress,
+            network,
+        )
+        .map_err(|e| e.with_url(url).with_path(path))
+    }
+
+    fn fetch_into(
+        url: &str,
+        path: PathBuf,
+        ensure_fresh: bool,
+        lock_timeout: Duration,
+        shallow: gix::remote::fetch::Shallow,
+        progress: Option<Arc<dyn Progress>>,
+        network: NetworkConfig,
     ) -> Result<Self, Error> {
         if !url.starts_with("https://") {
             fail!(
@@ -78,8 +214,6 @@ impl Repository {
             );
         }
 
-        let path = into_path.into();
-
         if let Some(parent) = path.parent() {
             if !parent.is_dir() {
                 fs::create_dir_all(parent)?;
@@ -152,23 +286,37 @@ impl Repository {
             let res = if let Some(repo) = repo {
                 (repo, None)
             } else {
-                let mut progress = gix::progress::Discard;
+                let mut progress = GixProgress::new(progress.clone());
                 let should_interrupt = &gix::interrupt::IS_INTERRUPTED;
 
-                let (mut prep_checkout, out) = gix::prepare_clone(url, path)
+                let (mut prep_checkout, out) = gix::prepare_clone(url, &path)
                     .map_err(|err| {
-                        format_err!(ErrorKind::Repo, "failed to prepare clone: {}", err)
+                        Error::with_source(
+                            ErrorKind::Repo,
+                            "failed to prepare clone".to_owned(),
+                            err,
+                        )
                     })?
                     .with_remote_name("origin")
-                    .map_err(|err| format_err!(ErrorKind::Repo, "invalid remote name: {}", err))?
+                    .map_err(|err| {
+                        Error::with_source(ErrorKind::Repo, "invalid remote name".to_owned(), err)
+                    })?
                     .configure_remote(|remote| Ok(remote.with_refspecs([REF_SPEC], DIR)?))
+                    .configure_connection(move |connection| configure_timeouts(connection, network))
+                    .with_shallow(shallow.clone())
                     .fetch_then_checkout(&mut progress, should_interrupt)
-                    .map_err(|err| format_err!(ErrorKind::Repo, "failed to fetch repo: {}", err))?;
+                    .map_err(|err| {
+                        Error::with_source(ErrorKind::Repo, "failed to fetch repo".to_owned(), err)
+                    })?;
 
                 let repo = prep_checkout
                     .main_worktree(&mut progress, should_interrupt)
                     .map_err(|err| {
-                        format_err!(ErrorKind::Repo, "failed to checkout fresh clone: {}", err)
+                        Error::with_source(
+                            ErrorKind::Repo,
+                            "failed to checkout fresh clone".to_owned(),
+                            err,
+                        )
                     })?
                     .0;
 
@@ -178,7 +326,8 @@ impl Repository {
             Ok(res)
         };
 
-        let (mut repo, fetch_outcome) = open_or_clone_repo()?;
+        let (mut repo, fetch_outcome) =
+            network.retry("fetching the repository", open_or_clone_repo)?;
 
         if let Some(fetch_outcome) = fetch_outcome {
             tame_index::utils::git::write_fetch_head(
@@ -191,7 +340,17 @@ impl Repository {
             // If we didn't open a fresh repo we need to peform a fetch ourselves, and
             // do the work of updating the HEAD to point at the latest remote HEAD, which
             // gix doesn't currently do.
-            Self::perform_fetch(&mut repo)?;
+            //
+            // Only shallow clones are kept shallow, full clones keep their history
+            let shallow = if repo.is_shallow() {
+                shallow
+            } else {
+                gix::remote::fetch::Shallow::NoChange
+            };
+
+            network.retry("fetching the repository", || {
+                Self::perform_fetch(&mut repo, shallow.clone(), progress.clone(), network)
+            })?;
         }
 
         repo.object_cache_size_if_unset(4 * 1024 * 1024);
@@ -216,12 +375,12 @@ impl Repository {
     pub fn open<P: Into<PathBuf>>(into_path: P) -> Result<Self, Error> {
         let path = into_path.into();
         let repo = gix::open(&path).map_err(|err| {
-            format_err!(
+            Error::with_source(
                 ErrorKind::Repo,
-                "failed to open repository at '{}': {}",
-                path.display(),
-                err
+                format!("failed to open repository at '{}'", path.display()),
+                err,
             )
+            .with_path(&path)
         })?;
 
         // TODO: Figure out how to detect if the worktree has modifications
@@ -234,6 +393,37 @@ impl Repository {
         Commit::from_repo_head(self)
     }
 
+    /// When the repository was last fetched from its remote, if known.
+    ///
+    /// This is the modification time of `FETCH_HEAD`, which is written on
+    /// every fetch (including the initial clone).
+    pub fn last_fetched(&self) -> Option<SystemTime> {
+        std::fs::metadata(self.repo.git_dir().join("FETCH_HEAD"))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Is this a shallow clone, i.e. one with only part of the history available?
+    pub fn is_shallow(&self) -> bool {
+        self.repo.is_shallow()
+    }
+
+    /// Fetch the complete history of a shallow clone.
+    ///
+    /// This is a no-op for repositories which already have their full history.
+    pub fn unshallow(&mut self) -> Result<(), Error> {
+        if !self.is_shallow() {
+            return Ok(());
+        }
+
+        Self::perform_fetch(
+            &mut self.repo,
+            gix::remote::fetch::Shallow::undo(),
+            None,
+            NetworkConfig::default(),
+        )
+    }
+
     /// Path to the local checkout of a git repository
     pub fn path(&self) -> &Path {
         // Safety: Would fail if this is a bare repo, which we aren't
@@ -256,41 +446,80 @@ impl Repository {
         lookup().unwrap_or_default()
     }
 
-    fn perform_fetch(repo: &mut gix::Repository) -> Result<(), Error> {
+    fn perform_fetch(
+        repo: &mut gix::Repository,
+        shallow: gix::remote::fetch::Shallow,
+        progress: Option<Arc<dyn Progress>>,
+        network: NetworkConfig,
+    ) -> Result<(), Error> {
         let mut config = repo.config_snapshot_mut();
         config
             .set_raw_value("committer", None, "name", "rustsec")
             .map_err(|err| {
-                format_err!(ErrorKind::Repo, "failed to set `committer.name`: {}", err)
+                Error::with_source(
+                    ErrorKind::Repo,
+                    "failed to set `committer.name`".to_owned(),
+                    err,
+                )
             })?;
         // Note we _have_ to set the email as well, but luckily gix does not actually
         // validate if it's a proper email or not :)
         config
             .set_raw_value("committer", None, "email", "")
             .map_err(|err| {
-                format_err!(ErrorKind::Repo, "failed to set `committer.email`: {}", err)
+                Error::with_source(
+                    ErrorKind::Repo,
+                    "failed to set `committer.email`".to_owned(),
+                    err,
+                )
             })?;
 
-        let repo = config
-            .commit_auto_rollback()
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to set `committer`: {}", err))?;
+        let repo = config.commit_auto_rollback().map_err(|err| {
+            Error::with_source(ErrorKind::Repo, "failed to set `committer`".to_owned(), err)
+        })?;
 
         let mut remote = repo.find_remote("origin").map_err(|err| {
-            format_err!(ErrorKind::Repo, "failed to find `origin` remote: {}", err)
+            Error::with_source(
+                ErrorKind::Repo,
+                "failed to find `origin` remote".to_owned(),
+                err,
+            )
         })?;
 
         remote
             .replace_refspecs(Some(REF_SPEC), DIR)
             .expect("valid statically known refspec");
 
+        let mut connection = remote.connect(DIR).map_err(|err| {
+            Error::with_source(
+                ErrorKind::Repo,
+                "failed to connect to remote".to_owned(),
+                err,
+            )
+        })?;
+
+        configure_timeouts(&mut connection, network).map_err(|err| {
+            Error::with_source(
+                ErrorKind::Repo,
+                "failed to configure connection".to_owned(),
+                err,
+            )
+        })?;
+
         // Perform the actual fetch
-        let outcome = remote
-            .connect(DIR)
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to connect to remote: {}", err))?
+        let outcome = connection
             .prepare_fetch(&mut gix::progress::Discard, Default::default())
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to prepare fetch: {}", err))?
-            .receive(&mut gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to fetch: {}", err))?;
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "failed to prepare fetch".to_owned(), err)
+            })?
+            .with_shallow(shallow)
+            .receive(
+                &mut GixProgress::new(progress),
+                &gix::interrupt::IS_INTERRUPTED,
+            )
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "failed to fetch".to_owned(), err)
+            })?;
 
         let remote_head_id = tame_index::utils::git::write_fetch_head(&repo, &outcome, &remote)
             .map_err(Error::from_tame)?;
@@ -304,7 +533,9 @@ impl Repository {
         use gix::head::Kind;
         let edit = match repo
             .head()
-            .map_err(|err| format_err!(ErrorKind::Repo, "unable to locate HEAD: {}", err))?
+            .map_err(|err| {
+                Error::with_source(ErrorKind::Repo, "unable to locate HEAD".to_owned(), err)
+            })?
             .kind
         {
             Kind::Symbolic(sref) => {
@@ -344,9 +575,62 @@ impl Repository {
             deref: true,
         });
 
-        repo.edit_reference(edit)
-            .map_err(|err| format_err!(ErrorKind::Repo, "failed to set update reflog: {}", err))?;
+        repo.edit_reference(edit).map_err(|err| {
+            Error::with_source(
+                ErrorKind::Repo,
+                "failed to set update reflog".to_owned(),
+                err,
+            )
+        })?;
 
         Ok(())
     }
 }
+
+/// Apply the timeouts of the network configuration to a connection, on top of
+/// the transport options from the git configuration
+fn configure_timeouts<T>(
+    connection: &mut gix::remote::Connection<'_, '_, T>,
+    network: NetworkConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    use gix::protocol::transport::client::http;
+
+    if network.connect_timeout.is_none() && network.request_timeout.is_none() {
+        return Ok(());
+    }
+
+    let remote = connection.remote();
+    let url = remote
+        .url(DIR)
+        .ok_or("remote has no fetch URL")?
+        .to_bstring();
+    let mut options = match remote.repo().transport_options(
+        gix::bstr::BStr::new(&url),
+        Some(gix::bstr::BStr::new("origin")),
+    )? {
+        Some(options) => options,
+        None => return Ok(()),
+    };
+
+    if let Some(options) = options.downcast_mut::<http::Options>() {
+        if network.connect_timeout.is_some() {
+            options.connect_timeout = network.connect_timeout;
+        }
+
+        // Fetches are made of several requests, each of which gets the timeout
+        if let Some(timeout) = network.request_timeout {
+            let configure_request: Box<http::reqwest::ConfigureRequestFn> =
+                Box::new(move |request| {
+                    *request.timeout_mut() = Some(timeout);
+                    Ok(())
+                });
+
+            options.backend = Some(Arc::new(Mutex::new(http::reqwest::Options {
+                configure_request: Some(configure_request),
+            })));
+        }
+    }
+
+    connection.set_transport_options(options);
+    Ok(())
+}
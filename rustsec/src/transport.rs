@@ -0,0 +1,334 @@
+//! Transport for the HTTP requests made to advisory and registry APIs
+//!
+//! All of the API clients in this crate (osv.dev, GitHub, NVD and crates.io)
+//! send their requests through a [`Transport`]. Besides talking to the network
+//! with [`HttpTransport`], requests can be recorded to and replayed from a
+//! directory of fixtures with [`Vcr`], which makes it possible to run audits
+//! deterministically and offline, e.g. in integration tests or to try out
+//! configuration changes against a recorded session.
+//!
+//! Only [`HttpTransport`] requires the `git` feature: other transports can be
+//! implemented on top of whatever is available to the application, e.g. the
+//! `fetch` API of the browser when running as WebAssembly.
+
+use crate::{
+    error::{Error, ErrorKind},
+    fs,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "git")]
+use std::time::Duration;
+#[cfg(feature = "git")]
+use tame_index::external::reqwest::{self, blocking::Client};
+
+/// Sends HTTP requests on behalf of the API clients
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a request and wait for the complete response
+    fn send(&self, request: &Request) -> Result<Response, Error>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        (**self).send(request)
+    }
+}
+
+/// HTTP request methods used by the API clients
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    /// `GET` request
+    Get,
+
+    /// `POST` request
+    Post,
+}
+
+impl Method {
+    /// Get the method name as used in HTTP
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+/// HTTP request
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// Request method
+    pub method: Method,
+
+    /// URL, including any query string
+    pub url: String,
+
+    /// Headers, which are never recorded since they may contain credentials
+    pub headers: Vec<(String, String)>,
+
+    /// Request body
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    /// Create a `GET` request for the given URL
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.into(),
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    /// Create a `POST` request for the given URL with a JSON body
+    pub fn post_json(url: impl Into<String>, body: Vec<u8>) -> Self {
+        Self {
+            method: Method::Post,
+            url: url.into(),
+            headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+            body: Some(body),
+        }
+    }
+
+    /// Add a header to the request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Key identifying the request among recorded fixtures: a hash of the
+    /// method, URL and body (FNV-1a, which is stable across platforms and
+    /// Rust versions)
+    fn fixture_key(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+        let parts: [&[u8]; 3] = [
+            self.method.as_str().as_bytes(),
+            self.url.as_bytes(),
+            self.body.as_deref().unwrap_or_default(),
+        ];
+
+        for part in parts {
+            for byte in part.iter().chain(b"\n") {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+
+        format!("{:016x}", hash)
+    }
+}
+
+/// HTTP response
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Response {
+    /// Status code
+    pub status: u16,
+
+    /// Response body
+    pub body: String,
+}
+
+impl Response {
+    /// Was the request successful?
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Get the body of a successful response, or an error for any other status
+    pub fn into_body(self) -> Result<String, Error> {
+        if self.is_success() {
+            Ok(self.body)
+        } else {
+            fail!(ErrorKind::Io, "HTTP status {}", self.status)
+        }
+    }
+}
+
+/// Transport which sends requests over the network
+#[cfg(feature = "git")]
+#[cfg_attr(docsrs, doc(cfg(feature = "git")))]
+#[derive(Clone, Debug)]
+pub struct HttpTransport {
+    client: Client,
+}
+
+#[cfg(feature = "git")]
+impl HttpTransport {
+    /// Create a new transport with the given timeout for each request
+    pub fn new(timeout: Duration) -> Result<Self, Error> {
+        let client = Client::builder()
+            .user_agent(concat!("rustsec/", env!("CARGO_PKG_VERSION")))
+            .timeout(timeout)
+            .build()
+            .map_err(|e| {
+                Error::with_source(ErrorKind::Io, "couldn't create HTTP client".to_owned(), e)
+            })?;
+
+        Ok(Self { client })
+    }
+}
+
+#[cfg(feature = "git")]
+impl Transport for HttpTransport {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+        };
+
+        let mut builder = self.client.request(method, &request.url);
+
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let response = builder.send().map_err(|e| {
+            Error::with_source(ErrorKind::Io, format!("couldn't fetch {}", request.url), e)
+                .with_url(&request.url)
+        })?;
+
+        let status = response.status().as_u16();
+        let body = response.text().map_err(|e| {
+            Error::with_source(ErrorKind::Io, format!("couldn't read {}", request.url), e)
+                .with_url(&request.url)
+        })?;
+
+        Ok(Response { status, body })
+    }
+}
+
+/// Transport which replays responses recorded in a directory of fixtures,
+/// optionally recording the responses of another transport first.
+///
+/// Each request is stored as `<hash>.json`, where the hash covers the method,
+/// URL and body of the request. Headers are neither recorded nor compared, so
+/// fixtures never contain credentials.
+#[derive(Debug)]
+pub struct Vcr {
+    /// Directory containing the fixtures
+    dir: PathBuf,
+
+    /// Transport whose responses are recorded, when recording
+    recording: Option<Box<dyn Transport>>,
+}
+
+/// Recorded request and its response
+#[derive(Debug, Deserialize, Serialize)]
+struct Fixture {
+    method: Method,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    response: Response,
+}
+
+impl Vcr {
+    /// Replay previously recorded responses. Requests which haven't been
+    /// recorded fail, without touching the network.
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            recording: None,
+        }
+    }
+
+    /// Send requests through the given transport, recording the responses
+    pub fn record(dir: impl Into<PathBuf>, transport: impl Transport + 'static) -> Self {
+        Self {
+            dir: dir.into(),
+            recording: Some(Box::new(transport)),
+        }
+    }
+
+    /// Directory containing the fixtures
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Is this recording new fixtures (as opposed to only replaying)?
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Path of the fixture for the given request
+    fn fixture_path(&self, request: &Request) -> PathBuf {
+        self.dir.join(format!("{}.json", request.fixture_key()))
+    }
+}
+
+impl Transport for Vcr {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let path = self.fixture_path(request);
+
+        let transport = match &self.recording {
+            Some(transport) => transport,
+            None => {
+                let json = fs::read_to_string(&path).map_err(|e| {
+                    Error::with_source(
+                        ErrorKind::NotFound,
+                        format!(
+                            "no recorded response for {} {}",
+                            request.method.as_str(),
+                            request.url
+                        ),
+                        e,
+                    )
+                    .with_url(&request.url)
+                    .with_path(&path)
+                })?;
+
+                let fixture: Fixture = serde_json::from_str(&json).map_err(|e| {
+                    Error::with_source(
+                        ErrorKind::Parse,
+                        format!("invalid fixture {}", path.display()),
+                        e,
+                    )
+                    .with_url(&request.url)
+                    .with_path(&path)
+                })?;
+
+                if fixture.method != request.method || fixture.url != request.url {
+                    fail!(
+                        ErrorKind::Parse,
+                        "fixture {} was recorded for {} {}",
+                        path.display(),
+                        fixture.method.as_str(),
+                        fixture.url
+                    );
+                }
+
+                return Ok(fixture.response);
+            }
+        };
+
+        let response = transport.send(request)?;
+
+        let fixture = Fixture {
+            method: request.method,
+            url: request.url.clone(),
+            body: request
+                .body
+                .as_ref()
+                .map(|body| String::from_utf8_lossy(body).into_owned()),
+            response,
+        };
+
+        let json = serde_json::to_string_pretty(&fixture).expect("couldn't serialize fixture");
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, json)?;
+
+        Ok(fixture.response)
+    }
+}
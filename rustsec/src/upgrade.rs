@@ -0,0 +1,107 @@
+//! Suggested upgrades for resolving vulnerabilities
+
+use crate::{advisory::Versions, osv, package};
+use semver::{Comparator, Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Lowest version of a vulnerable package which isn't affected by the
+/// vulnerability anymore, and how it relates to the current version
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct PatchedVersion {
+    /// Lowest version newer than the current one which isn't affected
+    pub version: Version,
+
+    /// Is the version allowed by the requirements on the package, i.e. can it
+    /// be upgraded to with `cargo update`, without changing the `Cargo.toml`
+    /// of the packages depending on it?
+    pub compatible: bool,
+}
+
+impl PatchedVersion {
+    /// Find the lowest version newer than `current` which isn't affected,
+    /// if any, given the versions affected by an advisory.
+    ///
+    /// The package is assumed to be required with Cargo's default (caret)
+    /// requirement on the current version, e.g. `1.2.3` for `^1.2.3`.
+    pub fn find(versions: &Versions, current: &Version) -> Option<Self> {
+        let requirement = VersionReq {
+            comparators: vec![Comparator {
+                op: Op::Caret,
+                major: current.major,
+                minor: Some(current.minor),
+                patch: Some(current.patch),
+                pre: current.pre.clone(),
+            }],
+        };
+
+        Self::find_for_requirements(versions, current, &[requirement])
+    }
+
+    /// Find the lowest version newer than `current` which isn't affected,
+    /// if any, given the versions affected by an advisory and the version
+    /// requirements the packages depending on it have on it
+    pub fn find_for_requirements(
+        versions: &Versions,
+        current: &Version,
+        requirements: &[VersionReq],
+    ) -> Option<Self> {
+        let version = osv::ranges_for_advisory(versions)
+            .into_iter()
+            .find(|range| range.affects(current))?
+            .fixed?;
+
+        Some(Self {
+            compatible: !requirements.is_empty()
+                && requirements.iter().all(|req| req.matches(&version)),
+            version,
+        })
+    }
+}
+
+impl fmt::Display for PatchedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.compatible {
+            write!(f, "fixed in {} (compatible)", self.version)
+        } else {
+            write!(f, "requires incompatible upgrade to {}", self.version)
+        }
+    }
+}
+
+/// Upgrade of a dependency of a root package (e.g. a workspace member) which
+/// allows a patched version of a vulnerable package to be used
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct UpgradeSuggestion {
+    /// Root package depending on the package to upgrade
+    pub dependent: package::Name,
+
+    /// Package to upgrade: either the vulnerable package itself or the
+    /// dependency of the root package which pulls it in
+    pub package: package::Name,
+
+    /// Version of the package which is currently used
+    pub current: Version,
+
+    /// Lowest version of the package allowing a patched version of the
+    /// vulnerable package, or `None` if no release does
+    pub upgrade_to: Option<Version>,
+}
+
+impl fmt::Display for UpgradeSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.upgrade_to {
+            Some(version) => write!(
+                f,
+                "upgrade {} {} -> {} (in {})",
+                self.package, self.current, version, self.dependent
+            ),
+            None => write!(
+                f,
+                "no release of {} allows a patched version (in {})",
+                self.package, self.dependent
+            ),
+        }
+    }
+}
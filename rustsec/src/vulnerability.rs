@@ -3,12 +3,18 @@
 
 use crate::{
     advisory::{self, affected::FunctionPath, Advisory},
+    dependency_path::DependencyPath,
     package::Package,
+    release::ReleaseInfo,
+    report::SeverityOverride,
+    upgrade::{PatchedVersion, UpgradeSuggestion},
 };
+use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 
 /// A vulnerable package and the associated advisory
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
 pub struct Vulnerability {
     /// Security advisory for which the package is vulnerable
     pub advisory: advisory::Metadata,
@@ -21,6 +27,27 @@ pub struct Vulnerability {
 
     /// Vulnerable package
     pub package: Package,
+
+    /// Lowest version of the package which isn't vulnerable (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    patched: Option<PatchedVersion>,
+
+    /// Release information about the vulnerable version (if looked up)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseInfo>,
+
+    /// Shortest dependency paths from the root packages (e.g. workspace
+    /// members) to the vulnerable package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<DependencyPath>,
+
+    /// Upgrades which resolve the vulnerability (if looked up)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<UpgradeSuggestion>,
+
+    /// Severity override configured for the advisory (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity_override: Option<SeverityOverride>,
 }
 
 impl Vulnerability {
@@ -31,9 +58,44 @@ impl Vulnerability {
             versions: advisory.versions.clone(),
             affected: advisory.affected.clone(),
             package: package.clone(),
+            patched: PatchedVersion::find(&advisory.versions, &package.version),
+            release: None,
+            paths: vec![],
+            suggestions: vec![],
+            severity_override: None,
+        }
+    }
+
+    /// Get the effective severity of this vulnerability: the overridden
+    /// severity if there is a [`SeverityOverride`], otherwise the severity of
+    /// the advisory's primary CVSS vector (if any)
+    pub fn severity(&self) -> Option<advisory::Severity> {
+        match &self.severity_override {
+            Some(severity_override) => Some(severity_override.severity),
+            None => self
+                .advisory
+                .primary_cvss()
+                .map(|cvss| cvss.score().severity()),
         }
     }
 
+    /// Get the lowest version of the package which isn't vulnerable (if any)
+    pub fn patched(&self) -> Option<&PatchedVersion> {
+        self.patched.as_ref()
+    }
+
+    /// Set the version requirements the packages depending on the vulnerable
+    /// package have on it, which determine whether the patched version is
+    /// compatible. Without them, Cargo's default (caret) requirement on the
+    /// current version is assumed.
+    pub fn set_requirements(&mut self, requirements: &[VersionReq]) {
+        self.patched = PatchedVersion::find_for_requirements(
+            &self.versions,
+            &self.package.version,
+            requirements,
+        );
+    }
+
     /// Get the set of functions affected by this vulnerability (if available)
     pub fn affected_functions(&self) -> Option<Vec<FunctionPath>> {
         self.affected.as_ref().and_then(|affected| {
@@ -53,4 +115,13 @@ impl Vulnerability {
             }
         })
     }
+
+    /// Get the crate features gating the vulnerable code (empty if the
+    /// vulnerability doesn't depend on any features)
+    pub fn affected_features(&self) -> &[String] {
+        self.affected
+            .as_ref()
+            .map(|affected| affected.features.as_slice())
+            .unwrap_or_default()
+    }
 }
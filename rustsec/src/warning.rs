@@ -1,12 +1,16 @@
 //! Warnings sourced from the Advisory DB
 
 use crate::error::{Error, ErrorKind};
-use crate::{advisory, package::Package};
+use crate::{
+    advisory, deprecation::Deprecation, package::Package, release::ReleaseInfo,
+    vulnerability::Vulnerability,
+};
 use serde::{Deserialize, Serialize};
 use std::{fmt, str::FromStr};
 
 /// Warnings sourced from the Advisory DB
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct Warning {
     /// Kind of warning
     pub kind: WarningKind,
@@ -22,6 +26,14 @@ pub struct Warning {
 
     /// Versions impacted by this warning
     pub versions: Option<advisory::Versions>,
+
+    /// Release information about the package version (if looked up)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseInfo>,
+
+    /// Crate which supersedes the package (for `deprecated` warnings)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
 }
 
 impl Warning {
@@ -39,6 +51,50 @@ impl Warning {
             advisory,
             affected,
             versions,
+            release: None,
+            deprecation: None,
+        }
+    }
+
+    /// Create a `deprecated` warning for a package which has been superseded
+    /// by another crate
+    pub fn deprecated(package: &Package, deprecation: Deprecation) -> Self {
+        Self {
+            deprecation: Some(deprecation),
+            ..Self::new(WarningKind::Deprecated, package, None, None, None)
+        }
+    }
+
+    /// Create an `uncompiled` warning for a vulnerability whose affected
+    /// functions aren't present in the audited artifact
+    pub fn uncompiled(vulnerability: Vulnerability) -> Self {
+        Self::from_vulnerability(WarningKind::Uncompiled, vulnerability)
+    }
+
+    /// Create a `not-applicable` warning for a vulnerability which doesn't
+    /// apply to the project as built, e.g. because the crate features gating
+    /// the vulnerable code aren't enabled
+    pub fn not_applicable(vulnerability: Vulnerability) -> Self {
+        Self::from_vulnerability(WarningKind::NotApplicable, vulnerability)
+    }
+
+    /// Create a `native` warning for an advisory about a native library
+    /// wrapped by the package
+    pub fn native(vulnerability: Vulnerability) -> Self {
+        Self::from_vulnerability(WarningKind::Native, vulnerability)
+    }
+
+    /// Create a warning of the given kind for a downgraded vulnerability
+    fn from_vulnerability(kind: WarningKind, vulnerability: Vulnerability) -> Self {
+        Self {
+            release: vulnerability.release,
+            ..Self::new(
+                kind,
+                &vulnerability.package,
+                Some(vulnerability.advisory),
+                vulnerability.affected,
+                Some(vulnerability.versions),
+            )
         }
     }
 
@@ -61,6 +117,29 @@ impl Warning {
     pub fn is_yanked(&self) -> bool {
         self.kind == WarningKind::Yanked
     }
+
+    /// Is this a warning about a crate which has been superseded?
+    pub fn is_deprecated(&self) -> bool {
+        self.kind == WarningKind::Deprecated
+    }
+
+    /// Is this a warning about a vulnerability whose affected functions
+    /// weren't compiled into the audited artifact?
+    pub fn is_uncompiled(&self) -> bool {
+        self.kind == WarningKind::Uncompiled
+    }
+
+    /// Is this a warning about a vulnerability which doesn't apply to the
+    /// project as built?
+    pub fn is_not_applicable(&self) -> bool {
+        self.kind == WarningKind::NotApplicable
+    }
+
+    /// Is this a warning about a vulnerability in a native library wrapped
+    /// by the package?
+    pub fn is_native(&self) -> bool {
+        self.kind == WarningKind::Native
+    }
 }
 
 /// Kinds of warnings
@@ -82,6 +161,25 @@ pub enum WarningKind {
     /// Yanked packages
     #[serde(rename = "yanked")]
     Yanked,
+
+    /// Packages which have been superseded by another crate
+    #[serde(rename = "deprecated")]
+    Deprecated,
+
+    /// Vulnerabilities whose affected functions weren't compiled into the
+    /// audited artifact
+    #[serde(rename = "uncompiled")]
+    Uncompiled,
+
+    /// Vulnerabilities which don't apply to the project as built, e.g.
+    /// because the crate features gating the vulnerable code aren't enabled
+    #[serde(rename = "not-applicable")]
+    NotApplicable,
+
+    /// Vulnerabilities in native libraries wrapped by `-sys` crates, which
+    /// may or may not affect the version of the library the crate builds
+    #[serde(rename = "native")]
+    Native,
 }
 
 impl WarningKind {
@@ -92,6 +190,10 @@ impl WarningKind {
             Self::Unmaintained => "unmaintained",
             Self::Unsound => "unsound",
             Self::Yanked => "yanked",
+            Self::Deprecated => "deprecated",
+            Self::Uncompiled => "uncompiled",
+            Self::NotApplicable => "not-applicable",
+            Self::Native => "native",
         }
     }
 }
@@ -105,6 +207,10 @@ impl FromStr for WarningKind {
             "unmaintained" => WarningKind::Unmaintained,
             "unsound" => WarningKind::Unsound,
             "yanked" => WarningKind::Yanked,
+            "deprecated" => WarningKind::Deprecated,
+            "uncompiled" => WarningKind::Uncompiled,
+            "not-applicable" => WarningKind::NotApplicable,
+            "native" => WarningKind::Native,
             other => fail!(ErrorKind::Parse, "invalid warning type: {}", other),
         })
     }
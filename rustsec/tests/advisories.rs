@@ -2,7 +2,7 @@
 
 #![warn(rust_2018_idioms, unused_qualifications)]
 
-use rustsec::advisory::{Category, License};
+use rustsec::advisory::{Category, Informational, License};
 use std::path::Path;
 
 /// Load example advisory from the filesystem
@@ -66,6 +66,32 @@ fn parse_affected() {
     assert!(!req.matches(&"1.2.3".parse().unwrap()));
 }
 
+/// Crate features gating the vulnerable code
+#[test]
+fn parse_affected_features() {
+    let advisory: rustsec::Advisory = r#"```toml
+[advisory]
+id = "RUSTSEC-2001-2101"
+package = "base"
+date = "2001-02-03"
+
+[versions]
+patched = [">= 1.2.3"]
+
+[affected]
+features = ["belong", "all-your-base"]
+```
+
+# All your base are belong to us
+"#
+    .parse()
+    .unwrap();
+
+    let affected = advisory.affected.unwrap();
+    assert_eq!(affected.features, ["belong", "all-your-base"]);
+    assert!(load_advisory("v3").affected.unwrap().features.is_empty());
+}
+
 /// Parsing of other aliased advisory IDs
 #[test]
 fn parse_aliases() {
@@ -104,3 +130,205 @@ fn parse_patched_version_reqs() {
     assert!(req.matches(&"1.2.3".parse().unwrap()));
     assert!(req.matches(&"1.2.4".parse().unwrap()));
 }
+
+/// Lowest versions which aren't affected anymore
+#[test]
+fn find_patched_version() {
+    use rustsec::{PatchedVersion, Version};
+
+    let advisory = load_advisory("v3");
+    let find = |version: &str| {
+        PatchedVersion::find(&advisory.versions, &version.parse::<Version>().unwrap())
+    };
+
+    let patched = find("1.0.0").unwrap();
+    assert_eq!(patched.version, Version::new(1, 2, 3));
+    assert!(patched.compatible);
+    assert_eq!(patched.to_string(), "fixed in 1.2.3 (compatible)");
+
+    let patched = find("0.5.0").unwrap();
+    assert_eq!(patched.version, Version::new(1, 2, 3));
+    assert!(!patched.compatible);
+    assert_eq!(patched.to_string(), "requires incompatible upgrade to 1.2.3");
+
+    // Versions which aren't affected have nothing to upgrade to
+    assert_eq!(find("1.3.0"), None);
+}
+
+/// Whether the patched version is compatible depends on the requirements
+/// on the vulnerable package, rather than on its current version
+#[test]
+fn find_patched_version_for_requirements() {
+    use rustsec::{PatchedVersion, Version, VersionReq};
+
+    let advisory = load_advisory("v3");
+    let current = Version::new(1, 0, 0);
+    let find = |requirements: &[&str]| {
+        let requirements: Vec<VersionReq> = requirements
+            .iter()
+            .map(|req| req.parse().unwrap())
+            .collect();
+        PatchedVersion::find_for_requirements(&advisory.versions, &current, &requirements)
+            .unwrap()
+            .compatible
+    };
+
+    assert!(find(&["1", ">= 0.9"]));
+    assert!(!find(&["1", "~1.0"]));
+    assert!(!find(&["=1.0.0"]));
+    assert!(!find(&[]));
+}
+
+/// Malformed advisories are rejected without panicking, even when the error
+/// message quotes multi-byte characters
+#[test]
+fn parse_malformed_start() {
+    let data = "ééééééééééééééééééééééééé";
+    assert!(data.parse::<rustsec::Advisory>().is_err());
+}
+
+/// The I/O error from loading a missing advisory is kept as the source
+#[test]
+fn load_missing_advisory() {
+    use std::error::Error as _;
+
+    let path = Path::new("./tests/support/missing_advisory.md");
+    let err = rustsec::Advisory::load_file(path).unwrap_err();
+    assert_eq!(err.kind(), rustsec::ErrorKind::Io);
+    assert_eq!(err.path(), Some(path));
+
+    let source = err.source().unwrap();
+    let io_error = source.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+}
+
+/// Malformed references to RustSec advisories in OSV data are skipped
+#[cfg(feature = "osv-export")]
+#[test]
+fn osv_malformed_rustsec_refs() {
+    let osv: rustsec::osv::OsvAdvisory = serde_json::from_str(
+        r#"{
+            "id": "GHSA-2001-2101-abcd",
+            "modified": "2001-02-03T00:00:00Z",
+            "published": "2001-02-03T00:00:00Z",
+            "references": [
+                {"type": "ADVISORY", "url": "https://rustsec.org/advisories/"},
+                {"type": "ADVISORY", "url": "https://rustsec.org/advisories/CVE-2001-2101.html"},
+                {"type": "ADVISORY", "url": "https://rustsec.org/advisories/RUSTSEC-2001-2101.html"}
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        osv.rustsec_refs_imported(),
+        ["RUSTSEC-2001-2101".parse().unwrap()]
+    );
+}
+
+/// Advisory templates parse as advisories
+#[test]
+fn parse_template() {
+    let package = "base".parse().unwrap();
+
+    let advisory: rustsec::Advisory = rustsec::Advisory::template(&package, None).parse().unwrap();
+    assert!(advisory.id().is_placeholder());
+    assert_eq!(advisory.metadata.package, package);
+    assert_eq!(advisory.date(), &rustsec::advisory::Date::today());
+    assert!(advisory.metadata.informational.is_none());
+    assert!(advisory.versions.patched().is_empty());
+
+    let advisory: rustsec::Advisory =
+        rustsec::Advisory::template(&package, Some(&Informational::Unmaintained))
+            .parse()
+            .unwrap();
+    assert_eq!(
+        advisory.metadata.informational,
+        Some(Informational::Unmaintained)
+    );
+}
+
+/// Advisories serialize into linter-clean Markdown which parses back into
+/// the same advisory
+#[test]
+fn round_trip_markdown() {
+    for advisory in &[
+        load_advisory("v3"),
+        load_advisory("v4"),
+        load_advisory("v4_from_ghsa"),
+    ] {
+        let markdown = advisory.to_markdown().unwrap();
+        let parsed: rustsec::Advisory = markdown.parse().unwrap();
+        assert_eq!(&parsed, advisory);
+        assert_eq!(parsed.to_markdown().unwrap(), markdown);
+
+        let lint = rustsec::advisory::Linter::lint_string(&markdown).unwrap();
+        assert!(lint.errors().is_empty(), "{:?}", lint.errors());
+    }
+}
+
+/// Advisories with several CVSS vectors
+#[test]
+fn parse_additional_cvss() {
+    const LOW: &str = "CVSS:3.1/AV:N/AC:H/PR:N/UI:R/S:U/C:L/I:N/A:N";
+    const HIGH: &str = "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H";
+
+    let advisory = |cvss: &str| -> rustsec::Advisory {
+        format!(
+            r#"```toml
+[advisory]
+id = "RUSTSEC-2001-2101"
+package = "base"
+date = "2001-02-03"
+{cvss}
+additional_cvss = [
+    {{ vector = "{LOW}", source = "NVD" }},
+    {{ vector = "{HIGH}", applies_to = "windows" }},
+]
+
+[versions]
+patched = [">= 1.2.3"]
+```
+
+# Title
+
+Description
+"#
+        )
+        .parse()
+        .unwrap()
+    };
+
+    // The `cvss` vector is primary
+    let with_primary = advisory(&format!("cvss = \"{LOW}\""));
+    let metadata = &with_primary.metadata;
+    assert_eq!(metadata.primary_cvss().unwrap().to_string(), LOW);
+    assert_eq!(metadata.cvss_vectors().count(), 3);
+    assert_eq!(metadata.additional_cvss[0].source.as_deref(), Some("NVD"));
+    assert_eq!(
+        metadata.additional_cvss[1].applies_to.as_deref(),
+        Some("windows")
+    );
+    assert_eq!(
+        with_primary.severity(),
+        Some(rustsec::advisory::Severity::Low)
+    );
+
+    // Otherwise the highest-scoring one is
+    let without_primary = advisory("");
+    assert_eq!(
+        without_primary.metadata.primary_cvss().unwrap().to_string(),
+        HIGH
+    );
+    assert_eq!(
+        without_primary.severity(),
+        Some(rustsec::advisory::Severity::Critical)
+    );
+
+    let markdown = with_primary.to_markdown().unwrap();
+    assert_eq!(markdown.parse::<rustsec::Advisory>().unwrap(), with_primary);
+    assert!(rustsec::advisory::Linter::lint_string(&markdown)
+        .unwrap()
+        .errors()
+        .is_empty());
+}
@@ -0,0 +1,54 @@
+//! Tests for the C API
+#![cfg(feature = "capi")]
+#![warn(rust_2018_idioms, unused_qualifications)]
+
+use rustsec::capi::*;
+use std::ffi::{CStr, CString};
+
+/// Take ownership of a string returned by the C API
+fn take_string(string: *mut std::ffi::c_char) -> String {
+    assert!(!string.is_null());
+    let owned = unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .unwrap()
+        .to_owned();
+    unsafe { rustsec_string_free(string) };
+    owned
+}
+
+#[test]
+fn audit_lockfile() {
+    let db_dir = tempfile::tempdir().unwrap();
+    let base_dir = db_dir.path().join("crates").join("base");
+    std::fs::create_dir_all(&base_dir).unwrap();
+    std::fs::copy(
+        "./tests/support/example_advisory_v3.md",
+        base_dir.join("RUSTSEC-2001-2101.md"),
+    )
+    .unwrap();
+
+    let db_path = CString::new(db_dir.path().to_str().unwrap()).unwrap();
+    let db = unsafe { rustsec_db_open(db_path.as_ptr()) };
+    assert!(!db.is_null());
+
+    let lockfile_path = CString::new("./tests/support/cratesio_cargo.lock").unwrap();
+    let json = take_string(unsafe { rustsec_audit_lockfile(db, lockfile_path.as_ptr()) });
+    let report: rustsec::Report = serde_json::from_str(&json).unwrap();
+    assert!(!report.vulnerabilities.found);
+
+    // Failures are described by the last error
+    let missing = CString::new("./tests/support/missing.lock").unwrap();
+    assert!(unsafe { rustsec_audit_lockfile(db, missing.as_ptr()) }.is_null());
+    assert!(!take_string(rustsec_last_error()).is_empty());
+
+    unsafe { rustsec_db_free(db) };
+}
+
+#[test]
+fn null_arguments_are_rejected() {
+    assert!(unsafe { rustsec_db_open(std::ptr::null()) }.is_null());
+    assert!(take_string(rustsec_last_error()).contains("null"));
+
+    let lockfile_path = CString::new("./tests/support/cratesio_cargo.lock").unwrap();
+    assert!(unsafe { rustsec_audit_lockfile(std::ptr::null(), lockfile_path.as_ptr()) }.is_null());
+}
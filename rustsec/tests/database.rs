@@ -2,7 +2,11 @@
 
 use cargo_lock::Lockfile;
 use once_cell::sync::Lazy;
-use rustsec::{database::Query, repository::git::Repository, Database};
+use rustsec::{
+    database::{LazyDatabase, Query},
+    repository::git::Repository,
+    Collection, Database,
+};
 use std::{path::Path, sync::Mutex};
 
 static DEFAULT_DATABASE: Lazy<Mutex<Database>> = Lazy::new(|| {
@@ -32,3 +36,378 @@ fn query_vulnerabilitie() {
     let vuln = db.vulnerabilities(&lockfile);
     assert_eq!(vuln_all, vuln);
 }
+
+#[test]
+fn lazy_database_only_parses_queried_packages() {
+    let db_dir = tempfile::tempdir().unwrap();
+
+    let base_dir = db_dir.path().join("crates").join("base");
+    std::fs::create_dir_all(&base_dir).unwrap();
+    std::fs::copy(
+        "./tests/support/example_advisory_v3.md",
+        base_dir.join("RUSTSEC-2001-2101.md"),
+    )
+    .unwrap();
+
+    let broken_dir = db_dir.path().join("crates").join("broken");
+    std::fs::create_dir_all(&broken_dir).unwrap();
+    std::fs::write(broken_dir.join("RUSTSEC-2002-0001.md"), "not an advisory").unwrap();
+
+    let mut db = LazyDatabase::open(db_dir.path()).unwrap();
+    assert_eq!(db.loaded_packages(), 0);
+
+    let query = Query::crate_scope().package_name("base".parse().unwrap());
+    let advisories = db.query(&query).unwrap();
+    assert_eq!(advisories.len(), 1);
+    assert_eq!(db.loaded_packages(), 1);
+
+    // Querying everything parses the broken advisory too
+    assert!(db.query(&Query::new()).is_err());
+}
+
+#[test]
+fn report_progress_of_parsing_advisories() {
+    use rustsec::progress::{Counter, Progress};
+
+    #[derive(Default)]
+    struct Recorder {
+        stages: Mutex<Vec<(String, Option<usize>, Counter)>>,
+        finished: Mutex<bool>,
+    }
+
+    impl Progress for Recorder {
+        fn stage(&self, name: &str, total: Option<usize>, done: Counter) {
+            self.stages
+                .lock()
+                .unwrap()
+                .push((name.to_owned(), total, done));
+        }
+
+        fn finish(&self) {
+            *self.finished.lock().unwrap() = true;
+        }
+    }
+
+    let db_dir = tempfile::tempdir().unwrap();
+    let base_dir = db_dir.path().join("crates").join("base");
+    std::fs::create_dir_all(&base_dir).unwrap();
+    std::fs::copy(
+        "./tests/support/example_advisory_v3.md",
+        base_dir.join("RUSTSEC-2001-2101.md"),
+    )
+    .unwrap();
+
+    let recorder = Recorder::default();
+    let db = Database::open_with_progress(db_dir.path(), &recorder).unwrap();
+    assert_eq!(db.iter().count(), 1);
+
+    let stages = recorder.stages.lock().unwrap();
+    assert_eq!(stages.len(), 1);
+    let (name, total, done) = &stages[0];
+    assert_eq!(name, "parsing advisories");
+    assert_eq!(*total, Some(1));
+    assert_eq!(done.load(std::sync::atomic::Ordering::Relaxed), 1);
+    assert!(*recorder.finished.lock().unwrap());
+}
+
+#[test]
+fn build_database_in_memory() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+
+    let db = Database::builder()
+        .advisory(advisory.clone())
+        .build()
+        .unwrap();
+
+    // Advisories without a collection are assumed to be about crates
+    let stored = db.get(advisory.id()).unwrap();
+    assert_eq!(stored.metadata.collection, Some(Collection::Crates));
+
+    let query = Query::crate_scope().package_name("base".parse().unwrap());
+    assert_eq!(db.query(&query), vec![stored]);
+    assert_eq!(db.query_iter(&query).count(), 1);
+
+    // Duplicate advisory IDs are rejected
+    let err = Database::builder()
+        .advisories([advisory.clone(), advisory.clone()])
+        .build()
+        .unwrap_err();
+    assert_eq!(err.advisory_id(), Some(advisory.id()));
+}
+
+#[test]
+fn find_advisories_by_alias() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+
+    let found = db.find_by_alias("CVE-2001-2101");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id().as_str(), "RUSTSEC-2001-2101");
+
+    // Lookups are case-insensitive, and RustSec IDs resolve to themselves
+    assert_eq!(db.find_by_alias("cve-2001-2101").len(), 1);
+    assert_eq!(db.find_by_alias("RUSTSEC-2001-2101").len(), 1);
+    assert!(db.find_by_alias("CVE-2001-0001").is_empty());
+}
+
+#[test]
+fn load_database_from_snapshot() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+
+    let loaded = Database::from_snapshot(&db.snapshot().unwrap()).unwrap();
+    assert_eq!(
+        loaded.iter().collect::<Vec<_>>(),
+        db.iter().collect::<Vec<_>>()
+    );
+
+    let query = Query::crate_scope().package_name("base".parse().unwrap());
+    assert_eq!(loaded.query(&query).len(), 1);
+
+    // Snapshots in other formats are rejected
+    let snapshot = r#"{"format":0,"rustsec_version":"0.0.0","advisories":[]}"#;
+    assert!(Database::from_snapshot(snapshot.as_bytes()).is_err());
+}
+
+#[test]
+fn superseded_advisories_are_omitted() {
+    let original = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+
+    let mut replacement = original.clone();
+    replacement.metadata.id = "RUSTSEC-2001-2102".parse().unwrap();
+    replacement.metadata.aliases = vec![];
+    replacement.metadata.supersedes = vec![original.id().clone()];
+
+    let db = Database::builder()
+        .advisories([original.clone(), replacement.clone()])
+        .build()
+        .unwrap();
+
+    assert!(db.is_superseded(&original));
+    assert!(!db.is_superseded(&replacement));
+    assert_eq!(db.superseded_by(original.id()), vec![&replacement]);
+
+    let query = Query::crate_scope().package_name("base".parse().unwrap());
+    assert_eq!(db.query(&query), vec![&replacement]);
+    assert_eq!(db.query(&query.clone().include_superseded()).len(), 2);
+    assert_eq!(db.query(&query.superseded(true)), vec![&original]);
+
+    // Withdrawn advisories don't supersede anything
+    replacement.metadata.withdrawn = Some("2001-02-03".parse().unwrap());
+    let db = Database::builder()
+        .advisories([original.clone(), replacement])
+        .build()
+        .unwrap();
+
+    assert!(!db.is_superseded(&original));
+    assert!(db.superseded_by(original.id()).is_empty());
+}
+
+#[test]
+fn advisories_declaring_their_successors_are_omitted() {
+    let mut original =
+        rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+
+    let mut replacement = original.clone();
+    replacement.metadata.id = "RUSTSEC-2001-2102".parse().unwrap();
+    replacement.metadata.aliases = vec![];
+    original.metadata.superseded_by = vec![replacement.id().clone()];
+
+    let db = Database::builder()
+        .advisories([original.clone(), replacement.clone()])
+        .build()
+        .unwrap();
+
+    assert!(db.is_superseded(&original));
+    assert_eq!(db.superseded_by(original.id()), vec![&replacement]);
+
+    let query = Query::crate_scope().package_name("base".parse().unwrap());
+    assert_eq!(db.query(&query), vec![&replacement]);
+
+    // Successors which aren't in the database don't count
+    let db = Database::builder()
+        .advisories([original.clone()])
+        .build()
+        .unwrap();
+
+    assert!(!db.is_superseded(&original));
+    assert_eq!(db.query(&query), vec![&original]);
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn export_database_to_osv() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+
+    let exported = rustsec::osv::export_database(&db);
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].id().as_str(), "RUSTSEC-2001-2101");
+    assert_eq!(exported[0].published(), "2001-02-03T12:00:00Z");
+
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(rustsec::osv::write_database(&db, dir.path()).unwrap(), 1);
+    assert!(dir.path().join("RUSTSEC-2001-2101.json").exists());
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn import_advisory_from_osv() {
+    let advisory =
+        rustsec::osv::import_file(Path::new("./tests/support/example_osv_from_ghsa.json")).unwrap();
+
+    assert_eq!(advisory.id().as_str(), "GHSA-2001-2101-abcd");
+    assert_eq!(advisory.metadata.package.as_str(), "base");
+    assert_eq!(advisory.title(), "All your base are belong to us");
+    assert_eq!(advisory.date().as_str(), "2001-02-03");
+    assert_eq!(advisory.metadata.aliases[0].as_str(), "CVE-2001-2101");
+    // OSV severities become additional vectors, the worst of which is used
+    // as the primary one
+    assert!(advisory.metadata.cvss.is_none());
+    assert_eq!(advisory.metadata.additional_cvss.len(), 1);
+    assert!(advisory.metadata.primary_cvss().is_some());
+    assert_eq!(advisory.metadata.references.len(), 2);
+
+    // Introduced/fixed events become version requirements
+    let versions = &advisory.versions;
+    assert_eq!(versions.unaffected()[0].to_string(), "<0.2.0");
+    assert_eq!(versions.patched()[0].to_string(), ">=1.2.3, <2.0.0");
+    assert_eq!(versions.patched()[1].to_string(), ">2.1.0");
+
+    for (version, vulnerable) in [
+        ("0.1.0", false),
+        ("0.2.0", true),
+        ("1.2.3", false),
+        ("2.1.0", true),
+        ("2.1.1", false),
+    ] {
+        assert_eq!(
+            versions.is_vulnerable(&version.parse().unwrap()),
+            vulnerable,
+            "{}",
+            version
+        );
+    }
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn osv_round_trip() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let db = Database::builder()
+        .advisory(advisory.clone())
+        .build()
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    rustsec::osv::write_database(&db, dir.path()).unwrap();
+
+    let imported = rustsec::osv::import_dir(dir.path()).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].id(), advisory.id());
+    assert_eq!(
+        imported[0].metadata.categories,
+        advisory.metadata.categories
+    );
+
+    for version in ["0.1.2", "1.0.0", "1.2.3"] {
+        let version = version.parse().unwrap();
+        assert_eq!(
+            imported[0].versions.is_vulnerable(&version),
+            advisory.versions.is_vulnerable(&version)
+        );
+    }
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn merge_advisories_from_osv() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+
+    // Known by its CVE alias, so it is skipped
+    let duplicate =
+        rustsec::osv::import_file(Path::new("./tests/support/example_osv_from_ghsa.json")).unwrap();
+
+    let mut unknown = duplicate.clone();
+    unknown.metadata.id = "GHSA-2001-2102-abcd".parse().unwrap();
+    unknown.metadata.aliases = vec!["CVE-2001-2102".parse().unwrap()];
+
+    let merged = db.merge(vec![duplicate, unknown.clone(), unknown]).unwrap();
+    assert_eq!(merged.iter().count(), 2);
+    assert!(merged
+        .get(&"GHSA-2001-2102-abcd".parse().unwrap())
+        .is_some());
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn extend_with_advisories_from_osv() {
+    let advisory = rustsec::Advisory::load_file("./tests/support/example_advisory_v3.md").unwrap();
+    let mut db = Database::builder().advisory(advisory).build().unwrap();
+
+    let mut unknown =
+        rustsec::osv::import_file(Path::new("./tests/support/example_osv_from_ghsa.json")).unwrap();
+    unknown.metadata.id = "GHSA-2001-2102-abcd".parse().unwrap();
+    unknown.metadata.aliases = vec!["CVE-2001-2102".parse().unwrap()];
+    let package = unknown.metadata.package.clone();
+
+    db.extend(vec![unknown.clone()]).unwrap();
+    db.extend(vec![unknown]).unwrap();
+    assert_eq!(db.iter().count(), 2);
+
+    // The indexes include the new advisory
+    assert_eq!(db.find_by_alias("CVE-2001-2102").len(), 1);
+    assert!(!db
+        .query(&Query::crate_scope().package_name(package))
+        .is_empty());
+}
+
+#[cfg(feature = "osv-export")]
+#[test]
+fn import_native_advisories() {
+    let mapping = rustsec::osv::SysMapping::builtin();
+    let advisories =
+        rustsec::osv::import_native_dir(Path::new("./tests/support/native_osv"), &mapping).unwrap();
+
+    // Only the advisory about a mapped library is imported
+    assert_eq!(advisories.len(), 1);
+    let advisory = &advisories[0];
+    assert_eq!(advisory.metadata.id.as_str(), "OSV-2022-1234");
+    assert_eq!(advisory.metadata.package.as_str(), "openssl-sys");
+    assert_eq!(advisory.metadata.collection, Some(Collection::Native));
+
+    let db = Database::builder()
+        .build()
+        .unwrap()
+        .merge(advisories)
+        .unwrap();
+
+    let query = Query::new()
+        .collection(Collection::Native)
+        .package_name("openssl-sys".parse().unwrap())
+        .package_version("0.9.80".parse().unwrap());
+    assert_eq!(db.query(&query).len(), 1);
+    assert!(db
+        .query(&Query::crate_scope().package_name("openssl-sys".parse().unwrap()))
+        .is_empty());
+
+    // Every version of the crate matches, so they're reported as warnings
+    let lockfile: Lockfile = r#"
+version = 3
+
+[[package]]
+name = "openssl-sys"
+version = "0.9.80"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+    .parse()
+    .unwrap();
+
+    let report = rustsec::Report::generate(&db, &lockfile, &Default::default());
+    assert!(report.vulnerabilities.list.is_empty());
+    assert_eq!(
+        report.warnings[&rustsec::warning::WarningKind::Native].len(),
+        1
+    );
+}
@@ -118,3 +118,33 @@ fn clone_into_existing_directory() {
     )
     .unwrap();
 }
+
+/// Shallow clones only have the latest commit until they're unshallowed,
+/// and fetching shallowly never truncates a full clone
+#[test]
+fn shallow_clone() {
+    let tmp = tempdir().unwrap();
+    let fetch_shallow = || {
+        git::Repository::fetch_shallow(
+            git::DEFAULT_URL,
+            tmp.path(),
+            true,
+            Duration::from_secs(5 * 60),
+            Default::default(),
+            None,
+        )
+        .unwrap()
+    };
+
+    let mut repo = fetch_shallow();
+    assert!(repo.is_shallow());
+    #[cfg(feature = "osv-export")]
+    assert!(git::GitModificationTimes::new(&repo).is_err());
+
+    repo.unshallow().unwrap();
+    assert!(!repo.is_shallow());
+    #[cfg(feature = "osv-export")]
+    assert!(git::GitModificationTimes::new(&repo).is_ok());
+
+    assert!(!fetch_shallow().is_shallow());
+}
@@ -99,3 +99,145 @@ fn invalid_example() {
     let invalid_section = lint.errors()[6].to_string();
     assert_eq!(invalid_section, "invalid key `invalid-section` in toplevel");
 }
+
+/// Well-known links are normalized by the opt-in URL lint
+#[test]
+fn canonical_urls() {
+    use rustsec::advisory::linter::canonical_url;
+
+    let canonical = |url: &str| canonical_url(&url.parse().unwrap()).map(|url| url.to_string());
+
+    assert_eq!(
+        canonical("http://www.github.com/rust-lang/rust/issues/12345/"),
+        Some("https://github.com/rust-lang/rust/issues/12345".to_owned())
+    );
+    assert_eq!(
+        canonical("https://github.com/rust-lang/rust/pull/12345/files?w=1"),
+        Some("https://github.com/rust-lang/rust/pull/12345".to_owned())
+    );
+    assert_eq!(
+        canonical("https://crates.io/#/crates/base/"),
+        Some("https://crates.io/crates/base".to_owned())
+    );
+    assert_eq!(
+        canonical("https://github.com/rust-lang/rust/issues/12345#issuecomment-1"),
+        None
+    );
+    assert_eq!(
+        canonical("https://www.youtube.com/watch?v=jQE66WA2s-A"),
+        None
+    );
+
+    let mut lint = rustsec::advisory::Linter::lint_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    lint.lint_url_canonicalization();
+    assert_eq!(lint.errors(), &[]);
+}
+
+/// Ensure duplicate advisories are detected across the database
+#[test]
+fn duplicate_advisories() {
+    use rustsec::advisory::linter::{find_duplicates, DuplicateReason};
+    use rustsec::database::AdvisoryBuilder;
+
+    let advisory = |id: &str, date: &str, metadata: &str, patched: &str| {
+        AdvisoryBuilder::new(id, "base")
+            .date(date)
+            .metadata(metadata)
+            .patched(patched)
+            .build()
+            .unwrap()
+    };
+
+    let db = rustsec::Database::builder()
+        .advisory(advisory(
+            "RUSTSEC-2001-0001",
+            "2001-02-03",
+            r#"aliases = ["CVE-2001-2101"]"#,
+            ">= 1.2.3",
+        ))
+        .advisory(advisory(
+            "RUSTSEC-2001-0002",
+            "2001-03-04",
+            r#"aliases = ["CVE-2001-2101"]"#,
+            ">= 2.0.0",
+        ))
+        .advisory(advisory("RUSTSEC-2001-0003", "2001-04-05", "", ">= 3.0.0"))
+        .advisory(advisory("RUSTSEC-2001-0004", "2001-04-05", "", ">= 3.0.0"))
+        .advisory(advisory("RUSTSEC-2001-0005", "2001-05-06", "", ">= 3.0.0"))
+        .build()
+        .unwrap();
+
+    let duplicates = find_duplicates(&db);
+    assert_eq!(duplicates.len(), 2);
+
+    assert_eq!(duplicates[0].first.as_str(), "RUSTSEC-2001-0001");
+    assert_eq!(duplicates[0].second.as_str(), "RUSTSEC-2001-0002");
+    assert_eq!(
+        duplicates[0].reason,
+        DuplicateReason::Alias("CVE-2001-2101".parse().unwrap())
+    );
+
+    assert_eq!(duplicates[1].first.as_str(), "RUSTSEC-2001-0003");
+    assert_eq!(duplicates[1].second.as_str(), "RUSTSEC-2001-0004");
+    assert_eq!(duplicates[1].reason, DuplicateReason::SameBug);
+}
+
+/// Advisories referred to by `related`, `supersedes` and `superseded_by`
+/// must exist
+#[test]
+fn references_to_other_advisories() {
+    let advisory = rustsec::database::AdvisoryBuilder::new("RUSTSEC-2001-0001", "base")
+        .date("2001-02-03")
+        .patched(">= 1.2.3")
+        .build()
+        .unwrap();
+    let db = rustsec::Database::builder()
+        .advisory(advisory)
+        .build()
+        .unwrap();
+
+    let lint_references = |references: &str| {
+        let mut lint = rustsec::advisory::Linter::lint_string(&format!(
+            r#"```toml
+[advisory]
+id = "RUSTSEC-2001-0002"
+package = "base"
+date = "2001-02-03"
+{}
+
+[versions]
+patched = [">= 1.2.3"]
+```
+
+# Title
+
+Description
+"#,
+            references
+        ))
+        .unwrap();
+
+        lint.lint_references(&db);
+        lint.errors()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(lint_references(
+        r#"related = ["RUSTSEC-2001-0001", "CVE-2001-2101"]
+superseded_by = ["RUSTSEC-2001-0001"]"#
+    )
+    .is_empty());
+
+    assert_eq!(
+        lint_references(
+            r#"supersedes = ["RUSTSEC-2001-0003", "CVE-2001-2101", "RUSTSEC-2001-0002"]"#
+        ),
+        [
+            "invalid value `RUSTSEC-2001-0003` for key `supersedes` in [advisory]: no such advisory in the database",
+            "invalid value `CVE-2001-2101` for key `supersedes` in [advisory]: expected a RustSec advisory ID",
+            "invalid value `RUSTSEC-2001-0002` for key `supersedes` in [advisory]: advisory refers to itself",
+        ]
+    );
+}
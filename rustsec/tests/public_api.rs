@@ -0,0 +1,273 @@
+//! Tests for the public API which embedders rely on
+//!
+//! Report types are `#[non_exhaustive]`, so they can only be constructed
+//! through the constructors and builders exercised here. Breaking changes to
+//! the public API itself are caught by `cargo semver-checks` in CI.
+
+#![warn(rust_2018_idioms, unused_qualifications)]
+
+use rustsec::{
+    advisory::{Category, Informational, Severity},
+    cwe::Cwe,
+    database::AdvisoryBuilder,
+    platforms::target::OS,
+    report::{self, SeverityOverride},
+    Advisory, Collection, Database, Lockfile, Report, Warning, WarningKind,
+};
+
+/// Example RustSec Advisory
+const EXAMPLE_ADVISORY_PATH: &str = "./tests/support/example_advisory_v3.md";
+
+/// Lockfile with a version of `base` affected by the example advisory
+const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "base"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+#[test]
+fn build_report_settings() {
+    let settings = report::Settings::builder()
+        .target_os(OS::Linux)
+        .severity(Severity::High)
+        .ignore("RUSTSEC-2001-2102".parse().unwrap())
+        .informational_warning(Informational::Unmaintained)
+        .informational_warning(Informational::Unmaintained)
+        .deprecated_warnings(true)
+        .build();
+
+    assert_eq!(settings.target_os, Some(OS::Linux));
+    assert_eq!(settings.severity, Some(Severity::High));
+    assert_eq!(settings.ignore.len(), 1);
+    assert_eq!(
+        settings.informational_warnings,
+        [Informational::Unmaintained]
+    );
+    assert!(settings.deprecated_warnings);
+
+    // Settings can still be adjusted field by field
+    let mut settings = report::Settings::default();
+    settings.ignore.push("RUSTSEC-2001-2101".parse().unwrap());
+    assert_eq!(settings.ignore.len(), 1);
+}
+
+#[test]
+fn generate_report() {
+    let advisory = Advisory::load_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+    let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+
+    let report = Report::generate(&db, &lockfile, &report::Settings::builder().build());
+    assert!(report.vulnerabilities.found);
+    assert_eq!(report.vulnerabilities.count, 1);
+
+    let vulnerability = &report.vulnerabilities.list[0];
+    assert_eq!(vulnerability.advisory.id.as_str(), "RUSTSEC-2001-2101");
+    assert_eq!(vulnerability.package.name.as_str(), "base");
+
+    let ignored = report::Settings::builder()
+        .ignore("RUSTSEC-2001-2101".parse().unwrap())
+        .build();
+    let report = Report::generate(&db, &lockfile, &ignored);
+    assert!(!report.vulnerabilities.found);
+
+    // Reports round-trip through JSON
+    let json = serde_json::to_string(&report).unwrap();
+    let report: Report = serde_json::from_str(&json).unwrap();
+    assert_eq!(report.vulnerabilities.count, 0);
+}
+
+#[test]
+fn severity_overrides() {
+    let advisory = Advisory::load_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+    let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+    let id: rustsec::advisory::Id = "RUSTSEC-2001-2101".parse().unwrap();
+
+    let severity_override =
+        SeverityOverride::new(id.clone(), Severity::Low).reason("not exploitable in our usage");
+    let settings = report::Settings::builder()
+        .severity_override(severity_override.clone())
+        .build();
+    assert_eq!(settings.severity_override(&id), Some(&severity_override));
+
+    let report = Report::generate(&db, &lockfile, &settings);
+    let vulnerability = &report.vulnerabilities.list[0];
+    assert_eq!(vulnerability.severity(), Some(Severity::Low));
+    assert_eq!(
+        vulnerability.severity_override.as_ref(),
+        Some(&severity_override)
+    );
+
+    // Overridden severities are counted in the aggregates too
+    assert_eq!(
+        report.aggregates().by_severity.get(&Severity::Low),
+        Some(&1)
+    );
+    assert_eq!(
+        report.aggregates().by_severity.get(&Severity::Critical),
+        None
+    );
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains(r#""reason":"not exploitable in our usage""#));
+
+    // The overridden severity is compared against the severity threshold
+    let threshold = report::Settings::builder().severity(Severity::High);
+    let report = Report::generate(&db, &lockfile, &threshold.clone().build());
+    assert!(report.vulnerabilities.found);
+    assert_eq!(
+        report.vulnerabilities.list[0].severity(),
+        Some(Severity::Critical)
+    );
+
+    let settings = threshold.severity_override(severity_override).build();
+    let report = Report::generate(&db, &lockfile, &settings);
+    assert!(!report.vulnerabilities.found);
+}
+
+#[test]
+fn report_aggregates() {
+    let advisory = Advisory::load_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+    let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+
+    let report = Report::generate(&db, &lockfile, &report::Settings::builder().build());
+    let aggregates = &report.aggregates();
+    assert_eq!(aggregates.by_severity.get(&Severity::Critical), Some(&1));
+    assert_eq!(aggregates.unscored, 0);
+    assert_eq!(
+        aggregates.by_category.get(&Category::CodeExecution),
+        Some(&1)
+    );
+    assert_eq!(
+        aggregates.by_category.get(&Category::PrivilegeEscalation),
+        Some(&1)
+    );
+    assert_eq!(aggregates.by_cwe.get(&Cwe::new(94)), Some(&1));
+    assert_eq!(aggregates.by_cwe.get(&Cwe::new(269)), Some(&1));
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains(r#""by_severity":{"critical":1}"#));
+    assert!(json.contains(r#""CWE-94":1"#));
+
+    let parsed: Report = serde_json::from_str(&json).unwrap();
+    assert_eq!(&parsed.aggregates(), aggregates);
+}
+
+#[test]
+fn group_versions() {
+    let advisory = Advisory::load_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+    let lockfile: Lockfile = format!(
+        "{}{}",
+        LOCKFILE,
+        r#"
+[[package]]
+name = "base"
+version = "1.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+    )
+    .parse()
+    .unwrap();
+
+    let report = Report::generate(&db, &lockfile, &report::Settings::builder().build());
+    assert_eq!(report.vulnerabilities.count, 2);
+
+    let groups = report.vulnerabilities.groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].advisory.as_str(), "RUSTSEC-2001-2101");
+    assert_eq!(groups[0].package.as_str(), "base");
+    assert_eq!(
+        groups[0].versions,
+        ["1.0.0".parse().unwrap(), "1.1.0".parse().unwrap()]
+    );
+
+    // Groups are only serialized when configured
+    let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+    assert!(json["vulnerabilities"].get("groups").is_none());
+
+    let settings = report::Settings::builder().group_versions(true).build();
+    let report = Report::generate(&db, &lockfile, &settings);
+    let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["vulnerabilities"]["count"], 2);
+    assert_eq!(
+        json["vulnerabilities"]["groups"][0]["versions"],
+        serde_json::json!(["1.0.0", "1.1.0"])
+    );
+
+    let parsed: Report = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.vulnerabilities.groups(), groups);
+}
+
+#[test]
+fn construct_warning() {
+    let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+    let warning = Warning::new(WarningKind::Yanked, &lockfile.packages[0], None, None, None);
+
+    assert!(warning.is_yanked());
+    assert!(warning.advisory.is_none());
+}
+
+#[test]
+fn build_advisory() {
+    let advisory = AdvisoryBuilder::new("RUSTSEC-2001-0001", "base")
+        .metadata(r#"informational = "unsound""#)
+        .patched(">= 1.2.3")
+        .collection(Collection::Rust)
+        .title("Use after free")
+        .build()
+        .unwrap();
+
+    assert_eq!(advisory.id().as_str(), "RUSTSEC-2001-0001");
+    assert_eq!(advisory.metadata.package.as_str(), "base");
+    assert_eq!(advisory.metadata.date.as_str(), "2001-01-01");
+    assert_eq!(
+        advisory.metadata.informational,
+        Some(Informational::Unsound)
+    );
+    assert_eq!(advisory.metadata.collection, Some(Collection::Rust));
+    assert_eq!(advisory.title(), "Use after free");
+    assert_eq!(advisory.description(), "Description");
+    assert!(!advisory.versions.is_vulnerable(&"1.2.3".parse().unwrap()));
+
+    // Invalid fields are reported rather than panicking
+    assert!(AdvisoryBuilder::new("RUSTSEC-2001-0001", "base")
+        .date("yesterday")
+        .build()
+        .is_err());
+}
+
+#[test]
+fn diff_reports() {
+    let advisory = Advisory::load_file(EXAMPLE_ADVISORY_PATH).unwrap();
+    let db = Database::builder().advisory(advisory).build().unwrap();
+    let lockfile: Lockfile = LOCKFILE.parse().unwrap();
+
+    let vulnerable = Report::generate(&db, &lockfile, &report::Settings::builder().build());
+    let fixed = Report::generate(
+        &db,
+        &lockfile,
+        &report::Settings::builder()
+            .ignore("RUSTSEC-2001-2101".parse().unwrap())
+            .build(),
+    );
+
+    let diff = report::diff::ReportDiff::new(&fixed, &vulnerable);
+    assert!(diff.has_changes());
+    assert_eq!(diff.introduced.vulnerabilities.count, 1);
+    assert_eq!(diff.resolved.vulnerabilities.count, 0);
+    assert_eq!(diff.persisting.vulnerabilities.count, 0);
+
+    let diff = report::diff::ReportDiff::new(&vulnerable, &fixed);
+    assert_eq!(diff.introduced.vulnerabilities.count, 0);
+    assert_eq!(diff.resolved.vulnerabilities.count, 1);
+
+    let diff = report::diff::ReportDiff::new(&vulnerable, &vulnerable);
+    assert!(!diff.has_changes());
+    assert_eq!(diff.persisting.vulnerabilities.count, 1);
+}
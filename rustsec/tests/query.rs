@@ -2,7 +2,12 @@
 
 #![warn(rust_2018_idioms, unused_qualifications)]
 
-use rustsec::{advisory::Severity, database::Query, package};
+use rustsec::{
+    advisory::{Category, Informational, Severity},
+    cwe::Cwe,
+    database::Query,
+    package, Collection,
+};
 
 /// Load example advisory from the filesystem
 fn load_advisory() -> rustsec::Advisory {
@@ -40,3 +45,126 @@ fn matches_severity() {
     let query_matches = Query::new().severity(Severity::Critical);
     assert!(query_matches.matches(&advisory));
 }
+
+#[test]
+fn explain_records_every_filter() {
+    let advisory = load_advisory();
+
+    let query = Query::new()
+        .package_name("base".parse().unwrap())
+        .year(2525);
+
+    let checks = query.explain(&advisory);
+    assert_eq!(checks.len(), 2);
+
+    assert_eq!(checks[0].filter, "package_name");
+    assert!(checks[0].passed);
+
+    assert_eq!(checks[1].filter, "year");
+    assert!(!checks[1].passed);
+
+    assert!(!query.matches(&advisory));
+}
+
+#[test]
+fn matches_severity_range() {
+    let advisory = load_advisory();
+
+    let query_matches = Query::new()
+        .severity(Severity::High)
+        .max_severity(Severity::Critical);
+    assert!(query_matches.matches(&advisory));
+
+    let query_nomatch = Query::new().max_severity(Severity::Medium);
+    assert!(!query_nomatch.matches(&advisory));
+}
+
+#[test]
+fn matches_category_and_cwe() {
+    let advisory = load_advisory();
+
+    let query_matches = Query::new().category(Category::CodeExecution).cwe(94);
+    assert!(query_matches.matches(&advisory));
+
+    assert!(!Query::new()
+        .category(Category::ThreadSafety)
+        .matches(&advisory));
+    assert!(!Query::new().cwe(416).matches(&advisory));
+}
+
+#[test]
+fn matches_cwe_class() {
+    let advisory = load_advisory();
+    assert_eq!(advisory.metadata.cwes(), [Cwe::new(94), Cwe::new(269)]);
+
+    // CWE-94 (Code Injection) is a kind of CWE-74 (Injection)
+    assert!(Query::new().cwe(74).matches(&advisory));
+    assert!(Query::new().cwe(707).matches(&advisory));
+    assert!(!Query::new().cwe(119).matches(&advisory));
+}
+
+#[test]
+fn matches_keywords() {
+    let advisory = load_advisory();
+
+    let query_matches = Query::new()
+        .keyword("how".parse().unwrap())
+        .keyword("gentlemen".parse().unwrap());
+    assert!(query_matches.matches(&advisory));
+
+    let query_nomatch = Query::new()
+        .keyword("how".parse().unwrap())
+        .keyword("ladies".parse().unwrap());
+    assert!(!query_nomatch.matches(&advisory));
+}
+
+#[test]
+fn matches_text() {
+    let advisory = load_advisory();
+
+    // Words may occur in either the title or the description, in any case
+    assert!(Query::new().text("BASE survive").matches(&advisory));
+    assert!(Query::new().text("belong").text("time").matches(&advisory));
+
+    assert!(!Query::new().text("base ladies").matches(&advisory));
+}
+
+#[test]
+fn matches_informational_kind() {
+    let advisory = load_advisory();
+
+    let query = Query::new().informational_kind(Informational::Unmaintained);
+    assert!(!query.matches(&advisory));
+}
+
+#[test]
+fn matches_date_range() {
+    let advisory = load_advisory();
+
+    let query_matches = Query::new()
+        .since("2001-01-01".parse().unwrap())
+        .until("2001-02-03".parse().unwrap());
+    assert!(query_matches.matches(&advisory));
+
+    let query_nomatch = Query::new().since("2001-02-04".parse().unwrap());
+    assert!(!query_nomatch.matches(&advisory));
+}
+
+#[test]
+fn matches_withdrawn() {
+    let mut advisory = load_advisory();
+    advisory.metadata.collection = Some(Collection::Crates);
+
+    assert!(Query::crate_scope().matches(&advisory));
+    assert!(!Query::new().withdrawn(true).matches(&advisory));
+
+    advisory.metadata.withdrawn = Some("2001-02-04".parse().unwrap());
+    assert!(!Query::crate_scope().matches(&advisory));
+    assert!(Query::crate_scope().include_withdrawn().matches(&advisory));
+    assert!(Query::new().withdrawn(true).matches(&advisory));
+
+    let settings = rustsec::report::Settings::builder()
+        .include_withdrawn(true)
+        .build();
+    assert!(settings.query().matches(&advisory));
+}
@@ -0,0 +1,71 @@
+//! Tests for recording and replaying API responses
+#![cfg(feature = "osv-export")]
+#![warn(rust_2018_idioms, unused_qualifications)]
+
+use std::sync::Arc;
+
+use rustsec::{
+    osv::OsvClient,
+    transport::{Request, Response, Transport, Vcr},
+    ErrorKind,
+};
+use tempfile::tempdir;
+
+/// OSV advisory served by the stub transport
+const EXAMPLE_OSV_PATH: &str = "./tests/support/example_osv_from_ghsa.json";
+
+/// Transport which answers every request with the example OSV advisory
+#[derive(Debug)]
+struct Stub;
+
+impl Transport for Stub {
+    fn send(&self, _request: &Request) -> Result<Response, rustsec::Error> {
+        Ok(Response {
+            status: 200,
+            body: std::fs::read_to_string(EXAMPLE_OSV_PATH).unwrap(),
+        })
+    }
+}
+
+/// Responses which are recorded can be replayed without the original transport
+#[test]
+fn record_and_replay() {
+    let dir = tempdir().unwrap();
+
+    let recording = OsvClient::new()
+        .unwrap()
+        .transport(Arc::new(Vcr::record(dir.path(), Stub)));
+    let recorded = recording.get("GHSA-2001-2101-abcd").unwrap();
+
+    let replaying = OsvClient::new()
+        .unwrap()
+        .transport(Arc::new(Vcr::replay(dir.path())));
+    let replayed = replaying.get("GHSA-2001-2101-abcd").unwrap();
+    assert_eq!(recorded.id(), replayed.id());
+
+    // Requests which weren't recorded fail instead of using the network
+    let err = replaying.get("GHSA-2001-2102-abcd").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+}
+
+/// Request headers (e.g. tokens) don't affect which fixture is used
+#[test]
+fn headers_are_not_recorded() {
+    let dir = tempdir().unwrap();
+    let url = "https://api.osv.dev/v1/vulns/GHSA-2001-2101-abcd";
+
+    Vcr::record(dir.path(), Stub)
+        .send(&Request::get(url).header("authorization", "Bearer secret"))
+        .unwrap();
+
+    let fixture = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(!std::fs::read_to_string(fixture).unwrap().contains("secret"));
+
+    let response = Vcr::replay(dir.path()).send(&Request::get(url)).unwrap();
+    assert!(response.is_success());
+}